@@ -0,0 +1,96 @@
+//! Tab accents (a color per tab, to tell shells apart)
+//!
+//! A tab's accent is drawn as a stripe along the top of its header and as
+//! the underline of the active tab, so a production shell can be told from
+//! a development one at a glance. Programs set it with the mochi extension
+//! `OSC 1337 ; TabAccent=<color> ST` (an empty color resets it); otherwise
+//! the first `[[tab_accents]]` rule whose pattern matches the tab title
+//! gives the color.
+
+use regex::Regex;
+use terminal_core::Color;
+
+use crate::config::{ConfigError, TabAccentRule};
+use crate::highlight::parse_color;
+
+/// Compiled tab accent rules
+#[derive(Clone, Default)]
+pub struct TabAccents {
+    rules: Vec<(Regex, Color)>,
+}
+
+impl TabAccents {
+    /// Compile the configured rules
+    pub fn new(configs: &[TabAccentRule]) -> Result<Self, ConfigError> {
+        let error = |message: String| ConfigError {
+            message,
+            field: Some("tab_accents".to_string()),
+        };
+        let rules = configs
+            .iter()
+            .map(|config| {
+                let regex = Regex::new(&config.pattern).map_err(|e| {
+                    error(format!(
+                        "Invalid tab accent pattern '{}': {}",
+                        config.pattern, e
+                    ))
+                })?;
+                let color = parse_color(&config.color)
+                    .ok_or_else(|| error(format!("Invalid tab accent color '{}'", config.color)))?;
+                Ok((regex, color))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { rules })
+    }
+
+    /// Accent of a tab: the one its program set, or the first rule matching
+    /// its title
+    pub fn for_tab(&self, title: &str, program: Option<Color>) -> Option<Color> {
+        program.or_else(|| {
+            self.rules
+                .iter()
+                .find(|(regex, _)| regex.is_match(title))
+                .map(|&(_, color)| color)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(pattern: &str, color: &str) -> TabAccentRule {
+        TabAccentRule {
+            pattern: pattern.to_string(),
+            color: color.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_tab_accents() {
+        let accents = TabAccents::new(&[rule("@prod", "red"), rule("^ssh ", "#00ff00")]).unwrap();
+        assert_eq!(
+            accents.for_tab("deploy@prod-db: ~", None),
+            Some(Color::Indexed(1))
+        );
+        assert_eq!(
+            accents.for_tab("ssh dev", None),
+            Some(Color::Rgb { r: 0, g: 255, b: 0 })
+        );
+        assert_eq!(accents.for_tab("vim", None), None);
+
+        // The program's own accent wins over the rules
+        assert_eq!(
+            accents.for_tab("deploy@prod-db: ~", Some(Color::Indexed(4))),
+            Some(Color::Indexed(4))
+        );
+    }
+
+    #[test]
+    fn test_tab_accents_invalid() {
+        for (pattern, color) in [("(", "red"), ("prod", "reddish")] {
+            let err = TabAccents::new(&[rule(pattern, color)]).err().unwrap();
+            assert_eq!(err.field.as_deref(), Some("tab_accents"));
+        }
+    }
+}
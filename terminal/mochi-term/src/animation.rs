@@ -0,0 +1,334 @@
+//! Renderer animations
+//!
+//! Time-based interpolation for visual effects that span several frames,
+//! such as smooth cursor movement, the cursor trail and tabs sliding into
+//! place after they are reordered. Animations are
+//! driven by the frame clock (`Instant`) passed in by the caller, so the
+//! same inputs always produce the same output.
+
+use std::time::{Duration, Instant};
+
+use crate::config::CursorAnimationConfig;
+
+/// Number of segments used to draw the cursor trail
+pub const TRAIL_SEGMENTS: usize = 8;
+
+/// Interpolates the cursor position between cells
+#[derive(Debug, Clone)]
+pub struct CursorAnimator {
+    /// Whether animation is enabled (if false, the cursor jumps)
+    enabled: bool,
+    /// Whether to draw a trail behind the moving cursor
+    trail: bool,
+    /// Duration of a single cursor move
+    duration: Duration,
+    /// Position the current animation started from (col, row)
+    from: (f32, f32),
+    /// Target cell of the current animation (col, row)
+    to: (f32, f32),
+    /// When the current animation started (None = at rest)
+    start: Option<Instant>,
+    /// Whether a target has been set yet (the first one never animates)
+    initialized: bool,
+}
+
+impl CursorAnimator {
+    /// Create a new cursor animator from configuration
+    pub fn new(config: &CursorAnimationConfig) -> Self {
+        Self {
+            enabled: config.enabled,
+            trail: config.trail,
+            duration: Duration::from_millis(config.duration_ms as u64),
+            from: (0.0, 0.0),
+            to: (0.0, 0.0),
+            start: None,
+            initialized: false,
+        }
+    }
+
+    /// Apply new configuration (e.g. after a config reload)
+    pub fn configure(&mut self, config: &CursorAnimationConfig) {
+        self.enabled = config.enabled;
+        self.trail = config.trail;
+        self.duration = Duration::from_millis(config.duration_ms as u64);
+        if !self.is_enabled() {
+            self.from = self.to;
+            self.start = None;
+        }
+    }
+
+    /// Check if animation is enabled
+    pub fn is_enabled(&self) -> bool {
+        self.enabled && !self.duration.is_zero()
+    }
+
+    /// Set the cell the cursor should move to
+    ///
+    /// Starts a new animation from the current interpolated position if the
+    /// target changed.
+    pub fn set_target(&mut self, col: usize, row: usize, now: Instant) {
+        let target = (col as f32, row as f32);
+
+        if !self.initialized || !self.is_enabled() {
+            self.initialized = true;
+            self.from = target;
+            self.to = target;
+            self.start = None;
+            return;
+        }
+
+        if target == self.to {
+            // Settle once the animation has run its course
+            if self.progress(now) >= 1.0 {
+                self.start = None;
+            }
+            return;
+        }
+
+        self.from = self.position(now);
+        self.to = target;
+        self.start = Some(now);
+    }
+
+    /// Get animation progress in range 0.0..=1.0
+    fn progress(&self, now: Instant) -> f32 {
+        match self.start {
+            Some(start) if !self.duration.is_zero() => {
+                let elapsed = now.saturating_duration_since(start);
+                (elapsed.as_secs_f32() / self.duration.as_secs_f32()).min(1.0)
+            }
+            _ => 1.0,
+        }
+    }
+
+    /// Get the interpolated cursor position in cell coordinates (col, row)
+    pub fn position(&self, now: Instant) -> (f32, f32) {
+        let t = ease_out_cubic(self.progress(now));
+        lerp(self.from, self.to, t)
+    }
+
+    /// Check if an animation is in progress
+    pub fn is_animating(&self, now: Instant) -> bool {
+        self.start.is_some() && self.progress(now) < 1.0
+    }
+
+    /// Check if another frame is needed to finish the animation
+    ///
+    /// Stays true until the final frame has been rendered, so the cursor
+    /// always comes to rest on its target cell.
+    pub fn needs_frame(&self) -> bool {
+        self.start.is_some()
+    }
+
+    /// Get the trail segment (tail, head) in cell coordinates
+    ///
+    /// The tail follows the head with linear timing, so it lags behind the
+    /// eased head and catches up when the animation ends.
+    pub fn trail(&self, now: Instant) -> Option<((f32, f32), (f32, f32))> {
+        if !self.trail || !self.is_animating(now) {
+            return None;
+        }
+        let tail = lerp(self.from, self.to, self.progress(now));
+        Some((tail, self.position(now)))
+    }
+}
+
+/// How long a reordered tab takes to slide into place
+pub const TAB_SLIDE_DURATION: Duration = Duration::from_millis(150);
+
+/// Slides tabs from where they were drawn to their new places after a
+/// reorder
+///
+/// Offsets are in tab widths, by the tabs' current indices, and shrink to
+/// zero over the slide.
+#[derive(Debug, Clone, Default)]
+pub struct TabSlide {
+    duration: Duration,
+    /// Offset of each tab when the slide started
+    offsets: Vec<f32>,
+    /// When the current slide started (None = at rest)
+    start: Option<Instant>,
+}
+
+impl TabSlide {
+    pub fn new(duration: Duration) -> Self {
+        Self {
+            duration,
+            offsets: Vec::new(),
+            start: None,
+        }
+    }
+
+    /// Record that the tab at `from` moved to `to` among `len` tabs
+    ///
+    /// Every tab starts from where it is drawn now, so moves during a
+    /// slide (dragging across several tabs) continue smoothly.
+    pub fn moved(&mut self, from: usize, to: usize, len: usize, now: Instant) {
+        if from >= len || to >= len || self.duration.is_zero() {
+            return;
+        }
+        let mut positions: Vec<f32> = (0..len)
+            .map(|index| index as f32 + self.offset(index, now))
+            .collect();
+        let position = positions.remove(from);
+        positions.insert(to, position);
+        self.offsets = positions
+            .iter()
+            .enumerate()
+            .map(|(index, position)| position - index as f32)
+            .collect();
+        self.start = Some(now);
+    }
+
+    /// Current offset of the tab at `index`
+    pub fn offset(&self, index: usize, now: Instant) -> f32 {
+        let Some(start) = self.start else {
+            return 0.0;
+        };
+        let elapsed = now.saturating_duration_since(start).as_secs_f32();
+        let t = (elapsed / self.duration.as_secs_f32()).min(1.0);
+        self.offsets.get(index).copied().unwrap_or(0.0) * (1.0 - ease_out_cubic(t))
+    }
+
+    /// Offsets for `len` tabs at `now`, settling the slide once it is over
+    ///
+    /// A tab opening or closing mid-slide ends it, since the offsets no
+    /// longer match the tabs.
+    pub fn offsets(&mut self, len: usize, now: Instant) -> Vec<f32> {
+        let done = self
+            .start
+            .is_some_and(|start| now.saturating_duration_since(start) >= self.duration);
+        if done || self.offsets.len() != len {
+            self.start = None;
+            self.offsets.clear();
+        }
+        (0..len).map(|index| self.offset(index, now)).collect()
+    }
+
+    /// Check if another frame is needed to finish the slide
+    pub fn needs_frame(&self) -> bool {
+        self.start.is_some()
+    }
+}
+
+/// Cubic ease-out curve
+fn ease_out_cubic(t: f32) -> f32 {
+    1.0 - (1.0 - t).powi(3)
+}
+
+/// Linear interpolation between two points
+fn lerp(a: (f32, f32), b: (f32, f32), t: f32) -> (f32, f32) {
+    (a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(enabled: bool, trail: bool) -> CursorAnimationConfig {
+        CursorAnimationConfig {
+            enabled,
+            duration_ms: 100,
+            trail,
+        }
+    }
+
+    #[test]
+    fn test_first_target_does_not_animate() {
+        let mut anim = CursorAnimator::new(&config(true, false));
+        let now = Instant::now();
+        anim.set_target(10, 5, now);
+        assert!(!anim.is_animating(now));
+        assert_eq!(anim.position(now), (10.0, 5.0));
+    }
+
+    #[test]
+    fn test_interpolates_between_cells() {
+        let mut anim = CursorAnimator::new(&config(true, false));
+        let start = Instant::now();
+        anim.set_target(0, 0, start);
+        anim.set_target(10, 0, start);
+
+        assert!(anim.is_animating(start));
+        let (col, row) = anim.position(start + Duration::from_millis(50));
+        assert!(col > 0.0 && col < 10.0);
+        assert_eq!(row, 0.0);
+
+        let end = start + Duration::from_millis(100);
+        assert!(!anim.is_animating(end));
+        assert_eq!(anim.position(end), (10.0, 0.0));
+
+        // The final frame settles the animation
+        assert!(anim.needs_frame());
+        anim.set_target(10, 0, end);
+        assert!(!anim.needs_frame());
+    }
+
+    #[test]
+    fn test_disabled_jumps() {
+        let mut anim = CursorAnimator::new(&config(false, true));
+        let now = Instant::now();
+        anim.set_target(0, 0, now);
+        anim.set_target(10, 3, now);
+        assert!(!anim.is_animating(now));
+        assert_eq!(anim.position(now), (10.0, 3.0));
+        assert!(anim.trail(now).is_none());
+    }
+
+    #[test]
+    fn test_retarget_starts_from_current_position() {
+        let mut anim = CursorAnimator::new(&config(true, false));
+        let start = Instant::now();
+        anim.set_target(0, 0, start);
+        anim.set_target(10, 0, start);
+
+        let mid = start + Duration::from_millis(50);
+        let mid_pos = anim.position(mid);
+        anim.set_target(0, 0, mid);
+        assert_eq!(anim.position(mid), mid_pos);
+    }
+
+    #[test]
+    fn test_trail_lags_head() {
+        let mut anim = CursorAnimator::new(&config(true, true));
+        let start = Instant::now();
+        anim.set_target(0, 0, start);
+        anim.set_target(10, 0, start);
+
+        let (tail, head) = anim.trail(start + Duration::from_millis(30)).unwrap();
+        assert!(tail.0 < head.0);
+        assert!(anim.trail(start + Duration::from_millis(100)).is_none());
+    }
+
+    #[test]
+    fn test_tab_slide() {
+        let mut slide = TabSlide::new(Duration::from_millis(100));
+        let start = Instant::now();
+        // Tab 0 moves to the end; the others shift left by one
+        slide.moved(0, 2, 3, start);
+        assert_eq!(slide.offsets(3, start), [1.0, 1.0, -2.0]);
+
+        let mid = slide.offsets(3, start + Duration::from_millis(50));
+        assert!(mid[2] < 0.0 && mid[2] > -2.0);
+        assert!(slide.needs_frame());
+
+        let end = start + Duration::from_millis(100);
+        assert_eq!(slide.offsets(3, end), [0.0, 0.0, 0.0]);
+        assert!(!slide.needs_frame());
+    }
+
+    #[test]
+    fn test_tab_slide_continues_and_resets() {
+        let mut slide = TabSlide::new(Duration::from_millis(100));
+        let start = Instant::now();
+        // Dragging tab 0 across two tabs, one step at a time
+        slide.moved(0, 1, 3, start);
+        slide.moved(1, 2, 3, start);
+        assert_eq!(slide.offsets(3, start), [1.0, 1.0, -2.0]);
+
+        // A new tab ends the slide
+        slide.moved(0, 1, 3, start);
+        assert_eq!(slide.offsets(4, start), [0.0; 4]);
+        assert!(!slide.needs_frame());
+    }
+}
@@ -2,25 +2,69 @@
 //!
 //! Ties together the terminal, PTY, and renderer.
 
-use std::io;
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use arboard::Clipboard;
-use terminal_pty::{Child, WindowSize};
+use terminal_pty::{
+    CgroupUsage, Child, CommandBuilder, ExitStatus, Priority, ProcessInfo, PtyWatcher, Reaper,
+    WindowSize,
+};
 use winit::dpi::{LogicalSize, PhysicalSize};
 use winit::event::{ElementState, Event, MouseButton, MouseScrollDelta, WindowEvent};
-use winit::event_loop::{ControlFlow, EventLoop};
+use winit::event_loop::{ControlFlow, EventLoopBuilder, EventLoopProxy, EventLoopWindowTarget};
 use winit::keyboard::{Key, ModifiersState, NamedKey};
 use winit::platform::modifier_supplement::KeyEventExtModifierSupplement;
-use winit::window::{Window, WindowBuilder};
-
-use terminal_core::{Point, SelectionType};
-
-use crate::config::Config;
-use crate::input::{encode_bracketed_paste, encode_focus, encode_key, encode_mouse, MouseEvent};
-use crate::renderer::{Renderer, TabInfo};
-use crate::terminal::Terminal;
+use winit::window::{Window, WindowBuilder, WindowId};
+
+use serde_json::{json, Value};
+use terminal_core::{Point, Selection, SelectionType, TextRegion};
+
+use crate::accent::TabAccents;
+use crate::clipboard_history::{self, ClipboardHistory, Picker};
+use crate::config::{
+    CloseConfirmation, ColorScheme, Config, ExitBehavior, HighlightRule, HookConfig,
+    KeybindingsConfig, LatencyConfig, MemoryConfig, OpenerRule, ScreenshotConfig, StartupCommand,
+    TabAccentRule, ThemeName,
+};
+use crate::config_watcher::{self, WatchTarget};
+use crate::control::{self, ControlRequest, ControlServer, RpcError};
+use crate::copy_mode::{CopyAction, CopyKey, CopyMode};
+use crate::event::{EventTiming, TerminalEvent};
+use crate::export;
+use crate::flow::{FlowControl, SKIP_FRAME_INTERVAL};
+use crate::highlight::HighlightRules;
+use crate::hints::{self, HintAction, HintMode};
+use crate::hooks::{self, HookAction, Hooks};
+use crate::input::{
+    encode_alternate_scroll, encode_bracketed_paste, encode_focus, encode_key, encode_mouse,
+    encode_other_key, sanitize_paste, shell_quote, MouseEncoding, MouseEvent,
+};
+use crate::inspector;
+use crate::instance::{self, Launch};
+use crate::keybindings::{Action, Keybindings};
+use crate::latency::KeyLatency;
+use crate::memory::{self, MemoryUsage, MEMORY_CHECK_INTERVAL};
+use crate::monitor::{self, Monitor, MonitorKind};
+use crate::opener::{self, Opened, Openers};
+use crate::pointer::{self, HoverTarget, PointerState};
+use crate::prediction::Prediction;
+use crate::renderer::{
+    FontSet, GridLayout, HintLabel, HoveredLink, PickerOverlay, PredictedEcho, Renderer,
+    SearchHighlight, StatusOverlay, TabBadge, TabInfo,
+};
+use crate::search::Search;
+use crate::session::{Session, TabSession};
+use crate::ssh;
+use crate::status_bar::{self, StatusBar};
+use crate::terminal::{ClipboardRead, ClipboardWrite, Terminal};
+use crate::theme;
+use crate::toast::{self, ToastQueue};
+use crate::trace::Trace;
 
 /// Padding added to cell height to compute tab bar height
 const TAB_BAR_PADDING: u32 = 8;
@@ -28,20 +72,84 @@ const TAB_BAR_PADDING: u32 = 8;
 const TAB_MAX_WIDTH: u32 = 200;
 /// Width of the close button area in each tab
 const CLOSE_BTN_WIDTH: u32 = 20;
+/// Width of the scrollbar area that can be dragged, in pixels
+const SCROLLBAR_HIT_WIDTH: f64 = 12.0;
 /// Width of the new tab (+) button
 const NEW_TAB_BTN_WIDTH: u32 = 32;
 
+/// How often each tab's foreground process is looked up
+const FOREGROUND_POLL_INTERVAL: Duration = Duration::from_millis(500);
+/// A shell that exits sooner than this after starting is held, not restarted
+const MIN_RESTART_UPTIME: Duration = Duration::from_secs(1);
+
+/// How often to poll tabs whose PTY could not be watched
+const UNWATCHED_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
 /// Compute tab bar height from the current cell size so it scales with HiDPI / font size.
 fn compute_tab_bar_height(cell_size: &crate::renderer::CellSize) -> u32 {
     cell_size.height as u32 + TAB_BAR_PADDING
 }
 
+/// A command run in a tab in place of the shell
+struct TabCommand {
+    /// Command line, run with `/bin/sh -c`
+    line: String,
+    /// Directory the command runs in (re-runs use the same one)
+    cwd: Option<PathBuf>,
+}
+
 /// A single terminal tab
 struct Tab {
     terminal: Terminal,
     child: Child,
+    /// Title reported by the shell (OSC 0/1/2)
     title: String,
+    /// Title set by the user, which OSC updates never replace
+    custom_title: Option<String>,
     scroll_offset: usize,
+    /// Zoom of this tab (Ctrl+=/-), relative to the configured font size
+    font_scale: f32,
+    /// Lines that scrolled in below the view while it was scrolled up (the
+    /// "new lines" pill)
+    unseen_lines: usize,
+    /// Foreground process of the PTY, refreshed every FOREGROUND_POLL_INTERVAL
+    foreground: Option<ProcessInfo>,
+    /// When `foreground` was last refreshed
+    foreground_checked: Option<Instant>,
+    /// Output arrived since `foreground` was last refreshed
+    foreground_stale: bool,
+    /// When the shell was started
+    started: Instant,
+    /// The shell has exited and the tab is held open
+    exited: bool,
+    /// Command run instead of the shell (a command tab)
+    command: Option<TabCommand>,
+    /// Profile the tab was opened with
+    profile: Option<String>,
+    /// Run instead of the shell (`-e` or `startup_command`)
+    startup: Option<StartupCommand>,
+    /// Wakes the event loop when the PTY has output
+    watcher: Option<PtyWatcher>,
+    /// Keyboard-driven selection, while the tab is in copy mode
+    copy_mode: Option<CopyMode>,
+    /// Labelled matches, while the tab is in hint mode
+    hint_mode: Option<HintMode>,
+    /// Activity or silence monitor, toggled with Ctrl+Shift+A
+    monitor: Option<Monitor>,
+    /// The shell integration installer was pointed out when ssh started
+    ssh_offered: bool,
+    /// Scrollback search, while the search bar is open
+    search: Option<Search>,
+    /// Priority the shell had before Ctrl+Shift+Z moved it to the background
+    foreground_priority: Option<Priority>,
+    /// Unit name of the systemd scope the shell runs in (`[scope]`)
+    scope: Option<String>,
+    /// Last usage read from the scope's cgroup, for the CPU percentage
+    usage: Option<(Instant, CgroupUsage)>,
+    /// Key-to-screen latency of the keys typed in the tab
+    latency: KeyLatency,
+    /// Typed characters drawn before their echo (`latency.predictive_echo`)
+    prediction: Prediction,
 }
 
 impl Tab {
@@ -49,16 +157,377 @@ impl Tab {
         Self {
             terminal,
             child,
-            title: String::from("Terminal"),
+            title: String::new(),
+            custom_title: None,
             scroll_offset: 0,
+            font_scale: 1.0,
+            unseen_lines: 0,
+            foreground: None,
+            foreground_checked: None,
+            foreground_stale: false,
+            started: Instant::now(),
+            exited: false,
+            command: None,
+            profile: None,
+            startup: None,
+            watcher: None,
+            copy_mode: None,
+            hint_mode: None,
+            monitor: None,
+            ssh_offered: false,
+            search: None,
+            foreground_priority: None,
+            scope: None,
+            usage: None,
+            latency: KeyLatency::default(),
+            prediction: Prediction::default(),
+        }
+    }
+
+    /// Title shown in the tab bar
+    ///
+    /// Falls back to the foreground command name when the shell has not
+    /// set a title.
+    fn display_title(&self) -> &str {
+        match &self.custom_title {
+            Some(title) => title,
+            None if !self.title.is_empty() => &self.title,
+            None => self.fallback_title(),
+        }
+    }
+
+    /// Label of the tab in the tab bar: the title, after the profile if
+    /// the tab has one
+    fn tab_label(&self) -> Cow<'_, str> {
+        match &self.profile {
+            Some(profile) => Cow::Owned(format!("{}: {}", profile, self.display_title())),
+            None => Cow::Borrowed(self.display_title()),
+        }
+    }
+
+    /// Title used when neither the user nor the shell has set one
+    fn fallback_title(&self) -> &str {
+        if let Some(command) = &self.command {
+            return &command.line;
         }
+        self.foreground
+            .as_ref()
+            .map_or("Terminal", |process| process.name.as_str())
     }
+
+    /// Refresh the cached foreground process if it is due
+    ///
+    /// Returns true if the process changed.
+    fn refresh_foreground(&mut self) -> bool {
+        if self.exited
+            || self
+                .foreground_checked
+                .is_some_and(|checked| checked.elapsed() < FOREGROUND_POLL_INTERVAL)
+        {
+            return false;
+        }
+        self.foreground_checked = Some(Instant::now());
+        self.foreground_stale = false;
+        let foreground = self.child.foreground_process();
+        let changed = foreground != self.foreground;
+        self.foreground = foreground;
+        changed
+    }
+
+    /// Whether ssh is running in the foreground
+    fn running_ssh(&self) -> bool {
+        !self.exited
+            && self
+                .foreground
+                .as_ref()
+                .is_some_and(|process| ssh::is_ssh(&process.name))
+    }
+
+    /// Name of the command running in the foreground, if it is not the shell
+    fn busy_command(&self) -> Option<&str> {
+        if self.exited {
+            return None;
+        }
+        if let Some(command) = &self.command {
+            return Some(&command.line);
+        }
+        if !self.child.has_foreground_job() {
+            return None;
+        }
+        Some(
+            self.foreground
+                .as_ref()
+                .map_or("A command", |process| process.name.as_str()),
+        )
+    }
+
+    /// Current working directory of the shell
+    ///
+    /// Reads the shell's cwd from /proc on Linux, falling back to the
+    /// directory reported via OSC 7.
+    fn cwd(&self) -> Option<PathBuf> {
+        #[cfg(target_os = "linux")]
+        if let Ok(path) = std::fs::read_link(format!("/proc/{}/cwd", self.child.pid())) {
+            return Some(path);
+        }
+        self.terminal.current_directory().map(Path::to_path_buf)
+    }
+
+    /// Read what the shell's scope uses: the CPU percentage since the last
+    /// read (None on the first) and the memory
+    fn sample_usage(&mut self, now: Instant) -> Option<(Option<f64>, u64)> {
+        if self.scope.is_none() || self.exited {
+            return None;
+        }
+        let usage = CgroupUsage::for_pid(self.child.pid())?;
+        let cpu = self.usage.and_then(|(then, last)| {
+            let elapsed = now.checked_duration_since(then)?.as_secs_f64();
+            let used = usage.cpu.checked_sub(last.cpu)?.as_secs_f64();
+            (elapsed > 0.0).then(|| used / elapsed * 100.0)
+        });
+        self.usage = Some((now, usage));
+        Some((cpu, usage.memory))
+    }
+
+    /// Check if closing this tab has to be confirmed first
+    fn needs_close_confirmation(&self, policy: CloseConfirmation) -> bool {
+        match policy {
+            CloseConfirmation::Never => false,
+            CloseConfirmation::Running => self.busy_command().is_some(),
+            CloseConfirmation::Always => !self.exited,
+        }
+    }
+
+    /// Scroll the view by `lines`; positive shows older output
+    ///
+    /// Returns true if the view moved.
+    fn scroll_by(&mut self, lines: isize) -> bool {
+        self.scroll_to(self.scroll_offset.saturating_add_signed(lines))
+    }
+
+    /// Scroll the view to `offset` lines above the bottom, clamped to the scrollback
+    fn scroll_to(&mut self, offset: usize) -> bool {
+        let offset = offset.min(self.terminal.screen().scrollback().len());
+        let moved = offset != self.scroll_offset;
+        self.scroll_offset = offset;
+        moved
+    }
+
+    /// Text of the "new lines" pill, shown while output has arrived below
+    /// a scrolled-up view
+    fn new_output_pill(&self) -> Option<String> {
+        match self.unseen_lines {
+            _ if self.scroll_offset == 0 => None,
+            0 => None,
+            1 => Some("1 new line \u{2193}".to_string()),
+            n => Some(format!("{} new lines \u{2193}", n)),
+        }
+    }
+
+    /// Send typed input to the shell, scrolling back to the bottom
+    ///
+    /// The PTY is not buffered, so the key reaches the shell at once; it
+    /// starts a latency measurement and, in slow ssh sessions, is predicted.
+    /// Returns true if the view moved.
+    fn type_input(&mut self, data: &[u8], config: &LatencyConfig) -> bool {
+        let now = Instant::now();
+        let _ = self.child.write_all(data);
+        self.latency.typed(now);
+        if self.predicting(config, now) {
+            self.prediction.typed(data, self.terminal.screen(), now);
+        } else {
+            self.prediction.reset();
+        }
+        self.scroll_to(0)
+    }
+
+    /// Whether typed characters are predicted: with `predictive_echo`, in
+    /// ssh sessions slower than `prediction_threshold_ms`, outside
+    /// full-screen programs, unless more than `misprediction_limit`
+    /// predictions were wrong in the last minute
+    fn predicting(&self, config: &LatencyConfig, now: Instant) -> bool {
+        let threshold = Duration::from_millis(config.prediction_threshold_ms);
+        config.predictive_echo
+            && self.running_ssh()
+            && !self.terminal.screen().modes().alternate_screen
+            && self.prediction.misses(now) <= config.misprediction_limit
+            && self
+                .latency
+                .median()
+                .is_some_and(|median| median >= threshold)
+    }
+
+    /// Scroll so `row` (negative in the scrollback) is in view, centering it
+    /// if it was not
+    fn scroll_row_into_view(&mut self, row: isize) -> bool {
+        let rows = self.terminal.screen().rows() as isize;
+        let view_row = row + self.scroll_offset as isize;
+        if (0..rows).contains(&view_row) {
+            return false;
+        }
+        self.scroll_to((rows / 2 - row).max(0) as usize)
+    }
+
+    /// Scroll so the previous (or next) scroll mark is the top line of the view
+    ///
+    /// Moving past the last mark scrolls to the bottom.
+    fn scroll_to_mark(&mut self, previous: bool) -> bool {
+        let screen = self.terminal.screen();
+        let scrollback_len = screen.scrollback().len();
+        let top = scrollback_len - self.scroll_offset.min(scrollback_len);
+        let mark = if previous {
+            screen.previous_mark(top)
+        } else {
+            screen.next_mark(top)
+        };
+        match mark {
+            Some(line) => self.scroll_to(scrollback_len.saturating_sub(line)),
+            None if !previous => self.scroll_to(0),
+            None => false,
+        }
+    }
+
+    /// Title shown in the window title bar while this tab is active
+    fn window_title(&self) -> &str {
+        match &self.custom_title {
+            Some(title) => title,
+            None if !self.terminal.title().is_empty() => self.terminal.title(),
+            None => self.display_title(),
+        }
+    }
+}
+
+/// Something the user has been asked to confirm closing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CloseRequest {
+    /// The tab at this index
+    Tab(usize),
+    /// The window and all its tabs
+    Window,
+}
+
+/// What the save prompt saves
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SaveTarget {
+    /// The screen or the scrollback as text, HTML or ANSI
+    Dump(export::Range),
+    /// A PNG of the view
+    Screenshot,
 }
 
-/// Application state
+/// Paste waiting for the user to confirm it
+#[derive(Debug, Clone)]
+struct PasteRequest {
+    text: String,
+    question: String,
+}
+
+/// Answer to a y/n question: Enter or y accepts, Escape or n declines, and
+/// other keys are ignored
+fn dialog_answer(key: &Key) -> Option<bool> {
+    match key {
+        Key::Named(NamedKey::Enter) => Some(true),
+        Key::Named(NamedKey::Escape) => Some(false),
+        Key::Character(c) if c.to_lowercase() == "y" => Some(true),
+        Key::Character(c) if c.to_lowercase() == "n" => Some(false),
+        _ => None,
+    }
+}
+
+/// What a key did in a picker
+enum PickerKey {
+    /// Moved the highlight
+    Moved,
+    /// Chose the entry at this index
+    Chosen(usize),
+    /// Closed the picker
+    Closed,
+}
+
+/// Handle a key in a picker over `len` entries: arrows or j/k move, Enter
+/// or a digit chooses, Escape or q closes, and other keys are ignored
+fn picker_key(picker: &mut Picker, key: &Key, len: usize) -> Option<PickerKey> {
+    match key {
+        Key::Named(NamedKey::ArrowUp) => picker.move_by(-1, len),
+        Key::Named(NamedKey::ArrowDown) => picker.move_by(1, len),
+        Key::Character(c) if c == "k" => picker.move_by(-1, len),
+        Key::Character(c) if c == "j" => picker.move_by(1, len),
+        Key::Named(NamedKey::Enter) => return Some(PickerKey::Chosen(picker.selected)),
+        Key::Character(c) if matches!(c.parse::<usize>(), Ok(1..=9)) => {
+            return c.parse::<usize>().ok().map(|n| PickerKey::Chosen(n - 1));
+        }
+        Key::Named(NamedKey::Escape) => return Some(PickerKey::Closed),
+        Key::Character(c) if c == "q" => return Some(PickerKey::Closed),
+        _ => return None,
+    }
+    Some(PickerKey::Moved)
+}
+
+/// Where the tab at `index` ends up when the tab at `from` moves to `to`
+fn moved_index(index: usize, from: usize, to: usize) -> usize {
+    if index == from {
+        to
+    } else if from < index && index <= to {
+        index - 1
+    } else if to <= index && index < from {
+        index + 1
+    } else {
+        index
+    }
+}
+
+/// Something a window needs the application to do
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AppRequest {
+    /// Open another window
+    NewWindow,
+    /// Reload the configuration file for every window
+    ReloadConfig,
+}
+
+/// Maximum length of a user-assigned tab title (in characters)
+const MAX_TAB_TITLE_LEN: usize = 64;
+
+/// Application state: the event loop and its windows
 pub struct App {
+    /// Configuration given to new windows
+    config: Config,
+    /// Fonts shared by the renderers of all windows
+    fonts: Rc<FontSet>,
+    /// Recent copies, shared by all windows
+    clipboard_history: Rc<RefCell<ClipboardHistory>>,
+    /// Trace of shell output (--trace, Ctrl+Shift+L), shared by all windows
+    trace: Rc<RefCell<Trace>>,
+    /// Open windows
+    windows: HashMap<WindowId, TerminalWindow>,
+    /// Control socket, if enabled
+    control: Option<ControlServer>,
+    /// Socket other launches open windows through (single-instance mode)
+    instance: Option<ControlServer>,
+    /// Reaps the shells of all windows and reports their exits
+    reaper: Option<Reaper>,
+    /// When memory was last checked against the `[memory]` budgets
+    memory_checked: Option<Instant>,
+}
+
+/// A window with its own renderer and tabs
+struct TerminalWindow {
     /// Configuration
     config: Config,
+    /// Fonts shared with other windows
+    fonts: Rc<FontSet>,
+    /// Recent copies, shared with other windows
+    clipboard_history: Rc<RefCell<ClipboardHistory>>,
+    /// Trace of shell output, shared with other windows
+    trace: Rc<RefCell<Trace>>,
+    /// Clipboard history picker, while it is open
+    clipboard_picker: Option<Picker>,
+    /// Profile picker for a new tab, while it is open
+    profile_picker: Option<Picker>,
+    /// Terminal inspector, while it is open (the highlight scrolls it)
+    inspector: Option<Picker>,
+    /// Colors of the theme shown, by theme (read once per theme change)
+    theme_colors: Option<(ThemeName, ColorScheme)>,
     /// Window (created on resume)
     window: Option<Rc<Window>>,
     /// Renderer
@@ -78,6 +547,17 @@ pub struct App {
     mouse_pixel: (f64, f64),
     /// Mouse button state
     mouse_buttons: [bool; 3],
+    /// Mouse pointer shape and visibility
+    pointer: PointerState,
+    /// The mouse is over the window
+    mouse_in_window: bool,
+    /// ID of the hyperlink under the mouse
+    hovered_link: Option<u32>,
+    /// Tab header under the mouse
+    hovered_tab: Option<usize>,
+    /// The left button was pressed to open a link (Ctrl+click) or on the
+    /// new output pill, so its release is not passed on
+    swallow_release: bool,
     /// Last render time
     last_render: Instant,
     /// Needs redraw
@@ -88,17 +568,467 @@ pub struct App {
     tab_bar_height: u32,
     /// Whether we're currently dragging the scrollbar
     scrollbar_dragging: bool,
+    /// Index of the tab being dragged in the tab bar
+    tab_drag: Option<usize>,
     /// Y position where scrollbar drag started (in pixels)
     scrollbar_drag_start_y: f64,
     /// Scroll offset when scrollbar drag started
     scrollbar_drag_start_offset: usize,
+    /// Frame pacing for animations
+    timing: EventTiming,
+    /// Text of the active tab's title while it is being renamed
+    tab_rename: Option<String>,
+    /// Show the foreground process overlay for the active tab
+    show_process_info: bool,
+    /// Close request waiting for the user to confirm it
+    close_confirm: Option<CloseRequest>,
+    /// Paste waiting for the user to confirm it
+    paste_confirm: Option<PasteRequest>,
+    /// Text of the command line being typed for a new command tab
+    command_prompt: Option<String>,
+    /// File name being typed for a screen dump or screenshot, and what to save
+    save_prompt: Option<(SaveTarget, String)>,
+    /// Buffer reused for PTY reads
+    read_buf: Vec<u8>,
+    /// Handle for PTY watchers to wake the event loop
+    proxy: EventLoopProxy<TerminalEvent>,
+    /// Reaps the shells of this window's tabs (shared by all windows)
+    reaper: Option<Reaper>,
+    /// Request for the application, handled after the current event
+    request: Option<AppRequest>,
+    /// Transient messages shown in the corner until they expire
+    toasts: ToastQueue,
+    /// Commands and notifications run on terminal events
+    hooks: Hooks,
+    /// Accent colors given to tabs by title
+    tab_accents: TabAccents,
+    /// Line below the terminal, if enabled
+    status_bar: Option<StatusBar>,
+    /// What Ctrl+click and open hints do with a match
+    openers: Openers,
+    /// Compiled `[keybindings]`
+    keybindings: Keybindings,
 }
 
 impl App {
     /// Create a new application
     pub fn new(config: Config) -> Result<Self, Box<dyn std::error::Error>> {
+        let clipboard_history = Rc::new(RefCell::new(ClipboardHistory::new(
+            config.clipboard_history.entries,
+            config.clipboard_history.max_kb * 1024,
+        )));
+        let mut trace = Trace::new(config.trace_file.clone());
+        if config.trace_file.is_some() {
+            trace.start().map_err(|e| {
+                format!(
+                    "Cannot write the trace to {}: {}",
+                    trace.path().display(),
+                    e
+                )
+            })?;
+        }
         Ok(Self {
             config,
+            fonts: Rc::new(FontSet::load()?),
+            clipboard_history,
+            trace: Rc::new(RefCell::new(trace)),
+            windows: HashMap::new(),
+            control: None,
+            instance: None,
+            reaper: None,
+            memory_checked: None,
+        })
+    }
+
+    /// Run the application
+    pub fn run(mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let event_loop = EventLoopBuilder::<TerminalEvent>::with_user_event().build()?;
+        let proxy = event_loop.create_proxy();
+
+        // Before other threads start, so they inherit the blocked SIGCHLD
+        self.start_reaper(&proxy);
+
+        // Before the first shell starts, so it can find the socket
+        self.start_control(&proxy);
+        self.start_instance(&proxy);
+
+        // The first window restores the session and runs -e and --command
+        let launch = Launch {
+            restore: true,
+            ..Launch::new(&self.config)
+        };
+        self.open_window(&event_loop, &proxy, &launch)?;
+        self.watch_config(&proxy);
+
+        // Run event loop
+        event_loop.run(move |event, elwt| {
+            match event {
+                Event::WindowEvent { window_id, event } => {
+                    if let Some(window) = self.windows.get_mut(&window_id) {
+                        window.handle_window_event(event);
+                    }
+                    self.handle_requests(elwt, &proxy);
+                }
+                Event::AboutToWait => {
+                    for window in self.windows.values_mut() {
+                        window.update();
+                    }
+                    self.enforce_memory_budgets();
+
+                    // A window closes once its last tab is gone
+                    self.windows.retain(|_, window| !window.tabs.is_empty());
+                    if self.windows.is_empty() {
+                        log::info!("Last window closed");
+                        elwt.exit();
+                        return;
+                    }
+
+                    // Sleep until a PTY watcher or the earliest deadline wakes us
+                    let control_flow = self
+                        .windows
+                        .values()
+                        .map(TerminalWindow::control_flow)
+                        .fold(ControlFlow::Wait, earliest);
+                    elwt.set_control_flow(control_flow);
+                }
+                Event::UserEvent(TerminalEvent::ConfigChanged) if self.config.live_reload => {
+                    self.reload_config();
+                }
+                Event::UserEvent(TerminalEvent::Control(request)) => {
+                    self.handle_control(elwt, &proxy, request);
+                }
+                Event::UserEvent(TerminalEvent::Instance(request)) => {
+                    self.handle_instance(elwt, &proxy, request);
+                }
+                // PTY output is read and exits are handled in AboutToWait,
+                // which follows these events
+                Event::UserEvent(TerminalEvent::PtyReadable) => {}
+                Event::UserEvent(TerminalEvent::ChildExited(_)) => {}
+                _ => {}
+            }
+        })?;
+
+        Ok(())
+    }
+
+    /// Trim what is over the `[memory]` budgets, at most once every
+    /// MEMORY_CHECK_INTERVAL
+    ///
+    /// The scrollback and image budgets cover the tabs of all windows, and
+    /// the tabs holding the most give up their oldest lines and images.
+    fn enforce_memory_budgets(&mut self) {
+        let now = Instant::now();
+        if self
+            .memory_checked
+            .is_some_and(|checked| now < checked + MEMORY_CHECK_INTERVAL)
+        {
+            return;
+        }
+        self.memory_checked = Some(now);
+        let budgets = &self.config.memory;
+        let usages: Vec<MemoryUsage> = self
+            .windows
+            .values()
+            .flat_map(|window| window.tabs.iter())
+            .map(|tab| tab.terminal.memory_usage())
+            .collect();
+        let share = |mb: usize, size: fn(&MemoryUsage) -> usize| {
+            let sizes: Vec<usize> = usages.iter().map(size).collect();
+            MemoryConfig::budget(mb).and_then(|budget| memory::fair_share(&sizes, budget))
+        };
+        let scrollback = share(budgets.scrollback_mb, |usage| usage.scrollback);
+        let images = share(budgets.images_mb, |usage| usage.images);
+        let glyphs = MemoryConfig::budget(budgets.glyph_cache_mb);
+        if scrollback.is_none() && images.is_none() && glyphs.is_none() {
+            return;
+        }
+        for window in self.windows.values_mut() {
+            window.trim_memory(scrollback, images, glyphs);
+        }
+    }
+
+    /// Watch the config file and theme files, reloading when they change
+    fn watch_config(&self, proxy: &EventLoopProxy<TerminalEvent>) {
+        let config_file = self
+            .config
+            .config_file
+            .clone()
+            .or_else(Config::default_config_path);
+        let targets = config_file
+            .as_deref()
+            .and_then(WatchTarget::file)
+            .into_iter()
+            .chain(theme::themes_dir().as_deref().map(WatchTarget::toml_files))
+            .collect();
+
+        let proxy = proxy.clone();
+        if let Err(e) = config_watcher::spawn(targets, move || {
+            let _ = proxy.send_event(TerminalEvent::ConfigChanged);
+        }) {
+            log::warn!("Failed to watch the config file: {}", e);
+        }
+    }
+
+    /// Reap shells as they exit, waking the event loop for each
+    ///
+    /// Without a reaper, tabs fall back to checking their shell with
+    /// waitpid(2) on every update.
+    fn start_reaper(&mut self, proxy: &EventLoopProxy<TerminalEvent>) {
+        let proxy = proxy.clone();
+        match Reaper::spawn(move |pid| {
+            let _ = proxy.send_event(TerminalEvent::ChildExited(pid.as_raw()));
+        }) {
+            Ok(reaper) => {
+                reaper.set_grace_period(self.config.close_grace());
+                self.reaper = Some(reaper);
+            }
+            Err(e) => log::warn!("Failed to start the child reaper: {}", e),
+        }
+    }
+
+    /// Listen on the control socket if it is enabled
+    ///
+    /// The socket path is exported to shells in MOCHI_CONTROL_SOCKET.
+    fn start_control(&mut self, proxy: &EventLoopProxy<TerminalEvent>) {
+        if !self.config.control.enabled {
+            return;
+        }
+        let path = self
+            .config
+            .control
+            .path
+            .clone()
+            .unwrap_or_else(control::default_socket_path);
+        let proxy = proxy.clone();
+        let submit = move |request| proxy.send_event(TerminalEvent::Control(request)).is_ok();
+        match control::start(&path, submit) {
+            Ok(server) => {
+                std::env::set_var(control::SOCKET_ENV, server.path());
+                self.control = Some(server);
+            }
+            Err(e) => log::warn!(
+                "Failed to start the control socket at {}: {}",
+                path.display(),
+                e
+            ),
+        }
+    }
+
+    /// Listen for other launches if single-instance mode is on
+    fn start_instance(&mut self, proxy: &EventLoopProxy<TerminalEvent>) {
+        if !self.config.single_instance {
+            return;
+        }
+        let path = instance::socket_path();
+        let proxy = proxy.clone();
+        let submit = move |request| proxy.send_event(TerminalEvent::Instance(request)).is_ok();
+        match control::start(&path, submit) {
+            Ok(server) => self.instance = Some(server),
+            Err(e) => log::warn!(
+                "Failed to listen for other launches at {}: {}",
+                path.display(),
+                e
+            ),
+        }
+    }
+
+    /// Open what another launch of mochi asked for (single-instance mode)
+    ///
+    /// With `tab`, the tabs open in the focused window (or any window);
+    /// otherwise, or if no window is open, in a new window.
+    fn handle_instance(
+        &mut self,
+        elwt: &EventLoopWindowTarget<TerminalEvent>,
+        proxy: &EventLoopProxy<TerminalEvent>,
+        request: ControlRequest,
+    ) {
+        let result = match request.method.as_str() {
+            instance::OPEN_METHOD => match serde_json::from_value(request.params.clone()) {
+                Ok(launch) => self
+                    .open_launch(elwt, proxy, &launch)
+                    .map(|()| Value::Bool(true))
+                    .map_err(|e| RpcError::failed(e.to_string())),
+                Err(e) => Err(RpcError::invalid_params(e.to_string())),
+            },
+            method => Err(RpcError::method_not_found(method)),
+        };
+        request.respond(result);
+    }
+
+    fn open_launch(
+        &mut self,
+        elwt: &EventLoopWindowTarget<TerminalEvent>,
+        proxy: &EventLoopProxy<TerminalEvent>,
+        launch: &Launch,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let focused = self.windows.values().position(|window| window.focused);
+        let window = self.windows.values_mut().nth(focused.unwrap_or(0));
+        match window.filter(|_| launch.tab) {
+            Some(window) => {
+                window.open_launch_tabs(launch);
+                Ok(())
+            }
+            None => self.open_window(elwt, proxy, launch),
+        }
+    }
+
+    /// Answer a request from the control socket
+    ///
+    /// Window and config requests are handled here; tab requests go to the
+    /// focused window.
+    fn handle_control(
+        &mut self,
+        elwt: &EventLoopWindowTarget<TerminalEvent>,
+        proxy: &EventLoopProxy<TerminalEvent>,
+        request: ControlRequest,
+    ) {
+        log::debug!("Control request: {}", request.method);
+        let result = match request.method.as_str() {
+            "new_window" => self
+                .open_window(elwt, proxy, &Launch::default())
+                .map(|()| Value::Bool(true))
+                .map_err(|e| RpcError::failed(e.to_string())),
+            "set_config" => self.set_config(&request.params),
+            "reload_config" => {
+                self.reload_config();
+                Ok(Value::Bool(true))
+            }
+            method => {
+                let focused = self.windows.values().position(|window| window.focused);
+                match self.windows.values_mut().nth(focused.unwrap_or(0)) {
+                    Some(window) => window.handle_control(method, &request.params),
+                    None => Err(RpcError::failed("No window is open")),
+                }
+            }
+        };
+        request.respond(result);
+    }
+
+    /// Change settings at runtime (control socket `set_config`)
+    ///
+    /// `params.settings` is shaped like the config file; only settings that
+    /// a config reload would apply take effect.
+    fn set_config(&mut self, params: &Value) -> Result<Value, RpcError> {
+        let settings = params
+            .get("settings")
+            .ok_or_else(|| RpcError::invalid_params("Missing settings"))?;
+        let new_config = self
+            .config
+            .with_overrides(settings)
+            .map_err(|e| RpcError::invalid_params(e.to_string()))?;
+        self.config.update_from(&new_config);
+        self.apply_config(&new_config);
+        Ok(Value::Bool(true))
+    }
+
+    /// Apply a reloaded or changed configuration to the windows and the reaper
+    fn apply_config(&mut self, new_config: &Config) {
+        for window in self.windows.values_mut() {
+            window.apply_config(new_config);
+        }
+        if let Some(reaper) = &self.reaper {
+            reaper.set_grace_period(self.config.close_grace());
+        }
+    }
+
+    /// Open a window with the tabs of a launch (or one shell tab)
+    fn open_window(
+        &mut self,
+        elwt: &EventLoopWindowTarget<TerminalEvent>,
+        proxy: &EventLoopProxy<TerminalEvent>,
+        launch: &Launch,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let window = WindowBuilder::new()
+            .with_title("Mochi Terminal")
+            .with_inner_size(LogicalSize::new(800, 600))
+            .build(elwt)?;
+        let window = Rc::new(window);
+
+        let mut state = TerminalWindow::new(
+            self.config.clone(),
+            self.fonts.clone(),
+            self.clipboard_history.clone(),
+            self.trace.clone(),
+            proxy.clone(),
+            self.reaper.clone(),
+        );
+        state.init_graphics(window.clone(), launch)?;
+        self.windows.insert(window.id(), state);
+        Ok(())
+    }
+
+    /// Carry out requests windows made while handling an event
+    fn handle_requests(
+        &mut self,
+        elwt: &EventLoopWindowTarget<TerminalEvent>,
+        proxy: &EventLoopProxy<TerminalEvent>,
+    ) {
+        let requests: Vec<AppRequest> = self
+            .windows
+            .values_mut()
+            .filter_map(|window| window.request.take())
+            .collect();
+        for request in requests {
+            match request {
+                AppRequest::NewWindow => {
+                    if let Err(e) = self.open_window(elwt, proxy, &Launch::default()) {
+                        log::error!("Failed to open a new window: {}", e);
+                    }
+                }
+                AppRequest::ReloadConfig => self.reload_config(),
+            }
+        }
+    }
+
+    /// Reload the configuration file and apply it to every window
+    fn reload_config(&mut self) {
+        log::info!("Reloading configuration...");
+
+        let notice = match self.config.reload() {
+            Ok(new_config) => {
+                self.config.update_from(&new_config);
+                self.apply_config(&new_config);
+                log::info!("Configuration reloaded successfully");
+                "Configuration reloaded".to_string()
+            }
+            Err(e) => {
+                log::warn!("Failed to reload configuration: {}", e);
+                e.to_string()
+            }
+        };
+        for window in self.windows.values_mut() {
+            window.show_toast(notice.clone(), toast::DEFAULT_TTL);
+        }
+    }
+}
+
+/// The earlier of two event loop deadlines
+fn earliest(a: ControlFlow, b: ControlFlow) -> ControlFlow {
+    match (a, b) {
+        (ControlFlow::WaitUntil(a), ControlFlow::WaitUntil(b)) => ControlFlow::WaitUntil(a.min(b)),
+        (ControlFlow::Wait, other) | (other, ControlFlow::Wait) => other,
+        _ => ControlFlow::Poll,
+    }
+}
+
+impl TerminalWindow {
+    /// Create the state for a window; `init_graphics` attaches the window
+    fn new(
+        config: Config,
+        fonts: Rc<FontSet>,
+        clipboard_history: Rc<RefCell<ClipboardHistory>>,
+        trace: Rc<RefCell<Trace>>,
+        proxy: EventLoopProxy<TerminalEvent>,
+        reaper: Option<Reaper>,
+    ) -> Self {
+        Self {
+            fonts,
+            clipboard_history,
+            trace,
+            clipboard_picker: None,
+            profile_picker: None,
+            inspector: None,
+            theme_colors: None,
             window: None,
             renderer: None,
             tabs: Vec::new(),
@@ -108,186 +1038,1060 @@ impl App {
             mouse_cell: (0, 0),
             mouse_pixel: (0.0, 0.0),
             mouse_buttons: [false; 3],
+            pointer: PointerState::new(),
+            mouse_in_window: false,
+            hovered_link: None,
+            hovered_tab: None,
+            swallow_release: false,
             last_render: Instant::now(),
             needs_redraw: true,
             focused: true,
             tab_bar_height: 0,
             scrollbar_dragging: false,
+            tab_drag: None,
             scrollbar_drag_start_y: 0.0,
             scrollbar_drag_start_offset: 0,
+            timing: EventTiming::default(),
+            tab_rename: None,
+            show_process_info: false,
+            close_confirm: None,
+            paste_confirm: None,
+            command_prompt: None,
+            save_prompt: None,
+            read_buf: Vec::new(),
+            proxy,
+            reaper,
+            request: None,
+            toasts: ToastQueue::new(),
+            hooks: Self::compile_hooks(&config.hooks),
+            tab_accents: Self::compile_tab_accents(&config.tab_accents),
+            status_bar: Self::compile_status_bar(&config),
+            openers: Self::compile_openers(&config.openers),
+            keybindings: Self::compile_keybindings(&config.keybindings),
+            config,
+        }
+    }
+
+    /// Compile the configured hooks; the config has been validated, so this
+    /// only fails for a config built some other way
+    fn compile_hooks(configs: &[HookConfig]) -> Hooks {
+        Hooks::new(configs).unwrap_or_else(|e| {
+            log::warn!("Ignoring hooks: {}", e);
+            Hooks::default()
         })
     }
 
-    /// Run the application
-    pub fn run(mut self) -> Result<(), Box<dyn std::error::Error>> {
-        let event_loop = EventLoop::new()?;
+    /// Compile the configured highlight rules, like `compile_hooks`
+    fn compile_highlights(rules: &[HighlightRule]) -> HighlightRules {
+        HighlightRules::new(rules).unwrap_or_else(|e| {
+            log::warn!("Ignoring highlight rules: {}", e);
+            HighlightRules::default()
+        })
+    }
 
-        // Create window
-        let window = WindowBuilder::new()
-            .with_title("Mochi Terminal")
-            .with_inner_size(LogicalSize::new(800, 600))
-            .build(&event_loop)?;
+    /// Compile the configured tab accent rules, like `compile_hooks`
+    fn compile_tab_accents(rules: &[TabAccentRule]) -> TabAccents {
+        TabAccents::new(rules).unwrap_or_else(|e| {
+            log::warn!("Ignoring tab accents: {}", e);
+            TabAccents::default()
+        })
+    }
 
-        let window = Rc::new(window);
+    /// Compile the configured opener rules, like `compile_hooks`
+    fn compile_openers(rules: &[OpenerRule]) -> Openers {
+        Openers::new(rules).unwrap_or_else(|e| {
+            log::warn!("Ignoring opener rules: {}", e);
+            Openers::default()
+        })
+    }
 
-        // Initialize graphics
-        self.init_graphics(window.clone())?;
+    /// Compile the configured keybindings, like `compile_hooks`
+    fn compile_keybindings(config: &KeybindingsConfig) -> Keybindings {
+        Keybindings::new(config).unwrap_or_else(|e| {
+            log::warn!("Ignoring keybindings: {}", e);
+            Keybindings::default()
+        })
+    }
 
-        // Run event loop
-        event_loop.run(move |event, elwt| {
-            elwt.set_control_flow(ControlFlow::Poll);
+    /// The configured status bar, like `compile_hooks`
+    fn compile_status_bar(config: &Config) -> Option<StatusBar> {
+        StatusBar::new(&config.status_bar).unwrap_or_else(|e| {
+            log::warn!("Ignoring status bar: {}", e);
+            None
+        })
+    }
 
-            match event {
-                Event::WindowEvent { event, .. } => {
-                    self.handle_window_event(event, elwt);
+    /// Recompute the status bar of the active tab if it is due or a frame
+    /// is about to be drawn anyway
+    fn update_status_bar(&mut self) {
+        let (Some(status_bar), Some(tab)) =
+            (&mut self.status_bar, self.tabs.get_mut(self.active_tab))
+        else {
+            return;
+        };
+        let now = Instant::now();
+        if !self.needs_redraw && !status_bar.refresh_due(now) {
+            return;
+        }
+        let cwd = status_bar.needs_cwd().then(|| tab.cwd()).flatten();
+        let usage = status_bar
+            .needs_usage()
+            .then(|| tab.sample_usage(now))
+            .flatten();
+        let values = status_bar::Values {
+            title: tab.display_title(),
+            cwd: cwd.as_deref(),
+            scroll: (tab.scroll_offset, tab.terminal.screen().scrollback().len()),
+            user_vars: Some(tab.terminal.user_vars()),
+            scope: tab.scope.as_deref(),
+            cpu: usage.and_then(|(cpu, _)| cpu),
+            memory: usage.map(|(_, memory)| memory),
+            ..Default::default()
+        };
+        if status_bar.refresh(values, now) {
+            self.needs_redraw = true;
+        }
+    }
+
+    /// Carry out what fired hooks asked for
+    fn run_hook_actions(&mut self, actions: Vec<HookAction>) {
+        for action in actions {
+            match action {
+                HookAction::Run { command, env } => hooks::run(&command, &env),
+                HookAction::Notify(text) => self.show_toast(text, toast::NOTIFICATION_TTL),
+            }
+        }
+    }
+
+    /// Handle window events
+    fn handle_window_event(&mut self, event: WindowEvent) {
+        match event {
+            WindowEvent::CloseRequested => {
+                self.request_close_window();
+            }
+            WindowEvent::Resized(size) => {
+                self.handle_resize(size);
+            }
+            WindowEvent::KeyboardInput { event, .. } => {
+                self.handle_key_input(&event);
+                self.update_pointer();
+            }
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.modifiers = modifiers.state();
+            }
+            WindowEvent::MouseInput { button, state, .. } => {
+                self.handle_mouse_input(button, state);
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                self.mouse_in_window = true;
+                self.handle_mouse_motion(position);
+                self.pointer.show();
+                self.update_pointer();
+            }
+            WindowEvent::CursorLeft { .. } => {
+                self.mouse_in_window = false;
+                self.update_pointer();
+            }
+            WindowEvent::CursorEntered { .. }
+                if self.config.focus_follows_mouse && !self.focused =>
+            {
+                if let Some(window) = &self.window {
+                    window.focus_window();
                 }
-                Event::AboutToWait => {
-                    // Poll PTY
-                    self.poll_pty();
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                self.handle_mouse_scroll(delta);
+            }
+            WindowEvent::Focused(focused) => {
+                self.handle_focus(focused);
+                self.pointer.show();
+                self.update_pointer();
+            }
+            WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                self.handle_scale_factor_changed(scale_factor);
+                self.update_frame_timing();
+            }
+            // The window may now be on a monitor with another refresh rate
+            WindowEvent::Moved(_) => {
+                self.update_frame_timing();
+            }
+            WindowEvent::DroppedFile(path) => {
+                self.handle_dropped_file(&path);
+            }
+            WindowEvent::RedrawRequested => {
+                self.render();
+            }
+            _ => {}
+        }
+    }
 
-                    // Check if child exited
-                    if !self.check_child() {
-                        log::info!("Child process exited");
-                        elwt.exit();
-                        return;
-                    }
+    /// Read PTY output, handle exited shells and render if needed
+    fn update(&mut self) {
+        // Poll PTY
+        self.poll_pty();
+        // Output may have changed the requested pointer shape
+        self.update_pointer();
 
-                    // Render directly if needed (more reliable than request_redraw on macOS)
-                    // This ensures TUI apps like Claude Code render immediately
-                    if self.needs_redraw {
-                        self.render();
+        // Check if child exited
+        if !self.check_child() {
+            log::info!("Child process exited");
+            return;
+        }
+
+        // Keep rendering while an animation is running, paced by the frame clock
+        if self.animation_frame_due() {
+            self.needs_redraw = true;
+        }
+
+        if self.toasts.expire(Instant::now()) {
+            self.needs_redraw = true;
+        }
+
+        if let Some(tab) = self.tabs.get_mut(self.active_tab) {
+            if tab.prediction.expire(Instant::now()) {
+                self.needs_redraw = true;
+            }
+        }
+
+        // Find search matches again once output has settled a little
+        if let Some(tab) = self.tabs.get_mut(self.active_tab) {
+            if let Some(search) = &mut tab.search {
+                if search.refresh_if_due(tab.terminal.screen(), Instant::now()) {
+                    self.needs_redraw = true;
+                }
+            }
+        }
+
+        self.update_status_bar();
+        self.update_monitors();
+
+        // Render directly if needed (more reliable than request_redraw on macOS)
+        // This ensures TUI apps like Claude Code render immediately. Changes
+        // that arrive within a frame of the last render wait for the frame
+        // deadline in control_flow, except the echo of a typed key.
+        let echo = self
+            .tabs
+            .get(self.active_tab)
+            .is_some_and(|tab| tab.latency.echo_pending());
+        if self.needs_redraw && (echo || Instant::now() >= self.timing.next_frame(self.last_render))
+        {
+            self.render();
+        }
+    }
+
+    /// Pace frames by the refresh rate of the window's monitor and max_fps
+    fn update_frame_timing(&mut self) {
+        let refresh = self
+            .window
+            .as_ref()
+            .and_then(|window| window.current_monitor())
+            .and_then(|monitor| monitor.refresh_rate_millihertz());
+        let timing = EventTiming::new(refresh, self.config.max_fps);
+        if timing != self.timing {
+            log::debug!("Rendering at up to {} fps", timing.target_fps);
+            self.timing = timing;
+        }
+    }
+
+    /// Initialize graphics
+    ///
+    /// The window opens the saved session (if the launch restores it), then
+    /// the launch's startup command and `--command` tabs, or one shell tab
+    /// if that leaves it empty.
+    fn init_graphics(
+        &mut self,
+        window: Rc<Window>,
+        launch: &Launch,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        // Create renderer with effective colors based on theme
+        let mut renderer = Renderer::new(
+            window.clone(),
+            self.fonts.clone(),
+            self.config.font_size(),
+            self.config.effective_colors(),
+            &self.config.cursor_animation,
+            GridLayout::from_config(&self.config),
+        )?;
+        renderer.set_minimum_contrast(self.config.minimum_contrast);
+        renderer.set_builtin_box_drawing(self.config.font.builtin_box_drawing);
+
+        // Calculate terminal dimensions (account for tab bar height)
+        let cell_size = renderer.cell_size();
+        self.tab_bar_height = compute_tab_bar_height(&cell_size);
+        let base_font_size = renderer.font_size();
+
+        // Restore the saved session, if any
+        self.active_tab = 0;
+        let session = if launch.restore {
+            self.load_session()
+        } else {
+            None
+        };
+        if let Some(session) = session {
+            for saved in &session.tabs {
+                match Self::spawn_tab(
+                    &renderer,
+                    base_font_size,
+                    self.tab_bar_height,
+                    &self.config,
+                    self.reaper.as_ref(),
+                    saved.profile.as_deref(),
+                    None,
+                    saved.cwd.as_deref(),
+                    None,
+                ) {
+                    Ok(mut tab) => {
+                        tab.custom_title = saved.title.clone();
+                        self.tabs.push(tab);
                     }
+                    Err(e) => log::warn!("Failed to restore tab: {}", e),
                 }
-                _ => {}
             }
-        })?;
+            self.active_tab = session.active_tab.min(self.tabs.len().saturating_sub(1));
+        }
+
+        // Run the startup command (-e) in place of the first shell
+        let profile = launch.profile.as_deref().or(self.config.profile.as_deref());
+        if let Some(startup) = &launch.startup_command {
+            let tab = Self::spawn_tab(
+                &renderer,
+                base_font_size,
+                self.tab_bar_height,
+                &self.config,
+                self.reaper.as_ref(),
+                profile,
+                Some(startup.clone()),
+                None,
+                None,
+            )?;
+            self.tabs.push(tab);
+            self.active_tab = self.tabs.len() - 1;
+        }
+
+        // Run the --command in its own tab
+        if let Some(line) = launch.command.clone() {
+            let command = TabCommand { line, cwd: None };
+            let tab = Self::spawn_tab(
+                &renderer,
+                base_font_size,
+                self.tab_bar_height,
+                &self.config,
+                self.reaper.as_ref(),
+                None,
+                None,
+                None,
+                Some(command),
+            )?;
+            self.tabs.push(tab);
+            self.active_tab = self.tabs.len() - 1;
+        }
+
+        // Create first tab
+        if self.tabs.is_empty() {
+            let tab = Self::spawn_tab(
+                &renderer,
+                base_font_size,
+                self.tab_bar_height,
+                &self.config,
+                self.reaper.as_ref(),
+                profile,
+                None,
+                None,
+                None,
+            )?;
+            self.tabs.push(tab);
+        }
+
+        self.window = Some(window);
+        self.renderer = Some(renderer);
+        self.update_frame_timing();
 
         Ok(())
     }
 
-    /// Handle window events
-    fn handle_window_event(
-        &mut self,
-        event: WindowEvent,
-        elwt: &winit::event_loop::EventLoopWindowTarget<()>,
-    ) {
-        match event {
-            WindowEvent::CloseRequested => {
-                elwt.exit();
-            }
-            WindowEvent::Resized(size) => {
-                self.handle_resize(size);
-            }
-            WindowEvent::KeyboardInput { event, .. } => {
-                self.handle_key_input(&event);
+    /// Spawn a shell (or a command) in a new tab that fills the window
+    ///
+    /// `base_font_size` is the configured font size in physical pixels; a
+    /// profile with a font size of its own zooms the tab to that size.
+    /// Unknown profiles are ignored. With `startup`, the tab runs that
+    /// command instead of the shell.
+    #[allow(clippy::too_many_arguments)]
+    fn spawn_tab(
+        renderer: &Renderer,
+        base_font_size: f32,
+        tab_bar_height: u32,
+        config: &Config,
+        reaper: Option<&Reaper>,
+        profile: Option<&str>,
+        startup: Option<StartupCommand>,
+        cwd: Option<&Path>,
+        command: Option<TabCommand>,
+    ) -> Result<Tab, Box<dyn std::error::Error>> {
+        let profile = profile.filter(|name| config.profiles.contains_key(*name));
+        let font_scale = config.profile_font_size(profile) / config.font_size();
+        let cell_size = renderer.cell_size_at(base_font_size * font_scale);
+        let (cols, rows) = renderer.grid_size_for(&cell_size, tab_bar_height);
+        let terminal = config
+            .terminal_builder(cols, rows)
+            .cell_pixel_size(cell_size.width as u32, cell_size.height as u32)
+            .capture_lines(hooks::watches_output(&config.hooks))
+            .highlight_rules(Self::compile_highlights(&config.highlights))
+            .build();
+        let (child, scope) = Self::spawn_child(
+            cols,
+            rows,
+            config,
+            reaper,
+            profile,
+            startup.as_ref(),
+            cwd,
+            command.as_ref(),
+        )?;
+        let mut tab = Tab::new(terminal, child);
+        tab.scope = scope;
+        tab.command = command;
+        tab.profile = profile.map(str::to_string);
+        tab.startup = startup;
+        tab.font_scale = font_scale;
+        Ok(tab)
+    }
+
+    /// Spawn the shell for a tab, or its command for a command tab
+    ///
+    /// A `startup` tab runs its startup command instead of the shell.
+    /// The child is handed to the reaper, if there is one. With `[scope]`
+    /// it runs in a new systemd scope, whose unit name is returned.
+    #[allow(clippy::too_many_arguments)]
+    fn spawn_child(
+        cols: usize,
+        rows: usize,
+        config: &Config,
+        reaper: Option<&Reaper>,
+        profile: Option<&str>,
+        startup: Option<&StartupCommand>,
+        cwd: Option<&Path>,
+        command: Option<&TabCommand>,
+    ) -> Result<(Child, Option<String>), Box<dyn std::error::Error>> {
+        let mut builder = match command {
+            Some(command) => config.with_environment(CommandBuilder::shell_command(&command.line)),
+            None => config
+                .startup_shell_command(startup, profile)
+                .unwrap_or_else(|| config.profile_shell_command(profile)),
+        };
+        // A known directory takes precedence over `working_directory`
+        if let Some(dir) = command.map_or(cwd, |command| command.cwd.as_deref()) {
+            builder.cwd(dir);
+        }
+        let scope = config.scope.new_scope();
+        let unit = scope.as_ref().map(|scope| scope.unit.clone());
+        builder.systemd_scope(scope);
+        let mut child = builder.spawn(WindowSize::new(cols as u16, rows as u16))?;
+        child.set_nonblocking(true)?;
+        if let Some(reaper) = reaper {
+            child.set_reaper(reaper);
+        }
+        Ok((child, unit))
+    }
+
+    /// Load the session to restore at startup
+    fn load_session(&self) -> Option<Session> {
+        let path = self.config.session_path()?;
+        if !path.exists() {
+            return None;
+        }
+        match Session::load(&path) {
+            Ok(session) => Some(session),
+            Err(e) => {
+                log::warn!("Failed to load session from {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    /// Save the open tabs to the session file (if sessions are enabled)
+    fn save_session(&self) {
+        let Some(path) = self.config.session_path() else {
+            return;
+        };
+        if self.tabs.is_empty() {
+            return;
+        }
+
+        let session = Session {
+            active_tab: self.active_tab,
+            tabs: self
+                .tabs
+                .iter()
+                .map(|tab| TabSession {
+                    title: tab.custom_title.clone(),
+                    cwd: tab.cwd(),
+                    profile: tab.profile.clone(),
+                })
+                .collect(),
+        };
+        match session.save(&path) {
+            Ok(()) => log::info!("Saved session to {}", path.display()),
+            Err(e) => log::warn!("Failed to save session to {}: {}", path.display(), e),
+        }
+    }
+
+    /// Create a new tab (with the default profile)
+    fn create_new_tab(&mut self) {
+        let profile = self.config.profile.clone();
+        self.open_tab(profile.as_deref(), None, None);
+    }
+
+    /// Open a command tab running `line` in the active tab's directory
+    fn open_command_tab(&mut self, line: String) {
+        let cwd = self.tabs.get(self.active_tab).and_then(Tab::cwd);
+        self.open_tab(None, None, Some(TabCommand { line, cwd }));
+    }
+
+    /// Open the tabs another launch of mochi asked for and raise the window
+    fn open_launch_tabs(&mut self, launch: &Launch) {
+        let profile = launch
+            .profile
+            .clone()
+            .or_else(|| self.config.profile.clone());
+        if launch.startup_command.is_some() || launch.command.is_none() {
+            self.open_tab(profile.as_deref(), launch.startup_command.clone(), None);
+        }
+        if let Some(line) = launch.command.clone() {
+            self.open_tab(None, None, Some(TabCommand { line, cwd: None }));
+        }
+        if let Some(window) = &self.window {
+            window.focus_window();
+        }
+    }
+
+    /// Open a new tab running a shell, or a command, and make it active
+    fn open_tab(
+        &mut self,
+        profile: Option<&str>,
+        startup: Option<StartupCommand>,
+        command: Option<TabCommand>,
+    ) {
+        let base_font_size = self.base_font_size();
+        let Some(renderer) = &self.renderer else {
+            return;
+        };
+        // New tabs start at the configured font size (or their profile's)
+        match Self::spawn_tab(
+            renderer,
+            base_font_size,
+            self.tab_bar_height,
+            &self.config,
+            self.reaper.as_ref(),
+            profile,
+            startup,
+            None,
+            command,
+        ) {
+            Ok(tab) => {
+                self.tab_rename = None;
+                self.tabs.push(tab);
+                self.active_tab = self.tabs.len() - 1;
+                self.needs_redraw = true;
+                log::info!("Created new tab {}", self.active_tab + 1);
+            }
+            Err(e) => {
+                log::error!("Failed to create new tab: {}", e);
+            }
+        }
+    }
+
+    /// Answer a control socket request for this window's tabs
+    fn handle_control(&mut self, method: &str, params: &Value) -> Result<Value, RpcError> {
+        match method {
+            "list_tabs" => {
+                let tabs = self
+                    .tabs
+                    .iter()
+                    .enumerate()
+                    .map(|(index, tab)| {
+                        json!({
+                            "index": index,
+                            "title": tab.display_title(),
+                            "profile": tab.profile,
+                            "active": index == self.active_tab,
+                            "exited": tab.exited,
+                        })
+                    })
+                    .collect();
+                Ok(Value::Array(tabs))
+            }
+            "get_text" => {
+                let screen = self.tabs[self.control_tab(params)?].terminal.screen();
+                let scrollback = params
+                    .get("scrollback")
+                    .and_then(Value::as_bool)
+                    .unwrap_or(false);
+                let top = if scrollback {
+                    -(screen.scrollback().len() as isize)
+                } else {
+                    0
+                };
+                let region = TextRegion::Rows {
+                    top,
+                    bottom: screen.rows() as isize - 1,
+                };
+                let text = screen.text_region(region, true);
+                let lines: Vec<&str> = text.split('\n').collect();
+                let cursor = screen.cursor();
+                Ok(json!({
+                    "lines": lines,
+                    "cols": screen.cols(),
+                    "rows": screen.rows(),
+                    "cursor": { "col": cursor.col, "row": cursor.row },
+                }))
+            }
+            "send_text" => {
+                let text = params
+                    .get("text")
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| RpcError::invalid_params("Missing text"))?;
+                let index = self.control_tab(params)?;
+                self.tabs[index]
+                    .child
+                    .write_all(text.as_bytes())
+                    .map_err(|e| RpcError::failed(e.to_string()))?;
+                Ok(Value::Bool(true))
+            }
+            "new_tab" => {
+                let command =
+                    params
+                        .get("command")
+                        .and_then(Value::as_str)
+                        .map(|line| TabCommand {
+                            line: line.to_string(),
+                            cwd: None,
+                        });
+                let profile = params.get("profile").and_then(Value::as_str);
+                let count = self.tabs.len();
+                self.open_tab(profile, None, command);
+                if self.tabs.len() == count {
+                    return Err(RpcError::failed("Failed to open a tab"));
+                }
+                Ok(json!({ "index": self.active_tab }))
+            }
+            "select_tab" => {
+                let index = self.control_tab(params)?;
+                self.switch_to_tab(index);
+                Ok(Value::Bool(true))
+            }
+            "screenshot" => {
+                let path = params
+                    .get("path")
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| RpcError::invalid_params("Missing path"))?;
+                let flag = |name: &str, default: bool| {
+                    params.get(name).and_then(Value::as_bool).unwrap_or(default)
+                };
+                let ScreenshotConfig { cursor, selection } = self.config.screenshot;
+                let (width, height) = self
+                    .take_screenshot(
+                        Path::new(path),
+                        flag("cursor", cursor),
+                        flag("selection", selection),
+                    )
+                    .map_err(|e| RpcError::failed(e.to_string()))?;
+                Ok(json!({ "width": width, "height": height }))
+            }
+            method => Err(RpcError::method_not_found(method)),
+        }
+    }
+
+    /// Tab named by a request's `tab` parameter, else the active tab
+    fn control_tab(&self, params: &Value) -> Result<usize, RpcError> {
+        match params.get("tab") {
+            None | Some(Value::Null) => Ok(self.active_tab),
+            Some(tab) => tab
+                .as_u64()
+                .map(|index| index as usize)
+                .filter(|&index| index < self.tabs.len())
+                .ok_or_else(|| RpcError::invalid_params(format!("No tab {}", tab))),
+        }
+    }
+
+    /// Close a tab, asking first if `confirm_close` requires it
+    fn request_close_tab(&mut self, index: usize) {
+        let policy = self.config.confirm_close;
+        let Some(tab) = self.tabs.get(index) else {
+            return;
+        };
+        if tab.needs_close_confirmation(policy) {
+            // Show the question on the tab it is about
+            self.switch_to_tab(index);
+            self.close_confirm = Some(CloseRequest::Tab(index));
+            self.needs_redraw = true;
+            return;
+        }
+        self.close_tab(index);
+    }
+
+    /// Close the window, asking first if `confirm_close` requires it
+    fn request_close_window(&mut self) {
+        let policy = self.config.confirm_close;
+        if self
+            .tabs
+            .iter()
+            .any(|tab| tab.needs_close_confirmation(policy))
+        {
+            self.close_confirm = Some(CloseRequest::Window);
+            self.needs_redraw = true;
+            return;
+        }
+        self.close_window();
+    }
+
+    /// Close a tab (the window closes with the last tab)
+    fn close_tab(&mut self, index: usize) {
+        if index >= self.tabs.len() {
+            return;
+        }
+        self.tab_rename = None;
+        self.close_confirm = None;
+        self.paste_confirm = None;
+
+        self.tabs.remove(index);
+        if self.tabs.is_empty() {
+            return;
+        }
+        if self.active_tab >= self.tabs.len() {
+            self.active_tab = self.tabs.len() - 1;
+        } else if self.active_tab > index {
+            self.active_tab -= 1;
+        }
+        self.needs_redraw = true;
+        self.update_window_title();
+        log::info!(
+            "Closed tab {}, now on tab {}",
+            index + 1,
+            self.active_tab + 1
+        );
+    }
+
+    /// Save the session and close all tabs, which ends the event loop
+    fn close_window(&mut self) {
+        self.save_session();
+        self.tabs.clear();
+    }
+
+    /// Handle a key while a close confirmation is shown
+    fn handle_close_confirm_key(&mut self, event: &winit::event::KeyEvent) {
+        let Some(request) = self.close_confirm else {
+            return;
+        };
+        let Some(confirmed) = dialog_answer(&event.logical_key) else {
+            return;
+        };
+        self.close_confirm = None;
+        self.needs_redraw = true;
+        if !confirmed {
+            return;
+        }
+        match request {
+            CloseRequest::Tab(index) => self.close_tab(index),
+            CloseRequest::Window => self.close_window(),
+        }
+    }
+
+    /// Question shown while a close request waits for confirmation
+    fn close_confirm_text(&self, request: CloseRequest) -> String {
+        match request {
+            CloseRequest::Tab(index) => match self.tabs.get(index).and_then(Tab::busy_command) {
+                Some(command) => format!("{} is running. Close this tab? (y/n)", command),
+                None => String::from("Close this tab? (y/n)"),
+            },
+            CloseRequest::Window => {
+                let busy = self
+                    .tabs
+                    .iter()
+                    .filter(|tab| tab.busy_command().is_some())
+                    .count();
+                match busy {
+                    0 => String::from("Close the window? (y/n)"),
+                    1 => String::from("1 tab is running a command. Close the window? (y/n)"),
+                    n => format!("{} tabs are running commands. Close the window? (y/n)", n),
+                }
+            }
+        }
+    }
+
+    /// Move the tab at `from` to `to`, shifting the tabs in between
+    ///
+    /// The active tab stays active wherever it ends up.
+    fn move_tab(&mut self, from: usize, to: usize) {
+        let len = self.tabs.len();
+        if from >= len || to >= len || from == to {
+            return;
+        }
+        let tab = self.tabs.remove(from);
+        self.tabs.insert(to, tab);
+
+        let moved = |index: usize| moved_index(index, from, to);
+        self.active_tab = moved(self.active_tab);
+        if let Some(CloseRequest::Tab(index)) = self.close_confirm {
+            self.close_confirm = Some(CloseRequest::Tab(moved(index)));
+        }
+        self.tab_drag = self.tab_drag.map(moved);
+        if let Some(renderer) = &mut self.renderer {
+            renderer.tab_moved(from, to, len);
+        }
+        self.needs_redraw = true;
+        log::info!("Moved tab {} to {}", from + 1, to + 1);
+    }
+
+    /// Switch to a specific tab (used by Cmd+1-9 on macOS and the control socket)
+    fn switch_to_tab(&mut self, index: usize) {
+        if index < self.tabs.len() && index != self.active_tab {
+            self.tab_rename = None;
+            self.active_tab = index;
+            self.needs_redraw = true;
+            self.update_window_title();
+            log::info!("Switched to tab {}", index + 1);
+        }
+    }
+
+    /// Set the window title from the active tab
+    fn update_window_title(&self) {
+        if let (Some(window), Some(tab)) = (&self.window, self.tabs.get(self.active_tab)) {
+            window.set_title(tab.window_title());
+        }
+    }
+
+    /// Start renaming the active tab in the tab bar
+    fn start_tab_rename(&mut self) {
+        if let Some(tab) = self.tabs.get(self.active_tab) {
+            self.tab_rename = Some(tab.display_title().to_string());
+            self.needs_redraw = true;
+        }
+    }
+
+    /// Finish renaming the active tab
+    ///
+    /// Committing an empty name clears the custom title, so the tab follows
+    /// the shell's title again.
+    fn finish_tab_rename(&mut self, commit: bool) {
+        let Some(name) = self.tab_rename.take() else {
+            return;
+        };
+        if commit {
+            if let Some(tab) = self.tabs.get_mut(self.active_tab) {
+                let name = name.trim();
+                tab.custom_title = (!name.is_empty()).then(|| name.to_string());
             }
-            WindowEvent::ModifiersChanged(modifiers) => {
-                self.modifiers = modifiers.state();
+            self.update_window_title();
+        }
+        self.needs_redraw = true;
+    }
+
+    /// Handle a key press while the command prompt is open
+    fn handle_command_prompt_key(&mut self, event: &winit::event::KeyEvent) {
+        let Some(line) = &mut self.command_prompt else {
+            return;
+        };
+        match &event.logical_key {
+            Key::Named(NamedKey::Enter) => {
+                let line = self.command_prompt.take().unwrap_or_default();
+                let line = line.trim();
+                if !line.is_empty() {
+                    self.open_command_tab(line.to_string());
+                }
             }
-            WindowEvent::MouseInput { button, state, .. } => {
-                self.handle_mouse_input(button, state);
+            Key::Named(NamedKey::Escape) => self.command_prompt = None,
+            Key::Named(NamedKey::Backspace) => {
+                line.pop();
             }
-            WindowEvent::CursorMoved { position, .. } => {
-                self.handle_mouse_motion(position);
+            _ => {
+                if let Some(text) = &event.text {
+                    line.extend(text.chars().filter(|c| !c.is_control()));
+                }
             }
-            WindowEvent::MouseWheel { delta, .. } => {
-                self.handle_mouse_scroll(delta);
+        }
+        self.needs_redraw = true;
+    }
+
+    /// Ask for the file to save the screen, the scrollback or a screenshot to
+    fn open_save_prompt(&mut self, target: SaveTarget) {
+        let name = match target {
+            SaveTarget::Dump(range) => format!("mochi-{}.txt", range.name()),
+            SaveTarget::Screenshot => "mochi-screenshot.png".to_string(),
+        };
+        self.save_prompt = Some((target, name));
+        self.needs_redraw = true;
+    }
+
+    /// Handle a key press while the screen dump file name is being typed
+    fn handle_save_prompt_key(&mut self, event: &winit::event::KeyEvent) {
+        let Some((target, name)) = &mut self.save_prompt else {
+            return;
+        };
+        match &event.logical_key {
+            Key::Named(NamedKey::Enter) => {
+                let target = *target;
+                let name = std::mem::take(name);
+                self.save_prompt = None;
+                match target {
+                    _ if name.trim().is_empty() => {}
+                    SaveTarget::Dump(range) => self.save_dump(range, name.trim()),
+                    SaveTarget::Screenshot => self.save_screenshot(name.trim()),
+                }
             }
-            WindowEvent::Focused(focused) => {
-                self.handle_focus(focused);
+            Key::Named(NamedKey::Escape) => self.save_prompt = None,
+            Key::Named(NamedKey::Backspace) => {
+                name.pop();
             }
-            WindowEvent::RedrawRequested => {
-                self.render();
+            _ => {
+                if let Some(text) = &event.text {
+                    name.extend(text.chars().filter(|c| !c.is_control()));
+                }
             }
-            _ => {}
         }
+        self.needs_redraw = true;
     }
 
-    /// Initialize graphics
-    fn init_graphics(&mut self, window: Rc<Window>) -> Result<(), Box<dyn std::error::Error>> {
-        let size = window.inner_size();
-
-        // Create renderer with effective colors based on theme
-        let renderer = Renderer::new(
-            window.clone(),
-            self.config.font_size(),
-            self.config.effective_colors(),
-        )?;
-
-        // Calculate terminal dimensions (account for tab bar height)
-        let cell_size = renderer.cell_size();
-        self.tab_bar_height = compute_tab_bar_height(&cell_size);
-        let cols = (size.width as f32 / cell_size.width) as usize;
-        let terminal_height = size.height.saturating_sub(self.tab_bar_height);
-        let rows = (terminal_height as f32 / cell_size.height) as usize;
-
-        // Create first tab
-        let terminal = Terminal::new(cols.max(1), rows.max(1));
-        let child = Child::spawn_shell(WindowSize::new(cols as u16, rows as u16))?;
-        child.set_nonblocking(true)?;
+    /// Where to save a file named `name` in the save prompt: a path relative
+    /// to the current tab's working directory (or starting with `~/`)
+    fn save_path(&self, name: &str) -> Option<PathBuf> {
+        let tab = self.tabs.get(self.active_tab)?;
+        Some(match name.strip_prefix("~/").zip(dirs::home_dir()) {
+            Some((rest, home)) => home.join(rest),
+            None => {
+                let base = tab.cwd().or_else(dirs::home_dir).unwrap_or_default();
+                base.join(name)
+            }
+        })
+    }
 
-        let tab = Tab::new(terminal, child);
-        self.tabs.push(tab);
-        self.active_tab = 0;
+    /// Save the current tab's screen (or scrollback) to `name` (see `save_path`)
+    fn save_dump(&mut self, range: export::Range, name: &str) {
+        let (Some(path), Some(tab)) = (self.save_path(name), self.tabs.get(self.active_tab)) else {
+            return;
+        };
+        let theme = self.config.profile_theme(tab.profile.as_deref());
+        let mut colors = self.config.theme_colors(theme);
+        tab.terminal.color_overrides().apply(&mut colors);
+        let lines = range.lines(tab.terminal.screen());
+        let dump = export::export(&lines, export::Format::from_path(&path), &colors);
+        let message = match std::fs::write(&path, dump) {
+            Ok(()) => format!("Saved the {} to {}", range.name(), path.display()),
+            Err(e) => format!(
+                "Cannot save the {} to {}: {}",
+                range.name(),
+                path.display(),
+                e
+            ),
+        };
+        self.show_toast(message, toast::DEFAULT_TTL);
+    }
 
-        self.window = Some(window);
-        self.renderer = Some(renderer);
+    /// Save a screenshot of the current tab to `name` (see `save_path`)
+    fn save_screenshot(&mut self, name: &str) {
+        let Some(path) = self.save_path(name) else {
+            return;
+        };
+        let ScreenshotConfig { cursor, selection } = self.config.screenshot;
+        let message = match self.take_screenshot(&path, cursor, selection) {
+            Ok(_) => format!("Saved a screenshot to {}", path.display()),
+            Err(e) => format!("Cannot save a screenshot to {}: {}", path.display(), e),
+        };
+        self.show_toast(message, toast::DEFAULT_TTL);
+    }
 
-        Ok(())
+    /// Draw the current tab's view offscreen and save it to `path` as a PNG,
+    /// returning its size in pixels
+    fn take_screenshot(
+        &mut self,
+        path: &Path,
+        cursor: bool,
+        selection: bool,
+    ) -> std::io::Result<(u32, u32)> {
+        self.fit_colors_to_active_tab();
+        let (Some(renderer), Some(tab)) = (&mut self.renderer, self.tabs.get(self.active_tab))
+        else {
+            return Err(std::io::Error::other("the window is not drawn yet"));
+        };
+        let screenshot =
+            renderer.screenshot(tab.terminal.screen(), tab.scroll_offset, cursor, selection);
+        screenshot.save(path)?;
+        Ok((screenshot.width, screenshot.height))
     }
 
-    /// Create a new tab
-    fn create_new_tab(&mut self) {
-        let Some(renderer) = &self.renderer else {
+    /// Move the current tab's shell and foreground job to the lowest
+    /// priority, or back to the priority the shell had before
+    fn toggle_background_priority(&mut self) {
+        let Some(tab) = self.tabs.get_mut(self.active_tab) else {
             return;
         };
-        let Some(window) = &self.window else { return };
+        let message = match tab.foreground_priority {
+            None => {
+                let previous = tab.child.priority().unwrap_or_default();
+                match tab.child.set_priority(Priority::BACKGROUND) {
+                    Ok(()) => {
+                        tab.foreground_priority = Some(previous);
+                        "Moved the tab to the background (nice 19, idle I/O)".to_string()
+                    }
+                    Err(e) => format!("Cannot lower the tab's priority: {}", e),
+                }
+            }
+            Some(previous) => match tab.child.set_priority(previous) {
+                Ok(()) => {
+                    tab.foreground_priority = None;
+                    "Restored the tab's priority".to_string()
+                }
+                // Lowering a nice level needs privileges
+                Err(e) => format!("Cannot restore the tab's priority: {}", e),
+            },
+        };
+        self.show_toast(message, toast::DEFAULT_TTL);
+    }
 
-        let size = window.inner_size();
-        let cell_size = renderer.cell_size();
-        let cols = (size.width as f32 / cell_size.width) as usize;
-        let terminal_height = size.height.saturating_sub(self.tab_bar_height);
-        let rows = (terminal_height as f32 / cell_size.height) as usize;
-
-        let terminal = Terminal::new(cols.max(1), rows.max(1));
-        match Child::spawn_shell(WindowSize::new(cols as u16, rows as u16)) {
-            Ok(child) => {
-                let _ = child.set_nonblocking(true);
-                let tab = Tab::new(terminal, child);
-                self.tabs.push(tab);
-                self.active_tab = self.tabs.len() - 1;
-                self.needs_redraw = true;
-                log::info!("Created new tab {}", self.active_tab + 1);
+    /// Handle a key press while the tab rename box is open
+    fn handle_tab_rename_key(&mut self, event: &winit::event::KeyEvent) {
+        let Some(name) = &mut self.tab_rename else {
+            return;
+        };
+        match &event.logical_key {
+            Key::Named(NamedKey::Enter) => self.finish_tab_rename(true),
+            Key::Named(NamedKey::Escape) => self.finish_tab_rename(false),
+            Key::Named(NamedKey::Backspace) => {
+                name.pop();
             }
-            Err(e) => {
-                log::error!("Failed to create new tab: {}", e);
+            _ => {
+                if let Some(text) = &event.text {
+                    for c in text.chars().filter(|c| !c.is_control()) {
+                        if name.chars().count() < MAX_TAB_TITLE_LEN {
+                            name.push(c);
+                        }
+                    }
+                }
             }
         }
+        self.needs_redraw = true;
     }
 
-    /// Close the current tab
-    fn close_current_tab(&mut self) -> bool {
-        if self.tabs.len() <= 1 {
-            return false;
-        }
-
-        self.tabs.remove(self.active_tab);
-        if self.active_tab >= self.tabs.len() {
-            self.active_tab = self.tabs.len() - 1;
-        }
-        self.needs_redraw = true;
-        log::info!("Closed tab, now on tab {}", self.active_tab + 1);
-        true
+    /// Width of each tab in the tab bar, in physical pixels
+    fn tab_width(&self) -> Option<u32> {
+        let window = self.window.as_ref()?;
+        let available_width = window.inner_size().width.saturating_sub(NEW_TAB_BTN_WIDTH);
+        Some(
+            available_width
+                .checked_div(self.tabs.len() as u32)
+                .map_or(TAB_MAX_WIDTH, |w| w.min(TAB_MAX_WIDTH)),
+        )
     }
 
-    /// Switch to a specific tab (used by Cmd+1-9 on macOS)
-    #[allow(dead_code)]
-    fn switch_to_tab(&mut self, index: usize) {
-        if index < self.tabs.len() && index != self.active_tab {
-            self.active_tab = index;
-            self.needs_redraw = true;
-            log::info!("Switched to tab {}", index + 1);
+    /// Move the dragged tab to the slot under the pointer
+    fn drag_tab(&mut self, x: f64) {
+        let (Some(dragged), Some(tab_width)) = (self.tab_drag, self.tab_width()) else {
+            return;
+        };
+        if tab_width == 0 {
+            return;
         }
+        let slot = ((x.max(0.0) as u32) / tab_width) as usize;
+        self.move_tab(dragged, slot.min(self.tabs.len() - 1));
     }
 
     /// Handle a click in the tab bar area
@@ -295,16 +2099,19 @@ impl App {
         if self.tabs.is_empty() {
             return;
         }
-        let Some(window) = &self.window else { return };
-
-        let window_width = window.inner_size().width;
-        let num_tabs = self.tabs.len() as u32;
-        let available_width = window_width.saturating_sub(NEW_TAB_BTN_WIDTH);
-        let tab_width = if num_tabs > 0 {
-            (available_width / num_tabs).min(TAB_MAX_WIDTH)
-        } else {
-            TAB_MAX_WIDTH
+        self.finish_tab_rename(false);
+        if self.close_confirm.take().is_some()
+            || self.paste_confirm.take().is_some()
+            || self.clipboard_picker.take().is_some()
+            || self.profile_picker.take().is_some()
+            || self.close_inspector()
+        {
+            self.needs_redraw = true;
+        }
+        let Some(tab_width) = self.tab_width() else {
+            return;
         };
+        let num_tabs = self.tabs.len() as u32;
 
         let click_x = x as u32;
         let tabs_end = num_tabs * tab_width;
@@ -321,16 +2128,10 @@ impl App {
                 let close_x_start = tab_start + tab_width.saturating_sub(CLOSE_BTN_WIDTH);
 
                 if click_x >= close_x_start && self.tabs.len() > 1 {
-                    self.tabs.remove(tab_index);
-                    if self.active_tab >= self.tabs.len() {
-                        self.active_tab = self.tabs.len() - 1;
-                    } else if self.active_tab > tab_index {
-                        self.active_tab -= 1;
-                    }
-                    self.needs_redraw = true;
-                    log::info!("Closed tab via click {}", tab_index + 1);
+                    self.request_close_tab(tab_index);
                 } else {
                     self.switch_to_tab(tab_index);
+                    self.tab_drag = Some(tab_index);
                 }
             }
         }
@@ -348,17 +2149,53 @@ impl App {
 
         // Update renderer
         renderer.resize(size.width, size.height);
+        self.resize_tabs();
+    }
 
-        // Calculate new terminal dimensions (account for tab bar)
-        let cell_size = renderer.cell_size();
-        let cols = (size.width as f32 / cell_size.width) as usize;
-        let terminal_height = size.height.saturating_sub(self.tab_bar_height);
-        let rows = (terminal_height as f32 / cell_size.height) as usize;
+    /// Handle the window moving to a monitor with a different scale factor
+    fn handle_scale_factor_changed(&mut self, scale_factor: f64) {
+        log::info!("Scale factor changed to {}", scale_factor);
+        let Some(renderer) = &mut self.renderer else {
+            return;
+        };
+        renderer.set_scale_factor(scale_factor as f32);
+        // The new physical size usually follows in a Resized event, but is
+        // already known
+        if let Some(window) = &self.window {
+            let size = window.inner_size();
+            if size.width > 0 && size.height > 0 {
+                renderer.resize(size.width, size.height);
+            }
+        }
+        self.resize_tabs();
+    }
 
-        // Resize all tabs
-        if cols > 0 && rows > 0 {
-            for tab in &mut self.tabs {
-                tab.terminal.resize(cols, rows);
+    /// Fit all tabs to the grid size after the window, font or layout changed
+    ///
+    /// Each tab gets the grid of its own zoom; the renderer is at the
+    /// active tab's.
+    fn resize_tabs(&mut self) {
+        let base_font_size = self.base_font_size();
+        let Some(renderer) = &self.renderer else {
+            return;
+        };
+        let active_cell_size = renderer.cell_size();
+        self.tab_bar_height = compute_tab_bar_height(&active_cell_size);
+
+        for (i, tab) in self.tabs.iter_mut().enumerate() {
+            let cell_size = if i == self.active_tab {
+                active_cell_size
+            } else {
+                renderer.cell_size_at(base_font_size * tab.font_scale)
+            };
+            let (cols, rows) = renderer.grid_size_for(&cell_size, self.tab_bar_height);
+            if cols == 0 || rows == 0 {
+                continue;
+            }
+            tab.terminal
+                .set_cell_pixel_size(cell_size.width as u32, cell_size.height as u32);
+            tab.terminal.resize(cols, rows);
+            if !tab.exited {
                 let _ = tab.child.resize(WindowSize::new(cols as u16, rows as u16));
             }
         }
@@ -366,46 +2203,143 @@ impl App {
         self.needs_redraw = true;
     }
 
+    /// The configured font size in physical pixels
+    fn base_font_size(&self) -> f32 {
+        let scale_factor = self
+            .window
+            .as_ref()
+            .map_or(1.0, |w| w.scale_factor() as f32);
+        self.config.font_size() * scale_factor
+    }
+
+    /// Set the renderer to the active tab's zoom, if another tab's was
+    /// shown
+    fn fit_font_to_active_tab(&mut self) {
+        let Some(tab) = self.tabs.get(self.active_tab) else {
+            return;
+        };
+        let font_size = self.base_font_size() * tab.font_scale;
+        let Some(renderer) = &mut self.renderer else {
+            return;
+        };
+        if (renderer.font_size() - font_size).abs() >= 0.01 {
+            renderer.set_font_size(font_size);
+            self.resize_tabs();
+        }
+    }
+
+    /// Set the renderer to the active tab's theme (its profile's, or the
+    /// configured one), with the colors its program changed drawn over it
+    fn fit_colors_to_active_tab(&mut self) {
+        let Some(tab) = self.tabs.get(self.active_tab) else {
+            return;
+        };
+        let theme = self.config.profile_theme(tab.profile.as_deref());
+        if self.theme_colors.as_ref().map(|(name, _)| name) != Some(theme) {
+            self.theme_colors = Some((theme.clone(), self.config.theme_colors(theme)));
+        }
+        let (Some((_, colors)), Some(renderer)) = (&self.theme_colors, &mut self.renderer) else {
+            return;
+        };
+        let mut colors = colors.clone();
+        tab.terminal.color_overrides().apply(&mut colors);
+        renderer.set_colors(colors);
+    }
+
     /// Handle keyboard input
     fn handle_key_input(&mut self, event: &winit::event::KeyEvent) {
         if event.state != ElementState::Pressed {
             return;
         }
 
-        // Check for app shortcuts (Ctrl+Shift combinations)
-        let ctrl_shift = self.modifiers.control_key() && self.modifiers.shift_key();
+        // The tab rename box takes all keys until it is closed
+        if self.tab_rename.is_some() {
+            self.handle_tab_rename_key(event);
+            return;
+        }
 
-        if ctrl_shift {
-            match &event.logical_key {
-                // Copy: Ctrl+Shift+C
-                Key::Character(c) if c.to_lowercase() == "c" => {
-                    self.handle_copy();
-                    return;
-                }
-                // Paste: Ctrl+Shift+V
-                Key::Character(c) if c.to_lowercase() == "v" => {
-                    self.handle_paste();
-                    return;
-                }
-                // Find: Ctrl+Shift+F
-                Key::Character(c) if c.to_lowercase() == "f" => {
-                    self.handle_find();
-                    return;
-                }
-                // Reload config: Ctrl+Shift+R
-                Key::Character(c) if c.to_lowercase() == "r" => {
-                    self.handle_reload_config();
-                    return;
-                }
-                // Toggle theme: Ctrl+Shift+T (macOS only; on Linux Ctrl+Shift+T is new tab)
-                #[cfg(target_os = "macos")]
-                Key::Character(c) if c.to_lowercase() == "t" => {
-                    self.handle_toggle_theme();
-                    return;
+        // So do a close or paste confirmation and the command prompt
+        if self.close_confirm.is_some() {
+            self.handle_close_confirm_key(event);
+            return;
+        }
+        if let Some(request) = &self.paste_confirm {
+            if let Some(confirmed) = dialog_answer(&event.logical_key) {
+                let text = request.text.clone();
+                self.paste_confirm = None;
+                self.needs_redraw = true;
+                if confirmed {
+                    self.send_paste(&text);
                 }
-                _ => {}
+            }
+            return;
+        }
+        let terminal = &mut self.tabs[self.active_tab].terminal;
+        if let Some(feature) = terminal.osc_prompt() {
+            if let Some(allow) = dialog_answer(&event.logical_key) {
+                terminal.answer_osc_prompt(feature, allow);
+                self.needs_redraw = true;
+                // Hand on what the held sequences did (titles, clipboard)
+                self.poll_pty();
+            }
+            return;
+        }
+        if self.clipboard_picker.is_some() {
+            self.handle_clipboard_picker_key(event);
+            return;
+        }
+        if self.profile_picker.is_some() {
+            self.handle_profile_picker_key(event);
+            return;
+        }
+        if self.inspector.is_some() {
+            self.handle_inspector_key(event);
+            return;
+        }
+        if self.command_prompt.is_some() {
+            self.handle_command_prompt_key(event);
+            return;
+        }
+        if self.save_prompt.is_some() {
+            self.handle_save_prompt_key(event);
+            return;
+        }
+        if self
+            .tabs
+            .get(self.active_tab)
+            .is_some_and(|tab| tab.copy_mode.is_some())
+        {
+            self.handle_copy_mode_key(event);
+            return;
+        }
+        if self
+            .tabs
+            .get(self.active_tab)
+            .is_some_and(|tab| tab.hint_mode.is_some())
+        {
+            self.handle_hint_key(event);
+            return;
+        }
+        if self
+            .tabs
+            .get(self.active_tab)
+            .is_some_and(|tab| tab.search.is_some())
+        {
+            self.handle_search_key(event);
+            return;
+        }
+
+        // Configured shortcuts ([keybindings])
+        let unmodified = event.key_without_modifiers();
+        if let Some(action) =
+            self.keybindings
+                .action(&event.logical_key, &unmodified, self.modifiers)
+        {
+            if self.run_action(action) {
+                return;
             }
         }
+        let ctrl_shift = self.modifiers.control_key() && self.modifiers.shift_key();
 
         // macOS: Cmd+V for paste, Cmd+C for copy, Cmd+N for new window, Cmd+T for new tab,
         // Cmd+W to close tab, Cmd+1-9 to switch tabs (standard macOS shortcuts)
@@ -430,10 +2364,7 @@ impl App {
                     return;
                 }
                 Key::Character(c) if c.to_lowercase() == "w" => {
-                    if !self.close_current_tab() {
-                        // Only one tab left - close the terminal window
-                        self.tabs.clear();
-                    }
+                    self.request_close_tab(self.active_tab);
                     return;
                 }
                 Key::Character(c) if c == "1" => {
@@ -476,7 +2407,7 @@ impl App {
             }
         }
 
-        // Linux: Ctrl+Shift+T for new tab, Ctrl+Shift+W to close tab
+        // Linux: Ctrl+Shift+T for new tab, Ctrl+Shift+W to close tab, Ctrl+Shift+N for new window
         #[cfg(not(target_os = "macos"))]
         if ctrl_shift {
             match &event.logical_key {
@@ -485,10 +2416,11 @@ impl App {
                     return;
                 }
                 Key::Character(c) if c.to_lowercase() == "w" => {
-                    if !self.close_current_tab() {
-                        // Only one tab left - close the terminal window
-                        self.tabs.clear();
-                    }
+                    self.request_close_tab(self.active_tab);
+                    return;
+                }
+                Key::Character(c) if c.to_lowercase() == "n" => {
+                    self.handle_new_window();
                     return;
                 }
                 _ => {}
@@ -498,6 +2430,16 @@ impl App {
         if self.tabs.is_empty() {
             return;
         }
+
+        // A held tab has no shell to send keys to
+        if self.tabs[self.active_tab].exited {
+            match &event.logical_key {
+                Key::Named(NamedKey::Enter) => self.close_tab(self.active_tab),
+                Key::Character(c) if c.to_lowercase() == "r" => self.restart_tab(self.active_tab),
+                _ => {}
+            }
+            return;
+        }
         let tab = &mut self.tabs[self.active_tab];
 
         // IMPORTANT: Handle control characters FIRST, before any other shortcut processing
@@ -521,7 +2463,9 @@ impl App {
                         first_char,
                         first_char as u8
                     );
-                    let _ = tab.child.write_all(&[first_char as u8]);
+                    if tab.type_input(&[first_char as u8], &self.config.latency) {
+                        self.needs_redraw = true;
+                    }
                     return;
                 }
             }
@@ -538,15 +2482,17 @@ impl App {
                         ch,
                         ch as u8
                     );
-                    let _ = tab.child.write_all(&[ch as u8]);
+                    if tab.type_input(&[ch as u8], &self.config.latency) {
+                        self.needs_redraw = true;
+                    }
                     return;
                 }
             }
         }
 
-        // Check for font zoom shortcuts
-        // On macOS: Cmd+=/- for zoom (standard macOS behavior)
-        // On Linux: Ctrl+Shift+=/- for zoom (Ctrl+arrows are used by terminal apps for word navigation)
+        // Font zoom with the arrows, besides the zoom keybindings
+        // On macOS: Cmd+Up/Down
+        // On Linux: Ctrl+Shift+Up/Down (Ctrl+arrows are used by terminal apps for word navigation)
         #[cfg(target_os = "macos")]
         let zoom_modifier = self.modifiers.super_key();
         #[cfg(not(target_os = "macos"))]
@@ -554,18 +2500,6 @@ impl App {
 
         if zoom_modifier {
             match &event.logical_key {
-                Key::Character(c) if c == "=" || c == "+" => {
-                    self.change_font_size(2.0);
-                    return;
-                }
-                Key::Character(c) if c == "-" => {
-                    self.change_font_size(-2.0);
-                    return;
-                }
-                Key::Character(c) if c == "0" => {
-                    self.reset_font_size();
-                    return;
-                }
                 Key::Named(NamedKey::ArrowUp) => {
                     self.change_font_size(2.0);
                     return;
@@ -578,79 +2512,385 @@ impl App {
             }
         }
 
-        let application_cursor_keys = tab.terminal.screen().modes().cursor_keys_application;
+        let modes = tab.terminal.screen().modes();
+        let key = &event.logical_key;
+        let data = encode_other_key(
+            key,
+            self.modifiers,
+            modes.modify_other_keys,
+            self.config.format_other_keys,
+        )
+        .or_else(|| encode_key(key, self.modifiers, modes.cursor_keys_application));
+        if let Some(data) = data {
+            log::debug!("Sending key data: {:?}", data);
+            if tab.type_input(&data, &self.config.latency) {
+                self.needs_redraw = true;
+            }
+            if self.config.hide_mouse_while_typing {
+                self.pointer.typed();
+            }
+        }
+    }
+
+    /// Do what a configured shortcut is bound to; returns false if the key
+    /// should go to the application instead
+    fn run_action(&mut self, action: Action) -> bool {
+        match action {
+            Action::Copy => self.handle_copy(),
+            Action::Paste => self.handle_paste(),
+            Action::Find => self.handle_find(),
+            Action::ReloadConfig => self.handle_reload_config(),
+            Action::ToggleTheme => self.handle_toggle_theme(),
+            Action::ZoomIn => self.change_font_size(2.0),
+            Action::ZoomOut => self.change_font_size(-2.0),
+            Action::ZoomReset => self.reset_font_size(),
+            Action::RenameTab => self.start_tab_rename(),
+            Action::ProcessInfo => {
+                self.show_process_info = !self.show_process_info;
+                self.needs_redraw = true;
+            }
+            Action::RunCommand => {
+                self.command_prompt = Some(String::new());
+                self.needs_redraw = true;
+            }
+            Action::ScrollPageUp
+            | Action::ScrollPageDown
+            | Action::ScrollToTop
+            | Action::ScrollToBottom
+            | Action::ScrollToPreviousMark
+            | Action::ScrollToNextMark => return self.scroll_action(action),
+            Action::SetMark => self.handle_set_mark(),
+            Action::CopyMode => self.enter_copy_mode(),
+            Action::Hints => self.enter_hint_mode(false),
+            Action::OpenHints => self.enter_hint_mode(true),
+            Action::ClipboardHistory => self.open_clipboard_picker(),
+            Action::Monitor => self.cycle_monitor(),
+            Action::SshIntegration => self.type_ssh_integration(),
+            Action::NewTabProfile => self.open_profile_picker(),
+            Action::MoveTabLeft => {
+                self.move_tab(self.active_tab, self.active_tab.saturating_sub(1))
+            }
+            Action::MoveTabRight => self.move_tab(self.active_tab, self.active_tab + 1),
+            Action::Inspector => self.open_inspector(),
+            Action::ToggleTrace => self.toggle_trace(),
+            Action::SaveScreen => self.open_save_prompt(SaveTarget::Dump(export::Range::Screen)),
+            Action::SaveScrollback => {
+                self.open_save_prompt(SaveTarget::Dump(export::Range::Scrollback))
+            }
+            Action::Screenshot => self.open_save_prompt(SaveTarget::Screenshot),
+            Action::Deprioritize => self.toggle_background_priority(),
+        }
+        true
+    }
+
+    /// Scroll the view through the scrollback or to a scroll mark
+    ///
+    /// On the alternate screen the key goes to the application, and false
+    /// is returned.
+    fn scroll_action(&mut self, action: Action) -> bool {
+        let Some(tab) = self.tabs.get_mut(self.active_tab) else {
+            return false;
+        };
+        if tab.terminal.screen().modes().alternate_screen {
+            return false;
+        }
 
-        if let Some(data) = encode_key(&event.logical_key, self.modifiers, application_cursor_keys)
-        {
-            log::debug!("Sending key data: {:?}", data);
-            let _ = tab.child.write_all(&data);
+        let page = tab.terminal.screen().rows() as isize;
+        let moved = match action {
+            Action::ScrollPageUp => tab.scroll_by(page),
+            Action::ScrollPageDown => tab.scroll_by(-page),
+            Action::ScrollToTop => tab.scroll_to(usize::MAX),
+            Action::ScrollToBottom => tab.scroll_to(0),
+            Action::ScrollToPreviousMark => tab.scroll_to_mark(true),
+            Action::ScrollToNextMark => tab.scroll_to_mark(false),
+            _ => false,
+        };
+        if moved {
+            self.needs_redraw = true;
         }
+        true
     }
 
-    /// Change font size by delta
-    fn change_font_size(&mut self, delta: f32) {
-        let Some(renderer) = &mut self.renderer else {
+    /// Start copy mode in the current tab
+    fn enter_copy_mode(&mut self) {
+        let Some(tab) = self.tabs.get_mut(self.active_tab) else {
             return;
         };
-        let Some(window) = &self.window else { return };
+        tab.copy_mode = Some(CopyMode::new(tab.terminal.screen(), tab.scroll_offset));
+        tab.terminal.screen_mut().selection_mut().clear();
+        self.needs_redraw = true;
+    }
 
-        let current_size = renderer.font_size();
-        let new_size = (current_size + delta).clamp(8.0, 72.0);
+    /// Handle a key while the current tab is in copy mode
+    fn handle_copy_mode_key(&mut self, event: &winit::event::KeyEvent) {
+        let key = match &event.logical_key {
+            Key::Character(c) => match c.chars().next() {
+                Some(ch) if self.modifiers.control_key() => CopyKey::Ctrl(ch.to_ascii_lowercase()),
+                Some(ch) => CopyKey::Char(ch),
+                None => return,
+            },
+            Key::Named(NamedKey::Space) => CopyKey::Char(' '),
+            Key::Named(NamedKey::ArrowLeft) => CopyKey::Left,
+            Key::Named(NamedKey::ArrowRight) => CopyKey::Right,
+            Key::Named(NamedKey::ArrowUp) => CopyKey::Up,
+            Key::Named(NamedKey::ArrowDown) => CopyKey::Down,
+            Key::Named(NamedKey::Home) => CopyKey::Home,
+            Key::Named(NamedKey::End) => CopyKey::End,
+            Key::Named(NamedKey::PageUp) => CopyKey::PageUp,
+            Key::Named(NamedKey::PageDown) => CopyKey::PageDown,
+            Key::Named(NamedKey::Enter) => CopyKey::Enter,
+            Key::Named(NamedKey::Escape) => CopyKey::Escape,
+            Key::Named(NamedKey::Backspace) => CopyKey::Backspace,
+            _ => return,
+        };
 
-        if (new_size - current_size).abs() < 0.1 {
+        let tab = &mut self.tabs[self.active_tab];
+        let Some(mode) = &mut tab.copy_mode else {
             return;
+        };
+        match mode.handle_key(key, tab.terminal.screen()) {
+            CopyAction::None => {}
+            CopyAction::Redraw => {
+                tab.scroll_offset =
+                    mode.scroll_offset_for(tab.terminal.screen(), tab.scroll_offset);
+                let selection = mode.selection().unwrap_or_else(Selection::new);
+                *tab.terminal.screen_mut().selection_mut() = selection;
+                self.needs_redraw = true;
+            }
+            CopyAction::Yank(text) => {
+                self.exit_copy_mode();
+                self.copy_to_clipboard(&text);
+            }
+            CopyAction::Exit => self.exit_copy_mode(),
         }
+    }
+
+    /// Leave copy mode, clearing its selection and scrolling back to the bottom
+    fn exit_copy_mode(&mut self) {
+        let tab = &mut self.tabs[self.active_tab];
+        tab.copy_mode = None;
+        tab.scroll_offset = 0;
+        tab.terminal.screen_mut().selection_mut().clear();
+        self.needs_redraw = true;
+    }
 
-        renderer.set_font_size(new_size);
+    /// Switch the current tab's monitor to the next of: off, activity,
+    /// silence
+    fn cycle_monitor(&mut self) {
+        let Some(tab) = self.tabs.get_mut(self.active_tab) else {
+            return;
+        };
+        let kind = monitor::cycle(tab.monitor.as_ref().map(Monitor::kind));
+        tab.monitor = kind.map(|kind| Monitor::new(kind, Instant::now()));
+        let text = match kind {
+            Some(MonitorKind::Activity) => "Monitoring for activity".to_string(),
+            Some(MonitorKind::Silence) => format!(
+                "Monitoring for {}s of silence",
+                self.config.monitor.silence_seconds
+            ),
+            None => "Monitor off".to_string(),
+        };
+        self.show_toast(text, toast::DEFAULT_TTL);
+    }
 
-        // Recalculate terminal dimensions (account for tab bar)
-        let size = window.inner_size();
-        let cell_size = renderer.cell_size();
-        self.tab_bar_height = compute_tab_bar_height(&cell_size);
-        let cols = (size.width as f32 / cell_size.width) as usize;
-        let terminal_height = size.height.saturating_sub(self.tab_bar_height);
-        let rows = (terminal_height as f32 / cell_size.height) as usize;
+    /// Type the shell integration installer on the remote prompt of the
+    /// current tab's ssh session, for the user to check and run
+    fn type_ssh_integration(&mut self) {
+        let Some(tab) = self.tabs.get_mut(self.active_tab) else {
+            return;
+        };
+        // The foreground process is refreshed only every so often
+        tab.foreground_checked = None;
+        tab.refresh_foreground();
+        if !tab.running_ssh() {
+            self.show_toast(
+                "ssh is not running in this tab".to_string(),
+                toast::DEFAULT_TTL,
+            );
+            return;
+        }
+        self.send_paste(&ssh::install_command());
+        self.show_toast(
+            "Check the command and press Enter to install".to_string(),
+            toast::DEFAULT_TTL,
+        );
+    }
 
-        // Resize all tabs
-        if cols > 0 && rows > 0 {
-            for tab in &mut self.tabs {
-                tab.terminal.resize(cols, rows);
-                let _ = tab.child.resize(WindowSize::new(cols as u16, rows as u16));
+    /// Fire silence monitors and clear the badge of the tab being looked at
+    fn update_monitors(&mut self) {
+        let now = Instant::now();
+        let silence = Duration::from_secs(self.config.monitor.silence_seconds);
+        let mut fired = Vec::new();
+        for (i, tab) in self.tabs.iter_mut().enumerate() {
+            let watched = i == self.active_tab && self.focused;
+            let Some(monitor) = tab.monitor.as_mut().filter(|_| !tab.exited) else {
+                continue;
+            };
+            if watched && monitor.seen() {
+                self.needs_redraw = true;
+            }
+            if monitor.poll(now, silence, watched) {
+                fired.push((i, MonitorKind::Silence));
             }
         }
+        for (i, kind) in fired {
+            self.monitor_fired(i, kind, now);
+        }
+    }
 
+    /// Tell the user that tab `index`'s monitor fired
+    fn monitor_fired(&mut self, index: usize, kind: MonitorKind, now: Instant) {
+        let event = match kind {
+            MonitorKind::Activity => hooks::Event::Activity,
+            MonitorKind::Silence => hooks::Event::Silence,
+        };
+        let actions = self.hooks.dispatch(event, index, now);
+        self.run_hook_actions(actions);
+        if self.config.monitor.notify {
+            let text = match kind {
+                MonitorKind::Activity => "Activity",
+                MonitorKind::Silence => "Silence",
+            };
+            self.toasts.push(
+                format!(
+                    "{} in tab {}: {}",
+                    text,
+                    index + 1,
+                    self.tabs[index].display_title()
+                ),
+                toast::DEFAULT_TTL,
+            );
+        }
         self.needs_redraw = true;
     }
 
-    /// Reset font size to default (scaled for HiDPI)
-    fn reset_font_size(&mut self) {
-        let Some(renderer) = &mut self.renderer else {
+    /// Label the URLs, paths and hashes on screen in the current tab; the
+    /// chosen one is opened if `open`, copied otherwise
+    fn enter_hint_mode(&mut self, open: bool) {
+        let patterns = match hints::compile_patterns(&self.config.hints) {
+            Ok(patterns) => patterns,
+            Err(e) => {
+                log::warn!("Invalid hint configuration: {}", e);
+                return;
+            }
+        };
+        let Some(tab) = self.tabs.get_mut(self.active_tab) else {
+            return;
+        };
+        let mut mode = HintMode::new(
+            tab.terminal.screen(),
+            tab.scroll_offset,
+            &patterns,
+            &self.config.hints.alphabet,
+        );
+        mode.set_open(open);
+        if mode.is_empty() {
+            self.show_toast("No hints".to_string(), toast::DEFAULT_TTL);
+            return;
+        }
+        tab.hint_mode = Some(mode);
+        self.needs_redraw = true;
+    }
+
+    /// Handle a key while the current tab is in hint mode
+    fn handle_hint_key(&mut self, event: &winit::event::KeyEvent) {
+        let tab = &mut self.tabs[self.active_tab];
+        let Some(mode) = &mut tab.hint_mode else {
             return;
         };
-        let Some(window) = &self.window else { return };
+        let action = match &event.logical_key {
+            Key::Named(NamedKey::Escape) => HintAction::Cancel,
+            Key::Named(NamedKey::Backspace) => {
+                mode.backspace();
+                HintAction::Pending
+            }
+            Key::Character(c) => match c.chars().next() {
+                Some(ch) => mode.handle_char(ch),
+                None => return,
+            },
+            _ => return,
+        };
+        self.needs_redraw = true;
+        match action {
+            HintAction::Pending => {}
+            HintAction::Select { text, .. } if mode.opens() => {
+                tab.hint_mode = None;
+                self.open_text(&text);
+            }
+            HintAction::Select { text, paste } => {
+                tab.hint_mode = None;
+                self.copy_to_clipboard(&text);
+                if paste {
+                    self.paste_text(&text);
+                }
+            }
+            HintAction::Cancel => tab.hint_mode = None,
+        }
+    }
 
-        let scale_factor = window.scale_factor() as f32;
-        let default_size = self.config.font_size() * scale_factor;
+    /// Hand a link or match to the first opener rule it matches
+    fn open_text(&mut self, text: &str) {
+        let cwd = self.tabs.get(self.active_tab).and_then(Tab::cwd);
+        match self.openers.route(text, |name| std::env::var(name).ok()) {
+            Some(Opened::Run(argv)) => {
+                log::info!("Opening with {:?}", argv);
+                opener::spawn(&argv, cwd.as_deref());
+            }
+            Some(Opened::Copy(text)) => self.copy_to_clipboard(&text),
+            None => self.show_toast("No opener for this match".to_string(), toast::DEFAULT_TTL),
+        }
+    }
 
-        renderer.set_font_size(default_size);
+    /// The hyperlink, or else the hint match, under the mouse
+    fn text_under_mouse(&self) -> Option<String> {
+        let tab = self.tabs.get(self.active_tab)?;
+        let screen = tab.terminal.screen();
+        if let Some(url) = self
+            .link_under_mouse()
+            .and_then(|id| screen.get_hyperlink(id))
+        {
+            return Some(url.to_string());
+        }
+        let patterns = hints::compile_patterns(&self.config.hints).ok()?;
+        let index = screen.scrollback().len() - tab.scroll_offset + self.mouse_cell.1 as usize;
+        let line = screen.history_line(index)?;
+        hints::match_at(line, self.mouse_cell.0 as usize, &patterns)
+    }
 
-        // Recalculate terminal dimensions (account for tab bar)
-        let size = window.inner_size();
-        let cell_size = renderer.cell_size();
-        self.tab_bar_height = compute_tab_bar_height(&cell_size);
-        let cols = (size.width as f32 / cell_size.width) as usize;
-        let terminal_height = size.height.saturating_sub(self.tab_bar_height);
-        let rows = (terminal_height as f32 / cell_size.height) as usize;
+    /// Set a scroll mark on the cursor line of the current tab
+    fn handle_set_mark(&mut self) {
+        let Some(tab) = self.tabs.get_mut(self.active_tab) else {
+            return;
+        };
+        tab.terminal.mark_cursor_line();
+        self.show_toast("Mark set".to_string(), toast::DEFAULT_TTL);
+    }
 
-        // Resize all tabs
-        if cols > 0 && rows > 0 {
-            for tab in &mut self.tabs {
-                tab.terminal.resize(cols, rows);
-                let _ = tab.child.resize(WindowSize::new(cols as u16, rows as u16));
-            }
+    /// Change the active tab's font size by delta
+    fn change_font_size(&mut self, delta: f32) {
+        let Some(renderer) = &self.renderer else {
+            return;
+        };
+        let current_size = renderer.font_size();
+        let new_size = (current_size + delta).clamp(8.0, 72.0);
+
+        if (new_size - current_size).abs() < 0.1 {
+            return;
         }
 
-        self.needs_redraw = true;
+        let base_font_size = self.base_font_size();
+        if let Some(tab) = self.tabs.get_mut(self.active_tab) {
+            tab.font_scale = new_size / base_font_size;
+        }
+        self.fit_font_to_active_tab();
+    }
+
+    /// Reset the active tab's font size to the configured size
+    fn reset_font_size(&mut self) {
+        if let Some(tab) = self.tabs.get_mut(self.active_tab) {
+            tab.font_scale = 1.0;
+        }
+        self.fit_font_to_active_tab();
     }
 
     /// Handle mouse input
@@ -659,7 +2899,7 @@ impl App {
             return;
         }
 
-        // Handle tab bar clicks
+        // Handle tab bar clicks; pressing on a tab also starts dragging it
         if button == MouseButton::Left
             && state == ElementState::Pressed
             && self.mouse_pixel.1 < self.tab_bar_height as f64
@@ -667,6 +2907,34 @@ impl App {
             self.handle_tab_bar_click(self.mouse_pixel.0);
             return;
         }
+        if button == MouseButton::Left
+            && state == ElementState::Released
+            && self.tab_drag.take().is_some()
+        {
+            return;
+        }
+
+        // Clicking the new output pill jumps to the bottom
+        if button == MouseButton::Left && state == ElementState::Pressed {
+            let tab = &mut self.tabs[self.active_tab];
+            let on_pill = tab.new_output_pill().is_some_and(|pill| {
+                self.renderer.as_ref().is_some_and(|renderer| {
+                    renderer.pill_contains(&pill, self.mouse_pixel.0, self.mouse_pixel.1)
+                })
+            });
+            if on_pill {
+                tab.scroll_to(0);
+                self.swallow_release = true;
+                self.needs_redraw = true;
+                return;
+            }
+        }
+        if button == MouseButton::Left
+            && state == ElementState::Released
+            && std::mem::take(&mut self.swallow_release)
+        {
+            return;
+        }
 
         // Handle scrollbar dragging first (left button only)
         if button == MouseButton::Left {
@@ -674,9 +2942,8 @@ impl App {
                 // Check if click is on scrollbar (right 12 pixels of window)
                 if let Some(window) = &self.window {
                     let window_width = window.inner_size().width as f64;
-                    let scrollbar_width = 12.0;
 
-                    if self.mouse_pixel.0 >= window_width - scrollbar_width
+                    if self.mouse_pixel.0 >= window_width - SCROLLBAR_HIT_WIDTH
                         && self.mouse_pixel.1 >= self.tab_bar_height as f64
                     {
                         let tab = &self.tabs[self.active_tab];
@@ -699,6 +2966,20 @@ impl App {
             }
         }
 
+        // Ctrl+click opens the link or match under the mouse, also while the
+        // program tracks the mouse; its release is not passed on either
+        if button == MouseButton::Left
+            && state == ElementState::Pressed
+            && self.modifiers.control_key()
+        {
+            if let Some(text) = self.text_under_mouse() {
+                self.swallow_release = true;
+                self.open_text(&text);
+                return;
+            }
+        }
+
+        let pixel = self.mouse_grid_pixel();
         let tab = &mut self.tabs[self.active_tab];
         let modes = tab.terminal.screen().modes().clone();
 
@@ -718,7 +2999,17 @@ impl App {
                 } else {
                     // Finish selection
                     tab.terminal.screen_mut().selection_mut().finish();
+                    if self.config.primary_selection {
+                        if let Some(text) = self.selection_text() {
+                            self.set_primary_selection(&text);
+                        }
+                    }
                 }
+            } else if button == MouseButton::Middle
+                && state == ElementState::Pressed
+                && self.config.primary_selection
+            {
+                self.paste_primary_selection();
             }
             // Track button state for selection dragging
             let idx = match button {
@@ -740,7 +3031,8 @@ impl App {
 
         if let Some(data) = encode_mouse(
             event,
-            modes.mouse_sgr,
+            pixel,
+            MouseEncoding::from_modes(&modes),
             modes.mouse_button_event,
             modes.mouse_any_event,
         ) {
@@ -766,12 +3058,23 @@ impl App {
             return;
         }
 
+        // Reorder tabs while one is dragged
+        if self.tab_drag.is_some() {
+            self.drag_tab(position.x);
+            return;
+        }
+
         // Handle scrollbar dragging
         if self.scrollbar_dragging {
+            let status_bar_height = self
+                .renderer
+                .as_ref()
+                .map_or(0, Renderer::status_bar_height);
             if let Some(window) = &self.window {
                 let tab = &mut self.tabs[self.active_tab];
-                let window_height =
-                    (window.inner_size().height as f64 - self.tab_bar_height as f64).max(1.0);
+                let window_height = (window.inner_size().height as f64
+                    - (self.tab_bar_height + status_bar_height) as f64)
+                    .max(1.0);
                 let scrollback_len = tab.terminal.screen().scrollback().len();
                 let visible_rows = tab.terminal.screen().rows();
 
@@ -811,20 +3114,21 @@ impl App {
             return;
         };
 
-        let cell_size = renderer.cell_size();
-        let col = (position.x / cell_size.width as f64) as u16;
-        let adjusted_y = (position.y - self.tab_bar_height as f64).max(0.0);
-        let row = (adjusted_y / cell_size.height as f64) as u16;
-
-        if col == self.mouse_cell.0 && row == self.mouse_cell.1 {
-            return;
-        }
+        let (col, row) = renderer.pixel_to_cell(position.x, position.y, self.tab_bar_height);
 
+        let cell_changed = (col, row) != self.mouse_cell;
         self.mouse_cell = (col, row);
+        let pixel = self.mouse_grid_pixel();
 
         let tab = &mut self.tabs[self.active_tab];
         let modes = tab.terminal.screen().modes().clone();
 
+        // SGR-Pixels reports every motion, other encodings only new cells
+        let pixel_motion = modes.mouse_sgr_pixels && modes.mouse_tracking_enabled();
+        if !cell_changed && !pixel_motion {
+            return;
+        }
+
         // Handle text selection dragging when mouse tracking is NOT enabled
         if !modes.mouse_tracking_enabled() && self.mouse_buttons[0] {
             // Left button is held - update selection
@@ -845,7 +3149,8 @@ impl App {
             let event = MouseEvent::Move(col, row);
             if let Some(data) = encode_mouse(
                 event,
-                modes.mouse_sgr,
+                pixel,
+                MouseEncoding::from_modes(&modes),
                 modes.mouse_button_event,
                 modes.mouse_any_event,
             ) {
@@ -854,12 +3159,102 @@ impl App {
         }
     }
 
+    /// Mouse position in pixels from the top left of the grid
+    fn mouse_grid_pixel(&self) -> (u16, u16) {
+        let (origin_x, origin_y) = self
+            .renderer
+            .as_ref()
+            .map_or((0, self.tab_bar_height), |r| {
+                r.grid_origin(self.tab_bar_height)
+            });
+        let x = self.mouse_pixel.0 - origin_x as f64;
+        let y = self.mouse_pixel.1 - origin_y as f64;
+        (
+            x.clamp(0.0, u16::MAX as f64) as u16,
+            y.clamp(0.0, u16::MAX as f64) as u16,
+        )
+    }
+
+    /// What the mouse is over in the active tab
+    fn hover_target(&self) -> HoverTarget {
+        let (x, y) = self.mouse_pixel;
+        if y < self.tab_bar_height as f64 {
+            return HoverTarget::TabBar;
+        }
+        let Some(tab) = self.tabs.get(self.active_tab) else {
+            return HoverTarget::Text;
+        };
+        let screen = tab.terminal.screen();
+        let on_scrollbar = self
+            .window
+            .as_ref()
+            .is_some_and(|window| x >= window.inner_size().width as f64 - SCROLLBAR_HIT_WIDTH);
+        if on_scrollbar && !screen.scrollback().is_empty() {
+            return HoverTarget::Scrollbar;
+        }
+        if screen.modes().mouse_tracking_enabled() {
+            return HoverTarget::MouseTracking;
+        }
+
+        if self.link_under_mouse().is_some() {
+            HoverTarget::Link
+        } else {
+            HoverTarget::Text
+        }
+    }
+
+    /// Hyperlink ID of the cell under the mouse in the active tab
+    fn link_under_mouse(&self) -> Option<u32> {
+        let tab = self.tabs.get(self.active_tab)?;
+        let screen = tab.terminal.screen();
+        let col = self.mouse_cell.0 as usize;
+        let index = screen.scrollback().len() - tab.scroll_offset + self.mouse_cell.1 as usize;
+        screen
+            .history_line(index)
+            .filter(|line| col < line.cols())
+            .map(|line| line.cell(col).hyperlink_id)
+            .filter(|&id| id != 0)
+    }
+
+    /// Update the pointer shape for what is under the mouse and apply it
+    ///
+    /// Each tab keeps the shape its program asked for with OSC 22.
+    fn update_pointer(&mut self) {
+        let target = self.hover_target();
+        let link = self
+            .link_under_mouse()
+            .filter(|_| self.mouse_in_window && target == HoverTarget::Link);
+        if link != self.hovered_link {
+            self.hovered_link = link;
+            self.needs_redraw = true;
+        }
+        let hovered_tab = self
+            .tab_width()
+            .filter(|&w| w > 0 && self.mouse_in_window && target == HoverTarget::TabBar)
+            .map(|w| (self.mouse_pixel.0.max(0.0) as u32 / w) as usize)
+            .filter(|&i| i < self.tabs.len());
+        if hovered_tab != self.hovered_tab {
+            self.hovered_tab = hovered_tab;
+            self.needs_redraw = true;
+        }
+        let requested = self
+            .tabs
+            .get(self.active_tab)
+            .and_then(|tab| tab.terminal.pointer_shape())
+            .and_then(pointer::parse_shape);
+        self.pointer.hover(target, requested);
+        if let Some(window) = &self.window {
+            self.pointer.apply(window);
+        }
+    }
+
     /// Handle mouse scroll
     fn handle_mouse_scroll(&mut self, delta: MouseScrollDelta) {
         if self.tabs.is_empty() {
             return;
         }
 
+        let pixel = self.mouse_grid_pixel();
         let tab = &mut self.tabs[self.active_tab];
         let modes = tab.terminal.screen().modes().clone();
         let lines = match delta {
@@ -871,8 +3266,8 @@ impl App {
             return;
         }
 
-        // If mouse tracking is enabled or in alternate screen, send to PTY
-        if modes.mouse_tracking_enabled() || modes.alternate_screen {
+        // If mouse tracking is enabled, send wheel events to the PTY
+        if modes.mouse_tracking_enabled() {
             let event = MouseEvent::Scroll {
                 x: self.mouse_cell.0,
                 y: self.mouse_cell.1,
@@ -880,96 +3275,334 @@ impl App {
             };
             if let Some(data) = encode_mouse(
                 event,
-                modes.mouse_sgr,
+                pixel,
+                MouseEncoding::from_modes(&modes),
                 modes.mouse_button_event,
                 modes.mouse_any_event,
             ) {
                 let _ = tab.child.write_all(&data);
             }
-        } else {
-            // Scroll the viewport through scrollback history
-            let scrollback_len = tab.terminal.screen().scrollback().len();
-            if lines > 0 {
-                // Scroll up (show older content)
-                tab.scroll_offset = (tab.scroll_offset + lines as usize).min(scrollback_len);
-            } else {
-                // Scroll down (show newer content)
-                tab.scroll_offset = tab.scroll_offset.saturating_sub((-lines) as usize);
+        } else if modes.alternate_screen {
+            // The alternate screen has no scrollback: translate the wheel to
+            // arrow keys so pagers and editors scroll (alternate scroll, 1007)
+            if modes.alternate_scroll {
+                let data = encode_alternate_scroll(lines, modes.cursor_keys_application);
+                let _ = tab.child.write_all(&data);
             }
+        } else {
+            // Scroll the viewport through scrollback history (positive shows older content)
+            tab.scroll_by(lines as isize);
             self.needs_redraw = true;
         }
     }
 
     /// Handle copy (Ctrl+Shift+C)
     fn handle_copy(&mut self) {
-        if self.tabs.is_empty() {
-            return;
+        if let Some(text) = self.selection_text() {
+            self.copy_to_clipboard(&text);
         }
-        let tab = &self.tabs[self.active_tab];
+    }
+
+    /// Text of the active tab's selection, if anything is selected
+    fn selection_text(&self) -> Option<String> {
+        let tab = self.tabs.get(self.active_tab)?;
 
         let screen = tab.terminal.screen();
         let selection = screen.selection();
 
         if selection.is_empty() {
+            return None;
+        }
+
+        // The region that is highlighted; soft-wrapped rows are joined
+        let (start, end) = selection.bounds();
+        let region = match selection.selection_type {
+            SelectionType::Line => TextRegion::Rows {
+                top: start.row,
+                bottom: end.row,
+            },
+            SelectionType::Block => TextRegion::Block { start, end },
+            SelectionType::Normal | SelectionType::Word => TextRegion::Stream { start, end },
+        };
+        let text = screen.text_region(region, true);
+        let text = text.trim_end().to_string();
+
+        (!text.is_empty()).then_some(text)
+    }
+
+    /// Put text on the clipboard and confirm it with a toast
+    fn copy_to_clipboard(&mut self, text: &str) {
+        let Some(clipboard) = &mut self.clipboard else {
+            return;
+        };
+
+        if let Err(e) = clipboard.set_text(text) {
+            log::warn!("Failed to copy to clipboard: {}", e);
+            self.show_toast("Copy failed".to_string(), toast::DEFAULT_TTL);
+        } else {
+            log::debug!("Copied {} bytes to clipboard", text.len());
+            let lines = text.lines().count();
+            let message = match lines {
+                1 => "Copied 1 line".to_string(),
+                n => format!("Copied {} lines", n),
+            };
+            self.show_toast(message, toast::DEFAULT_TTL);
+            self.clipboard_history.borrow_mut().push(text);
+        }
+    }
+
+    /// Open the clipboard history picker on the newest entry
+    fn open_clipboard_picker(&mut self) {
+        if self.clipboard_history.borrow().is_empty() {
+            self.show_toast("Clipboard history is empty".to_string(), toast::DEFAULT_TTL);
+            return;
+        }
+        self.clipboard_picker = Some(Picker::default());
+        self.needs_redraw = true;
+    }
+
+    /// Handle a key while the clipboard history picker is open: arrows or
+    /// j/k move, Enter or a digit pastes, Escape or q closes
+    fn handle_clipboard_picker_key(&mut self, event: &winit::event::KeyEvent) {
+        let Some(picker) = &mut self.clipboard_picker else {
+            return;
+        };
+        let len = self.clipboard_history.borrow().len();
+        let chosen = match picker_key(picker, &event.logical_key, len) {
+            Some(PickerKey::Moved) => None,
+            Some(PickerKey::Chosen(index)) => Some(index),
+            Some(PickerKey::Closed) => {
+                self.clipboard_picker = None;
+                None
+            }
+            None => return,
+        };
+        self.needs_redraw = true;
+        let Some(index) = chosen else {
+            return;
+        };
+        let text = self
+            .clipboard_history
+            .borrow()
+            .get(index)
+            .map(str::to_string);
+        if let Some(text) = text {
+            self.clipboard_picker = None;
+            self.paste_text(&text);
+        }
+    }
+
+    /// Open the profile picker, for a new tab with the chosen profile
+    fn open_profile_picker(&mut self) {
+        if self.config.profiles.is_empty() {
+            self.show_toast("No profiles are configured".to_string(), toast::DEFAULT_TTL);
+            return;
+        }
+        self.profile_picker = Some(Picker::default());
+        self.needs_redraw = true;
+    }
+
+    /// Handle a key while the profile picker is open: arrows or j/k move,
+    /// Enter or a digit opens the tab, Escape or q closes
+    fn handle_profile_picker_key(&mut self, event: &winit::event::KeyEvent) {
+        let Some(picker) = &mut self.profile_picker else {
+            return;
+        };
+        let len = self.config.profiles.len();
+        match picker_key(picker, &event.logical_key, len) {
+            Some(PickerKey::Moved) => {}
+            Some(PickerKey::Chosen(index)) => {
+                let Some(name) = self.config.profiles.keys().nth(index).cloned() else {
+                    return;
+                };
+                self.profile_picker = None;
+                self.open_tab(Some(&name), None, None);
+            }
+            Some(PickerKey::Closed) => self.profile_picker = None,
+            None => return,
+        }
+        self.needs_redraw = true;
+    }
+
+    /// Start tracing shell output to the trace file, or stop
+    fn toggle_trace(&mut self) {
+        let mut trace = self.trace.borrow_mut();
+        let path = trace.path().display().to_string();
+        let message = if trace.is_active() {
+            match trace.stop() {
+                Ok(()) => format!("Trace written to {}", path),
+                Err(e) => format!("Cannot write the trace to {}: {}", path, e),
+            }
+        } else {
+            match trace.start() {
+                Ok(()) => format!("Tracing to {} - Ctrl+Shift+L stops", path),
+                Err(e) => format!("Cannot write the trace to {}: {}", path, e),
+            }
+        };
+        drop(trace);
+        self.show_toast(message, toast::DEFAULT_TTL);
+    }
+
+    /// Open the terminal inspector over the current tab
+    fn open_inspector(&mut self) {
+        if let Some(tab) = self.tabs.get_mut(self.active_tab) {
+            tab.terminal.set_action_log(true);
+        }
+        self.inspector = Some(Picker::default());
+        self.needs_redraw = true;
+    }
+
+    /// Close the terminal inspector and stop recording actions; returns
+    /// whether it was open
+    fn close_inspector(&mut self) -> bool {
+        if self.inspector.take().is_none() {
+            return false;
+        }
+        for tab in &mut self.tabs {
+            tab.terminal.set_action_log(false);
+        }
+        true
+    }
+
+    /// Handle a key while the inspector is open: arrows or j/k scroll,
+    /// Escape, q or the inspector keybinding (Ctrl+Shift+D) closes
+    fn handle_inspector_key(&mut self, event: &winit::event::KeyEvent) {
+        let len = self.inspector_lines().len();
+        let Some(picker) = &mut self.inspector else {
             return;
+        };
+        let toggle = self.keybindings.action(
+            &event.logical_key,
+            &event.key_without_modifiers(),
+            self.modifiers,
+        ) == Some(Action::Inspector);
+        match picker_key(picker, &event.logical_key, len) {
+            Some(PickerKey::Moved) => {}
+            Some(PickerKey::Closed) => {
+                self.close_inspector();
+            }
+            _ if toggle => {
+                self.close_inspector();
+            }
+            _ => return,
+        }
+        self.needs_redraw = true;
+    }
+
+    /// What the inspector shows for the current tab
+    fn inspector_lines(&self) -> Vec<String> {
+        let Some(tab) = self.tabs.get(self.active_tab) else {
+            return Vec::new();
+        };
+        let terminal = &tab.terminal;
+        let mut lines = inspector::state_lines(terminal.screen(), terminal.parser_state());
+        lines.extend(inspector::stats_lines(terminal.stats()));
+        lines.push(inspector::latency_line(&tab.latency));
+        if self.config.latency.predictive_echo {
+            lines.push(inspector::prediction_line(
+                &tab.prediction,
+                &self.config.latency,
+            ));
+        }
+        lines.extend(inspector::memory_lines(
+            &terminal.memory_usage(),
+            &self.memory_usage(),
+            &self.config.memory,
+        ));
+        lines.push("Recent actions (newest first):".to_string());
+        if let Some(log) = terminal.action_log() {
+            lines.extend(log.lines().map(|line| format!("  {}", line)));
+        }
+        lines
+    }
+
+    /// Memory held by the tabs, the glyph cache and the clipboard history
+    fn memory_usage(&self) -> MemoryUsage {
+        let mut usage = MemoryUsage {
+            glyphs: self
+                .renderer
+                .as_ref()
+                .map_or(0, Renderer::glyph_cache_bytes),
+            clipboard: self.clipboard_history.borrow().bytes(),
+            ..Default::default()
+        };
+        for tab in &self.tabs {
+            usage += tab.terminal.memory_usage();
+        }
+        usage
+    }
+
+    /// Trim the tabs whose scrollback or images are over a share of the
+    /// budget (see `memory::fair_share`), and the glyph cache if it is over
+    /// `glyph_budget`
+    fn trim_memory(
+        &mut self,
+        scrollback_share: Option<usize>,
+        image_share: Option<usize>,
+        glyph_budget: Option<usize>,
+    ) {
+        for (i, tab) in self.tabs.iter_mut().enumerate() {
+            let screen = tab.terminal.screen_mut();
+            let lines = scrollback_share.map_or(0, |max| screen.trim_scrollback(max));
+            let images = image_share.map_or(0, |max| screen.trim_images(max));
+            if lines == 0 && images == 0 {
+                continue;
+            }
+            log::debug!(
+                "Memory budget: dropped {} scrollback lines and {} images from tab {}",
+                lines,
+                images,
+                i + 1
+            );
+            tab.scroll_to(tab.scroll_offset);
+            if i == self.active_tab {
+                self.needs_redraw = true;
+            }
         }
-
-        // Get selected text using the Line::text() method
-        let (start, end) = selection.bounds();
-        let mut text = String::new();
-        let cols = screen.cols();
-
-        for row in start.row..=end.row {
-            let start_col = if row == start.row { start.col } else { 0 };
-            let end_col = if row == end.row { end.col } else { cols };
-
-            // Get line from screen or scrollback
-            if row < 0 {
-                // Line is in scrollback
-                let scrollback_idx = (-row - 1) as usize;
-                if let Some(line) = screen.scrollback().get_from_end(scrollback_idx) {
-                    let line_text = line.text();
-                    let chars: Vec<char> = line_text.chars().collect();
-                    for ch in chars.iter().take(end_col.min(chars.len())).skip(start_col) {
-                        text.push(*ch);
-                    }
-                }
-            } else if (row as usize) < screen.grid().rows() {
-                // Line is in visible grid
-                let line = screen.line(row as usize);
-                let line_text = line.text();
-                let chars: Vec<char> = line_text.chars().collect();
-                for ch in chars.iter().take(end_col.min(chars.len())).skip(start_col) {
-                    text.push(*ch);
-                }
+        if let (Some(budget), Some(renderer)) = (glyph_budget, &mut self.renderer) {
+            if renderer.trim_glyph_cache(budget) {
+                log::debug!("Memory budget: emptied the glyph cache");
+                self.needs_redraw = true;
             }
+        }
+    }
 
-            // Add newline between lines (but not after the last line)
-            if row < end.row {
-                // Trim trailing spaces before newline
-                while text.ends_with(' ') {
-                    text.pop();
-                }
-                text.push('\n');
+    /// Put text on the PRIMARY selection (Linux only)
+    fn set_primary_selection(&mut self, text: &str) {
+        #[cfg(target_os = "linux")]
+        if let Some(clipboard) = &mut self.clipboard {
+            use arboard::{LinuxClipboardKind, SetExtLinux};
+            if let Err(e) = clipboard
+                .set()
+                .clipboard(LinuxClipboardKind::Primary)
+                .text(text)
+            {
+                log::debug!("Failed to set the primary selection: {}", e);
             }
         }
+        #[cfg(not(target_os = "linux"))]
+        let _ = text;
+    }
 
-        // Trim trailing whitespace
-        let text = text.trim_end().to_string();
-
-        if text.is_empty() {
-            return;
+    /// Text of the PRIMARY selection (Linux only)
+    fn primary_selection(&mut self) -> Option<String> {
+        #[cfg(target_os = "linux")]
+        if let Some(clipboard) = &mut self.clipboard {
+            use arboard::{GetExtLinux, LinuxClipboardKind};
+            return clipboard
+                .get()
+                .clipboard(LinuxClipboardKind::Primary)
+                .text()
+                .map_err(|e| log::debug!("Failed to get the primary selection: {}", e))
+                .ok();
         }
+        None
+    }
 
-        // Now copy to clipboard
-        let Some(clipboard) = &mut self.clipboard else {
-            return;
-        };
-
-        if let Err(e) = clipboard.set_text(&text) {
-            log::warn!("Failed to copy to clipboard: {}", e);
-        } else {
-            log::debug!("Copied {} bytes to clipboard", text.len());
+    /// Paste the PRIMARY selection (middle click, Linux only)
+    fn paste_primary_selection(&mut self) {
+        if let Some(text) = self.primary_selection() {
+            self.paste_text(&text);
         }
     }
 
@@ -983,93 +3616,222 @@ impl App {
             return;
         }
 
-        let tab = &mut self.tabs[self.active_tab];
-
         match clipboard.get_text() {
-            Ok(text) => {
-                if text.is_empty() {
-                    return;
-                }
-                let data = if tab.terminal.screen().modes().bracketed_paste {
-                    encode_bracketed_paste(&text)
-                } else {
-                    text.into_bytes()
-                };
-                if let Err(e) = tab.child.write_all(&data) {
-                    log::warn!("Failed to write paste data to PTY: {}", e);
-                } else {
-                    log::debug!("Pasted {} bytes", data.len());
-                }
-            }
+            Ok(text) => self.paste_text(&text),
             Err(e) => {
                 log::warn!("Failed to get clipboard text: {}", e);
             }
         }
     }
 
-    /// Handle find (Ctrl+Shift+F)
+    /// Send text to the current tab as a paste, asking first if it is large
+    /// or would run commands in a shell without bracketed paste
+    fn paste_text(&mut self, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        let Some(tab) = self.tabs.get(self.active_tab) else {
+            return;
+        };
+        let config = &self.config.paste;
+        let lines = text.lines().count();
+        let question = if config.confirm_above_kb > 0 && text.len() > config.confirm_above_kb * 1024
+        {
+            Some(format!(
+                "Paste {} KB ({} lines)? (y/n)",
+                text.len().div_ceil(1024),
+                lines
+            ))
+        } else if config.confirm_multiline
+            && text.contains(['\n', '\r'])
+            && !tab.terminal.screen().modes().bracketed_paste
+            && tab.busy_command().is_none()
+        {
+            Some(match lines {
+                1 => String::from("Paste 1 line into the shell? It runs as a command (y/n)"),
+                n => format!(
+                    "Paste {} lines into the shell? They run as commands (y/n)",
+                    n
+                ),
+            })
+        } else {
+            None
+        };
+        if let Some(question) = question {
+            self.paste_confirm = Some(PasteRequest {
+                text: text.to_string(),
+                question,
+            });
+            self.needs_redraw = true;
+            return;
+        }
+        self.send_paste(text);
+    }
+
+    /// Write a paste to the current tab's PTY
+    fn send_paste(&mut self, text: &str) {
+        let Some(tab) = self.tabs.get_mut(self.active_tab) else {
+            return;
+        };
+        let text = sanitize_paste(text, self.config.paste.control_characters);
+        let data = if tab.terminal.screen().modes().bracketed_paste {
+            encode_bracketed_paste(&text)
+        } else {
+            text.into_bytes()
+        };
+        if let Err(e) = tab.child.write_all(&data) {
+            log::warn!("Failed to write paste data to PTY: {}", e);
+        } else {
+            log::debug!("Pasted {} bytes", data.len());
+        }
+    }
+
+    /// Paste the shell-quoted path of a file dropped onto the window
     ///
-    /// Search UI is planned for a future release.
+    /// Several dropped files arrive one by one, so each path is followed by
+    /// a space to keep them apart on the command line.
+    fn handle_dropped_file(&mut self, path: &Path) {
+        let Some(text) = path.to_str() else {
+            log::warn!("Ignoring dropped file with a non-UTF-8 path: {:?}", path);
+            return;
+        };
+        let quoted = shell_quote(text);
+        let text = if self.config.drop_directory_cd && path.is_dir() {
+            format!("cd {} ", quoted)
+        } else {
+            format!("{} ", quoted)
+        };
+        self.paste_text(&text);
+    }
+
+    /// Handle find (Ctrl+Shift+F): open the search bar
     fn handle_find(&mut self) {
-        log::info!("Find requested (Ctrl+Shift+F) - search UI not yet implemented");
+        let Some(tab) = self.tabs.get_mut(self.active_tab) else {
+            return;
+        };
+        tab.search = Some(Search::new());
+        self.needs_redraw = true;
     }
 
-    /// Handle new window (Cmd+N on macOS)
+    /// Handle a key while the search bar is open
     ///
-    /// Spawns a new instance of the Mochi terminal.
-    #[cfg(target_os = "macos")]
-    fn handle_new_window(&mut self) {
-        log::info!("Opening new terminal window...");
-
-        // Get the path to the current executable
-        if let Ok(exe_path) = std::env::current_exe() {
-            match std::process::Command::new(&exe_path).spawn() {
-                Ok(child) => {
-                    log::info!("New terminal window spawned successfully");
-                    // Spawn a thread to wait on the child process to prevent zombie processes.
-                    // When the child exits, this thread will reap it by calling wait().
-                    std::thread::spawn(move || {
-                        let mut child = child;
-                        let _ = child.wait();
-                    });
-                }
-                Err(e) => {
-                    log::error!("Failed to spawn new terminal window: {}", e);
+    /// Enter and Up focus the previous (older) match, Shift+Enter and Down
+    /// the next one; Escape closes the bar and leaves the view where it is.
+    fn handle_search_key(&mut self, event: &winit::event::KeyEvent) {
+        let shift = self.modifiers.shift_key();
+        let ctrl = self.modifiers.control_key();
+        let tab = &mut self.tabs[self.active_tab];
+        let Some(search) = &mut tab.search else {
+            return;
+        };
+        let screen = tab.terminal.screen();
+        match &event.logical_key {
+            Key::Named(NamedKey::Escape) => {
+                tab.search = None;
+                self.needs_redraw = true;
+                return;
+            }
+            Key::Named(NamedKey::Enter) => search.step(!shift),
+            Key::Named(NamedKey::ArrowUp) => search.step(true),
+            Key::Named(NamedKey::ArrowDown) => search.step(false),
+            Key::Named(NamedKey::Backspace) => search.pop(screen),
+            Key::Named(NamedKey::Space) => search.push(' ', screen),
+            Key::Character(c) if !ctrl => {
+                for ch in c.chars() {
+                    search.push(ch, screen);
                 }
             }
-        } else {
-            log::error!("Failed to get current executable path");
+            _ => return,
+        }
+        if let Some(focused) = search.focused() {
+            tab.scroll_row_into_view(focused.row);
         }
+        self.needs_redraw = true;
+    }
+
+    /// Handle new window (Cmd+N on macOS, Ctrl+Shift+N elsewhere)
+    fn handle_new_window(&mut self) {
+        self.request = Some(AppRequest::NewWindow);
     }
 
     /// Handle reload config (Ctrl+Shift+R)
+    ///
+    /// The application reloads the file once and applies it to every window.
     fn handle_reload_config(&mut self) {
-        log::info!("Reloading configuration...");
+        self.request = Some(AppRequest::ReloadConfig);
+    }
 
-        match Config::load() {
-            Some(new_config) => {
-                // Update theme
-                self.config.theme = new_config.theme;
-                self.config.font = new_config.font.clone();
-                self.config.keybindings = new_config.keybindings.clone();
-                self.config.security = new_config.security.clone();
-
-                // Apply theme change
-                if let Some(renderer) = &mut self.renderer {
-                    renderer.set_colors(self.config.effective_colors());
-                }
+    /// Apply a reloaded configuration to this window
+    fn apply_config(&mut self, new_config: &Config) {
+        let font_size_changed = self.config.font.size != new_config.font.size;
+        let old_layout = GridLayout::from_config(&self.config);
+        self.config.update_from(new_config);
+        let layout = GridLayout::from_config(&self.config);
 
-                log::info!("Configuration reloaded successfully");
-                self.needs_redraw = true;
+        self.clipboard_history.borrow_mut().set_limits(
+            self.config.clipboard_history.entries,
+            self.config.clipboard_history.max_kb * 1024,
+        );
+        self.hooks = Self::compile_hooks(&self.config.hooks);
+        self.tab_accents = Self::compile_tab_accents(&self.config.tab_accents);
+        self.status_bar = Self::compile_status_bar(&self.config);
+        self.openers = Self::compile_openers(&self.config.openers);
+        self.keybindings = Self::compile_keybindings(&self.config.keybindings);
+        let capture_lines = hooks::watches_output(&self.config.hooks);
+        let highlight_rules = Self::compile_highlights(&self.config.highlights);
+        for tab in &mut self.tabs {
+            tab.terminal
+                .set_scrollback_limit(self.config.scrollback_lines);
+            tab.terminal
+                .set_capture_alternate_screen(self.config.capture_alternate_screen);
+            tab.terminal.set_capture_lines(capture_lines);
+            tab.terminal.set_highlight_rules(highlight_rules.clone());
+            tab.terminal
+                .set_osc_policies(self.config.osc_policies(), self.config.osc52_max_size());
+            tab.terminal
+                .set_answer_queries(self.config.security.answer_queries);
+            tab.terminal
+                .set_device_attributes(&self.config.security.device_attributes);
+            tab.terminal
+                .set_answerback(&self.config.security.answerback);
+            tab.terminal
+                .set_identify_as_xterm(self.config.identify_as_xterm);
+            tab.terminal
+                .set_tmux_passthrough(self.config.security.tmux_passthrough);
+        }
+        // Keep the tabs' zoom unless the configured size itself changed
+        if font_size_changed {
+            for tab in &mut self.tabs {
+                tab.font_scale = 1.0;
             }
-            None => {
-                log::warn!("No config file found or failed to parse");
+            self.fit_font_to_active_tab();
+            self.resize_tabs();
+        }
+        if layout != old_layout {
+            if let Some(renderer) = &mut self.renderer {
+                renderer.set_layout(layout);
             }
+            self.resize_tabs();
+        }
+
+        // Apply theme change (read again by the next frame)
+        self.theme_colors = None;
+        if let Some(renderer) = &mut self.renderer {
+            renderer.set_cursor_animation(&self.config.cursor_animation);
+            renderer.set_minimum_contrast(self.config.minimum_contrast);
+            renderer.set_builtin_box_drawing(self.config.font.builtin_box_drawing);
         }
+        self.update_frame_timing();
+        self.needs_redraw = true;
     }
 
-    /// Handle toggle theme (Ctrl+Shift+T on macOS)
-    #[allow(dead_code)]
+    /// Show a short message in the corner for `ttl`
+    fn show_toast(&mut self, text: String, ttl: Duration) {
+        self.toasts.push(text, ttl);
+        self.needs_redraw = true;
+    }
+
+    /// Handle toggle theme (the toggle_theme keybinding)
     fn handle_toggle_theme(&mut self) {
         let new_theme = self.config.theme.next();
         log::info!(
@@ -1079,10 +3841,7 @@ impl App {
         );
 
         self.config.theme = new_theme;
-
-        if let Some(renderer) = &mut self.renderer {
-            renderer.set_colors(self.config.effective_colors());
-        }
+        self.theme_colors = None;
 
         self.needs_redraw = true;
     }
@@ -1090,6 +3849,9 @@ impl App {
     /// Handle focus change
     fn handle_focus(&mut self, focused: bool) {
         self.focused = focused;
+        if self.config.dim_unfocused > 0.0 {
+            self.needs_redraw = true;
+        }
 
         if self.tabs.is_empty() {
             return;
@@ -1104,111 +3866,698 @@ impl App {
 
     /// Poll PTY for output from all tabs
     fn poll_pty(&mut self) {
-        let mut buf = [0u8; 65536];
-
-        // Poll all tabs for output
+        let flow = FlowControl::new(&self.config.flow_control);
+        let mut buf = std::mem::take(&mut self.read_buf);
+        let mut hook_actions = Vec::new();
+        let mut clipboard_writes = Vec::new();
+        let mut clipboard_reads = Vec::new();
+        let mut monitors_fired = Vec::new();
+        let now = Instant::now();
+        let mut trace = self.trace.borrow_mut();
+        let mut trace_error = None;
+
+        // Poll all tabs for output, reading a bounded amount from each
         for (i, tab) in self.tabs.iter_mut().enumerate() {
-            let mut received_output = false;
-
-            loop {
-                match tab.child.pty_mut().try_read(&mut buf) {
-                    Ok(0) => break,
-                    Ok(n) => {
-                        tab.terminal.process(&buf[..n]);
-                        received_output = true;
-                        // Only trigger redraw if synchronized output mode is disabled
-                        // and this is the active tab
-                        if i == self.active_tab && !tab.terminal.is_synchronized_output() {
-                            self.needs_redraw = true;
-                        }
+            let pushed = tab.terminal.screen().scrollback().pushed();
+            let pty = tab.child.pty_mut();
+            let received_output = flow.read_pass(&mut buf, |out| pty.try_read(out)) > 0;
+
+            if received_output {
+                let tracing = trace.is_active() && trace_error.is_none();
+                tab.terminal.set_trace(tracing);
+                tab.terminal.process(&buf);
+                if tracing {
+                    let events = tab.terminal.take_trace();
+                    if let Err(e) = trace.record(tab.child.pid().as_raw(), &buf, &events) {
+                        trace_error = Some(e);
+                    }
+                }
+                tab.foreground_stale = true;
+                // The real echo replaces what it predicted; the first output
+                // after a key is drawn without waiting for the frame deadline
+                tab.prediction.output(tab.terminal.screen(), now);
+                tab.latency.output(now);
+                let watched = i == self.active_tab && self.focused;
+                if let Some(monitor) = &mut tab.monitor {
+                    if monitor.output(now, watched) {
+                        monitors_fired.push((i, MonitorKind::Activity));
+                    }
+                }
+                // Only trigger redraw if synchronized output mode is disabled
+                // and this is the active tab
+                if i == self.active_tab && !tab.terminal.is_synchronized_output() {
+                    // While a flood is pending, skip frames to catch up with the latest output
+                    let pending = tab.child.pty().bytes_available().unwrap_or(0);
+                    if !flow.should_skip_frames(pending)
+                        || self.last_render.elapsed() >= SKIP_FRAME_INTERVAL
+                    {
+                        self.needs_redraw = true;
+                    }
+                }
+            }
+
+            // Wait for more output, or stop watching once the shell has exited
+            if tab.exited {
+                tab.watcher = None;
+            } else if let Some(watcher) = &tab.watcher {
+                watcher.rearm();
+            } else {
+                let proxy = self.proxy.clone();
+                match PtyWatcher::spawn(tab.child.pty(), move || {
+                    let _ = proxy.send_event(TerminalEvent::PtyReadable);
+                }) {
+                    Ok(watcher) => tab.watcher = Some(watcher),
+                    Err(e) => log::warn!("Failed to watch PTY, polling instead: {}", e),
+                }
+            }
+
+            // New output keeps a scrolled-up view on the same lines, counting
+            // those that arrive below it for the "new lines" pill
+            // (copy mode and the search bar leave the offset to themselves)
+            if let Some(search) = tab.search.as_mut().filter(|_| received_output) {
+                search.mark_stale(Instant::now());
+            }
+            if tab.scroll_offset == 0 {
+                tab.unseen_lines = 0;
+            } else if received_output && tab.copy_mode.is_none() && tab.search.is_none() {
+                let added = (tab.terminal.screen().scrollback().pushed() - pushed) as usize;
+                if added > 0 {
+                    tab.scroll_to(tab.scroll_offset + added);
+                    tab.unseen_lines += added;
+                    if i == self.active_tab {
+                        self.needs_redraw = true;
                     }
-                    Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
-                    Err(_) => break,
                 }
             }
 
-            // Reset scroll offset when new output arrives (auto-scroll to bottom)
-            if received_output && tab.scroll_offset > 0 {
-                tab.scroll_offset = 0;
+            // The foreground process names the tab until the shell sets a title
+            let foreground_changed = tab.refresh_foreground();
+            if foreground_changed && tab.title.is_empty() {
+                if i == self.active_tab {
+                    if let Some(window) = &self.window {
+                        window.set_title(tab.window_title());
+                    }
+                }
+                self.needs_redraw = true;
+            }
+            // Point out the remote shell integration installer, once per tab
+            if foreground_changed
+                && self.config.ssh.offer_integration
+                && !tab.ssh_offered
+                && tab.running_ssh()
+            {
+                tab.ssh_offered = true;
+                self.toasts.push(
+                    format!(
+                        "Tab {}: ssh - Ctrl+Shift+U types a command that installs shell integration on the host",
+                        i + 1
+                    ),
+                    toast::NOTIFICATION_TTL,
+                );
+                self.needs_redraw = true;
             }
 
             // Check for title change (only update window title for active tab)
             if tab.terminal.take_title_changed() {
-                tab.title = tab.terminal.title().to_string();
+                tab.title = tab.terminal.tab_title().to_string();
+                let event = hooks::Event::TitleChanged(&tab.title);
+                hook_actions.extend(self.hooks.dispatch(event, i, now));
                 if i == self.active_tab {
                     if let Some(window) = &self.window {
-                        window.set_title(&tab.title);
+                        window.set_title(tab.window_title());
                     }
                 }
+                self.needs_redraw = true;
             }
 
-            // Check for bell
+            // Check for bell; a bell in a background tab is worth pointing out
             if tab.terminal.take_bell() {
                 log::debug!("Bell!");
+                hook_actions.extend(self.hooks.dispatch(hooks::Event::Bell, i, now));
+                if i != self.active_tab {
+                    self.toasts.push(
+                        format!("Bell in tab {}: {}", i + 1, tab.display_title()),
+                        toast::DEFAULT_TTL,
+                    );
+                    self.needs_redraw = true;
+                }
+            }
+
+            for line in tab.terminal.take_captured_lines() {
+                let event = hooks::Event::Output(&line);
+                hook_actions.extend(self.hooks.dispatch(event, i, now));
+            }
+
+            // Show notifications sent with OSC 9 / OSC 777
+            for notification in tab.terminal.take_notifications() {
+                let text = if i == self.active_tab {
+                    notification.text()
+                } else {
+                    format!("Tab {}: {}", i + 1, notification.text())
+                };
+                self.toasts.push(text, toast::NOTIFICATION_TTL);
+                self.needs_redraw = true;
+            }
+
+            // Clipboard writes and reads (OSC 52), when [security.osc] allows them
+            for write in tab.terminal.take_clipboard_writes() {
+                clipboard_writes.push((i, write));
+            }
+            for read in tab.terminal.take_clipboard_reads() {
+                clipboard_reads.push((i, read));
+            }
+
+            // A background tab's question waits until the tab is shown
+            if tab.terminal.take_osc_prompt_raised() {
+                if let Some(feature) = tab.terminal.osc_prompt().filter(|_| i != self.active_tab) {
+                    self.toasts.push(
+                        format!(
+                            "Tab {} asks to {}; switch to it to answer",
+                            i + 1,
+                            feature.description()
+                        ),
+                        toast::NOTIFICATION_TTL,
+                    );
+                }
+                self.needs_redraw = true;
+            }
+
+            // Graphics that cannot be drawn leave a placeholder and a notice
+            if let Some(kind) = tab.terminal.take_unsupported_graphics() {
+                self.toasts.push(
+                    format!("Tab {}: {} graphics are not supported", i + 1, kind.name()),
+                    toast::DEFAULT_TTL,
+                );
+                self.needs_redraw = true;
             }
 
-            // Send any pending responses back to the PTY (DSR, DA1, etc.)
-            let responses = tab.terminal.take_pending_responses();
-            for response in responses {
-                if let Err(e) = tab.child.write_all(&response) {
+            // Send any pending responses back to the PTY (DSR, DA1, etc.),
+            // in one write
+            let responses = tab.terminal.take_pending_responses().concat();
+            if !responses.is_empty() {
+                if let Err(e) = tab.child.write_all(&responses) {
                     log::warn!("Failed to send response to PTY: {}", e);
                 }
             }
         }
+        if let Some(e) = trace_error {
+            let _ = trace.stop();
+            let message = format!("Tracing stopped: {}: {}", trace.path().display(), e);
+            log::warn!("{}", message);
+            self.toasts.push(message, toast::DEFAULT_TTL);
+            self.needs_redraw = true;
+        }
+        drop(trace);
+        self.run_hook_actions(hook_actions);
+        for (i, kind) in monitors_fired {
+            self.monitor_fired(i, kind, now);
+        }
+        for (tab, write) in clipboard_writes {
+            self.apply_clipboard_write(tab, write);
+        }
+        for (tab, read) in clipboard_reads {
+            self.answer_clipboard_read(tab, read);
+        }
+    }
+
+    /// Send the clipboard's text to a program that asked with OSC 52
+    fn answer_clipboard_read(&mut self, tab: usize, read: ClipboardRead) {
+        let text = if read.primary {
+            self.primary_selection()
+        } else {
+            self.clipboard.as_mut().and_then(|clipboard| {
+                clipboard
+                    .get_text()
+                    .map_err(|e| log::debug!("Failed to get the clipboard for OSC 52: {}", e))
+                    .ok()
+            })
+        };
+        let reply = read.reply(&text.unwrap_or_default());
+        if reply.len() > self.config.osc52_max_size() {
+            log::debug!("OSC 52 clipboard read too large ({} bytes)", reply.len());
+            return;
+        }
+        if let Some(tab) = self.tabs.get_mut(tab) {
+            if let Err(e) = tab.child.write_all(&reply) {
+                log::warn!("Failed to send the clipboard to PTY: {}", e);
+            }
+        }
+    }
+
+    /// Put text a program sent with OSC 52 on the clipboard
+    fn apply_clipboard_write(&mut self, tab: usize, write: ClipboardWrite) {
+        if write.primary {
+            self.set_primary_selection(&write.text);
+        } else if let Some(clipboard) = &mut self.clipboard {
+            if let Err(e) = clipboard.set_text(&write.text) {
+                log::warn!("Failed to set the clipboard for OSC 52: {}", e);
+                return;
+            }
+        }
+        if self.config.security.osc52_notify {
+            let target = if write.primary {
+                "selection"
+            } else {
+                "clipboard"
+            };
+            self.show_toast(
+                format!(
+                    "Tab {} set the {} ({} bytes)",
+                    tab + 1,
+                    target,
+                    write.text.len()
+                ),
+                toast::DEFAULT_TTL,
+            );
+        }
     }
 
     /// Render the terminal
     fn render(&mut self) {
-        let Some(renderer) = &mut self.renderer else {
+        if self.tabs.is_empty() {
             return;
+        }
+        // Another tab may have become active since the last frame
+        self.fit_font_to_active_tab();
+        self.fit_colors_to_active_tab();
+
+        let active = &self.tabs[self.active_tab];
+        let copy_status = active.copy_mode.as_ref().map(CopyMode::status);
+        let search_status = active.search.as_ref().map(Search::status);
+        let osc_prompt = active.terminal.osc_prompt();
+        let overlay_text = match (self.close_confirm, &self.command_prompt) {
+            (Some(request), _) => Some(self.close_confirm_text(request)),
+            (None, _) if self.paste_confirm.is_some() => {
+                self.paste_confirm.as_ref().map(|p| p.question.clone())
+            }
+            (None, _) if osc_prompt.is_some() => osc_prompt
+                .map(|feature| format!("Allow this tab to {}? (y/n)", feature.description())),
+            (None, Some(line)) => Some(format!("Run: {}_", line)),
+            (None, None) if self.save_prompt.is_some() => {
+                self.save_prompt
+                    .as_ref()
+                    .map(|(target, name)| match target {
+                        SaveTarget::Dump(range) => format!(
+                            "Save the {} as (.txt, .html, .ans): {}_",
+                            range.name(),
+                            name
+                        ),
+                        SaveTarget::Screenshot => format!("Save a screenshot as: {}_", name),
+                    })
+            }
+            (None, None) if search_status.is_some() => search_status,
+            (None, None) if copy_status.is_some() => copy_status,
+            (None, None) if self.show_process_info => Some(process_summary(
+                self.tabs[self.active_tab].foreground.as_ref(),
+            )),
+            (None, None) => None,
         };
 
-        if self.tabs.is_empty() {
-            return;
+        let number = |i: usize| {
+            if i < 9 {
+                format!("{}", i + 1)
+            } else {
+                " ".into()
+            }
+        };
+        if self.inspector.is_some() {
+            // Record the tab shown, also after switching tabs
+            if let Some(tab) = self.tabs.get_mut(self.active_tab) {
+                tab.terminal.set_action_log(true);
+            }
         }
+        let picker_items: Vec<String> = match (self.clipboard_picker, self.profile_picker) {
+            (Some(_), _) => self
+                .clipboard_history
+                .borrow()
+                .iter()
+                .enumerate()
+                .map(|(i, text)| format!("{} {}", number(i), clipboard_history::preview(text, 60)))
+                .collect(),
+            (None, Some(_)) => self
+                .config
+                .profiles
+                .keys()
+                .enumerate()
+                .map(|(i, name)| format!("{} {}", number(i), name))
+                .collect(),
+            (None, None) if self.inspector.is_some() => self.inspector_lines(),
+            (None, None) => Vec::new(),
+        };
+        let picker = match (self.clipboard_picker, self.profile_picker) {
+            (Some(picker), _) => Some(PickerOverlay {
+                title: "Clipboard history - Enter pastes, Esc closes",
+                items: &picker_items,
+                selected: picker.selected,
+            }),
+            (None, Some(picker)) => Some(PickerOverlay {
+                title: "New tab with profile - Enter opens, Esc closes",
+                items: &picker_items,
+                selected: picker.selected,
+            }),
+            (None, None) => self.inspector.map(|picker| PickerOverlay {
+                title: "Inspector - arrows scroll, Esc closes",
+                items: &picker_items,
+                selected: picker.selected.min(picker_items.len().saturating_sub(1)),
+            }),
+        };
+        let tab_width = self.tab_width();
+        let tab_labels: Vec<Cow<'_, str>> = self.tabs.iter().map(Tab::tab_label).collect();
+
+        let Some(renderer) = &mut self.renderer else {
+            return;
+        };
+        renderer.set_dim(if self.focused {
+            0.0
+        } else {
+            self.config.dim_unfocused
+        });
 
         let tab_infos: Vec<TabInfo<'_>> = self
             .tabs
             .iter()
-            .map(|t| TabInfo { title: &t.title })
+            .enumerate()
+            .map(|(i, t)| {
+                let accent = self
+                    .tab_accents
+                    .for_tab(t.display_title(), t.terminal.tab_accent());
+                let badge = t.monitor.as_ref().map(|monitor| {
+                    if monitor.fired() {
+                        TabBadge::Fired
+                    } else {
+                        TabBadge::Watching
+                    }
+                });
+                match &self.tab_rename {
+                    Some(name) if i == self.active_tab => TabInfo {
+                        title: name,
+                        editing: true,
+                        accent,
+                        badge,
+                        progress: t.terminal.progress(),
+                    },
+                    _ => TabInfo {
+                        title: &tab_labels[i],
+                        editing: false,
+                        accent,
+                        badge,
+                        progress: t.terminal.progress(),
+                    },
+                }
+            })
             .collect();
         let tab = &self.tabs[self.active_tab];
         let screen = tab.terminal.screen();
-        let selection = screen.selection();
+
+        let overlay = overlay_text.as_deref().map(|text| StatusOverlay {
+            text,
+            dialog: self.close_confirm.is_some()
+                || self.paste_confirm.is_some()
+                || osc_prompt.is_some(),
+        });
+        let toasts: Vec<&str> = self.toasts.texts().collect();
+        // Copy mode cursor in view coordinates
+        let copy_cursor = tab.copy_mode.as_ref().and_then(|mode| {
+            let cursor = mode.cursor();
+            let row = cursor.row + tab.scroll_offset as isize;
+            (0..screen.rows() as isize)
+                .contains(&row)
+                .then_some((cursor.col, row as usize))
+        });
+        // Hint labels, without the part already typed
+        let hint_labels: Vec<HintLabel<'_>> = tab
+            .hint_mode
+            .iter()
+            .flat_map(|mode| {
+                mode.visible().map(|hint| HintLabel {
+                    col: hint.col,
+                    row: hint.row,
+                    text: &hint.label[mode.typed_len()..],
+                })
+            })
+            .collect();
+        let pill = tab.new_output_pill();
+        let predicted = tab.prediction.shown().filter(|_| tab.scroll_offset == 0);
+        let prediction = predicted.as_ref().map(|predicted| PredictedEcho {
+            col: predicted.col,
+            row: predicted.row,
+            text: &predicted.text,
+            cursor: predicted.cursor,
+        });
+        let hovered_link = self
+            .hovered_link
+            .and_then(|id| screen.get_hyperlink(id).map(|url| HoveredLink { id, url }));
+        // The user variables of the tab under the mouse, below its header
+        let tab_tooltip = self.hovered_tab.and_then(|i| {
+            let vars = self.tabs.get(i)?.terminal.user_vars();
+            let text = vars
+                .iter()
+                .map(|(name, value)| format!("{}={}", name, value))
+                .collect::<Vec<_>>()
+                .join("  ");
+            let x = i as u32 * tab_width?;
+            (!text.is_empty()).then_some((text, x as i32))
+        });
+        let search_highlights: Vec<SearchHighlight> = tab
+            .search
+            .iter()
+            .flat_map(|search| search.visible(screen.rows(), screen.cols(), tab.scroll_offset))
+            .map(|(row, start, end, focused)| SearchHighlight {
+                row,
+                start,
+                end,
+                focused,
+            })
+            .collect();
 
         if let Err(e) = renderer.render(
             screen,
-            selection,
             tab.scroll_offset,
             self.tab_bar_height,
             &tab_infos,
             self.active_tab,
+            overlay.as_ref(),
+            picker.as_ref(),
+            &toasts,
+            copy_cursor,
+            &hint_labels,
+            &search_highlights,
+            self.status_bar.as_ref().map(StatusBar::text),
+            hovered_link.as_ref(),
+            tab_tooltip.as_ref().map(|(text, x)| (text.as_str(), *x)),
+            pill.as_deref(),
+            prediction.as_ref(),
         ) {
             log::warn!("Render error: {:?}", e);
         }
 
         self.needs_redraw = false;
         self.last_render = Instant::now();
+        self.tabs[self.active_tab]
+            .latency
+            .rendered(self.last_render);
     }
 
-    /// Check if active tab's child is still running
-    fn check_child(&mut self) -> bool {
-        if self.tabs.is_empty() {
-            return false;
+    /// How long the event loop may sleep before the next AboutToWait
+    ///
+    /// PTY watchers wake the loop when output arrives, so it only needs a
+    /// deadline for the next frame (while animating or when changes were
+    /// held back by frame pacing), for one last foreground refresh after
+    /// output stops, and for tabs that could not be watched.
+    fn control_flow(&self) -> ControlFlow {
+        if self
+            .tabs
+            .iter()
+            .any(|tab| !tab.exited && tab.watcher.is_none())
+        {
+            return ControlFlow::wait_duration(UNWATCHED_POLL_INTERVAL);
         }
 
-        // Check if active tab's child is running
-        let active_running = self.tabs[self.active_tab].child.is_running();
+        let animating = self
+            .renderer
+            .as_ref()
+            .is_some_and(|r| r.needs_animation_frame());
+        let frame =
+            (animating || self.needs_redraw).then(|| self.timing.next_frame(self.last_render));
+        let foreground = self
+            .tabs
+            .iter()
+            .filter(|tab| tab.foreground_stale && !tab.exited)
+            .filter_map(|tab| tab.foreground_checked)
+            .map(|checked| checked + FOREGROUND_POLL_INTERVAL)
+            .min();
+
+        let toast = self.toasts.next_expiry();
+        let status = self.status_bar.as_ref().and_then(StatusBar::next_refresh);
+        let silence = Duration::from_secs(self.config.monitor.silence_seconds);
+        let monitor = self
+            .tabs
+            .iter()
+            .filter(|tab| !tab.exited)
+            .filter_map(|tab| tab.monitor.as_ref()?.deadline(silence))
+            .min();
+        let search = self
+            .tabs
+            .get(self.active_tab)
+            .and_then(|tab| tab.search.as_ref())
+            .and_then(Search::refresh_deadline);
+        let prediction = self
+            .tabs
+            .get(self.active_tab)
+            .and_then(|tab| tab.prediction.deadline());
+
+        match frame
+            .into_iter()
+            .chain(foreground)
+            .chain(toast)
+            .chain(status)
+            .chain(monitor)
+            .chain(search)
+            .chain(prediction)
+            .min()
+        {
+            Some(deadline) => ControlFlow::WaitUntil(deadline),
+            None => ControlFlow::Wait,
+        }
+    }
 
-        // Remove any tabs whose children have exited
-        self.tabs.retain(|tab| tab.child.is_running());
+    /// Check if an animation frame should be rendered now
+    fn animation_frame_due(&self) -> bool {
+        let animating = self
+            .renderer
+            .as_ref()
+            .is_some_and(|r| r.needs_animation_frame());
+        animating && Instant::now() >= self.timing.next_frame(self.last_render)
+    }
 
-        // Adjust active tab index if needed
-        if self.active_tab >= self.tabs.len() {
-            self.active_tab = self.tabs.len().saturating_sub(1);
+    /// Handle tabs whose shell has exited, per `exit_behavior`
+    ///
+    /// Returns false once no tabs are left.
+    fn check_child(&mut self) -> bool {
+        let mut index = 0;
+        while index < self.tabs.len() {
+            let tab = &self.tabs[index];
+            if tab.exited {
+                index += 1;
+                continue;
+            }
+            let status = match tab.child.try_wait() {
+                Ok(None) => {
+                    index += 1;
+                    continue;
+                }
+                Ok(Some(status)) => ExitStatus::from_wait_status(status),
+                Err(_) => None,
+            };
+            let uptime = tab.started.elapsed();
+            let status = status.map_or_else(|| String::from("exited"), |s| s.to_string());
+            log::info!("Shell in tab {} {}", index + 1, status);
+            let actions = self
+                .hooks
+                .dispatch(hooks::Event::Exited(&status), index, Instant::now());
+            self.run_hook_actions(actions);
+
+            // Command tabs stay open so the result can be read and the command re-run
+            if let Some(command) = &self.tabs[index].command {
+                let message = format!(
+                    "{} {}. Press R to run it again or Enter to close.",
+                    command.line, status
+                );
+                self.tabs[index].exited = true;
+                self.print_to_tab(index, &message);
+                index += 1;
+                continue;
+            }
+
+            match self.config.exit_behavior {
+                ExitBehavior::Close => {
+                    self.close_tab(index);
+                    continue;
+                }
+                ExitBehavior::Restart if uptime >= MIN_RESTART_UPTIME => {
+                    self.print_to_tab(index, &format!("Process {}. Restarting.", status));
+                    self.restart_tab(index);
+                }
+                ExitBehavior::Hold | ExitBehavior::Restart => {
+                    self.tabs[index].exited = true;
+                    self.print_to_tab(
+                        index,
+                        &format!("Process {}. Press Enter to close or R to restart.", status),
+                    );
+                }
+            }
+            index += 1;
+        }
+        !self.tabs.is_empty()
+    }
+
+    /// Print a bracketed notice on its own line in a tab
+    fn print_to_tab(&mut self, index: usize, message: &str) {
+        if let Some(tab) = self.tabs.get_mut(index) {
+            tab.terminal
+                .process(format!("\x1b[0m\r\n[{}]\r\n", message).as_bytes());
+            self.needs_redraw = true;
+        }
+    }
+
+    /// Start the shell (or command) again in a tab whose shell has exited
+    fn restart_tab(&mut self, index: usize) {
+        let Some(tab) = self.tabs.get_mut(index) else {
+            return;
+        };
+        // The old shell is gone, so use the directory it was last seen in
+        let cwd = tab
+            .foreground
+            .as_ref()
+            .and_then(|process| process.cwd.clone())
+            .or_else(|| tab.cwd());
+        let screen = tab.terminal.screen();
+        match Self::spawn_child(
+            screen.cols(),
+            screen.rows(),
+            &self.config,
+            self.reaper.as_ref(),
+            tab.profile.as_deref(),
+            tab.startup.as_ref(),
+            cwd.as_deref(),
+            tab.command.as_ref(),
+        ) {
+            Ok((child, scope)) => {
+                tab.child = child;
+                tab.scope = scope;
+                tab.usage = None;
+                tab.foreground_priority = None;
+                tab.watcher = None;
+                tab.started = Instant::now();
+                tab.exited = false;
+                tab.foreground = None;
+                tab.foreground_checked = None;
+                log::info!("Restarted shell in tab {}", index + 1);
+            }
+            Err(e) => {
+                log::error!("Failed to restart shell: {}", e);
+                tab.exited = true;
+                self.print_to_tab(index, &format!("Failed to restart the shell: {}", e));
+            }
         }
+        self.needs_redraw = true;
+    }
+}
 
-        // Return true if there are still tabs with running children
-        !self.tabs.is_empty() && (active_running || self.tabs[self.active_tab].child.is_running())
+/// One-line description of a foreground process for the status overlay
+fn process_summary(process: Option<&ProcessInfo>) -> String {
+    let Some(process) = process else {
+        return String::from("No foreground process");
+    };
+    match &process.cwd {
+        Some(cwd) => {
+            let cwd = match dirs::home_dir().and_then(|home| cwd.strip_prefix(home).ok()) {
+                Some(rest) if rest.as_os_str().is_empty() => PathBuf::from("~"),
+                Some(rest) => Path::new("~").join(rest),
+                None => cwd.clone(),
+            };
+            format!("{} ({}) in {}", process.name, process.pid, cwd.display())
+        }
+        None => format!("{} ({})", process.name, process.pid),
     }
 }
@@ -0,0 +1,477 @@
+//! Box drawing, block element and powerline glyphs drawn at cell size
+//!
+//! Fonts rarely draw these to the exact cell: the primary font's lines stop
+//! short of the padding and line height, and a fallback font has an advance
+//! of its own, so TUI borders show gaps and statusline arrows overlap or
+//! miss their neighbours. With `font.builtin_box_drawing` the renderer asks
+//! `synthesize` first, which draws lines (U+2500..U+257F), blocks and shades
+//! (U+2580..U+259F) and the powerline separators (U+E0B0..U+E0B7) into a
+//! bitmap exactly one cell in size, so they connect across cells whatever
+//! the font.
+
+/// Arm weights
+const NONE: u8 = 0;
+const LIGHT: u8 = 1;
+const HEAVY: u8 = 2;
+const DOUBLE: u8 = 3;
+
+/// Arms of U+2500..=U+257F as up, right, down and left weights; empty for
+/// the dashed, rounded and diagonal lines, which are drawn separately
+#[rustfmt::skip]
+const LINES: [&str; 128] = [
+    "0101", "0202", "1010", "2020", "", "", "", "",
+    "", "", "", "", "0110", "0210", "0120", "0220",
+    "0011", "0012", "0021", "0022", "1100", "1200", "2100", "2200",
+    "1001", "1002", "2001", "2002", "1110", "1210", "2110", "1120",
+    "2120", "2210", "1220", "2220", "1011", "1012", "2011", "1021",
+    "2021", "2012", "1022", "2022", "0111", "0112", "0211", "0212",
+    "0121", "0122", "0221", "0222", "1101", "1102", "1201", "1202",
+    "2101", "2102", "2201", "2202", "1111", "1112", "1211", "1212",
+    "2111", "1121", "2121", "2112", "2211", "1122", "1221", "2212",
+    "1222", "2122", "2221", "2222", "", "", "", "",
+    "0303", "3030", "0310", "0130", "0330", "0013", "0031", "0033",
+    "1300", "3100", "3300", "1003", "3001", "3003", "1310", "3130",
+    "3330", "1013", "3031", "3033", "0313", "0131", "0333", "1303",
+    "3101", "3303", "1313", "3131", "3333", "", "", "",
+    "", "", "", "", "0001", "1000", "0100", "0010",
+    "0002", "2000", "0200", "0020", "0201", "1020", "0102", "2010",
+];
+
+/// Check if `synthesize` draws a character
+pub fn is_builtin(c: char) -> bool {
+    matches!(c, '\u{2500}'..='\u{259f}' | '\u{e0b0}'..='\u{e0b7}')
+}
+
+/// Draw a character as an alpha bitmap of `width` x `height` (one cell),
+/// or None if it is not one of the built-in glyphs
+pub fn synthesize(c: char, width: usize, height: usize) -> Option<Vec<u8>> {
+    if !is_builtin(c) || width == 0 || height == 0 {
+        return None;
+    }
+    let mut canvas = Canvas::new(width, height);
+    match c as u32 {
+        code @ 0x2500..=0x257f => canvas.line(c, LINES[(code - 0x2500) as usize]),
+        code @ 0x2580..=0x259f => canvas.block(code),
+        code => canvas.powerline(code),
+    }
+    Some(canvas.alpha)
+}
+
+/// A pixel between `start` and `end` (exclusive) on one axis
+type Span = (i32, i32);
+
+struct Canvas {
+    width: i32,
+    height: i32,
+    alpha: Vec<u8>,
+    /// Thickness of a light line (as of underlines), heavy is twice that
+    light: i32,
+}
+
+impl Canvas {
+    fn new(width: usize, height: usize) -> Self {
+        Self {
+            width: width as i32,
+            height: height as i32,
+            alpha: vec![0; width * height],
+            light: (height as i32 / 16).max(1),
+        }
+    }
+
+    /// A band of `thickness` pixels around `center`
+    fn band(center: i32, thickness: i32) -> Span {
+        (center - thickness / 2, center - thickness / 2 + thickness)
+    }
+
+    /// Fill a rectangle, clipped to the cell
+    fn rect(&mut self, (x0, x1): Span, (y0, y1): Span, alpha: u8) {
+        for y in y0.max(0)..y1.min(self.height) {
+            for x in x0.max(0)..x1.min(self.width) {
+                let pixel = &mut self.alpha[(y * self.width + x) as usize];
+                *pixel = (*pixel).max(alpha);
+            }
+        }
+    }
+
+    /// Cover the pixels of a shape, anti-aliased by sampling each pixel
+    /// 4x4 times; `inside` takes a point in pixel coordinates
+    fn shape(&mut self, inside: impl Fn(f32, f32) -> bool) {
+        const SAMPLES: u32 = 4;
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let mut hits = 0;
+                for sy in 0..SAMPLES {
+                    for sx in 0..SAMPLES {
+                        let px = x as f32 + (sx as f32 + 0.5) / SAMPLES as f32;
+                        let py = y as f32 + (sy as f32 + 0.5) / SAMPLES as f32;
+                        hits += inside(px, py) as u32;
+                    }
+                }
+                let alpha = (hits * 255 / (SAMPLES * SAMPLES)) as u8;
+                let pixel = &mut self.alpha[(y * self.width + x) as usize];
+                *pixel = (*pixel).max(alpha);
+            }
+        }
+    }
+
+    fn line(&mut self, c: char, arms: &str) {
+        if let [up, right, down, left] = arms.as_bytes() {
+            let [up, right, down, left] = [up, right, down, left].map(|&weight| weight - b'0');
+            self.arms([left, right], [up, down], false);
+            self.arms([up, down], [left, right], true);
+            return;
+        }
+        match c {
+            '\u{2504}' => self.dashes(3, LIGHT, false),
+            '\u{2505}' => self.dashes(3, HEAVY, false),
+            '\u{2506}' => self.dashes(3, LIGHT, true),
+            '\u{2507}' => self.dashes(3, HEAVY, true),
+            '\u{2508}' => self.dashes(4, LIGHT, false),
+            '\u{2509}' => self.dashes(4, HEAVY, false),
+            '\u{250a}' => self.dashes(4, LIGHT, true),
+            '\u{250b}' => self.dashes(4, HEAVY, true),
+            '\u{254c}' => self.dashes(2, LIGHT, false),
+            '\u{254d}' => self.dashes(2, HEAVY, false),
+            '\u{254e}' => self.dashes(2, LIGHT, true),
+            '\u{254f}' => self.dashes(2, HEAVY, true),
+            '\u{256d}' => self.arc(true, true),
+            '\u{256e}' => self.arc(false, true),
+            '\u{256f}' => self.arc(false, false),
+            '\u{2570}' => self.arc(true, false),
+            _ => self.diagonals(c != '\u{2572}', c != '\u{2571}'),
+        }
+    }
+
+    fn thickness(&self, weight: u8) -> i32 {
+        if weight == HEAVY {
+            self.light * 2
+        } else {
+            self.light
+        }
+    }
+
+    /// Fill a span along an axis and one across it (`vertical` swaps them)
+    fn axis_rect(&mut self, along: Span, across: Span, vertical: bool) {
+        if vertical {
+            self.rect(across, along, 255);
+        } else {
+            self.rect(along, across, 255);
+        }
+    }
+
+    /// Draw the two arms on one axis, from the cell edges to the centre
+    ///
+    /// `arms` are the weights of the arms towards the start and the end of
+    /// the axis, `across` those on the other axis. The arms stop where they
+    /// meet the lines across: double lines leave the inside of their
+    /// corners and crossings open, the way fonts draw them.
+    fn arms(&mut self, arms: [u8; 2], across: [u8; 2], vertical: bool) {
+        let (len, cross_len) = if vertical {
+            (self.height, self.width)
+        } else {
+            (self.width, self.height)
+        };
+        let (mid, cross_mid) = (len / 2, cross_len / 2);
+        let gap = self.light;
+        let near = Self::band(mid - gap, self.light);
+        let far = Self::band(mid + gap, self.light);
+        let across_double = across.contains(&DOUBLE);
+        let across_width = across
+            .iter()
+            .filter(|&&weight| weight != NONE && weight != DOUBLE)
+            .map(|&weight| self.thickness(weight))
+            .max()
+            .unwrap_or(self.light);
+        for (i, &weight) in arms.iter().enumerate() {
+            if weight == NONE {
+                continue;
+            }
+            // Parallel lines with the side of the centre they are on
+            let lines: &[(i32, usize)] = if weight == DOUBLE {
+                &[(cross_mid - gap, 0), (cross_mid + gap, 1)]
+            } else {
+                &[(cross_mid, 0)]
+            };
+            for &(center, side) in lines {
+                // Where an arm from the start ends and one from the end starts
+                let (start, end) = if across == [NONE, NONE] {
+                    (mid, mid)
+                } else if weight == DOUBLE && across_double {
+                    if across[side] == DOUBLE {
+                        (far.0, near.1)
+                    } else {
+                        (near.0, far.1)
+                    }
+                } else if across_double {
+                    if arms[1 - i] != NONE {
+                        (mid, mid)
+                    } else {
+                        (far.0, near.1)
+                    }
+                } else {
+                    let band = Self::band(mid, across_width);
+                    (band.0, band.1)
+                };
+                let along = if i == 0 { (0, end) } else { (start, len) };
+                let across = Self::band(center, self.thickness(weight));
+                self.axis_rect(along, across, vertical);
+            }
+        }
+    }
+
+    /// A line of `count` dashes, with half a gap at either end so that
+    /// dashes repeat evenly across cells
+    fn dashes(&mut self, count: i32, weight: u8, vertical: bool) {
+        let (len, cross_len) = if vertical {
+            (self.height, self.width)
+        } else {
+            (self.width, self.height)
+        };
+        let segment = len as f32 / count as f32;
+        let gap = (segment / 4.0).round().max(1.0);
+        let across = Self::band(cross_len / 2, self.thickness(weight));
+        for i in 0..count {
+            let start = (i as f32 * segment + gap / 2.0).round() as i32;
+            let end = ((i + 1) as f32 * segment - gap / 2.0).round() as i32;
+            self.axis_rect((start, end), across, vertical);
+        }
+    }
+
+    /// Centre of the light line on each axis
+    fn line_centers(&self) -> (f32, f32) {
+        let (x0, x1) = Self::band(self.width / 2, self.light);
+        let (y0, y1) = Self::band(self.height / 2, self.light);
+        ((x0 + x1) as f32 / 2.0, (y0 + y1) as f32 / 2.0)
+    }
+
+    /// A rounded corner joining the arms to the right or left and down or up
+    fn arc(&mut self, right: bool, down: bool) {
+        let (cx, cy) = self.line_centers();
+        let radius = (self.width / 2).min(self.height / 2) as f32;
+        let (sx, sy) = (
+            if right { 1.0 } else { -1.0 },
+            if down { 1.0 } else { -1.0 },
+        );
+        let (ox, oy) = (cx + sx * radius, cy + sy * radius);
+        let half = self.light as f32 / 2.0;
+        self.shape(|x, y| {
+            let distance = ((x - ox).powi(2) + (y - oy).powi(2)).sqrt();
+            (x - ox) * sx <= 0.0 && (y - oy) * sy <= 0.0 && (distance - radius).abs() <= half
+        });
+        // Straight on from the ends of the arc to the edges
+        let horizontal = if right {
+            (ox.round() as i32, self.width)
+        } else {
+            (0, ox.round() as i32)
+        };
+        let vertical = if down {
+            (oy.round() as i32, self.height)
+        } else {
+            (0, oy.round() as i32)
+        };
+        let (light, width, height) = (self.light, self.width, self.height);
+        self.rect(horizontal, Self::band(height / 2, light), 255);
+        self.rect(Self::band(width / 2, light), vertical, 255);
+    }
+
+    /// Corner to corner lines: `rising` bottom left to top right, `falling`
+    /// top left to bottom right
+    fn diagonals(&mut self, rising: bool, falling: bool) {
+        let (w, h) = (self.width as f32, self.height as f32);
+        let norm = (w * w + h * h).sqrt();
+        let half = self.light as f32 / 2.0;
+        self.shape(|x, y| {
+            (rising && (h * x + w * y - w * h).abs() / norm <= half)
+                || (falling && (h * x - w * y).abs() / norm <= half)
+        });
+    }
+
+    fn block(&mut self, code: u32) {
+        let (w, h) = (self.width, self.height);
+        // n eighths of the cell size
+        let eighths = |size: i32, n: u32| (size as f32 * n as f32 / 8.0).round() as i32;
+        let (mid_x, mid_y) = (eighths(w, 4), eighths(h, 4));
+        match code {
+            0x2580 => self.rect((0, w), (0, mid_y), 255),
+            0x2581..=0x2588 => self.rect((0, w), (h - eighths(h, code - 0x2580), h), 255),
+            0x2589..=0x258f => self.rect((0, eighths(w, 0x2590 - code)), (0, h), 255),
+            0x2590 => self.rect((mid_x, w), (0, h), 255),
+            0x2591..=0x2593 => {
+                let alpha = (code - 0x2590) as u8 * 64;
+                self.rect((0, w), (0, h), alpha);
+            }
+            0x2594 => self.rect((0, w), (0, eighths(h, 1)), 255),
+            0x2595 => self.rect((w - eighths(w, 1), w), (0, h), 255),
+            _ => {
+                // Quadrants: upper left, upper right, lower left, lower right
+                let quadrants: [bool; 4] = match code {
+                    0x2596 => [false, false, true, false],
+                    0x2597 => [false, false, false, true],
+                    0x2598 => [true, false, false, false],
+                    0x2599 => [true, false, true, true],
+                    0x259a => [true, false, false, true],
+                    0x259b => [true, true, true, false],
+                    0x259c => [true, true, false, true],
+                    0x259d => [false, true, false, false],
+                    0x259e => [false, true, true, false],
+                    _ => [false, true, true, true],
+                };
+                let spans = [
+                    ((0, mid_x), (0, mid_y)),
+                    ((mid_x, w), (0, mid_y)),
+                    ((0, mid_x), (mid_y, h)),
+                    ((mid_x, w), (mid_y, h)),
+                ];
+                for (&filled, &(x, y)) in quadrants.iter().zip(&spans) {
+                    if filled {
+                        self.rect(x, y, 255);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Separators pointing right (even codes) and left (odd codes, after
+    /// mirroring): solid and thin arrows, solid and thin half circles
+    fn powerline(&mut self, code: u32) {
+        let (w, h) = (self.width as f32, self.height as f32);
+        let half = self.light as f32 / 2.0;
+        let mirror = matches!(code, 0xe0b2 | 0xe0b3 | 0xe0b6 | 0xe0b7);
+        let thin = matches!(code, 0xe0b1 | 0xe0b3 | 0xe0b5 | 0xe0b7);
+        let round = code >= 0xe0b4;
+        let norm = (w * w + h * h / 4.0).sqrt();
+        self.shape(|x, y| {
+            let x = if mirror { w - x } else { x };
+            // Distance from the middle row, folding the lower half up
+            let dy = (y - h / 2.0).abs();
+            match (round, thin) {
+                (false, false) => x <= w * (1.0 - dy / (h / 2.0)),
+                (false, true) => ((h / 2.0) * x - w * (h / 2.0 - dy)).abs() / norm <= half,
+                (true, false) => (x / w).powi(2) + (dy / (h / 2.0)).powi(2) <= 1.0,
+                (true, true) => {
+                    let outer = (x / w).powi(2) + (dy / (h / 2.0)).powi(2) <= 1.0;
+                    let (a, b) = (w - 2.0 * half, h / 2.0 - 2.0 * half);
+                    outer && (x / a).powi(2) + (dy / b).powi(2) > 1.0
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZES: [(usize, usize); 3] = [(9, 18), (10, 21), (16, 33)];
+
+    fn column(bitmap: &[u8], width: usize, x: usize) -> Vec<u8> {
+        bitmap.iter().skip(x).step_by(width).copied().collect()
+    }
+
+    fn row(bitmap: &[u8], width: usize, y: usize) -> Vec<u8> {
+        bitmap[y * width..(y + 1) * width].to_vec()
+    }
+
+    #[test]
+    fn test_not_builtin() {
+        assert!(synthesize('a', 9, 18).is_none());
+        assert!(synthesize('\u{2600}', 9, 18).is_none());
+        assert!(synthesize('─', 0, 18).is_none());
+        assert!(is_builtin('┼') && is_builtin('█') && is_builtin('\u{e0b0}'));
+    }
+
+    #[test]
+    fn test_lines_connect() {
+        // Every arm reaches the edge exactly where the straight line of its
+        // weight does, so neighbouring cells join without gaps or steps
+        for (width, height) in SIZES {
+            let reference = |c: char| synthesize(c, width, height).unwrap();
+            let straight = |weight: u8| match weight {
+                LIGHT => ('─', '│'),
+                HEAVY => ('━', '┃'),
+                _ => ('═', '║'),
+            };
+            for (i, arms) in LINES.iter().enumerate() {
+                let Some(c) = char::from_u32(0x2500 + i as u32) else {
+                    continue;
+                };
+                if arms.is_empty() {
+                    continue;
+                }
+                let bitmap = reference(c);
+                let weights: Vec<u8> = arms.bytes().map(|b| b - b'0').collect();
+                let edges = [
+                    (weights[0], row(&bitmap, width, 0)),
+                    (weights[1], column(&bitmap, width, width - 1)),
+                    (weights[2], row(&bitmap, width, height - 1)),
+                    (weights[3], column(&bitmap, width, 0)),
+                ];
+                for (edge, (weight, pixels)) in edges.into_iter().enumerate() {
+                    let expected = if weight == NONE {
+                        vec![0; pixels.len()]
+                    } else {
+                        let (horizontal, vertical) = straight(weight);
+                        match edge {
+                            0 => row(&reference(vertical), width, 0),
+                            1 => column(&reference(horizontal), width, width - 1),
+                            2 => row(&reference(vertical), width, height - 1),
+                            _ => column(&reference(horizontal), width, 0),
+                        }
+                    };
+                    assert_eq!(
+                        pixels, expected,
+                        "{:?} edge {} at {}x{}",
+                        c, edge, width, height
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_double_corner_open_inside() {
+        let (width, height) = (9, 18);
+        let bitmap = synthesize('╔', width, height).unwrap();
+        let centre = (height / 2) * width + width / 2;
+        // The space between the two lines stays empty
+        assert_eq!(bitmap[centre], 0);
+        // Outer corner is closed
+        assert_eq!(bitmap[(height / 2 - 1) * width + width / 2 - 1], 255);
+    }
+
+    #[test]
+    fn test_blocks() {
+        let (width, height) = (9, 18);
+        assert!(synthesize('█', width, height)
+            .unwrap()
+            .iter()
+            .all(|&a| a == 255));
+        let lower = synthesize('▄', width, height).unwrap();
+        assert!(row(&lower, width, 0).iter().all(|&a| a == 0));
+        assert!(row(&lower, width, height - 1).iter().all(|&a| a == 255));
+        let left = synthesize('▌', width, height).unwrap();
+        assert_eq!(column(&left, width, 0), vec![255; height]);
+        assert_eq!(column(&left, width, width - 1), vec![0; height]);
+        let shade = synthesize('▒', width, height).unwrap();
+        assert!(shade.iter().all(|&a| a == 128));
+        let quadrant = synthesize('▚', width, height).unwrap();
+        assert_eq!(quadrant[0], 255);
+        assert_eq!(quadrant[width - 1], 0);
+        assert_eq!(quadrant[width * height - 1], 255);
+    }
+
+    #[test]
+    fn test_powerline() {
+        let (width, height) = (9, 18);
+        let arrow = synthesize('\u{e0b0}', width, height).unwrap();
+        // Fills the whole height at the left edge and comes to a point
+        assert!(column(&arrow, width, 0).iter().all(|&a| a > 0));
+        assert_eq!(arrow[width - 1], 0);
+        assert!(arrow[(height / 2) * width + width - 1] > 0);
+        let left = synthesize('\u{e0b2}', width, height).unwrap();
+        assert!(column(&left, width, width - 1).iter().all(|&a| a > 0));
+        assert_eq!(left[0], 0);
+        let thin = synthesize('\u{e0b1}', width, height).unwrap();
+        assert_eq!(thin[(height / 2) * width], 0);
+    }
+}
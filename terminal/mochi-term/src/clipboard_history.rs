@@ -0,0 +1,193 @@
+//! Clipboard history (recent copies, picked from an overlay)
+//!
+//! Every copy made in mochi (selections, copy mode, hints) is also kept in a
+//! small ring shared by all windows, newest first. Ctrl+Shift+Y opens a
+//! picker over the terminal listing the entries; choosing one pastes it into
+//! the active tab like a normal paste. The ring is bounded both by number of
+//! entries and by total size, so copying a huge selection cannot pin memory.
+
+use std::collections::VecDeque;
+
+/// Recent copies, newest first
+#[derive(Debug, Default)]
+pub struct ClipboardHistory {
+    entries: VecDeque<String>,
+    max_entries: usize,
+    max_bytes: usize,
+    bytes: usize,
+}
+
+impl ClipboardHistory {
+    /// A history of at most `max_entries` copies and `max_bytes` in total
+    /// (0 entries keeps nothing)
+    pub fn new(max_entries: usize, max_bytes: usize) -> Self {
+        Self {
+            entries: VecDeque::new(),
+            max_entries,
+            max_bytes,
+            bytes: 0,
+        }
+    }
+
+    /// Change the limits, dropping the oldest entries that no longer fit
+    pub fn set_limits(&mut self, max_entries: usize, max_bytes: usize) {
+        self.max_entries = max_entries;
+        self.max_bytes = max_bytes;
+        self.trim();
+    }
+
+    /// Remember a copy
+    ///
+    /// Copying text that is already in the history moves it to the front.
+    /// Text larger than the whole history is not kept.
+    pub fn push(&mut self, text: &str) {
+        if text.is_empty() || text.len() > self.max_bytes || self.max_entries == 0 {
+            return;
+        }
+        if let Some(index) = self.entries.iter().position(|entry| entry == text) {
+            let entry = self.entries.remove(index).unwrap();
+            self.entries.push_front(entry);
+            return;
+        }
+        self.bytes += text.len();
+        self.entries.push_front(text.to_string());
+        self.trim();
+    }
+
+    fn trim(&mut self) {
+        while self.entries.len() > self.max_entries || self.bytes > self.max_bytes {
+            match self.entries.pop_back() {
+                Some(entry) => self.bytes -= entry.len(),
+                None => break,
+            }
+        }
+    }
+
+    /// Entry `index` (0 is the newest)
+    pub fn get(&self, index: usize) -> Option<&str> {
+        self.entries.get(index).map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Total size of the entries in bytes
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    /// Entries, newest first
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().map(String::as_str)
+    }
+}
+
+/// One-line summary of an entry for the picker: its first non-blank line,
+/// cut to `max_chars`, and how many more lines follow
+pub fn preview(text: &str, max_chars: usize) -> String {
+    let mut lines = text.lines().filter(|line| !line.trim().is_empty());
+    let first: String = lines
+        .next()
+        .unwrap_or("")
+        .trim()
+        .chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect();
+    let mut preview: String = first.chars().take(max_chars).collect();
+    if first.chars().count() > max_chars {
+        preview.truncate(preview.trim_end().len());
+        preview.push_str("...");
+    }
+    match lines.count() {
+        0 => preview,
+        1 => format!("{} (+1 line)", preview),
+        n => format!("{} (+{} lines)", preview, n),
+    }
+}
+
+/// Picker over the history: which entry is highlighted
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Picker {
+    pub selected: usize,
+}
+
+impl Picker {
+    /// Move the highlight by `delta`, wrapping around `len` entries
+    pub fn move_by(&mut self, delta: isize, len: usize) {
+        if len > 0 {
+            self.selected = (self.selected as isize + delta).rem_euclid(len as isize) as usize;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries(history: &ClipboardHistory) -> Vec<&str> {
+        history.iter().collect()
+    }
+
+    #[test]
+    fn test_history_order_and_limits() {
+        let mut history = ClipboardHistory::new(3, 1024);
+        for text in ["one", "two", "three", "four"] {
+            history.push(text);
+        }
+        assert_eq!(entries(&history), ["four", "three", "two"]);
+
+        // A repeated copy moves to the front instead of being added twice
+        history.push("two");
+        assert_eq!(entries(&history), ["two", "four", "three"]);
+        history.push("");
+        assert_eq!(history.len(), 3);
+
+        history.set_limits(1, 1024);
+        assert_eq!(entries(&history), ["two"]);
+    }
+
+    #[test]
+    fn test_history_memory_cap() {
+        let mut history = ClipboardHistory::new(10, 10);
+        history.push("aaaa");
+        history.push("bbbb");
+        history.push("cccc");
+        assert_eq!(entries(&history), ["cccc", "bbbb"]);
+
+        // Too large to keep at all
+        history.push("xxxxxxxxxxxx");
+        assert_eq!(entries(&history), ["cccc", "bbbb"]);
+
+        let mut history = ClipboardHistory::new(0, 10);
+        history.push("a");
+        assert!(history.is_empty());
+    }
+
+    #[test]
+    fn test_preview() {
+        assert_eq!(preview("ls -la", 20), "ls -la");
+        assert_eq!(
+            preview("\n  first\nsecond\n\nthird\n", 20),
+            "first (+2 lines)"
+        );
+        assert_eq!(preview("a\nb", 20), "a (+1 line)");
+        assert_eq!(preview("abcdef ghij", 7), "abcdef...");
+        assert_eq!(preview("tab\there", 20), "tab here");
+    }
+
+    #[test]
+    fn test_picker_wraps() {
+        let mut picker = Picker::default();
+        picker.move_by(-1, 3);
+        assert_eq!(picker.selected, 2);
+        picker.move_by(1, 3);
+        assert_eq!(picker.selected, 0);
+        picker.move_by(1, 0);
+        assert_eq!(picker.selected, 0);
+    }
+}
@@ -9,9 +9,27 @@
 
 use clap::Parser;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::env;
 use std::fs;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use terminal_pty::{CommandBuilder, IoPriority, Priority, SystemdScope, DEFAULT_TERM};
+
+use crate::accent::TabAccents;
+use crate::contrast::{CONTRAST_DISABLED, MAX_CONTRAST};
+use crate::highlight::HighlightRules;
+use crate::hints;
+use crate::hooks::Hooks;
+use crate::keybindings::Keybindings;
+use crate::opener::Openers;
+use crate::session::Session;
+use crate::status_bar::StatusBar;
+use crate::terminal::{OscPolicies, TerminalBuilder, TerminalLimits, DEFAULT_DEVICE_ATTRIBUTES};
+use crate::terminfo;
+use crate::theme;
 
 /// CLI arguments for Mochi Terminal
 #[derive(Parser, Debug, Clone)]
@@ -32,14 +50,30 @@ pub struct CliArgs {
     #[arg(long, value_name = "SIZE")]
     pub font_size: Option<f32>,
 
-    /// Theme name (dark, light, solarized-dark, solarized-light, dracula, nord)
+    /// Theme name (dark, light, solarized-dark, solarized-light, dracula, nord, or a theme file)
     #[arg(short, long, value_name = "THEME")]
     pub theme: Option<String>,
 
-    /// Shell command to run
+    /// List the built-in and user themes, then exit
+    #[arg(long)]
+    pub list_themes: bool,
+
+    /// Shell to run; a value with spaces is split into program and arguments ("zsh -l")
     #[arg(short, long, value_name = "SHELL")]
     pub shell: Option<String>,
 
+    /// Set an environment variable for shells (repeatable)
+    #[arg(long = "env", value_name = "KEY=VALUE")]
+    pub env: Vec<String>,
+
+    /// Directory new shells start in
+    #[arg(long, value_name = "DIR")]
+    pub working_directory: Option<PathBuf>,
+
+    /// Profile of the first tab and of new tabs
+    #[arg(short, long, value_name = "NAME")]
+    pub profile: Option<String>,
+
     /// Number of scrollback lines
     #[arg(long, value_name = "LINES")]
     pub scrollback: Option<usize>,
@@ -55,10 +89,50 @@ pub struct CliArgs {
     /// Enable OSC 52 clipboard (security risk)
     #[arg(long)]
     pub enable_osc52: bool,
+
+    /// Restore tabs from a session file (and save them back on exit)
+    #[arg(long, value_name = "FILE")]
+    pub session: Option<PathBuf>,
+
+    /// Run a command in its own tab; the tab stays open when it exits so it can be re-run
+    #[arg(long, value_name = "CMD")]
+    pub command: Option<String>,
+
+    /// Run --command on a PTY without a window, answering its terminal queries, and exit with its status
+    #[arg(long, requires = "command")]
+    pub headless: bool,
+
+    /// Print the shell integration snippet for bash and zsh, then exit
+    #[arg(long)]
+    pub shell_integration: bool,
+
+    /// Print the source of the mochi terminfo entry, then exit (install it
+    /// with `mochi --emit-terminfo | tic -x -`)
+    #[arg(long)]
+    pub emit_terminfo: bool,
+
+    /// Run a command instead of the shell in the first tab (xterm style:
+    /// everything after -e is the program and its arguments)
+    #[arg(
+        short = 'e',
+        long = "execute",
+        value_name = "COMMAND",
+        num_args = 1..,
+        allow_hyphen_values = true
+    )]
+    pub execute: Vec<String>,
+
+    /// With single_instance, open a tab in the running mochi instead of a window
+    #[arg(long)]
+    pub tab: bool,
+
+    /// Log shell output, the parsed actions and unhandled sequences to a file (JSON lines)
+    #[arg(long, value_name = "FILE")]
+    pub trace: Option<PathBuf>,
 }
 
 /// Available theme names
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
 #[serde(rename_all = "kebab-case")]
 pub enum ThemeName {
     /// Mochi theme - cute pink kawaii aesthetic (default)
@@ -78,6 +152,9 @@ pub enum ThemeName {
     Nord,
     /// Custom theme (uses colors field)
     Custom,
+    /// Theme loaded from `<name>.toml` in the themes directory
+    #[serde(untagged)]
+    User(String),
 }
 
 impl ThemeName {
@@ -96,8 +173,12 @@ impl ThemeName {
         }
     }
 
-    /// Get all available theme names
-    #[allow(dead_code)] // Will be used for theme listing UI
+    /// Parse a theme name, treating unknown names as user theme files
+    pub fn parse(s: &str) -> Self {
+        Self::from_str(s).unwrap_or_else(|| ThemeName::User(s.to_string()))
+    }
+
+    /// Get all built-in theme names
     pub fn all_names() -> &'static [&'static str] {
         &[
             "mochi",
@@ -113,7 +194,7 @@ impl ThemeName {
 
     /// Cycle to the next theme (for toggle keybinding)
     #[allow(dead_code)]
-    pub fn next(self) -> Self {
+    pub fn next(&self) -> Self {
         match self {
             ThemeName::Mochi => ThemeName::Dark,
             ThemeName::Dark => ThemeName::Light,
@@ -122,11 +203,139 @@ impl ThemeName {
             ThemeName::SolarizedLight => ThemeName::Dracula,
             ThemeName::Dracula => ThemeName::Nord,
             ThemeName::Nord => ThemeName::Mochi,
-            ThemeName::Custom => ThemeName::Mochi,
+            ThemeName::Custom | ThemeName::User(_) => ThemeName::Mochi,
+        }
+    }
+}
+
+/// Shell to run in new tabs
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ShellCommand {
+    /// Path or name of the shell, started as a login shell
+    Program(String),
+    /// Program and arguments, run exactly as given
+    Argv(Vec<String>),
+}
+
+impl ShellCommand {
+    /// Parse a command line argument
+    ///
+    /// A value with whitespace is split into program and arguments; shell
+    /// quoting is not supported.
+    pub fn from_arg(arg: &str) -> Self {
+        let argv: Vec<String> = arg.split_whitespace().map(String::from).collect();
+        if argv.len() > 1 {
+            ShellCommand::Argv(argv)
+        } else {
+            ShellCommand::Program(arg.trim().to_string())
+        }
+    }
+
+    /// Build the command to spawn
+    ///
+    /// Returns None if the program is empty.
+    pub fn to_command(&self) -> Option<CommandBuilder> {
+        match self {
+            ShellCommand::Program(program) if program.is_empty() => None,
+            ShellCommand::Program(program) => {
+                let mut builder = CommandBuilder::new(program);
+                builder.login_shell(true);
+                Some(builder)
+            }
+            ShellCommand::Argv(argv) => CommandBuilder::from_argv(argv),
+        }
+    }
+}
+
+/// Command the first tab runs instead of the shell
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum StartupCommand {
+    /// Command line, run with `/bin/sh -c`
+    Line(String),
+    /// Program and arguments, run exactly as given
+    Argv(Vec<String>),
+}
+
+impl StartupCommand {
+    /// The command given with `-e`
+    ///
+    /// A single argument with whitespace is a command line (as desktop
+    /// files and some launchers pass it); otherwise the arguments are the
+    /// program and its arguments.
+    pub fn from_args(args: &[String]) -> Option<Self> {
+        match args {
+            [] => None,
+            [line] if line.contains(char::is_whitespace) => {
+                Some(StartupCommand::Line(line.clone()))
+            }
+            argv => Some(StartupCommand::Argv(argv.to_vec())),
+        }
+    }
+
+    /// Build the command to spawn
+    ///
+    /// Returns None if there is no program.
+    pub fn to_command(&self) -> Option<CommandBuilder> {
+        match self {
+            StartupCommand::Line(line) if line.trim().is_empty() => None,
+            StartupCommand::Line(line) => Some(CommandBuilder::shell_command(line)),
+            StartupCommand::Argv(argv) if argv.first().is_some_and(|p| p.is_empty()) => None,
+            StartupCommand::Argv(argv) => CommandBuilder::from_argv(argv),
         }
     }
 }
 
+/// What happens to a tab when its shell exits
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum ExitBehavior {
+    /// Close the tab (default)
+    #[default]
+    Close,
+    /// Keep the tab open and show the exit status
+    Hold,
+    /// Start the shell again in the same tab
+    Restart,
+}
+
+/// How keys are reported under modifyOtherKeys (xterm's formatOtherKeys)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum OtherKeysFormat {
+    /// `CSI 27 ; modifier ; code ~` (default)
+    #[default]
+    Xterm,
+    /// `CSI code ; modifier u`
+    CsiU,
+}
+
+/// When to ask before closing a tab or the window
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum CloseConfirmation {
+    /// Never ask
+    Never,
+    /// Ask when a command other than the shell is running (default)
+    #[default]
+    Running,
+    /// Always ask
+    Always,
+}
+
+/// I/O scheduling class of new shells (Linux)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum IoClass {
+    /// No class: I/O follows the nice level
+    Normal,
+    /// Best-effort at `io_level`
+    BestEffort,
+    /// Disk time only when no other process needs it
+    Idle,
+}
+
 /// Keybinding action
 #[allow(dead_code)] // Will be used when keybinding parsing is implemented
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -146,6 +355,11 @@ pub enum KeyAction {
     ScrollPageDown,
     ScrollToTop,
     ScrollToBottom,
+    ScrollToPreviousMark,
+    ScrollToNextMark,
+    SetMark,
+    CopyMode,
+    Hints,
     ClearScrollback,
 }
 
@@ -195,6 +409,81 @@ pub struct KeybindingsConfig {
     /// Reset zoom to default
     #[serde(default = "default_zoom_reset_key")]
     pub zoom_reset: String,
+    /// Rename the current tab
+    #[serde(default = "default_rename_tab_key")]
+    pub rename_tab: String,
+    /// Show the foreground process of the current tab
+    #[serde(default = "default_process_info_key")]
+    pub process_info: String,
+    /// Run a command in a new command tab
+    #[serde(default = "default_run_command_key")]
+    pub run_command: String,
+    /// Scroll up one page
+    #[serde(default = "default_scroll_page_up_key")]
+    pub scroll_page_up: String,
+    /// Scroll down one page
+    #[serde(default = "default_scroll_page_down_key")]
+    pub scroll_page_down: String,
+    /// Scroll to the top of the scrollback
+    #[serde(default = "default_scroll_to_top_key")]
+    pub scroll_to_top: String,
+    /// Scroll to the bottom
+    #[serde(default = "default_scroll_to_bottom_key")]
+    pub scroll_to_bottom: String,
+    /// Scroll to the previous mark (prompt or user mark)
+    #[serde(default = "default_scroll_to_previous_mark_key")]
+    pub scroll_to_previous_mark: String,
+    /// Scroll to the next mark
+    #[serde(default = "default_scroll_to_next_mark_key")]
+    pub scroll_to_next_mark: String,
+    /// Set a mark on the cursor line
+    #[serde(default = "default_set_mark_key")]
+    pub set_mark: String,
+    /// Enter copy mode (keyboard-driven selection)
+    #[serde(default = "default_copy_mode_key")]
+    pub copy_mode: String,
+    /// Enter hint mode (label and pick matches on screen)
+    #[serde(default = "default_hints_key")]
+    pub hints: String,
+    /// Open the clipboard history picker
+    #[serde(default = "default_clipboard_history_key")]
+    pub clipboard_history: String,
+    /// Hint mode that opens the chosen match
+    #[serde(default = "default_open_hints_key")]
+    pub open_hints: String,
+    /// Cycle the current tab's monitor (off, activity, silence)
+    #[serde(default = "default_monitor_key")]
+    pub monitor: String,
+    /// Type the shell integration installer into the remote shell of ssh
+    #[serde(default = "default_ssh_integration_key")]
+    pub ssh_integration: String,
+    /// Open a new tab with a profile picked from a list
+    #[serde(default = "default_new_tab_profile_key")]
+    pub new_tab_profile: String,
+    /// Move the current tab one place to the left
+    #[serde(default = "default_move_tab_left_key")]
+    pub move_tab_left: String,
+    /// Move the current tab one place to the right
+    #[serde(default = "default_move_tab_right_key")]
+    pub move_tab_right: String,
+    /// Show the terminal inspector (modes, cursor, parser state, recent actions)
+    #[serde(default = "default_inspector_key")]
+    pub inspector: String,
+    /// Start or stop tracing shell output to a file
+    #[serde(default = "default_toggle_trace_key")]
+    pub toggle_trace: String,
+    /// Save the screen to a file (text, HTML or ANSI)
+    #[serde(default = "default_save_screen_key")]
+    pub save_screen: String,
+    /// Save the scrollback and the screen to a file (text, HTML or ANSI)
+    #[serde(default = "default_save_scrollback_key")]
+    pub save_scrollback: String,
+    /// Save a screenshot of the current tab as a PNG
+    #[serde(default = "default_screenshot_key")]
+    pub screenshot: String,
+    /// Move the current tab's shell and job to the lowest priority, or back
+    #[serde(default = "default_deprioritize_key")]
+    pub deprioritize: String,
 }
 
 fn default_copy_key() -> String {
@@ -209,17 +498,115 @@ fn default_find_key() -> String {
 fn default_reload_key() -> String {
     "ctrl+shift+r".to_string()
 }
+// Ctrl+Shift+T opens a tab outside macOS, so the theme has no shortcut there
+#[cfg(target_os = "macos")]
 fn default_toggle_theme_key() -> String {
     "ctrl+shift+t".to_string()
 }
+#[cfg(not(target_os = "macos"))]
+fn default_toggle_theme_key() -> String {
+    String::new()
+}
+// Zoom follows the platform: Cmd on macOS, Ctrl+Shift elsewhere (Ctrl with
+// arrows and symbols belongs to terminal applications)
+#[cfg(target_os = "macos")]
+fn default_zoom_in_key() -> String {
+    "super+equal".to_string()
+}
+#[cfg(not(target_os = "macos"))]
 fn default_zoom_in_key() -> String {
-    "ctrl+plus".to_string()
+    "ctrl+shift+plus".to_string()
+}
+#[cfg(target_os = "macos")]
+fn default_zoom_out_key() -> String {
+    "super+minus".to_string()
 }
+#[cfg(not(target_os = "macos"))]
 fn default_zoom_out_key() -> String {
-    "ctrl+minus".to_string()
+    "ctrl+shift+minus".to_string()
+}
+#[cfg(target_os = "macos")]
+fn default_zoom_reset_key() -> String {
+    "super+0".to_string()
 }
+#[cfg(not(target_os = "macos"))]
 fn default_zoom_reset_key() -> String {
-    "ctrl+0".to_string()
+    "ctrl+shift+0".to_string()
+}
+fn default_rename_tab_key() -> String {
+    "ctrl+shift+e".to_string()
+}
+fn default_process_info_key() -> String {
+    "ctrl+shift+i".to_string()
+}
+fn default_run_command_key() -> String {
+    "ctrl+shift+x".to_string()
+}
+fn default_scroll_page_up_key() -> String {
+    "shift+pageup".to_string()
+}
+fn default_scroll_page_down_key() -> String {
+    "shift+pagedown".to_string()
+}
+fn default_scroll_to_top_key() -> String {
+    "shift+home".to_string()
+}
+fn default_scroll_to_bottom_key() -> String {
+    "shift+end".to_string()
+}
+fn default_scroll_to_previous_mark_key() -> String {
+    "ctrl+shift+k".to_string()
+}
+fn default_scroll_to_next_mark_key() -> String {
+    "ctrl+shift+j".to_string()
+}
+fn default_set_mark_key() -> String {
+    "ctrl+shift+m".to_string()
+}
+fn default_copy_mode_key() -> String {
+    "ctrl+shift+space".to_string()
+}
+fn default_hints_key() -> String {
+    "ctrl+shift+h".to_string()
+}
+fn default_clipboard_history_key() -> String {
+    "ctrl+shift+y".to_string()
+}
+fn default_open_hints_key() -> String {
+    "ctrl+shift+o".to_string()
+}
+fn default_monitor_key() -> String {
+    "ctrl+shift+a".to_string()
+}
+fn default_ssh_integration_key() -> String {
+    "ctrl+shift+u".to_string()
+}
+fn default_new_tab_profile_key() -> String {
+    "ctrl+shift+p".to_string()
+}
+fn default_move_tab_left_key() -> String {
+    "ctrl+shift+pageup".to_string()
+}
+fn default_move_tab_right_key() -> String {
+    "ctrl+shift+pagedown".to_string()
+}
+fn default_inspector_key() -> String {
+    "ctrl+shift+d".to_string()
+}
+fn default_toggle_trace_key() -> String {
+    "ctrl+shift+l".to_string()
+}
+fn default_save_screen_key() -> String {
+    "ctrl+shift+s".to_string()
+}
+fn default_save_scrollback_key() -> String {
+    "ctrl+shift+b".to_string()
+}
+fn default_screenshot_key() -> String {
+    "ctrl+shift+g".to_string()
+}
+fn default_deprioritize_key() -> String {
+    "ctrl+shift+z".to_string()
 }
 
 impl Default for KeybindingsConfig {
@@ -233,6 +620,31 @@ impl Default for KeybindingsConfig {
             zoom_in: default_zoom_in_key(),
             zoom_out: default_zoom_out_key(),
             zoom_reset: default_zoom_reset_key(),
+            rename_tab: default_rename_tab_key(),
+            process_info: default_process_info_key(),
+            run_command: default_run_command_key(),
+            scroll_page_up: default_scroll_page_up_key(),
+            scroll_page_down: default_scroll_page_down_key(),
+            scroll_to_top: default_scroll_to_top_key(),
+            scroll_to_bottom: default_scroll_to_bottom_key(),
+            scroll_to_previous_mark: default_scroll_to_previous_mark_key(),
+            scroll_to_next_mark: default_scroll_to_next_mark_key(),
+            set_mark: default_set_mark_key(),
+            copy_mode: default_copy_mode_key(),
+            hints: default_hints_key(),
+            clipboard_history: default_clipboard_history_key(),
+            open_hints: default_open_hints_key(),
+            monitor: default_monitor_key(),
+            ssh_integration: default_ssh_integration_key(),
+            new_tab_profile: default_new_tab_profile_key(),
+            move_tab_left: default_move_tab_left_key(),
+            move_tab_right: default_move_tab_right_key(),
+            inspector: default_inspector_key(),
+            toggle_trace: default_toggle_trace_key(),
+            save_screen: default_save_screen_key(),
+            save_scrollback: default_save_scrollback_key(),
+            screenshot: default_screenshot_key(),
+            deprioritize: default_deprioritize_key(),
         }
     }
 }
@@ -258,6 +670,10 @@ pub struct FontConfig {
     /// Line height multiplier (1.0 = normal)
     #[serde(default = "default_line_height")]
     pub line_height: f32,
+    /// Draw box drawing, block and powerline characters to fill the cell
+    /// instead of taking them from the font
+    #[serde(default = "default_true")]
+    pub builtin_box_drawing: bool,
 }
 
 fn default_font_family() -> String {
@@ -286,10 +702,22 @@ impl Default for FontConfig {
             cell_padding_x: 0,
             cell_padding_y: 0,
             line_height: default_line_height(),
+            builtin_box_drawing: true,
         }
     }
 }
 
+/// Space between the window edges and the grid, in pixels (scaled for HiDPI)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PaddingConfig {
+    /// Left and right padding
+    #[serde(default)]
+    pub x: u32,
+    /// Top (below the tab bar) and bottom padding
+    #[serde(default)]
+    pub y: u32,
+}
+
 /// Security configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SecurityConfig {
@@ -305,6 +733,29 @@ pub struct SecurityConfig {
     /// Maximum title updates per second (throttling)
     #[serde(default = "default_title_update_rate")]
     pub title_update_rate: u32,
+    /// Show notifications sent by programs (OSC 9 / OSC 777)
+    #[serde(default = "default_true")]
+    pub notifications: bool,
+    /// Show images sent with OSC 1337
+    #[serde(default = "default_true")]
+    pub inline_images: bool,
+    /// Answer status and capability queries (DA, DSR, XTGETTCAP, ...)
+    #[serde(default = "default_true")]
+    pub answer_queries: bool,
+    /// Parameters of the primary device attributes reply (`CSI ? <attrs> c`)
+    #[serde(default = "default_device_attributes")]
+    pub device_attributes: String,
+    /// Reply to ENQ (0x05); empty sends nothing
+    #[serde(default)]
+    pub answerback: String,
+    /// Handle sequences that programs inside tmux pass through to the
+    /// terminal (`DCS tmux; ... ST`)
+    #[serde(default = "default_true")]
+    pub tmux_passthrough: bool,
+    /// Allow, deny or ask about each OSC feature; unset features follow
+    /// `osc52_clipboard`, `notifications` and `inline_images`
+    #[serde(default)]
+    pub osc: OscPolicyConfig,
 }
 
 fn default_osc52_max_size() -> usize {
@@ -316,6 +767,9 @@ fn default_true() -> bool {
 fn default_title_update_rate() -> u32 {
     10
 }
+fn default_device_attributes() -> String {
+    DEFAULT_DEVICE_ATTRIBUTES.to_string()
+}
 
 impl Default for SecurityConfig {
     fn default() -> Self {
@@ -324,157 +778,990 @@ impl Default for SecurityConfig {
             osc52_max_size: default_osc52_max_size(),
             osc52_notify: true,
             title_update_rate: default_title_update_rate(),
+            notifications: true,
+            inline_images: true,
+            answer_queries: true,
+            device_attributes: default_device_attributes(),
+            answerback: String::new(),
+            tmux_passthrough: true,
+            osc: OscPolicyConfig::default(),
         }
     }
 }
 
-/// Terminal configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Config {
-    /// Font configuration
-    #[serde(default)]
-    pub font: FontConfig,
-
-    /// Number of scrollback lines
-    #[serde(default = "default_scrollback_lines")]
-    pub scrollback_lines: usize,
-
-    /// Window dimensions (columns, rows)
-    #[serde(default = "default_dimensions")]
-    pub dimensions: (u16, u16),
+/// What happens when a program uses an OSC feature
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OscPolicy {
+    Allow,
+    Deny,
+    /// Ask the first time a tab uses the feature, and keep the answer for
+    /// the tab
+    Prompt,
+}
 
-    /// Theme name
+/// Policy for each OSC feature (`[security.osc]`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OscPolicyConfig {
+    /// Window and icon titles (OSC 0, 1 and 2)
     #[serde(default)]
-    pub theme: ThemeName,
-
-    /// Custom color scheme (used when theme is "custom")
+    pub title: Option<OscPolicy>,
+    /// Hyperlinks (OSC 8)
     #[serde(default)]
-    pub colors: ColorScheme,
-
-    /// Shell command (None = use $SHELL)
+    pub hyperlinks: Option<OscPolicy>,
+    /// Notifications (OSC 9 and 777)
     #[serde(default)]
-    pub shell: Option<String>,
-
-    /// Cursor style (block, underline, bar)
-    #[serde(default = "default_cursor_style")]
-    pub cursor_style: String,
+    pub notifications: Option<OscPolicy>,
+    /// Palette, foreground, background and cursor colors (OSC 4, 10, 11
+    /// and 12)
+    #[serde(default)]
+    pub colors: Option<OscPolicy>,
+    /// Clipboard writes (OSC 52)
+    #[serde(default)]
+    pub clipboard_write: Option<OscPolicy>,
+    /// Clipboard reads (`OSC 52 ; c ; ?`), which let programs see what was
+    /// copied
+    #[serde(default)]
+    pub clipboard_read: Option<OscPolicy>,
+    /// Inline images (OSC 1337 File=)
+    #[serde(default)]
+    pub images: Option<OscPolicy>,
+}
 
-    /// Cursor blink
-    #[serde(default = "default_true")]
-    pub cursor_blink: bool,
+/// What happens to control characters in pasted text
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum PasteControls {
+    /// Remove them (default)
+    #[default]
+    Strip,
+    /// Replace them with caret notation (`^[`), as visible text
+    Escape,
+    /// Send them unchanged
+    Keep,
+}
 
-    /// Keybindings
+/// Paste handling
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PasteConfig {
+    /// Control characters (ESC, other C0 and C1 controls, DEL) in pasted
+    /// text; tabs and line breaks are always kept
     #[serde(default)]
-    pub keybindings: KeybindingsConfig,
+    pub control_characters: PasteControls,
+    /// Ask before pasting more than this many kilobytes (0 = never)
+    #[serde(default = "default_paste_confirm_kb")]
+    pub confirm_above_kb: usize,
+    /// Ask before pasting several lines into a shell that has not turned on
+    /// bracketed paste, where each line would run as a command
+    #[serde(default = "default_true")]
+    pub confirm_multiline: bool,
+}
 
-    /// Security settings
-    #[serde(default)]
-    pub security: SecurityConfig,
+fn default_paste_confirm_kb() -> usize {
+    64
+}
 
-    // Legacy fields for backwards compatibility
-    #[serde(skip_serializing, default)]
-    font_family: Option<String>,
-    #[serde(skip_serializing, default)]
-    font_size: Option<f32>,
-    #[serde(skip_serializing, default)]
-    osc52_clipboard: Option<bool>,
-    #[serde(skip_serializing, default)]
-    osc52_max_size: Option<usize>,
+impl Default for PasteConfig {
+    fn default() -> Self {
+        Self {
+            control_characters: PasteControls::Strip,
+            confirm_above_kb: default_paste_confirm_kb(),
+            confirm_multiline: true,
+        }
+    }
 }
 
-fn default_scrollback_lines() -> usize {
-    10000
+/// Clipboard history (copies made in mochi, for the history picker)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClipboardHistoryConfig {
+    /// Copies kept (0 turns the history off)
+    #[serde(default = "default_clipboard_history_entries")]
+    pub entries: usize,
+    /// Total size of the kept copies in kilobytes
+    #[serde(default = "default_clipboard_history_max_kb")]
+    pub max_kb: usize,
 }
-fn default_dimensions() -> (u16, u16) {
-    (80, 24)
+
+fn default_clipboard_history_entries() -> usize {
+    20
 }
-fn default_cursor_style() -> String {
-    "block".to_string()
+fn default_clipboard_history_max_kb() -> usize {
+    1024
 }
 
-impl Default for Config {
+impl Default for ClipboardHistoryConfig {
     fn default() -> Self {
         Self {
-            font: FontConfig::default(),
-            scrollback_lines: default_scrollback_lines(),
-            dimensions: default_dimensions(),
-            theme: ThemeName::Mochi,
-            colors: ColorScheme::default(),
-            shell: None,
-            cursor_style: default_cursor_style(),
-            cursor_blink: true,
-            keybindings: KeybindingsConfig::default(),
-            security: SecurityConfig::default(),
-            font_family: None,
-            font_size: None,
-            osc52_clipboard: None,
-            osc52_max_size: None,
+            entries: default_clipboard_history_entries(),
+            max_kb: default_clipboard_history_max_kb(),
         }
     }
 }
 
-/// Color scheme configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ColorScheme {
-    /// Foreground color (hex)
-    pub foreground: String,
-    /// Background color (hex)
-    pub background: String,
-    /// Cursor color (hex)
-    pub cursor: String,
-    /// Selection color (hex)
-    pub selection: String,
-    /// ANSI colors 0-15 (hex)
-    pub ansi: [String; 16],
+/// Activity and silence monitors (toggled per tab with Ctrl+Shift+A)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MonitorConfig {
+    /// Seconds without output before a silence monitor fires
+    #[serde(default = "default_silence_seconds")]
+    pub silence_seconds: u64,
+    /// Show a toast when a monitor fires
+    #[serde(default = "default_true")]
+    pub notify: bool,
 }
 
-impl Default for ColorScheme {
+fn default_silence_seconds() -> u64 {
+    30
+}
+
+fn default_close_grace_ms() -> u64 {
+    terminal_pty::DEFAULT_GRACE_PERIOD.as_millis() as u64
+}
+
+impl Default for MonitorConfig {
     fn default() -> Self {
         Self {
-            foreground: "#d4d4d4".to_string(),
-            background: "#1e1e1e".to_string(),
-            cursor: "#ffffff".to_string(),
-            selection: "#264f78".to_string(),
-            ansi: [
-                "#000000".to_string(), // Black
-                "#cd3131".to_string(), // Red
-                "#0dbc79".to_string(), // Green
-                "#e5e510".to_string(), // Yellow
-                "#2472c8".to_string(), // Blue
-                "#bc3fbc".to_string(), // Magenta
-                "#11a8cd".to_string(), // Cyan
-                "#e5e5e5".to_string(), // White
-                "#666666".to_string(), // Bright Black
-                "#f14c4c".to_string(), // Bright Red
-                "#23d18b".to_string(), // Bright Green
-                "#f5f543".to_string(), // Bright Yellow
-                "#3b8eea".to_string(), // Bright Blue
-                "#d670d6".to_string(), // Bright Magenta
-                "#29b8db".to_string(), // Bright Cyan
-                "#ffffff".to_string(), // Bright White
-            ],
+            silence_seconds: default_silence_seconds(),
+            notify: true,
         }
     }
 }
 
-/// Configuration error
-#[derive(Debug, Clone)]
-pub struct ConfigError {
-    pub message: String,
-    pub field: Option<String>,
+/// A named set of settings for the shells of new tabs
+///
+/// Unset fields fall back to the top-level settings; `env` is added to the
+/// top-level `env`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProfileConfig {
+    /// Shell command (None = the top-level `shell`)
+    #[serde(default)]
+    pub shell: Option<ShellCommand>,
+    /// Directory the shell starts in
+    #[serde(default)]
+    pub working_directory: Option<PathBuf>,
+    /// Environment variables set for the shell
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    /// Theme shown while a tab with this profile is active
+    #[serde(default)]
+    pub theme: Option<ThemeName>,
+    /// Font size of tabs with this profile, in points
+    #[serde(default)]
+    pub font_size: Option<f32>,
 }
 
-impl std::fmt::Display for ConfigError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        if let Some(field) = &self.field {
-            write!(f, "Config error in '{}': {}", field, self.message)
-        } else {
-            write!(f, "Config error: {}", self.message)
+/// SSH helpers
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SshConfig {
+    /// TERM the shell integration's `ssh` wrapper gives remote sessions
+    /// (as `MOCHI_SSH_TERM`); empty keeps the local TERM
+    #[serde(default = "default_term")]
+    pub term: String,
+    /// Point out the shell integration installer when ssh starts in a tab
+    #[serde(default = "default_true")]
+    pub offer_integration: bool,
+}
+
+fn default_term() -> String {
+    DEFAULT_TERM.to_string()
+}
+
+impl Default for SshConfig {
+    fn default() -> Self {
+        Self {
+            term: default_term(),
+            offer_integration: true,
         }
     }
 }
 
-impl std::error::Error for ConfigError {}
+/// Control socket configuration
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ControlConfig {
+    /// Listen for JSON-RPC requests on a unix socket (disabled by default)
+    #[serde(default)]
+    pub enabled: bool,
+    /// Socket path (defaults to a per-process socket in the runtime directory)
+    #[serde(default)]
+    pub path: Option<PathBuf>,
+}
+
+/// Priority of new shells and commands
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PriorityConfig {
+    /// Nice level, from -20 (highest) to 19 (lowest; None = mochi's own)
+    #[serde(default)]
+    pub nice: Option<i32>,
+    /// I/O scheduling class on Linux (None = mochi's own)
+    #[serde(default)]
+    pub io_class: Option<IoClass>,
+    /// Level in the best-effort class, from 0 (highest) to 7 (lowest)
+    #[serde(default = "default_io_level")]
+    pub io_level: u8,
+}
+
+fn default_io_level() -> u8 {
+    4
+}
+
+impl Default for PriorityConfig {
+    fn default() -> Self {
+        Self {
+            nice: None,
+            io_class: None,
+            io_level: default_io_level(),
+        }
+    }
+}
+
+impl PriorityConfig {
+    pub fn to_priority(&self) -> Priority {
+        Priority {
+            nice: self.nice,
+            io: self.io_class.map(|class| match class {
+                IoClass::Normal => IoPriority::Normal,
+                IoClass::BestEffort => IoPriority::BestEffort(self.io_level),
+                IoClass::Idle => IoPriority::Idle,
+            }),
+        }
+    }
+}
+
+/// Memory budgets for all windows together, in megabytes (0 = no budget)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MemoryConfig {
+    /// Scrollback of all tabs; the largest histories lose their oldest lines
+    #[serde(default)]
+    pub scrollback_mb: usize,
+    /// Inline images of all tabs; the largest stores lose their oldest images
+    #[serde(default)]
+    pub images_mb: usize,
+    /// Glyph cache of each window; emptied when it grows past the budget
+    #[serde(default)]
+    pub glyph_cache_mb: usize,
+}
+
+/// Largest memory budget accepted, in megabytes (1 TiB)
+const MAX_MEMORY_BUDGET_MB: usize = 1024 * 1024;
+
+impl MemoryConfig {
+    /// A budget in bytes, or None for no budget
+    pub fn budget(mb: usize) -> Option<usize> {
+        (mb > 0).then(|| mb * 1024 * 1024)
+    }
+}
+
+/// Systemd scopes for the shells of tabs (Linux)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScopeConfig {
+    /// Start each tab's shell in a transient scope unit (disabled by default)
+    #[serde(default)]
+    pub enabled: bool,
+    /// Unit properties limiting the scope, e.g. "MemoryMax=4G"
+    #[serde(default)]
+    pub properties: Vec<String>,
+}
+
+/// Scopes created by this process, for unique unit names
+static SCOPES_CREATED: AtomicUsize = AtomicUsize::new(0);
+
+impl ScopeConfig {
+    /// A scope with a new unit name, or None if scopes are disabled or
+    /// systemd cannot create them
+    pub fn new_scope(&self) -> Option<SystemdScope> {
+        if !self.enabled {
+            return None;
+        }
+        if !SystemdScope::is_available() {
+            log::warn!("systemd-run --user is not available, starting the shell without a scope");
+            return None;
+        }
+        let n = SCOPES_CREATED.fetch_add(1, Ordering::Relaxed) + 1;
+        let mut scope = SystemdScope::new(scope_unit(std::process::id(), n));
+        scope.properties = self.properties.clone();
+        Some(scope)
+    }
+}
+
+/// Unit name of the `n`th scope of a mochi process
+fn scope_unit(pid: u32, n: usize) -> String {
+    format!("mochi-{}-{}", pid, n)
+}
+
+/// Screenshot configuration
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScreenshotConfig {
+    /// Draw the cursor in screenshots
+    #[serde(default = "default_true")]
+    pub cursor: bool,
+    /// Draw the selection in screenshots
+    #[serde(default = "default_true")]
+    pub selection: bool,
+}
+
+impl Default for ScreenshotConfig {
+    fn default() -> Self {
+        Self {
+            cursor: true,
+            selection: true,
+        }
+    }
+}
+
+/// Cursor animation configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CursorAnimationConfig {
+    /// Smoothly move the cursor between cells (disabled by default)
+    #[serde(default)]
+    pub enabled: bool,
+    /// Duration of a cursor move in milliseconds
+    #[serde(default = "default_cursor_animation_duration")]
+    pub duration_ms: u32,
+    /// Draw a fading trail behind the moving cursor
+    #[serde(default)]
+    pub trail: bool,
+}
+
+fn default_cursor_animation_duration() -> u32 {
+    80
+}
+
+impl Default for CursorAnimationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            duration_ms: default_cursor_animation_duration(),
+            trail: false,
+        }
+    }
+}
+
+/// A user pattern for hint mode
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HintPattern {
+    /// Name for documentation and logs
+    #[serde(default)]
+    pub name: String,
+    /// Regular expression to match within a row
+    pub regex: String,
+}
+
+/// Hint mode (quick select) configuration
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HintsConfig {
+    /// Characters used for hint labels
+    #[serde(default = "default_hint_alphabet")]
+    pub alphabet: String,
+    /// Patterns matched in addition to URLs, paths, hashes and IP addresses
+    #[serde(default)]
+    pub patterns: Vec<HintPattern>,
+}
+
+fn default_hint_alphabet() -> String {
+    "asdfghjklqwertyuiopzxcvbnm".to_string()
+}
+
+impl Default for HintsConfig {
+    fn default() -> Self {
+        Self {
+            alphabet: default_hint_alphabet(),
+            patterns: Vec::new(),
+        }
+    }
+}
+
+/// Event that fires a hook
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum HookEvent {
+    /// The program rang the bell
+    Bell,
+    /// The program changed the title
+    Title,
+    /// The shell or command exited
+    Exit,
+    /// The program printed a line matching `pattern`
+    Output,
+    /// A tab's activity monitor fired
+    Activity,
+    /// A tab's silence monitor fired
+    Silence,
+}
+
+/// A user command or notification run on an event
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HookConfig {
+    pub event: HookEvent,
+    /// Regular expression the title, exit status or output line must match
+    /// (required for output hooks)
+    #[serde(default)]
+    pub pattern: Option<String>,
+    /// Command run with `/bin/sh -c`
+    #[serde(default)]
+    pub command: Option<String>,
+    /// Show a toast in the window
+    #[serde(default)]
+    pub notify: bool,
+    /// Shortest time between two firings of this hook, in milliseconds
+    #[serde(default = "default_hook_interval")]
+    pub min_interval_ms: u64,
+}
+
+fn default_hook_interval() -> u64 {
+    1000
+}
+
+/// A rule that colors output matching a pattern
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HighlightRule {
+    /// Regular expression matched within each line as it is completed
+    pub pattern: String,
+    /// Text color: hex ("#ff5555") or an ANSI color name ("red", "bright-red")
+    #[serde(default)]
+    pub foreground: Option<String>,
+    /// Background color, like `foreground`
+    #[serde(default)]
+    pub background: Option<String>,
+    /// Color the whole line instead of just the match
+    #[serde(default)]
+    pub whole_line: bool,
+}
+
+/// What an opener rule does with a match
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OpenerAction {
+    /// Open with the desktop's default handler (xdg-open, or open on macOS)
+    Open,
+    /// Run `command`
+    Command,
+    /// Copy to the clipboard
+    Copy,
+}
+
+/// A rule for what Ctrl+click and open hints do with a match
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OpenerRule {
+    /// Regular expression the clicked link or hint must match
+    pub pattern: String,
+    #[serde(default = "default_opener_action")]
+    pub action: OpenerAction,
+    /// Program and arguments for `command`, with `{0}` and `{name}`
+    /// replaced by the match and its groups and `$NAME` arguments by
+    /// environment variables
+    #[serde(default)]
+    pub command: Vec<String>,
+}
+
+fn default_opener_action() -> OpenerAction {
+    OpenerAction::Open
+}
+
+fn default_openers() -> Vec<OpenerRule> {
+    vec![OpenerRule {
+        pattern: "^(?:https?|file)://".to_string(),
+        action: OpenerAction::Open,
+        command: Vec::new(),
+    }]
+}
+
+/// A rule that gives matching tabs an accent color
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TabAccentRule {
+    /// Regular expression matched against the tab title
+    pub pattern: String,
+    /// Accent color: hex ("#ff5555") or an ANSI color name ("red", "bright-red")
+    pub color: String,
+}
+
+/// Flow control between the PTY and the parser
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FlowControlConfig {
+    /// Maximum bytes read from each tab per event loop pass
+    #[serde(default = "default_read_limit")]
+    pub read_limit: usize,
+    /// Skip frames while more than this many bytes are waiting (0 = never)
+    #[serde(default)]
+    pub skip_frames_above: usize,
+}
+
+fn default_read_limit() -> usize {
+    256 * 1024
+}
+
+impl Default for FlowControlConfig {
+    fn default() -> Self {
+        Self {
+            read_limit: default_read_limit(),
+            skip_frames_above: 0,
+        }
+    }
+}
+
+/// Input latency (`[latency]`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LatencyConfig {
+    /// Draw characters typed in ssh sessions before the remote echo arrives
+    #[serde(default)]
+    pub predictive_echo: bool,
+    /// Only predict in tabs whose median key-to-screen latency is at least this
+    #[serde(default = "default_prediction_threshold_ms")]
+    pub prediction_threshold_ms: u64,
+    /// Stop predicting while more than this many predictions in the last
+    /// minute turned out wrong
+    #[serde(default = "default_misprediction_limit")]
+    pub misprediction_limit: usize,
+}
+
+fn default_prediction_threshold_ms() -> u64 {
+    30
+}
+
+fn default_misprediction_limit() -> usize {
+    5
+}
+
+impl Default for LatencyConfig {
+    fn default() -> Self {
+        Self {
+            predictive_echo: false,
+            prediction_threshold_ms: default_prediction_threshold_ms(),
+            misprediction_limit: default_misprediction_limit(),
+        }
+    }
+}
+
+/// The status bar below the terminal
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StatusBarConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Template for the left side (see `status_bar` for the syntax)
+    #[serde(default = "default_status_left")]
+    pub left: String,
+    /// Template for the right side
+    #[serde(default = "default_status_right")]
+    pub right: String,
+    /// Command printing the git branch, run in the tab's directory
+    #[serde(default = "default_git_command")]
+    pub git_command: String,
+}
+
+fn default_status_left() -> String {
+    "{title}".to_string()
+}
+fn default_status_right() -> String {
+    "[{scroll}  ][{git}  ][{cwd}  ]{clock}".to_string()
+}
+fn default_git_command() -> String {
+    "git rev-parse --abbrev-ref HEAD".to_string()
+}
+
+impl Default for StatusBarConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            left: default_status_left(),
+            right: default_status_right(),
+            git_command: default_git_command(),
+        }
+    }
+}
+
+/// Terminal configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Config {
+    /// Font configuration
+    #[serde(default)]
+    pub font: FontConfig,
+
+    /// Number of scrollback lines
+    #[serde(default = "default_scrollback_lines")]
+    pub scrollback_lines: usize,
+
+    /// Window dimensions (columns, rows)
+    #[serde(default = "default_dimensions")]
+    pub dimensions: (u16, u16),
+
+    /// Theme name (a built-in theme or a file in the themes directory)
+    #[serde(default)]
+    pub theme: ThemeName,
+
+    /// Custom color scheme (used when theme is "custom")
+    #[serde(default)]
+    pub colors: ColorScheme,
+
+    /// Shell command (None = use $SHELL)
+    #[serde(default)]
+    pub shell: Option<ShellCommand>,
+
+    /// Command the first tab runs instead of the shell (None = the shell)
+    #[serde(default)]
+    pub startup_command: Option<StartupCommand>,
+
+    /// TERM given to shells and commands, if this host has its terminfo
+    /// entry (otherwise xterm-256color); `env.TERM` overrides it
+    #[serde(default = "default_term")]
+    pub term: String,
+
+    /// Environment variables set for shells and commands
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+
+    /// Environment variables removed for shells and commands
+    #[serde(default)]
+    pub env_remove: Vec<String>,
+
+    /// Directory new shells start in (None = home directory)
+    #[serde(default)]
+    pub working_directory: Option<PathBuf>,
+
+    /// Named shell, directory, environment, theme and font size settings
+    /// that tabs can be opened with
+    #[serde(default)]
+    pub profiles: BTreeMap<String, ProfileConfig>,
+
+    /// Profile of the first tab and of new tabs (None = the top-level
+    /// settings)
+    #[serde(default)]
+    pub profile: Option<String>,
+
+    /// Cursor style (block, underline, bar)
+    #[serde(default = "default_cursor_style")]
+    pub cursor_style: String,
+
+    /// Cursor blink
+    #[serde(default = "default_true")]
+    pub cursor_blink: bool,
+
+    /// Cursor animation
+    #[serde(default)]
+    pub cursor_animation: CursorAnimationConfig,
+
+    /// PTY read throttling
+    #[serde(default)]
+    pub flow_control: FlowControlConfig,
+
+    /// Key-to-screen latency and predictive echo
+    #[serde(default)]
+    pub latency: LatencyConfig,
+
+    /// Status bar below the terminal
+    #[serde(default)]
+    pub status_bar: StatusBarConfig,
+
+    /// Space between the window edges and the grid
+    #[serde(default)]
+    pub padding: PaddingConfig,
+
+    /// Hint mode patterns and labels
+    #[serde(default)]
+    pub hints: HintsConfig,
+
+    /// Activity and silence monitors
+    #[serde(default)]
+    pub monitor: MonitorConfig,
+
+    /// SSH helpers
+    #[serde(default)]
+    pub ssh: SshConfig,
+
+    /// Commands and notifications run on terminal events
+    #[serde(default)]
+    pub hooks: Vec<HookConfig>,
+
+    /// Rules that color matching output
+    #[serde(default)]
+    pub highlights: Vec<HighlightRule>,
+
+    /// Rules that give tabs an accent color by title
+    #[serde(default)]
+    pub tab_accents: Vec<TabAccentRule>,
+
+    /// Rules for what Ctrl+click and open hints do with a match
+    #[serde(default = "default_openers")]
+    pub openers: Vec<OpenerRule>,
+
+    /// Highest frame rate (0 = the monitor's refresh rate)
+    #[serde(default)]
+    pub max_fps: u32,
+
+    /// Minimum contrast ratio between text and its background (1.0 = off)
+    #[serde(default = "default_minimum_contrast")]
+    pub minimum_contrast: f32,
+
+    /// How far the grid fades towards the background while it is not
+    /// focused (0.0 = off, 1.0 = fully faded)
+    #[serde(default)]
+    pub dim_unfocused: f32,
+
+    /// Keybindings
+    #[serde(default)]
+    pub keybindings: KeybindingsConfig,
+
+    /// Security settings
+    #[serde(default)]
+    pub security: SecurityConfig,
+
+    /// Paste sanitization and confirmation
+    #[serde(default)]
+    pub paste: PasteConfig,
+
+    /// Recent copies for the history picker
+    #[serde(default)]
+    pub clipboard_history: ClipboardHistoryConfig,
+
+    /// JSON-RPC control socket (read at startup)
+    #[serde(default)]
+    pub control: ControlConfig,
+
+    /// What screenshots include
+    #[serde(default)]
+    pub screenshot: ScreenshotConfig,
+
+    /// Nice level and I/O priority of new shells
+    #[serde(default)]
+    pub priority: PriorityConfig,
+
+    /// Run each tab's shell in a systemd scope with resource limits
+    #[serde(default)]
+    pub scope: ScopeConfig,
+
+    /// Budgets for the scrollback, images and glyph caches
+    #[serde(default)]
+    pub memory: MemoryConfig,
+
+    /// Open new launches as windows of the running mochi (read at startup)
+    #[serde(default)]
+    pub single_instance: bool,
+
+    /// Translate the mouse wheel to arrow keys on the alternate screen
+    /// (initial state of DEC mode 1007)
+    #[serde(default = "default_true")]
+    pub alternate_scroll: bool,
+
+    /// Copy the alternate screen into the scrollback when a full-screen app
+    /// exits
+    #[serde(default)]
+    pub capture_alternate_screen: bool,
+
+    /// Answer XTVERSION (`CSI > q`) as xterm, for programs that only
+    /// enable features for terminals they recognize
+    #[serde(default)]
+    pub identify_as_xterm: bool,
+
+    /// Save open tabs on exit and restore them at the next startup
+    #[serde(default)]
+    pub restore_session: bool,
+
+    /// What happens to a tab when its shell exits
+    #[serde(default)]
+    pub exit_behavior: ExitBehavior,
+
+    /// When to ask before closing a tab or the window
+    #[serde(default)]
+    pub confirm_close: CloseConfirmation,
+
+    /// How long the processes of a closed tab get after SIGHUP (and then
+    /// after SIGTERM) before the next signal, in milliseconds
+    #[serde(default = "default_close_grace_ms")]
+    pub close_grace_ms: u64,
+
+    /// Apply changes to the config file and theme files as soon as they are saved
+    #[serde(default = "default_true")]
+    pub live_reload: bool,
+
+    /// Hide the mouse pointer while typing, until the mouse moves
+    #[serde(default = "default_true")]
+    pub hide_mouse_while_typing: bool,
+
+    /// Put selections on the PRIMARY selection and paste it with the middle
+    /// button (X11 / Wayland; separate from the clipboard)
+    #[serde(default = "default_true")]
+    pub primary_selection: bool,
+
+    /// Focus the terminal when the mouse moves over it, without clicking
+    #[serde(default)]
+    pub focus_follows_mouse: bool,
+
+    /// Dropping a single directory onto the terminal inserts `cd <dir>`
+    /// instead of just its path
+    #[serde(default)]
+    pub drop_directory_cd: bool,
+
+    /// Format of modified keys once an application turns on modifyOtherKeys
+    #[serde(default)]
+    pub format_other_keys: OtherKeysFormat,
+
+    /// Session file given on the command line (not read from the config file)
+    #[serde(skip)]
+    pub session_file: Option<PathBuf>,
+
+    /// Config file this configuration was loaded from (and is reloaded from)
+    #[serde(skip)]
+    pub config_file: Option<PathBuf>,
+
+    /// Command given with --command (not read from the config file)
+    #[serde(skip)]
+    pub command: Option<String>,
+
+    /// Trace file given with --trace (not read from the config file)
+    #[serde(skip)]
+    pub trace_file: Option<PathBuf>,
+
+    // Legacy fields for backwards compatibility
+    #[serde(skip_serializing, default)]
+    font_family: Option<String>,
+    #[serde(skip_serializing, default)]
+    font_size: Option<f32>,
+    #[serde(skip_serializing, default)]
+    osc52_clipboard: Option<bool>,
+    #[serde(skip_serializing, default)]
+    osc52_max_size: Option<usize>,
+}
+
+fn default_scrollback_lines() -> usize {
+    10000
+}
+fn default_dimensions() -> (u16, u16) {
+    (80, 24)
+}
+fn default_cursor_style() -> String {
+    "block".to_string()
+}
+fn default_minimum_contrast() -> f32 {
+    CONTRAST_DISABLED
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            font: FontConfig::default(),
+            scrollback_lines: default_scrollback_lines(),
+            dimensions: default_dimensions(),
+            theme: ThemeName::Mochi,
+            colors: ColorScheme::default(),
+            shell: None,
+            startup_command: None,
+            term: default_term(),
+            env: BTreeMap::new(),
+            env_remove: Vec::new(),
+            working_directory: None,
+            profiles: BTreeMap::new(),
+            profile: None,
+            cursor_style: default_cursor_style(),
+            cursor_blink: true,
+            cursor_animation: CursorAnimationConfig::default(),
+            flow_control: FlowControlConfig::default(),
+            latency: LatencyConfig::default(),
+            status_bar: StatusBarConfig::default(),
+            monitor: MonitorConfig::default(),
+            ssh: SshConfig::default(),
+            padding: PaddingConfig::default(),
+            hints: HintsConfig::default(),
+            hooks: Vec::new(),
+            highlights: Vec::new(),
+            tab_accents: Vec::new(),
+            openers: default_openers(),
+            max_fps: 0,
+            minimum_contrast: default_minimum_contrast(),
+            dim_unfocused: 0.0,
+            keybindings: KeybindingsConfig::default(),
+            security: SecurityConfig::default(),
+            paste: PasteConfig::default(),
+            clipboard_history: ClipboardHistoryConfig::default(),
+            control: ControlConfig::default(),
+            screenshot: ScreenshotConfig::default(),
+            priority: PriorityConfig::default(),
+            scope: ScopeConfig::default(),
+            memory: MemoryConfig::default(),
+            single_instance: false,
+            alternate_scroll: true,
+            capture_alternate_screen: false,
+            identify_as_xterm: false,
+            restore_session: false,
+            exit_behavior: ExitBehavior::Close,
+            confirm_close: CloseConfirmation::Running,
+            close_grace_ms: default_close_grace_ms(),
+            live_reload: true,
+            hide_mouse_while_typing: true,
+            primary_selection: true,
+            focus_follows_mouse: false,
+            drop_directory_cd: false,
+            format_other_keys: OtherKeysFormat::Xterm,
+            session_file: None,
+            config_file: None,
+            command: None,
+            trace_file: None,
+            font_family: None,
+            font_size: None,
+            osc52_clipboard: None,
+            osc52_max_size: None,
+        }
+    }
+}
+
+/// Color scheme configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ColorScheme {
+    /// Foreground color (hex)
+    pub foreground: String,
+    /// Background color (hex)
+    pub background: String,
+    /// Cursor color (hex)
+    pub cursor: String,
+    /// Selection color (hex)
+    pub selection: String,
+    /// ANSI colors 0-15 (hex)
+    pub ansi: [String; 16],
+}
+
+impl Default for ColorScheme {
+    fn default() -> Self {
+        Self {
+            foreground: "#d4d4d4".to_string(),
+            background: "#1e1e1e".to_string(),
+            cursor: "#ffffff".to_string(),
+            selection: "#264f78".to_string(),
+            ansi: [
+                "#000000".to_string(), // Black
+                "#cd3131".to_string(), // Red
+                "#0dbc79".to_string(), // Green
+                "#e5e510".to_string(), // Yellow
+                "#2472c8".to_string(), // Blue
+                "#bc3fbc".to_string(), // Magenta
+                "#11a8cd".to_string(), // Cyan
+                "#e5e5e5".to_string(), // White
+                "#666666".to_string(), // Bright Black
+                "#f14c4c".to_string(), // Bright Red
+                "#23d18b".to_string(), // Bright Green
+                "#f5f543".to_string(), // Bright Yellow
+                "#3b8eea".to_string(), // Bright Blue
+                "#d670d6".to_string(), // Bright Magenta
+                "#29b8db".to_string(), // Bright Cyan
+                "#ffffff".to_string(), // Bright White
+            ],
+        }
+    }
+}
+
+/// Configuration error
+#[derive(Debug, Clone)]
+pub struct ConfigError {
+    pub message: String,
+    pub field: Option<String>,
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(field) = &self.field {
+            write!(f, "Config error in '{}': {}", field, self.message)
+        } else {
+            write!(f, "Config error: {}", self.message)
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
 
 impl Config {
     /// Load configuration with full precedence:
@@ -497,6 +1784,8 @@ impl Config {
             }
         }
 
+        config.config_file = config_path;
+
         // Apply environment variables
         config.apply_env_vars();
 
@@ -506,16 +1795,77 @@ impl Config {
         // Validate the final configuration
         config.validate()?;
 
+        if !config.env.contains_key("TERM") && config.term_name() != config.term {
+            log::warn!(
+                "No terminfo entry for TERM '{}', using {} (install it with `mochi --emit-terminfo | tic -x -`)",
+                config.term,
+                DEFAULT_TERM
+            );
+        }
+
         Ok(config)
     }
 
-    /// Load configuration from file only (legacy method)
-    pub fn load() -> Option<Self> {
-        let config_path = Self::default_config_path()?;
-        if !config_path.exists() {
-            return None;
+    /// Load the config file again (for Ctrl+Shift+R and live reload)
+    ///
+    /// Only the file is read; CLI and environment overrides are not reapplied.
+    pub fn reload(&self) -> Result<Self, ConfigError> {
+        let path = self
+            .config_file
+            .clone()
+            .or_else(Self::default_config_path)
+            .ok_or_else(|| ConfigError {
+                message: "Could not determine config path".to_string(),
+                field: None,
+            })?;
+        let mut config = Self::load_from_file(&path)?;
+        config.validate()?;
+        config.config_file = Some(path);
+        Ok(config)
+    }
+
+    /// This configuration with some settings replaced, validated
+    ///
+    /// `overrides` is a JSON object shaped like the config file, e.g.
+    /// `{"theme": "dark", "font": {"size": 16}}`; nested tables are merged.
+    pub fn with_overrides(&self, overrides: &serde_json::Value) -> Result<Self, ConfigError> {
+        fn merge(base: &mut serde_json::Value, overrides: &serde_json::Value) {
+            match (base, overrides) {
+                (serde_json::Value::Object(base), serde_json::Value::Object(overrides)) => {
+                    for (key, value) in overrides {
+                        match base.get_mut(key) {
+                            Some(existing) => merge(existing, value),
+                            None => {
+                                base.insert(key.clone(), value.clone());
+                            }
+                        }
+                    }
+                }
+                (base, value) => *base = value.clone(),
+            }
+        }
+
+        if !overrides.is_object() {
+            return Err(ConfigError {
+                message: "Settings must be an object".to_string(),
+                field: None,
+            });
         }
-        Self::load_from_file(&config_path).ok()
+        let mut value = serde_json::to_value(self).map_err(|e| ConfigError {
+            message: format!("Failed to serialize config: {}", e),
+            field: None,
+        })?;
+        merge(&mut value, overrides);
+        let mut config: Config = serde_json::from_value(value).map_err(|e| ConfigError {
+            message: format!("Invalid settings: {}", e),
+            field: None,
+        })?;
+        config.validate()?;
+        config.session_file = self.session_file.clone();
+        config.config_file = self.config_file.clone();
+        config.command = self.command.clone();
+        config.trace_file = self.trace_file.clone();
+        Ok(config)
     }
 
     /// Load configuration from a specific file
@@ -563,12 +1913,10 @@ impl Config {
             }
         }
         if let Ok(val) = env::var("MOCHI_THEME") {
-            if let Some(theme) = ThemeName::from_str(&val) {
-                self.theme = theme;
-            }
+            self.theme = ThemeName::parse(&val);
         }
         if let Ok(val) = env::var("MOCHI_SHELL") {
-            self.shell = Some(val);
+            self.shell = Some(ShellCommand::Program(val));
         }
         if let Ok(val) = env::var("MOCHI_SCROLLBACK") {
             if let Ok(lines) = val.parse() {
@@ -589,12 +1937,24 @@ impl Config {
             self.font.size = size;
         }
         if let Some(theme_str) = &args.theme {
-            if let Some(theme) = ThemeName::from_str(theme_str) {
-                self.theme = theme;
-            }
+            self.theme = ThemeName::parse(theme_str);
         }
         if let Some(shell) = &args.shell {
-            self.shell = Some(shell.clone());
+            self.shell = Some(ShellCommand::from_arg(shell));
+        }
+        for var in &args.env {
+            match var.split_once('=') {
+                Some((key, value)) => {
+                    self.env.insert(key.to_string(), value.to_string());
+                }
+                None => log::warn!("Ignoring --env {:?}: expected KEY=VALUE", var),
+            }
+        }
+        if let Some(dir) = &args.working_directory {
+            self.working_directory = Some(dir.clone());
+        }
+        if let Some(profile) = &args.profile {
+            self.profile = Some(profile.clone());
         }
         if let Some(scrollback) = args.scrollback {
             self.scrollback_lines = scrollback;
@@ -608,10 +1968,198 @@ impl Config {
         if args.enable_osc52 {
             self.security.osc52_clipboard = true;
         }
+        if let Some(session) = &args.session {
+            self.session_file = Some(session.clone());
+        }
+        if let Some(command) = &args.command {
+            self.command = Some(command.clone());
+        }
+        if let Some(command) = StartupCommand::from_args(&args.execute) {
+            self.startup_command = Some(command);
+        }
+        if let Some(trace) = &args.trace {
+            self.trace_file = Some(trace.clone());
+        }
+    }
+
+    /// Build the command for a new shell
+    ///
+    /// Uses `shell` (or $SHELL as a login shell) with `env`, `env_remove` and
+    /// `working_directory` applied.
+    pub fn shell_command(&self) -> CommandBuilder {
+        let builder = self
+            .shell
+            .as_ref()
+            .and_then(ShellCommand::to_command)
+            .unwrap_or_else(CommandBuilder::default_shell);
+        self.with_environment(builder)
+    }
+
+    /// Build the command for a new shell with a profile
+    ///
+    /// The profile's `shell`, `env` and `working_directory` take precedence
+    /// over the top-level ones. An unknown profile is ignored.
+    pub fn profile_shell_command(&self, name: Option<&str>) -> CommandBuilder {
+        let Some(profile) = name.and_then(|name| self.profiles.get(name)) else {
+            return self.shell_command();
+        };
+        let builder = profile
+            .shell
+            .as_ref()
+            .or(self.shell.as_ref())
+            .and_then(ShellCommand::to_command)
+            .unwrap_or_else(CommandBuilder::default_shell);
+        self.with_profile_environment(builder, name)
+    }
+
+    /// Build a startup command (`startup_command`, or one forwarded by
+    /// another launch) with a profile's environment
+    pub fn startup_shell_command(
+        &self,
+        command: Option<&StartupCommand>,
+        profile: Option<&str>,
+    ) -> Option<CommandBuilder> {
+        let builder = command?.to_command()?;
+        Some(self.with_profile_environment(builder, profile))
+    }
+
+    /// Apply the top-level environment and then a profile's `env` and
+    /// `working_directory` to a command
+    fn with_profile_environment(
+        &self,
+        builder: CommandBuilder,
+        name: Option<&str>,
+    ) -> CommandBuilder {
+        let mut builder = self.with_environment(builder);
+        if let Some(profile) = name.and_then(|name| self.profiles.get(name)) {
+            for (key, value) in &profile.env {
+                builder.env(key, value);
+            }
+            if let Some(dir) = &profile.working_directory {
+                builder.cwd(dir);
+            }
+        }
+        builder
+    }
+
+    /// Theme of tabs with a profile (the top-level theme if it sets none)
+    pub fn profile_theme(&self, name: Option<&str>) -> &ThemeName {
+        name.and_then(|name| self.profiles.get(name))
+            .and_then(|profile| profile.theme.as_ref())
+            .unwrap_or(&self.theme)
+    }
+
+    /// Font size of tabs with a profile, in points
+    pub fn profile_font_size(&self, name: Option<&str>) -> f32 {
+        name.and_then(|name| self.profiles.get(name))
+            .and_then(|profile| profile.font_size)
+            .unwrap_or(self.font.size)
+    }
+
+    /// Apply `env`, `env_remove`, `working_directory` and `priority` to a
+    /// command
+    pub fn with_environment(&self, mut builder: CommandBuilder) -> CommandBuilder {
+        builder.priority(self.priority.to_priority());
+        builder.env("TERM", self.term_name());
+        if !self.ssh.term.is_empty() {
+            builder.env("MOCHI_SSH_TERM", &self.ssh.term);
+        }
+        for (key, value) in &self.env {
+            builder.env(key, value);
+        }
+        for key in &self.env_remove {
+            builder.env_remove(key);
+        }
+        if let Some(dir) = &self.working_directory {
+            builder.cwd(dir);
+        }
+        builder
     }
 
     /// Validate configuration
     fn validate(&self) -> Result<(), ConfigError> {
+        // Validate shell
+        if self
+            .shell
+            .as_ref()
+            .is_some_and(|shell| shell.to_command().is_none())
+        {
+            return Err(ConfigError {
+                message: "Shell must name a program".to_string(),
+                field: Some("shell".to_string()),
+            });
+        }
+        if let Some(key) = self
+            .env
+            .keys()
+            .find(|key| key.is_empty() || key.contains('='))
+        {
+            return Err(ConfigError {
+                message: format!("Invalid environment variable name {:?}", key),
+                field: Some("env".to_string()),
+            });
+        }
+        if self
+            .startup_command
+            .as_ref()
+            .is_some_and(|command| command.to_command().is_none())
+        {
+            return Err(ConfigError {
+                message: "Startup command must name a program".to_string(),
+                field: Some("startup_command".to_string()),
+            });
+        }
+
+        // Validate profiles
+        for (name, profile) in &self.profiles {
+            let error = |message: String, field: &str| ConfigError {
+                message,
+                field: Some(format!("profiles.{}.{}", name, field)),
+            };
+            if profile
+                .shell
+                .as_ref()
+                .is_some_and(|shell| shell.to_command().is_none())
+            {
+                return Err(error(
+                    format!("Shell of profile '{}' must name a program", name),
+                    "shell",
+                ));
+            }
+            if let Some(key) = profile
+                .env
+                .keys()
+                .find(|key| key.is_empty() || key.contains('='))
+            {
+                return Err(error(
+                    format!("Invalid environment variable name {:?}", key),
+                    "env",
+                ));
+            }
+            if profile
+                .font_size
+                .is_some_and(|size| !(4.0..=200.0).contains(&size))
+            {
+                return Err(error(
+                    format!(
+                        "Font size of profile '{}' must be between 4.0 and 200.0",
+                        name
+                    ),
+                    "font_size",
+                ));
+            }
+        }
+        if let Some(name) = self
+            .profile
+            .as_ref()
+            .filter(|name| !self.profiles.contains_key(*name))
+        {
+            return Err(ConfigError {
+                message: format!("Unknown profile '{}'", name),
+                field: Some("profile".to_string()),
+            });
+        }
+
         // Validate font size
         if self.font.size < 4.0 {
             return Err(ConfigError {
@@ -662,6 +2210,212 @@ impl Config {
             });
         }
 
+        // Validate padding
+        for (value, field) in [(self.padding.x, "padding.x"), (self.padding.y, "padding.y")] {
+            if value > 200 {
+                return Err(ConfigError {
+                    message: "Padding must be at most 200 pixels".to_string(),
+                    field: Some(field.to_string()),
+                });
+            }
+        }
+        for (value, field) in [
+            (self.font.cell_padding_x, "font.cell_padding_x"),
+            (self.font.cell_padding_y, "font.cell_padding_y"),
+        ] {
+            if value > 50 {
+                return Err(ConfigError {
+                    message: "Cell padding must be at most 50 pixels".to_string(),
+                    field: Some(field.to_string()),
+                });
+            }
+        }
+
+        if self.close_grace_ms > 60_000 {
+            return Err(ConfigError {
+                message: "Close grace period must be at most 60000 ms".to_string(),
+                field: Some("close_grace_ms".to_string()),
+            });
+        }
+
+        // Validate cursor animation
+        if self.cursor_animation.duration_ms > 1000 {
+            return Err(ConfigError {
+                message: "Cursor animation duration must be at most 1000 ms".to_string(),
+                field: Some("cursor_animation.duration_ms".to_string()),
+            });
+        }
+
+        // Validate flow control
+        if !(4096..=64 * 1024 * 1024).contains(&self.flow_control.read_limit) {
+            return Err(ConfigError {
+                message: "Read limit must be between 4096 and 67108864 bytes".to_string(),
+                field: Some("flow_control.read_limit".to_string()),
+            });
+        }
+
+        // Validate latency
+        if self.latency.prediction_threshold_ms > 10_000 {
+            return Err(ConfigError {
+                message: "Prediction threshold must be at most 10000 ms".to_string(),
+                field: Some("latency.prediction_threshold_ms".to_string()),
+            });
+        }
+        if self.latency.misprediction_limit > 100 {
+            return Err(ConfigError {
+                message: "Misprediction limit must be at most 100".to_string(),
+                field: Some("latency.misprediction_limit".to_string()),
+            });
+        }
+
+        // Validate priority
+        if self
+            .priority
+            .nice
+            .is_some_and(|nice| !(-20..=19).contains(&nice))
+        {
+            return Err(ConfigError {
+                message: "Nice level must be between -20 and 19".to_string(),
+                field: Some("priority.nice".to_string()),
+            });
+        }
+        if self.priority.io_level > 7 {
+            return Err(ConfigError {
+                message: "I/O level must be between 0 and 7".to_string(),
+                field: Some("priority.io_level".to_string()),
+            });
+        }
+
+        // Validate memory budgets
+        for (name, mb) in [
+            ("scrollback_mb", self.memory.scrollback_mb),
+            ("images_mb", self.memory.images_mb),
+            ("glyph_cache_mb", self.memory.glyph_cache_mb),
+        ] {
+            if mb > MAX_MEMORY_BUDGET_MB {
+                return Err(ConfigError {
+                    message: format!("Memory budget must be at most {} MB", MAX_MEMORY_BUDGET_MB),
+                    field: Some(format!("memory.{}", name)),
+                });
+            }
+        }
+
+        // Validate scope properties
+        if let Some(property) = self
+            .scope
+            .properties
+            .iter()
+            .find(|property| !property.contains('='))
+        {
+            return Err(ConfigError {
+                message: format!("Scope property '{}' must be NAME=VALUE", property),
+                field: Some("scope.properties".to_string()),
+            });
+        }
+
+        // Validate device attributes
+        let attributes = &self.security.device_attributes;
+        if attributes.is_empty()
+            || attributes.len() > 64
+            || !attributes.bytes().all(|b| b.is_ascii_digit() || b == b';')
+        {
+            return Err(ConfigError {
+                message: "Device attributes must be numbers separated by ';' (at most 64 bytes)"
+                    .to_string(),
+                field: Some("security.device_attributes".to_string()),
+            });
+        }
+
+        // Validate answerback
+        if self.security.answerback.len() > 256 {
+            return Err(ConfigError {
+                message: "Answerback must be at most 256 bytes".to_string(),
+                field: Some("security.answerback".to_string()),
+            });
+        }
+
+        // Validate clipboard history
+        if self.clipboard_history.entries > 1000 {
+            return Err(ConfigError {
+                message: "Clipboard history must keep at most 1000 entries".to_string(),
+                field: Some("clipboard_history.entries".to_string()),
+            });
+        }
+
+        // Validate monitors
+        if !(1..=86400).contains(&self.monitor.silence_seconds) {
+            return Err(ConfigError {
+                message: "Silence must be between 1 and 86400 seconds".to_string(),
+                field: Some("monitor.silence_seconds".to_string()),
+            });
+        }
+
+        // Validate the TERM of shells and of ssh sessions
+        let term = &self.term;
+        if term.is_empty() || term.len() > 64 || !term.bytes().all(|b| b.is_ascii_graphic()) {
+            return Err(ConfigError {
+                message: format!("Invalid TERM '{}'", term),
+                field: Some("term".to_string()),
+            });
+        }
+        let term = &self.ssh.term;
+        if term.len() > 64 || !term.bytes().all(|b| b.is_ascii_graphic()) {
+            return Err(ConfigError {
+                message: format!("Invalid ssh TERM '{}'", term),
+                field: Some("ssh.term".to_string()),
+            });
+        }
+
+        // Validate hint labels and patterns
+        let alphabet: Vec<char> = self.hints.alphabet.chars().collect();
+        let distinct = alphabet
+            .iter()
+            .enumerate()
+            .all(|(i, c)| !alphabet[..i].contains(c));
+        if alphabet.len() < 2 || !distinct || alphabet.iter().any(|c| !c.is_lowercase()) {
+            return Err(ConfigError {
+                message: "Hint alphabet must have at least 2 distinct lowercase letters"
+                    .to_string(),
+                field: Some("hints.alphabet".to_string()),
+            });
+        }
+        hints::compile_patterns(&self.hints)?;
+        Hooks::new(&self.hooks)?;
+        HighlightRules::new(&self.highlights)?;
+        TabAccents::new(&self.tab_accents)?;
+        Openers::new(&self.openers)?;
+        Keybindings::new(&self.keybindings)?;
+        StatusBar::new(&self.status_bar)?;
+
+        // Validate frame rate
+        if self.max_fps > 1000 {
+            return Err(ConfigError {
+                message: "Max FPS must be at most 1000 (0 = monitor refresh rate)".to_string(),
+                field: Some("max_fps".to_string()),
+            });
+        }
+
+        // Validate minimum contrast
+        if !(CONTRAST_DISABLED..=MAX_CONTRAST).contains(&self.minimum_contrast) {
+            return Err(ConfigError {
+                message: "Minimum contrast must be between 1.0 and 21.0".to_string(),
+                field: Some("minimum_contrast".to_string()),
+            });
+        }
+
+        // Validate unfocused dimming
+        if !(0.0..=1.0).contains(&self.dim_unfocused) {
+            return Err(ConfigError {
+                message: "Unfocused dimming must be between 0.0 and 1.0".to_string(),
+                field: Some("dim_unfocused".to_string()),
+            });
+        }
+
+        // Validate the theme file
+        if let ThemeName::User(name) = &self.theme {
+            theme::load(name)?;
+        }
+
         // Validate colors
         self.validate_color(&self.colors.foreground, "colors.foreground")?;
         self.validate_color(&self.colors.background, "colors.background")?;
@@ -685,11 +2439,72 @@ impl Config {
         Ok(())
     }
 
+    /// Take the settings that can change at runtime from a reloaded config
+    pub fn update_from(&mut self, new_config: &Config) {
+        self.theme = new_config.theme.clone();
+        self.font = new_config.font.clone();
+        self.keybindings = new_config.keybindings.clone();
+        self.security = new_config.security.clone();
+        self.paste = new_config.paste.clone();
+        self.clipboard_history = new_config.clipboard_history.clone();
+        self.memory = new_config.memory.clone();
+        self.scrollback_lines = new_config.scrollback_lines;
+        self.cursor_animation = new_config.cursor_animation.clone();
+        self.flow_control = new_config.flow_control.clone();
+        self.latency = new_config.latency.clone();
+        self.status_bar = new_config.status_bar.clone();
+        self.monitor = new_config.monitor.clone();
+        self.ssh = new_config.ssh.clone();
+        self.profiles = new_config.profiles.clone();
+        self.hints = new_config.hints.clone();
+        self.hooks = new_config.hooks.clone();
+        self.highlights = new_config.highlights.clone();
+        self.tab_accents = new_config.tab_accents.clone();
+        self.openers = new_config.openers.clone();
+        self.padding = new_config.padding.clone();
+        self.max_fps = new_config.max_fps;
+        self.minimum_contrast = new_config.minimum_contrast;
+        self.dim_unfocused = new_config.dim_unfocused;
+        self.alternate_scroll = new_config.alternate_scroll;
+        self.capture_alternate_screen = new_config.capture_alternate_screen;
+        self.identify_as_xterm = new_config.identify_as_xterm;
+        self.exit_behavior = new_config.exit_behavior;
+        self.confirm_close = new_config.confirm_close;
+        self.close_grace_ms = new_config.close_grace_ms;
+        self.shell = new_config.shell.clone();
+        self.term = new_config.term.clone();
+        self.env = new_config.env.clone();
+        self.env_remove = new_config.env_remove.clone();
+        self.working_directory = new_config.working_directory.clone();
+        self.live_reload = new_config.live_reload;
+        self.hide_mouse_while_typing = new_config.hide_mouse_while_typing;
+        self.primary_selection = new_config.primary_selection;
+        self.focus_follows_mouse = new_config.focus_follows_mouse;
+        self.drop_directory_cd = new_config.drop_directory_cd;
+        self.format_other_keys = new_config.format_other_keys;
+    }
+
     /// Get the default configuration file path
     pub fn default_config_path() -> Option<PathBuf> {
         dirs::config_dir().map(|p| p.join("mochi").join("config.toml"))
     }
 
+    /// How long closed tabs' processes get before the next signal
+    pub fn close_grace(&self) -> Duration {
+        Duration::from_millis(self.close_grace_ms)
+    }
+
+    /// Get the session file to restore from and save to, if any
+    ///
+    /// A `--session` file takes precedence over the last-session file.
+    pub fn session_path(&self) -> Option<PathBuf> {
+        match &self.session_file {
+            Some(path) => Some(path.clone()),
+            None if self.restore_session => Session::default_path(),
+            None => None,
+        }
+    }
+
     /// Save configuration to file
     #[allow(dead_code)]
     pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
@@ -706,8 +2521,16 @@ impl Config {
     }
 
     /// Get the effective color scheme based on the theme setting
+    ///
+    /// A user theme is read from its file, falling back to the default
+    /// colors if it cannot be loaded.
     pub fn effective_colors(&self) -> ColorScheme {
-        match self.theme {
+        self.theme_colors(&self.theme)
+    }
+
+    /// Get the color scheme of a theme
+    pub fn theme_colors(&self, theme: &ThemeName) -> ColorScheme {
+        match theme {
             ThemeName::Custom => self.colors.clone(),
             ThemeName::Mochi => ColorScheme::mochi(),
             ThemeName::Dark => ColorScheme::dark(),
@@ -716,6 +2539,10 @@ impl Config {
             ThemeName::SolarizedLight => ColorScheme::solarized_light(),
             ThemeName::Dracula => ColorScheme::dracula(),
             ThemeName::Nord => ColorScheme::nord(),
+            ThemeName::User(name) => theme::load(name).unwrap_or_else(|e| {
+                log::warn!("{}", e);
+                ColorScheme::default()
+            }),
         }
     }
 
@@ -729,15 +2556,68 @@ impl Config {
         self.font.size
     }
 
-    #[allow(dead_code)] // Will be used when OSC 52 handling is implemented
-    pub fn osc52_clipboard(&self) -> bool {
-        self.security.osc52_clipboard
+    /// Policy for each OSC feature, with the older switches as defaults
+    pub fn osc_policies(&self) -> OscPolicies {
+        let security = &self.security;
+        let switch = |enabled: bool| {
+            if enabled {
+                OscPolicy::Allow
+            } else {
+                OscPolicy::Deny
+            }
+        };
+        let osc = &security.osc;
+        OscPolicies {
+            title: osc.title.unwrap_or(OscPolicy::Allow),
+            hyperlinks: osc.hyperlinks.unwrap_or(OscPolicy::Allow),
+            notifications: osc.notifications.unwrap_or(switch(security.notifications)),
+            colors: osc.colors.unwrap_or(OscPolicy::Allow),
+            clipboard_write: osc
+                .clipboard_write
+                .unwrap_or(switch(security.osc52_clipboard)),
+            clipboard_read: osc.clipboard_read.unwrap_or(OscPolicy::Deny),
+            images: osc.images.unwrap_or(switch(security.inline_images)),
+        }
     }
 
-    #[allow(dead_code)] // Will be used when OSC 52 handling is implemented
     pub fn osc52_max_size(&self) -> usize {
         self.security.osc52_max_size
     }
+
+    /// TERM given to shells: `env.TERM`, else `term` when this host has
+    /// its terminfo entry, else the default
+    pub fn term_name(&self) -> &str {
+        if let Some(term) = self.env.get("TERM") {
+            return term;
+        }
+        if self.term == DEFAULT_TERM || terminfo::is_installed(&self.term) {
+            &self.term
+        } else {
+            DEFAULT_TERM
+        }
+    }
+
+    /// A terminal builder with the settings that apply to every tab
+    pub fn terminal_builder(&self, cols: usize, rows: usize) -> TerminalBuilder {
+        let limits = TerminalLimits {
+            clipboard_len: self.osc52_max_size(),
+            ..TerminalLimits::default()
+        };
+        let mut builder = TerminalBuilder::new(cols, rows);
+        builder
+            .scrollback_lines(self.scrollback_lines)
+            .alternate_scroll(self.alternate_scroll)
+            .capture_alternate_screen(self.capture_alternate_screen)
+            .term_name(self.term_name())
+            .osc_policies(self.osc_policies())
+            .answer_queries(self.security.answer_queries)
+            .device_attributes(&self.security.device_attributes)
+            .answerback(&self.security.answerback)
+            .tmux_passthrough(self.security.tmux_passthrough)
+            .identify_as_xterm(self.identify_as_xterm)
+            .limits(limits);
+        builder
+    }
 }
 
 impl ColorScheme {
@@ -971,6 +2851,76 @@ mod tests {
         assert!(!config.security.osc52_clipboard);
     }
 
+    #[test]
+    fn test_tmux_passthrough_config() {
+        assert!(Config::default().security.tmux_passthrough);
+        let config: Config = toml::from_str("[security]\ntmux_passthrough = false").unwrap();
+        assert!(!config.security.tmux_passthrough);
+    }
+
+    #[test]
+    fn test_query_config() {
+        let config = Config::default();
+        let mut term = config.terminal_builder(80, 24).build();
+        term.process(b"\x1b[?c");
+        assert_eq!(term.take_pending_responses(), vec![b"\x1b[?1;2c".to_vec()]);
+
+        let toml_str = r#"
+            [security]
+            device_attributes = "62;22"
+        "#;
+        let mut config: Config = toml::from_str(toml_str).unwrap();
+        assert!(config.validate().is_ok());
+        let mut term = config.terminal_builder(80, 24).build();
+        term.process(b"\x1b[?c");
+        assert_eq!(
+            term.take_pending_responses(),
+            vec![b"\x1b[?62;22c".to_vec()]
+        );
+
+        config.security.answer_queries = false;
+        let mut term = config.terminal_builder(80, 24).build();
+        term.process(b"\x1b[?c\x1b[6n");
+        assert!(term.take_pending_responses().is_empty());
+
+        config.security.device_attributes = "1;2c\x1b]0;x".to_string();
+        assert!(config.validate().is_err());
+        config.security.device_attributes = String::new();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_osc_policy_config() {
+        let policies = Config::default().osc_policies();
+        assert_eq!(policies.title, OscPolicy::Allow);
+        assert_eq!(policies.clipboard_write, OscPolicy::Deny);
+        assert_eq!(policies.clipboard_read, OscPolicy::Deny);
+        assert_eq!(policies.images, OscPolicy::Allow);
+
+        // The older switches apply to features the table leaves unset
+        let config: Config = toml::from_str(
+            r#"
+            [security]
+            osc52_clipboard = true
+            inline_images = false
+
+            [security.osc]
+            clipboard_read = "prompt"
+            colors = "deny"
+            images = "allow"
+            "#,
+        )
+        .unwrap();
+        let policies = config.osc_policies();
+        assert_eq!(policies.clipboard_write, OscPolicy::Allow);
+        assert_eq!(policies.clipboard_read, OscPolicy::Prompt);
+        assert_eq!(policies.colors, OscPolicy::Deny);
+        assert_eq!(policies.images, OscPolicy::Allow);
+        assert_eq!(policies.notifications, OscPolicy::Allow);
+
+        assert!(toml::from_str::<Config>("[security.osc]\ntitle = \"ask\"").is_err());
+    }
+
     #[test]
     fn test_parse_hex() {
         assert_eq!(ColorScheme::parse_hex("#ff0000"), Some((255, 0, 0)));
@@ -985,16 +2935,48 @@ mod tests {
     }
 
     #[test]
-    fn test_theme_from_str() {
-        assert_eq!(ThemeName::from_str("dark"), Some(ThemeName::Dark));
-        assert_eq!(ThemeName::from_str("light"), Some(ThemeName::Light));
+    fn test_theme_from_str() {
+        assert_eq!(ThemeName::from_str("dark"), Some(ThemeName::Dark));
+        assert_eq!(ThemeName::from_str("light"), Some(ThemeName::Light));
+        assert_eq!(
+            ThemeName::from_str("solarized-dark"),
+            Some(ThemeName::SolarizedDark)
+        );
+        assert_eq!(ThemeName::from_str("dracula"), Some(ThemeName::Dracula));
+        assert_eq!(ThemeName::from_str("nord"), Some(ThemeName::Nord));
+        assert_eq!(ThemeName::from_str("invalid"), None);
+    }
+
+    #[test]
+    fn test_theme_parse_user_theme() {
+        assert_eq!(ThemeName::parse("nord"), ThemeName::Nord);
+        assert_eq!(
+            ThemeName::parse("gruvbox"),
+            ThemeName::User("gruvbox".to_string())
+        );
+
+        let config: Config = toml::from_str(r#"theme = "mochi-test-missing-theme""#).unwrap();
+        assert_eq!(
+            config.theme,
+            ThemeName::User("mochi-test-missing-theme".to_string())
+        );
+        let config: Config = toml::from_str(r#"theme = "solarized-dark""#).unwrap();
+        assert_eq!(config.theme, ThemeName::SolarizedDark);
+    }
+
+    #[test]
+    fn test_missing_user_theme() {
+        let config = Config {
+            theme: ThemeName::User("mochi-test-missing-theme".to_string()),
+            ..Config::default()
+        };
+        let err = config.validate().unwrap_err();
+        assert_eq!(err.field.as_deref(), Some("theme"));
+        // Falls back to the default colors at runtime
         assert_eq!(
-            ThemeName::from_str("solarized-dark"),
-            Some(ThemeName::SolarizedDark)
+            config.effective_colors().background,
+            ColorScheme::default().background
         );
-        assert_eq!(ThemeName::from_str("dracula"), Some(ThemeName::Dracula));
-        assert_eq!(ThemeName::from_str("nord"), Some(ThemeName::Nord));
-        assert_eq!(ThemeName::from_str("invalid"), None);
     }
 
     #[test]
@@ -1034,7 +3016,13 @@ mod tests {
         assert_eq!(kb.paste, "ctrl+shift+v");
         assert_eq!(kb.find, "ctrl+shift+f");
         assert_eq!(kb.reload_config, "ctrl+shift+r");
+        #[cfg(target_os = "macos")]
         assert_eq!(kb.toggle_theme, "ctrl+shift+t");
+        #[cfg(not(target_os = "macos"))]
+        assert_eq!(kb.toggle_theme, "");
+        assert_eq!(kb.rename_tab, "ctrl+shift+e");
+        assert_eq!(kb.process_info, "ctrl+shift+i");
+        assert_eq!(kb.run_command, "ctrl+shift+x");
     }
 
     #[test]
@@ -1062,6 +3050,692 @@ mod tests {
         assert_eq!(config.font.size, 16.0);
         assert_eq!(config.keybindings.copy, "ctrl+c");
         assert!(config.security.osc52_clipboard);
+        assert!(config.validate().is_ok());
+
+        let config: Config = toml::from_str("[keybindings]\nfind = \"ctrl+shift+?f\"").unwrap();
+        let err = config.validate().unwrap_err();
+        assert_eq!(err.field.as_deref(), Some("keybindings.find"));
+    }
+
+    #[test]
+    fn test_cursor_animation_config() {
+        let config = Config::default();
+        assert!(!config.cursor_animation.enabled);
+        assert!(!config.cursor_animation.trail);
+
+        let toml_str = r#"
+            [cursor_animation]
+            enabled = true
+            trail = true
+        "#;
+        let mut config: Config = toml::from_str(toml_str).unwrap();
+        assert!(config.cursor_animation.enabled);
+        assert!(config.cursor_animation.trail);
+        assert_eq!(config.cursor_animation.duration_ms, 80);
+        assert!(config.validate().is_ok());
+
+        config.cursor_animation.duration_ms = 5000;
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_update_from_keeps_startup_settings() {
+        let mut config = Config {
+            dimensions: (100, 30),
+            ..Config::default()
+        };
+        let reloaded = Config {
+            theme: ThemeName::Nord,
+            dimensions: (80, 24),
+            ..Config::default()
+        };
+        config.update_from(&reloaded);
+        assert_eq!(config.theme, ThemeName::Nord);
+        assert_eq!(config.dimensions, (100, 30));
+    }
+
+    #[test]
+    fn test_reload_from_config_file() {
+        let dir = std::env::temp_dir().join(format!("mochi-reload-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        let config = Config {
+            config_file: Some(path.clone()),
+            ..Config::default()
+        };
+
+        fs::write(&path, "theme = \"nord\"\nscrollback_lines = 500").unwrap();
+        let reloaded = config.reload().unwrap();
+        assert_eq!(reloaded.theme, ThemeName::Nord);
+        assert_eq!(reloaded.scrollback_lines, 500);
+        assert_eq!(reloaded.config_file.as_deref(), Some(path.as_path()));
+
+        // Invalid values are reported instead of applied
+        fs::write(&path, "[font]\nsize = 1.0").unwrap();
+        let err = config.reload().unwrap_err();
+        assert_eq!(err.field.as_deref(), Some("font.size"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_priority_config() {
+        let config = Config::default();
+        assert!(config.priority.to_priority().is_unchanged());
+        let builder = config.with_environment(CommandBuilder::new("/bin/true"));
+        assert!(builder.get_priority().is_unchanged());
+
+        let toml_str = r#"
+            [priority]
+            nice = 10
+            io_class = "best-effort"
+            io_level = 6
+        "#;
+        let mut config: Config = toml::from_str(toml_str).unwrap();
+        assert!(config.validate().is_ok());
+        let builder = config.with_environment(CommandBuilder::new("/bin/true"));
+        assert_eq!(
+            builder.get_priority(),
+            Priority {
+                nice: Some(10),
+                io: Some(IoPriority::BestEffort(6)),
+            }
+        );
+
+        config.priority.nice = Some(20);
+        assert!(config.validate().is_err());
+        config.priority.nice = None;
+        config.priority.io_level = 8;
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_memory_config() {
+        let config = Config::default();
+        assert_eq!(MemoryConfig::budget(config.memory.scrollback_mb), None);
+
+        let toml_str = r#"
+            [memory]
+            scrollback_mb = 256
+            glyph_cache_mb = 32
+        "#;
+        let mut config: Config = toml::from_str(toml_str).unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(
+            MemoryConfig::budget(config.memory.scrollback_mb),
+            Some(256 << 20)
+        );
+        assert_eq!(config.memory.images_mb, 0);
+
+        config.memory.images_mb = MAX_MEMORY_BUDGET_MB + 1;
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_scope_config() {
+        let config = Config::default();
+        assert!(!config.scope.enabled);
+        assert_eq!(config.scope.new_scope(), None);
+
+        let toml_str = r#"
+            [scope]
+            enabled = true
+            properties = ["MemoryMax=4G", "CPUQuota=200%"]
+        "#;
+        let mut config: Config = toml::from_str(toml_str).unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.scope.properties, ["MemoryMax=4G", "CPUQuota=200%"]);
+        assert_eq!(scope_unit(1234, 2), "mochi-1234-2");
+        if let Some(scope) = config.scope.new_scope() {
+            assert_eq!(scope.properties, config.scope.properties);
+            assert_ne!(config.scope.new_scope().unwrap().unit, scope.unit);
+        }
+
+        config.scope.properties.push("MemoryMax".to_string());
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_screenshot_config() {
+        let config = Config::default();
+        assert!(config.screenshot.cursor && config.screenshot.selection);
+
+        let toml_str = r#"
+            [screenshot]
+            cursor = false
+        "#;
+        let config: Config = toml::from_str(toml_str).unwrap();
+        assert!(!config.screenshot.cursor);
+        assert!(config.screenshot.selection);
+    }
+
+    #[test]
+    fn test_flow_control_config() {
+        let config = Config::default();
+        assert_eq!(config.flow_control, FlowControlConfig::default());
+        assert_eq!(config.flow_control.skip_frames_above, 0);
+
+        let toml_str = r#"
+            [flow_control]
+            skip_frames_above = 1048576
+        "#;
+        let mut config: Config = toml::from_str(toml_str).unwrap();
+        assert_eq!(config.flow_control.read_limit, 256 * 1024);
+        assert_eq!(config.flow_control.skip_frames_above, 1048576);
+        assert!(config.validate().is_ok());
+
+        config.flow_control.read_limit = 16;
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_latency_config() {
+        let config = Config::default();
+        assert_eq!(config.latency, LatencyConfig::default());
+        assert!(!config.latency.predictive_echo);
+
+        let toml_str = r#"
+            [latency]
+            predictive_echo = true
+        "#;
+        let mut config: Config = toml::from_str(toml_str).unwrap();
+        assert!(config.latency.predictive_echo);
+        assert_eq!(config.latency.prediction_threshold_ms, 30);
+        assert_eq!(config.latency.misprediction_limit, 5);
+        assert!(config.validate().is_ok());
+
+        config.latency.prediction_threshold_ms = 60_000;
+        assert!(config.validate().is_err());
+
+        config.latency.prediction_threshold_ms = 30;
+        config.latency.misprediction_limit = 1000;
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_paste_config() {
+        let config = Config::default();
+        assert_eq!(config.paste.control_characters, PasteControls::Strip);
+        assert_eq!(config.paste.confirm_above_kb, 64);
+        assert!(config.paste.confirm_multiline);
+
+        let config: Config = toml::from_str(
+            r#"
+            [paste]
+            control_characters = "escape"
+            confirm_above_kb = 0
+        "#,
+        )
+        .unwrap();
+        assert_eq!(config.paste.control_characters, PasteControls::Escape);
+        assert_eq!(config.paste.confirm_above_kb, 0);
+        assert!(config.paste.confirm_multiline);
+    }
+
+    #[test]
+    fn test_clipboard_history_config() {
+        let config = Config::default();
+        assert_eq!(config.clipboard_history.entries, 20);
+        assert_eq!(config.keybindings.clipboard_history, "ctrl+shift+y");
+
+        let mut config: Config = toml::from_str(
+            r#"
+            [clipboard_history]
+            entries = 5
+        "#,
+        )
+        .unwrap();
+        assert_eq!(config.clipboard_history.entries, 5);
+        assert_eq!(config.clipboard_history.max_kb, 1024);
+        assert!(config.validate().is_ok());
+
+        config.clipboard_history.entries = 5000;
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_hints_config() {
+        let config: Config = toml::from_str(
+            r#"
+            [hints]
+            alphabet = "jkl"
+            patterns = [{ name = "ticket", regex = "[A-Z]+-[0-9]+" }]
+        "#,
+        )
+        .unwrap();
+        assert_eq!(config.hints.alphabet, "jkl");
+        assert_eq!(config.hints.patterns[0].name, "ticket");
+        assert!(config.validate().is_ok());
+
+        for alphabet in ["a", "aab", "ABC"] {
+            let mut config = Config::default();
+            config.hints.alphabet = alphabet.to_string();
+            let err = config.validate().unwrap_err();
+            assert_eq!(err.field.as_deref(), Some("hints.alphabet"));
+        }
+    }
+
+    #[test]
+    fn test_hooks_config() {
+        let config: Config = toml::from_str(
+            r#"
+            [[hooks]]
+            event = "output"
+            pattern = "ERROR"
+            command = "notify-send mochi \"$MOCHI_HOOK_TEXT\""
+
+            [[hooks]]
+            event = "bell"
+            notify = true
+            min_interval_ms = 5000
+        "#,
+        )
+        .unwrap();
+        assert_eq!(config.hooks.len(), 2);
+        assert_eq!(config.hooks[0].event, HookEvent::Output);
+        assert_eq!(config.hooks[0].min_interval_ms, 1000);
+        assert!(config.hooks[1].notify);
+        assert!(config.validate().is_ok());
+
+        let config: Config =
+            toml::from_str("[[hooks]]\nevent = \"output\"\nnotify = true").unwrap();
+        let err = config.validate().unwrap_err();
+        assert_eq!(err.field.as_deref(), Some("hooks"));
+    }
+
+    #[test]
+    fn test_highlights_config() {
+        let config: Config = toml::from_str(
+            r##"
+            [[highlights]]
+            pattern = "ERROR"
+            foreground = "red"
+            whole_line = true
+
+            [[highlights]]
+            pattern = "WARN"
+            background = "#553300"
+        "##,
+        )
+        .unwrap();
+        assert_eq!(config.highlights.len(), 2);
+        assert!(config.highlights[0].whole_line);
+        assert!(config.validate().is_ok());
+
+        for rule in [
+            "pattern = \"(\"\nforeground = \"red\"",
+            "pattern = \"x\"\nforeground = \"reddish\"",
+            "pattern = \"x\"",
+        ] {
+            let config: Config = toml::from_str(&format!("[[highlights]]\n{}", rule)).unwrap();
+            let err = config.validate().unwrap_err();
+            assert_eq!(err.field.as_deref(), Some("highlights"));
+        }
+    }
+
+    #[test]
+    fn test_profiles() {
+        let config: Config = toml::from_str(
+            "env = { EDITOR = \"vi\" }\n\
+             profile = \"work\"\n\
+             [profiles.work]\n\
+             shell = [\"zsh\", \"-l\"]\n\
+             working_directory = \"/tmp\"\n\
+             env = { EDITOR = \"nvim\" }\n\
+             theme = \"nord\"\n\
+             font_size = 16.0\n\
+             [profiles.plain]",
+        )
+        .unwrap();
+        assert!(config.validate().is_ok());
+
+        let work = config.profile_shell_command(Some("work"));
+        assert_eq!(work.get_program(), "zsh");
+        assert_eq!(work.get_cwd(), Some(std::path::Path::new("/tmp")));
+        assert_eq!(work.get_env("EDITOR"), Some(std::ffi::OsStr::new("nvim")));
+        assert_eq!(config.profile_theme(Some("work")), &ThemeName::Nord);
+        assert_eq!(config.profile_font_size(Some("work")), 16.0);
+
+        // Unset fields and unknown profiles fall back to the top level
+        let plain = config.profile_shell_command(Some("plain"));
+        assert_eq!(plain.get_env("EDITOR"), Some(std::ffi::OsStr::new("vi")));
+        assert_eq!(config.profile_theme(Some("plain")), &config.theme);
+        assert_eq!(config.profile_font_size(None), config.font.size);
+        assert_eq!(
+            config.profile_shell_command(Some("none")).get_program(),
+            config.shell_command().get_program()
+        );
+
+        let config: Config = toml::from_str("profile = \"none\"").unwrap();
+        let err = config.validate().unwrap_err();
+        assert_eq!(err.field.as_deref(), Some("profile"));
+        let config: Config = toml::from_str("[profiles.big]\nfont_size = 500.0").unwrap();
+        let err = config.validate().unwrap_err();
+        assert_eq!(err.field.as_deref(), Some("profiles.big.font_size"));
+    }
+
+    #[test]
+    fn test_startup_command() {
+        let args = CliArgs::try_parse_from(["mochi", "-p", "work", "-e", "vim", "-p", "a.txt"]);
+        let args = args.unwrap();
+        assert_eq!(args.profile.as_deref(), Some("work"));
+        assert_eq!(
+            StartupCommand::from_args(&args.execute),
+            Some(StartupCommand::Argv(vec![
+                "vim".to_string(),
+                "-p".to_string(),
+                "a.txt".to_string()
+            ]))
+        );
+        assert_eq!(
+            StartupCommand::from_args(&["htop -d 10".to_string()]),
+            Some(StartupCommand::Line("htop -d 10".to_string()))
+        );
+        assert_eq!(StartupCommand::from_args(&[]), None);
+
+        let config: Config = toml::from_str("startup_command = [\"htop\", \"-t\"]").unwrap();
+        assert!(config.validate().is_ok());
+        let command = config
+            .startup_shell_command(config.startup_command.as_ref(), None)
+            .unwrap();
+        assert_eq!(command.get_program(), "htop");
+        assert_eq!(command.get_args(), ["-t"]);
+        assert!(Config::default()
+            .startup_shell_command(None, None)
+            .is_none());
+
+        let config: Config = toml::from_str("startup_command = \" \"").unwrap();
+        let err = config.validate().unwrap_err();
+        assert_eq!(err.field.as_deref(), Some("startup_command"));
+    }
+
+    #[test]
+    fn test_term_config() {
+        let config = Config::default();
+        assert_eq!(config.term_name(), DEFAULT_TERM);
+
+        // A TERM without a terminfo entry here falls back to the default
+        let config: Config = toml::from_str("term = \"mochi-nonexistent\"").unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.term_name(), DEFAULT_TERM);
+        assert_eq!(
+            config.shell_command().get_env("TERM"),
+            Some(std::ffi::OsStr::new(DEFAULT_TERM))
+        );
+
+        // env.TERM is used as given
+        let config: Config =
+            toml::from_str("term = \"mochi\"\nenv = { TERM = \"mochi-nonexistent\" }").unwrap();
+        assert_eq!(config.term_name(), "mochi-nonexistent");
+
+        let config: Config = toml::from_str("term = \"\"").unwrap();
+        let err = config.validate().unwrap_err();
+        assert_eq!(err.field.as_deref(), Some("term"));
+    }
+
+    #[test]
+    fn test_ssh_config() {
+        let config = Config::default();
+        assert_eq!(config.ssh.term, DEFAULT_TERM);
+        assert_eq!(
+            config.shell_command().get_env("MOCHI_SSH_TERM"),
+            Some(std::ffi::OsStr::new(DEFAULT_TERM))
+        );
+
+        let config: Config = toml::from_str("[ssh]\nterm = \"\"").unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.shell_command().get_env("MOCHI_SSH_TERM"), None);
+
+        let config: Config = toml::from_str("[ssh]\nterm = \"xterm 256\"").unwrap();
+        let err = config.validate().unwrap_err();
+        assert_eq!(err.field.as_deref(), Some("ssh.term"));
+    }
+
+    #[test]
+    fn test_monitor_config() {
+        let config: Config =
+            toml::from_str("[monitor]\nsilence_seconds = 5\nnotify = false").unwrap();
+        assert_eq!(config.monitor.silence_seconds, 5);
+        assert!(!config.monitor.notify);
+        assert!(config.validate().is_ok());
+        assert_eq!(Config::default().monitor, MonitorConfig::default());
+
+        let config: Config = toml::from_str("[monitor]\nsilence_seconds = 0").unwrap();
+        let err = config.validate().unwrap_err();
+        assert_eq!(err.field.as_deref(), Some("monitor.silence_seconds"));
+    }
+
+    #[test]
+    fn test_openers_config() {
+        assert_eq!(Config::default().openers, default_openers());
+        let config: Config = toml::from_str(
+            r#"
+            [[openers]]
+            pattern = '^(?P<file>[^:]+):(?P<line>\d+)$'
+            action = "command"
+            command = ["$EDITOR", "+{line}", "{file}"]
+        "#,
+        )
+        .unwrap();
+        assert_eq!(config.openers.len(), 1);
+        assert_eq!(config.openers[0].action, OpenerAction::Command);
+        assert!(config.validate().is_ok());
+
+        let config: Config =
+            toml::from_str("[[openers]]\npattern = \"x\"\naction = \"command\"").unwrap();
+        let err = config.validate().unwrap_err();
+        assert_eq!(err.field.as_deref(), Some("openers"));
+    }
+
+    #[test]
+    fn test_status_bar_config() {
+        let config: Config = toml::from_str(
+            r#"
+            [status_bar]
+            enabled = true
+            left = "{title} [({git})]"
+        "#,
+        )
+        .unwrap();
+        assert!(config.status_bar.enabled);
+        assert_eq!(config.status_bar.right, default_status_right());
+        assert!(config.validate().is_ok());
+
+        let config: Config =
+            toml::from_str("[status_bar]\nenabled = true\nright = \"{branch}\"").unwrap();
+        let err = config.validate().unwrap_err();
+        assert_eq!(err.field.as_deref(), Some("status_bar.right"));
+    }
+
+    #[test]
+    fn test_minimum_contrast_config() {
+        let mut config = Config::default();
+        assert_eq!(config.minimum_contrast, 1.0);
+
+        config = toml::from_str("minimum_contrast = 4.5").unwrap();
+        assert_eq!(config.minimum_contrast, 4.5);
+        assert!(config.validate().is_ok());
+
+        config.minimum_contrast = 0.5;
+        assert!(config.validate().is_err());
+        config.minimum_contrast = 30.0;
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_config_overrides() {
+        let config = Config::default();
+        let updated = config
+            .with_overrides(&serde_json::json!({ "theme": "dark", "font": { "size": 18.0 } }))
+            .unwrap();
+        assert_eq!(updated.theme, ThemeName::Dark);
+        assert_eq!(updated.font.size, 18.0);
+        // Settings that were not given keep their values
+        assert_eq!(updated.font.family, config.font.family);
+        assert_eq!(updated.scrollback_lines, config.scrollback_lines);
+
+        let err = config
+            .with_overrides(&serde_json::json!({ "scrollback_lines": 100_000_000 }))
+            .unwrap_err();
+        assert_eq!(err.field.as_deref(), Some("scrollback_lines"));
+        assert!(config
+            .with_overrides(&serde_json::json!({ "font": { "size": "big" } }))
+            .is_err());
+        assert!(config.with_overrides(&serde_json::json!([])).is_err());
+
+        let config: Config = toml::from_str("[control]\nenabled = true").unwrap();
+        assert!(config.control.enabled);
+        assert_eq!(config.control.path, None);
+    }
+
+    #[test]
+    fn test_max_fps_config() {
+        assert_eq!(Config::default().max_fps, 0);
+        let mut config: Config = toml::from_str("max_fps = 30").unwrap();
+        assert_eq!(config.max_fps, 30);
+        assert!(config.validate().is_ok());
+        config.max_fps = 5000;
+        assert_eq!(
+            config.validate().unwrap_err().field.as_deref(),
+            Some("max_fps")
+        );
+    }
+
+    #[test]
+    fn test_padding_config() {
+        let mut config: Config = toml::from_str(
+            "padding = { x = 8, y = 4 }\n[font]\nline_height = 1.2\ncell_padding_x = 1",
+        )
+        .unwrap();
+        assert_eq!(config.padding, PaddingConfig { x: 8, y: 4 });
+        assert_eq!(config.font.cell_padding_x, 1);
+        assert!(config.validate().is_ok());
+
+        config.padding.y = 500;
+        let err = config.validate().unwrap_err();
+        assert_eq!(err.field.as_deref(), Some("padding.y"));
+        config.padding.y = 0;
+        config.font.cell_padding_y = 100;
+        let err = config.validate().unwrap_err();
+        assert_eq!(err.field.as_deref(), Some("font.cell_padding_y"));
+    }
+
+    #[test]
+    fn test_builtin_box_drawing_config() {
+        assert!(Config::default().font.builtin_box_drawing);
+        let config: Config = toml::from_str("[font]\nbuiltin_box_drawing = false").unwrap();
+        assert!(!config.font.builtin_box_drawing);
+    }
+
+    #[test]
+    fn test_focus_config() {
+        let config = Config::default();
+        assert_eq!(config.dim_unfocused, 0.0);
+        assert!(!config.focus_follows_mouse);
+
+        let mut config: Config =
+            toml::from_str("dim_unfocused = 0.3\nfocus_follows_mouse = true").unwrap();
+        assert_eq!(config.dim_unfocused, 0.3);
+        assert!(config.focus_follows_mouse);
+        assert!(config.validate().is_ok());
+
+        config.dim_unfocused = 1.5;
+        let err = config.validate().unwrap_err();
+        assert_eq!(err.field.as_deref(), Some("dim_unfocused"));
+    }
+
+    #[test]
+    fn test_alternate_scroll_config() {
+        assert!(Config::default().alternate_scroll);
+        let config: Config = toml::from_str("alternate_scroll = false").unwrap();
+        assert!(!config.alternate_scroll);
+    }
+
+    #[test]
+    fn test_format_other_keys() {
+        assert_eq!(Config::default().format_other_keys, OtherKeysFormat::Xterm);
+        let config: Config = toml::from_str("format_other_keys = \"csi-u\"").unwrap();
+        assert_eq!(config.format_other_keys, OtherKeysFormat::CsiU);
+    }
+
+    #[test]
+    fn test_exit_behavior_and_close_confirmation() {
+        let config = Config::default();
+        assert_eq!(config.exit_behavior, ExitBehavior::Close);
+        assert_eq!(config.confirm_close, CloseConfirmation::Running);
+
+        let toml_str = r#"
+            exit_behavior = "hold"
+            confirm_close = "always"
+        "#;
+        let config: Config = toml::from_str(toml_str).unwrap();
+        assert_eq!(config.exit_behavior, ExitBehavior::Hold);
+        assert_eq!(config.confirm_close, CloseConfirmation::Always);
+
+        let config: Config = toml::from_str("exit_behavior = \"restart\"").unwrap();
+        assert_eq!(config.exit_behavior, ExitBehavior::Restart);
+        assert!(toml::from_str::<Config>("confirm_close = \"sometimes\"").is_err());
+
+        assert_eq!(Config::default().close_grace_ms, 2000);
+        let config: Config = toml::from_str("close_grace_ms = 120000").unwrap();
+        let err = config.validate().unwrap_err();
+        assert_eq!(err.field.as_deref(), Some("close_grace_ms"));
+    }
+
+    #[test]
+    fn test_shell_command_config() {
+        let config: Config = toml::from_str(r#"shell = "/bin/zsh""#).unwrap();
+        assert_eq!(config.shell, Some(ShellCommand::Program("/bin/zsh".into())));
+        let command = config.shell_command();
+        assert_eq!(command.get_program(), "/bin/zsh");
+        assert!(command.is_login_shell());
+
+        let toml_str = r#"
+            shell = ["zsh", "-i"]
+            env = { EDITOR = "vim" }
+            env_remove = ["COLORTERM"]
+            working_directory = "/tmp"
+        "#;
+        let config: Config = toml::from_str(toml_str).unwrap();
+        assert!(config.validate().is_ok());
+        let command = config.shell_command();
+        assert_eq!(command.get_program(), "zsh");
+        assert_eq!(command.get_args(), ["-i"]);
+        assert!(!command.is_login_shell());
+        assert_eq!(command.get_env("EDITOR").unwrap(), "vim");
+        assert_eq!(command.get_env("COLORTERM"), None);
+        assert_eq!(command.get_cwd(), Some(std::path::Path::new("/tmp")));
+    }
+
+    #[test]
+    fn test_shell_command_validation() {
+        let config: Config = toml::from_str("shell = []").unwrap();
+        assert!(config.validate().is_err());
+        let config: Config = toml::from_str(r#"env = { "A=B" = "c" }"#).unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_shell_from_arg() {
+        assert_eq!(
+            ShellCommand::from_arg("/bin/fish"),
+            ShellCommand::Program("/bin/fish".into())
+        );
+        assert_eq!(
+            ShellCommand::from_arg("zsh -l"),
+            ShellCommand::Argv(vec!["zsh".into(), "-l".into()])
+        );
+    }
+
+    #[test]
+    fn test_session_path() {
+        let mut config = Config::default();
+        assert!(config.session_path().is_none());
+
+        config = toml::from_str("restore_session = true").unwrap();
+        assert_eq!(config.session_path(), Session::default_path());
+
+        config.session_file = Some(PathBuf::from("/tmp/work.toml"));
+        assert_eq!(config.session_path(), Some(PathBuf::from("/tmp/work.toml")));
     }
 
     #[test]
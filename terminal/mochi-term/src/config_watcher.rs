@@ -0,0 +1,355 @@
+//! Watching the config file and theme files for changes
+//!
+//! A background thread watches the directories holding the config file and
+//! the user themes; watching directories rather than files catches editors
+//! that save by renaming a new file over the old one. On Linux the thread
+//! blocks on inotify; elsewhere it compares modification times every
+//! `POLL_INTERVAL`. A burst of changes is reported once, after `DEBOUNCE`
+//! without further changes.
+
+use std::ffi::OsString;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+/// Quiet period after a change before it is reported
+pub const DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// How often modification times are compared where inotify is unavailable
+#[cfg(not(target_os = "linux"))]
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Which files in a watched directory matter
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WatchFilter {
+    /// One file, by name
+    File(OsString),
+    /// Any `.toml` file
+    Toml,
+}
+
+impl WatchFilter {
+    /// Check if a file name in the directory matches
+    pub fn matches(&self, name: &Path) -> bool {
+        match self {
+            WatchFilter::File(file) => name.as_os_str() == file,
+            WatchFilter::Toml => name.extension().is_some_and(|ext| ext == "toml"),
+        }
+    }
+}
+
+/// A directory to watch
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatchTarget {
+    pub dir: PathBuf,
+    pub filter: WatchFilter,
+}
+
+impl WatchTarget {
+    /// Watch one file (through its directory)
+    pub fn file(path: &Path) -> Option<Self> {
+        Some(Self {
+            dir: path.parent()?.to_path_buf(),
+            filter: WatchFilter::File(path.file_name()?.to_os_string()),
+        })
+    }
+
+    /// Watch the `.toml` files in a directory
+    pub fn toml_files(dir: &Path) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            filter: WatchFilter::Toml,
+        }
+    }
+}
+
+/// Start a thread that calls `notify` after watched files change
+///
+/// Directories that do not exist when the watcher starts are skipped. The
+/// thread runs for the rest of the process.
+pub fn spawn(targets: Vec<WatchTarget>, notify: impl Fn() + Send + 'static) -> io::Result<()> {
+    let targets: Vec<WatchTarget> = targets
+        .into_iter()
+        .filter(|target| target.dir.is_dir())
+        .collect();
+    if targets.is_empty() {
+        log::debug!("No config directories to watch");
+        return Ok(());
+    }
+
+    #[cfg(target_os = "linux")]
+    let watcher = inotify::Watcher::new(&targets)?;
+
+    thread::Builder::new()
+        .name("config-watcher".to_string())
+        .spawn(move || {
+            #[cfg(target_os = "linux")]
+            watcher.run(notify);
+            #[cfg(not(target_os = "linux"))]
+            poll_mtimes(&targets, notify);
+        })?;
+    Ok(())
+}
+
+/// Watch by comparing modification times (platforms without inotify)
+#[cfg(not(target_os = "linux"))]
+fn poll_mtimes(targets: &[WatchTarget], notify: impl Fn()) {
+    let mut last = snapshot(targets);
+    loop {
+        thread::sleep(POLL_INTERVAL);
+        let mut current = snapshot(targets);
+        if current == last {
+            continue;
+        }
+        // Wait for the burst of writes to settle
+        loop {
+            thread::sleep(DEBOUNCE);
+            let settled = snapshot(targets);
+            if settled == current {
+                break;
+            }
+            current = settled;
+        }
+        last = current;
+        notify();
+    }
+}
+
+/// Modification time and size of every matching file, sorted by path
+#[cfg(not(target_os = "linux"))]
+fn snapshot(targets: &[WatchTarget]) -> Vec<(PathBuf, std::time::SystemTime, u64)> {
+    let mut files = Vec::new();
+    for target in targets {
+        let Ok(entries) = std::fs::read_dir(&target.dir) else {
+            continue;
+        };
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let path = entry.path();
+            let Some(name) = path.file_name() else {
+                continue;
+            };
+            if !target.filter.matches(Path::new(name)) {
+                continue;
+            }
+            if let Ok(metadata) = entry.metadata() {
+                let modified = metadata.modified().unwrap_or(std::time::UNIX_EPOCH);
+                files.push((path, modified, metadata.len()));
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+#[cfg(target_os = "linux")]
+mod inotify {
+    use std::ffi::{CString, OsStr};
+    use std::io;
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::Path;
+
+    use super::{WatchFilter, WatchTarget, DEBOUNCE};
+
+    /// Events that mean a file was written, replaced or removed
+    const MASK: u32 = libc::IN_CLOSE_WRITE
+        | libc::IN_MOVED_TO
+        | libc::IN_MOVED_FROM
+        | libc::IN_CREATE
+        | libc::IN_DELETE;
+
+    /// An inotify instance watching the target directories
+    pub struct Watcher {
+        fd: OwnedFd,
+        /// Watch descriptor and filter of each directory
+        watches: Vec<(i32, WatchFilter)>,
+    }
+
+    impl Watcher {
+        pub fn new(targets: &[WatchTarget]) -> io::Result<Self> {
+            let fd = unsafe { libc::inotify_init1(libc::IN_CLOEXEC) };
+            if fd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+            let mut watches = Vec::new();
+            for target in targets {
+                let path = CString::new(target.dir.as_os_str().as_bytes())
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+                let wd = unsafe { libc::inotify_add_watch(fd.as_raw_fd(), path.as_ptr(), MASK) };
+                if wd < 0 {
+                    log::warn!(
+                        "Cannot watch {}: {}",
+                        target.dir.display(),
+                        io::Error::last_os_error()
+                    );
+                    continue;
+                }
+                watches.push((wd, target.filter.clone()));
+            }
+            Ok(Self { fd, watches })
+        }
+
+        /// Read events forever, calling `notify` after each burst of changes
+        pub fn run(self, notify: impl Fn()) {
+            let mut buf = [0u8; 4096];
+            loop {
+                match self.read_changes(&mut buf) {
+                    Ok(false) => continue,
+                    Ok(true) => {}
+                    Err(e) => {
+                        log::warn!("Config watcher stopped: {}", e);
+                        return;
+                    }
+                }
+                // Drain further events until the files are quiet
+                while self.wait_readable(DEBOUNCE) {
+                    if self.read_changes(&mut buf).is_err() {
+                        return;
+                    }
+                }
+                notify();
+            }
+        }
+
+        /// Read one batch of events; returns true if a watched file changed
+        fn read_changes(&self, buf: &mut [u8]) -> io::Result<bool> {
+            let n = unsafe {
+                libc::read(
+                    self.fd.as_raw_fd(),
+                    buf.as_mut_ptr() as *mut libc::c_void,
+                    buf.len(),
+                )
+            };
+            if n < 0 {
+                let err = io::Error::last_os_error();
+                return match err.kind() {
+                    io::ErrorKind::Interrupted => Ok(false),
+                    _ => Err(err),
+                };
+            }
+            Ok(parse_events(&buf[..n as usize]).any(|(wd, name)| {
+                self.watches
+                    .iter()
+                    .any(|(watch, filter)| *watch == wd && filter.matches(Path::new(name)))
+            }))
+        }
+
+        /// Wait up to `timeout` for more events
+        fn wait_readable(&self, timeout: std::time::Duration) -> bool {
+            let mut pfd = libc::pollfd {
+                fd: self.fd.as_raw_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            let ret = unsafe { libc::poll(&mut pfd, 1, timeout.as_millis() as libc::c_int) };
+            ret > 0
+        }
+    }
+
+    /// Split a buffer of inotify events into (watch descriptor, file name)
+    pub fn parse_events(buf: &[u8]) -> impl Iterator<Item = (i32, &OsStr)> {
+        const HEADER: usize = std::mem::size_of::<libc::inotify_event>();
+        let mut offset = 0;
+        std::iter::from_fn(move || {
+            if offset + HEADER > buf.len() {
+                return None;
+            }
+            let event: libc::inotify_event =
+                unsafe { std::ptr::read_unaligned(buf[offset..].as_ptr() as *const _) };
+            let start = offset + HEADER;
+            let end = (start + event.len as usize).min(buf.len());
+            offset = end;
+            // The name is padded with NULs
+            let name = &buf[start..end];
+            let len = name.iter().position(|&b| b == 0).unwrap_or(name.len());
+            Some((event.wd, OsStr::from_bytes(&name[..len])))
+        })
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn event(wd: i32, name: &str) -> Vec<u8> {
+            let padded = (name.len() + 1).next_multiple_of(16);
+            let header = libc::inotify_event {
+                wd,
+                mask: libc::IN_CLOSE_WRITE,
+                cookie: 0,
+                len: padded as u32,
+            };
+            let mut bytes = unsafe {
+                std::slice::from_raw_parts(
+                    &header as *const _ as *const u8,
+                    std::mem::size_of::<libc::inotify_event>(),
+                )
+            }
+            .to_vec();
+            bytes.extend_from_slice(name.as_bytes());
+            bytes.resize(bytes.len() + padded - name.len(), 0);
+            bytes
+        }
+
+        #[test]
+        fn test_parse_events() {
+            let mut buf = event(1, "config.toml");
+            buf.extend(event(2, "gruvbox.toml"));
+            let events: Vec<_> = parse_events(&buf).collect();
+            assert_eq!(
+                events,
+                [
+                    (1, OsStr::new("config.toml")),
+                    (2, OsStr::new("gruvbox.toml"))
+                ]
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_watch_filter() {
+        let file = WatchFilter::File("config.toml".into());
+        assert!(file.matches(Path::new("config.toml")));
+        assert!(!file.matches(Path::new("config.toml.swp")));
+        assert!(WatchFilter::Toml.matches(Path::new("nord.toml")));
+        assert!(!WatchFilter::Toml.matches(Path::new("nord.txt")));
+    }
+
+    #[test]
+    fn test_watch_target_file() {
+        let target = WatchTarget::file(Path::new("/home/me/.config/mochi/config.toml")).unwrap();
+        assert_eq!(target.dir, Path::new("/home/me/.config/mochi"));
+        assert_eq!(target.filter, WatchFilter::File("config.toml".into()));
+    }
+
+    #[test]
+    fn test_spawn_notifies_on_change() {
+        let dir = std::env::temp_dir().join(format!("mochi-watch-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let config = dir.join("config.toml");
+
+        let (tx, rx) = mpsc::channel();
+        spawn(vec![WatchTarget::file(&config).unwrap()], move || {
+            let _ = tx.send(());
+        })
+        .unwrap();
+
+        // A file that is not watched does not notify
+        fs::write(dir.join("other.toml"), "x").unwrap();
+        assert!(rx.recv_timeout(DEBOUNCE * 3).is_err());
+
+        fs::write(&config, "theme = \"nord\"").unwrap();
+        rx.recv_timeout(Duration::from_secs(5))
+            .expect("watcher should notify after the config file changes");
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
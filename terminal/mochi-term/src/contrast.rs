@@ -0,0 +1,140 @@
+//! Color contrast utilities
+//!
+//! Implements the WCAG 2.x relative luminance and contrast ratio formulas,
+//! and adjusts foreground colors that are too close to their background.
+//!
+//! Reference: https://www.w3.org/TR/WCAG21/#dfn-contrast-ratio
+
+/// Minimum contrast ratio that disables adjustment entirely
+pub const CONTRAST_DISABLED: f32 = 1.0;
+
+/// Maximum possible WCAG contrast ratio (black on white)
+pub const MAX_CONTRAST: f32 = 21.0;
+
+/// Convert an sRGB channel (0-255) to linear light
+fn linearize(channel: u8) -> f32 {
+    let c = channel as f32 / 255.0;
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Relative luminance of an sRGB color (0.0 = black, 1.0 = white)
+pub fn relative_luminance(color: (u8, u8, u8)) -> f32 {
+    0.2126 * linearize(color.0) + 0.7152 * linearize(color.1) + 0.0722 * linearize(color.2)
+}
+
+/// WCAG contrast ratio between two colors (1.0 to 21.0)
+pub fn contrast_ratio(a: (u8, u8, u8), b: (u8, u8, u8)) -> f32 {
+    let la = relative_luminance(a);
+    let lb = relative_luminance(b);
+    let (lighter, darker) = if la > lb { (la, lb) } else { (lb, la) };
+    (lighter + 0.05) / (darker + 0.05)
+}
+
+/// Blend two colors (t = 0.0 gives `a`, t = 1.0 gives `b`)
+pub fn mix(a: (u8, u8, u8), b: (u8, u8, u8), t: f32) -> (u8, u8, u8) {
+    let channel = |x: u8, y: u8| (x as f32 + (y as f32 - x as f32) * t).round() as u8;
+    (channel(a.0, b.0), channel(a.1, b.1), channel(a.2, b.2))
+}
+
+/// Adjust a foreground color so it meets a minimum contrast ratio
+///
+/// The foreground is moved towards white or black (whichever contrasts more
+/// with the background) by the smallest amount that reaches `minimum`.
+/// Colors that already meet the threshold are returned unchanged, as are
+/// foregrounds identical to the background (intentionally invisible text).
+pub fn ensure_contrast(fg: (u8, u8, u8), bg: (u8, u8, u8), minimum: f32) -> (u8, u8, u8) {
+    if minimum <= CONTRAST_DISABLED || fg == bg {
+        return fg;
+    }
+    if contrast_ratio(fg, bg) >= minimum {
+        return fg;
+    }
+
+    let white = (255, 255, 255);
+    let black = (0, 0, 0);
+    let target = if contrast_ratio(white, bg) >= contrast_ratio(black, bg) {
+        white
+    } else {
+        black
+    };
+
+    if contrast_ratio(target, bg) < minimum {
+        return target;
+    }
+
+    // Binary search for the smallest blend factor that meets the threshold
+    let mut low = 0.0;
+    let mut high = 1.0;
+    for _ in 0..16 {
+        let mid = (low + high) / 2.0;
+        if contrast_ratio(mix(fg, target, mid), bg) >= minimum {
+            high = mid;
+        } else {
+            low = mid;
+        }
+    }
+    mix(fg, target, high)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relative_luminance() {
+        assert_eq!(relative_luminance((0, 0, 0)), 0.0);
+        assert!((relative_luminance((255, 255, 255)) - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_contrast_ratio() {
+        let ratio = contrast_ratio((0, 0, 0), (255, 255, 255));
+        assert!((ratio - MAX_CONTRAST).abs() < 0.01);
+        assert_eq!(contrast_ratio((10, 20, 30), (10, 20, 30)), 1.0);
+        // Order does not matter
+        assert_eq!(
+            contrast_ratio((200, 0, 0), (0, 0, 50)),
+            contrast_ratio((0, 0, 50), (200, 0, 0))
+        );
+    }
+
+    #[test]
+    fn test_ensure_contrast_disabled() {
+        let fg = (40, 40, 40);
+        let bg = (30, 30, 30);
+        assert_eq!(ensure_contrast(fg, bg, CONTRAST_DISABLED), fg);
+    }
+
+    #[test]
+    fn test_ensure_contrast_already_sufficient() {
+        let fg = (255, 255, 255);
+        let bg = (0, 0, 0);
+        assert_eq!(ensure_contrast(fg, bg, 4.5), fg);
+    }
+
+    #[test]
+    fn test_ensure_contrast_adjusts() {
+        let bg = (30, 30, 30);
+        let fg = (50, 50, 50);
+        let adjusted = ensure_contrast(fg, bg, 4.5);
+        assert!(contrast_ratio(adjusted, bg) >= 4.5);
+        // Dark background pushes the foreground lighter
+        assert!(adjusted.0 > fg.0);
+
+        let bg = (250, 250, 250);
+        let fg = (230, 230, 230);
+        let adjusted = ensure_contrast(fg, bg, 4.5);
+        assert!(contrast_ratio(adjusted, bg) >= 4.5);
+        assert!(adjusted.0 < fg.0);
+    }
+
+    #[test]
+    fn test_ensure_contrast_keeps_invisible_text() {
+        let c = (30, 30, 30);
+        assert_eq!(ensure_contrast(c, c, 7.0), c);
+    }
+}
@@ -0,0 +1,309 @@
+//! Control socket (JSON-RPC over a unix socket)
+//!
+//! With `[control] enabled = true`, mochi listens on a unix socket for
+//! JSON-RPC 2.0 requests, one JSON object per line, so scripts and
+//! end-to-end tests can read the screen, type into tabs, open tabs and
+//! change settings without looking at the window. Each connection is served
+//! by its own thread, which hands requests to the event loop and writes back
+//! the answers in order.
+//!
+//! The socket is only accessible to the user (mode 0600), but anyone who can
+//! connect can type into every shell.
+
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+
+use serde_json::{json, Value};
+
+/// Environment variable that tells programs in the shells where the socket is
+pub const SOCKET_ENV: &str = "MOCHI_CONTROL_SOCKET";
+
+/// Longest request line accepted, in bytes
+const MAX_REQUEST_LEN: u64 = 1024 * 1024;
+
+/// A JSON-RPC error
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl RpcError {
+    pub const PARSE_ERROR: i64 = -32700;
+    pub const INVALID_REQUEST: i64 = -32600;
+    pub const METHOD_NOT_FOUND: i64 = -32601;
+    pub const INVALID_PARAMS: i64 = -32602;
+    /// The request was valid but could not be carried out
+    pub const FAILED: i64 = -32000;
+
+    pub fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+
+    pub fn method_not_found(method: &str) -> Self {
+        Self::new(
+            Self::METHOD_NOT_FOUND,
+            format!("Unknown method '{}'", method),
+        )
+    }
+
+    pub fn invalid_params(message: impl Into<String>) -> Self {
+        Self::new(Self::INVALID_PARAMS, message)
+    }
+
+    pub fn failed(message: impl Into<String>) -> Self {
+        Self::new(Self::FAILED, message)
+    }
+}
+
+/// A request waiting to be handled by the event loop
+#[derive(Debug)]
+pub struct ControlRequest {
+    pub method: String,
+    pub params: Value,
+    /// Request id; notifications (without one) get no response
+    id: Option<Value>,
+    reply: Sender<Option<String>>,
+}
+
+impl ControlRequest {
+    /// Send the result back to the client
+    pub fn respond(self, result: Result<Value, RpcError>) {
+        let response = self.id.map(|id| response(&id, result));
+        let _ = self.reply.send(response);
+    }
+}
+
+/// A JSON-RPC response line
+fn response(id: &Value, result: Result<Value, RpcError>) -> String {
+    let response = match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(error) => json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": { "code": error.code, "message": error.message },
+        }),
+    };
+    response.to_string()
+}
+
+/// Parse a request line into (id, method, params), or the error response
+fn parse_request(line: &str) -> Result<(Option<Value>, String, Value), String> {
+    let value: Value = serde_json::from_str(line).map_err(|e| {
+        let error = RpcError::new(RpcError::PARSE_ERROR, e.to_string());
+        response(&Value::Null, Err(error))
+    })?;
+    let id = value.get("id").cloned();
+    let invalid = |message: &str| {
+        let error = RpcError::new(RpcError::INVALID_REQUEST, message);
+        response(id.as_ref().unwrap_or(&Value::Null), Err(error))
+    };
+    if value.get("jsonrpc").and_then(Value::as_str) != Some("2.0") {
+        return Err(invalid("Expected \"jsonrpc\": \"2.0\""));
+    }
+    let Some(method) = value.get("method").and_then(Value::as_str) else {
+        return Err(invalid("Missing method"));
+    };
+    let params = value.get("params").cloned().unwrap_or(Value::Null);
+    if !(params.is_object() || params.is_null()) {
+        return Err(invalid("Params must be an object"));
+    }
+    Ok((id, method.to_string(), params))
+}
+
+/// Default socket path: one per process, in the user's runtime directory
+pub fn default_socket_path() -> PathBuf {
+    dirs::runtime_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("mochi")
+        .join(format!("control-{}.sock", std::process::id()))
+}
+
+/// A listening control socket; the socket file is removed on drop
+#[derive(Debug)]
+pub struct ControlServer {
+    path: PathBuf,
+}
+
+impl ControlServer {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for ControlServer {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Listen on `path` and pass each request to `submit`
+///
+/// `submit` returns false once the event loop is gone, which ends the
+/// connection. A stale socket file left by a crashed process is replaced;
+/// one that still accepts connections is an error.
+pub fn start(
+    path: &Path,
+    submit: impl Fn(ControlRequest) -> bool + Clone + Send + 'static,
+) -> io::Result<ControlServer> {
+    if let Some(dir) = path.parent() {
+        fs::DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(dir)?;
+    }
+    if path.exists() {
+        if UnixStream::connect(path).is_ok() {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrInUse,
+                format!("{} is in use", path.display()),
+            ));
+        }
+        fs::remove_file(path)?;
+    }
+    let listener = UnixListener::bind(path)?;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+
+    thread::Builder::new()
+        .name("control-socket".to_string())
+        .spawn(move || {
+            for stream in listener.incoming() {
+                let stream = match stream {
+                    Ok(stream) => stream,
+                    Err(e) => {
+                        log::warn!("Control socket accept failed: {}", e);
+                        continue;
+                    }
+                };
+                let submit = submit.clone();
+                let spawned = thread::Builder::new()
+                    .name("control-client".to_string())
+                    .spawn(move || {
+                        if let Err(e) = serve(stream, submit) {
+                            log::debug!("Control connection closed: {}", e);
+                        }
+                    });
+                if let Err(e) = spawned {
+                    log::warn!("Failed to serve a control connection: {}", e);
+                }
+            }
+        })?;
+
+    log::info!("Control socket listening on {}", path.display());
+    Ok(ControlServer {
+        path: path.to_path_buf(),
+    })
+}
+
+/// Answer the requests of one connection until it closes
+fn serve(stream: UnixStream, submit: impl Fn(ControlRequest) -> bool) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+    let mut line = String::new();
+    loop {
+        line.clear();
+        let read = Read::by_ref(&mut reader)
+            .take(MAX_REQUEST_LEN)
+            .read_line(&mut line)?;
+        if read == 0 {
+            return Ok(());
+        }
+        if !line.ends_with('\n') && read as u64 == MAX_REQUEST_LEN {
+            let error = RpcError::new(RpcError::INVALID_REQUEST, "Request too long");
+            writeln!(writer, "{}", response(&Value::Null, Err(error)))?;
+            return Ok(());
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let reply = match parse_request(&line) {
+            Err(response) => Some(response),
+            Ok((id, method, params)) => {
+                let (reply, answer) = mpsc::channel();
+                let request = ControlRequest {
+                    method,
+                    params,
+                    id,
+                    reply,
+                };
+                if !submit(request) {
+                    return Ok(());
+                }
+                match answer.recv() {
+                    Ok(reply) => reply,
+                    Err(_) => return Ok(()),
+                }
+            }
+        };
+        if let Some(reply) = reply {
+            writeln!(writer, "{}", reply)?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_request() {
+        let (id, method, params) =
+            parse_request(r#"{"jsonrpc":"2.0","id":1,"method":"get_text","params":{"tab":0}}"#)
+                .unwrap();
+        assert_eq!(id, Some(json!(1)));
+        assert_eq!(method, "get_text");
+        assert_eq!(params, json!({ "tab": 0 }));
+
+        let err = parse_request("not json").unwrap_err();
+        assert!(err.contains("-32700"));
+        let err = parse_request(r#"{"jsonrpc":"2.0","id":"a","params":[]}"#).unwrap_err();
+        assert!(err.contains("-32600") && err.contains("\"a\""));
+    }
+
+    #[test]
+    fn test_socket_round_trip() {
+        let dir = std::env::temp_dir().join(format!("mochi-control-test-{}", std::process::id()));
+        let path = dir.join("control.sock");
+        let server = start(&path, |request: ControlRequest| {
+            let result = match request.method.as_str() {
+                "echo" => Ok(request.params.clone()),
+                method => Err(RpcError::method_not_found(method)),
+            };
+            request.respond(result);
+            true
+        })
+        .unwrap();
+        let mode = fs::metadata(server.path()).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        let mut stream = UnixStream::connect(&path).unwrap();
+        writeln!(
+            stream,
+            r#"{{"jsonrpc":"2.0","id":1,"method":"echo","params":{{"a":1}}}}"#
+        )
+        .unwrap();
+        // A notification gets no response
+        writeln!(stream, r#"{{"jsonrpc":"2.0","method":"echo"}}"#).unwrap();
+        writeln!(stream, r#"{{"jsonrpc":"2.0","id":2,"method":"nope"}}"#).unwrap();
+
+        let mut lines = BufReader::new(stream).lines();
+        let first: Value = serde_json::from_str(&lines.next().unwrap().unwrap()).unwrap();
+        assert_eq!(first["result"], json!({ "a": 1 }));
+        let second: Value = serde_json::from_str(&lines.next().unwrap().unwrap()).unwrap();
+        assert_eq!(second["id"], json!(2));
+        assert_eq!(second["error"]["code"], json!(RpcError::METHOD_NOT_FOUND));
+
+        drop(server);
+        assert!(!path.exists());
+        let _ = fs::remove_dir(&dir);
+    }
+}
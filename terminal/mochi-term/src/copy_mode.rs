@@ -0,0 +1,612 @@
+//! Keyboard-driven selection (copy mode)
+//!
+//! Copy mode moves a cursor over the screen and scrollback with vi-style
+//! keys, without sending anything to the shell. `v`, `V` and `Ctrl+v` start
+//! a character, line or block selection, `y` or Enter copies it, and `/` or
+//! `?` search forwards or backwards. Rows are numbered like selections:
+//! 0 is the top of the screen and scrollback rows are negative.
+
+use terminal_core::{Point, Screen, Selection, SelectionType, TextRegion};
+
+/// A key press in copy mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CopyKey {
+    Char(char),
+    /// A letter typed with Ctrl held
+    Ctrl(char),
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Enter,
+    Escape,
+    Backspace,
+}
+
+/// What the window should do after a key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CopyAction {
+    /// Nothing changed
+    None,
+    /// The cursor, selection or status line changed
+    Redraw,
+    /// Copy this text and leave copy mode
+    Yank(String),
+    /// Leave copy mode
+    Exit,
+}
+
+/// Character classes for word motions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CharClass {
+    Space,
+    Word,
+    Punctuation,
+}
+
+fn char_class(c: char) -> CharClass {
+    if c.is_whitespace() || c == '\0' {
+        CharClass::Space
+    } else if c.is_alphanumeric() || c == '_' {
+        CharClass::Word
+    } else {
+        CharClass::Punctuation
+    }
+}
+
+/// A search being typed
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct SearchInput {
+    query: String,
+    backward: bool,
+}
+
+/// Copy mode state for one tab
+#[derive(Debug, Clone)]
+pub struct CopyMode {
+    cursor: Point,
+    /// Where the selection started, and its type
+    anchor: Option<(Point, SelectionType)>,
+    /// Search being typed after `/` or `?`
+    search: Option<SearchInput>,
+    /// Last search, repeated by `n` and `N`
+    last_search: Option<SearchInput>,
+    /// Count typed before a motion (the 5 in `5j`)
+    count: Option<usize>,
+    /// `g` was pressed; a second `g` moves to the top
+    pending_g: bool,
+}
+
+impl CopyMode {
+    /// Enter copy mode at the terminal cursor, or at the top of the view
+    /// when scrolled back
+    pub fn new(screen: &Screen, scroll_offset: usize) -> Self {
+        let cursor = if scroll_offset == 0 {
+            Point::new(screen.cursor().col, screen.cursor().row as isize)
+        } else {
+            Point::new(0, -(scroll_offset as isize))
+        };
+        Self {
+            cursor,
+            anchor: None,
+            search: None,
+            last_search: None,
+            count: None,
+            pending_g: false,
+        }
+    }
+
+    pub fn cursor(&self) -> Point {
+        self.cursor
+    }
+
+    /// Selection to highlight, if one has been started
+    pub fn selection(&self) -> Option<Selection> {
+        let (anchor, selection_type) = self.anchor?;
+        let mut selection = Selection::new();
+        selection.start(anchor, selection_type);
+        selection.update(self.cursor);
+        Some(selection)
+    }
+
+    /// Text for the status line
+    pub fn status(&self) -> String {
+        if let Some(search) = &self.search {
+            let prompt = if search.backward { '?' } else { '/' };
+            return format!("{}{}_", prompt, search.query);
+        }
+        match self.anchor {
+            None => "Copy mode".to_string(),
+            Some((_, SelectionType::Line)) => "Copy mode: visual line".to_string(),
+            Some((_, SelectionType::Block)) => "Copy mode: visual block".to_string(),
+            Some(_) => "Copy mode: visual".to_string(),
+        }
+    }
+
+    /// Scroll offset that keeps the cursor in view, starting from `offset`
+    pub fn scroll_offset_for(&self, screen: &Screen, offset: usize) -> usize {
+        let rows = screen.rows() as isize;
+        let top = -(offset as isize);
+        if self.cursor.row < top {
+            (-self.cursor.row) as usize
+        } else if self.cursor.row >= top + rows {
+            (rows - 1 - self.cursor.row).max(0) as usize
+        } else {
+            offset
+        }
+    }
+
+    /// Handle a key press
+    pub fn handle_key(&mut self, key: CopyKey, screen: &Screen) -> CopyAction {
+        if self.search.is_some() {
+            return self.handle_search_key(key, screen);
+        }
+
+        // Counts: a leading 0 is the start-of-line motion instead
+        if let CopyKey::Char(c @ '0'..='9') = key {
+            if c != '0' || self.count.is_some() {
+                let digit = c.to_digit(10).unwrap_or(0) as usize;
+                let count = self.count.unwrap_or(0).saturating_mul(10) + digit;
+                self.count = Some(count.min(100_000));
+                return CopyAction::None;
+            }
+        }
+        let count = self.count.take().unwrap_or(1);
+        let pending_g = std::mem::take(&mut self.pending_g);
+
+        let rows = screen.rows() as isize;
+        let before = (self.cursor, self.anchor);
+        match key {
+            CopyKey::Escape if self.anchor.is_some() => self.anchor = None,
+            CopyKey::Escape | CopyKey::Char('q') => return CopyAction::Exit,
+            CopyKey::Enter | CopyKey::Char('y') => {
+                return match self.selected_text(screen) {
+                    Some(text) => CopyAction::Yank(text),
+                    None => CopyAction::Exit,
+                };
+            }
+
+            CopyKey::Char('h') | CopyKey::Left | CopyKey::Backspace => {
+                self.cursor.col = self.cursor.col.saturating_sub(count);
+            }
+            CopyKey::Char('l') | CopyKey::Right => {
+                self.cursor.col = (self.cursor.col + count).min(screen.cols() - 1);
+            }
+            CopyKey::Char('k') | CopyKey::Up => self.move_rows(-(count as isize), screen),
+            CopyKey::Char('j') | CopyKey::Down => self.move_rows(count as isize, screen),
+            CopyKey::Char('0') | CopyKey::Home => self.cursor.col = 0,
+            CopyKey::Char('^') => {
+                let chars = row_chars(screen, self.cursor.row);
+                self.cursor.col = chars
+                    .iter()
+                    .position(|&c| char_class(c) != CharClass::Space)
+                    .unwrap_or(0);
+            }
+            CopyKey::Char('$') | CopyKey::End => {
+                let chars = row_chars(screen, self.cursor.row);
+                self.cursor.col = chars
+                    .iter()
+                    .rposition(|&c| char_class(c) != CharClass::Space)
+                    .unwrap_or(0);
+            }
+            CopyKey::Char('w') => (0..count).for_each(|_| self.word_forward(screen)),
+            CopyKey::Char('b') => (0..count).for_each(|_| self.word_backward(screen)),
+            CopyKey::Char('e') => (0..count).for_each(|_| self.word_end(screen)),
+            CopyKey::Char('g') if pending_g => {
+                self.cursor = Point::new(0, min_row(screen));
+            }
+            CopyKey::Char('g') => {
+                self.pending_g = true;
+                return CopyAction::None;
+            }
+            CopyKey::Char('G') => self.cursor = Point::new(0, rows - 1),
+            CopyKey::Ctrl('u') => self.move_rows(-(rows / 2) * count as isize, screen),
+            CopyKey::Ctrl('d') => self.move_rows((rows / 2) * count as isize, screen),
+            CopyKey::Ctrl('b') | CopyKey::PageUp => self.move_rows(-rows * count as isize, screen),
+            CopyKey::Ctrl('f') | CopyKey::PageDown => self.move_rows(rows * count as isize, screen),
+
+            CopyKey::Char('v') => self.toggle_selection(SelectionType::Normal),
+            CopyKey::Char('V') => self.toggle_selection(SelectionType::Line),
+            CopyKey::Ctrl('v') => self.toggle_selection(SelectionType::Block),
+
+            CopyKey::Char('/') | CopyKey::Char('?') => {
+                self.search = Some(SearchInput {
+                    query: String::new(),
+                    backward: key == CopyKey::Char('?'),
+                });
+                return CopyAction::Redraw;
+            }
+            CopyKey::Char('n') | CopyKey::Char('N') => {
+                if let Some(search) = self.last_search.clone() {
+                    let backward = search.backward != (key == CopyKey::Char('N'));
+                    for _ in 0..count {
+                        self.find(&search.query, backward, screen);
+                    }
+                }
+            }
+            _ => return CopyAction::None,
+        }
+
+        if (self.cursor, self.anchor) == before {
+            CopyAction::None
+        } else {
+            CopyAction::Redraw
+        }
+    }
+
+    fn handle_search_key(&mut self, key: CopyKey, screen: &Screen) -> CopyAction {
+        match (key, &mut self.search) {
+            (CopyKey::Char(c), Some(search)) => search.query.push(c),
+            (CopyKey::Backspace, Some(search)) => {
+                search.query.pop();
+            }
+            (CopyKey::Escape, _) => self.search = None,
+            (CopyKey::Enter, _) => {
+                if let Some(search) = self.search.take().filter(|s| !s.query.is_empty()) {
+                    self.find(&search.query, search.backward, screen);
+                    self.last_search = Some(search);
+                }
+            }
+            _ => return CopyAction::None,
+        }
+        CopyAction::Redraw
+    }
+
+    fn toggle_selection(&mut self, selection_type: SelectionType) {
+        self.anchor = match self.anchor {
+            Some((_, current)) if current == selection_type => None,
+            // Switching type keeps the anchor
+            Some((anchor, _)) => Some((anchor, selection_type)),
+            None => Some((self.cursor, selection_type)),
+        };
+    }
+
+    fn move_rows(&mut self, delta: isize, screen: &Screen) {
+        let max_row = screen.rows() as isize - 1;
+        self.cursor.row = (self.cursor.row + delta).clamp(min_row(screen), max_row);
+    }
+
+    /// Move to the start of the next word (`w`)
+    fn word_forward(&mut self, screen: &Screen) {
+        let mut pos = self.cursor;
+        let mut chars = row_chars(screen, pos.row);
+        let start = class_at(&chars, pos.col);
+        // Skip the rest of the current word; a line break ends it
+        let mut crossed_line = false;
+        while !crossed_line && start != CharClass::Space && class_at(&chars, pos.col) == start {
+            let Some((next, crossed)) = step_forward(pos, &chars, screen) else {
+                return;
+            };
+            pos = next;
+            if crossed {
+                chars = row_chars(screen, pos.row);
+                crossed_line = true;
+            }
+        }
+        // Then any blanks
+        while class_at(&chars, pos.col) == CharClass::Space {
+            let Some((next, crossed)) = step_forward(pos, &chars, screen) else {
+                return;
+            };
+            pos = next;
+            if crossed {
+                chars = row_chars(screen, pos.row);
+            }
+        }
+        self.cursor = pos;
+    }
+
+    /// Move to the start of the previous word (`b`)
+    fn word_backward(&mut self, screen: &Screen) {
+        let mut pos = self.cursor;
+        let mut chars = row_chars(screen, pos.row);
+        // Step back at least once, then over blanks
+        loop {
+            match step_backward(pos, screen) {
+                Some((next, crossed)) => {
+                    pos = next;
+                    if crossed {
+                        chars = row_chars(screen, pos.row);
+                    }
+                }
+                None => return,
+            }
+            if class_at(&chars, pos.col) != CharClass::Space {
+                break;
+            }
+        }
+        // Then to the first character of the word
+        let class = class_at(&chars, pos.col);
+        while pos.col > 0 && class_at(&chars, pos.col - 1) == class {
+            pos.col -= 1;
+        }
+        self.cursor = pos;
+    }
+
+    /// Move to the end of the word (`e`)
+    fn word_end(&mut self, screen: &Screen) {
+        let mut pos = self.cursor;
+        let mut chars = row_chars(screen, pos.row);
+        loop {
+            match step_forward(pos, &chars, screen) {
+                Some((next, crossed)) => {
+                    pos = next;
+                    if crossed {
+                        chars = row_chars(screen, pos.row);
+                    }
+                }
+                None => return,
+            }
+            if class_at(&chars, pos.col) != CharClass::Space {
+                break;
+            }
+        }
+        let class = class_at(&chars, pos.col);
+        while pos.col + 1 < chars.len() && class_at(&chars, pos.col + 1) == class {
+            pos.col += 1;
+        }
+        self.cursor = pos;
+    }
+
+    /// Move to the next (or previous) match of `query`, wrapping around
+    ///
+    /// A query without capitals matches case-insensitively.
+    fn find(&mut self, query: &str, backward: bool, screen: &Screen) {
+        let ignore_case = !query.chars().any(char::is_uppercase);
+        let fold = |s: String| if ignore_case { s.to_lowercase() } else { s };
+        let query = fold(query.to_string());
+
+        let min = min_row(screen);
+        let total = screen.rows() as isize - min;
+        for step in 0..=total {
+            let offset = if backward { -step } else { step };
+            let row = min + (self.cursor.row - min + offset).rem_euclid(total);
+            let chars = row_chars(screen, row);
+            let text = fold(chars.iter().collect());
+            // Byte offsets of the matches, converted back to columns
+            let cols: Vec<usize> = text
+                .match_indices(&query)
+                .map(|(i, _)| text[..i].chars().count())
+                .collect();
+            let found = match (step, backward) {
+                (0, false) => cols.into_iter().find(|&c| c > self.cursor.col),
+                (0, true) => cols.into_iter().rev().find(|&c| c < self.cursor.col),
+                (_, false) => cols.into_iter().next(),
+                (_, true) => cols.into_iter().next_back(),
+            };
+            if let Some(col) = found {
+                self.cursor = Point::new(col, row);
+                return;
+            }
+        }
+    }
+
+    /// Text covered by the selection, or None if nothing is selected
+    pub fn selected_text(&self, screen: &Screen) -> Option<String> {
+        let (anchor, selection_type) = self.anchor?;
+        let (start, end) = if (anchor.row, anchor.col) <= (self.cursor.row, self.cursor.col) {
+            (anchor, self.cursor)
+        } else {
+            (self.cursor, anchor)
+        };
+        let region = match selection_type {
+            SelectionType::Line => TextRegion::Rows {
+                top: start.row,
+                bottom: end.row,
+            },
+            SelectionType::Block => TextRegion::Block { start, end },
+            _ => TextRegion::Stream { start, end },
+        };
+        Some(screen.text_region(region, true))
+    }
+}
+
+/// Topmost row (the oldest scrollback line)
+fn min_row(screen: &Screen) -> isize {
+    -(screen.scrollback().len() as isize)
+}
+
+/// Characters of a row, one per column (wide characters fill both columns)
+fn row_chars(screen: &Screen, row: isize) -> Vec<char> {
+    let Some(line) = screen.line_at(row) else {
+        return Vec::new();
+    };
+    let mut chars = Vec::with_capacity(line.cols());
+    for col in 0..line.cols() {
+        let cell = line.cell(col);
+        let c = if cell.is_continuation() {
+            chars.last().copied().unwrap_or(' ')
+        } else {
+            cell.display_char()
+        };
+        chars.push(c);
+    }
+    chars
+}
+
+fn class_at(chars: &[char], col: usize) -> CharClass {
+    chars.get(col).copied().map_or(CharClass::Space, char_class)
+}
+
+/// Next position, and whether it moved to a new row
+fn step_forward(pos: Point, chars: &[char], screen: &Screen) -> Option<(Point, bool)> {
+    if pos.col + 1 < chars.len() {
+        Some((Point::new(pos.col + 1, pos.row), false))
+    } else if pos.row + 1 < screen.rows() as isize {
+        Some((Point::new(0, pos.row + 1), true))
+    } else {
+        None
+    }
+}
+
+/// Previous position, and whether it moved to a new row
+fn step_backward(pos: Point, screen: &Screen) -> Option<(Point, bool)> {
+    if pos.col > 0 {
+        Some((Point::new(pos.col - 1, pos.row), false))
+    } else if pos.row > min_row(screen) {
+        let row = pos.row - 1;
+        let cols = row_chars(screen, row).len();
+        Some((Point::new(cols.saturating_sub(1), row), true))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use terminal_core::Dimensions;
+
+    /// A 20x3 screen with two lines in scrollback
+    fn screen() -> Screen {
+        let mut screen = Screen::new(Dimensions::new(20, 3));
+        let lines = [
+            "first line",
+            "foo.bar baz",
+            "hello world",
+            "error: oops",
+            "$ ",
+        ];
+        for (i, line) in lines.iter().enumerate() {
+            for c in line.chars() {
+                screen.print(c);
+            }
+            if i + 1 < lines.len() {
+                screen.carriage_return();
+                screen.linefeed();
+            }
+        }
+        screen
+    }
+
+    fn keys(mode: &mut CopyMode, screen: &Screen, keys: &str) -> CopyAction {
+        let mut action = CopyAction::None;
+        for c in keys.chars() {
+            let key = match c {
+                '\n' => CopyKey::Enter,
+                '\x1b' => CopyKey::Escape,
+                c => CopyKey::Char(c),
+            };
+            action = mode.handle_key(key, screen);
+        }
+        action
+    }
+
+    #[test]
+    fn test_copy_mode_starts_at_cursor() {
+        let screen = screen();
+        assert_eq!(screen.scrollback().len(), 2);
+        let mode = CopyMode::new(&screen, 0);
+        assert_eq!(mode.cursor(), Point::new(2, 2));
+        assert_eq!(CopyMode::new(&screen, 2).cursor(), Point::new(0, -2));
+        assert!(mode.selection().is_none());
+        assert_eq!(mode.status(), "Copy mode");
+    }
+
+    #[test]
+    fn test_copy_mode_motions() {
+        let screen = screen();
+        let mut mode = CopyMode::new(&screen, 0);
+        keys(&mut mode, &screen, "gg");
+        assert_eq!(mode.cursor(), Point::new(0, -2));
+        keys(&mut mode, &screen, "w");
+        assert_eq!(mode.cursor(), Point::new(6, -2));
+        // The line break ends a word
+        keys(&mut mode, &screen, "w");
+        assert_eq!(mode.cursor(), Point::new(0, -1));
+        // Punctuation is its own word
+        keys(&mut mode, &screen, "w");
+        assert_eq!(mode.cursor(), Point::new(3, -1));
+        keys(&mut mode, &screen, "e");
+        assert_eq!(mode.cursor(), Point::new(6, -1));
+        keys(&mut mode, &screen, "b");
+        assert_eq!(mode.cursor(), Point::new(4, -1));
+        keys(&mut mode, &screen, "$");
+        assert_eq!(mode.cursor(), Point::new(10, -1));
+        keys(&mut mode, &screen, "2j0");
+        assert_eq!(mode.cursor(), Point::new(0, 1));
+        keys(&mut mode, &screen, "G");
+        assert_eq!(mode.cursor(), Point::new(0, 2));
+        // Movement is clamped to the buffer
+        keys(&mut mode, &screen, "99j");
+        assert_eq!(mode.cursor(), Point::new(0, 2));
+    }
+
+    #[test]
+    fn test_copy_mode_yank_selection() {
+        let screen = screen();
+        let mut mode = CopyMode::new(&screen, 0);
+        keys(&mut mode, &screen, "kkk$b");
+        assert_eq!(mode.cursor(), Point::new(8, -1));
+        keys(&mut mode, &screen, "vj$");
+        assert_eq!(mode.status(), "Copy mode: visual");
+        assert!(mode.selection().is_some());
+        assert_eq!(
+            keys(&mut mode, &screen, "y"),
+            CopyAction::Yank("baz\nhello world".to_string())
+        );
+
+        let mut mode = CopyMode::new(&screen, 0);
+        assert_eq!(
+            keys(&mut mode, &screen, "kVk\n"),
+            CopyAction::Yank("hello world\nerror: oops".to_string())
+        );
+
+        // Nothing selected: yank just leaves copy mode
+        let mut mode = CopyMode::new(&screen, 0);
+        assert_eq!(keys(&mut mode, &screen, "y"), CopyAction::Exit);
+    }
+
+    #[test]
+    fn test_copy_mode_block_selection() {
+        let screen = screen();
+        let mut mode = CopyMode::new(&screen, 0);
+        keys(&mut mode, &screen, "gg");
+        mode.handle_key(CopyKey::Ctrl('v'), &screen);
+        assert_eq!(mode.status(), "Copy mode: visual block");
+        assert_eq!(
+            keys(&mut mode, &screen, "jlly"),
+            CopyAction::Yank("fir\nfoo".to_string())
+        );
+    }
+
+    #[test]
+    fn test_copy_mode_search() {
+        let screen = screen();
+        let mut mode = CopyMode::new(&screen, 0);
+        keys(&mut mode, &screen, "?o");
+        assert_eq!(mode.status(), "?o_");
+        keys(&mut mode, &screen, "\n");
+        assert_eq!(mode.cursor(), Point::new(8, 1));
+        keys(&mut mode, &screen, "n");
+        assert_eq!(mode.cursor(), Point::new(7, 1));
+        keys(&mut mode, &screen, "N");
+        assert_eq!(mode.cursor(), Point::new(8, 1));
+
+        // Wraps from the bottom to the top, and ignores case without capitals
+        keys(&mut mode, &screen, "G/FIRST\n");
+        assert_eq!(mode.cursor(), Point::new(0, 2));
+        keys(&mut mode, &screen, "/first\n");
+        assert_eq!(mode.cursor(), Point::new(0, -2));
+    }
+
+    #[test]
+    fn test_copy_mode_escape_and_scroll() {
+        let screen = screen();
+        let mut mode = CopyMode::new(&screen, 0);
+        keys(&mut mode, &screen, "v");
+        assert_eq!(keys(&mut mode, &screen, "\x1b"), CopyAction::Redraw);
+        assert!(mode.selection().is_none());
+        assert_eq!(keys(&mut mode, &screen, "\x1b"), CopyAction::Exit);
+
+        keys(&mut mode, &screen, "gg");
+        assert_eq!(mode.scroll_offset_for(&screen, 0), 2);
+        keys(&mut mode, &screen, "G");
+        assert_eq!(mode.scroll_offset_for(&screen, 2), 0);
+        assert_eq!(mode.scroll_offset_for(&screen, 1), 0);
+    }
+}
@@ -1,14 +1,27 @@
 //! Event handling for the terminal application
 
-use std::time::Duration;
+use std::time::{Duration, Instant};
+
+use crate::control::ControlRequest;
+
+/// Frame rate when the monitor does not report its refresh rate
+const DEFAULT_FPS: u32 = 60;
 
 /// Events that can occur in the terminal application
 #[derive(Debug)]
 #[allow(dead_code)]
 pub enum TerminalEvent {
+    /// A PTY has output to read (sent by its watcher thread)
+    PtyReadable,
+    /// The config file or a theme file changed on disk
+    ConfigChanged,
+    /// A request arrived on the control socket
+    Control(ControlRequest),
+    /// Another launch of mochi asked to open a window (single-instance mode)
+    Instance(ControlRequest),
     /// Data received from PTY
     PtyOutput(Vec<u8>),
-    /// Child process exited
+    /// A shell exited and was reaped (sent by the reaper thread with its pid)
     ChildExited(i32),
     /// Window resize
     Resize { cols: u16, rows: u16 },
@@ -21,27 +34,64 @@ pub enum TerminalEvent {
 }
 
 /// Event loop timing
-#[allow(dead_code)]
+///
+/// Frames are rendered no faster than the monitor refreshes, or than
+/// `max_fps` if it is lower. Changes that arrive sooner after a frame are
+/// coalesced into the next one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct EventTiming {
     /// Target frame rate
     pub target_fps: u32,
-    /// PTY poll interval
-    pub pty_poll_interval: Duration,
 }
 
 impl Default for EventTiming {
     fn default() -> Self {
         Self {
-            target_fps: 60,
-            pty_poll_interval: Duration::from_millis(1),
+            target_fps: DEFAULT_FPS,
         }
     }
 }
 
 impl EventTiming {
+    /// Timing for a monitor refresh rate (if known), capped at `max_fps`
+    /// (0 = no cap)
+    pub fn new(refresh_millihertz: Option<u32>, max_fps: u32) -> Self {
+        let refresh = refresh_millihertz
+            .map(|mhz| (mhz + 500) / 1000)
+            .filter(|&hz| hz > 0)
+            .unwrap_or(DEFAULT_FPS);
+        let target_fps = match max_fps {
+            0 => refresh,
+            max => refresh.min(max),
+        };
+        Self { target_fps }
+    }
+
     /// Get frame duration
-    #[allow(dead_code)]
     pub fn frame_duration(&self) -> Duration {
         Duration::from_secs_f64(1.0 / self.target_fps as f64)
     }
+
+    /// When the frame after one rendered at `last_render` may be rendered
+    pub fn next_frame(&self, last_render: Instant) -> Instant {
+        last_render + self.frame_duration()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_timing() {
+        assert_eq!(EventTiming::new(None, 0).target_fps, 60);
+        assert_eq!(EventTiming::new(Some(143_856), 0).target_fps, 144);
+        assert_eq!(EventTiming::new(Some(143_856), 30).target_fps, 30);
+        assert_eq!(EventTiming::new(Some(60_000), 120).target_fps, 60);
+        assert_eq!(EventTiming::new(Some(0), 0).target_fps, 60);
+
+        let timing = EventTiming::new(Some(50_000), 0);
+        let now = Instant::now();
+        assert_eq!(timing.next_frame(now), now + Duration::from_millis(20));
+    }
 }
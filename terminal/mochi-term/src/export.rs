@@ -0,0 +1,313 @@
+//! Screen dumps (the screen or the scrollback saved to a file)
+//!
+//! Ctrl+Shift+S saves the screen and Ctrl+Shift+B the whole scrollback with
+//! the screen below it. The extension of the file name picks the format:
+//! `.html` or `.htm` for a page that reproduces colors and attributes with
+//! inline styles, `.ans` or `.ansi` for the text with SGR sequences (for
+//! `cat` or `less -R`), and plain text otherwise. Soft-wrapped lines are
+//! joined, as when copying.
+
+use std::fmt::Write;
+use std::path::Path;
+
+use terminal_core::{CellAttributes, Color, Line, Screen};
+
+use crate::config::ColorScheme;
+
+/// What a dump is written as
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Text,
+    Html,
+    Ansi,
+}
+
+impl Format {
+    /// The format for a file name, by its extension
+    pub fn from_path(path: &Path) -> Self {
+        let extension = path
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase());
+        match extension.as_deref() {
+            Some("html" | "htm") => Format::Html,
+            Some("ans" | "ansi") => Format::Ansi,
+            _ => Format::Text,
+        }
+    }
+}
+
+/// Which lines a dump holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Range {
+    /// The lines on the screen
+    Screen,
+    /// The scrollback and the screen below it
+    Scrollback,
+}
+
+impl Range {
+    pub fn name(self) -> &'static str {
+        match self {
+            Range::Screen => "screen",
+            Range::Scrollback => "scrollback",
+        }
+    }
+
+    pub fn lines(self, screen: &Screen) -> Vec<&Line> {
+        let history = match self {
+            Range::Screen => None,
+            Range::Scrollback => Some(screen.scrollback().iter()),
+        };
+        history
+            .into_iter()
+            .flatten()
+            .chain((0..screen.rows()).map(|row| screen.line(row)))
+            .collect()
+    }
+}
+
+/// Write `lines` in `format`, resolving colors with `colors`
+pub fn export(lines: &[&Line], format: Format, colors: &ColorScheme) -> String {
+    let mut out = String::new();
+    if format == Format::Html {
+        let (fg, bg) = (colors.foreground_rgb(), colors.background_rgb());
+        let _ = write!(
+            out,
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>mochi</title>\n</head>\n\
+             <body style=\"margin:0;background:{bg}\">\n\
+             <pre style=\"margin:0;padding:8px;background:{bg};color:{fg};font-family:monospace\">",
+            fg = hex(fg),
+            bg = hex(bg)
+        );
+    }
+    // Blank lines at the end are left out (in the middle they are kept)
+    let used = lines
+        .iter()
+        .rposition(|line| written_cols(line, format) > 0)
+        .map_or(0, |last| last + 1);
+    for line in &lines[..used] {
+        let end = if line.wrapped {
+            line.wrap_end()
+        } else {
+            written_cols(line, format)
+        };
+        for run in line.runs().take_while(|run| run.col < end) {
+            let text = if run.col + run.cols <= end {
+                run.text
+            } else {
+                line.text_range(run.col, end)
+            };
+            match format {
+                Format::Text => out.push_str(&text),
+                Format::Ansi => {
+                    let _ = write!(out, "\x1b[{}m{}", sgr(&run.attrs), text);
+                }
+                Format::Html => match html_style(&run.attrs, colors) {
+                    style if style.is_empty() => out.push_str(&escape_html(&text)),
+                    style => {
+                        let _ = write!(
+                            out,
+                            "<span style=\"{}\">{}</span>",
+                            style,
+                            escape_html(&text)
+                        );
+                    }
+                },
+            }
+        }
+        if format == Format::Ansi && end > 0 {
+            out.push_str("\x1b[0m");
+        }
+        if !line.wrapped {
+            out.push('\n');
+        }
+    }
+    if format == Format::Html {
+        out.push_str("</pre>\n</body>\n</html>\n");
+    }
+    out
+}
+
+/// Columns of a line worth writing: up to the last character, or for the
+/// styled formats the last colored background
+fn written_cols(line: &Line, format: Format) -> usize {
+    if format == Format::Text {
+        return line.content_end();
+    }
+    (0..line.cols())
+        .rev()
+        .find(|&col| {
+            let cell = line.cell(col);
+            !cell.is_empty() || cell.attrs.effective_bg() != Color::Default
+        })
+        .map_or(0, |col| col + line.cell(col).width().max(1) as usize)
+}
+
+/// SGR parameters reproducing `attrs` from a reset
+fn sgr(attrs: &CellAttributes) -> String {
+    let mut params = vec!["0".to_string()];
+    let flags = [
+        (attrs.bold, "1"),
+        (attrs.faint, "2"),
+        (attrs.italic, "3"),
+        (attrs.underline && !attrs.double_underline, "4"),
+        (attrs.double_underline, "21"),
+        (attrs.blink, "5"),
+        (attrs.inverse, "7"),
+        (attrs.hidden, "8"),
+        (attrs.strikethrough, "9"),
+        (attrs.overline, "53"),
+    ];
+    params.extend(
+        flags
+            .iter()
+            .filter(|(set, _)| *set)
+            .map(|(_, param)| param.to_string()),
+    );
+    params.extend(sgr_color(attrs.fg, 30, 90, 38));
+    params.extend(sgr_color(attrs.bg, 40, 100, 48));
+    params.join(";")
+}
+
+fn sgr_color(color: Color, base: u8, bright: u8, extended: u8) -> Option<String> {
+    match color {
+        Color::Default => None,
+        Color::Indexed(index @ 0..=7) => Some((base + index).to_string()),
+        Color::Indexed(index @ 8..=15) => Some((bright + index - 8).to_string()),
+        Color::Indexed(index) => Some(format!("{};5;{}", extended, index)),
+        Color::Rgb { r, g, b } => Some(format!("{};2;{};{};{}", extended, r, g, b)),
+    }
+}
+
+/// Inline CSS for `attrs`; empty for the default look
+fn html_style(attrs: &CellAttributes, colors: &ColorScheme) -> String {
+    let mut style = String::new();
+    let (fg, bg) = (attrs.effective_fg(), attrs.effective_bg());
+    let bg_rgb = resolve(colors, bg, false);
+    if attrs.hidden {
+        let _ = write!(style, "color:{};", hex(bg_rgb));
+    } else if fg != Color::Default || attrs.inverse {
+        let _ = write!(style, "color:{};", hex(resolve(colors, fg, true)));
+    }
+    if bg != Color::Default || attrs.inverse {
+        let _ = write!(style, "background:{};", hex(bg_rgb));
+    }
+    if attrs.bold {
+        style.push_str("font-weight:bold;");
+    }
+    if attrs.faint {
+        style.push_str("opacity:0.6;");
+    }
+    if attrs.italic {
+        style.push_str("font-style:italic;");
+    }
+    let decorations: Vec<&str> = [
+        (attrs.underline || attrs.double_underline, "underline"),
+        (attrs.overline, "overline"),
+        (attrs.strikethrough, "line-through"),
+    ]
+    .iter()
+    .filter(|(set, _)| *set)
+    .map(|(_, name)| *name)
+    .collect();
+    if !decorations.is_empty() {
+        let _ = write!(style, "text-decoration:{};", decorations.join(" "));
+        if attrs.double_underline {
+            style.push_str("text-decoration-style:double;");
+        }
+    }
+    style
+}
+
+/// The color as drawn, like the renderer resolves it
+fn resolve(colors: &ColorScheme, color: Color, is_fg: bool) -> (u8, u8, u8) {
+    match color {
+        Color::Default if is_fg => colors.foreground_rgb(),
+        Color::Default => colors.background_rgb(),
+        Color::Indexed(index) if index < 16 => colors.ansi_rgb(index as usize),
+        color => color.to_rgb(),
+    }
+}
+
+fn hex((r, g, b): (u8, u8, u8)) -> String {
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::terminal::Terminal;
+
+    fn dump(stream: &[u8], range: Range, format: Format) -> String {
+        let mut term = Terminal::new(10, 3);
+        term.process(stream);
+        let lines = range.lines(term.screen());
+        export(&lines, format, &ColorScheme::default())
+    }
+
+    #[test]
+    fn test_format_from_path() {
+        assert_eq!(Format::from_path(Path::new("a.html")), Format::Html);
+        assert_eq!(Format::from_path(Path::new("a.HTM")), Format::Html);
+        assert_eq!(Format::from_path(Path::new("a.ans")), Format::Ansi);
+        assert_eq!(Format::from_path(Path::new("a.txt")), Format::Text);
+        assert_eq!(Format::from_path(Path::new("screen")), Format::Text);
+    }
+
+    #[test]
+    fn test_export_text() {
+        // The wrapped line is joined; blank lines at the end are dropped
+        let stream = b"one\r\n0123456789ab\r\nthree\r\nfour";
+        assert_eq!(
+            dump(stream, Range::Screen, Format::Text),
+            "ab\nthree\nfour\n"
+        );
+        assert_eq!(
+            dump(stream, Range::Scrollback, Format::Text),
+            "one\n0123456789ab\nthree\nfour\n"
+        );
+        assert_eq!(dump(b"", Range::Screen, Format::Text), "");
+    }
+
+    #[test]
+    fn test_export_ansi() {
+        let out = dump(
+            b"\x1b[1;31mred\x1b[0m x\x1b[44m  ",
+            Range::Screen,
+            Format::Ansi,
+        );
+        assert_eq!(out, "\x1b[0;1;31mred\x1b[0m x\x1b[0;44m  \x1b[0m\n");
+        let mut term = Terminal::new(10, 3);
+        term.process(out.as_bytes());
+        let again = Range::Screen.lines(term.screen());
+        assert_eq!(export(&again, Format::Ansi, &ColorScheme::default()), out);
+    }
+
+    #[test]
+    fn test_export_html() {
+        let out = dump(
+            b"<a>\x1b[1;38;2;1;2;3m&\x1b[7m\"",
+            Range::Screen,
+            Format::Html,
+        );
+        assert!(out.starts_with("<!DOCTYPE html>"));
+        assert!(out.contains("&lt;a&gt;"));
+        assert!(out.contains("<span style=\"color:#010203;font-weight:bold;\">&amp;</span>"));
+        assert!(out.contains("background:#010203;font-weight:bold;\">&quot;</span>"));
+        assert!(out.ends_with("</pre>\n</body>\n</html>\n"));
+    }
+}
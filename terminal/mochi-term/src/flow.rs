@@ -0,0 +1,138 @@
+//! Flow control between the PTY and the parser
+//!
+//! Output is read in passes of at most `read_limit` bytes per tab, coalesced
+//! into one buffer and parsed in a single call. Anything beyond the limit
+//! stays in the kernel's PTY buffer; once that fills, the writer blocks, so a
+//! flood from `yes` or `cat` cannot starve input handling or rendering.
+//!
+//! When more than `skip_frames_above` bytes are still pending after a pass,
+//! intermediate frames are skipped and the screen jumps to the latest output.
+
+use std::io;
+use std::time::Duration;
+
+use crate::config::FlowControlConfig;
+
+/// Minimum time between frames while output is being skipped
+pub const SKIP_FRAME_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Size of each read within a pass
+const READ_CHUNK: usize = 64 * 1024;
+
+/// Read throttling settings for one pass over the tabs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FlowControl {
+    /// Maximum bytes read from one PTY per pass
+    read_limit: usize,
+    /// Pending bytes above which frames are skipped (None = never)
+    skip_threshold: Option<usize>,
+}
+
+impl FlowControl {
+    /// Create flow control from the configuration
+    pub fn new(config: &FlowControlConfig) -> Self {
+        Self {
+            read_limit: config.read_limit,
+            skip_threshold: (config.skip_frames_above > 0).then_some(config.skip_frames_above),
+        }
+    }
+
+    /// Read one pass of output into `buf`, replacing its contents
+    ///
+    /// Calls `read` until it returns no data, fails, or `read_limit` bytes
+    /// have been read. Returns the number of bytes read.
+    pub fn read_pass(
+        &self,
+        buf: &mut Vec<u8>,
+        mut read: impl FnMut(&mut [u8]) -> io::Result<usize>,
+    ) -> usize {
+        buf.clear();
+        while buf.len() < self.read_limit {
+            let start = buf.len();
+            let chunk = READ_CHUNK.min(self.read_limit - start);
+            buf.resize(start + chunk, 0);
+            match read(&mut buf[start..]) {
+                Ok(n) if n > 0 => buf.truncate(start + n),
+                _ => {
+                    buf.truncate(start);
+                    break;
+                }
+            }
+        }
+        buf.len()
+    }
+
+    /// Check if frames should be skipped with `pending` bytes left to read
+    pub fn should_skip_frames(&self, pending: usize) -> bool {
+        self.skip_threshold
+            .is_some_and(|threshold| pending > threshold)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flow(read_limit: usize, skip_frames_above: usize) -> FlowControl {
+        FlowControl::new(&FlowControlConfig {
+            read_limit,
+            skip_frames_above,
+        })
+    }
+
+    /// A reader that hands out `data` in pieces of at most `max` bytes
+    fn reader(data: &[u8], max: usize) -> impl FnMut(&mut [u8]) -> io::Result<usize> + '_ {
+        let mut offset = 0;
+        move |out: &mut [u8]| {
+            let n = out.len().min(max).min(data.len() - offset);
+            out[..n].copy_from_slice(&data[offset..offset + n]);
+            offset += n;
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn test_read_pass_coalesces_reads() {
+        let data: Vec<u8> = (0..10_000u32).map(|i| i as u8).collect();
+        let mut buf = Vec::new();
+        let n = flow(65536, 0).read_pass(&mut buf, reader(&data, 100));
+        assert_eq!(n, data.len());
+        assert_eq!(buf, data);
+    }
+
+    #[test]
+    fn test_read_pass_stops_at_limit() {
+        let data = vec![b'y'; 200_000];
+        let mut buf = Vec::new();
+        let flow = flow(4096, 0);
+        let mut read = reader(&data, 1000);
+        assert_eq!(flow.read_pass(&mut buf, &mut read), 4096);
+        assert_eq!(flow.read_pass(&mut buf, &mut read), 4096);
+        assert_eq!(buf.len(), 4096);
+    }
+
+    #[test]
+    fn test_read_pass_stops_on_error() {
+        let mut calls = 0;
+        let mut buf = Vec::new();
+        let n = flow(65536, 0).read_pass(&mut buf, |out| {
+            calls += 1;
+            if calls == 1 {
+                out[..3].copy_from_slice(b"abc");
+                Ok(3)
+            } else {
+                Err(io::Error::from(io::ErrorKind::Other))
+            }
+        });
+        assert_eq!(n, 3);
+        assert_eq!(buf, b"abc");
+    }
+
+    #[test]
+    fn test_should_skip_frames() {
+        assert!(!flow(65536, 0).should_skip_frames(usize::MAX));
+        let flow = flow(65536, 1024);
+        assert!(!flow.should_skip_frames(1024));
+        assert!(flow.should_skip_frames(1025));
+    }
+}
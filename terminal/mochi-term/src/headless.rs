@@ -0,0 +1,131 @@
+//! Headless mode (running a command without a window)
+//!
+//! `mochi --headless --command CMD` runs CMD on a PTY and feeds its output
+//! to the same `Terminal` the window uses, answering its queries (cursor
+//! position reports, device attributes, XTGETTCAP), but draws nothing.
+//! Conformance suites such as esctest probe the terminal they run in with
+//! those queries, so they can be run against mochi this way, in CI or over
+//! SSH. mochi exits with the command's status.
+
+use std::io;
+
+use terminal_pty::{CommandBuilder, ExitStatus, WindowSize};
+
+use crate::config::Config;
+use crate::trace::Trace;
+
+/// Run `command` with `/bin/sh -c` until it exits; returns its exit code
+/// (128 + the signal number if it was killed)
+pub fn run(config: &Config, command: &str) -> io::Result<i32> {
+    let (cols, rows) = config.dimensions;
+    let mut terminal = config
+        .terminal_builder(cols as usize, rows as usize)
+        .build();
+    let mut child = config
+        .with_environment(CommandBuilder::shell_command(command))
+        .spawn(WindowSize::new(cols, rows))
+        .map_err(io::Error::other)?;
+    let mut trace = config.trace_file.clone().map(|path| Trace::new(Some(path)));
+    if let Some(trace) = &mut trace {
+        trace.start()?;
+        terminal.set_trace(true);
+    }
+
+    let mut buf = [0u8; 65536];
+    loop {
+        match child.read(&mut buf) {
+            // EIO once the command and everything it started have exited
+            Ok(0) => break,
+            Err(e) if e.raw_os_error() == Some(libc::EIO) => break,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+            Ok(n) => {
+                terminal.process(&buf[..n]);
+                if let Some(trace) = &mut trace {
+                    trace.record(child.pid().as_raw(), &buf[..n], &terminal.take_trace())?;
+                }
+                for response in terminal.take_pending_responses() {
+                    child.write_all(&response)?;
+                }
+            }
+        }
+    }
+
+    let status = child.wait().map_err(io::Error::other)?;
+    Ok(match ExitStatus::from_wait_status(status) {
+        Some(ExitStatus::Code(code)) => code,
+        Some(ExitStatus::Signal(signal)) => 128 + signal as i32,
+        None => 1,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+    use std::fs;
+    use std::path::PathBuf;
+    use std::process::{Command, Stdio};
+
+    use super::*;
+
+    /// Run the esctest-style subset in `tests/conformance` and check that no
+    /// test fails that is not listed as a known failure
+    #[test]
+    fn test_headless_conformance() {
+        let python = Command::new("python3")
+            .arg("--version")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+        if !python.is_ok_and(|status| status.success()) {
+            eprintln!("python3 not found, skipping the conformance run");
+            return;
+        }
+
+        let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/conformance");
+        let log =
+            std::env::temp_dir().join(format!("mochi-conformance-{}.log", std::process::id()));
+        let command = format!(
+            "python3 '{}' '{}'",
+            dir.join("vt_subset.py").display(),
+            log.display()
+        );
+        let mut config = Config::default();
+        config.dimensions = (80, 24);
+        assert_eq!(
+            run(&config, &command).unwrap(),
+            0,
+            "the suite did not finish"
+        );
+
+        let results = fs::read_to_string(&log).unwrap();
+        let _ = fs::remove_file(&log);
+        let known: BTreeSet<&str> = include_str!("../tests/conformance/known-failures.txt")
+            .lines()
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .collect();
+        let (mut passed, mut total) = (0, 0);
+        let mut failures = Vec::new();
+        for line in results.lines() {
+            if let Some(name) = line.strip_prefix("PASS ") {
+                passed += 1;
+                assert!(
+                    !known.contains(name),
+                    "{} passes now; remove it from known-failures.txt",
+                    name
+                );
+            } else if let Some(failure) = line.strip_prefix("FAIL ") {
+                let name = failure.split(':').next().unwrap_or(failure);
+                if !known.contains(name) {
+                    failures.push(failure.to_string());
+                }
+            } else {
+                continue;
+            }
+            total += 1;
+        }
+        eprintln!("Conformance: {}/{} passed", passed, total);
+        assert!(total > 0, "no results in {}", results);
+        assert!(failures.is_empty(), "regressions:\n{}", failures.join("\n"));
+    }
+}
@@ -0,0 +1,177 @@
+//! Highlight rules (coloring matching output)
+//!
+//! `[[highlights]]` rules color text that matches a pattern, e.g. every line
+//! containing "ERROR" in red. A line is matched once, when the program ends
+//! it with a line feed, and the colors are stored on the line itself, so
+//! they stay with it in the scrollback and are drawn over its SGR colors.
+//! Matches do not span rows.
+
+use regex::Regex;
+use terminal_core::{Color, Line, LineHighlight};
+
+use crate::config::{ColorScheme, ConfigError, HighlightRule};
+
+/// ANSI color names accepted in rules, by palette index
+const COLOR_NAMES: &[&str] = &[
+    "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+];
+
+/// A color from a rule: hex, or an ANSI name that follows the theme
+pub fn parse_color(name: &str) -> Option<Color> {
+    if let Some((r, g, b)) = name
+        .starts_with('#')
+        .then(|| ColorScheme::parse_hex(name))
+        .flatten()
+    {
+        return Some(Color::Rgb { r, g, b });
+    }
+    let (base, bright) = match name.strip_prefix("bright-") {
+        Some(base) => (base, 8),
+        None => (name, 0),
+    };
+    COLOR_NAMES
+        .iter()
+        .position(|&known| known == base)
+        .map(|index| Color::Indexed(index as u8 + bright))
+}
+
+#[derive(Clone)]
+struct Rule {
+    regex: Regex,
+    fg: Option<Color>,
+    bg: Option<Color>,
+    whole_line: bool,
+}
+
+/// Compiled highlight rules
+#[derive(Clone, Default)]
+pub struct HighlightRules {
+    rules: Vec<Rule>,
+}
+
+impl HighlightRules {
+    /// Compile the configured rules
+    pub fn new(configs: &[HighlightRule]) -> Result<Self, ConfigError> {
+        let error = |message: String| ConfigError {
+            message,
+            field: Some("highlights".to_string()),
+        };
+        let color = |name: &Option<String>| match name {
+            Some(name) => parse_color(name)
+                .map(Some)
+                .ok_or_else(|| error(format!("Invalid highlight color '{}'", name))),
+            None => Ok(None),
+        };
+        let rules = configs
+            .iter()
+            .map(|config| {
+                let regex = Regex::new(&config.pattern).map_err(|e| {
+                    error(format!(
+                        "Invalid highlight pattern '{}': {}",
+                        config.pattern, e
+                    ))
+                })?;
+                let (fg, bg) = (color(&config.foreground)?, color(&config.background)?);
+                if fg.is_none() && bg.is_none() {
+                    return Err(error(format!(
+                        "Highlight '{}' needs a foreground or background",
+                        config.pattern
+                    )));
+                }
+                Ok(Rule {
+                    regex,
+                    fg,
+                    bg,
+                    whole_line: config.whole_line,
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { rules })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Highlights for a line, in rule order
+    pub fn find(&self, line: &Line) -> Vec<LineHighlight> {
+        let (text, cols) = line.text_columns();
+        let mut highlights = Vec::new();
+        for rule in &self.rules {
+            for found in rule.regex.find_iter(&text) {
+                if found.is_empty() {
+                    continue;
+                }
+                let (start, end) = if rule.whole_line {
+                    (0, line.cols().saturating_sub(1))
+                } else {
+                    let last = cols[found.end() - 1];
+                    let width = (line.cell(last).width() as usize).max(1);
+                    (cols[found.start()], last + width - 1)
+                };
+                highlights.push(LineHighlight {
+                    start,
+                    end,
+                    fg: rule.fg,
+                    bg: rule.bg,
+                });
+                if rule.whole_line {
+                    break;
+                }
+            }
+        }
+        highlights
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(text: &str) -> Line {
+        let mut line = Line::new(20);
+        for (col, c) in text.chars().enumerate() {
+            line.cell_mut(col).set_char(c);
+        }
+        line
+    }
+
+    fn rule(pattern: &str, whole_line: bool) -> HighlightRule {
+        HighlightRule {
+            pattern: pattern.to_string(),
+            foreground: Some("red".to_string()),
+            background: None,
+            whole_line,
+        }
+    }
+
+    #[test]
+    fn test_parse_color() {
+        assert_eq!(parse_color("red"), Some(Color::Indexed(1)));
+        assert_eq!(parse_color("bright-white"), Some(Color::Indexed(15)));
+        assert_eq!(
+            parse_color("#102030"),
+            Some(Color::Rgb {
+                r: 0x10,
+                g: 0x20,
+                b: 0x30
+            })
+        );
+        assert_eq!(parse_color("102030"), None);
+        assert_eq!(parse_color("bright-pink"), None);
+    }
+
+    #[test]
+    fn test_find_highlights() {
+        let rules = HighlightRules::new(&[rule(r"ERR\w*", false), rule("fatal", true)]).unwrap();
+        let found = rules.find(&line("ok ERROR and ERR"));
+        let spans: Vec<(usize, usize)> = found.iter().map(|h| (h.start, h.end)).collect();
+        assert_eq!(spans, [(3, 7), (13, 15)]);
+        assert_eq!(found[0].fg, Some(Color::Indexed(1)));
+
+        let found = rules.find(&line("fatal fatal"));
+        assert_eq!(found.len(), 1);
+        assert_eq!((found[0].start, found[0].end), (0, 19));
+        assert!(rules.find(&line("fine")).is_empty());
+    }
+}
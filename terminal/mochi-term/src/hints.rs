@@ -0,0 +1,358 @@
+//! Hint mode (quick select)
+//!
+//! Hint mode scans the visible rows for URLs, file paths, git hashes, IP
+//! addresses and user-configured patterns, and labels each match. Typing a
+//! label copies the match; typing it in capitals also pastes it into the
+//! shell. Hint mode started to open matches hands them to the openers
+//! instead. Matches do not span rows.
+
+use regex::Regex;
+use terminal_core::{Line, Screen};
+
+use crate::config::{ConfigError, HintsConfig};
+
+/// Built-in patterns, in priority order (earlier patterns win overlaps)
+const BUILTIN_PATTERNS: &[(&str, &str)] = &[
+    ("url", r#"(?:https?|ftp|file)://[^\s<>"'`]+"#),
+    ("path", r"(?:~|\.{1,2})?/?(?:[\w.@+-]+/)+[\w.@+-]*"),
+    ("hash", r"\b[0-9a-f]{7,40}\b"),
+    ("ip", r"\b(?:\d{1,3}\.){3}\d{1,3}(?::\d{1,5})?\b"),
+];
+
+/// Trailing characters that usually end a sentence rather than a match
+const TRAILING_PUNCTUATION: &[char] = &['.', ',', ';', ':', '!', '?', '\'', '"'];
+
+/// Compile the built-in and configured patterns
+pub fn compile_patterns(config: &HintsConfig) -> Result<Vec<Regex>, ConfigError> {
+    let builtin = BUILTIN_PATTERNS.iter().map(|(_, pattern)| *pattern);
+    let custom = config.patterns.iter().map(|p| p.regex.as_str());
+    builtin
+        .chain(custom)
+        .map(|pattern| {
+            Regex::new(pattern).map_err(|e| ConfigError {
+                message: format!("Invalid hint pattern '{}': {}", pattern, e),
+                field: Some("hints.patterns".to_string()),
+            })
+        })
+        .collect()
+}
+
+/// A labelled match on the screen
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hint {
+    /// Row in the view (0 is the top visible row)
+    pub row: usize,
+    /// First column of the match
+    pub col: usize,
+    pub text: String,
+    pub label: String,
+}
+
+/// What hint mode does after a key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HintAction {
+    /// The typed prefix still matches some labels
+    Pending,
+    /// Copy the match (and paste it if `paste`)
+    Select { text: String, paste: bool },
+    /// No label matches; leave hint mode
+    Cancel,
+}
+
+/// Hint mode state for one window
+#[derive(Debug, Clone)]
+pub struct HintMode {
+    hints: Vec<Hint>,
+    /// Label characters typed so far
+    typed: String,
+    /// A label was typed in capitals
+    paste: bool,
+    /// Selected matches are opened instead of copied
+    open: bool,
+}
+
+impl HintMode {
+    /// Find and label the matches in the rows shown at `scroll_offset`
+    pub fn new(screen: &Screen, scroll_offset: usize, patterns: &[Regex], alphabet: &str) -> Self {
+        let first = screen.scrollback().len().saturating_sub(scroll_offset);
+        let mut found = Vec::new();
+        for row in 0..screen.rows() {
+            let Some(line) = screen.history_line(first + row) else {
+                break;
+            };
+            let (text, cols) = line.text_columns();
+            for (start, matched) in find_matches(&text, patterns) {
+                found.push((row, cols[start], matched));
+            }
+        }
+
+        let labels = labels(found.len(), alphabet);
+        let hints = found
+            .into_iter()
+            .zip(labels)
+            .map(|((row, col, text), label)| Hint {
+                row,
+                col,
+                text,
+                label,
+            })
+            .collect();
+        Self {
+            hints,
+            typed: String::new(),
+            paste: false,
+            open: false,
+        }
+    }
+
+    /// Open the selected match instead of copying it
+    pub fn set_open(&mut self, open: bool) {
+        self.open = open;
+    }
+
+    /// Whether the selected match is opened instead of copied
+    pub fn opens(&self) -> bool {
+        self.open
+    }
+
+    /// Hints still matching what has been typed
+    pub fn visible(&self) -> impl Iterator<Item = &Hint> {
+        self.hints
+            .iter()
+            .filter(|hint| hint.label.starts_with(&self.typed))
+    }
+
+    /// Number of label characters typed so far
+    pub fn typed_len(&self) -> usize {
+        self.typed.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hints.is_empty()
+    }
+
+    /// Handle a typed character
+    pub fn handle_char(&mut self, c: char) -> HintAction {
+        if c.is_uppercase() {
+            self.paste = true;
+        }
+        self.typed.extend(c.to_lowercase());
+        let mut visible = self.visible();
+        match (visible.next(), visible.next()) {
+            (None, _) => HintAction::Cancel,
+            (Some(hint), None) if hint.label == self.typed => HintAction::Select {
+                text: hint.text.clone(),
+                paste: self.paste,
+            },
+            _ => HintAction::Pending,
+        }
+    }
+
+    /// Remove the last typed character
+    pub fn backspace(&mut self) {
+        self.typed.pop();
+    }
+}
+
+/// The match covering column `col` of `line`, if any
+pub fn match_at(line: &Line, col: usize, patterns: &[Regex]) -> Option<String> {
+    let (text, cols) = line.text_columns();
+    find_matches(&text, patterns)
+        .into_iter()
+        .find(|(start, matched)| cols[*start] <= col && col <= cols[start + matched.len() - 1])
+        .map(|(_, matched)| matched)
+}
+
+/// Find non-overlapping matches in a row, as (byte offset, text)
+///
+/// Earlier patterns take precedence where matches overlap.
+fn find_matches(text: &str, patterns: &[Regex]) -> Vec<(usize, String)> {
+    let mut taken: Vec<(usize, usize)> = Vec::new();
+    let mut found = Vec::new();
+    for pattern in patterns {
+        for m in pattern.find_iter(text) {
+            let matched = trim_match(m.as_str());
+            // A bare slash or dot is not worth a label
+            if matched.chars().filter(|c| c.is_alphanumeric()).count() < 2 {
+                continue;
+            }
+            let (start, end) = (m.start(), m.start() + matched.len());
+            if taken.iter().any(|&(s, e)| start < e && s < end) {
+                continue;
+            }
+            taken.push((start, end));
+            found.push((start, matched.to_string()));
+        }
+    }
+    found.sort_by_key(|&(start, _)| start);
+    found
+}
+
+/// Drop trailing punctuation, and closing brackets that were not opened
+fn trim_match(mut text: &str) -> &str {
+    loop {
+        let Some(last) = text.chars().last() else {
+            return text;
+        };
+        let open = match last {
+            ')' => '(',
+            ']' => '[',
+            '}' => '{',
+            c if TRAILING_PUNCTUATION.contains(&c) => {
+                text = &text[..text.len() - 1];
+                continue;
+            }
+            _ => return text,
+        };
+        if text.matches(open).count() >= text.matches(last).count() {
+            return text;
+        }
+        text = &text[..text.len() - 1];
+    }
+}
+
+/// Labels for `count` hints, all the same length so none is a prefix of another
+pub fn labels(count: usize, alphabet: &str) -> Vec<String> {
+    let chars: Vec<char> = alphabet.chars().collect();
+    if count == 0 || chars.len() < 2 {
+        return Vec::new();
+    }
+    let mut len = 1;
+    let mut capacity = chars.len();
+    while capacity < count {
+        len += 1;
+        capacity = capacity.saturating_mul(chars.len());
+    }
+    (0..count)
+        .map(|mut n| {
+            let mut label = vec![chars[0]; len];
+            for slot in label.iter_mut().rev() {
+                *slot = chars[n % chars.len()];
+                n /= chars.len();
+            }
+            label.into_iter().collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::HintPattern;
+    use terminal_core::Dimensions;
+
+    fn screen(lines: &[&str]) -> Screen {
+        let mut screen = Screen::new(Dimensions::new(60, lines.len()));
+        for (i, line) in lines.iter().enumerate() {
+            for c in line.chars() {
+                screen.print(c);
+            }
+            if i + 1 < lines.len() {
+                screen.carriage_return();
+                screen.linefeed();
+            }
+        }
+        screen
+    }
+
+    fn patterns() -> Vec<Regex> {
+        compile_patterns(&HintsConfig::default()).unwrap()
+    }
+
+    #[test]
+    fn test_labels() {
+        assert_eq!(labels(3, "asd"), ["a", "s", "d"]);
+        assert_eq!(labels(4, "asd"), ["aa", "as", "ad", "sa"]);
+        assert_eq!(labels(10, "asd").len(), 10);
+        assert!(labels(1, "a").is_empty());
+    }
+
+    #[test]
+    fn test_find_builtin_matches() {
+        let found = find_matches(
+            "see https://example.com/a_(b). or ./src/main.rs at 1a2b3c4d 10.0.0.1:8080",
+            &patterns(),
+        );
+        let texts: Vec<&str> = found.iter().map(|(_, text)| text.as_str()).collect();
+        assert_eq!(
+            texts,
+            [
+                "https://example.com/a_(b)",
+                "./src/main.rs",
+                "1a2b3c4d",
+                "10.0.0.1:8080"
+            ]
+        );
+        assert_eq!(found[0].0, 4);
+    }
+
+    #[test]
+    fn test_custom_pattern() {
+        let config = HintsConfig {
+            patterns: vec![HintPattern {
+                name: "ticket".to_string(),
+                regex: r"\b[A-Z]+-\d+\b".to_string(),
+            }],
+            ..HintsConfig::default()
+        };
+        let found = find_matches("fixes MOCHI-42", &compile_patterns(&config).unwrap());
+        assert_eq!(found, [(6, "MOCHI-42".to_string())]);
+
+        let config = HintsConfig {
+            patterns: vec![HintPattern {
+                name: "bad".to_string(),
+                regex: "(".to_string(),
+            }],
+            ..HintsConfig::default()
+        };
+        let err = compile_patterns(&config).unwrap_err();
+        assert_eq!(err.field.as_deref(), Some("hints.patterns"));
+    }
+
+    #[test]
+    fn test_match_at() {
+        let screen = screen(&["open ./src/main.rs now"]);
+        let line = screen.line(0);
+        assert_eq!(
+            match_at(line, 5, &patterns()).as_deref(),
+            Some("./src/main.rs")
+        );
+        assert_eq!(
+            match_at(line, 17, &patterns()).as_deref(),
+            Some("./src/main.rs")
+        );
+        assert_eq!(match_at(line, 18, &patterns()), None);
+        assert_eq!(match_at(line, 0, &patterns()), None);
+    }
+
+    #[test]
+    fn test_hint_mode_select() {
+        let screen = screen(&["ls /etc/hosts", "commit deadbeef1"]);
+        let mut mode = HintMode::new(&screen, 0, &patterns(), "ab");
+        let hints: Vec<&Hint> = mode.visible().collect();
+        assert_eq!(hints.len(), 2);
+        assert_eq!((hints[0].row, hints[0].col), (0, 3));
+        assert_eq!(hints[1].text, "deadbeef1");
+        assert_eq!(hints[1].label, "b");
+
+        assert_eq!(
+            mode.handle_char('B'),
+            HintAction::Select {
+                text: "deadbeef1".to_string(),
+                paste: true
+            }
+        );
+
+        let mut mode = HintMode::new(&screen, 0, &patterns(), "ab");
+        assert_eq!(mode.handle_char('x'), HintAction::Cancel);
+        mode.backspace();
+        assert_eq!(mode.typed_len(), 0);
+        assert_eq!(
+            mode.handle_char('a'),
+            HintAction::Select {
+                text: "/etc/hosts".to_string(),
+                paste: false
+            }
+        );
+    }
+}
@@ -0,0 +1,290 @@
+//! Hooks (user commands run on terminal events)
+//!
+//! Each `[[hooks]]` entry names an event (bell, title change, exit of the
+//! shell, an output line matching a pattern, or a tab monitor firing) and what to do when it
+//! fires: run a command, show a toast, or both. Events from every tab are
+//! dispatched through one `Hooks` per window. A hook fires at most once per
+//! `min_interval_ms`, so a flood of bells or matching lines does not spawn a
+//! flood of processes.
+
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use regex::Regex;
+
+use crate::config::{ConfigError, HookConfig, HookEvent};
+
+/// An event that happened in a tab
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event<'a> {
+    Bell,
+    TitleChanged(&'a str),
+    /// The exit status, as shown in the tab ("exited with status 1")
+    Exited(&'a str),
+    /// A line of output, without trailing spaces
+    Output(&'a str),
+    /// The tab's activity monitor fired
+    Activity,
+    /// The tab's silence monitor fired
+    Silence,
+}
+
+impl Event<'_> {
+    pub fn kind(&self) -> HookEvent {
+        match self {
+            Event::Bell => HookEvent::Bell,
+            Event::TitleChanged(_) => HookEvent::Title,
+            Event::Exited(_) => HookEvent::Exit,
+            Event::Output(_) => HookEvent::Output,
+            Event::Activity => HookEvent::Activity,
+            Event::Silence => HookEvent::Silence,
+        }
+    }
+
+    /// Text that patterns are matched against
+    fn text(&self) -> &str {
+        match self {
+            Event::Bell | Event::Activity | Event::Silence => "",
+            Event::TitleChanged(text) | Event::Exited(text) | Event::Output(text) => text,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Event::Bell => "bell",
+            Event::TitleChanged(_) => "title",
+            Event::Exited(_) => "exit",
+            Event::Output(_) => "output",
+            Event::Activity => "activity",
+            Event::Silence => "silence",
+        }
+    }
+
+    /// Toast text for a notifying hook
+    fn describe(&self) -> String {
+        match self {
+            Event::Bell => "Bell".to_string(),
+            Event::TitleChanged(title) => format!("Title: {}", title),
+            Event::Exited(status) => format!("Process {}", status),
+            Event::Output(line) => line.to_string(),
+            Event::Activity => "Activity".to_string(),
+            Event::Silence => "Silence".to_string(),
+        }
+    }
+}
+
+/// What a fired hook asks the window to do
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HookAction {
+    /// Run `command` with `/bin/sh -c` and these extra environment variables
+    Run {
+        command: String,
+        env: Vec<(&'static str, String)>,
+    },
+    /// Show a toast
+    Notify(String),
+}
+
+struct Hook {
+    config: HookConfig,
+    pattern: Option<Regex>,
+    last_fired: Option<Instant>,
+}
+
+/// The hooks of one window
+#[derive(Default)]
+pub struct Hooks {
+    hooks: Vec<Hook>,
+}
+
+impl Hooks {
+    /// Compile the configured hooks
+    pub fn new(configs: &[HookConfig]) -> Result<Self, ConfigError> {
+        let hooks = configs
+            .iter()
+            .map(|config| {
+                let error = |message: String| ConfigError {
+                    message,
+                    field: Some("hooks".to_string()),
+                };
+                if config.command.is_none() && !config.notify {
+                    return Err(error(format!(
+                        "A {:?} hook needs a command or notify = true",
+                        config.event
+                    )));
+                }
+                let pattern = match (&config.pattern, config.event) {
+                    (Some(_), HookEvent::Bell | HookEvent::Activity | HookEvent::Silence) => {
+                        return Err(error(format!(
+                            "{:?} hooks cannot have a pattern",
+                            config.event
+                        )));
+                    }
+                    (None, HookEvent::Output) => {
+                        return Err(error("Output hooks need a pattern".to_string()));
+                    }
+                    (Some(pattern), _) => Some(Regex::new(pattern).map_err(|e| {
+                        error(format!("Invalid hook pattern '{}': {}", pattern, e))
+                    })?),
+                    (None, _) => None,
+                };
+                Ok(Hook {
+                    config: config.clone(),
+                    pattern,
+                    last_fired: None,
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { hooks })
+    }
+
+    /// Fire the hooks for an event in tab `tab` (0-based)
+    pub fn dispatch(&mut self, event: Event, tab: usize, now: Instant) -> Vec<HookAction> {
+        let mut actions = Vec::new();
+        for hook in &mut self.hooks {
+            if hook.config.event != event.kind() {
+                continue;
+            }
+            let matched = match &hook.pattern {
+                Some(pattern) => match pattern.find(event.text()) {
+                    Some(found) => found.as_str(),
+                    None => continue,
+                },
+                None => event.text(),
+            };
+            let interval = Duration::from_millis(hook.config.min_interval_ms);
+            if hook
+                .last_fired
+                .is_some_and(|last| now.duration_since(last) < interval)
+            {
+                log::debug!("Hook for {} rate limited", event.name());
+                continue;
+            }
+            hook.last_fired = Some(now);
+
+            if let Some(command) = &hook.config.command {
+                actions.push(HookAction::Run {
+                    command: command.clone(),
+                    env: vec![
+                        ("MOCHI_HOOK_EVENT", event.name().to_string()),
+                        ("MOCHI_HOOK_TEXT", event.text().to_string()),
+                        ("MOCHI_HOOK_MATCH", matched.to_string()),
+                        ("MOCHI_HOOK_TAB", (tab + 1).to_string()),
+                    ],
+                });
+            }
+            if hook.config.notify {
+                actions.push(HookAction::Notify(format!(
+                    "Tab {}: {}",
+                    tab + 1,
+                    event.describe()
+                )));
+            }
+        }
+        actions
+    }
+}
+
+/// Whether any hook watches output lines, so tabs must keep them
+pub fn watches_output(configs: &[HookConfig]) -> bool {
+    configs
+        .iter()
+        .any(|config| config.event == HookEvent::Output)
+}
+
+/// Run a hook command in the background
+///
+/// The command's output is discarded; a thread waits for it so it does not
+/// linger as a zombie.
+pub fn run(command: &str, env: &[(&'static str, String)]) {
+    let spawned = Command::new("/bin/sh")
+        .arg("-c")
+        .arg(command)
+        .envs(env.iter().map(|(key, value)| (key, value)))
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+    match spawned {
+        Ok(mut child) => {
+            let _ = thread::Builder::new()
+                .name("hook".to_string())
+                .spawn(move || child.wait());
+        }
+        Err(e) => log::warn!("Failed to run hook '{}': {}", command, e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hook(event: HookEvent, pattern: Option<&str>) -> HookConfig {
+        HookConfig {
+            event,
+            pattern: pattern.map(str::to_string),
+            command: Some("true".to_string()),
+            notify: false,
+            min_interval_ms: 1000,
+        }
+    }
+
+    #[test]
+    fn test_hook_validation() {
+        assert!(Hooks::new(&[hook(HookEvent::Bell, None)]).is_ok());
+        assert!(Hooks::new(&[hook(HookEvent::Bell, Some("x"))]).is_err());
+        assert!(Hooks::new(&[hook(HookEvent::Output, None)]).is_err());
+        assert!(Hooks::new(&[hook(HookEvent::Silence, Some("x"))]).is_err());
+        assert!(Hooks::new(&[hook(HookEvent::Output, Some("("))]).is_err());
+
+        let mut silent = hook(HookEvent::Exit, None);
+        silent.command = None;
+        let err = Hooks::new(&[silent]).err().unwrap();
+        assert_eq!(err.field.as_deref(), Some("hooks"));
+    }
+
+    #[test]
+    fn test_hook_dispatch() {
+        let mut notify = hook(HookEvent::Title, Some("vim"));
+        notify.command = None;
+        notify.notify = true;
+        let configs = [hook(HookEvent::Output, Some(r"ERROR \d+")), notify];
+        assert!(watches_output(&configs));
+        let mut hooks = Hooks::new(&configs).unwrap();
+
+        let now = Instant::now();
+        assert!(hooks.dispatch(Event::Output("all good"), 0, now).is_empty());
+        let actions = hooks.dispatch(Event::Output("got ERROR 42 here"), 1, now);
+        let [HookAction::Run { command, env }] = &actions[..] else {
+            panic!("unexpected actions {:?}", actions);
+        };
+        assert_eq!(command, "true");
+        assert!(env.contains(&("MOCHI_HOOK_MATCH", "ERROR 42".to_string())));
+        assert!(env.contains(&("MOCHI_HOOK_TAB", "2".to_string())));
+
+        assert_eq!(
+            hooks.dispatch(Event::TitleChanged("vim main.rs"), 0, now),
+            [HookAction::Notify("Tab 1: Title: vim main.rs".to_string())]
+        );
+        assert!(hooks.dispatch(Event::Bell, 0, now).is_empty());
+    }
+
+    #[test]
+    fn test_hook_rate_limit() {
+        let configs = [hook(HookEvent::Bell, None)];
+        assert!(!watches_output(&configs));
+        let mut hooks = Hooks::new(&configs).unwrap();
+        let now = Instant::now();
+        assert_eq!(hooks.dispatch(Event::Bell, 0, now).len(), 1);
+        assert!(hooks
+            .dispatch(Event::Bell, 0, now + Duration::from_millis(500))
+            .is_empty());
+        assert_eq!(
+            hooks
+                .dispatch(Event::Bell, 0, now + Duration::from_millis(1000))
+                .len(),
+            1
+        );
+    }
+}
@@ -0,0 +1,363 @@
+//! iTerm2 inline images (OSC 1337 ; File=)
+//!
+//! Parses the `File=` arguments, decodes the base64 body and the PNG or
+//! JPEG image inside it, and works out how many cells the image covers.
+//!
+//! Reference: https://iterm2.com/documentation-images.html
+
+use std::io::Cursor;
+
+use terminal_core::ImageData;
+
+/// Maximum decoded image dimension (pixels) accepted in either direction
+const MAX_IMAGE_DIMENSION: u32 = 8192;
+
+/// A width or height argument
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dimension {
+    /// Use the image's own size
+    Auto,
+    /// Number of cells
+    Cells(u32),
+    /// Number of pixels
+    Pixels(u32),
+    /// Percentage of the terminal width or height
+    Percent(u32),
+}
+
+impl Dimension {
+    /// Parse `N`, `Npx`, `N%` or `auto`
+    fn parse(value: &str) -> Option<Self> {
+        if value.eq_ignore_ascii_case("auto") {
+            Some(Dimension::Auto)
+        } else if let Some(px) = value.strip_suffix("px") {
+            px.parse().ok().map(Dimension::Pixels)
+        } else if let Some(pct) = value.strip_suffix('%') {
+            pct.parse().ok().map(Dimension::Percent)
+        } else {
+            value.parse().ok().map(Dimension::Cells)
+        }
+    }
+
+    /// Convert to pixels, given the cell size and the terminal size (pixels)
+    fn to_pixels(self, cell: u32, total: u32) -> Option<u32> {
+        match self {
+            Dimension::Auto => None,
+            Dimension::Cells(n) => Some(n.saturating_mul(cell)),
+            Dimension::Pixels(n) => Some(n),
+            Dimension::Percent(p) => Some((total as u64 * p.min(100) as u64 / 100) as u32),
+        }
+    }
+}
+
+/// Arguments of an OSC 1337 File= sequence
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InlineImageArgs {
+    /// Display the file inline (otherwise it is a download)
+    pub inline: bool,
+    /// Requested width
+    pub width: Dimension,
+    /// Requested height
+    pub height: Dimension,
+    /// Keep the image's aspect ratio when scaling
+    pub preserve_aspect_ratio: bool,
+}
+
+impl Default for InlineImageArgs {
+    fn default() -> Self {
+        Self {
+            inline: false,
+            width: Dimension::Auto,
+            height: Dimension::Auto,
+            preserve_aspect_ratio: true,
+        }
+    }
+}
+
+impl InlineImageArgs {
+    /// Parse `key=value` pairs separated by `;`
+    ///
+    /// Unknown keys (such as `name` and `size`) and malformed values are
+    /// ignored.
+    pub fn parse(args: &str) -> Self {
+        let mut result = Self::default();
+        for pair in args.split(';') {
+            let Some((key, value)) = pair.split_once('=') else {
+                continue;
+            };
+            match key {
+                "inline" => result.inline = value == "1",
+                "width" => {
+                    if let Some(width) = Dimension::parse(value) {
+                        result.width = width;
+                    }
+                }
+                "height" => {
+                    if let Some(height) = Dimension::parse(value) {
+                        result.height = height;
+                    }
+                }
+                "preserveAspectRatio" => result.preserve_aspect_ratio = value != "0",
+                _ => {}
+            }
+        }
+        result
+    }
+
+    /// Compute the cell box (cols, rows) for an image
+    ///
+    /// `cell_size` is the cell size in pixels and `screen` the terminal size
+    /// in cells. The box never exceeds the terminal's width or height, so a
+    /// program cannot make an image scroll the screen without end.
+    pub fn cell_box(
+        &self,
+        image: &ImageData,
+        cell_size: (u32, u32),
+        screen: (usize, usize),
+    ) -> (usize, usize) {
+        let (cell_w, cell_h) = (cell_size.0.max(1), cell_size.1.max(1));
+        let screen_w = screen.0.max(1) as u32 * cell_w;
+        let screen_h = screen.1.max(1) as u32 * cell_h;
+        let aspect = image.height as f64 / image.width as f64;
+
+        let width = self.width.to_pixels(cell_w, screen_w);
+        let height = self.height.to_pixels(cell_h, screen_h);
+
+        let (mut w, mut h) = match (width, height) {
+            (Some(w), Some(h)) => (w as f64, h as f64),
+            (Some(w), None) if self.preserve_aspect_ratio => (w as f64, w as f64 * aspect),
+            (None, Some(h)) if self.preserve_aspect_ratio => (h as f64 / aspect, h as f64),
+            (Some(w), None) => (w as f64, image.height as f64),
+            (None, Some(h)) => (image.width as f64, h as f64),
+            (None, None) => (image.width as f64, image.height as f64),
+        };
+
+        // Scale oversized images down to the terminal width
+        if w > screen_w as f64 {
+            if self.preserve_aspect_ratio {
+                h *= screen_w as f64 / w;
+            }
+            w = screen_w as f64;
+        }
+        // and to the terminal height
+        if h > screen_h as f64 {
+            if self.preserve_aspect_ratio {
+                w *= screen_h as f64 / h;
+            }
+            h = screen_h as f64;
+        }
+
+        let cols = (w / cell_w as f64).ceil().max(1.0) as usize;
+        let rows = (h / cell_h as f64).ceil().max(1.0) as usize;
+        (cols.min(screen.0.max(1)), rows.min(screen.1.max(1)))
+    }
+}
+
+/// Encode as standard base64, with padding
+pub fn encode_base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// Decode standard base64, ignoring whitespace and missing padding
+pub fn decode_base64(data: &str) -> Option<Vec<u8>> {
+    fn value(c: u8) -> Option<u32> {
+        match c {
+            b'A'..=b'Z' => Some((c - b'A') as u32),
+            b'a'..=b'z' => Some((c - b'a' + 26) as u32),
+            b'0'..=b'9' => Some((c - b'0' + 52) as u32),
+            b'+' => Some(62),
+            b'/' => Some(63),
+            _ => None,
+        }
+    }
+
+    let mut out = Vec::with_capacity(data.len() / 4 * 3);
+    let mut acc = 0u32;
+    let mut bits = 0;
+    for c in data.bytes() {
+        if c.is_ascii_whitespace() {
+            continue;
+        }
+        if c == b'=' {
+            break;
+        }
+        acc = (acc << 6) | value(c)?;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+            acc &= (1 << bits) - 1;
+        }
+    }
+    Some(out)
+}
+
+/// Decode a PNG or JPEG file into RGBA8 pixels
+pub fn decode_image(bytes: &[u8]) -> Option<ImageData> {
+    let image = if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        decode_png(bytes)
+    } else if bytes.starts_with(&[0xFF, 0xD8]) {
+        decode_jpeg(bytes)
+    } else {
+        log::debug!("Unsupported inline image format");
+        None
+    }?;
+
+    if image.width > MAX_IMAGE_DIMENSION || image.height > MAX_IMAGE_DIMENSION {
+        log::debug!("Inline image too large: {}x{}", image.width, image.height);
+        return None;
+    }
+    Some(image)
+}
+
+fn decode_png(bytes: &[u8]) -> Option<ImageData> {
+    let mut decoder = png::Decoder::new(Cursor::new(bytes));
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().ok()?;
+
+    let info = reader.info();
+    if info.width > MAX_IMAGE_DIMENSION || info.height > MAX_IMAGE_DIMENSION {
+        return None;
+    }
+
+    let mut buf = vec![0; reader.output_buffer_size()?];
+    let frame = reader.next_frame(&mut buf).ok()?;
+    let data = &buf[..frame.buffer_size()];
+
+    let pixels = match frame.color_type {
+        png::ColorType::Rgba => data.to_vec(),
+        png::ColorType::Rgb => data
+            .chunks_exact(3)
+            .flat_map(|p| [p[0], p[1], p[2], 255])
+            .collect(),
+        png::ColorType::GrayscaleAlpha => data
+            .chunks_exact(2)
+            .flat_map(|p| [p[0], p[0], p[0], p[1]])
+            .collect(),
+        png::ColorType::Grayscale => data.iter().flat_map(|&g| [g, g, g, 255]).collect(),
+        png::ColorType::Indexed => return None,
+    };
+
+    ImageData::new(frame.width, frame.height, pixels)
+}
+
+fn decode_jpeg(bytes: &[u8]) -> Option<ImageData> {
+    use zune_jpeg::zune_core::colorspace::ColorSpace;
+    use zune_jpeg::zune_core::options::DecoderOptions;
+
+    let options = DecoderOptions::default()
+        .set_max_width(MAX_IMAGE_DIMENSION as usize)
+        .set_max_height(MAX_IMAGE_DIMENSION as usize)
+        .jpeg_set_out_colorspace(ColorSpace::RGBA);
+    let mut decoder = zune_jpeg::JpegDecoder::new_with_options(bytes, options);
+    let pixels = decoder.decode().ok()?;
+    let info = decoder.info()?;
+
+    ImageData::new(info.width as u32, info.height as u32, pixels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 2x1 RGB PNG: one red pixel, one blue pixel
+    const TEST_PNG: &str =
+        "iVBORw0KGgoAAAANSUhEUgAAAAIAAAABCAIAAAB7QOjdAAAADUlEQVR4nGP4zwAE/wEHAAH/4iOeWQAAAABJRU5ErkJggg==";
+
+    fn image(width: u32, height: u32) -> ImageData {
+        ImageData::new(width, height, vec![0; (width * height * 4) as usize]).unwrap()
+    }
+
+    #[test]
+    fn test_decode_base64() {
+        assert_eq!(decode_base64("aGVsbG8=").unwrap(), b"hello");
+        assert_eq!(decode_base64("aGVsbG8").unwrap(), b"hello");
+        assert_eq!(decode_base64("aGVs\nbG8=").unwrap(), b"hello");
+        assert_eq!(decode_base64("").unwrap(), b"");
+        assert!(decode_base64("a*b").is_none());
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let args = InlineImageArgs::parse("name=Zm9v;size=10;inline=1;width=20;height=50%");
+        assert!(args.inline);
+        assert_eq!(args.width, Dimension::Cells(20));
+        assert_eq!(args.height, Dimension::Percent(50));
+        assert!(args.preserve_aspect_ratio);
+
+        let args = InlineImageArgs::parse("width=100px;height=auto;preserveAspectRatio=0");
+        assert!(!args.inline);
+        assert_eq!(args.width, Dimension::Pixels(100));
+        assert_eq!(args.height, Dimension::Auto);
+        assert!(!args.preserve_aspect_ratio);
+    }
+
+    #[test]
+    fn test_cell_box_natural_size() {
+        let args = InlineImageArgs::parse("inline=1");
+        assert_eq!(args.cell_box(&image(100, 50), (10, 20), (80, 24)), (10, 3));
+    }
+
+    #[test]
+    fn test_cell_box_preserves_aspect_ratio() {
+        let args = InlineImageArgs::parse("inline=1;width=20");
+        // 200px wide, so 100px tall at 2:1
+        assert_eq!(args.cell_box(&image(100, 50), (10, 20), (80, 24)), (20, 5));
+
+        let args = InlineImageArgs::parse("inline=1;width=20;preserveAspectRatio=0");
+        assert_eq!(args.cell_box(&image(100, 50), (10, 20), (80, 24)), (20, 3));
+    }
+
+    #[test]
+    fn test_cell_box_clamped_to_screen_width() {
+        let args = InlineImageArgs::parse("inline=1");
+        assert_eq!(
+            args.cell_box(&image(1600, 800), (10, 20), (80, 24)),
+            (80, 20)
+        );
+    }
+
+    #[test]
+    fn test_cell_box_clamped_to_screen_height() {
+        let args = InlineImageArgs::parse("inline=1;width=40;height=100000000");
+        // 400px wide, scaled down with the height
+        assert_eq!(args.cell_box(&image(100, 50), (10, 20), (80, 24)), (1, 24));
+
+        let args = InlineImageArgs::parse("inline=1;height=100000000;preserveAspectRatio=0");
+        assert_eq!(args.cell_box(&image(100, 50), (10, 20), (80, 24)), (10, 24));
+
+        let args = InlineImageArgs::parse("inline=1;height=4294967295px");
+        assert_eq!(args.cell_box(&image(1, 1000), (10, 20), (80, 24)), (1, 24));
+    }
+
+    #[test]
+    fn test_decode_png() {
+        let bytes = decode_base64(TEST_PNG).unwrap();
+        let image = decode_image(&bytes).unwrap();
+        assert_eq!((image.width, image.height), (2, 1));
+        assert_eq!(image.pixels, vec![255, 0, 0, 255, 0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn test_decode_rejects_garbage() {
+        assert!(decode_image(b"not an image").is_none());
+        assert!(decode_image(b"\x89PNG\r\n\x1a\ntruncated").is_none());
+    }
+}
@@ -2,9 +2,12 @@
 //!
 //! Converts GUI input events to terminal escape sequences.
 
+use terminal_core::Modes;
 use winit::event::MouseButton;
 use winit::keyboard::{Key, ModifiersState, NamedKey};
 
+use crate::config::{OtherKeysFormat, PasteControls};
+
 /// Encode a key press to terminal escape sequence
 pub fn encode_key(
     key: &Key,
@@ -60,6 +63,72 @@ pub fn encode_key(
     }
 }
 
+/// Encode a modified key for xterm's modifyOtherKeys
+///
+/// Returns None when the key keeps its usual encoding. Level 1 only
+/// changes keys whose usual bytes lose a modifier (Ctrl+1, Ctrl+Enter,
+/// Ctrl+Alt+A); level 2 changes every character and Enter, Tab,
+/// Backspace, Escape and Space with a modifier, so Ctrl+I no longer sends
+/// a Tab. Shift alone on a character still sends the shifted character.
+/// Cursor and function keys report modifiers already and are unaffected.
+pub fn encode_other_key(
+    key: &Key,
+    modifiers: ModifiersState,
+    level: u8,
+    format: OtherKeysFormat,
+) -> Option<Vec<u8>> {
+    let (ctrl, alt, shift) = (
+        modifiers.control_key(),
+        modifiers.alt_key(),
+        modifiers.shift_key(),
+    );
+    let (code, is_char) = match key {
+        Key::Character(text) => {
+            let c = text.chars().next()?;
+            // Ctrl+letter can arrive as the control character itself
+            let c = match c as u32 {
+                1..=26 if ctrl => char::from(c as u8 + b'`'),
+                _ => c,
+            };
+            (c as u32, true)
+        }
+        Key::Named(NamedKey::Enter) => (0x0d, false),
+        Key::Named(NamedKey::Tab) => (0x09, false),
+        Key::Named(NamedKey::Backspace) => (0x7f, false),
+        Key::Named(NamedKey::Escape) => (0x1b, false),
+        Key::Named(NamedKey::Space) => (0x20, false),
+        _ => return None,
+    };
+    if level == 0 || !(ctrl || alt || (shift && !is_char)) {
+        return None;
+    }
+    if level == 1 {
+        // Shift is part of a character itself; only Ctrl and Alt can be lost
+        let held = [
+            (ctrl, ModifiersState::CONTROL),
+            (alt, ModifiersState::ALT),
+            (shift && !is_char, ModifiersState::SHIFT),
+        ];
+        let usual = encode_key(key, modifiers, false);
+        let lost = usual.is_none()
+            || held
+                .iter()
+                .any(|&(on, modifier)| on && encode_key(key, modifiers - modifier, false) == usual);
+        if !lost {
+            return None;
+        }
+    }
+
+    let modifier = 1 + shift as u8 + 2 * alt as u8 + 4 * ctrl as u8;
+    Some(
+        match format {
+            OtherKeysFormat::Xterm => format!("\x1b[27;{};{}~", modifier, code),
+            OtherKeysFormat::CsiU => format!("\x1b[{};{}u", code, modifier),
+        }
+        .into_bytes(),
+    )
+}
+
 /// Encode a named key to terminal escape sequence
 fn encode_named_key(
     key: &NamedKey,
@@ -134,9 +203,17 @@ fn encode_named_key(
             application_cursor_keys,
         )),
 
-        // Navigation keys
-        NamedKey::Home => Some(encode_special_key(b'H', modifier_code)),
-        NamedKey::End => Some(encode_special_key(b'F', modifier_code)),
+        // Navigation keys; Home and End follow cursor key mode, as in xterm
+        NamedKey::Home => Some(encode_cursor_key(
+            b'H',
+            modifier_code,
+            application_cursor_keys,
+        )),
+        NamedKey::End => Some(encode_cursor_key(
+            b'F',
+            modifier_code,
+            application_cursor_keys,
+        )),
         NamedKey::PageUp => Some(encode_tilde_key(5, modifier_code)),
         NamedKey::PageDown => Some(encode_tilde_key(6, modifier_code)),
         NamedKey::Insert => Some(encode_tilde_key(2, modifier_code)),
@@ -160,7 +237,10 @@ fn encode_named_key(
     }
 }
 
-/// Encode cursor key (arrow keys)
+/// Encode cursor key (arrow keys, Home, End)
+///
+/// Application cursor key mode (DECCKM) selects SS3 over CSI; with
+/// modifiers the CSI form is always used.
 fn encode_cursor_key(key: u8, modifier: Option<u8>, application_mode: bool) -> Vec<u8> {
     if let Some(m) = modifier {
         format!("\x1b[1;{}{}", m, key as char).into_bytes()
@@ -171,15 +251,6 @@ fn encode_cursor_key(key: u8, modifier: Option<u8>, application_mode: bool) -> V
     }
 }
 
-/// Encode special key (Home, End)
-fn encode_special_key(key: u8, modifier: Option<u8>) -> Vec<u8> {
-    if let Some(m) = modifier {
-        format!("\x1b[1;{}{}", m, key as char).into_bytes()
-    } else {
-        vec![0x1b, b'[', key]
-    }
-}
-
 /// Encode tilde key (Insert, Delete, PageUp, PageDown)
 fn encode_tilde_key(code: u8, modifier: Option<u8>) -> Vec<u8> {
     if let Some(m) = modifier {
@@ -230,13 +301,58 @@ pub enum MouseEvent {
     Scroll { x: u16, y: u16, delta: i8 },
 }
 
+/// How mouse event coordinates are reported
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MouseEncoding {
+    /// X10/VT200: ESC [ M Cb Cx Cy, cells up to 223
+    Default,
+    /// SGR (1006): ESC [ < b ; x ; y M/m, in cells
+    Sgr,
+    /// SGR-Pixels (1016): the SGR format, in pixels from the grid's top left
+    SgrPixels,
+}
+
+impl MouseEncoding {
+    /// Encoding selected by the modes; SGR-Pixels wins over SGR
+    pub fn from_modes(modes: &Modes) -> Self {
+        if modes.mouse_sgr_pixels {
+            MouseEncoding::SgrPixels
+        } else if modes.mouse_sgr {
+            MouseEncoding::Sgr
+        } else {
+            MouseEncoding::Default
+        }
+    }
+}
+
 /// Encode mouse event to terminal escape sequence
+///
+/// The event carries the cell under the pointer; `pixel` is the pointer
+/// position in pixels from the grid's top left, reported instead of the cell
+/// with SGR-Pixels.
 pub fn encode_mouse(
     event: MouseEvent,
-    sgr_mode: bool,
+    pixel: (u16, u16),
+    encoding: MouseEncoding,
     button_event_mode: bool,
     any_event_mode: bool,
 ) -> Option<Vec<u8>> {
+    let event = match (encoding, event) {
+        (MouseEncoding::SgrPixels, MouseEvent::Press(button, _, _)) => {
+            MouseEvent::Press(button, pixel.0, pixel.1)
+        }
+        (MouseEncoding::SgrPixels, MouseEvent::Release(button, _, _)) => {
+            MouseEvent::Release(button, pixel.0, pixel.1)
+        }
+        (MouseEncoding::SgrPixels, MouseEvent::Move(_, _)) => MouseEvent::Move(pixel.0, pixel.1),
+        (MouseEncoding::SgrPixels, MouseEvent::Scroll { delta, .. }) => MouseEvent::Scroll {
+            x: pixel.0,
+            y: pixel.1,
+            delta,
+        },
+        (_, event) => event,
+    };
+    let sgr_mode = encoding != MouseEncoding::Default;
     match event {
         MouseEvent::Press(button, x, y) => {
             let button_code = match button {
@@ -301,7 +417,46 @@ pub fn encode_focus(focused: bool) -> Vec<u8> {
     }
 }
 
+/// Encode a wheel scroll as arrow keys (alternate scroll mode, DEC 1007)
+///
+/// Positive `lines` scroll up (towards older content) and send Up arrows.
+pub fn encode_alternate_scroll(lines: i32, application_cursor_keys: bool) -> Vec<u8> {
+    let key = if lines > 0 { b'A' } else { b'B' };
+    encode_cursor_key(key, None, application_cursor_keys).repeat(lines.unsigned_abs() as usize)
+}
+
 /// Wrap text for bracketed paste
+/// Prepare pasted text for the PTY
+///
+/// Line breaks become CR, as typed with Enter, and tabs are kept. Other
+/// control characters are removed, shown in caret notation or kept, as
+/// configured. The bracketed paste end marker is always removed, so pasted
+/// text cannot end the paste early and have the rest run as typed input.
+pub fn sanitize_paste(text: &str, controls: PasteControls) -> String {
+    let mut text = text.replace("\r\n", "\r").replace('\n', "\r");
+    while text.contains("\x1b[201~") {
+        text = text.replace("\x1b[201~", "");
+    }
+    if controls == PasteControls::Keep {
+        return text;
+    }
+    let mut result = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\t' | '\r' => result.push(c),
+            _ if !c.is_control() => result.push(c),
+            _ if controls == PasteControls::Strip => {}
+            '\x7f' => result.push_str("^?"),
+            '\0'..='\x1f' => {
+                result.push('^');
+                result.push(char::from(c as u8 + b'@'));
+            }
+            _ => result.push_str(&format!("<U+{:04X}>", c as u32)),
+        }
+    }
+    result
+}
+
 pub fn encode_bracketed_paste(text: &str) -> Vec<u8> {
     let mut result = b"\x1b[200~".to_vec();
     result.extend(text.as_bytes());
@@ -309,6 +464,18 @@ pub fn encode_bracketed_paste(text: &str) -> Vec<u8> {
     result
 }
 
+/// Quote text as a single shell word (POSIX sh), for dropped file paths
+///
+/// Text made only of characters that are never special is left as it is;
+/// anything else is single-quoted, with embedded single quotes escaped.
+pub fn shell_quote(text: &str) -> String {
+    let plain = |c: char| c.is_ascii_alphanumeric() || "_-./:@%+=,".contains(c);
+    if !text.is_empty() && text.chars().all(plain) {
+        return text.to_string();
+    }
+    format!("'{}'", text.replace('\'', "'\\''"))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -344,6 +511,146 @@ mod tests {
         assert_eq!(result, Some(b"\x1bOA".to_vec()));
     }
 
+    /// A named key and its bytes: normal, application cursor keys, and
+    /// with Shift+Ctrl (modifier parameter 6) in either mode
+    type KeyRow = (NamedKey, &'static [u8], &'static [u8], &'static [u8]);
+
+    /// Every named key the encoder knows
+    #[test]
+    fn test_encode_named_keys_table() {
+        #[rustfmt::skip]
+        let table: &[KeyRow] = &[
+            (NamedKey::ArrowUp, b"\x1b[A", b"\x1bOA", b"\x1b[1;6A"),
+            (NamedKey::ArrowDown, b"\x1b[B", b"\x1bOB", b"\x1b[1;6B"),
+            (NamedKey::ArrowRight, b"\x1b[C", b"\x1bOC", b"\x1b[1;6C"),
+            (NamedKey::ArrowLeft, b"\x1b[D", b"\x1bOD", b"\x1b[1;6D"),
+            (NamedKey::Home, b"\x1b[H", b"\x1bOH", b"\x1b[1;6H"),
+            (NamedKey::End, b"\x1b[F", b"\x1bOF", b"\x1b[1;6F"),
+            (NamedKey::Insert, b"\x1b[2~", b"\x1b[2~", b"\x1b[2;6~"),
+            (NamedKey::Delete, b"\x1b[3~", b"\x1b[3~", b"\x1b[3;6~"),
+            (NamedKey::PageUp, b"\x1b[5~", b"\x1b[5~", b"\x1b[5;6~"),
+            (NamedKey::PageDown, b"\x1b[6~", b"\x1b[6~", b"\x1b[6;6~"),
+            (NamedKey::F1, b"\x1bOP", b"\x1bOP", b"\x1b[1;6P"),
+            (NamedKey::F2, b"\x1bOQ", b"\x1bOQ", b"\x1b[1;6Q"),
+            (NamedKey::F3, b"\x1bOR", b"\x1bOR", b"\x1b[1;6R"),
+            (NamedKey::F4, b"\x1bOS", b"\x1bOS", b"\x1b[1;6S"),
+            (NamedKey::F5, b"\x1b[15~", b"\x1b[15~", b"\x1b[15;6~"),
+            (NamedKey::F6, b"\x1b[17~", b"\x1b[17~", b"\x1b[17;6~"),
+            (NamedKey::F7, b"\x1b[18~", b"\x1b[18~", b"\x1b[18;6~"),
+            (NamedKey::F8, b"\x1b[19~", b"\x1b[19~", b"\x1b[19;6~"),
+            (NamedKey::F9, b"\x1b[20~", b"\x1b[20~", b"\x1b[20;6~"),
+            (NamedKey::F10, b"\x1b[21~", b"\x1b[21~", b"\x1b[21;6~"),
+            (NamedKey::F11, b"\x1b[23~", b"\x1b[23~", b"\x1b[23;6~"),
+            (NamedKey::F12, b"\x1b[24~", b"\x1b[24~", b"\x1b[24;6~"),
+            (NamedKey::Enter, b"\r", b"\r", b"\r"),
+            (NamedKey::Tab, b"\t", b"\t", b"\x1b[Z"),
+            (NamedKey::Backspace, b"\x7f", b"\x7f", b"\x08"),
+            (NamedKey::Escape, b"\x1b", b"\x1b", b"\x1b"),
+            (NamedKey::Space, b" ", b" ", b"\0"),
+        ];
+        let shift_ctrl = ModifiersState::SHIFT | ModifiersState::CONTROL;
+        for (named, normal, application, modified) in table {
+            let key = Key::Named(*named);
+            let encode = |modifiers, application| encode_key(&key, modifiers, application).unwrap();
+            assert_eq!(
+                encode(ModifiersState::empty(), false),
+                *normal,
+                "{:?}",
+                named
+            );
+            assert_eq!(
+                encode(ModifiersState::empty(), true),
+                *application,
+                "{:?}",
+                named
+            );
+            assert_eq!(encode(shift_ctrl, false), *modified, "{:?}", named);
+            assert_eq!(
+                encode(shift_ctrl, true),
+                *modified,
+                "{:?} (application)",
+                named
+            );
+        }
+
+        // All eight modifier combinations of an arrow key
+        let key = Key::Named(NamedKey::ArrowUp);
+        for (modifiers, code) in [
+            (ModifiersState::SHIFT, 2),
+            (ModifiersState::ALT, 3),
+            (ModifiersState::SHIFT | ModifiersState::ALT, 4),
+            (ModifiersState::CONTROL, 5),
+            (ModifiersState::SHIFT | ModifiersState::CONTROL, 6),
+            (ModifiersState::ALT | ModifiersState::CONTROL, 7),
+            (
+                ModifiersState::SHIFT | ModifiersState::ALT | ModifiersState::CONTROL,
+                8,
+            ),
+        ] {
+            assert_eq!(
+                encode_key(&key, modifiers, true),
+                Some(format!("\x1b[1;{}A", code).into_bytes())
+            );
+        }
+        assert_eq!(
+            encode_key(&key, ModifiersState::SUPER, true),
+            Some(b"\x1bOA".to_vec())
+        );
+    }
+
+    #[test]
+    fn test_encode_other_keys() {
+        let xterm = OtherKeysFormat::Xterm;
+        let ctrl = ModifiersState::CONTROL;
+        let encode = |key: Key, modifiers, level| {
+            encode_other_key(&key, modifiers, level, xterm).map(|b| String::from_utf8(b).unwrap())
+        };
+        let char_key = |c: &str| Key::Character(c.into());
+        let tab = Key::Named(NamedKey::Tab);
+        let enter = Key::Named(NamedKey::Enter);
+
+        // Off: everything keeps its usual encoding
+        assert_eq!(encode(char_key("i"), ctrl, 0), None);
+        assert_eq!(encode(enter.clone(), ctrl, 0), None);
+
+        // Level 1: only keys that would lose a modifier
+        assert_eq!(encode(char_key("i"), ctrl, 1), None);
+        assert_eq!(encode(char_key("1"), ctrl, 1).unwrap(), "\x1b[27;5;49~");
+        assert_eq!(encode(enter.clone(), ctrl, 1).unwrap(), "\x1b[27;5;13~");
+        assert_eq!(
+            encode(enter.clone(), ModifiersState::SHIFT, 1).unwrap(),
+            "\x1b[27;2;13~"
+        );
+        assert_eq!(
+            encode(char_key("a"), ctrl | ModifiersState::ALT, 1).unwrap(),
+            "\x1b[27;7;97~"
+        );
+        assert_eq!(encode(char_key("a"), ModifiersState::ALT, 1), None);
+        assert_eq!(encode(tab.clone(), ModifiersState::SHIFT, 1), None);
+        assert_eq!(encode(Key::Named(NamedKey::Backspace), ctrl, 1), None);
+
+        // Level 2: Ctrl+I and Tab differ
+        assert_eq!(encode(char_key("i"), ctrl, 2).unwrap(), "\x1b[27;5;105~");
+        assert_eq!(encode(char_key("\x09"), ctrl, 2).unwrap(), "\x1b[27;5;105~");
+        assert_eq!(encode(tab.clone(), ModifiersState::empty(), 2), None);
+        assert_eq!(
+            encode(tab, ModifiersState::SHIFT, 2).unwrap(),
+            "\x1b[27;2;9~"
+        );
+        assert_eq!(
+            encode(char_key("a"), ModifiersState::ALT, 2).unwrap(),
+            "\x1b[27;3;97~"
+        );
+        assert_eq!(encode(char_key("A"), ModifiersState::SHIFT, 2), None);
+        assert_eq!(encode(char_key("a"), ModifiersState::empty(), 2), None);
+        assert_eq!(encode(Key::Named(NamedKey::ArrowUp), ctrl, 2), None);
+
+        assert_eq!(
+            encode_other_key(&char_key("i"), ctrl, 2, OtherKeysFormat::CsiU),
+            Some(b"\x1b[105;5u".to_vec())
+        );
+    }
+
     #[test]
     fn test_encode_function_keys() {
         let key = Key::Named(NamedKey::F1);
@@ -364,12 +671,66 @@ mod tests {
         assert_eq!(result, b"\x1b[<0;11;21m".to_vec());
     }
 
+    #[test]
+    fn test_encode_mouse_sgr_pixels() {
+        let event = MouseEvent::Press(MouseButton::Left, 2, 3);
+        let result = encode_mouse(event, (25, 61), MouseEncoding::SgrPixels, false, false);
+        assert_eq!(result, Some(b"\x1b[<0;26;62M".to_vec()));
+
+        // Cells are reported without SGR-Pixels
+        let result = encode_mouse(event, (25, 61), MouseEncoding::Sgr, false, false);
+        assert_eq!(result, Some(b"\x1b[<0;3;4M".to_vec()));
+
+        let mut modes = Modes::new();
+        modes.mouse_sgr = true;
+        assert_eq!(MouseEncoding::from_modes(&modes), MouseEncoding::Sgr);
+        modes.mouse_sgr_pixels = true;
+        assert_eq!(MouseEncoding::from_modes(&modes), MouseEncoding::SgrPixels);
+    }
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("/tmp/notes.txt"), "/tmp/notes.txt");
+        assert_eq!(shell_quote("/tmp/my notes.txt"), "'/tmp/my notes.txt'");
+        assert_eq!(shell_quote("it's"), "'it'\\''s'");
+        assert_eq!(shell_quote("$(rm -rf ~)"), "'$(rm -rf ~)'");
+        assert_eq!(shell_quote(""), "''");
+    }
+
     #[test]
     fn test_bracketed_paste() {
         let result = encode_bracketed_paste("hello");
         assert_eq!(result, b"\x1b[200~hello\x1b[201~".to_vec());
     }
 
+    #[test]
+    fn test_sanitize_paste() {
+        let text = "echo hi\r\nls\n\tx\x1b[31mred\x07\u{9b}1m\x7f";
+        assert_eq!(
+            sanitize_paste(text, PasteControls::Strip),
+            "echo hi\rls\r\tx[31mred1m"
+        );
+        assert_eq!(
+            sanitize_paste(text, PasteControls::Escape),
+            "echo hi\rls\r\tx^[[31mred^G<U+009B>1m^?"
+        );
+        assert_eq!(
+            sanitize_paste(text, PasteControls::Keep),
+            "echo hi\rls\r\tx\x1b[31mred\x07\u{9b}1m\x7f"
+        );
+
+        // The end marker cannot be smuggled in, even split around another
+        let text = "a\x1b[20\x1b[201~1~\rrm -rf ~";
+        assert_eq!(sanitize_paste(text, PasteControls::Keep), "a\rrm -rf ~");
+    }
+
+    #[test]
+    fn test_encode_alternate_scroll() {
+        assert_eq!(encode_alternate_scroll(2, false), b"\x1b[A\x1b[A".to_vec());
+        assert_eq!(encode_alternate_scroll(-1, false), b"\x1b[B".to_vec());
+        assert_eq!(encode_alternate_scroll(1, true), b"\x1bOA".to_vec());
+    }
+
     #[test]
     fn test_focus_events() {
         assert_eq!(encode_focus(true), b"\x1b[I".to_vec());
@@ -0,0 +1,478 @@
+//! Terminal inspector (a debug overlay)
+//!
+//! Ctrl+Shift+D shows the state of the current tab's terminal over it: the
+//! cursor, scroll region, modes, character sets and parser state, the
+//! unhandled sequences received most often, the memory the tab and window
+//! hold, its key-to-screen latency and mispredicted echoes, and the last
+//! actions the parser produced, newest first. Actions are only
+//! recorded while the inspector is open; printed text is recorded as one
+//! entry per run of characters.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use terminal_core::{Charset, Screen};
+use terminal_parser::{Action, CsiAction, ParserState};
+
+use crate::config::{LatencyConfig, MemoryConfig};
+use crate::latency::KeyLatency;
+use crate::memory::{format_bytes, MemoryUsage};
+use crate::prediction::Prediction;
+use crate::stats::ParserStats;
+
+/// How many actions the inspector keeps
+pub const ACTION_LOG_LEN: usize = 200;
+
+/// How many kinds of unhandled sequences the inspector lists
+const TOP_UNHANDLED: usize = 5;
+
+/// Longest description of one action (in characters)
+const MAX_DESCRIPTION_LEN: usize = 100;
+
+#[derive(Debug)]
+enum Entry {
+    /// Printed characters (the first MAX_DESCRIPTION_LEN) and their count
+    Text(String, usize),
+    Action(String),
+}
+
+/// The last actions of a terminal, while the inspector is open
+#[derive(Debug, Default)]
+pub struct ActionLog {
+    entries: VecDeque<Entry>,
+}
+
+impl ActionLog {
+    pub fn record(&mut self, action: &Action) {
+        if let Action::Print(c) = action {
+            if let Some(Entry::Text(text, count)) = self.entries.back_mut() {
+                if *count < MAX_DESCRIPTION_LEN {
+                    text.push(*c);
+                }
+                *count += 1;
+                return;
+            }
+            self.push(Entry::Text(c.to_string(), 1));
+        } else {
+            self.push(Entry::Action(describe(action)));
+        }
+    }
+
+    fn push(&mut self, entry: Entry) {
+        if self.entries.len() == ACTION_LOG_LEN {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+
+    /// One line per action, newest first
+    pub fn lines(&self) -> impl Iterator<Item = String> + '_ {
+        self.entries.iter().rev().map(|entry| match entry {
+            Entry::Text(text, 1) => format!("Print {:?}", text),
+            Entry::Text(text, count) => format!("Print {:?} ({} chars)", text, count),
+            Entry::Action(description) => description.clone(),
+        })
+    }
+}
+
+/// A one-line description of an action, in the notation of the escape
+/// sequence docs where there is one (`CSI ? 1049 h`)
+pub fn describe(action: &Action) -> String {
+    let description = match action {
+        Action::Print(c) => format!("Print {:?}", c),
+        Action::Control(byte) => format!("C0 {}", control_name(*byte)),
+        Action::Esc(esc) => format!("ESC {:?}", esc),
+        Action::Csi(csi) => format!("CSI {}", csi_text(csi)),
+        Action::Osc(osc) => format!("OSC {:?}", osc),
+        Action::Dcs {
+            params,
+            intermediates,
+            final_byte,
+            data,
+        } => format!(
+            "DCS {}{}{} ({} bytes)",
+            join_params(params.iter()),
+            String::from_utf8_lossy(intermediates),
+            *final_byte as char,
+            data.len()
+        ),
+        Action::Tek(data) => format!("Tek ({} bytes)", data.len()),
+        Action::Apc(data) => format!("APC ({} bytes)", data.len()),
+        Action::Pm(data) => format!("PM ({} bytes)", data.len()),
+        Action::Sos(data) => format!("SOS ({} bytes)", data.len()),
+        Action::Invalid(data) => format!("Invalid {:?}", String::from_utf8_lossy(data)),
+    };
+    truncate(description)
+}
+
+fn truncate(mut text: String) -> String {
+    if let Some((index, _)) = text.char_indices().nth(MAX_DESCRIPTION_LEN) {
+        text.truncate(index);
+        text.push_str("...");
+    }
+    text
+}
+
+fn control_name(byte: u8) -> String {
+    let name = match byte {
+        0x00 => "NUL",
+        0x05 => "ENQ",
+        0x07 => "BEL",
+        0x08 => "BS",
+        0x09 => "HT",
+        0x0a => "LF",
+        0x0b => "VT",
+        0x0c => "FF",
+        0x0d => "CR",
+        0x0e => "SO",
+        0x0f => "SI",
+        _ => return format!("0x{:02x}", byte),
+    };
+    name.to_string()
+}
+
+fn join_params(params: impl Iterator<Item = u16>) -> String {
+    params
+        .map(|param| param.to_string())
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+fn csi_text(csi: &CsiAction) -> String {
+    let mut text = String::new();
+    if csi.private {
+        text.push_str("? ");
+    }
+    let params = join_params(csi.params.iter());
+    if !params.is_empty() {
+        text.push_str(&params);
+        text.push(' ');
+    }
+    for &byte in &csi.intermediates {
+        text.push(byte as char);
+        text.push(' ');
+    }
+    text.push(csi.final_byte as char);
+    text
+}
+
+fn on_off(enabled: bool) -> &'static str {
+    if enabled {
+        "on"
+    } else {
+        "off"
+    }
+}
+
+fn charset_name(charset: Charset) -> &'static str {
+    match charset {
+        Charset::Ascii => "ASCII",
+        Charset::DecSpecialGraphics => "DEC graphics",
+        Charset::Uk => "UK",
+    }
+}
+
+/// Lines describing the state of a screen and its parser
+pub fn state_lines(screen: &Screen, parser: ParserState) -> Vec<String> {
+    let cursor = screen.cursor();
+    let modes = screen.modes();
+    let (top, bottom) = screen.scroll_region();
+    let charset = screen.charset();
+
+    let mut lines = vec![
+        format!(
+            "Screen: {}x{}, {} ({} lines of scrollback)",
+            screen.cols(),
+            screen.rows(),
+            if modes.alternate_screen {
+                "alternate"
+            } else {
+                "primary"
+            },
+            screen.scrollback().len()
+        ),
+        format!(
+            "Cursor: row {}, col {}{}, {:?}, {}",
+            cursor.row + 1,
+            cursor.col + 1,
+            if cursor.pending_wrap {
+                " (pending wrap)"
+            } else {
+                ""
+            },
+            cursor.style,
+            if modes.cursor_visible {
+                "visible"
+            } else {
+                "hidden"
+            }
+        ),
+        format!(
+            "Scroll region: rows {}-{}, origin mode {}",
+            top + 1,
+            bottom + 1,
+            on_off(modes.origin_mode)
+        ),
+        format!(
+            "Charsets: G0 {}, G1 {}, G2 {}, G3 {}; GL is G{}{}",
+            charset_name(charset.g0),
+            charset_name(charset.g1),
+            charset_name(charset.g2),
+            charset_name(charset.g3),
+            charset.active,
+            charset
+                .single_shift
+                .map(|shift| format!(", single shift G{}", shift))
+                .unwrap_or_default()
+        ),
+        format!("Parser: {:?}", parser),
+    ];
+
+    let flags = [
+        ("IRM", modes.insert_mode),
+        ("LNM", modes.linefeed_mode),
+        ("DECCKM", modes.cursor_keys_application),
+        ("DECAWM", modes.auto_wrap),
+        ("DECSCNM", modes.reverse_video),
+        ("bracketed paste", modes.bracketed_paste),
+        ("focus events", modes.focus_events),
+        ("alternate scroll", modes.alternate_scroll),
+        ("synchronized output", modes.synchronized_output),
+        ("in-band resize", modes.in_band_resize),
+    ];
+    let set: Vec<&str> = flags
+        .iter()
+        .filter(|(_, enabled)| *enabled)
+        .map(|(name, _)| *name)
+        .collect();
+    lines.push(format!(
+        "Modes: {}",
+        if set.is_empty() {
+            "none".to_string()
+        } else {
+            set.join(", ")
+        }
+    ));
+
+    let mouse = [
+        ("X10", modes.mouse_x10),
+        ("normal", modes.mouse_vt200),
+        ("button", modes.mouse_button_event),
+        ("any", modes.mouse_any_event),
+    ]
+    .iter()
+    .filter(|(_, enabled)| *enabled)
+    .map(|(name, _)| *name)
+    .collect::<Vec<_>>();
+    let encoding = if modes.mouse_sgr_pixels {
+        "SGR pixels"
+    } else if modes.mouse_sgr {
+        "SGR"
+    } else {
+        "default"
+    };
+    lines.push(format!(
+        "Mouse: {}, {} encoding; modifyOtherKeys {}",
+        if mouse.is_empty() {
+            "off".to_string()
+        } else {
+            mouse.join(", ")
+        },
+        encoding,
+        modes.modify_other_keys
+    ));
+    lines
+}
+
+/// Lines listing the unhandled sequences received most often
+pub fn stats_lines(stats: &ParserStats) -> Vec<String> {
+    let mut lines = vec![format!("Unhandled sequences: {}", stats.total())];
+    for (sequence, count) in stats.top(TOP_UNHANDLED) {
+        lines.push(format!("  {} ({}x)", sequence, count));
+    }
+    lines
+}
+
+/// Line with the tab's key-to-screen latency
+pub fn latency_line(latency: &KeyLatency) -> String {
+    let ms = |duration: Option<Duration>| duration.map_or(0.0, |d| d.as_secs_f64() * 1000.0);
+    match latency.len() {
+        0 => "Key-to-screen latency: no keys measured".to_string(),
+        keys => format!(
+            "Key-to-screen latency: last {:.1} ms, median {:.1} ms, p95 {:.1} ms ({} keys)",
+            ms(latency.last()),
+            ms(latency.median()),
+            ms(latency.percentile(95)),
+            keys
+        ),
+    }
+}
+
+/// Line with the tab's mispredicted echoes, against the limit
+pub fn prediction_line(prediction: &Prediction, config: &LatencyConfig) -> String {
+    let misses = prediction.misses(Instant::now());
+    format!(
+        "Predictive echo: {} mispredicted in the last minute (limit {}){}",
+        misses,
+        config.misprediction_limit,
+        if misses > config.misprediction_limit {
+            ", paused"
+        } else {
+            ""
+        }
+    )
+}
+
+/// Lines with the memory held by the tab and its window, and the budgets
+pub fn memory_lines(
+    tab: &MemoryUsage,
+    window: &MemoryUsage,
+    budgets: &MemoryConfig,
+) -> Vec<String> {
+    let size = |bytes: usize| format_bytes(bytes as u64);
+    let budget = |mb: usize| MemoryConfig::budget(mb).map_or("none".to_string(), size);
+    vec![
+        format!(
+            "Memory: scrollback {}, images {}",
+            size(tab.scrollback),
+            size(tab.images)
+        ),
+        format!(
+            "  Window: {} (scrollback {}, images {}, glyph cache {}, clipboard history {})",
+            size(window.total()),
+            size(window.scrollback),
+            size(window.images),
+            size(window.glyphs),
+            size(window.clipboard)
+        ),
+        format!(
+            "  Budgets: scrollback {}, images {}, glyph cache {}",
+            budget(budgets.scrollback_mb),
+            budget(budgets.images_mb),
+            budget(budgets.glyph_cache_mb)
+        ),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use terminal_parser::Parser;
+
+    fn parse(data: &[u8]) -> Vec<Action> {
+        Parser::new().parse_collect(data)
+    }
+
+    #[test]
+    fn test_describe() {
+        let described: Vec<String> = parse(b"\x1b[?1049h\x1b[1;31m\x1b[>4;2m\r\x1b7")
+            .iter()
+            .map(describe)
+            .collect();
+        assert_eq!(
+            described,
+            [
+                "CSI ? 1049 h",
+                "CSI 1;31 m",
+                "CSI 4;2 > m",
+                "C0 CR",
+                "ESC SaveCursor"
+            ]
+        );
+        let long = describe(&Action::Invalid(vec![b'x'; 500]));
+        assert_eq!(long.chars().count(), MAX_DESCRIPTION_LEN + 3);
+    }
+
+    #[test]
+    fn test_action_log() {
+        let mut log = ActionLog::default();
+        for action in parse(b"hello\r\nworld") {
+            log.record(&action);
+        }
+        let lines: Vec<String> = log.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                "Print \"world\" (5 chars)",
+                "C0 LF",
+                "C0 CR",
+                "Print \"hello\" (5 chars)"
+            ]
+        );
+
+        for _ in 0..ACTION_LOG_LEN * 2 {
+            log.record(&Action::Control(0x07));
+        }
+        assert_eq!(log.lines().count(), ACTION_LOG_LEN);
+    }
+
+    #[test]
+    fn test_stats_lines() {
+        use crate::terminal::Terminal;
+
+        let mut term = Terminal::new(40, 10);
+        term.process(b"\x1b[?9999h\x1b[?9999l\x1b]999;x\x07\x1b[1;73m\x1b[99h");
+        assert_eq!(
+            stats_lines(term.stats()),
+            [
+                "Unhandled sequences: 5",
+                "  DEC mode 9999 (2x)",
+                "  OSC 999 (1x)",
+                "  SGR 73 (1x)",
+                "  mode 99 (1x)"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_latency_line() {
+        let mut latency = KeyLatency::default();
+        assert_eq!(
+            latency_line(&latency),
+            "Key-to-screen latency: no keys measured"
+        );
+        let start = std::time::Instant::now();
+        for ms in [4, 2, 30] {
+            latency.typed(start);
+            latency.output(start);
+            latency.rendered(start + Duration::from_millis(ms));
+        }
+        assert_eq!(
+            latency_line(&latency),
+            "Key-to-screen latency: last 30.0 ms, median 4.0 ms, p95 30.0 ms (3 keys)"
+        );
+    }
+
+    #[test]
+    fn test_prediction_line() {
+        assert_eq!(
+            prediction_line(&Prediction::default(), &LatencyConfig::default()),
+            "Predictive echo: 0 mispredicted in the last minute (limit 5)"
+        );
+    }
+
+    #[test]
+    fn test_memory_lines() {
+        let tab = MemoryUsage {
+            scrollback: 3 << 19,
+            ..Default::default()
+        };
+        let window = MemoryUsage {
+            glyphs: 2048,
+            clipboard: 100,
+            ..tab
+        };
+        let budgets = MemoryConfig {
+            scrollback_mb: 256,
+            ..Default::default()
+        };
+        assert_eq!(
+            memory_lines(&tab, &window, &budgets),
+            [
+                "Memory: scrollback 1.5M, images 0B",
+                "  Window: 1.5M (scrollback 1.5M, images 0B, glyph cache 2.0K, clipboard history 100B)",
+                "  Budgets: scrollback 256M, images none, glyph cache none"
+            ]
+        );
+    }
+}
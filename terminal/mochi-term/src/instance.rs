@@ -0,0 +1,180 @@
+//! Single-instance mode (one process for all windows)
+//!
+//! With `single_instance = true`, the first mochi listens on a socket in the
+//! user's runtime directory, one per display. Launching mochi again sends
+//! its `-e`, `--command`, `--profile` and `--tab` to that socket and exits;
+//! the running process opens the window (or tab), which shares its fonts,
+//! clipboard history and configuration. The requests are JSON-RPC like on
+//! the control socket, but the only method is `open`.
+//!
+//! Shells opened this way start with the environment of the running
+//! process, not that of the launch.
+
+use std::env;
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::config::{Config, StartupCommand};
+
+/// The method that asks the running instance to open a window or tab
+pub const OPEN_METHOD: &str = "open";
+
+/// How long a launch waits for the running instance to answer
+const ANSWER_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// What a window (or tab) is opened with besides the shell
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Launch {
+    /// Profile of the first tab (None = the configured one)
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// Run in place of the first shell (`-e`)
+    #[serde(default)]
+    pub startup_command: Option<StartupCommand>,
+    /// Run in a command tab (`--command`)
+    #[serde(default)]
+    pub command: Option<String>,
+    /// Open a tab in an existing window instead of a window
+    #[serde(default)]
+    pub tab: bool,
+    /// Restore the saved session (only the first window of a process)
+    #[serde(skip)]
+    pub restore: bool,
+}
+
+impl Launch {
+    /// The launch described by a configuration and its command line
+    pub fn new(config: &Config) -> Self {
+        Self {
+            profile: config.profile.clone(),
+            startup_command: config.startup_command.clone(),
+            command: config.command.clone(),
+            tab: false,
+            restore: false,
+        }
+    }
+}
+
+/// Socket the running instance listens on for this user and display
+pub fn socket_path() -> PathBuf {
+    let display = env::var("WAYLAND_DISPLAY")
+        .or_else(|_| env::var("DISPLAY"))
+        .unwrap_or_default();
+    dirs::runtime_dir()
+        .unwrap_or_else(env::temp_dir)
+        .join("mochi")
+        .join(socket_name(&display))
+}
+
+fn socket_name(display: &str) -> String {
+    let display: String = display
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if display.is_empty() {
+        "instance.sock".to_string()
+    } else {
+        format!("instance-{}.sock", display)
+    }
+}
+
+/// Ask the instance listening on `path` to open `launch`
+///
+/// Returns false if no instance is listening there.
+pub fn open_in_running(path: &Path, launch: &Launch) -> io::Result<bool> {
+    let Ok(mut stream) = UnixStream::connect(path) else {
+        return Ok(false);
+    };
+    stream.set_read_timeout(Some(ANSWER_TIMEOUT))?;
+    let request = json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": OPEN_METHOD,
+        "params": launch,
+    });
+    writeln!(stream, "{}", request)?;
+
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line)?;
+    let response: Value =
+        serde_json::from_str(&line).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    match response.get("error") {
+        Some(error) => Err(io::Error::other(
+            error["message"].as_str().unwrap_or("failed").to_string(),
+        )),
+        None => Ok(true),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::control::{self, ControlRequest, RpcError};
+    use std::fs;
+
+    #[test]
+    fn test_socket_name() {
+        assert_eq!(socket_name(""), "instance.sock");
+        assert_eq!(socket_name(":0"), "instance-_0.sock");
+        assert_eq!(socket_name("wayland-1"), "instance-wayland_1.sock");
+    }
+
+    #[test]
+    fn test_launch() {
+        use crate::config::CliArgs;
+        use clap::Parser;
+
+        assert_eq!(Launch::new(&Config::default()), Launch::default());
+        let args = CliArgs::try_parse_from(["mochi", "--tab", "-e", "htop"]).unwrap();
+        let mut config = Config::default();
+        config.startup_command = StartupCommand::from_args(&args.execute);
+        let launch = Launch {
+            tab: args.tab,
+            ..Launch::new(&config)
+        };
+        assert!(launch.tab);
+        assert_eq!(
+            launch.startup_command,
+            Some(StartupCommand::Argv(vec!["htop".to_string()]))
+        );
+        // Only the first window of a process restores the session
+        let launch: Launch = serde_json::from_value(json!({ "restore": true })).unwrap();
+        assert!(!launch.restore);
+    }
+
+    #[test]
+    fn test_open_in_running() {
+        let dir = std::env::temp_dir().join(format!("mochi-instance-test-{}", std::process::id()));
+        let path = dir.join("instance.sock");
+        let launch = Launch {
+            startup_command: Some(StartupCommand::Argv(vec!["htop".to_string()])),
+            tab: true,
+            ..Launch::default()
+        };
+        assert!(!open_in_running(&path, &launch).unwrap());
+
+        let expected = launch.clone();
+        let server = control::start(&path, move |request: ControlRequest| {
+            let result = match request.method.as_str() {
+                OPEN_METHOD => {
+                    let received: Launch = serde_json::from_value(request.params.clone()).unwrap();
+                    assert_eq!(received, expected);
+                    Ok(Value::Bool(true))
+                }
+                method => Err(RpcError::method_not_found(method)),
+            };
+            request.respond(result);
+            true
+        })
+        .unwrap();
+        assert!(open_in_running(&path, &launch).unwrap());
+
+        drop(server);
+        let _ = fs::remove_dir(&dir);
+    }
+}
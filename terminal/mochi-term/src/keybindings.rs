@@ -0,0 +1,358 @@
+//! Keyboard shortcuts (`[keybindings]`)
+//!
+//! Each action is bound to a combination written as modifiers and a key
+//! joined by `+`, such as `ctrl+shift+c` or `shift+pageup`. The modifiers
+//! are `ctrl`, `shift`, `alt` and `super` (or `cmd`); the key is a single
+//! character, a named key (`space`, `enter`, `tab`, `escape`, `backspace`,
+//! `delete`, `insert`, `home`, `end`, `pageup`, `pagedown`, `up`, `down`,
+//! `left`, `right`, `f1` to `f12`) or `plus`, `minus` or `equal`. An empty
+//! binding leaves the action without a shortcut.
+//!
+//! Modifiers must match exactly. A character matches the key as typed or
+//! as it would be without modifiers, ignoring case, so `ctrl+shift+plus`
+//! is Ctrl+Shift+= on a US layout and `ctrl+shift+minus` is Ctrl+Shift+-
+//! although Shift turns it into `_`.
+
+use winit::keyboard::{Key, ModifiersState, NamedKey};
+
+use crate::config::{ConfigError, KeybindingsConfig};
+
+/// Something a shortcut does
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Copy,
+    Paste,
+    Find,
+    ReloadConfig,
+    ToggleTheme,
+    ZoomIn,
+    ZoomOut,
+    ZoomReset,
+    RenameTab,
+    ProcessInfo,
+    RunCommand,
+    ScrollPageUp,
+    ScrollPageDown,
+    ScrollToTop,
+    ScrollToBottom,
+    ScrollToPreviousMark,
+    ScrollToNextMark,
+    SetMark,
+    CopyMode,
+    Hints,
+    OpenHints,
+    ClipboardHistory,
+    Monitor,
+    SshIntegration,
+    NewTabProfile,
+    MoveTabLeft,
+    MoveTabRight,
+    Inspector,
+    ToggleTrace,
+    SaveScreen,
+    SaveScrollback,
+    Screenshot,
+    Deprioritize,
+}
+
+/// The key of a combination
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Trigger {
+    /// A character, lowercase
+    Char(char),
+    Named(NamedKey),
+}
+
+impl Trigger {
+    fn parse(name: &str) -> Option<Self> {
+        let named = match name {
+            "space" => NamedKey::Space,
+            "enter" | "return" => NamedKey::Enter,
+            "tab" => NamedKey::Tab,
+            "escape" | "esc" => NamedKey::Escape,
+            "backspace" => NamedKey::Backspace,
+            "delete" => NamedKey::Delete,
+            "insert" => NamedKey::Insert,
+            "home" => NamedKey::Home,
+            "end" => NamedKey::End,
+            "pageup" => NamedKey::PageUp,
+            "pagedown" => NamedKey::PageDown,
+            "up" => NamedKey::ArrowUp,
+            "down" => NamedKey::ArrowDown,
+            "left" => NamedKey::ArrowLeft,
+            "right" => NamedKey::ArrowRight,
+            "f1" => NamedKey::F1,
+            "f2" => NamedKey::F2,
+            "f3" => NamedKey::F3,
+            "f4" => NamedKey::F4,
+            "f5" => NamedKey::F5,
+            "f6" => NamedKey::F6,
+            "f7" => NamedKey::F7,
+            "f8" => NamedKey::F8,
+            "f9" => NamedKey::F9,
+            "f10" => NamedKey::F10,
+            "f11" => NamedKey::F11,
+            "f12" => NamedKey::F12,
+            "plus" => return Some(Trigger::Char('+')),
+            "minus" => return Some(Trigger::Char('-')),
+            "equal" => return Some(Trigger::Char('=')),
+            _ => {
+                let mut chars = name.chars();
+                return match (chars.next(), chars.next()) {
+                    (Some(c), None) => Some(Trigger::Char(c)),
+                    _ => None,
+                };
+            }
+        };
+        Some(Trigger::Named(named))
+    }
+
+    fn matches(&self, key: &Key) -> bool {
+        match (self, key) {
+            (Trigger::Char(c), Key::Character(text)) => {
+                let mut chars = text.chars().flat_map(char::to_lowercase);
+                chars.next() == Some(*c) && chars.next().is_none()
+            }
+            (Trigger::Named(named), Key::Named(key)) => named == key,
+            _ => false,
+        }
+    }
+}
+
+/// A key with modifiers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyCombo {
+    modifiers: ModifiersState,
+    trigger: Trigger,
+}
+
+impl KeyCombo {
+    /// Parse a combination such as `ctrl+shift+c`; None if it is not valid
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim().to_lowercase();
+        // The key itself may be `+`
+        let (modifiers, key) = match text.strip_suffix("++") {
+            Some(rest) => (rest, "+"),
+            None => text.rsplit_once('+').unwrap_or(("", &text)),
+        };
+        let mut state = ModifiersState::empty();
+        for modifier in modifiers.split('+').filter(|m| !m.is_empty()) {
+            state |= match modifier {
+                "ctrl" | "control" => ModifiersState::CONTROL,
+                "shift" => ModifiersState::SHIFT,
+                "alt" | "option" => ModifiersState::ALT,
+                "super" | "cmd" | "command" => ModifiersState::SUPER,
+                _ => return None,
+            };
+        }
+        Some(Self {
+            modifiers: state,
+            trigger: Trigger::parse(key)?,
+        })
+    }
+
+    /// Whether a key pressed with `modifiers` is this combination; `key` is
+    /// the key as typed and `unmodified` without modifiers
+    pub fn matches(&self, key: &Key, unmodified: &Key, modifiers: ModifiersState) -> bool {
+        let relevant = ModifiersState::CONTROL
+            | ModifiersState::SHIFT
+            | ModifiersState::ALT
+            | ModifiersState::SUPER;
+        modifiers & relevant == self.modifiers
+            && (self.trigger.matches(key) || self.trigger.matches(unmodified))
+    }
+}
+
+/// The configured shortcuts
+#[derive(Debug, Clone, Default)]
+pub struct Keybindings {
+    bindings: Vec<(KeyCombo, Action)>,
+}
+
+impl Keybindings {
+    pub fn new(config: &KeybindingsConfig) -> Result<Self, ConfigError> {
+        let table = [
+            ("copy", &config.copy, Action::Copy),
+            ("paste", &config.paste, Action::Paste),
+            ("find", &config.find, Action::Find),
+            ("reload_config", &config.reload_config, Action::ReloadConfig),
+            ("toggle_theme", &config.toggle_theme, Action::ToggleTheme),
+            ("zoom_in", &config.zoom_in, Action::ZoomIn),
+            ("zoom_out", &config.zoom_out, Action::ZoomOut),
+            ("zoom_reset", &config.zoom_reset, Action::ZoomReset),
+            ("rename_tab", &config.rename_tab, Action::RenameTab),
+            ("process_info", &config.process_info, Action::ProcessInfo),
+            ("run_command", &config.run_command, Action::RunCommand),
+            (
+                "scroll_page_up",
+                &config.scroll_page_up,
+                Action::ScrollPageUp,
+            ),
+            (
+                "scroll_page_down",
+                &config.scroll_page_down,
+                Action::ScrollPageDown,
+            ),
+            ("scroll_to_top", &config.scroll_to_top, Action::ScrollToTop),
+            (
+                "scroll_to_bottom",
+                &config.scroll_to_bottom,
+                Action::ScrollToBottom,
+            ),
+            (
+                "scroll_to_previous_mark",
+                &config.scroll_to_previous_mark,
+                Action::ScrollToPreviousMark,
+            ),
+            (
+                "scroll_to_next_mark",
+                &config.scroll_to_next_mark,
+                Action::ScrollToNextMark,
+            ),
+            ("set_mark", &config.set_mark, Action::SetMark),
+            ("copy_mode", &config.copy_mode, Action::CopyMode),
+            ("hints", &config.hints, Action::Hints),
+            ("open_hints", &config.open_hints, Action::OpenHints),
+            (
+                "clipboard_history",
+                &config.clipboard_history,
+                Action::ClipboardHistory,
+            ),
+            ("monitor", &config.monitor, Action::Monitor),
+            (
+                "ssh_integration",
+                &config.ssh_integration,
+                Action::SshIntegration,
+            ),
+            (
+                "new_tab_profile",
+                &config.new_tab_profile,
+                Action::NewTabProfile,
+            ),
+            ("move_tab_left", &config.move_tab_left, Action::MoveTabLeft),
+            (
+                "move_tab_right",
+                &config.move_tab_right,
+                Action::MoveTabRight,
+            ),
+            ("inspector", &config.inspector, Action::Inspector),
+            ("toggle_trace", &config.toggle_trace, Action::ToggleTrace),
+            ("save_screen", &config.save_screen, Action::SaveScreen),
+            (
+                "save_scrollback",
+                &config.save_scrollback,
+                Action::SaveScrollback,
+            ),
+            ("screenshot", &config.screenshot, Action::Screenshot),
+            ("deprioritize", &config.deprioritize, Action::Deprioritize),
+        ];
+        let mut bindings = Vec::new();
+        for (name, binding, action) in table {
+            if binding.trim().is_empty() {
+                continue;
+            }
+            let combo = KeyCombo::parse(binding).ok_or_else(|| ConfigError {
+                message: format!("Invalid key binding '{}'", binding),
+                field: Some(format!("keybindings.{}", name)),
+            })?;
+            bindings.push((combo, action));
+        }
+        Ok(Self { bindings })
+    }
+
+    /// The action bound to a key, if any; see `KeyCombo::matches`
+    pub fn action(&self, key: &Key, unmodified: &Key, modifiers: ModifiersState) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(combo, _)| combo.matches(key, unmodified, modifiers))
+            .map(|&(_, action)| action)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn char_key(c: &str) -> Key {
+        Key::Character(c.into())
+    }
+
+    const CTRL_SHIFT: ModifiersState = ModifiersState::CONTROL.union(ModifiersState::SHIFT);
+
+    #[test]
+    fn test_parse() {
+        let combo = KeyCombo::parse("Ctrl+Shift+C").unwrap();
+        assert_eq!(combo.modifiers, CTRL_SHIFT);
+        assert_eq!(combo.trigger, Trigger::Char('c'));
+
+        let combo = KeyCombo::parse("cmd+plus").unwrap();
+        assert_eq!(combo.modifiers, ModifiersState::SUPER);
+        assert_eq!(combo.trigger, Trigger::Char('+'));
+        assert_eq!(KeyCombo::parse("ctrl++"), Some(combo_with("ctrl", '+')));
+
+        let combo = KeyCombo::parse("shift+pageup").unwrap();
+        assert_eq!(combo.trigger, Trigger::Named(NamedKey::PageUp));
+        assert_eq!(
+            KeyCombo::parse("f5").unwrap().trigger,
+            Trigger::Named(NamedKey::F5)
+        );
+
+        assert_eq!(KeyCombo::parse("hyper+c"), None);
+        assert_eq!(KeyCombo::parse("ctrl+pgup"), None);
+        assert_eq!(KeyCombo::parse("ctrl+"), None);
+    }
+
+    fn combo_with(modifier: &str, c: char) -> KeyCombo {
+        KeyCombo::parse(&format!("{}+{}", modifier, c)).unwrap()
+    }
+
+    #[test]
+    fn test_matches() {
+        let copy = KeyCombo::parse("ctrl+shift+c").unwrap();
+        assert!(copy.matches(&char_key("C"), &char_key("c"), CTRL_SHIFT));
+        assert!(!copy.matches(&char_key("c"), &char_key("c"), ModifiersState::CONTROL));
+        let with_alt = CTRL_SHIFT | ModifiersState::ALT;
+        assert!(!copy.matches(&char_key("C"), &char_key("c"), with_alt));
+
+        // Symbols match as typed or without Shift
+        let zoom_in = KeyCombo::parse("ctrl+shift+plus").unwrap();
+        assert!(zoom_in.matches(&char_key("+"), &char_key("="), CTRL_SHIFT));
+        let zoom_out = KeyCombo::parse("ctrl+shift+minus").unwrap();
+        assert!(zoom_out.matches(&char_key("_"), &char_key("-"), CTRL_SHIFT));
+
+        let page_up = KeyCombo::parse("shift+pageup").unwrap();
+        let key = Key::Named(NamedKey::PageUp);
+        assert!(page_up.matches(&key, &key, ModifiersState::SHIFT));
+        assert!(!page_up.matches(&key, &key, CTRL_SHIFT));
+    }
+
+    #[test]
+    fn test_keybindings() {
+        let bindings = Keybindings::new(&KeybindingsConfig::default()).unwrap();
+        let action = |key: &str| bindings.action(&char_key(key), &char_key(key), CTRL_SHIFT);
+        assert_eq!(action("E"), Some(Action::RenameTab));
+        assert_eq!(action("D"), Some(Action::Inspector));
+        assert_eq!(action("Q"), None);
+
+        let config = KeybindingsConfig {
+            rename_tab: "alt+r".to_string(),
+            inspector: String::new(),
+            ..KeybindingsConfig::default()
+        };
+        let bindings = Keybindings::new(&config).unwrap();
+        let key = char_key("r");
+        assert_eq!(
+            bindings.action(&key, &key, ModifiersState::ALT),
+            Some(Action::RenameTab)
+        );
+        let key = char_key("D");
+        assert_eq!(bindings.action(&key, &key, CTRL_SHIFT), None);
+
+        let config = KeybindingsConfig {
+            copy: "ctrl+shift+nope".to_string(),
+            ..KeybindingsConfig::default()
+        };
+        let err = Keybindings::new(&config).unwrap_err();
+        assert_eq!(err.field.as_deref(), Some("keybindings.copy"));
+    }
+}
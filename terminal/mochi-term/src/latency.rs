@@ -0,0 +1,179 @@
+//! Key-to-screen latency
+//!
+//! A key written to the shell starts a measurement, the first output the
+//! tab receives after it is taken to be its echo (and drawn at once rather
+//! than at the next frame deadline), and the frame that shows it ends the
+//! measurement. Keys typed while one is being measured are not measured
+//! themselves. A key nothing answers within ECHO_TIMEOUT (a password
+//! prompt, a program that does not echo) is forgotten.
+//!
+//! The inspector shows the numbers for the current tab, and predictive
+//! echo (`prediction`) uses them to tell a slow session from a fast one.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Number of recent measurements kept per tab
+pub const LATENCY_SAMPLES: usize = 100;
+
+/// How long a key waits for its echo before it is no longer measured
+const ECHO_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Key-to-screen latency of one tab
+#[derive(Debug, Default)]
+pub struct KeyLatency {
+    /// When the key being measured was written
+    typed: Option<Instant>,
+    /// Its echo arrived and waits to be drawn
+    echoed: bool,
+    /// Recent measurements, oldest first
+    samples: VecDeque<Duration>,
+}
+
+impl KeyLatency {
+    /// A key was written to the shell
+    pub fn typed(&mut self, now: Instant) {
+        if self.echoed {
+            return;
+        }
+        if self
+            .typed
+            .is_none_or(|typed| now.duration_since(typed) > ECHO_TIMEOUT)
+        {
+            self.typed = Some(now);
+        }
+    }
+
+    /// Output arrived for the tab
+    ///
+    /// Returns true if it is the echo of the key being measured, which
+    /// should be drawn right away.
+    pub fn output(&mut self, now: Instant) -> bool {
+        match self.typed {
+            Some(_) if self.echoed => false,
+            Some(typed) if now.duration_since(typed) > ECHO_TIMEOUT => {
+                self.typed = None;
+                false
+            }
+            Some(_) => {
+                self.echoed = true;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Check if an echo arrived and has not been drawn yet
+    pub fn echo_pending(&self) -> bool {
+        self.echoed
+    }
+
+    /// A frame of the tab was drawn; ends the measurement if it shows the echo
+    pub fn rendered(&mut self, now: Instant) {
+        if !self.echoed {
+            return;
+        }
+        if let Some(typed) = self.typed.take() {
+            if self.samples.len() == LATENCY_SAMPLES {
+                self.samples.pop_front();
+            }
+            let latency = now.duration_since(typed);
+            log::trace!("Key-to-screen latency: {:?}", latency);
+            self.samples.push_back(latency);
+        }
+        self.echoed = false;
+    }
+
+    /// Number of measurements kept
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// The latest measurement
+    pub fn last(&self) -> Option<Duration> {
+        self.samples.back().copied()
+    }
+
+    /// The measurement `percent` of the kept ones are at or below
+    pub fn percentile(&self, percent: usize) -> Option<Duration> {
+        let mut sorted: Vec<Duration> = self.samples.iter().copied().collect();
+        sorted.sort();
+        let last = sorted.len().checked_sub(1)?;
+        sorted.get((last * percent.min(100)).div_ceil(100)).copied()
+    }
+
+    /// The median measurement
+    pub fn median(&self) -> Option<Duration> {
+        self.percentile(50)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn test_measure_echo() {
+        let start = Instant::now();
+        let mut latency = KeyLatency::default();
+        assert!(!latency.output(start));
+        assert_eq!(latency.last(), None);
+
+        // The first key is measured until the frame showing its echo
+        latency.typed(start);
+        latency.typed(start + ms(5));
+        assert!(!latency.echo_pending());
+        assert!(latency.output(start + ms(20)));
+        assert!(latency.echo_pending());
+        assert!(!latency.output(start + ms(21)));
+        latency.typed(start + ms(22));
+        latency.rendered(start + ms(24));
+        assert!(!latency.echo_pending());
+        assert_eq!(latency.last(), Some(ms(24)));
+        assert_eq!(latency.len(), 1);
+
+        // A frame without an echo measures nothing
+        latency.rendered(start + ms(30));
+        assert_eq!(latency.len(), 1);
+    }
+
+    #[test]
+    fn test_unanswered_key() {
+        let start = Instant::now();
+        let mut latency = KeyLatency::default();
+        latency.typed(start);
+        assert!(!latency.output(start + ECHO_TIMEOUT + ms(1)));
+        latency.rendered(start + ECHO_TIMEOUT + ms(2));
+        assert_eq!(latency.len(), 0);
+
+        // A later key starts a new measurement
+        let later = start + ms(5000);
+        latency.typed(start);
+        latency.typed(later);
+        assert!(latency.output(later + ms(3)));
+        latency.rendered(later + ms(4));
+        assert_eq!(latency.last(), Some(ms(4)));
+    }
+
+    #[test]
+    fn test_percentiles() {
+        let start = Instant::now();
+        let mut latency = KeyLatency::default();
+        assert_eq!(latency.median(), None);
+        for i in 1..=LATENCY_SAMPLES as u64 + 10 {
+            latency.typed(start);
+            latency.output(start);
+            latency.rendered(start + ms(i));
+        }
+        // The oldest measurements were dropped
+        assert_eq!(latency.len(), LATENCY_SAMPLES);
+        assert_eq!(latency.percentile(0), Some(ms(11)));
+        assert_eq!(latency.median(), Some(ms(61)));
+        assert_eq!(latency.percentile(100), Some(ms(110)));
+        assert_eq!(latency.last(), Some(ms(110)));
+    }
+}
@@ -2,18 +2,55 @@
 //!
 //! A VT/xterm-compatible terminal emulator built from scratch.
 
+mod accent;
+mod animation;
 mod app;
+mod box_drawing;
+mod clipboard_history;
 mod config;
+mod config_watcher;
+mod contrast;
+mod control;
+mod copy_mode;
 mod event;
+mod export;
+mod flow;
+mod headless;
+mod highlight;
+mod hints;
+mod hooks;
+mod inline_image;
 mod input;
+mod inspector;
+mod instance;
+mod keybindings;
+mod latency;
+mod memory;
+mod monitor;
+mod opener;
+mod pointer;
+mod prediction;
 mod renderer;
+#[cfg(test)]
+mod replay;
+mod screenshot;
+mod search;
+mod session;
+mod ssh;
+mod stats;
+mod status_bar;
+mod termcap;
 mod terminal;
+mod terminfo;
+mod theme;
+mod toast;
+mod trace;
 
 use std::error::Error;
 
 use app::App;
 use clap::Parser;
-use config::{CliArgs, Config};
+use config::{CliArgs, Config, ThemeName};
 
 fn main() -> Result<(), Box<dyn Error>> {
     // Initialize logging with "warn" level by default for faster startup
@@ -25,6 +62,19 @@ fn main() -> Result<(), Box<dyn Error>> {
     // Parse CLI arguments
     let args = CliArgs::parse();
 
+    if args.list_themes {
+        list_themes();
+        return Ok(());
+    }
+    if args.shell_integration {
+        print!("{}", ssh::SHELL_INTEGRATION);
+        return Ok(());
+    }
+    if args.emit_terminfo {
+        print!("{}", terminfo::source());
+        return Ok(());
+    }
+
     // Load configuration with precedence: CLI > env > file > defaults
     let config = match Config::load_with_args(&args) {
         Ok(config) => config,
@@ -38,6 +88,25 @@ fn main() -> Result<(), Box<dyn Error>> {
     log::debug!("Theme: {:?}", config.theme);
     log::debug!("Font: {} @ {}pt", config.font.family, config.font.size);
 
+    if args.headless {
+        let command = config.command.as_deref().unwrap_or_default();
+        let code = headless::run(&config, command)?;
+        std::process::exit(code);
+    }
+
+    // Hand the launch to the running instance, if there is one
+    if config.single_instance {
+        let launch = instance::Launch {
+            tab: args.tab,
+            ..instance::Launch::new(&config)
+        };
+        match instance::open_in_running(&instance::socket_path(), &launch) {
+            Ok(true) => return Ok(()),
+            Ok(false) => {}
+            Err(e) => log::warn!("Failed to open in the running instance: {}", e),
+        }
+    }
+
     // Run the application
     let app = App::new(config)?;
     app.run()?;
@@ -45,3 +114,24 @@ fn main() -> Result<(), Box<dyn Error>> {
     log::debug!("Mochi Terminal exited");
     Ok(())
 }
+
+/// Print the built-in and user themes (--list-themes)
+fn list_themes() {
+    println!("Built-in themes:");
+    for name in ThemeName::all_names() {
+        println!("  {}", name);
+    }
+
+    let dir = theme::themes_dir();
+    let user_themes = theme::list();
+    match &dir {
+        Some(dir) => println!("\nUser themes ({}):", dir.display()),
+        None => println!("\nUser themes:"),
+    }
+    if user_themes.is_empty() {
+        println!("  (none)");
+    }
+    for name in user_themes {
+        println!("  {}", name);
+    }
+}
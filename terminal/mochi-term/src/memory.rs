@@ -0,0 +1,127 @@
+//! Memory accounting (what terminals and windows keep, against budgets)
+//!
+//! `Terminal::memory_usage` reports what one terminal holds in its
+//! scrollback and inline images, each renderer its glyph cache, and the
+//! clipboard history its copies. `[memory]` sets budgets for all windows
+//! together; about once a second the application adds up the usage of every
+//! tab, and when the scrollback or images are over budget it trims the
+//! largest tabs down to an equal share, dropping their oldest lines and
+//! images first. A glyph cache over budget is emptied and refilled with the
+//! glyphs the next frame draws. The inspector (Ctrl+Shift+D) shows the
+//! numbers.
+
+use std::ops::AddAssign;
+use std::time::Duration;
+
+/// How often usage is checked against the budgets
+pub const MEMORY_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Approximate memory held, in bytes, by kind
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemoryUsage {
+    /// Scrollback lines
+    pub scrollback: usize,
+    /// Decoded inline images
+    pub images: usize,
+    /// Rasterized glyphs
+    pub glyphs: usize,
+    /// Clipboard history entries
+    pub clipboard: usize,
+}
+
+impl MemoryUsage {
+    pub fn total(&self) -> usize {
+        self.scrollback + self.images + self.glyphs + self.clipboard
+    }
+}
+
+impl AddAssign for MemoryUsage {
+    fn add_assign(&mut self, other: Self) {
+        self.scrollback += other.scrollback;
+        self.images += other.images;
+        self.glyphs += other.glyphs;
+        self.clipboard += other.clipboard;
+    }
+}
+
+/// The most each of `sizes` may keep so that together they fit in `budget`,
+/// taking from the largest first; None if they already fit
+///
+/// Sizes below the share keep everything, and the rest is split evenly
+/// among the larger ones.
+pub fn fair_share(sizes: &[usize], budget: usize) -> Option<usize> {
+    if sizes.iter().sum::<usize>() <= budget {
+        return None;
+    }
+    let mut sorted = sizes.to_vec();
+    sorted.sort_unstable();
+    let mut remaining = budget;
+    for (i, &size) in sorted.iter().enumerate() {
+        let larger = sorted.len() - i;
+        if size * larger > remaining {
+            return Some(remaining / larger);
+        }
+        remaining -= size;
+    }
+    None
+}
+
+/// A size in bytes with a binary unit, e.g. "512M" or "1.5G"
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "K", "M", "G", "T"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 || value >= 10.0 {
+        format!("{:.0}{}", value, UNITS[unit])
+    } else {
+        format!("{:.1}{}", value, UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fair_share() {
+        assert_eq!(fair_share(&[10, 20], 30), None);
+        assert_eq!(fair_share(&[], 0), None);
+        // The small one keeps everything, the large ones split the rest
+        assert_eq!(fair_share(&[10, 100, 60], 90), Some(40));
+        assert_eq!(fair_share(&[50, 50], 60), Some(30));
+        assert_eq!(fair_share(&[50, 50], 0), Some(0));
+        let share = fair_share(&[5, 100, 300, 20], 200).unwrap();
+        let kept: usize = [5, 100, 300, 20].iter().map(|&size| size.min(share)).sum();
+        assert!(kept <= 200 && kept > 190);
+    }
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes(0), "0B");
+        assert_eq!(format_bytes(512), "512B");
+        assert_eq!(format_bytes(1536), "1.5K");
+        assert_eq!(format_bytes(300 << 20), "300M");
+        assert_eq!(format_bytes(3 << 29), "1.5G");
+    }
+
+    #[test]
+    fn test_memory_usage() {
+        let mut usage = MemoryUsage {
+            scrollback: 100,
+            images: 20,
+            ..Default::default()
+        };
+        usage += MemoryUsage {
+            scrollback: 1,
+            glyphs: 3,
+            clipboard: 4,
+            ..Default::default()
+        };
+        assert_eq!(usage.scrollback, 101);
+        assert_eq!(usage.total(), 128);
+    }
+}
@@ -0,0 +1,151 @@
+//! Activity and silence monitors (flagging tabs by their output)
+//!
+//! Ctrl+Shift+A cycles the monitor of the current tab. An activity monitor
+//! fires when the tab prints anything while it is not being looked at (a
+//! background tab, or any tab while the window is unfocused); a silence
+//! monitor fires when the tab has printed nothing for `silence_seconds`,
+//! e.g. once a long build stops. A fired monitor badges the tab until it
+//! is looked at, and fires again only after that (activity) or after the
+//! next output (silence).
+
+use std::time::{Duration, Instant};
+
+/// What a monitor watches for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MonitorKind {
+    Activity,
+    Silence,
+}
+
+/// The monitor of one tab
+#[derive(Debug, Clone)]
+pub struct Monitor {
+    kind: MonitorKind,
+    last_output: Instant,
+    /// Output arrived since the silence monitor last fired
+    armed: bool,
+    fired: bool,
+}
+
+impl Monitor {
+    pub fn new(kind: MonitorKind, now: Instant) -> Self {
+        Self {
+            kind,
+            last_output: now,
+            armed: true,
+            fired: false,
+        }
+    }
+
+    pub fn kind(&self) -> MonitorKind {
+        self.kind
+    }
+
+    /// The monitor fired and the tab has not been looked at since
+    pub fn fired(&self) -> bool {
+        self.fired
+    }
+
+    /// The tab printed something; returns true if that fires the monitor
+    pub fn output(&mut self, now: Instant, watched: bool) -> bool {
+        self.last_output = now;
+        self.armed = true;
+        if self.kind == MonitorKind::Activity && !watched && !self.fired {
+            self.fired = true;
+            return true;
+        }
+        false
+    }
+
+    /// Check for silence; returns true if the monitor fires now
+    ///
+    /// Silence in a tab that is being looked at is seen already, so it is
+    /// consumed without firing.
+    pub fn poll(&mut self, now: Instant, silence: Duration, watched: bool) -> bool {
+        if self.kind != MonitorKind::Silence || !self.armed {
+            return false;
+        }
+        if now.duration_since(self.last_output) < silence {
+            return false;
+        }
+        self.armed = false;
+        self.fired = !watched;
+        self.fired
+    }
+
+    /// The tab is being looked at: clear the badge
+    ///
+    /// Returns true if there was one.
+    pub fn seen(&mut self) -> bool {
+        std::mem::take(&mut self.fired)
+    }
+
+    /// When a silence monitor would fire if nothing is printed
+    pub fn deadline(&self, silence: Duration) -> Option<Instant> {
+        (self.kind == MonitorKind::Silence && self.armed).then(|| self.last_output + silence)
+    }
+}
+
+/// The next monitor in the Ctrl+Shift+A cycle: none, activity, silence
+pub fn cycle(current: Option<MonitorKind>) -> Option<MonitorKind> {
+    match current {
+        None => Some(MonitorKind::Activity),
+        Some(MonitorKind::Activity) => Some(MonitorKind::Silence),
+        Some(MonitorKind::Silence) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_activity_monitor() {
+        let now = Instant::now();
+        let mut monitor = Monitor::new(MonitorKind::Activity, now);
+        assert!(!monitor.output(now, true));
+        assert!(monitor.output(now, false));
+        // Fires once until the tab is looked at
+        assert!(!monitor.output(now, false));
+        assert!(monitor.fired());
+        assert!(monitor.seen());
+        assert!(!monitor.seen());
+        assert!(monitor.output(now, false));
+        assert_eq!(monitor.deadline(Duration::from_secs(1)), None);
+    }
+
+    #[test]
+    fn test_silence_monitor() {
+        let silence = Duration::from_secs(10);
+        let start = Instant::now();
+        let mut monitor = Monitor::new(MonitorKind::Silence, start);
+        assert_eq!(monitor.deadline(silence), Some(start + silence));
+        assert!(!monitor.poll(start + Duration::from_secs(5), silence, false));
+
+        let later = start + Duration::from_secs(5);
+        monitor.output(later, false);
+        assert!(!monitor.fired());
+        assert!(!monitor.poll(start + Duration::from_secs(12), silence, false));
+        assert!(monitor.poll(later + silence, silence, false));
+        assert!(monitor.fired());
+
+        // Silent until the next output, however long it stays quiet
+        assert_eq!(monitor.deadline(silence), None);
+        assert!(!monitor.poll(later + silence * 3, silence, false));
+        monitor.seen();
+        monitor.output(later + silence * 3, false);
+        assert!(!monitor.poll(later + silence * 5, silence, true));
+        assert!(!monitor.fired());
+        assert_eq!(monitor.deadline(silence), None);
+    }
+
+    #[test]
+    fn test_cycle() {
+        assert_eq!(cycle(None), Some(MonitorKind::Activity));
+        assert_eq!(
+            cycle(Some(MonitorKind::Activity)),
+            Some(MonitorKind::Silence)
+        );
+        assert_eq!(cycle(Some(MonitorKind::Silence)), None);
+    }
+}
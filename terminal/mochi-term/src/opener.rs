@@ -0,0 +1,270 @@
+//! Openers (what Ctrl+click and open hints do with a match)
+//!
+//! `[[openers]]` rules route a clicked hyperlink or hint match by the first
+//! pattern it matches: `open` hands it to the desktop's opener (xdg-open,
+//! or open on macOS), `command` runs a command template and `copy` copies
+//! it to the clipboard.
+//!
+//! Commands never go through a shell. A template is a list of arguments in
+//! which `{0}` (the whole match) and `{name}` (a named group of the
+//! pattern) are replaced, and an argument that is exactly `$NAME` becomes
+//! the words of that environment variable, e.g. `["$EDITOR", "+{line}",
+//! "{file}"]`. Matched text thus always stays within the argument it was
+//! put in, whatever it contains.
+
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::thread;
+
+use regex::Regex;
+
+use crate::config::{ConfigError, OpenerAction, OpenerRule};
+
+/// Program that opens URLs and files with the desktop's default handler
+#[cfg(target_os = "macos")]
+const DESKTOP_OPENER: &str = "open";
+#[cfg(not(target_os = "macos"))]
+const DESKTOP_OPENER: &str = "xdg-open";
+
+/// What to do with a match
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Opened {
+    /// Run this program with these arguments
+    Run(Vec<String>),
+    /// Copy this text
+    Copy(String),
+}
+
+#[derive(Debug, Clone)]
+struct Rule {
+    regex: Regex,
+    action: OpenerAction,
+    command: Vec<String>,
+}
+
+/// Compiled opener rules
+#[derive(Debug, Clone, Default)]
+pub struct Openers {
+    rules: Vec<Rule>,
+}
+
+impl Openers {
+    /// Compile the configured rules
+    pub fn new(configs: &[OpenerRule]) -> Result<Self, ConfigError> {
+        let error = |message: String| ConfigError {
+            message,
+            field: Some("openers".to_string()),
+        };
+        let rules = configs
+            .iter()
+            .map(|config| {
+                let regex = Regex::new(&config.pattern).map_err(|e| {
+                    error(format!(
+                        "Invalid opener pattern '{}': {}",
+                        config.pattern, e
+                    ))
+                })?;
+                if config.action == OpenerAction::Command {
+                    if config.command.is_empty() {
+                        return Err(error(format!(
+                            "Opener '{}' needs a command",
+                            config.pattern
+                        )));
+                    }
+                    for name in config.command.iter().flat_map(|arg| placeholders(arg)) {
+                        let known = match name.parse::<usize>() {
+                            Ok(index) => index < regex.captures_len(),
+                            Err(_) => regex.capture_names().flatten().any(|n| n == name),
+                        };
+                        if !known {
+                            return Err(error(format!(
+                                "Opener '{}' has no group '{{{}}}'",
+                                config.pattern, name
+                            )));
+                        }
+                    }
+                }
+                Ok(Rule {
+                    regex,
+                    action: config.action,
+                    command: config.command.clone(),
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { rules })
+    }
+
+    /// What the first rule matching `text` does with it
+    ///
+    /// `env` looks up environment variables for `$NAME` arguments. Text
+    /// with control characters is never opened, and neither is text that
+    /// would look like an option to the desktop opener.
+    pub fn route(&self, text: &str, env: impl Fn(&str) -> Option<String>) -> Option<Opened> {
+        if text.chars().any(char::is_control) {
+            return None;
+        }
+        let rule = self.rules.iter().find(|rule| rule.regex.is_match(text))?;
+        match rule.action {
+            OpenerAction::Copy => Some(Opened::Copy(text.to_string())),
+            OpenerAction::Open if text.starts_with('-') => None,
+            OpenerAction::Open => Some(Opened::Run(vec![
+                DESKTOP_OPENER.to_string(),
+                text.to_string(),
+            ])),
+            OpenerAction::Command => {
+                let captures = rule.regex.captures(text)?;
+                let mut argv = Vec::new();
+                for arg in &rule.command {
+                    if let Some(name) = arg.strip_prefix('$').filter(|name| is_env_name(name)) {
+                        let value = env(name).filter(|value| !value.trim().is_empty());
+                        let Some(value) = value else {
+                            log::warn!("Opener command needs ${}, which is not set", name);
+                            return None;
+                        };
+                        argv.extend(value.split_whitespace().map(str::to_string));
+                        continue;
+                    }
+                    argv.push(expand(arg, |name| {
+                        let group = match name.parse::<usize>() {
+                            Ok(index) => captures.get(index),
+                            Err(_) => captures.name(name),
+                        };
+                        group.map_or("", |m| m.as_str())
+                    }));
+                }
+                Some(Opened::Run(argv))
+            }
+        }
+    }
+}
+
+fn is_env_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Names of the `{name}` placeholders in a template argument
+fn placeholders(arg: &str) -> impl Iterator<Item = &str> {
+    arg.split('{')
+        .skip(1)
+        .filter_map(|rest| rest.split_once('}').map(|(name, _)| name))
+}
+
+/// Replace the `{name}` placeholders in a template argument
+fn expand<'a>(arg: &str, value: impl Fn(&str) -> &'a str) -> String {
+    let mut out = String::new();
+    let mut rest = arg;
+    while let Some(start) = rest.find('{') {
+        let Some(len) = rest[start..].find('}') else {
+            break;
+        };
+        out.push_str(&rest[..start]);
+        out.push_str(value(&rest[start + 1..start + len]));
+        rest = &rest[start + len + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Start `argv` in `cwd` without waiting for it
+pub fn spawn(argv: &[String], cwd: Option<&Path>) {
+    let Some((program, args)) = argv.split_first() else {
+        return;
+    };
+    let mut command = Command::new(program);
+    command
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    if let Some(cwd) = cwd {
+        command.current_dir(cwd);
+    }
+    match command.spawn() {
+        Ok(mut child) => {
+            let _ = thread::Builder::new()
+                .name("opener".to_string())
+                .spawn(move || child.wait());
+        }
+        Err(e) => log::warn!("Failed to run '{}': {}", program, e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(pattern: &str, action: OpenerAction, command: &[&str]) -> OpenerRule {
+        OpenerRule {
+            pattern: pattern.to_string(),
+            action,
+            command: command.iter().map(|arg| arg.to_string()).collect(),
+        }
+    }
+
+    fn env(name: &str) -> Option<String> {
+        (name == "EDITOR").then(|| "nvim -p".to_string())
+    }
+
+    fn run(args: &[&str]) -> Option<Opened> {
+        Some(Opened::Run(
+            args.iter().map(|arg| arg.to_string()).collect(),
+        ))
+    }
+
+    #[test]
+    fn test_openers_route() {
+        let openers = Openers::new(&[
+            rule("^https?://", OpenerAction::Open, &[]),
+            rule(
+                r"^(?P<file>[\w./-]+):(?P<line>\d+)$",
+                OpenerAction::Command,
+                &["$EDITOR", "+{line}", "{file}"],
+            ),
+            rule(r"^[0-9a-f]{7,40}$", OpenerAction::Copy, &[]),
+        ])
+        .unwrap();
+
+        assert_eq!(
+            openers.route("https://example.com", env),
+            run(&[DESKTOP_OPENER, "https://example.com"])
+        );
+        assert_eq!(
+            openers.route("src/main.rs:42", env),
+            run(&["nvim", "-p", "+42", "src/main.rs"])
+        );
+        assert_eq!(
+            openers.route("deadbeef", env),
+            Some(Opened::Copy("deadbeef".to_string()))
+        );
+        assert_eq!(openers.route("no match", env), None);
+        assert_eq!(openers.route("src/main.rs:42", |_| None), None);
+    }
+
+    #[test]
+    fn test_openers_keep_text_in_its_argument() {
+        let openers = Openers::new(&[
+            rule("^-", OpenerAction::Open, &[]),
+            rule("^x.*", OpenerAction::Command, &["echo", "[{0}]"]),
+        ])
+        .unwrap();
+        assert_eq!(openers.route("--help", env), None);
+        assert_eq!(
+            openers.route("x; rm -rf ~", env),
+            run(&["echo", "[x; rm -rf ~]"])
+        );
+        assert_eq!(openers.route("x\x1b[31m", env), None);
+    }
+
+    #[test]
+    fn test_openers_invalid() {
+        for config in [
+            rule("(", OpenerAction::Open, &[]),
+            rule("x", OpenerAction::Command, &[]),
+            rule("(?P<file>x)", OpenerAction::Command, &["vim", "{line}"]),
+            rule("(x)", OpenerAction::Command, &["vim", "{2}"]),
+        ] {
+            let err = Openers::new(&[config]).unwrap_err();
+            assert_eq!(err.field.as_deref(), Some("openers"));
+        }
+    }
+}
@@ -0,0 +1,168 @@
+//! Mouse pointer shape and visibility
+//!
+//! The pointer is an I-beam over text, a hand over hyperlinks and an arrow
+//! over the tab bar, the scrollbar and programs that track the mouse.
+//! Programs can ask for another shape over the grid with OSC 22. The pointer
+//! can be hidden while typing; moving the mouse shows it again.
+
+use std::str::FromStr;
+
+use winit::window::{CursorIcon, Window};
+
+/// X11 cursor font names that programs send with OSC 22, and their CSS names
+const X11_SHAPES: &[(&str, CursorIcon)] = &[
+    ("left_ptr", CursorIcon::Default),
+    ("xterm", CursorIcon::Text),
+    ("hand1", CursorIcon::Pointer),
+    ("hand2", CursorIcon::Pointer),
+    ("watch", CursorIcon::Wait),
+    ("question_arrow", CursorIcon::Help),
+    ("fleur", CursorIcon::Move),
+    ("cross", CursorIcon::Crosshair),
+    ("tcross", CursorIcon::Crosshair),
+    ("sb_h_double_arrow", CursorIcon::ColResize),
+    ("sb_v_double_arrow", CursorIcon::RowResize),
+];
+
+/// Shape for an OSC 22 request: the first known name in a comma-separated list
+///
+/// CSS names ("pointer", "text", "crosshair", ...) and common X11 cursor
+/// names are understood; unknown names are skipped.
+pub fn parse_shape(names: &str) -> Option<CursorIcon> {
+    names.split(',').find_map(|name| {
+        X11_SHAPES
+            .iter()
+            .find(|(x11, _)| *x11 == name)
+            .map(|&(_, icon)| icon)
+            .or_else(|| CursorIcon::from_str(name).ok())
+    })
+}
+
+/// What the mouse is over
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HoverTarget {
+    TabBar,
+    Scrollbar,
+    Text,
+    /// A cell with an OSC 8 hyperlink
+    Link,
+    /// The grid while the program has mouse tracking enabled
+    MouseTracking,
+}
+
+impl HoverTarget {
+    /// Pointer shape shown over the target
+    pub fn icon(self) -> CursorIcon {
+        match self {
+            HoverTarget::TabBar | HoverTarget::Scrollbar | HoverTarget::MouseTracking => {
+                CursorIcon::Default
+            }
+            HoverTarget::Text => CursorIcon::Text,
+            HoverTarget::Link => CursorIcon::Pointer,
+        }
+    }
+}
+
+/// Pointer state of one window
+///
+/// Changes are collected and applied to the window in one go, so the window
+/// is only told about a shape or visibility when it actually changes.
+#[derive(Debug)]
+pub struct PointerState {
+    icon: CursorIcon,
+    visible: bool,
+    /// Shape and visibility last applied to the window
+    applied: Option<(CursorIcon, bool)>,
+}
+
+impl Default for PointerState {
+    fn default() -> Self {
+        Self {
+            icon: CursorIcon::Text,
+            visible: true,
+            applied: None,
+        }
+    }
+}
+
+impl PointerState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The mouse is now over `target`
+    ///
+    /// A shape `requested` by the program replaces the text and mouse
+    /// tracking shapes, but not the tab bar, scrollbar or link shapes.
+    pub fn hover(&mut self, target: HoverTarget, requested: Option<CursorIcon>) {
+        self.icon = match (target, requested) {
+            (HoverTarget::Text | HoverTarget::MouseTracking, Some(icon)) => icon,
+            _ => target.icon(),
+        };
+    }
+
+    /// A key was typed into the terminal
+    pub fn typed(&mut self) {
+        self.visible = false;
+    }
+
+    /// The mouse moved or the window lost focus
+    pub fn show(&mut self) {
+        self.visible = true;
+    }
+
+    /// Shape and visibility to apply, if they changed since the last call
+    fn take_change(&mut self) -> Option<(CursorIcon, bool)> {
+        let current = (self.icon, self.visible);
+        if self.applied == Some(current) {
+            return None;
+        }
+        self.applied = Some(current);
+        Some(current)
+    }
+
+    /// Apply any change to the window
+    pub fn apply(&mut self, window: &Window) {
+        if let Some((icon, visible)) = self.take_change() {
+            window.set_cursor_icon(icon);
+            window.set_cursor_visible(visible);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pointer_changes_are_applied_once() {
+        let mut pointer = PointerState::new();
+        assert_eq!(pointer.take_change(), Some((CursorIcon::Text, true)));
+        assert_eq!(pointer.take_change(), None);
+
+        pointer.hover(HoverTarget::Link, None);
+        assert_eq!(pointer.take_change(), Some((CursorIcon::Pointer, true)));
+        pointer.hover(HoverTarget::Link, None);
+        assert_eq!(pointer.take_change(), None);
+
+        pointer.typed();
+        assert_eq!(pointer.take_change(), Some((CursorIcon::Pointer, false)));
+        pointer.show();
+        pointer.hover(HoverTarget::TabBar, None);
+        assert_eq!(pointer.take_change(), Some((CursorIcon::Default, true)));
+    }
+
+    #[test]
+    fn test_requested_shape() {
+        assert_eq!(parse_shape("crosshair"), Some(CursorIcon::Crosshair));
+        assert_eq!(parse_shape("hand2"), Some(CursorIcon::Pointer));
+        assert_eq!(parse_shape("nonsense,text"), Some(CursorIcon::Text));
+        assert_eq!(parse_shape("nonsense"), None);
+
+        let mut pointer = PointerState::new();
+        pointer.hover(HoverTarget::MouseTracking, Some(CursorIcon::Crosshair));
+        assert_eq!(pointer.take_change(), Some((CursorIcon::Crosshair, true)));
+        pointer.hover(HoverTarget::TabBar, Some(CursorIcon::Crosshair));
+        assert_eq!(pointer.take_change(), Some((CursorIcon::Default, true)));
+    }
+}
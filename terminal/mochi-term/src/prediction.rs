@@ -0,0 +1,449 @@
+//! Predictive local echo (opt-in, `latency.predictive_echo`)
+//!
+//! Over a slow ssh link a typed character only appears once the remote
+//! shell's echo has made the round trip. With predictive echo, editing keys
+//! typed at a shell prompt are applied at once to a copy of the cursor row:
+//! printable characters are inserted at the cursor, Backspace deletes the
+//! character before it and the Left and Right arrows move it. The cells the
+//! keys changed are drawn underlined to mark them as a guess, with the
+//! predicted cursor after them; the screen model is never changed.
+//!
+//! Predictions are reconciled against the tab's output, as in mosh: the row
+//! on the screen is compared with the row as predicted after each key. If it
+//! matches one of them, the keys up to that one have been echoed and only
+//! the ones after it are still drawn; if it matches none, the program did
+//! something else and all predictions are dropped. Mispredictions that were
+//! on screen are counted, and prediction stops for a while once there were
+//! too many (`latency.misprediction_limit` in MISPREDICTION_WINDOW).
+//!
+//! Predictions are held back until the echo is known to work: after a key
+//! that cannot be predicted (Enter, a control key, an escape sequence other
+//! than the arrows) nothing is shown until a predicted key has come back as
+//! expected. Text typed at a password prompt, which is never echoed, is
+//! therefore not shown.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use terminal_core::Screen;
+
+/// Predictions the tab's output has not answered in this long are dropped
+pub const PREDICTION_TTL: Duration = Duration::from_secs(1);
+
+/// How long a misprediction counts against `latency.misprediction_limit`
+pub const MISPREDICTION_WINDOW: Duration = Duration::from_secs(60);
+
+/// Most keys predicted ahead of the echo
+const MAX_PREDICTED_KEYS: usize = 64;
+
+/// An editing key whose effect on the cursor row can be predicted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Key {
+    Char(char),
+    Backspace,
+    Left,
+    Right,
+}
+
+/// The keys in typed input, if all of them can be predicted
+fn keys(data: &[u8]) -> Option<Vec<Key>> {
+    match data {
+        [] => None,
+        b"\x1b[D" | b"\x1bOD" => Some(vec![Key::Left]),
+        b"\x1b[C" | b"\x1bOC" => Some(vec![Key::Right]),
+        _ => data
+            .iter()
+            .map(|&b| match b {
+                0x20..=0x7e => Some(Key::Char(char::from(b))),
+                0x08 | 0x7f => Some(Key::Backspace),
+                _ => None,
+            })
+            .collect(),
+    }
+}
+
+/// The characters of the cursor row and the cursor column
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct RowState {
+    chars: Vec<char>,
+    cursor: usize,
+}
+
+impl RowState {
+    /// Row `row` of the screen, with the cursor's column
+    fn read(screen: &Screen, row: usize) -> Self {
+        let line = screen.line(row);
+        Self {
+            chars: (0..screen.cols())
+                .map(|col| line.cell(col).display_char())
+                .collect(),
+            cursor: screen.cursor().col,
+        }
+    }
+
+    /// Column after the last character on the row, or the cursor if it is
+    /// further right
+    fn end(&self) -> usize {
+        self.chars
+            .iter()
+            .rposition(|&c| c != ' ')
+            .map_or(0, |i| i + 1)
+            .max(self.cursor)
+    }
+
+    /// Apply a key as a line editor would, never left of `floor`
+    ///
+    /// Returns false if its effect cannot be predicted: it would wrap the
+    /// line (the last column is kept free), or move or delete past the
+    /// start of the input or the end of the line.
+    fn apply(&mut self, key: Key, floor: usize) -> bool {
+        match key {
+            Key::Char(c) => {
+                if self.end() + 1 >= self.chars.len() {
+                    return false;
+                }
+                self.chars.insert(self.cursor, c);
+                self.chars.pop();
+                self.cursor += 1;
+            }
+            Key::Backspace => {
+                if self.cursor <= floor {
+                    return false;
+                }
+                self.cursor -= 1;
+                self.chars.remove(self.cursor);
+                self.chars.push(' ');
+            }
+            Key::Left => {
+                if self.cursor <= floor {
+                    return false;
+                }
+                self.cursor -= 1;
+            }
+            Key::Right => {
+                if self.cursor >= self.end() {
+                    return false;
+                }
+                self.cursor += 1;
+            }
+        }
+        true
+    }
+}
+
+/// What the shown predictions change on the screen
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Predicted {
+    /// Screen row of the predictions
+    pub row: usize,
+    /// First column that differs from the screen
+    pub col: usize,
+    /// Predicted characters from `col` through the last one that differs
+    /// (empty if only the cursor moved)
+    pub text: String,
+    /// Predicted cursor column
+    pub cursor: usize,
+}
+
+/// Keys typed ahead of the echo in one tab
+#[derive(Debug, Default)]
+pub struct Prediction {
+    /// Screen row of the predictions
+    row: usize,
+    /// The row as last seen on the screen, followed by the row as predicted
+    /// after each key not echoed yet (empty if nothing is predicted)
+    states: Vec<RowState>,
+    /// When the oldest key not echoed yet was typed
+    since: Option<Instant>,
+    /// Column where typing on the row started; keys never edit left of it
+    floor: Option<usize>,
+    /// The echo of a predicted key was seen since the last key that could
+    /// not be predicted
+    confirmed: bool,
+    /// When shown predictions turned out wrong, within MISPREDICTION_WINDOW
+    misses: VecDeque<Instant>,
+}
+
+impl Prediction {
+    /// Predict the effect of `data`, typed on `screen`
+    ///
+    /// Input that is not made of editing keys, or whose effect cannot be
+    /// predicted, drops the predictions and starts over.
+    pub fn typed(&mut self, data: &[u8], screen: &Screen, now: Instant) {
+        let Some(keys) = keys(data) else {
+            self.reset();
+            return;
+        };
+        if self.states.is_empty() {
+            let cursor = screen.cursor();
+            if cursor.row >= screen.rows() {
+                self.reset();
+                return;
+            }
+            if self.floor.is_none() || cursor.row != self.row {
+                self.floor = Some(cursor.col);
+            }
+            self.row = cursor.row;
+            self.states.push(RowState::read(screen, cursor.row));
+            self.since = Some(now);
+        }
+        let floor = self.floor.unwrap_or_default();
+        for key in keys {
+            let mut next = self.states[self.states.len() - 1].clone();
+            if self.states.len() > MAX_PREDICTED_KEYS || !next.apply(key, floor) {
+                self.reset();
+                return;
+            }
+            self.states.push(next);
+        }
+    }
+
+    /// The tab's output arrived; reconciles the predictions with the screen
+    ///
+    /// Keys whose echo is on the screen are dropped, and the predictions are
+    /// confirmed. If the screen matches no predicted state, all predictions
+    /// are dropped and held back, and counted as a misprediction if shown.
+    pub fn output(&mut self, screen: &Screen, now: Instant) {
+        if self.states.is_empty() {
+            return;
+        }
+        let cursor = screen.cursor();
+        let seen = (cursor.row == self.row && self.row < screen.rows())
+            .then(|| RowState::read(screen, self.row));
+        let echoed = seen.and_then(|seen| self.states.iter().rposition(|state| *state == seen));
+        match echoed {
+            // Nothing was echoed yet
+            Some(0) => {}
+            Some(echoed) => {
+                self.confirmed = true;
+                self.states.drain(..echoed);
+                if self.states.len() == 1 {
+                    self.clear();
+                } else {
+                    self.since = Some(now);
+                }
+            }
+            None => self.mispredicted(now),
+        }
+    }
+
+    /// Drop predictions nothing answered within PREDICTION_TTL
+    ///
+    /// Returns true if predictions were shown and are now gone.
+    pub fn expire(&mut self, now: Instant) -> bool {
+        if self
+            .since
+            .is_some_and(|since| now.duration_since(since) >= PREDICTION_TTL)
+        {
+            let shown = self.is_shown();
+            self.mispredicted(now);
+            return shown;
+        }
+        false
+    }
+
+    /// When the predictions expire, if there are any
+    pub fn deadline(&self) -> Option<Instant> {
+        self.since.map(|since| since + PREDICTION_TTL)
+    }
+
+    /// How the predictions change the screen, if they are shown
+    pub fn shown(&self) -> Option<Predicted> {
+        if !self.is_shown() {
+            return None;
+        }
+        let base = self.states.first()?;
+        let predicted = self.states.last()?;
+        let differs = |col: &usize| base.chars[*col] != predicted.chars[*col];
+        let cols = 0..predicted.chars.len();
+        let first = cols.clone().find(differs);
+        let last = cols.rev().find(differs);
+        let (col, text) = match (first, last) {
+            (Some(first), Some(last)) => (first, predicted.chars[first..=last].iter().collect()),
+            _ if base.cursor != predicted.cursor => (predicted.cursor, String::new()),
+            _ => return None,
+        };
+        Some(Predicted {
+            row: self.row,
+            col,
+            text,
+            cursor: predicted.cursor,
+        })
+    }
+
+    /// How many shown predictions turned out wrong in the last
+    /// MISPREDICTION_WINDOW
+    pub fn misses(&self, now: Instant) -> usize {
+        self.misses
+            .iter()
+            .filter(|&&at| now.duration_since(at) < MISPREDICTION_WINDOW)
+            .count()
+    }
+
+    /// Drop the predictions
+    pub fn clear(&mut self) {
+        self.states.clear();
+        self.since = None;
+    }
+
+    /// Drop the predictions and hold back new ones until the echo is seen
+    pub fn reset(&mut self) {
+        self.clear();
+        self.floor = None;
+        self.confirmed = false;
+    }
+
+    fn is_shown(&self) -> bool {
+        self.confirmed && self.states.len() > 1
+    }
+
+    /// The predictions were wrong: count them if they were shown, and reset
+    fn mispredicted(&mut self, now: Instant) {
+        if self.is_shown() {
+            while self
+                .misses
+                .front()
+                .is_some_and(|&at| now.duration_since(at) >= MISPREDICTION_WINDOW)
+            {
+                self.misses.pop_front();
+            }
+            self.misses.push_back(now);
+        }
+        self.reset();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use terminal_core::Dimensions;
+
+    fn screen_with(text: &str) -> Screen {
+        let mut screen = Screen::new(Dimensions::new(20, 3));
+        for c in text.chars() {
+            screen.print(c);
+        }
+        screen
+    }
+
+    fn predicted(col: usize, text: &str, cursor: usize) -> Option<Predicted> {
+        Some(Predicted {
+            row: 0,
+            col,
+            text: text.to_string(),
+            cursor,
+        })
+    }
+
+    /// A prediction whose echo of "$ a" was seen
+    fn confirmed() -> Prediction {
+        let mut prediction = Prediction::default();
+        prediction.typed(b"a", &screen_with("$ "), Instant::now());
+        prediction.output(&screen_with("$ a"), Instant::now());
+        prediction
+    }
+
+    #[test]
+    fn test_predictions_wait_for_echo() {
+        let now = Instant::now();
+        let mut prediction = Prediction::default();
+
+        // Nothing is shown until an echo confirms a prediction
+        prediction.typed(b"l", &screen_with("$ "), now);
+        assert_eq!(prediction.shown(), None);
+        prediction.output(&screen_with("$ "), now);
+        assert_eq!(prediction.shown(), None);
+        prediction.output(&screen_with("$ l"), now);
+        assert_eq!(prediction.shown(), None);
+
+        let screen = screen_with("$ l");
+        prediction.typed(b"s", &screen, now);
+        prediction.typed(b" -l", &screen, now);
+        assert_eq!(prediction.shown(), predicted(3, "s -l", 7));
+
+        // A partial echo leaves the rest predicted
+        prediction.output(&screen_with("$ ls "), now);
+        assert_eq!(prediction.shown(), predicted(5, "-l", 7));
+        prediction.output(&screen_with("$ ls -l"), now);
+        assert_eq!(prediction.shown(), None);
+        assert_eq!(prediction.deadline(), None);
+
+        // Enter starts over
+        prediction.typed(b"\r", &screen_with("$ ls -l"), now);
+        prediction.typed(b"x", &screen_with("$ ls -l"), now);
+        assert_eq!(prediction.shown(), None);
+    }
+
+    #[test]
+    fn test_cursor_movement() {
+        let now = Instant::now();
+        let mut prediction = confirmed();
+        let screen = screen_with("$ abc");
+        let mut predict = |data: &[u8]| {
+            prediction.typed(data, &screen, now);
+            prediction.shown()
+        };
+
+        assert_eq!(predict(b"\x1b[D"), predicted(4, "", 4));
+        assert_eq!(predict(b"\x1bOD"), predicted(3, "", 3));
+
+        // Characters are inserted at the cursor, Backspace deletes before it
+        assert_eq!(predict(b"x"), predicted(3, "xbc", 4));
+        assert_eq!(predict(b"\x7f\x7f"), predicted(2, "bc ", 2));
+        assert_eq!(predict(b"\x1b[C"), predicted(2, "bc ", 3));
+
+        // Nothing is predicted left of where typing started
+        assert_eq!(predict(b"\x1b[D"), predicted(2, "bc ", 2));
+        assert_eq!(predict(b"\x1b[D"), None);
+        assert_eq!(prediction.deadline(), None);
+    }
+
+    #[test]
+    fn test_mispredictions() {
+        let now = Instant::now();
+        let mut prediction = confirmed();
+        prediction.typed(b"b", &screen_with("$ a"), now);
+        assert!(prediction.shown().is_some());
+
+        // Output that matches no prediction drops them and holds back
+        prediction.output(&screen_with("$ aB"), now);
+        assert_eq!(prediction.shown(), None);
+        assert_eq!(prediction.misses(now), 1);
+        prediction.typed(b"c", &screen_with("$ aB"), now);
+        assert_eq!(prediction.shown(), None);
+
+        // Hidden predictions are not counted
+        prediction.output(&screen_with("$ aBx"), now);
+        assert_eq!(prediction.misses(now), 1);
+
+        // Mispredictions are forgotten after a while
+        assert_eq!(prediction.misses(now + MISPREDICTION_WINDOW), 0);
+    }
+
+    #[test]
+    fn test_prediction_limits() {
+        let now = Instant::now();
+        let mut prediction = confirmed();
+
+        // The line is never predicted to wrap
+        prediction.typed(b"0123456789", &screen_with("$ a"), now);
+        assert_eq!(prediction.shown(), predicted(3, "0123456789", 13));
+        prediction.typed(b"0123456789", &screen_with("$ a"), now);
+        assert_eq!(prediction.shown(), None);
+
+        // Unanswered predictions expire, and count as mispredictions
+        let mut prediction = confirmed();
+        prediction.typed(b"b", &screen_with("$ a"), now);
+        assert_eq!(prediction.deadline(), Some(now + PREDICTION_TTL));
+        assert!(!prediction.expire(now));
+        assert!(prediction.expire(now + PREDICTION_TTL));
+        assert_eq!(prediction.shown(), None);
+        assert_eq!(prediction.deadline(), None);
+        assert_eq!(prediction.misses(now), 1);
+
+        // Text that is not printable ASCII is not predicted
+        let mut prediction = confirmed();
+        prediction.typed("é".as_bytes(), &screen_with("$ a"), now);
+        assert_eq!(prediction.shown(), None);
+    }
+}
@@ -2,30 +2,226 @@
 //!
 //! Renders the terminal screen to a software buffer.
 
+use std::cell::OnceCell;
 use std::collections::HashMap;
 use std::num::NonZeroU32;
 use std::rc::Rc;
+use std::time::Instant;
 
 use fontdue::{Font, FontSettings};
 use softbuffer::{Context, Surface};
-use terminal_core::{Color, Screen, Selection};
+use terminal_core::{CellAttributes, Color, ImagePlacement, Screen};
 use winit::window::Window;
 
-use crate::config::ColorScheme;
+use crate::animation::{CursorAnimator, TabSlide, TAB_SLIDE_DURATION, TRAIL_SEGMENTS};
+use crate::box_drawing;
+use crate::config::{ColorScheme, Config, CursorAnimationConfig};
+use crate::contrast;
+use crate::screenshot::Screenshot;
+use crate::terminal::Progress;
+
+/// Space above and below the status bar text, in physical pixels
+const STATUS_BAR_PADDING: u32 = 8;
 
 /// Information about a tab for rendering
 pub struct TabInfo<'a> {
     pub title: &'a str,
+    /// The title is being edited (draws an edit box with a cursor)
+    pub editing: bool,
+    /// Accent color, drawn along the top of the header
+    pub accent: Option<Color>,
+    /// The tab's monitor, drawn after the title
+    pub badge: Option<TabBadge>,
+    /// Progress reported by the program, drawn along the bottom
+    pub progress: Option<Progress>,
 }
 
-/// Cell dimensions in pixels
+/// State of a tab's activity or silence monitor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TabBadge {
+    /// Monitoring (an outlined square)
+    Watching,
+    /// The monitor fired and the tab has not been looked at (a filled one)
+    Fired,
+}
+
+/// A one-line message drawn over the bottom-right corner of the terminal
+pub struct StatusOverlay<'a> {
+    pub text: &'a str,
+    /// Draw centered with a red border, as a question the user has to answer
+    pub dialog: bool,
+}
+
+/// The hyperlink under the mouse: every cell with its ID is underlined and
+/// its URL is shown in the bottom-left corner
+pub struct HoveredLink<'a> {
+    pub id: u32,
+    pub url: &'a str,
+}
+
+/// A list to pick from, drawn in the middle of the window
+pub struct PickerOverlay<'a> {
+    pub title: &'a str,
+    pub items: &'a [String],
+    /// Index of the highlighted item
+    pub selected: usize,
+}
+
+/// A hint mode label drawn over the start of its match
+pub struct HintLabel<'a> {
+    pub col: usize,
+    /// Row in the view (0 is the top visible row)
+    pub row: usize,
+    pub text: &'a str,
+}
+
+/// Typed text drawn ahead of its echo (predictive echo), underlined
+pub struct PredictedEcho<'a> {
+    pub col: usize,
+    /// Row in the view (0 is the top visible row)
+    pub row: usize,
+    pub text: &'a str,
+    /// Column of the predicted cursor on the row
+    pub cursor: usize,
+}
+
+/// A search match on one row of the view
+pub struct SearchHighlight {
+    /// Row in the view (0 is the top visible row)
+    pub row: usize,
+    /// First and last (inclusive) column
+    pub start: usize,
+    pub end: usize,
+    /// The match the search bar is on
+    pub focused: bool,
+}
+
+/// Where a message box is drawn
 #[derive(Debug, Clone, Copy)]
+enum BoxPosition {
+    /// Bottom-right corner (status overlays)
+    BottomRight,
+    /// Left edge with the bottom of the box at this y (link previews)
+    BottomLeft(i32),
+    /// Centered with the bottom of the box at this y (the new output pill)
+    BottomCenter(i32),
+    /// Middle of the window (dialogs)
+    Center,
+    /// Right edge with the top of the box at this y (toasts)
+    TopRight(i32),
+    /// Left edge at x, as far as it fits, with the top at y (tab tooltips)
+    TopLeft(i32, i32),
+}
+
+/// Cell dimensions in pixels
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct CellSize {
     pub width: f32,
     pub height: f32,
     pub baseline: f32,
 }
 
+/// Spacing around and inside the grid, in logical pixels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GridLayout {
+    /// Space between the window edges (and tab bar) and the grid
+    pub padding_x: u32,
+    pub padding_y: u32,
+    /// Line height multiplier (1.0 = normal)
+    pub line_height: f32,
+    /// Extra space added to each cell
+    pub cell_padding_x: u32,
+    pub cell_padding_y: u32,
+    /// Leave a line for the status bar below the grid
+    pub status_bar: bool,
+}
+
+impl Default for GridLayout {
+    fn default() -> Self {
+        Self {
+            padding_x: 0,
+            padding_y: 0,
+            line_height: 1.0,
+            cell_padding_x: 0,
+            cell_padding_y: 0,
+            status_bar: false,
+        }
+    }
+}
+
+impl GridLayout {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            padding_x: config.padding.x,
+            padding_y: config.padding.y,
+            line_height: config.font.line_height,
+            cell_padding_x: config.font.cell_padding_x,
+            cell_padding_y: config.font.cell_padding_y,
+            status_bar: config.status_bar.enabled,
+        }
+    }
+}
+
+/// Cell size for a font size (in physical pixels) and layout
+///
+/// Extra line height is split above and below the glyphs.
+fn compute_cell_size(font: &Font, font_size: f32, layout: &GridLayout, scale: f32) -> CellSize {
+    let metrics = font.metrics('M', font_size);
+    let natural_height = font_size * 1.4;
+    let height = (natural_height * layout.line_height).ceil()
+        + (layout.cell_padding_y as f32 * scale).round();
+    CellSize {
+        width: metrics.advance_width.ceil() + (layout.cell_padding_x as f32 * scale).round(),
+        height,
+        baseline: (font_size + (height - natural_height) / 2.0).round(),
+    }
+}
+
+/// Fonts loaded once and shared by the renderers of all windows
+pub struct FontSet {
+    /// Regular font
+    regular: Font,
+    /// Bold font (lazily loaded on first use)
+    bold: OnceCell<Option<Font>>,
+    /// Fallback fonts for emoji and symbols (lazily loaded)
+    fallbacks: OnceCell<Vec<Font>>,
+}
+
+impl FontSet {
+    /// Load the bundled regular font
+    ///
+    /// The bold font and system fallback fonts are loaded on first use.
+    pub fn load() -> Result<Self, Box<dyn std::error::Error>> {
+        // Load default font (bundled in assets for cross-platform support)
+        let font_data = include_bytes!("../assets/DejaVuSansMono.ttf");
+        let regular = Font::from_bytes(font_data as &[u8], FontSettings::default())?;
+        Ok(Self {
+            regular,
+            bold: OnceCell::new(),
+            fallbacks: OnceCell::new(),
+        })
+    }
+
+    /// Get the regular or bold font
+    fn font(&self, bold: bool) -> &Font {
+        if !bold {
+            return &self.regular;
+        }
+        self.bold
+            .get_or_init(|| {
+                let bold_font_data = include_bytes!("../assets/DejaVuSansMono-Bold.ttf");
+                Font::from_bytes(bold_font_data as &[u8], FontSettings::default()).ok()
+            })
+            .as_ref()
+            .unwrap_or(&self.regular)
+    }
+
+    /// Get the fallback fonts, loading them on first use
+    fn fallbacks(&self) -> &[Font] {
+        self.fallbacks.get_or_init(load_fallback_fonts)
+    }
+}
+
 /// Glyph cache entry
 struct GlyphEntry {
     /// Bitmap data (alpha values)
@@ -40,6 +236,215 @@ struct GlyphEntry {
     ymin: i32,
 }
 
+/// The parts of the renderer that draw the grid, borrowed apart from the
+/// surface so the grid can also be drawn into an offscreen buffer
+struct GridPainter<'a> {
+    glyph_cache: &'a HashMap<(char, bool), GlyphEntry>,
+    colors: &'a ColorScheme,
+    cell_size: CellSize,
+    minimum_contrast: f32,
+    dim: f32,
+}
+
+/// What is drawn over the cells of the grid
+#[derive(Default)]
+struct GridMarks<'a> {
+    /// Highlight the selection
+    selection: bool,
+    /// Draw the cursor cell (solid, or an outline while it is hidden)
+    cursor: bool,
+    copy_cursor: Option<(usize, usize)>,
+    search: &'a [SearchHighlight],
+    link: Option<&'a HoveredLink<'a>>,
+}
+
+impl GridPainter<'_> {
+    /// Draw the cells and inline images of the view of `screen` scrolled
+    /// back `scroll_offset` lines, with the grid's top left corner at `origin`
+    #[allow(clippy::too_many_arguments)]
+    fn draw(
+        &self,
+        buffer: &mut [u32],
+        width: u32,
+        height: u32,
+        screen: &Screen,
+        scroll_offset: usize,
+        (origin_x, origin_y): (i32, i32),
+        marks: &GridMarks<'_>,
+    ) {
+        let bg_color = self.colors.background_rgb();
+        let fg_color = self.colors.foreground_rgb();
+        let sel_color = self.colors.selection_rgb();
+        let cursor_color = self.colors.cursor_rgb();
+        let cell_width_px = self.cell_size.width;
+        let cell_height_px = self.cell_size.height;
+        let baseline = self.cell_size.baseline;
+        let cols = screen.cols();
+        let rows = screen.rows();
+        let cursor = screen.cursor();
+
+        // Search matches by view row
+        let mut row_matches: Vec<Vec<&SearchHighlight>> = vec![Vec::new(); rows];
+        for highlight in marks.search {
+            if let Some(matches) = row_matches.get_mut(highlight.row) {
+                matches.push(highlight);
+            }
+        }
+        let match_color = self.colors.ansi_rgb(3);
+
+        // Render each cell
+        for row in 0..rows {
+            // Rows count from the top of the screen, negative in scrollback,
+            // the same coordinates selections use
+            let buffer_row = row as isize - scroll_offset as isize;
+            let Some(line) = screen.line_at(buffer_row) else {
+                continue;
+            };
+            let is_from_scrollback = buffer_row < 0;
+            let actual_screen_row = usize::try_from(buffer_row).ok();
+
+            for col in 0..cols.min(line.cols()) {
+                let cell = line.cell(col);
+
+                // Skip continuation cells
+                if cell.is_continuation() {
+                    continue;
+                }
+
+                let x = (col as f32 * cell_width_px) as i32 + origin_x;
+                let y = (row as f32 * cell_height_px) as i32 + origin_y;
+
+                // Determine colors
+                let is_selected = marks.selection && screen.is_selected(col, buffer_row);
+                // Check if this is the cursor position (regardless of visibility)
+                let is_cursor_position = marks.cursor
+                    && !is_from_scrollback
+                    && scroll_offset == 0
+                    && actual_screen_row == Some(cursor.row)
+                    && cursor.col == col;
+                // Solid cursor when visible, outline when hidden
+                let is_solid_cursor = is_cursor_position && cursor.visible;
+                let is_outline_cursor = is_cursor_position && !cursor.visible;
+                let is_copy_cursor = marks.copy_cursor == Some((col, row));
+                let search_match = row_matches
+                    .get(row)
+                    .and_then(|matches| matches.iter().find(|m| (m.start..=m.end).contains(&col)));
+
+                let (fg, bg) = if is_copy_cursor {
+                    (bg_color, cursor_color)
+                } else if is_selected {
+                    (fg_color, sel_color)
+                } else if let Some(m) = search_match {
+                    // The focused match stands out from the others
+                    let bg = if m.focused { cursor_color } else { match_color };
+                    (bg_color, bg)
+                } else if is_solid_cursor {
+                    (bg_color, cursor_color)
+                } else {
+                    // Highlight rule colors replace the SGR colors they set
+                    let highlight = line.highlight_at(col);
+                    let fg = Renderer::resolve_color_static(
+                        self.colors,
+                        &highlight
+                            .and_then(|h| h.fg)
+                            .unwrap_or_else(|| cell.attrs.effective_fg()),
+                        true,
+                        fg_color,
+                        bg_color,
+                    );
+                    let bg = Renderer::resolve_color_static(
+                        self.colors,
+                        &highlight
+                            .and_then(|h| h.bg)
+                            .unwrap_or_else(|| cell.attrs.effective_bg()),
+                        false,
+                        fg_color,
+                        bg_color,
+                    );
+                    (contrast::ensure_contrast(fg, bg, self.minimum_contrast), bg)
+                };
+                let (fg, bg) = if self.dim > 0.0 {
+                    (
+                        contrast::mix(fg, bg_color, self.dim),
+                        contrast::mix(bg, bg_color, self.dim),
+                    )
+                } else {
+                    (fg, bg)
+                };
+
+                // Draw background
+                let cell_w = (cell.width() as f32 * cell_width_px) as i32;
+                let cell_h = cell_height_px as i32;
+                Renderer::fill_rect_static(buffer, x, y, cell_w, cell_h, bg, width, height);
+
+                // Draw character (hidden text keeps only its background)
+                let c = cell.display_char();
+                if c != ' ' && !cell.is_empty() && !cell.attrs.hidden {
+                    if let Some(glyph) = self.glyph_cache.get(&(c, cell.attrs.bold)) {
+                        Renderer::draw_glyph_static(
+                            buffer, x, y, glyph, fg, baseline, width, height,
+                        );
+                    }
+                }
+
+                // Draw underlines, overline and strikethrough across the cell
+                // (cells of the hovered link are underlined)
+                if !cell.attrs.hidden {
+                    let mut attrs = cell.attrs;
+                    if marks.link.is_some_and(|link| cell.hyperlink_id == link.id) {
+                        attrs.underline |= !attrs.double_underline;
+                    }
+                    for (dy, line_h) in Renderer::decoration_lines(&attrs, cell_h, baseline) {
+                        Renderer::fill_rect_static(
+                            buffer,
+                            x,
+                            y + dy,
+                            cell_w,
+                            line_h,
+                            fg,
+                            width,
+                            height,
+                        );
+                    }
+                }
+
+                // Draw outline cursor when cursor is hidden (provides visual feedback)
+                if is_outline_cursor {
+                    Renderer::draw_rect_outline_static(
+                        buffer,
+                        x,
+                        y,
+                        cell_w,
+                        cell_h,
+                        cursor_color,
+                        width,
+                        height,
+                    );
+                }
+            }
+        }
+
+        // Draw inline images on top of the text they cover
+        for placement in screen.images().placements() {
+            let display_row = placement.row + scroll_offset as isize;
+            if display_row + placement.rows as isize <= 0 || display_row >= rows as isize {
+                continue;
+            }
+            Renderer::draw_image_static(
+                buffer,
+                placement,
+                (placement.col as f32 * cell_width_px) as i32 + origin_x,
+                (display_row as f32 * cell_height_px) as i32 + origin_y,
+                (placement.cols as f32 * cell_width_px) as i32,
+                (placement.rows as f32 * cell_height_px) as i32,
+                origin_y,
+                width,
+                height,
+            );
+        }
+    }
+}
+
 /// Terminal renderer
 pub struct Renderer {
     /// Softbuffer context
@@ -47,16 +452,8 @@ pub struct Renderer {
     context: Context<Rc<Window>>,
     /// Softbuffer surface
     surface: Surface<Rc<Window>, Rc<Window>>,
-    /// Font
-    font: Font,
-    /// Bold font (lazily loaded on first use)
-    bold_font: Option<Font>,
-    /// Whether we've attempted to load the bold font
-    bold_font_loaded: bool,
-    /// Fallback fonts for emoji and symbols (lazily loaded)
-    fallback_fonts: Vec<Font>,
-    /// Whether we've attempted to load fallback fonts
-    fallback_fonts_loaded: bool,
+    /// Fonts shared with other windows
+    fonts: Rc<FontSet>,
     /// Glyph cache
     glyph_cache: HashMap<(char, bool), GlyphEntry>,
     /// Cell size
@@ -69,6 +466,20 @@ pub struct Renderer {
     height: u32,
     /// Current font size (scaled for HiDPI)
     font_size: f32,
+    /// Cursor movement animation
+    cursor_animator: CursorAnimator,
+    /// Tabs sliding into place after a reorder
+    tab_slide: TabSlide,
+    /// Minimum contrast ratio between text and background (1.0 = off)
+    minimum_contrast: f32,
+    /// Draw box drawing, block and powerline characters at cell size
+    builtin_box_drawing: bool,
+    /// Padding, line height and cell spacing
+    layout: GridLayout,
+    /// HiDPI scale factor, applied to the layout's logical pixels
+    scale_factor: f32,
+    /// How far the grid fades towards the background (0.0 = off)
+    dim: f32,
 }
 
 impl Renderer {
@@ -79,30 +490,24 @@ impl Renderer {
     /// - Common ASCII glyphs are pre-cached for faster first render
     pub fn new(
         window: Rc<Window>,
+        fonts: Rc<FontSet>,
         font_size: f32,
         colors: ColorScheme,
+        cursor_animation: &CursorAnimationConfig,
+        layout: GridLayout,
     ) -> Result<Self, Box<dyn std::error::Error>> {
         let context = Context::new(window.clone())?;
         let surface = Surface::new(&context, window.clone())?;
 
-        // Load default font (bundled in assets for cross-platform support)
-        let font_data = include_bytes!("../assets/DejaVuSansMono.ttf");
-        let font = Font::from_bytes(font_data as &[u8], FontSettings::default())?;
-
-        // Bold font is loaded lazily on first use to improve startup time
-        // Most terminal sessions don't use bold text immediately
+        // Fonts are loaded once per process; the bold font is loaded lazily
+        // on first use since most sessions don't use bold text immediately
+        let font = &fonts.regular;
 
         // Scale font size for HiDPI displays
         let scale_factor = window.scale_factor() as f32;
         let scaled_font_size = font_size * scale_factor;
 
-        // Calculate cell size
-        let metrics = font.metrics('M', scaled_font_size);
-        let cell_size = CellSize {
-            width: metrics.advance_width.ceil(),
-            height: (scaled_font_size * 1.4).ceil(),
-            baseline: scaled_font_size,
-        };
+        let cell_size = compute_cell_size(font, scaled_font_size, &layout, scale_factor);
 
         let size = window.inner_size();
 
@@ -123,17 +528,20 @@ impl Renderer {
         Ok(Self {
             context,
             surface,
-            font,
-            bold_font: None,
-            bold_font_loaded: false,
-            fallback_fonts: Vec::new(),
-            fallback_fonts_loaded: false,
+            fonts,
             glyph_cache,
             cell_size,
             colors,
             width: size.width,
             height: size.height,
             font_size: scaled_font_size,
+            cursor_animator: CursorAnimator::new(cursor_animation),
+            tab_slide: TabSlide::new(TAB_SLIDE_DURATION),
+            minimum_contrast: contrast::CONTRAST_DISABLED,
+            builtin_box_drawing: true,
+            layout,
+            scale_factor,
+            dim: 0.0,
         })
     }
 
@@ -147,22 +555,111 @@ impl Renderer {
         self.font_size
     }
 
+    /// Cell size the grid would have at `font_size` (for tabs zoomed
+    /// differently from the one shown)
+    pub fn cell_size_at(&self, font_size: f32) -> CellSize {
+        compute_cell_size(
+            &self.fonts.regular,
+            font_size,
+            &self.layout,
+            self.scale_factor,
+        )
+    }
+
     /// Change font size and recalculate cell dimensions
     pub fn set_font_size(&mut self, font_size: f32) {
         self.font_size = font_size;
 
         // Recalculate cell size
-        let metrics = self.font.metrics('M', font_size);
-        self.cell_size = CellSize {
-            width: metrics.advance_width.ceil(),
-            height: (font_size * 1.4).ceil(),
-            baseline: font_size,
-        };
+        self.cell_size = compute_cell_size(
+            &self.fonts.regular,
+            font_size,
+            &self.layout,
+            self.scale_factor,
+        );
 
         // Clear glyph cache since font size changed
         self.glyph_cache.clear();
     }
 
+    /// Follow a change of the window's scale factor (moved to a monitor
+    /// with a different DPI)
+    ///
+    /// The font size keeps its zoom relative to the configured size, and
+    /// glyphs are rasterized again at the new size.
+    pub fn set_scale_factor(&mut self, scale_factor: f32) {
+        if scale_factor <= 0.0 || scale_factor == self.scale_factor {
+            return;
+        }
+        let font_size = self.font_size / self.scale_factor * scale_factor;
+        self.scale_factor = scale_factor;
+        self.set_font_size(font_size);
+    }
+
+    /// Change the padding, line height and cell spacing
+    pub fn set_layout(&mut self, layout: GridLayout) {
+        self.layout = layout;
+        let cell_size = compute_cell_size(
+            &self.fonts.regular,
+            self.font_size,
+            &self.layout,
+            self.scale_factor,
+        );
+        // Glyphs are placed (and box drawing drawn) for the old cell size
+        if cell_size != self.cell_size {
+            self.glyph_cache.clear();
+        }
+        self.cell_size = cell_size;
+    }
+
+    /// Window padding in physical pixels
+    fn padding(&self) -> (u32, u32) {
+        (
+            (self.layout.padding_x as f32 * self.scale_factor).round() as u32,
+            (self.layout.padding_y as f32 * self.scale_factor).round() as u32,
+        )
+    }
+
+    /// Top left corner of the grid in the window
+    pub fn grid_origin(&self, tab_bar_height: u32) -> (u32, u32) {
+        let (padding_x, padding_y) = self.padding();
+        (padding_x, tab_bar_height + padding_y)
+    }
+
+    /// Height of the status bar (0 when it is off)
+    pub fn status_bar_height(&self) -> u32 {
+        if self.layout.status_bar {
+            self.cell_size.height as u32 + STATUS_BAR_PADDING
+        } else {
+            0
+        }
+    }
+
+    /// Columns and rows that fit with cells of `cell_size`
+    pub fn grid_size_for(&self, cell_size: &CellSize, tab_bar_height: u32) -> (usize, usize) {
+        let (padding_x, padding_y) = self.padding();
+        let width = self.width.saturating_sub(2 * padding_x);
+        let height = self
+            .height
+            .saturating_sub(tab_bar_height + self.status_bar_height() + 2 * padding_y);
+        (
+            (width as f32 / cell_size.width) as usize,
+            (height as f32 / cell_size.height) as usize,
+        )
+    }
+
+    /// Cell under a window position; positions in the padding map to the
+    /// nearest edge cells on the top and left
+    pub fn pixel_to_cell(&self, x: f64, y: f64, tab_bar_height: u32) -> (u16, u16) {
+        let (origin_x, origin_y) = self.grid_origin(tab_bar_height);
+        let x = (x - origin_x as f64).max(0.0);
+        let y = (y - origin_y as f64).max(0.0);
+        (
+            (x / self.cell_size.width as f64) as u16,
+            (y / self.cell_size.height as f64) as u16,
+        )
+    }
+
     /// Resize the renderer
     pub fn resize(&mut self, width: u32, height: u32) {
         self.width = width;
@@ -174,15 +671,61 @@ impl Renderer {
         self.colors = colors;
     }
 
+    /// Set the minimum text contrast ratio (1.0 disables adjustment)
+    pub fn set_minimum_contrast(&mut self, minimum_contrast: f32) {
+        self.minimum_contrast = minimum_contrast;
+    }
+
+    /// Draw box drawing, block and powerline characters at cell size
+    /// instead of taking them from the fonts
+    pub fn set_builtin_box_drawing(&mut self, builtin: bool) {
+        if builtin != self.builtin_box_drawing {
+            self.builtin_box_drawing = builtin;
+            self.glyph_cache.clear();
+        }
+    }
+
+    /// Fade the grid towards the background (0.0 = off), e.g. while unfocused
+    pub fn set_dim(&mut self, dim: f32) {
+        self.dim = dim;
+    }
+
+    /// Set cursor animation options (for config reload)
+    pub fn set_cursor_animation(&mut self, config: &CursorAnimationConfig) {
+        self.cursor_animator.configure(config);
+    }
+
+    /// Check if an animation needs more frames
+    pub fn needs_animation_frame(&self) -> bool {
+        self.cursor_animator.needs_frame() || self.tab_slide.needs_frame()
+    }
+
+    /// Slide the tabs to their new places after the tab at `from` moved to
+    /// `to` among `len` tabs
+    pub fn tab_moved(&mut self, from: usize, to: usize, len: usize) {
+        self.tab_slide.moved(from, to, len, Instant::now());
+    }
+
     /// Render the terminal screen
+    #[allow(clippy::too_many_arguments)]
     pub fn render(
         &mut self,
         screen: &Screen,
-        selection: &Selection,
         scroll_offset: usize,
         tab_bar_height: u32,
         tabs: &[TabInfo<'_>],
         active_tab: usize,
+        overlay: Option<&StatusOverlay<'_>>,
+        picker: Option<&PickerOverlay<'_>>,
+        toasts: &[&str],
+        copy_cursor: Option<(usize, usize)>,
+        hints: &[HintLabel<'_>],
+        search: &[SearchHighlight],
+        status: Option<(&str, &str)>,
+        link: Option<&HoveredLink<'_>>,
+        tab_tooltip: Option<(&str, i32)>,
+        pill: Option<&str>,
+        prediction: Option<&PredictedEcho<'_>>,
     ) -> Result<(), Box<dyn std::error::Error>> {
         let width = self.width;
         let height = self.height;
@@ -205,11 +748,12 @@ impl Renderer {
         let cell_width_px = self.cell_size.width;
         let cell_height_px = self.cell_size.height;
         let baseline = self.cell_size.baseline;
+        let (origin_x, origin_y) = self.grid_origin(tab_bar_height);
+        let (origin_x, origin_y) = (origin_x as i32, origin_y as i32);
+        let status_bar_height = self.status_bar_height();
 
-        let cols = screen.cols();
         let rows = screen.rows();
-        let scrollback = screen.scrollback();
-        let scrollback_len = scrollback.len();
+        let scrollback_len = screen.scrollback().len();
 
         // Pre-cache glyphs for tab titles
         for tab in tabs {
@@ -221,50 +765,42 @@ impl Renderer {
         }
         self.ensure_glyph_cached('+', false);
         self.ensure_glyph_cached('x', false);
-
-        // Pre-cache all glyphs we'll need (from both screen and scrollback if scrolled)
-        for row in 0..rows {
-            let line = if scroll_offset > 0 {
-                // Calculate which line to show
-                let scrollback_row = scrollback_len.saturating_sub(scroll_offset) + row;
-                if scrollback_row < scrollback_len {
-                    // This row comes from scrollback
-                    if let Some(sb_line) = scrollback.get(scrollback_row) {
-                        for col in 0..cols.min(sb_line.cols()) {
-                            let cell = sb_line.cell(col);
-                            if !cell.is_continuation() && !cell.is_empty() {
-                                let c = cell.display_char();
-                                if c != ' ' {
-                                    self.ensure_glyph_cached(c, cell.attrs.bold);
-                                }
-                            }
-                        }
-                    }
-                    continue;
-                } else {
-                    // This row comes from screen
-                    let screen_row = scrollback_row - scrollback_len;
-                    if screen_row < rows {
-                        screen.line(screen_row)
-                    } else {
-                        continue;
-                    }
-                }
-            } else {
-                screen.line(row)
-            };
-
-            for col in 0..cols {
-                let cell = line.cell(col);
-                if !cell.is_continuation() && !cell.is_empty() {
-                    let c = cell.display_char();
-                    if c != ' ' {
-                        self.ensure_glyph_cached(c, cell.attrs.bold);
-                    }
+        self.ensure_glyph_cached('_', false);
+        let overlay_texts = overlay.map(|overlay| overlay.text).into_iter();
+        let hint_texts = hints.iter().map(|hint| hint.text);
+        let picker_texts = picker.into_iter().flat_map(|picker| {
+            [picker.title]
+                .into_iter()
+                .chain(picker.items.iter().map(String::as_str))
+        });
+        let status_texts = status.into_iter().flat_map(|(left, right)| [left, right]);
+        let link_texts = link.map(|link| link.url).into_iter();
+        let predicted_texts = prediction.map(|prediction| prediction.text).into_iter();
+        for text in overlay_texts
+            .chain(status_texts)
+            .chain(link_texts)
+            .chain(predicted_texts)
+            .chain(pill)
+            .chain(toasts.iter().copied())
+            .chain(hint_texts)
+            .chain(picker_texts)
+        {
+            for c in text.chars() {
+                if c != ' ' {
+                    self.ensure_glyph_cached(c, false);
                 }
             }
         }
 
+        self.cache_screen_glyphs(screen, scroll_offset);
+
+        // Advance the cursor animation to the current frame
+        let now = Instant::now();
+        let cursor = screen.cursor();
+        self.cursor_animator.set_target(cursor.col, cursor.row, now);
+        let cursor_animating =
+            cursor.visible && scroll_offset == 0 && self.cursor_animator.is_animating(now);
+
         let mut buffer = self.surface.buffer_mut()?;
 
         // Clear with background color
@@ -272,141 +808,337 @@ impl Renderer {
         buffer.fill(bg_pixel);
 
         // Draw tab bar
+        let tab_offsets = self.tab_slide.offsets(tabs.len(), now);
         if tab_bar_height > 0 && !tabs.is_empty() {
             Self::draw_tab_bar_static(
                 &mut buffer,
                 &self.glyph_cache,
                 tabs,
+                &tab_offsets,
                 active_tab,
                 tab_bar_height,
                 width,
                 height,
                 &self.cell_size,
+                &self.colors,
                 bg_color,
                 fg_color,
             );
         }
 
-        let cursor = screen.cursor();
+        let painter = GridPainter {
+            glyph_cache: &self.glyph_cache,
+            colors: &self.colors,
+            cell_size: self.cell_size,
+            minimum_contrast: self.minimum_contrast,
+            dim: self.dim,
+        };
+        let marks = GridMarks {
+            selection: true,
+            // While animating, the cursor is drawn separately below
+            cursor: !cursor_animating,
+            copy_cursor,
+            search,
+            link,
+        };
+        painter.draw(
+            &mut buffer,
+            width,
+            height,
+            screen,
+            scroll_offset,
+            (origin_x, origin_y),
+            &marks,
+        );
 
-        // Render each cell
-        for row in 0..rows {
-            // Calculate which line to render based on scroll offset
-            let (line, is_from_scrollback, actual_screen_row) = if scroll_offset > 0 {
-                let scrollback_row = scrollback_len.saturating_sub(scroll_offset) + row;
-                if scrollback_row < scrollback_len {
-                    // This row comes from scrollback
-                    if let Some(sb_line) = scrollback.get(scrollback_row) {
-                        (sb_line, true, None)
-                    } else {
-                        continue;
-                    }
-                } else {
-                    // This row comes from screen
-                    let screen_row = scrollback_row - scrollback_len;
-                    if screen_row < rows {
-                        (screen.line(screen_row), false, Some(screen_row))
-                    } else {
-                        continue;
-                    }
-                }
-            } else {
-                (screen.line(row), false, Some(row))
+        // Draw the moving cursor (and its trail) at the interpolated position
+        if cursor_animating {
+            let cell_w = cell_width_px as i32;
+            let cell_h = cell_height_px as i32;
+            let to_pixels = |(col, row): (f32, f32)| {
+                (
+                    (col * cell_width_px) as i32 + origin_x,
+                    (row * cell_height_px) as i32 + origin_y,
+                )
             };
 
-            for col in 0..cols.min(line.cols()) {
-                let cell = line.cell(col);
-
-                // Skip continuation cells
-                if cell.is_continuation() {
-                    continue;
+            if let Some((tail, head)) = self.cursor_animator.trail(now) {
+                for i in 0..TRAIL_SEGMENTS {
+                    let t = i as f32 / TRAIL_SEGMENTS as f32;
+                    let point = (
+                        tail.0 + (head.0 - tail.0) * t,
+                        tail.1 + (head.1 - tail.1) * t,
+                    );
+                    let (x, y) = to_pixels(point);
+                    // Fade in from the tail towards the head
+                    let alpha = (i + 1) as f32 / (TRAIL_SEGMENTS + 1) as f32 * 0.6;
+                    let color = Self::blend_color(bg_color, cursor_color, alpha);
+                    Self::fill_rect_static(&mut buffer, x, y, cell_w, cell_h, color, width, height);
                 }
+            }
 
-                let x = (col as f32 * cell_width_px) as i32;
-                let y = (row as f32 * cell_height_px) as i32 + tab_bar_height as i32;
+            let (x, y) = to_pixels(self.cursor_animator.position(now));
+            Self::fill_rect_static(
+                &mut buffer,
+                x,
+                y,
+                cell_w,
+                cell_h,
+                cursor_color,
+                width,
+                height,
+            );
+        }
 
-                // Determine colors
-                // Don't highlight empty selections (single click without drag)
-                let is_selected = !selection.is_empty() && selection.contains(col, row as isize);
-                // Check if this is the cursor position (regardless of visibility)
-                let is_cursor_position = !is_from_scrollback
-                    && scroll_offset == 0
-                    && actual_screen_row == Some(cursor.row)
-                    && cursor.col == col;
-                // Solid cursor when visible, outline when hidden
-                let is_solid_cursor = is_cursor_position && cursor.visible;
-                let is_outline_cursor = is_cursor_position && !cursor.visible;
+        // Hint labels cover the first cells of their matches
+        let hint_bg = self.colors.ansi_rgb(3);
+        for hint in hints {
+            let x = (hint.col as f32 * cell_width_px) as i32 + origin_x;
+            let y = (hint.row as f32 * cell_height_px) as i32 + origin_y;
+            let w = (hint.text.chars().count() as f32 * cell_width_px) as i32;
+            Self::fill_rect_static(
+                &mut buffer,
+                x,
+                y,
+                w,
+                cell_height_px as i32,
+                hint_bg,
+                width,
+                height,
+            );
+            Self::draw_text_static(
+                &mut buffer,
+                &self.glyph_cache,
+                hint.text,
+                x,
+                y,
+                bg_color,
+                cell_width_px,
+                baseline,
+                width,
+                height,
+                w,
+            );
+        }
 
-                let (fg, bg) = if is_selected {
-                    (fg_color, sel_color)
-                } else if is_solid_cursor {
-                    (bg_color, cursor_color)
-                } else {
-                    let fg = Self::resolve_color_static(
-                        &self.colors,
-                        &cell.attrs.effective_fg(),
-                        true,
-                        fg_color,
-                        bg_color,
-                    );
-                    let bg = Self::resolve_color_static(
-                        &self.colors,
-                        &cell.attrs.effective_bg(),
-                        false,
-                        fg_color,
-                        bg_color,
-                    );
-                    (fg, bg)
-                };
+        // Predicted echo covers the cells it will be drawn in, underlined, with
+        // a dimmed cursor where the keys will leave it
+        if let Some(prediction) = prediction {
+            let x = (prediction.col as f32 * cell_width_px) as i32 + origin_x;
+            let y = (prediction.row as f32 * cell_height_px) as i32 + origin_y;
+            let cell_w = cell_width_px as i32;
+            let cell_h = cell_height_px as i32;
+            let w = (prediction.text.chars().count() as f32 * cell_width_px) as i32;
+            Self::fill_rect_static(&mut buffer, x, y, w, cell_h, bg_color, width, height);
+            Self::draw_text_static(
+                &mut buffer,
+                &self.glyph_cache,
+                prediction.text,
+                x,
+                y,
+                fg_color,
+                cell_width_px,
+                baseline,
+                width,
+                height,
+                w,
+            );
+            let thickness = (cell_h / 16).max(1);
+            let underline = (baseline as i32 + thickness).min(cell_h - thickness);
+            Self::fill_rect_static(
+                &mut buffer,
+                x,
+                y + underline,
+                w,
+                thickness,
+                fg_color,
+                width,
+                height,
+            );
+            let cursor = Self::blend_color(bg_color, cursor_color, 0.5);
+            let cursor_x = (prediction.cursor as f32 * cell_width_px) as i32 + origin_x;
+            Self::fill_rect_static(
+                &mut buffer,
+                cursor_x,
+                y,
+                cell_w,
+                cell_h,
+                cursor,
+                width,
+                height,
+            );
+        }
+
+        // Draw scrollbar if there's scrollback content
+        if scrollback_len > 0 {
+            Self::draw_scrollbar_static(
+                &mut buffer,
+                scroll_offset,
+                scrollback_len,
+                rows,
+                width,
+                height.saturating_sub(status_bar_height),
+                tab_bar_height,
+            );
+        }
+
+        if let Some((left, right)) = status.filter(|_| status_bar_height > 0) {
+            let y = height.saturating_sub(status_bar_height) as i32;
+            let bar_color = Self::blend_color(bg_color, (0, 0, 0), 0.3);
+            let text_color = Self::blend_color(fg_color, bg_color, 0.2);
+            Self::fill_rect_static(
+                &mut buffer,
+                0,
+                y,
+                width as i32,
+                status_bar_height as i32,
+                bar_color,
+                width,
+                height,
+            );
+            let text_y = y + (STATUS_BAR_PADDING / 2) as i32;
+            let margin = (cell_width_px as i32).max(4);
+            // The right side is kept whole; the left side is cut to fit
+            let right_width = (right.chars().count() as f32 * cell_width_px) as i32;
+            let right_x = (width as i32 - margin - right_width).max(margin);
+            Self::draw_text_static(
+                &mut buffer,
+                &self.glyph_cache,
+                right,
+                right_x,
+                text_y,
+                text_color,
+                cell_width_px,
+                baseline,
+                width,
+                height,
+                width as i32 - margin - right_x,
+            );
+            Self::draw_text_static(
+                &mut buffer,
+                &self.glyph_cache,
+                left,
+                margin,
+                text_y,
+                text_color,
+                cell_width_px,
+                baseline,
+                width,
+                height,
+                right_x - 2 * margin,
+            );
+        }
+
+        if let Some(link) = link {
+            Self::draw_message_box_static(
+                &mut buffer,
+                &self.glyph_cache,
+                link.url,
+                BoxPosition::BottomLeft(height.saturating_sub(status_bar_height) as i32),
+                bg_color,
+                fg_color,
+                sel_color,
+                cell_width_px,
+                cell_height_px,
+                baseline,
+                width,
+                height,
+            );
+        }
+
+        if let Some((text, x)) = tab_tooltip {
+            Self::draw_message_box_static(
+                &mut buffer,
+                &self.glyph_cache,
+                text,
+                BoxPosition::TopLeft(x, tab_bar_height as i32),
+                bg_color,
+                fg_color,
+                sel_color,
+                cell_width_px,
+                cell_height_px,
+                baseline,
+                width,
+                height,
+            );
+        }
 
-                // Draw background
-                let cell_w = (cell.width() as f32 * cell_width_px) as i32;
-                let cell_h = cell_height_px as i32;
-                Self::fill_rect_static(&mut buffer, x, y, cell_w, cell_h, bg, width, height);
+        if let Some(pill) = pill {
+            Self::draw_message_box_static(
+                &mut buffer,
+                &self.glyph_cache,
+                pill,
+                BoxPosition::BottomCenter(height.saturating_sub(status_bar_height) as i32),
+                bg_color,
+                fg_color,
+                sel_color,
+                cell_width_px,
+                cell_height_px,
+                baseline,
+                width,
+                height,
+            );
+        }
 
-                // Draw character
-                let c = cell.display_char();
-                if c != ' ' && !cell.is_empty() {
-                    if let Some(glyph) = self.glyph_cache.get(&(c, cell.attrs.bold)) {
-                        Self::draw_glyph_static(
-                            &mut buffer,
-                            x,
-                            y,
-                            glyph,
-                            fg,
-                            baseline,
-                            width,
-                            height,
-                        );
-                    }
-                }
+        if let Some(overlay) = overlay {
+            let border_color = if overlay.dialog {
+                self.colors.ansi_rgb(1)
+            } else {
+                sel_color
+            };
+            let position = if overlay.dialog {
+                BoxPosition::Center
+            } else {
+                BoxPosition::BottomRight
+            };
+            Self::draw_message_box_static(
+                &mut buffer,
+                &self.glyph_cache,
+                overlay.text,
+                position,
+                bg_color,
+                fg_color,
+                border_color,
+                cell_width_px,
+                cell_height_px,
+                baseline,
+                width,
+                height,
+            );
+        }
 
-                // Draw outline cursor when cursor is hidden (provides visual feedback)
-                if is_outline_cursor {
-                    Self::draw_rect_outline_static(
-                        &mut buffer,
-                        x,
-                        y,
-                        cell_w,
-                        cell_h,
-                        cursor_color,
-                        width,
-                        height,
-                    );
-                }
-            }
+        if let Some(picker) = picker {
+            Self::draw_picker_static(
+                &mut buffer,
+                &self.glyph_cache,
+                picker,
+                bg_color,
+                fg_color,
+                sel_color,
+                cell_width_px,
+                cell_height_px,
+                baseline,
+                width,
+                height,
+            );
         }
 
-        // Draw scrollbar if there's scrollback content
-        if scrollback_len > 0 {
-            Self::draw_scrollbar_static(
+        // Toasts stack downwards from below the tab bar, newest last
+        let mut toast_y = tab_bar_height as i32 + (cell_width_px as i32).max(4);
+        for text in toasts {
+            toast_y += Self::draw_message_box_static(
                 &mut buffer,
-                scroll_offset,
-                scrollback_len,
-                rows,
+                &self.glyph_cache,
+                text,
+                BoxPosition::TopRight(toast_y),
+                bg_color,
+                fg_color,
+                sel_color,
+                cell_width_px,
+                cell_height_px,
+                baseline,
                 width,
                 height,
-                tab_bar_height,
             );
         }
 
@@ -416,6 +1148,239 @@ impl Renderer {
         Ok(())
     }
 
+    /// Draw the view of `screen` scrolled back `scroll_offset` lines
+    /// offscreen, inside the window padding but without the tab bar, status
+    /// bar, scrollbar or overlays (and without dimming)
+    pub fn screenshot(
+        &mut self,
+        screen: &Screen,
+        scroll_offset: usize,
+        cursor: bool,
+        selection: bool,
+    ) -> Screenshot {
+        self.cache_screen_glyphs(screen, scroll_offset);
+        let (padding_x, padding_y) = self.padding();
+        let width = 2 * padding_x + (screen.cols() as f32 * self.cell_size.width).ceil() as u32;
+        let height = 2 * padding_y + (screen.rows() as f32 * self.cell_size.height).ceil() as u32;
+        let (r, g, b) = self.colors.background_rgb();
+        let mut pixels = vec![Self::rgb_to_pixel(r, g, b); width as usize * height as usize];
+        let painter = GridPainter {
+            glyph_cache: &self.glyph_cache,
+            colors: &self.colors,
+            cell_size: self.cell_size,
+            minimum_contrast: self.minimum_contrast,
+            dim: 0.0,
+        };
+        let marks = GridMarks {
+            selection,
+            cursor,
+            ..GridMarks::default()
+        };
+        painter.draw(
+            &mut pixels,
+            width,
+            height,
+            screen,
+            scroll_offset,
+            (padding_x as i32, padding_y as i32),
+            &marks,
+        );
+        Screenshot {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Draw a one-line message in a bordered box (static version)
+    ///
+    /// Returns the height taken, including the margin below the box.
+    #[allow(clippy::too_many_arguments)]
+    fn draw_message_box_static(
+        buffer: &mut [u32],
+        glyph_cache: &HashMap<(char, bool), GlyphEntry>,
+        text: &str,
+        position: BoxPosition,
+        bg_color: (u8, u8, u8),
+        fg_color: (u8, u8, u8),
+        border_color: (u8, u8, u8),
+        cell_width: f32,
+        cell_height: f32,
+        baseline: f32,
+        buf_width: u32,
+        buf_height: u32,
+    ) -> i32 {
+        let Some((x, y, w, h)) = Self::message_box_rect(
+            text,
+            position,
+            cell_width,
+            cell_height,
+            buf_width,
+            buf_height,
+        ) else {
+            return 0;
+        };
+        let padding = (cell_width as i32).max(4);
+        let margin = padding;
+        let text_width = w - 2 * padding;
+
+        let box_color = Self::blend_color(bg_color, fg_color, 0.1);
+        Self::fill_rect_static(buffer, x, y, w, h, box_color, buf_width, buf_height);
+        Self::draw_rect_outline_static(buffer, x, y, w, h, border_color, buf_width, buf_height);
+        Self::draw_text_static(
+            buffer,
+            glyph_cache,
+            text,
+            x + padding,
+            y + padding / 2,
+            fg_color,
+            cell_width,
+            baseline,
+            buf_width,
+            buf_height,
+            text_width,
+        );
+        h + margin / 2
+    }
+
+    /// Where a message box for `text` goes: x, y, width and height
+    fn message_box_rect(
+        text: &str,
+        position: BoxPosition,
+        cell_width: f32,
+        cell_height: f32,
+        buf_width: u32,
+        buf_height: u32,
+    ) -> Option<(i32, i32, i32, i32)> {
+        let padding = (cell_width as i32).max(4);
+        let margin = padding;
+        let max_text_width = buf_width as i32 - 2 * (margin + padding);
+        if max_text_width <= 0 {
+            return None;
+        }
+
+        let text_width = (text.chars().count() as f32 * cell_width) as i32;
+        let text_width = text_width.min(max_text_width);
+        let w = text_width + 2 * padding;
+        let h = cell_height as i32 + padding;
+        let (x, y) = match position {
+            BoxPosition::Center => ((buf_width as i32 - w) / 2, (buf_height as i32 - h) / 2),
+            BoxPosition::BottomRight => (
+                buf_width as i32 - w - margin,
+                buf_height as i32 - h - margin,
+            ),
+            BoxPosition::TopRight(y) => (buf_width as i32 - w - margin, y),
+            BoxPosition::BottomLeft(y) => (margin, y - h - margin),
+            BoxPosition::BottomCenter(y) => ((buf_width as i32 - w) / 2, y - h - margin),
+            BoxPosition::TopLeft(x, y) => (x.min(buf_width as i32 - w - margin).max(margin), y),
+        };
+        Some((x, y, w, h))
+    }
+
+    /// Whether the window position (`x`, `y`) is on the new output pill
+    /// showing `text`
+    pub fn pill_contains(&self, text: &str, x: f64, y: f64) -> bool {
+        let bottom = self.height.saturating_sub(self.status_bar_height()) as i32;
+        let Some((left, top, w, h)) = Self::message_box_rect(
+            text,
+            BoxPosition::BottomCenter(bottom),
+            self.cell_size.width,
+            self.cell_size.height,
+            self.width,
+            self.height,
+        ) else {
+            return false;
+        };
+        (left as f64..(left + w) as f64).contains(&x) && (top as f64..(top + h) as f64).contains(&y)
+    }
+
+    /// Draw a picker: a bordered box in the middle of the window with the
+    /// title on its first line and one item per line below, the selected
+    /// item highlighted
+    #[allow(clippy::too_many_arguments)]
+    fn draw_picker_static(
+        buffer: &mut [u32],
+        glyph_cache: &HashMap<(char, bool), GlyphEntry>,
+        picker: &PickerOverlay<'_>,
+        bg_color: (u8, u8, u8),
+        fg_color: (u8, u8, u8),
+        accent_color: (u8, u8, u8),
+        cell_width: f32,
+        cell_height: f32,
+        baseline: f32,
+        buf_width: u32,
+        buf_height: u32,
+    ) {
+        let padding = (cell_width as i32).max(4);
+        let line_height = cell_height as i32;
+        let max_text_width = buf_width as i32 - 4 * padding;
+        // Title and items, as many as fit
+        let max_lines = ((buf_height as i32 - 4 * padding) / line_height.max(1)).max(0) as usize;
+        if max_text_width <= 0 || max_lines < 2 {
+            return;
+        }
+        let visible = picker.items.len().min(max_lines - 1);
+        // Keep the selected item in view
+        let first = (picker.selected + 1).saturating_sub(visible);
+        let items = &picker.items[first..first + visible];
+
+        let widest = items
+            .iter()
+            .map(|item| item.chars().count())
+            .chain([picker.title.chars().count()])
+            .max()
+            .unwrap_or(0);
+        let text_width = ((widest as f32 * cell_width) as i32).min(max_text_width);
+        let w = text_width + 2 * padding;
+        let h = (visible as i32 + 1) * line_height + padding;
+        let (x, y) = ((buf_width as i32 - w) / 2, (buf_height as i32 - h) / 2);
+
+        let box_color = Self::blend_color(bg_color, fg_color, 0.1);
+        Self::fill_rect_static(buffer, x, y, w, h, box_color, buf_width, buf_height);
+        Self::draw_rect_outline_static(buffer, x, y, w, h, accent_color, buf_width, buf_height);
+        let text_y = |line: usize| y + padding / 2 + line as i32 * line_height;
+        Self::draw_text_static(
+            buffer,
+            glyph_cache,
+            picker.title,
+            x + padding,
+            text_y(0),
+            Self::blend_color(box_color, fg_color, 0.7),
+            cell_width,
+            baseline,
+            buf_width,
+            buf_height,
+            text_width,
+        );
+        for (i, item) in items.iter().enumerate() {
+            if first + i == picker.selected {
+                Self::fill_rect_static(
+                    buffer,
+                    x + 1,
+                    text_y(i + 1),
+                    w - 2,
+                    line_height,
+                    accent_color,
+                    buf_width,
+                    buf_height,
+                );
+            }
+            Self::draw_text_static(
+                buffer,
+                glyph_cache,
+                item,
+                x + padding,
+                text_y(i + 1),
+                fg_color,
+                cell_width,
+                baseline,
+                buf_width,
+                buf_height,
+                text_width,
+            );
+        }
+    }
+
     /// Draw a scrollbar on the right side of the terminal (static version)
     fn draw_scrollbar_static(
         buffer: &mut [u32],
@@ -480,6 +1445,45 @@ impl Renderer {
         );
     }
 
+    /// Cache the glyphs of the view (from both screen and scrollback if scrolled)
+    fn cache_screen_glyphs(&mut self, screen: &Screen, scroll_offset: usize) {
+        for row in 0..screen.rows() {
+            let Some(line) = screen.line_at(row as isize - scroll_offset as isize) else {
+                continue;
+            };
+            for col in 0..screen.cols().min(line.cols()) {
+                let cell = line.cell(col);
+                if !cell.is_continuation() && !cell.is_empty() {
+                    let c = cell.display_char();
+                    if c != ' ' {
+                        self.ensure_glyph_cached(c, cell.attrs.bold);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Memory held by the glyph cache, in bytes
+    pub fn glyph_cache_bytes(&self) -> usize {
+        self.glyph_cache
+            .values()
+            .map(|entry| std::mem::size_of::<GlyphEntry>() + entry.bitmap.capacity())
+            .sum()
+    }
+
+    /// Empty the glyph cache if it holds more than `max_bytes`; the next
+    /// frame rasterizes the glyphs it draws again
+    ///
+    /// Returns true if the cache was emptied.
+    pub fn trim_glyph_cache(&mut self, max_bytes: usize) -> bool {
+        if self.glyph_cache_bytes() <= max_bytes {
+            return false;
+        }
+        self.glyph_cache.clear();
+        self.glyph_cache.shrink_to_fit();
+        true
+    }
+
     /// Ensure a glyph is cached
     ///
     /// Bold font is loaded lazily on first use to improve startup time
@@ -489,25 +1493,24 @@ impl Renderer {
             return;
         }
 
-        // Lazy load bold font on first use
-        if bold && !self.bold_font_loaded {
-            self.bold_font_loaded = true;
-            let bold_font_data = include_bytes!("../assets/DejaVuSansMono-Bold.ttf");
-            self.bold_font =
-                Font::from_bytes(bold_font_data as &[u8], FontSettings::default()).ok();
-        }
-
-        // Lazy load fallback fonts on first use (for emoji and symbols)
-        if !self.fallback_fonts_loaded {
-            self.fallback_fonts_loaded = true;
-            self.load_fallback_fonts();
+        let cell_w = self.cell_size.width as usize;
+        let cell_h = self.cell_size.height as usize;
+        if self.builtin_box_drawing {
+            if let Some(bitmap) = box_drawing::synthesize(c, cell_w, cell_h) {
+                let entry = GlyphEntry {
+                    bitmap,
+                    width: cell_w,
+                    height: cell_h,
+                    xmin: 0,
+                    ymin: self.cell_size.baseline as i32 - cell_h as i32,
+                };
+                self.glyph_cache.insert(key, entry);
+                return;
+            }
         }
 
-        let font = if bold {
-            self.bold_font.as_ref().unwrap_or(&self.font)
-        } else {
-            &self.font
-        };
+        // Bold and fallback fonts are loaded on first use
+        let font = self.fonts.font(bold);
 
         // Check if primary font has this glyph (glyph index 0 means missing)
         let has_glyph = font.lookup_glyph_index(c) != 0;
@@ -518,14 +1521,20 @@ impl Renderer {
         } else {
             // Try each fallback font
             let mut found = None;
-            for fallback in &self.fallback_fonts {
+            for fallback in self.fonts.fallbacks() {
                 if fallback.lookup_glyph_index(c) != 0 {
                     found = Some(fallback.rasterize(c, self.cell_size.baseline));
                     break;
                 }
             }
             // Use primary font as last resort (will show tofu/replacement char)
-            found.unwrap_or_else(|| font.rasterize(c, self.cell_size.baseline))
+            let mut found = found.unwrap_or_else(|| font.rasterize(c, self.cell_size.baseline));
+            // A fallback font's advance is not the cell width: keep a glyph
+            // that fits in one cell from spilling into its neighbours
+            if found.0.width <= cell_w {
+                found.0.xmin = found.0.xmin.clamp(0, (cell_w - found.0.width) as i32);
+            }
+            found
         };
 
         let entry = GlyphEntry {
@@ -539,40 +1548,34 @@ impl Renderer {
         self.glyph_cache.insert(key, entry);
     }
 
-    fn load_fallback_fonts(&mut self) {
-        // System font paths for emoji and symbol fonts
-        let fallback_paths: &[&str] = if cfg!(target_os = "macos") {
-            &[
-                "/System/Library/Fonts/Apple Color Emoji.ttc",
-                "/System/Library/Fonts/Supplemental/Arial Unicode.ttf",
-                "/Library/Fonts/Arial Unicode.ttf",
-                "/System/Library/Fonts/Supplemental/Symbola.ttf",
-            ]
-        } else {
-            // Linux paths
-            &[
-                "/usr/share/fonts/truetype/noto/NotoColorEmoji.ttf",
-                "/usr/share/fonts/noto-emoji/NotoColorEmoji.ttf",
-                "/usr/share/fonts/google-noto-emoji/NotoColorEmoji.ttf",
-                "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
-                "/usr/share/fonts/TTF/DejaVuSans.ttf",
-                "/usr/share/fonts/truetype/unifont/unifont.ttf",
-                "/usr/share/fonts/unifont/unifont.ttf",
-            ]
-        };
-
-        for path in fallback_paths {
-            if let Ok(data) = std::fs::read(path) {
-                if let Ok(font) = Font::from_bytes(data, FontSettings::default()) {
-                    self.fallback_fonts.push(font);
-                    log::debug!("Loaded fallback font: {}", path);
-                }
-            }
+    /// Lines drawn over a cell for its text decorations, as (offset from
+    /// the cell top, thickness) pairs
+    ///
+    /// Underlines sit just below the baseline, kept inside the cell so the
+    /// next row does not paint over them; strikethrough crosses the middle
+    /// of lowercase letters.
+    fn decoration_lines(attrs: &CellAttributes, cell_h: i32, baseline: f32) -> Vec<(i32, i32)> {
+        let mut lines = Vec::new();
+        if !(attrs.underline || attrs.double_underline || attrs.overline || attrs.strikethrough) {
+            return lines;
         }
-
-        if self.fallback_fonts.is_empty() {
-            log::warn!("No fallback fonts found for emoji/symbol support");
+        let thickness = (cell_h / 16).max(1);
+        let baseline = baseline as i32;
+        let bottom = cell_h - thickness;
+        if attrs.double_underline {
+            let lower = (baseline + 3 * thickness).min(bottom);
+            lines.push(((lower - 2 * thickness).max(0), thickness));
+            lines.push((lower, thickness));
+        } else if attrs.underline {
+            lines.push(((baseline + thickness).min(bottom), thickness));
+        }
+        if attrs.overline {
+            lines.push((0, thickness));
+        }
+        if attrs.strikethrough {
+            lines.push(((baseline - cell_h / 4 - thickness / 2).max(0), thickness));
         }
+        lines
     }
 
     /// Fill a rectangle with a color (static version)
@@ -609,6 +1612,79 @@ impl Renderer {
         }
     }
 
+    /// Draw an inline image scaled into its cell box (static version)
+    ///
+    /// Uses nearest-neighbor scaling and alpha blending. Rows above `clip_top`
+    /// (the tab bar) are not drawn.
+    #[allow(clippy::too_many_arguments)]
+    fn draw_image_static(
+        buffer: &mut [u32],
+        placement: &ImagePlacement,
+        x: i32,
+        y: i32,
+        box_w: i32,
+        box_h: i32,
+        clip_top: i32,
+        buf_width: u32,
+        buf_height: u32,
+    ) {
+        let image = &placement.image;
+        let (img_w, img_h) = (image.width as f32, image.height as f32);
+
+        let (dst_w, dst_h) = if placement.preserve_aspect_ratio {
+            let scale = (box_w as f32 / img_w).min(box_h as f32 / img_h);
+            ((img_w * scale) as i32, (img_h * scale) as i32)
+        } else {
+            (box_w, box_h)
+        };
+        if dst_w <= 0 || dst_h <= 0 {
+            return;
+        }
+
+        for dy in 0..dst_h {
+            let py = y + dy;
+            if py < clip_top || py >= buf_height as i32 {
+                continue;
+            }
+            let src_y = ((dy as f32 * img_h / dst_h as f32) as u32).min(image.height - 1);
+
+            for dx in 0..dst_w {
+                let px = x + dx;
+                if px < 0 || px >= buf_width as i32 {
+                    continue;
+                }
+                let src_x = ((dx as f32 * img_w / dst_w as f32) as u32).min(image.width - 1);
+                let src = ((src_y * image.width + src_x) * 4) as usize;
+                let [r, g, b, a] = [
+                    image.pixels[src],
+                    image.pixels[src + 1],
+                    image.pixels[src + 2],
+                    image.pixels[src + 3],
+                ];
+                if a == 0 {
+                    continue;
+                }
+
+                let idx = (py as u32 * buf_width + px as u32) as usize;
+                if idx >= buffer.len() {
+                    continue;
+                }
+                let color = if a == 255 {
+                    (r, g, b)
+                } else {
+                    let existing = buffer[idx];
+                    let under = (
+                        ((existing >> 16) & 0xFF) as u8,
+                        ((existing >> 8) & 0xFF) as u8,
+                        (existing & 0xFF) as u8,
+                    );
+                    Self::blend_color(under, (r, g, b), a as f32 / 255.0)
+                };
+                buffer[idx] = Self::rgb_to_pixel(color.0, color.1, color.2);
+            }
+        }
+    }
+
     /// Draw a rectangle outline (hollow rectangle) for cursor indication
     #[allow(clippy::too_many_arguments)]
     fn draw_rect_outline_static(
@@ -785,11 +1861,13 @@ impl Renderer {
         buffer: &mut [u32],
         glyph_cache: &HashMap<(char, bool), GlyphEntry>,
         tabs: &[TabInfo<'_>],
+        offsets: &[f32],
         active_tab: usize,
         tab_bar_height: u32,
         buf_width: u32,
         buf_height: u32,
         cell_size: &CellSize,
+        colors: &ColorScheme,
         bg_color: (u8, u8, u8),
         fg_color: (u8, u8, u8),
     ) {
@@ -818,15 +1896,22 @@ impl Renderer {
 
         let num_tabs = tabs.len() as u32;
         let available_width = buf_width.saturating_sub(new_tab_btn_width);
+        #[allow(clippy::manual_checked_ops)]
         let tab_width = if num_tabs > 0 {
             (available_width / num_tabs).min(tab_max_width)
         } else {
             tab_max_width
         };
 
-        for (i, tab) in tabs.iter().enumerate() {
+        // The active tab is drawn last, over the others while it slides
+        let order = (0..tabs.len())
+            .filter(|&i| i != active_tab)
+            .chain((active_tab < tabs.len()).then_some(active_tab));
+        for i in order {
+            let tab = &tabs[i];
             let is_active = i == active_tab;
-            let tab_x = (i as u32 * tab_width) as i32;
+            let offset = offsets.get(i).copied().unwrap_or(0.0);
+            let tab_x = ((i as f32 + offset) * tab_width as f32) as i32;
             let tab_bg = if is_active {
                 active_tab_bg
             } else {
@@ -845,8 +1930,25 @@ impl Renderer {
                 buf_height,
             );
 
+            let accent = tab
+                .accent
+                .map(|color| Self::resolve_color_static(colors, &color, true, fg_color, bg_color));
+            if let Some(accent) = accent {
+                Self::fill_rect_static(
+                    buffer,
+                    tab_x,
+                    0,
+                    tab_width as i32,
+                    3,
+                    accent,
+                    buf_width,
+                    buf_height,
+                );
+            }
+
             if is_active {
-                let accent = Self::blend_color(fg_color, (100, 149, 237), 0.5);
+                let accent =
+                    accent.unwrap_or_else(|| Self::blend_color(fg_color, (100, 149, 237), 0.5));
                 Self::fill_rect_static(
                     buffer,
                     tab_x,
@@ -859,6 +1961,37 @@ impl Renderer {
                 );
             }
 
+            if let Some(progress) = tab.progress {
+                let (color, percent) = match progress {
+                    Progress::Normal(percent) => (Color::Indexed(12), percent),
+                    Progress::Error(percent) => (Color::Indexed(9), percent),
+                    Progress::Paused(percent) => (Color::Indexed(11), percent),
+                    Progress::Indeterminate => (Color::Indexed(12), 100),
+                };
+                let color = Self::resolve_color_static(colors, &color, true, fg_color, bg_color);
+                let bar_y = tab_bar_height as i32 - 5;
+                let bar_width = (tab_width * percent as u32 / 100) as i32;
+                if progress == Progress::Indeterminate {
+                    // Dashes across the whole tab: busy, but no telling how long
+                    for dash_x in (0..bar_width).step_by(8) {
+                        Self::fill_rect_static(
+                            buffer,
+                            tab_x + dash_x,
+                            bar_y,
+                            4,
+                            2,
+                            color,
+                            buf_width,
+                            buf_height,
+                        );
+                    }
+                } else {
+                    Self::fill_rect_static(
+                        buffer, tab_x, bar_y, bar_width, 2, color, buf_width, buf_height,
+                    );
+                }
+            }
+
             if i < tabs.len() - 1 {
                 Self::fill_rect_static(
                     buffer,
@@ -874,7 +2007,9 @@ impl Renderer {
 
             let text_x = tab_x + tab_padding as i32;
             let text_y = ((tab_bar_height as f32 - cell_size.height) / 2.0).max(0.0) as i32;
-            let max_text_width = tab_width.saturating_sub(tab_padding * 2 + close_btn_width) as i32;
+            let badge_width: u32 = if tab.badge.is_some() { 12 } else { 0 };
+            let max_text_width =
+                tab_width.saturating_sub(tab_padding * 2 + close_btn_width + badge_width) as i32;
 
             Self::draw_text_static(
                 buffer,
@@ -890,6 +2025,64 @@ impl Renderer {
                 max_text_width,
             );
 
+            if tab.editing {
+                // Edit box around the title, with a cursor after the text
+                let text_width = (tab.title.chars().count() as f32 * cell_size.width) as i32;
+                let cursor_x = text_x + text_width.min(max_text_width);
+                Self::draw_rect_outline_static(
+                    buffer,
+                    text_x - 3,
+                    text_y - 1,
+                    max_text_width + 6,
+                    cell_size.height as i32 + 2,
+                    text_color,
+                    buf_width,
+                    buf_height,
+                );
+                if let Some(glyph) = glyph_cache.get(&('_', false)) {
+                    Self::draw_glyph_static(
+                        buffer,
+                        cursor_x,
+                        text_y,
+                        glyph,
+                        text_color,
+                        cell_size.baseline,
+                        buf_width,
+                        buf_height,
+                    );
+                }
+            }
+
+            if let Some(badge) = tab.badge {
+                let size = 6;
+                let badge_x = tab_x + tab_width as i32 - close_btn_width as i32 - size - 4;
+                let badge_y = (tab_bar_height as i32 - size) / 2;
+                match badge {
+                    TabBadge::Watching => Self::draw_rect_outline_static(
+                        buffer,
+                        badge_x,
+                        badge_y,
+                        size,
+                        size,
+                        inactive_fg,
+                        buf_width,
+                        buf_height,
+                    ),
+                    TabBadge::Fired => {
+                        let color = Self::resolve_color_static(
+                            colors,
+                            &Color::Indexed(11),
+                            true,
+                            fg_color,
+                            bg_color,
+                        );
+                        Self::fill_rect_static(
+                            buffer, badge_x, badge_y, size, size, color, buf_width, buf_height,
+                        );
+                    }
+                }
+            }
+
             if tabs.len() > 1 {
                 let close_x = tab_x + tab_width as i32 - close_btn_width as i32;
                 let close_y = text_y;
@@ -985,3 +2178,42 @@ impl Renderer {
         )
     }
 }
+
+/// Load system fonts for emoji and symbols
+fn load_fallback_fonts() -> Vec<Font> {
+    // System font paths for emoji and symbol fonts
+    let mut fonts = Vec::new();
+    let fallback_paths: &[&str] = if cfg!(target_os = "macos") {
+        &[
+            "/System/Library/Fonts/Apple Color Emoji.ttc",
+            "/System/Library/Fonts/Supplemental/Arial Unicode.ttf",
+            "/Library/Fonts/Arial Unicode.ttf",
+            "/System/Library/Fonts/Supplemental/Symbola.ttf",
+        ]
+    } else {
+        // Linux paths
+        &[
+            "/usr/share/fonts/truetype/noto/NotoColorEmoji.ttf",
+            "/usr/share/fonts/noto-emoji/NotoColorEmoji.ttf",
+            "/usr/share/fonts/google-noto-emoji/NotoColorEmoji.ttf",
+            "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+            "/usr/share/fonts/TTF/DejaVuSans.ttf",
+            "/usr/share/fonts/truetype/unifont/unifont.ttf",
+            "/usr/share/fonts/unifont/unifont.ttf",
+        ]
+    };
+
+    for path in fallback_paths {
+        if let Ok(data) = std::fs::read(path) {
+            if let Ok(font) = Font::from_bytes(data, FontSettings::default()) {
+                fonts.push(font);
+                log::debug!("Loaded fallback font: {}", path);
+            }
+        }
+    }
+
+    if fonts.is_empty() {
+        log::warn!("No fallback fonts found for emoji/symbol support");
+    }
+    fonts
+}
@@ -0,0 +1,142 @@
+//! Replay tests: the same bytes in any chunking give the same terminal
+//!
+//! The PTY hands output to the terminal in whatever pieces the reads
+//! return, so an escape sequence or UTF-8 character can be split anywhere.
+//! These tests replay captured byte streams from `tests/corpus/*.vt`
+//! through the parser and screen in random chunkings and check that the
+//! final state (screen, scrollback, title and replies) matches the state
+//! after processing the stream in one piece. Traces (`mochi --trace`,
+//! `tests/corpus/*.jsonl`) are replayed too, also in the chunks they were
+//! read in.
+//!
+//! To cover a new feature, add a capture of its output to the corpus (for
+//! example with `script -q -c <command> out.vt`, trimmed to the interesting
+//! part), or call [`assert_chunking_independent`] from its own tests.
+
+use std::fs;
+use std::path::PathBuf;
+
+use proptest::prelude::*;
+use proptest::sample::Index;
+
+use crate::terminal::Terminal;
+use crate::trace;
+
+/// Terminal size used for replays; small, so streams scroll and wrap
+const COLS: usize = 40;
+const ROWS: usize = 10;
+
+/// Captured streams, by file name
+fn corpus() -> Vec<(String, Vec<u8>)> {
+    captures()
+        .into_iter()
+        .map(|(name, chunks)| (name, chunks.concat()))
+        .collect()
+}
+
+/// Captured streams in the chunks they were read in (a `.vt` capture is
+/// one chunk), by file name
+fn captures() -> Vec<(String, Vec<Vec<u8>>)> {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/corpus");
+    let mut files: Vec<_> = fs::read_dir(&dir)
+        .unwrap_or_else(|e| panic!("Cannot read {}: {}", dir.display(), e))
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|ext| ext == "vt" || ext == "jsonl")
+        })
+        .collect();
+    files.sort();
+    assert!(!files.is_empty(), "No captures in {}", dir.display());
+    files
+        .into_iter()
+        .map(|path| {
+            let name = path.file_name().unwrap().to_string_lossy().into_owned();
+            if path.extension().is_some_and(|ext| ext == "jsonl") {
+                let text = fs::read_to_string(&path).unwrap();
+                let chunks = trace::read_chunks(&text)
+                    .unwrap_or_else(|e| panic!("Invalid trace {}: {}", name, e));
+                (name, chunks.into_iter().map(|(_, data)| data).collect())
+            } else {
+                (name, vec![fs::read(&path).unwrap()])
+            }
+        })
+        .collect()
+}
+
+/// Process `data` split at `cuts` and describe the resulting state
+fn replay(data: &[u8], cuts: &[usize]) -> String {
+    let mut term = Terminal::new(COLS, ROWS);
+    let mut responses = Vec::new();
+    let mut start = 0;
+    for &cut in cuts.iter().chain([data.len()].iter()) {
+        term.process(&data[start..cut]);
+        responses.extend(term.take_pending_responses().concat());
+        start = cut;
+    }
+    let snapshot = term.screen().snapshot(true).to_json().unwrap();
+    format!(
+        "{}\ntitle: {:?}\nresponses: {:?}",
+        snapshot,
+        term.title(),
+        String::from_utf8_lossy(&responses)
+    )
+}
+
+/// Sorted, deduplicated cut positions within `len`
+fn cut_positions(len: usize, indices: &[Index]) -> Vec<usize> {
+    let mut cuts: Vec<usize> = indices.iter().map(|index| index.index(len + 1)).collect();
+    cuts.sort_unstable();
+    cuts.dedup();
+    cuts
+}
+
+/// Assert that `data` gives the same state whether it is processed in one
+/// piece, one byte at a time, or split at `cuts`
+pub fn assert_chunking_independent(name: &str, data: &[u8], cuts: &[usize]) {
+    let whole = replay(data, &[]);
+    let bytewise: Vec<usize> = (1..data.len()).collect();
+    assert_eq!(
+        whole,
+        replay(data, &bytewise),
+        "{}: one byte at a time",
+        name
+    );
+    assert_eq!(whole, replay(data, cuts), "{}: split at {:?}", name, cuts);
+}
+
+#[test]
+fn test_replay_corpus_bytewise() {
+    for (name, data) in corpus() {
+        assert_chunking_independent(&name, &data, &[]);
+    }
+}
+
+#[test]
+fn test_replay_traces() {
+    for (name, chunks) in captures() {
+        if chunks.len() < 2 {
+            continue;
+        }
+        let cuts: Vec<usize> = chunks
+            .iter()
+            .scan(0, |end, chunk| {
+                *end += chunk.len();
+                Some(*end)
+            })
+            .collect();
+        assert_chunking_independent(&name, &chunks.concat(), &cuts[..cuts.len() - 1]);
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn test_replay_corpus_random_chunks(indices in prop::collection::vec(any::<Index>(), 1..48)) {
+        for (name, data) in corpus() {
+            let cuts = cut_positions(data.len(), &indices);
+            prop_assert_eq!(replay(&data, &[]), replay(&data, &cuts), "{} split at {:?}", name, &cuts);
+        }
+    }
+}
@@ -0,0 +1,68 @@
+//! Screenshots (the grid drawn offscreen and saved as a PNG)
+//!
+//! Ctrl+Shift+G asks for a file name and saves the current tab's view as
+//! the renderer draws it, including inline images, but without the tab bar,
+//! status bar or overlays; the `screenshot` control socket method does the
+//! same for scripts. The cursor and the selection can be left out
+//! (`[screenshot]` in the config, or the method's parameters), so
+//! screenshots for documentation do not show them.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// A drawn view
+pub struct Screenshot {
+    pub width: u32,
+    pub height: u32,
+    /// Pixels as `0x00RRGGBB`, row by row
+    pub pixels: Vec<u32>,
+}
+
+impl Screenshot {
+    /// The screenshot as an RGB PNG
+    pub fn to_png(&self) -> Result<Vec<u8>, png::EncodingError> {
+        let mut data = Vec::new();
+        let mut encoder = png::Encoder::new(&mut data, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let rgb: Vec<u8> = self
+            .pixels
+            .iter()
+            .flat_map(|pixel| {
+                let [_, r, g, b] = pixel.to_be_bytes();
+                [r, g, b]
+            })
+            .collect();
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&rgb)?;
+        writer.finish()?;
+        Ok(data)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.to_png()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_to_png() {
+        let screenshot = Screenshot {
+            width: 2,
+            height: 2,
+            pixels: vec![0x00ff0000, 0x0000ff00, 0x000000ff, 0x00102030],
+        };
+        let data = screenshot.to_png().unwrap();
+        let mut reader = png::Decoder::new(Cursor::new(data)).read_info().unwrap();
+        let mut rgb = vec![0; reader.output_buffer_size().unwrap()];
+        let info = reader.next_frame(&mut rgb).unwrap();
+        assert_eq!((info.width, info.height), (2, 2));
+        assert_eq!(info.color_type, png::ColorType::Rgb);
+        assert_eq!(rgb, [255, 0, 0, 0, 255, 0, 0, 0, 255, 0x10, 0x20, 0x30]);
+    }
+}
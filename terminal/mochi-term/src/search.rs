@@ -0,0 +1,350 @@
+//! Scrollback search
+//!
+//! The search bar finds every match of the query in the scrollback and on
+//! the screen. All matches are highlighted, the focused one in its own color,
+//! and the bar shows which match is focused ("3/17"). Matches are found again
+//! as the query is typed and, no more than every REFRESH_INTERVAL, while
+//! output arrives. Rows joined by a soft wrap are searched as one line.
+
+use std::time::{Duration, Instant};
+
+use terminal_core::Screen;
+
+/// Shortest time between two searches caused by new output
+pub const REFRESH_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A match, in selection coordinates (row 0 is the top of the screen,
+/// negative rows are in the scrollback)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchMatch {
+    pub row: isize,
+    /// First column of the match
+    pub start: usize,
+    /// Last row of the match, below `row` if it continues past a soft wrap
+    pub end_row: isize,
+    /// Last column of the match (inclusive)
+    pub end: usize,
+}
+
+/// Search state of one tab, while the search bar is open
+#[derive(Debug, Default)]
+pub struct Search {
+    query: String,
+    /// Matches from the oldest line to the newest
+    matches: Vec<SearchMatch>,
+    focused: Option<usize>,
+    /// Scrollback length when the matches were found, to follow lines that
+    /// have scrolled up since
+    scrollback_len: usize,
+    /// When output first arrived after the matches were found
+    stale_since: Option<Instant>,
+}
+
+impl Search {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a character to the query
+    pub fn push(&mut self, c: char, screen: &Screen) {
+        self.query.push(c);
+        self.refresh(screen, 0);
+    }
+
+    /// Remove the last character of the query
+    pub fn pop(&mut self, screen: &Screen) {
+        self.query.pop();
+        self.refresh(screen, 0);
+    }
+
+    /// Focus the match above (`older`) or below the focused one, wrapping around
+    pub fn step(&mut self, older: bool) {
+        let count = self.matches.len();
+        if count == 0 {
+            return;
+        }
+        self.focused = Some(match (self.focused, older) {
+            (Some(i), true) => (i + count - 1) % count,
+            (Some(i), false) => (i + 1) % count,
+            (None, _) => count - 1,
+        });
+    }
+
+    /// The focused match
+    pub fn focused(&self) -> Option<SearchMatch> {
+        self.focused.map(|i| self.matches[i])
+    }
+
+    /// Text of the search bar
+    pub fn status(&self) -> String {
+        match (self.query.is_empty(), self.focused) {
+            (true, _) => "Find: _".to_string(),
+            (false, Some(i)) => {
+                format!("Find: {}_  {}/{}", self.query, i + 1, self.matches.len())
+            }
+            (false, None) => format!("Find: {}_  no matches", self.query),
+        }
+    }
+
+    /// Note that output arrived, so the matches may be out of date
+    pub fn mark_stale(&mut self, now: Instant) {
+        self.stale_since.get_or_insert(now);
+    }
+
+    /// When the matches should be found again, if output arrived
+    pub fn refresh_deadline(&self) -> Option<Instant> {
+        self.stale_since.map(|since| since + REFRESH_INTERVAL)
+    }
+
+    /// Find the matches again if output arrived long enough ago; returns
+    /// true if they were
+    pub fn refresh_if_due(&mut self, screen: &Screen, now: Instant) -> bool {
+        if self
+            .refresh_deadline()
+            .is_none_or(|deadline| now < deadline)
+        {
+            return false;
+        }
+        // Lines that scrolled into the scrollback moved up by this much
+        let scrolled = screen
+            .scrollback()
+            .len()
+            .saturating_sub(self.scrollback_len);
+        self.refresh(screen, scrolled as isize);
+        true
+    }
+
+    /// Parts of matches on the rows shown at `scroll_offset`, as (view row,
+    /// first column, last column, focused); a match across a soft wrap has a
+    /// part on each row
+    pub fn visible(
+        &self,
+        rows: usize,
+        cols: usize,
+        scroll_offset: usize,
+    ) -> impl Iterator<Item = (usize, usize, usize, bool)> + '_ {
+        let top = -(scroll_offset as isize);
+        let bottom = top + rows as isize;
+        let first = self.matches.partition_point(|m| m.end_row < top);
+        self.matches[first..]
+            .iter()
+            .enumerate()
+            .take_while(move |(_, m)| m.row < bottom)
+            .flat_map(move |(i, m)| {
+                let focused = self.focused == Some(first + i);
+                (m.row.max(top)..=m.end_row.min(bottom - 1)).map(move |row| {
+                    let start = if row == m.row { m.start } else { 0 };
+                    let end = if row == m.end_row { m.end } else { cols - 1 };
+                    ((row - top) as usize, start, end, focused)
+                })
+            })
+    }
+
+    /// Find all matches, keeping the focus near the focused match after it
+    /// moved up by `scrolled` lines
+    fn refresh(&mut self, screen: &Screen, scrolled: isize) {
+        let target = self
+            .focused()
+            .map(|m| (m.row - scrolled, m.start))
+            .filter(|_| !self.query.is_empty());
+        self.matches = find_matches(screen, &self.query);
+        self.scrollback_len = screen.scrollback().len();
+        self.stale_since = None;
+
+        self.focused = match target {
+            // The last match at or before the old focus, else the first after it
+            Some(target) => {
+                let after = self.matches.partition_point(|m| (m.row, m.start) <= target);
+                match after {
+                    0 if self.matches.is_empty() => None,
+                    0 => Some(0),
+                    n => Some(n - 1),
+                }
+            }
+            // Start from the newest match
+            None => self.matches.len().checked_sub(1),
+        };
+    }
+}
+
+/// All matches of `query` in the scrollback and on the screen
+///
+/// A query without capitals matches case-insensitively.
+pub fn find_matches(screen: &Screen, query: &str) -> Vec<SearchMatch> {
+    if query.is_empty() {
+        return Vec::new();
+    }
+    let ignore_case = !query.chars().any(char::is_uppercase);
+    let query = if ignore_case {
+        query.to_lowercase()
+    } else {
+        query.to_string()
+    };
+
+    let mut matches = Vec::new();
+    let mut row = -(screen.scrollback().len() as isize);
+    while let Some((top, bottom)) = screen.logical_line_rows(row) {
+        // Text of the logical line, with the row and column of each byte
+        let mut text = String::new();
+        let mut cols = Vec::new();
+        for row in top..=bottom {
+            let Some(line) = screen.line_at(row) else {
+                break;
+            };
+            let (row_text, row_cols) = line.text_columns();
+            let end = if row < bottom {
+                row_cols.partition_point(|&col| col < line.wrap_end())
+            } else {
+                row_text.len()
+            };
+            for (i, c) in row_text[..end].char_indices() {
+                let before = text.len();
+                // Lowercasing can change a character's length
+                if ignore_case {
+                    text.extend(c.to_lowercase());
+                } else {
+                    text.push(c);
+                }
+                cols.extend(std::iter::repeat_n((row, row_cols[i]), text.len() - before));
+            }
+        }
+        for (start, found) in text.match_indices(&query) {
+            let (end_row, last) = cols[start + found.len() - 1];
+            let width = screen
+                .line_at(end_row)
+                .map_or(1, |line| (line.cell(last).width() as usize).max(1));
+            matches.push(SearchMatch {
+                row: cols[start].0,
+                start: cols[start].1,
+                end_row,
+                end: last + width - 1,
+            });
+        }
+        row = bottom + 1;
+    }
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use terminal_core::Dimensions;
+
+    fn screen(lines: &[&str], rows: usize) -> Screen {
+        let mut screen = Screen::new(Dimensions::new(20, rows));
+        for (i, line) in lines.iter().enumerate() {
+            for c in line.chars() {
+                screen.print(c);
+            }
+            if i + 1 < lines.len() {
+                screen.carriage_return();
+                screen.linefeed();
+            }
+        }
+        screen
+    }
+
+    #[test]
+    fn test_find_matches() {
+        let screen = screen(&["error one", "ok", "Error 日本"], 2);
+        let matches = find_matches(&screen, "error");
+        assert_eq!(
+            matches,
+            [
+                SearchMatch {
+                    row: -1,
+                    start: 0,
+                    end_row: -1,
+                    end: 4
+                },
+                SearchMatch {
+                    row: 1,
+                    start: 0,
+                    end_row: 1,
+                    end: 4
+                },
+            ]
+        );
+        // Capitals match case-sensitively
+        assert_eq!(find_matches(&screen, "Error").len(), 1);
+        // Wide characters cover two columns
+        assert_eq!(
+            find_matches(&screen, "日本"),
+            [SearchMatch {
+                row: 1,
+                start: 6,
+                end_row: 1,
+                end: 9
+            }]
+        );
+    }
+
+    #[test]
+    fn test_find_matches_across_wraps() {
+        // 20 columns: "target" wraps after "tar"
+        let screen = screen(&["aaaaaaaaaaaaaaaaatarget", "日"], 3);
+        let matches = find_matches(&screen, "target");
+        assert_eq!(
+            matches,
+            [SearchMatch {
+                row: 0,
+                start: 17,
+                end_row: 1,
+                end: 2
+            }]
+        );
+        let mut search = Search::new();
+        for c in "target".chars() {
+            search.push(c, &screen);
+        }
+        let parts: Vec<_> = search.visible(3, 20, 0).collect();
+        assert_eq!(parts, [(0, 17, 19, true), (1, 0, 2, true)]);
+    }
+
+    #[test]
+    fn test_search_focus_and_status() {
+        let screen = screen(&["a match", "b match", "c match"], 3);
+        let mut search = Search::new();
+        assert_eq!(search.status(), "Find: _");
+        for c in "match".chars() {
+            search.push(c, &screen);
+        }
+        // Focus starts at the newest match and moves up
+        assert_eq!(search.status(), "Find: match_  3/3");
+        search.step(true);
+        assert_eq!(search.focused().map(|m| m.row), Some(1));
+        search.step(false);
+        search.step(false);
+        assert_eq!(search.status(), "Find: match_  1/3");
+
+        let visible: Vec<(usize, bool)> = search
+            .visible(2, 20, 0)
+            .map(|(row, _, _, focused)| (row, focused))
+            .collect();
+        assert_eq!(visible, [(0, true), (1, false)]);
+
+        search.push('x', &screen);
+        assert_eq!(search.status(), "Find: matchx_  no matches");
+    }
+
+    #[test]
+    fn test_search_refresh_follows_output() {
+        let mut screen = screen(&["one match", "two match"], 2);
+        let mut search = Search::new();
+        for c in "match".chars() {
+            search.push(c, &screen);
+        }
+        search.step(true);
+        assert_eq!(search.focused().map(|m| m.row), Some(0));
+
+        let now = Instant::now();
+        screen.carriage_return();
+        screen.linefeed();
+        search.mark_stale(now);
+        assert!(!search.refresh_if_due(&screen, now));
+        assert!(search.refresh_if_due(&screen, now + REFRESH_INTERVAL));
+        // "one match" scrolled into the scrollback and is still focused
+        assert_eq!(search.focused().map(|m| m.row), Some(-1));
+        assert_eq!(search.status(), "Find: match_  1/2");
+    }
+}
@@ -0,0 +1,141 @@
+//! Session save and restore
+//!
+//! A session records the open tabs (custom titles, working directories and
+//! profiles) so they can be recreated at startup. Sessions are
+//! stored as TOML, either in a file given with `--session <file>` or in the
+//! last-session file when `restore_session` is enabled.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+/// Saved state of one tab
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TabSession {
+    /// User-assigned title (None = follow the shell's title)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// Working directory of the shell
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<PathBuf>,
+    /// Profile the tab was opened with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+}
+
+/// Saved layout of a window
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Session {
+    /// Index of the active tab
+    #[serde(default)]
+    pub active_tab: usize,
+    /// Tabs, in tab bar order
+    #[serde(default)]
+    pub tabs: Vec<TabSession>,
+}
+
+impl Session {
+    /// Get the default last-session file path
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::data_local_dir().map(|p| p.join("mochi").join("last-session.toml"))
+    }
+
+    /// Load a session from a file
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let content = fs::read_to_string(path)?;
+        Self::from_toml(&content)
+    }
+
+    /// Parse a session from TOML
+    pub fn from_toml(content: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut session: Session = toml::from_str(content)?;
+        if session.active_tab >= session.tabs.len() {
+            session.active_tab = 0;
+        }
+        Ok(session)
+    }
+
+    /// Save the session to a file, creating parent directories as needed
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, toml::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_roundtrip() {
+        let session = Session {
+            active_tab: 1,
+            tabs: vec![
+                TabSession {
+                    title: Some("build".to_string()),
+                    cwd: Some(PathBuf::from("/tmp/project")),
+                    profile: Some("work".to_string()),
+                },
+                TabSession::default(),
+            ],
+        };
+
+        let toml_str = toml::to_string_pretty(&session).unwrap();
+        assert_eq!(Session::from_toml(&toml_str).unwrap(), session);
+    }
+
+    #[test]
+    fn test_session_parsing() {
+        let toml_str = r#"
+            active_tab = 5
+
+            [[tabs]]
+            cwd = "/home/user"
+            scroll_offset = 12
+
+            [[tabs]]
+            title = "logs"
+        "#;
+
+        let session = Session::from_toml(toml_str).unwrap();
+        assert_eq!(session.tabs.len(), 2);
+        assert_eq!(
+            session.tabs[0].cwd.as_deref(),
+            Some(Path::new("/home/user"))
+        );
+        assert_eq!(session.tabs[1].title.as_deref(), Some("logs"));
+        // Files from older versions may still carry scroll_offset
+        assert_eq!(
+            session.tabs[0],
+            TabSession {
+                cwd: Some(PathBuf::from("/home/user")),
+                ..TabSession::default()
+            }
+        );
+        // Out of range active tab falls back to the first tab
+        assert_eq!(session.active_tab, 0);
+    }
+
+    #[test]
+    fn test_session_save_and_load() {
+        let path = std::env::temp_dir()
+            .join(format!("mochi-session-test-{}", std::process::id()))
+            .join("session.toml");
+        let session = Session {
+            active_tab: 0,
+            tabs: vec![TabSession {
+                title: None,
+                cwd: Some(PathBuf::from("/")),
+                profile: None,
+            }],
+        };
+
+        session.save(&path).unwrap();
+        assert_eq!(Session::load(&path).unwrap(), session);
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+}
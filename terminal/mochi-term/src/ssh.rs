@@ -0,0 +1,87 @@
+//! SSH helpers (shell integration on remote hosts, TERM fallback)
+//!
+//! `mochi --shell-integration` prints a snippet for bash and zsh that
+//! reports prompts, commands and the working directory (OSC 133 and 7).
+//! Sourced locally, it also wraps `ssh` so remote sessions get
+//! `$MOCHI_SSH_TERM` (`[ssh] term`) as their TERM, for hosts without
+//! terminfo for the local one.
+//!
+//! When `ssh` becomes the foreground process of a tab, mochi points out
+//! Ctrl+Shift+U, which types a command on the remote prompt that appends
+//! the snippet to the remote shell's rc file (once). The command is only
+//! typed, not run: the user checks it and presses Enter.
+
+use crate::inline_image::encode_base64;
+
+/// Marks an rc file that already has the snippet
+const MARKER: &str = "mochi-shell-integration";
+
+/// Shell integration for bash and zsh
+pub const SHELL_INTEGRATION: &str = r#"# mochi-shell-integration
+if [ -n "$ZSH_VERSION" ]; then
+  _mochi_precmd() {
+    printf '\e]133;D;%s\a\e]7;file://%s%s\a\e]133;A\a' "$?" "$HOST" "$PWD"
+  }
+  _mochi_preexec() { printf '\e]133;C\a'; }
+  precmd_functions+=(_mochi_precmd)
+  preexec_functions+=(_mochi_preexec)
+  PS1="$PS1%{$(printf '\e]133;B\a')%}"
+elif [ -n "$BASH_VERSION" ]; then
+  _mochi_prompt() {
+    printf '\e]133;D;%s\a\e]7;file://%s%s\a\e]133;A\a' "$?" "$HOSTNAME" "$PWD"
+  }
+  PROMPT_COMMAND="_mochi_prompt${PROMPT_COMMAND:+;$PROMPT_COMMAND}"
+  PS1="$PS1\[$(printf '\e]133;B\a')\]"
+  PS0="$(printf '\e]133;C\a')$PS0"
+fi
+if [ -n "$MOCHI_SSH_TERM" ]; then
+  ssh() { TERM="$MOCHI_SSH_TERM" command ssh "$@"; }
+fi
+"#;
+
+/// Whether a foreground process name is an ssh client
+pub fn is_ssh(name: &str) -> bool {
+    name == "ssh"
+}
+
+/// A one-line command that appends the snippet to the rc file of the
+/// shell it runs in (bash or zsh), unless it is there already
+///
+/// The snippet is sent base64 encoded, so nothing in it is interpreted by
+/// the remote shell while typing. The leading space keeps the command out
+/// of the history where `HISTCONTROL` or `HIST_IGNORE_SPACE` allow.
+pub fn install_command() -> String {
+    format!(
+        " rc=~/.bashrc; [ -n \"$ZSH_VERSION\" ] && rc=~/.zshrc; \
+         grep -qs {} \"$rc\" || echo {} | base64 -d >> \"$rc\"; \
+         echo \"Shell integration is in $rc; open a new shell to use it\"",
+        MARKER,
+        encode_base64(SHELL_INTEGRATION.as_bytes())
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inline_image::decode_base64;
+
+    #[test]
+    fn test_encode_base64() {
+        assert_eq!(encode_base64(b""), "");
+        assert_eq!(encode_base64(b"f"), "Zg==");
+        assert_eq!(encode_base64(b"fo"), "Zm8=");
+        assert_eq!(encode_base64(b"foo"), "Zm9v");
+        let text = SHELL_INTEGRATION.as_bytes();
+        assert_eq!(decode_base64(&encode_base64(text)).as_deref(), Some(text));
+    }
+
+    #[test]
+    fn test_install_command() {
+        let command = install_command();
+        assert!(command.starts_with(' '));
+        assert!(!command.contains(['\n', '\'']));
+        assert!(SHELL_INTEGRATION.starts_with(&format!("# {}", MARKER)));
+        assert!(is_ssh("ssh"));
+        assert!(!is_ssh("sshd"));
+    }
+}
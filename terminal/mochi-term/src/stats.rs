@@ -0,0 +1,198 @@
+//! Counts of the sequences a terminal did not handle
+//!
+//! Every unknown escape sequence, mode and SGR parameter is counted by its
+//! kind (the final byte for CSI and DCS, the command number for OSC), not
+//! its parameters, so the counts show which missing sequences real programs
+//! send most often. The inspector (Ctrl+Shift+D) lists the top ones.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use terminal_parser::CsiAction;
+
+/// Most kinds of sequences counted; rarer ones after that only add to the
+/// total, so hostile output cannot grow the table without bound
+const MAX_KINDS: usize = 256;
+
+/// Kind of an unhandled sequence
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Sequence {
+    /// ESC followed by these bytes
+    Esc(Vec<u8>),
+    /// CSI with this private marker (`?`), intermediates and final byte
+    Csi {
+        private: bool,
+        intermediates: Vec<u8>,
+        final_byte: u8,
+    },
+    /// OSC with this command number
+    Osc(u16),
+    /// DCS with these intermediates and final byte
+    Dcs {
+        intermediates: Vec<u8>,
+        final_byte: u8,
+    },
+    /// DEC private mode (DECSET / DECRST)
+    DecMode(u16),
+    /// APC string starting with this byte (e.g. `G` for kitty graphics)
+    Apc(Option<u8>),
+    /// PM string
+    Pm,
+    /// SOS string
+    Sos,
+    /// Standard mode (SM / RM)
+    Mode(u16),
+    /// SGR parameter
+    Sgr(u16),
+    /// Bytes the parser could not make a sequence of
+    Invalid,
+}
+
+impl Sequence {
+    pub fn csi(csi: &CsiAction) -> Self {
+        Sequence::Csi {
+            private: csi.private,
+            intermediates: csi.intermediates.clone(),
+            final_byte: csi.final_byte,
+        }
+    }
+}
+
+impl fmt::Display for Sequence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Sequence::Esc(data) => write!(f, "ESC {}", String::from_utf8_lossy(data)),
+            Sequence::Csi {
+                private,
+                intermediates,
+                final_byte,
+            } => {
+                write!(f, "CSI ")?;
+                if *private {
+                    write!(f, "? ")?;
+                }
+                write_final(f, intermediates, *final_byte)
+            }
+            Sequence::Osc(command) => write!(f, "OSC {}", command),
+            Sequence::Dcs {
+                intermediates,
+                final_byte,
+            } => {
+                write!(f, "DCS ")?;
+                write_final(f, intermediates, *final_byte)
+            }
+            Sequence::Apc(Some(byte)) if byte.is_ascii_graphic() => {
+                write!(f, "APC {}", *byte as char)
+            }
+            Sequence::Apc(_) => write!(f, "APC"),
+            Sequence::Pm => write!(f, "PM"),
+            Sequence::Sos => write!(f, "SOS"),
+            Sequence::DecMode(mode) => write!(f, "DEC mode {}", mode),
+            Sequence::Mode(mode) => write!(f, "mode {}", mode),
+            Sequence::Sgr(param) => write!(f, "SGR {}", param),
+            Sequence::Invalid => write!(f, "invalid sequence"),
+        }
+    }
+}
+
+/// Intermediates and final byte, spaced like the escape sequence docs
+fn write_final(f: &mut fmt::Formatter<'_>, intermediates: &[u8], final_byte: u8) -> fmt::Result {
+    for &byte in intermediates {
+        write!(f, "{} ", byte as char)?;
+    }
+    write!(f, "{}", final_byte as char)
+}
+
+/// Unhandled sequences received by a terminal, by kind
+#[derive(Debug, Default)]
+pub struct ParserStats {
+    counts: HashMap<Sequence, u64>,
+    total: u64,
+}
+
+impl ParserStats {
+    pub fn record(&mut self, sequence: Sequence) {
+        self.total += 1;
+        if self.counts.len() < MAX_KINDS || self.counts.contains_key(&sequence) {
+            *self.counts.entry(sequence).or_default() += 1;
+        }
+    }
+
+    /// Unhandled sequences received in all
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// How often a kind of sequence was received
+    #[allow(dead_code)]
+    pub fn count(&self, sequence: &Sequence) -> u64 {
+        self.counts.get(sequence).copied().unwrap_or(0)
+    }
+
+    /// The `n` most frequent kinds, most frequent first
+    pub fn top(&self, n: usize) -> Vec<(&Sequence, u64)> {
+        let mut top: Vec<(&Sequence, u64)> = self
+            .counts
+            .iter()
+            .map(|(sequence, &count)| (sequence, count))
+            .collect();
+        // Ties in the order of their names, so the list does not shuffle
+        top.sort_by_cached_key(|&(sequence, count)| {
+            (std::cmp::Reverse(count), sequence.to_string())
+        });
+        top.truncate(n);
+        top
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sequence_display() {
+        let csi = Sequence::Csi {
+            private: true,
+            intermediates: Vec::new(),
+            final_byte: b'x',
+        };
+        assert_eq!(csi.to_string(), "CSI ? x");
+        let dcs = Sequence::Dcs {
+            intermediates: b"$".to_vec(),
+            final_byte: b'q',
+        };
+        assert_eq!(dcs.to_string(), "DCS $ q");
+        assert_eq!(Sequence::Esc(b"%G".to_vec()).to_string(), "ESC %G");
+        assert_eq!(Sequence::DecMode(9999).to_string(), "DEC mode 9999");
+        assert_eq!(Sequence::Apc(Some(b'G')).to_string(), "APC G");
+        assert_eq!(Sequence::Apc(None).to_string(), "APC");
+    }
+
+    #[test]
+    fn test_parser_stats() {
+        let mut stats = ParserStats::default();
+        for _ in 0..3 {
+            stats.record(Sequence::Osc(999));
+        }
+        stats.record(Sequence::Sgr(73));
+        stats.record(Sequence::DecMode(9999));
+        assert_eq!(stats.total(), 5);
+        assert_eq!(stats.count(&Sequence::Osc(999)), 3);
+        assert_eq!(stats.count(&Sequence::Mode(4)), 0);
+        let top: Vec<String> = stats
+            .top(2)
+            .iter()
+            .map(|(sequence, count)| format!("{} {}", sequence, count))
+            .collect();
+        assert_eq!(top, ["OSC 999 3", "DEC mode 9999 1"]);
+
+        // The table stops growing, the total does not
+        for mode in 0..1000 {
+            stats.record(Sequence::Mode(mode));
+        }
+        stats.record(Sequence::Osc(999));
+        assert_eq!(stats.total(), 1006);
+        assert_eq!(stats.count(&Sequence::Osc(999)), 4);
+        assert_eq!(stats.top(usize::MAX).len(), MAX_KINDS);
+    }
+}
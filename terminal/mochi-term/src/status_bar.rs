@@ -0,0 +1,485 @@
+//! Status bar (a line of information below the terminal)
+//!
+//! `[status_bar]` adds a line at the bottom of the window about the active
+//! tab. Its `left` and `right` sides are templates: `{name}` is replaced by
+//! a segment, text in `[...]` is left out when a segment inside it is
+//! empty, and `{{`, `}}`, `[[` and `]]` stand for literal brackets. For
+//! example `"[{git} | ]{clock}"` shows "main | 14:05" in a repository and
+//! just "14:05" elsewhere. `{var:NAME}` is a user variable the tab's shell
+//! set with OSC 1337 ; SetUserVar, e.g. `"[aws:{var:AWS_PROFILE}]"`.
+//! `{scope}`, `{cpu}` and `{memory}` show the systemd scope of a tab started
+//! with `[scope]` and what its processes use, e.g. `"[{cpu} {memory} ]"`;
+//! they are empty for other tabs.
+//!
+//! The git branch comes from `git_command`, run with `/bin/sh -c` in the
+//! tab's directory on a background thread, so a slow repository never
+//! holds up a frame. The first line it prints is shown.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::config::{ConfigError, StatusBarConfig};
+use crate::memory::format_bytes;
+
+/// How often the segments are recomputed (the bar is only redrawn when
+/// its text changes)
+pub const STATUS_REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+/// How long a directory's git branch is kept before the command runs again
+const GIT_REFRESH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// What a `{name}` placeholder shows
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Segment {
+    /// Title of the active tab
+    Title,
+    /// Working directory of the active tab, with the home directory as `~`
+    Cwd,
+    /// Output of `git_command` in that directory
+    Git,
+    /// Local time as HH:MM
+    Clock,
+    /// Lines scrolled back and the scrollback size, while scrolled back
+    Scroll,
+    /// A user variable of the active tab (`{var:NAME}`)
+    UserVar(String),
+    /// Unit name of the active tab's systemd scope
+    Scope,
+    /// CPU used by the scope since the last refresh, as a percentage of a core
+    Cpu,
+    /// Memory used by the scope
+    Memory,
+}
+
+impl Segment {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "title" => Self::Title,
+            "cwd" => Self::Cwd,
+            "git" => Self::Git,
+            "clock" => Self::Clock,
+            "scroll" => Self::Scroll,
+            "scope" => Self::Scope,
+            "cpu" => Self::Cpu,
+            "memory" => Self::Memory,
+            _ => match name.strip_prefix("var:") {
+                Some(var) if !var.is_empty() => Self::UserVar(var.to_string()),
+                _ => return None,
+            },
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Text(String),
+    Segment(Segment),
+    /// Parts left out when one of their segments is empty
+    Optional(Vec<Part>),
+}
+
+/// A parsed side of the status bar
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+impl Template {
+    /// Parse a template; the error describes the first problem
+    pub fn parse(template: &str) -> Result<Self, String> {
+        let mut parts = Vec::new();
+        let mut optional: Option<Vec<Part>> = None;
+        let mut text = String::new();
+        let mut chars = template.chars().peekable();
+        while let Some(c) = chars.next() {
+            let doubled = chars.peek() == Some(&c);
+            match c {
+                '{' | '}' | '[' | ']' if doubled => {
+                    chars.next();
+                    text.push(c);
+                }
+                '{' => {
+                    let name: String = chars.by_ref().take_while(|&c| c != '}').collect();
+                    let segment = Segment::from_name(&name)
+                        .ok_or_else(|| format!("Unknown status bar segment '{{{}}}'", name))?;
+                    let current = optional.as_mut().unwrap_or(&mut parts);
+                    if !text.is_empty() {
+                        current.push(Part::Text(std::mem::take(&mut text)));
+                    }
+                    current.push(Part::Segment(segment));
+                }
+                '[' if optional.is_none() => {
+                    if !text.is_empty() {
+                        parts.push(Part::Text(std::mem::take(&mut text)));
+                    }
+                    optional = Some(Vec::new());
+                }
+                ']' if optional.is_some() => {
+                    let mut group = optional.take().unwrap();
+                    if !text.is_empty() {
+                        group.push(Part::Text(std::mem::take(&mut text)));
+                    }
+                    parts.push(Part::Optional(group));
+                }
+                '}' | '[' | ']' => return Err(format!("Unmatched '{}' in status bar", c)),
+                c => text.push(c),
+            }
+        }
+        if optional.is_some() {
+            return Err("Unclosed '[' in status bar".to_string());
+        }
+        if !text.is_empty() {
+            parts.push(Part::Text(text));
+        }
+        Ok(Self { parts })
+    }
+
+    /// Whether the template shows `segment`
+    pub fn uses(&self, segment: &Segment) -> bool {
+        fn uses(parts: &[Part], segment: &Segment) -> bool {
+            parts.iter().any(|part| match part {
+                Part::Segment(s) => s == segment,
+                Part::Optional(group) => uses(group, segment),
+                Part::Text(_) => false,
+            })
+        }
+        uses(&self.parts, segment)
+    }
+
+    /// Fill in the segments
+    pub fn render(&self, values: &Values) -> String {
+        fn render(parts: &[Part], values: &Values, out: &mut String) {
+            for part in parts {
+                match part {
+                    Part::Text(text) => out.push_str(text),
+                    Part::Segment(segment) => out.push_str(&values.get(segment)),
+                    Part::Optional(group) => {
+                        let complete = group.iter().all(|part| match part {
+                            Part::Segment(segment) => !values.get(segment).is_empty(),
+                            _ => true,
+                        });
+                        if complete {
+                            render(group, values, out);
+                        }
+                    }
+                }
+            }
+        }
+        let mut out = String::new();
+        render(&self.parts, values, &mut out);
+        out
+    }
+}
+
+/// What the segments show
+#[derive(Debug, Clone, Default)]
+pub struct Values<'a> {
+    pub title: &'a str,
+    pub cwd: Option<&'a Path>,
+    pub git: Option<&'a str>,
+    pub clock: &'a str,
+    /// Lines scrolled back and the scrollback size
+    pub scroll: (usize, usize),
+    pub user_vars: Option<&'a BTreeMap<String, String>>,
+    pub scope: Option<&'a str>,
+    /// Percentage of a core
+    pub cpu: Option<f64>,
+    /// Bytes
+    pub memory: Option<u64>,
+}
+
+impl Values<'_> {
+    fn get(&self, segment: &Segment) -> String {
+        match segment {
+            Segment::Title => self.title.to_string(),
+            Segment::Cwd => self.cwd.map(display_path).unwrap_or_default(),
+            Segment::Git => self.git.unwrap_or_default().to_string(),
+            Segment::Clock => self.clock.to_string(),
+            Segment::Scroll => match self.scroll {
+                (0, _) => String::new(),
+                (offset, total) => format!("{}/{}", offset, total),
+            },
+            Segment::UserVar(name) => self
+                .user_vars
+                .and_then(|vars| vars.get(name))
+                .cloned()
+                .unwrap_or_default(),
+            Segment::Scope => self.scope.unwrap_or_default().to_string(),
+            Segment::Cpu => self
+                .cpu
+                .map(|cpu| format!("{:.0}%", cpu))
+                .unwrap_or_default(),
+            Segment::Memory => self.memory.map(format_bytes).unwrap_or_default(),
+        }
+    }
+}
+
+/// A path with the home directory shortened to `~`
+fn display_path(path: &Path) -> String {
+    match dirs::home_dir().and_then(|home| path.strip_prefix(home).ok().map(Path::to_path_buf)) {
+        Some(rest) if rest.as_os_str().is_empty() => "~".to_string(),
+        Some(rest) => format!("~/{}", rest.display()),
+        None => path.display().to_string(),
+    }
+}
+
+/// Local time as HH:MM
+pub fn clock(now: SystemTime) -> String {
+    let secs = now
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() as libc::time_t);
+    // SAFETY: localtime_r only writes to the tm it is given
+    let tm = unsafe {
+        let mut tm: libc::tm = std::mem::zeroed();
+        libc::localtime_r(&secs, &mut tm);
+        tm
+    };
+    format!("{:02}:{:02}", tm.tm_hour, tm.tm_min)
+}
+
+#[derive(Debug, Clone)]
+struct Branch {
+    name: Option<String>,
+    checked: Instant,
+    running: bool,
+}
+
+/// The status bar of a window
+pub struct StatusBar {
+    left: Template,
+    right: Template,
+    git_command: String,
+    /// Git branch by directory, filled in by the command threads
+    branches: Arc<Mutex<HashMap<PathBuf, Branch>>>,
+    text: (String, String),
+    refreshed: Option<Instant>,
+}
+
+impl StatusBar {
+    /// The status bar for the config, or None if it is disabled
+    pub fn new(config: &StatusBarConfig) -> Result<Option<Self>, ConfigError> {
+        if !config.enabled {
+            return Ok(None);
+        }
+        let parse = |side: &str, template: &str| {
+            Template::parse(template).map_err(|message| ConfigError {
+                message,
+                field: Some(format!("status_bar.{}", side)),
+            })
+        };
+        Ok(Some(Self {
+            left: parse("left", &config.left)?,
+            right: parse("right", &config.right)?,
+            git_command: config.git_command.clone(),
+            branches: Arc::default(),
+            text: Default::default(),
+            refreshed: None,
+        }))
+    }
+
+    /// Left and right text
+    pub fn text(&self) -> (&str, &str) {
+        (&self.text.0, &self.text.1)
+    }
+
+    /// Whether the segments are due to be recomputed
+    pub fn refresh_due(&self, now: Instant) -> bool {
+        self.refreshed
+            .is_none_or(|refreshed| now >= refreshed + STATUS_REFRESH_INTERVAL)
+    }
+
+    /// When the segments should next be recomputed
+    pub fn next_refresh(&self) -> Option<Instant> {
+        self.refreshed
+            .map(|refreshed| refreshed + STATUS_REFRESH_INTERVAL)
+    }
+
+    /// Whether the template shows the working directory or git branch
+    /// (which have to be looked up)
+    pub fn needs_cwd(&self) -> bool {
+        [Segment::Cwd, Segment::Git]
+            .iter()
+            .any(|segment| self.left.uses(segment) || self.right.uses(segment))
+    }
+
+    /// Whether the template shows the usage of a tab's scope (which has to
+    /// be read from its cgroup)
+    pub fn needs_usage(&self) -> bool {
+        [Segment::Cpu, Segment::Memory]
+            .iter()
+            .any(|segment| self.left.uses(segment) || self.right.uses(segment))
+    }
+
+    /// Recompute the text; returns whether it changed
+    ///
+    /// A git branch that is still being looked up shows up in a later
+    /// refresh.
+    pub fn refresh(&mut self, values: Values, now: Instant) -> bool {
+        self.refreshed = Some(now);
+        let uses_git = self.left.uses(&Segment::Git) || self.right.uses(&Segment::Git);
+        let branch = match values.cwd {
+            Some(cwd) if uses_git => self.branch(cwd, now),
+            _ => None,
+        };
+        let clock = clock(SystemTime::now());
+        let values = Values {
+            git: branch.as_deref(),
+            clock: &clock,
+            ..values
+        };
+        let text = (self.left.render(&values), self.right.render(&values));
+        let changed = text != self.text;
+        self.text = text;
+        changed
+    }
+
+    /// The git branch of `dir`, starting a lookup if it is unknown or old
+    fn branch(&self, dir: &Path, now: Instant) -> Option<String> {
+        let mut branches = self.branches.lock().unwrap();
+        let entry = branches.entry(dir.to_path_buf()).or_insert(Branch {
+            name: None,
+            checked: now,
+            running: false,
+        });
+        let stale = entry.name.is_none() || now >= entry.checked + GIT_REFRESH_INTERVAL;
+        if stale && !entry.running {
+            entry.running = true;
+            let branches = Arc::clone(&self.branches);
+            let command = self.git_command.clone();
+            let dir = dir.to_path_buf();
+            let spawned = thread::Builder::new()
+                .name("status-git".to_string())
+                .spawn(move || {
+                    let name = run_command(&command, &dir);
+                    if let Some(entry) = branches.lock().unwrap().get_mut(&dir) {
+                        *entry = Branch {
+                            name,
+                            checked: Instant::now(),
+                            running: false,
+                        };
+                    }
+                });
+            if let Err(e) = spawned {
+                log::warn!("Failed to start status bar command: {}", e);
+                entry.running = false;
+            }
+        }
+        entry.name.clone()
+    }
+}
+
+/// First line `command` prints when run in `dir`, if it succeeds
+fn run_command(command: &str, dir: &Path) -> Option<String> {
+    let output = Command::new("/bin/sh")
+        .arg("-c")
+        .arg(command)
+        .current_dir(dir)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let line = stdout.lines().next()?.trim();
+    (output.status.success() && !line.is_empty()).then(|| line.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values() -> Values<'static> {
+        Values {
+            title: "vim",
+            cwd: Some(Path::new("/srv/app")),
+            git: Some("main"),
+            clock: "14:05",
+            scroll: (0, 100),
+            user_vars: None,
+            scope: None,
+            cpu: None,
+            memory: None,
+        }
+    }
+
+    #[test]
+    fn test_template_segments() {
+        let template = Template::parse("{title} in {cwd} ({git}) {clock}").unwrap();
+        assert_eq!(template.render(&values()), "vim in /srv/app (main) 14:05");
+        assert!(template.uses(&Segment::Git));
+        assert!(!template.uses(&Segment::Scroll));
+
+        let template = Template::parse("{scroll}").unwrap();
+        assert_eq!(template.render(&values()), "");
+        let scrolled = Values {
+            scroll: (12, 100),
+            ..values()
+        };
+        assert_eq!(template.render(&scrolled), "12/100");
+    }
+
+    #[test]
+    fn test_template_optional_parts() {
+        let template = Template::parse("[{git} | ]{clock}").unwrap();
+        assert_eq!(template.render(&values()), "main | 14:05");
+        let no_git = Values {
+            git: None,
+            ..values()
+        };
+        assert_eq!(template.render(&no_git), "14:05");
+
+        let template = Template::parse("{{[[{clock}]]}}").unwrap();
+        assert_eq!(template.render(&values()), "{[14:05]}");
+    }
+
+    #[test]
+    fn test_template_user_vars() {
+        let template = Template::parse("[aws:{var:AWS_PROFILE} ]{clock}").unwrap();
+        assert_eq!(template.render(&values()), "14:05");
+        let vars = BTreeMap::from([("AWS_PROFILE".to_string(), "prod".to_string())]);
+        let with_vars = Values {
+            user_vars: Some(&vars),
+            ..values()
+        };
+        assert_eq!(template.render(&with_vars), "aws:prod 14:05");
+    }
+
+    #[test]
+    fn test_template_scope_usage() {
+        let template = Template::parse("[{scope}: {cpu} {memory} ]{clock}").unwrap();
+        assert_eq!(template.render(&values()), "14:05");
+        let scoped = Values {
+            scope: Some("mochi-1-1"),
+            cpu: Some(42.4),
+            memory: Some(3 << 29),
+            ..values()
+        };
+        assert_eq!(template.render(&scoped), "mochi-1-1: 42% 1.5G 14:05");
+    }
+
+    #[test]
+    fn test_template_errors() {
+        for template in ["{branch}", "{var:}", "[{git}", "{git}]", "[[{git}]", "a}b"] {
+            assert!(Template::parse(template).is_err(), "{}", template);
+        }
+    }
+
+    #[test]
+    fn test_clock_format() {
+        let text = clock(SystemTime::now());
+        assert_eq!(text.len(), 5);
+        assert_eq!(&text[2..3], ":");
+    }
+
+    #[test]
+    fn test_git_command() {
+        let dir = std::env::temp_dir();
+        assert_eq!(
+            run_command("echo main; echo other", &dir).as_deref(),
+            Some("main")
+        );
+        assert_eq!(run_command("echo main; exit 1", &dir), None);
+    }
+}
@@ -0,0 +1,142 @@
+//! Terminfo capabilities for XTGETTCAP
+//!
+//! Programs such as tmux and Neovim ask for capabilities with
+//! `DCS + q <hex names> ST` instead of trusting the installed terminfo entry.
+//! The table describes the entry for the TERM that is advertised to the
+//! shell, plus the common extensions (Tc, RGB, Ss, Ms, Sync) that mochi
+//! supports.
+
+/// Capability names and values; `None` marks a boolean capability
+const CAPABILITIES: &[(&str, Option<&str>)] = &[
+    ("Co", Some("256")),
+    ("colors", Some("256")),
+    ("RGB", Some("8")),
+    ("Tc", None),
+    ("setrgbf", Some("\x1b[38;2;%p1%d;%p2%d;%p3%dm")),
+    ("setrgbb", Some("\x1b[48;2;%p1%d;%p2%d;%p3%dm")),
+    ("Ss", Some("\x1b[%p1%d q")),
+    ("Se", Some("\x1b[2 q")),
+    ("Ms", Some("\x1b]52;%p1%s;%p2%s\x07")),
+    ("Sync", Some("\x1b[?2026%?%p1%{1}%-%tl%eh%;")),
+    ("smcup", Some("\x1b[?1049h")),
+    ("rmcup", Some("\x1b[?1049l")),
+    ("civis", Some("\x1b[?25l")),
+    ("cnorm", Some("\x1b[?12l\x1b[?25h")),
+    ("bel", Some("\x07")),
+    ("kcuu1", Some("\x1bOA")),
+    ("kcud1", Some("\x1bOB")),
+    ("kcuf1", Some("\x1bOC")),
+    ("kcub1", Some("\x1bOD")),
+    ("khome", Some("\x1bOH")),
+    ("kend", Some("\x1bOF")),
+    ("kbs", Some("\x7f")),
+    ("kich1", Some("\x1b[2~")),
+    ("kdch1", Some("\x1b[3~")),
+    ("kpp", Some("\x1b[5~")),
+    ("knp", Some("\x1b[6~")),
+    ("kf1", Some("\x1bOP")),
+    ("kf2", Some("\x1bOQ")),
+    ("kf3", Some("\x1bOR")),
+    ("kf4", Some("\x1bOS")),
+    ("kf5", Some("\x1b[15~")),
+    ("kf6", Some("\x1b[17~")),
+    ("kf7", Some("\x1b[18~")),
+    ("kf8", Some("\x1b[19~")),
+    ("kf9", Some("\x1b[20~")),
+    ("kf10", Some("\x1b[21~")),
+    ("kf11", Some("\x1b[23~")),
+    ("kf12", Some("\x1b[24~")),
+];
+
+/// Look up a capability: `Some(None)` for a boolean, `Some(Some(value))`
+/// for a string or number, `None` if unknown
+pub fn lookup(name: &str) -> Option<Option<&'static str>> {
+    CAPABILITIES
+        .iter()
+        .find(|(cap, _)| *cap == name)
+        .map(|&(_, value)| value)
+}
+
+/// Responses to an XTGETTCAP request, one per requested capability
+///
+/// `data` is the request payload: hex-encoded names separated by `;`. As in
+/// xterm, each known capability is answered with `DCS 1 + r name=value ST`
+/// (without `=value` for booleans); an unknown or malformed name gets
+/// `DCS 0 + r name ST` and the rest of the request is still answered.
+/// `TN` (and `name`) is `term`, the TERM advertised to the shell.
+pub fn responses(data: &[u8], term: &str) -> Vec<Vec<u8>> {
+    data.split(|&b| b == b';')
+        .filter(|hex_name| !hex_name.is_empty())
+        .map(|hex_name| {
+            let hex_name = String::from_utf8_lossy(hex_name);
+            let value = decode_hex(&hex_name)
+                .and_then(|name| String::from_utf8(name).ok())
+                .and_then(|name| match name.as_str() {
+                    "TN" | "name" => Some(Some(term)),
+                    name => lookup(name),
+                });
+            match value {
+                Some(Some(value)) => {
+                    format!(
+                        "\x1bP1+r{}={}\x1b\\",
+                        hex_name,
+                        encode_hex(value.as_bytes())
+                    )
+                }
+                Some(None) => format!("\x1bP1+r{}\x1b\\", hex_name),
+                None => format!("\x1bP0+r{}\x1b\\", hex_name),
+            }
+            .into_bytes()
+        })
+        .collect()
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02X}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hex_round_trip() {
+        assert_eq!(encode_hex(b"Co"), "436F");
+        assert_eq!(decode_hex("436f").as_deref(), Some(&b"Co"[..]));
+        assert_eq!(decode_hex("436"), None);
+        assert_eq!(decode_hex("zz"), None);
+    }
+
+    #[test]
+    fn test_responses() {
+        // "Co", "Tc", "xx"
+        let replies = responses(b"436F;5463;7878", "xterm-256color");
+        assert_eq!(
+            replies,
+            [
+                b"\x1bP1+r436F=323536\x1b\\".to_vec(),
+                b"\x1bP1+r5463\x1b\\".to_vec(),
+                b"\x1bP0+r7878\x1b\\".to_vec(),
+            ]
+        );
+        assert_eq!(
+            responses(b"nothex", "xterm-256color"),
+            [b"\x1bP0+rnothex\x1b\\".to_vec()]
+        );
+        // "TN" is the advertised TERM
+        assert_eq!(
+            responses(b"544E", "mochi"),
+            [b"\x1bP1+r544E=6D6F636869\x1b\\".to_vec()]
+        );
+    }
+}
@@ -2,8 +2,520 @@
 //!
 //! Integrates the parser and screen model to handle terminal emulation.
 
-use terminal_core::{Color, CursorStyle, Dimensions, Screen, Snapshot};
-use terminal_parser::{Action, CsiAction, EscAction, OscAction, Parser};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use terminal_core::{
+    Color, CursorStyle, Dimensions, ModeState, Screen, SemanticZone, Snapshot, ZoneKind,
+};
+use terminal_parser::{Action, CsiAction, EscAction, OscAction, Parser, ParserState};
+
+use crate::config::{ColorScheme, OscPolicy};
+use crate::highlight::{self, HighlightRules};
+use crate::inline_image::{self, InlineImageArgs};
+use crate::inspector::{self, ActionLog};
+use crate::memory::MemoryUsage;
+use crate::stats::{ParserStats, Sequence};
+use crate::termcap;
+use crate::trace::TraceEvent;
+
+/// Longest notification title or body kept, in characters
+const MAX_NOTIFICATION_LEN: usize = 256;
+
+/// Most notifications queued between two reads by the application
+const MAX_PENDING_NOTIFICATIONS: usize = 8;
+
+/// Longest pointer shape name list kept (OSC 22)
+const MAX_POINTER_SHAPE_LEN: usize = 128;
+
+/// Most completed output lines kept between two reads by the application
+const MAX_CAPTURED_LINES: usize = 256;
+
+/// Most user variables a program can set (OSC 1337 ; SetUserVar)
+const MAX_USER_VARS: usize = 32;
+
+/// Longest user variable value kept, in characters
+const MAX_USER_VAR_LEN: usize = 256;
+
+/// Most titles saved with CSI 22 t, as in xterm
+const MAX_TITLE_STACK: usize = 10;
+
+/// Largest OSC 52 payload accepted by default, in bytes of base64
+const MAX_CLIPBOARD_LEN: usize = 100_000;
+
+/// Primary device attributes (DA1) reported by default: VT100 with AVO
+pub const DEFAULT_DEVICE_ATTRIBUTES: &str = "1;2";
+
+/// Name and version reported by XTVERSION when mimicking xterm
+const XTERM_VERSION: &str = "XTerm(388)";
+
+/// Caps on what programs can make the terminal keep
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TerminalLimits {
+    /// Longest notification title or body kept, in characters
+    pub notification_len: usize,
+    /// Most notifications queued between two reads by the application
+    pub pending_notifications: usize,
+    /// Longest pointer shape name list kept (OSC 22)
+    pub pointer_shape_len: usize,
+    /// Most completed output lines kept between two reads by the application
+    pub captured_lines: usize,
+    /// Largest OSC 52 payload accepted, in bytes of base64
+    pub clipboard_len: usize,
+    /// Most user variables kept (OSC 1337 ; SetUserVar)
+    pub user_vars: usize,
+    /// Longest user variable value kept, in characters
+    pub user_var_len: usize,
+    /// Most titles saved with CSI 22 t; the oldest is dropped past it
+    pub title_stack: usize,
+}
+
+impl Default for TerminalLimits {
+    fn default() -> Self {
+        Self {
+            notification_len: MAX_NOTIFICATION_LEN,
+            pending_notifications: MAX_PENDING_NOTIFICATIONS,
+            pointer_shape_len: MAX_POINTER_SHAPE_LEN,
+            captured_lines: MAX_CAPTURED_LINES,
+            clipboard_len: MAX_CLIPBOARD_LEN,
+            user_vars: MAX_USER_VARS,
+            user_var_len: MAX_USER_VAR_LEN,
+            title_stack: MAX_TITLE_STACK,
+        }
+    }
+}
+
+/// Text a program asked to put on the clipboard (OSC 52)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClipboardWrite {
+    /// Whether the PRIMARY selection was targeted rather than the clipboard
+    pub primary: bool,
+    pub text: String,
+}
+
+/// Title saved with CSI 22 t; only the parts named are restored
+#[derive(Debug, Clone, Default)]
+struct SavedTitle {
+    icon_name: Option<String>,
+    title: Option<String>,
+}
+
+/// A program's request to read the clipboard (`OSC 52 ; c ; ?`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClipboardRead {
+    /// Whether the PRIMARY selection was asked for rather than the clipboard
+    pub primary: bool,
+    /// Selection parameter of the request, repeated in the reply
+    selection: String,
+}
+
+impl ClipboardRead {
+    /// The reply that hands `text` to the program
+    pub fn reply(&self, text: &str) -> Vec<u8> {
+        format!(
+            "\x1b]52;{};{}\x1b\\",
+            self.selection,
+            inline_image::encode_base64(text.as_bytes())
+        )
+        .into_bytes()
+    }
+}
+
+/// OSC features that `[security.osc]` allows, denies or asks about
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum OscFeature {
+    Title,
+    Hyperlinks,
+    Notifications,
+    Colors,
+    ClipboardWrite,
+    ClipboardRead,
+    Images,
+}
+
+impl OscFeature {
+    /// Feature an OSC action uses, if it is under a policy
+    ///
+    /// Resets of colors and ends of hyperlinks are always allowed, as they
+    /// only undo what the feature did.
+    fn of(osc: &OscAction) -> Option<Self> {
+        match osc {
+            OscAction::SetIconAndTitle(_) | OscAction::SetTitle(_) | OscAction::SetIconName(_) => {
+                Some(OscFeature::Title)
+            }
+            OscAction::Hyperlink { uri, .. } if !uri.is_empty() => Some(OscFeature::Hyperlinks),
+            OscAction::Notify { .. } => Some(OscFeature::Notifications),
+            OscAction::SetColor { .. }
+            | OscAction::SetForegroundColor(_)
+            | OscAction::SetBackgroundColor(_)
+            | OscAction::SetCursorColor(_) => Some(OscFeature::Colors),
+            OscAction::Clipboard { data, .. } if data == "?" => Some(OscFeature::ClipboardRead),
+            OscAction::Clipboard { .. } => Some(OscFeature::ClipboardWrite),
+            OscAction::InlineFile { .. } => Some(OscFeature::Images),
+            _ => None,
+        }
+    }
+
+    /// What the feature lets a program do, for the question asked
+    pub fn description(self) -> &'static str {
+        match self {
+            OscFeature::Title => "set the title",
+            OscFeature::Hyperlinks => "add hyperlinks",
+            OscFeature::Notifications => "send notifications",
+            OscFeature::Colors => "change colors",
+            OscFeature::ClipboardWrite => "write to the clipboard",
+            OscFeature::ClipboardRead => "read the clipboard",
+            OscFeature::Images => "show images",
+        }
+    }
+}
+
+/// Policy for each OSC feature, checked in one place as OSC sequences are
+/// handled
+///
+/// The default allows everything except the clipboard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OscPolicies {
+    pub title: OscPolicy,
+    pub hyperlinks: OscPolicy,
+    pub notifications: OscPolicy,
+    pub colors: OscPolicy,
+    pub clipboard_write: OscPolicy,
+    pub clipboard_read: OscPolicy,
+    pub images: OscPolicy,
+}
+
+impl Default for OscPolicies {
+    fn default() -> Self {
+        Self {
+            title: OscPolicy::Allow,
+            hyperlinks: OscPolicy::Allow,
+            notifications: OscPolicy::Allow,
+            colors: OscPolicy::Allow,
+            clipboard_write: OscPolicy::Deny,
+            clipboard_read: OscPolicy::Deny,
+            images: OscPolicy::Allow,
+        }
+    }
+}
+
+impl OscPolicies {
+    pub fn get(&self, feature: OscFeature) -> OscPolicy {
+        match feature {
+            OscFeature::Title => self.title,
+            OscFeature::Hyperlinks => self.hyperlinks,
+            OscFeature::Notifications => self.notifications,
+            OscFeature::Colors => self.colors,
+            OscFeature::ClipboardWrite => self.clipboard_write,
+            OscFeature::ClipboardRead => self.clipboard_read,
+            OscFeature::Images => self.images,
+        }
+    }
+}
+
+/// A notification sent by a program (OSC 9 / OSC 777)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Notification {
+    /// Title (empty for OSC 9)
+    pub title: String,
+    pub body: String,
+}
+
+impl Notification {
+    /// One-line text for display
+    pub fn text(&self) -> String {
+        match (self.title.is_empty(), self.body.is_empty()) {
+            (true, _) => self.body.clone(),
+            (false, true) => self.title.clone(),
+            (false, false) => format!("{}: {}", self.title, self.body),
+        }
+    }
+}
+
+/// Progress a program reported with OSC 9 ; 4
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Progress {
+    /// Percent done
+    Normal(u8),
+    /// Failed, at this percent
+    Error(u8),
+    /// Busy for an unknown time
+    Indeterminate,
+    /// Paused, at this percent
+    Paused(u8),
+}
+
+impl Progress {
+    /// Percent done, if known
+    pub fn percent(self) -> Option<u8> {
+        match self {
+            Progress::Normal(percent) | Progress::Error(percent) | Progress::Paused(percent) => {
+                Some(percent)
+            }
+            Progress::Indeterminate => None,
+        }
+    }
+}
+
+/// Colors a program changed (OSC 4, 10, 11 and 12), drawn over the theme
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ColorOverrides {
+    pub foreground: Option<(u8, u8, u8)>,
+    pub background: Option<(u8, u8, u8)>,
+    pub cursor: Option<(u8, u8, u8)>,
+    /// ANSI colors 0-15 by index
+    pub palette: BTreeMap<u8, (u8, u8, u8)>,
+}
+
+impl ColorOverrides {
+    /// Put the changed colors into a theme's color scheme
+    pub fn apply(&self, colors: &mut ColorScheme) {
+        let hex = |(r, g, b): (u8, u8, u8)| format!("#{:02x}{:02x}{:02x}", r, g, b);
+        if let Some(color) = self.foreground {
+            colors.foreground = hex(color);
+        }
+        if let Some(color) = self.background {
+            colors.background = hex(color);
+        }
+        if let Some(color) = self.cursor {
+            colors.cursor = hex(color);
+        }
+        for (&index, &color) in &self.palette {
+            colors.ansi[index as usize] = hex(color);
+        }
+    }
+}
+
+/// Parse an X11 color specification: `#rrggbb` or `rgb:r/g/b` with one to
+/// four hex digits per component
+fn parse_color_spec(spec: &str) -> Option<(u8, u8, u8)> {
+    if spec.starts_with('#') {
+        return ColorScheme::parse_hex(spec);
+    }
+    let mut components = spec.strip_prefix("rgb:")?.split('/').map(|component| {
+        let value = u32::from_str_radix(component, 16).ok()?;
+        let max = match component.len() {
+            1..=4 => (1u32 << (4 * component.len())) - 1,
+            _ => return None,
+        };
+        Some((value * 255 / max) as u8)
+    });
+    let color = (
+        components.next()??,
+        components.next()??,
+        components.next()??,
+    );
+    components.next().is_none().then_some(color)
+}
+
+/// Graphics that are consumed but not drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnsupportedGraphics {
+    /// DCS p (ReGIS)
+    Regis,
+    /// DECSET 38 (Tektronix 4014 mode)
+    Tektronix,
+}
+
+impl UnsupportedGraphics {
+    pub fn name(self) -> &'static str {
+        match self {
+            UnsupportedGraphics::Regis => "ReGIS",
+            UnsupportedGraphics::Tektronix => "Tektronix",
+        }
+    }
+}
+
+/// Behavior chosen when the terminal is built
+#[derive(Debug, Clone)]
+struct Options {
+    answer_queries: bool,
+    device_attributes: String,
+    term_name: String,
+    answerback: String,
+    identify_as_xterm: bool,
+    osc_policies: OscPolicies,
+    limits: TerminalLimits,
+}
+
+/// Builder for a `Terminal`
+///
+/// The defaults match `Terminal::new`: queries are answered, the TERM of
+/// `terminal_pty` is advertised, inline images are shown and OSC 52 writes
+/// are refused.
+#[derive(Clone)]
+pub struct TerminalBuilder {
+    cols: usize,
+    rows: usize,
+    scrollback_lines: Option<usize>,
+    cell_pixel_size: (u32, u32),
+    default_alternate_scroll: bool,
+    capture_alternate_screen: bool,
+    capture_lines: bool,
+    highlight_rules: HighlightRules,
+    tmux_passthrough: bool,
+    options: Options,
+}
+
+impl TerminalBuilder {
+    pub fn new(cols: usize, rows: usize) -> Self {
+        Self {
+            cols: cols.max(1),
+            rows: rows.max(1),
+            scrollback_lines: None,
+            cell_pixel_size: (8, 16),
+            default_alternate_scroll: false,
+            capture_alternate_screen: false,
+            capture_lines: false,
+            highlight_rules: HighlightRules::default(),
+            tmux_passthrough: true,
+            options: Options {
+                answer_queries: true,
+                device_attributes: DEFAULT_DEVICE_ATTRIBUTES.to_string(),
+                term_name: terminal_pty::DEFAULT_TERM.to_string(),
+                answerback: String::new(),
+                identify_as_xterm: false,
+                osc_policies: OscPolicies::default(),
+                limits: TerminalLimits::default(),
+            },
+        }
+    }
+
+    /// Lines of scrollback kept
+    pub fn scrollback_lines(&mut self, lines: usize) -> &mut Self {
+        self.scrollback_lines = Some(lines);
+        self
+    }
+
+    /// Cell size in pixels, used to size inline images
+    pub fn cell_pixel_size(&mut self, width: u32, height: u32) -> &mut Self {
+        self.cell_pixel_size = (width, height);
+        self
+    }
+
+    /// Initial state of alternate scroll mode (1007), restored by a full
+    /// reset (RIS)
+    pub fn alternate_scroll(&mut self, enabled: bool) -> &mut Self {
+        self.default_alternate_scroll = enabled;
+        self
+    }
+
+    /// Keep the alternate screen's final contents in the scrollback when an
+    /// application leaves it
+    pub fn capture_alternate_screen(&mut self, enabled: bool) -> &mut Self {
+        self.capture_alternate_screen = enabled;
+        self
+    }
+
+    /// Keep completed output lines for `take_captured_lines`
+    pub fn capture_lines(&mut self, enabled: bool) -> &mut Self {
+        self.capture_lines = enabled;
+        self
+    }
+
+    pub fn highlight_rules(&mut self, rules: HighlightRules) -> &mut Self {
+        self.highlight_rules = rules;
+        self
+    }
+
+    /// Answer status and capability queries (DA, DSR, XTGETTCAP); without
+    /// answers the terminal only listens, as when replaying a recording
+    pub fn answer_queries(&mut self, enabled: bool) -> &mut Self {
+        self.options.answer_queries = enabled;
+        self
+    }
+
+    /// Parameters of the primary device attributes reply (`CSI ? <attrs> c`)
+    pub fn device_attributes(&mut self, attributes: &str) -> &mut Self {
+        self.options.device_attributes = attributes.to_string();
+        self
+    }
+
+    /// Terminal name reported by XTGETTCAP (`TN`); should match the TERM
+    /// given to the shell
+    pub fn term_name(&mut self, name: &str) -> &mut Self {
+        self.options.term_name = name.to_string();
+        self
+    }
+
+    /// Reply to ENQ (0x05); empty sends nothing
+    pub fn answerback(&mut self, answerback: &str) -> &mut Self {
+        self.options.answerback = answerback.to_string();
+        self
+    }
+
+    /// Answer XTVERSION with xterm's name and version instead of mochi's
+    pub fn identify_as_xterm(&mut self, enabled: bool) -> &mut Self {
+        self.options.identify_as_xterm = enabled;
+        self
+    }
+
+    /// Allow, deny or ask about titles, hyperlinks, notifications, colors,
+    /// the clipboard and inline images
+    pub fn osc_policies(&mut self, policies: OscPolicies) -> &mut Self {
+        self.options.osc_policies = policies;
+        self
+    }
+
+    /// Handle sequences that programs inside tmux wrap in `DCS tmux; ST`
+    pub fn tmux_passthrough(&mut self, enabled: bool) -> &mut Self {
+        self.tmux_passthrough = enabled;
+        self
+    }
+
+    pub fn limits(&mut self, limits: TerminalLimits) -> &mut Self {
+        self.options.limits = limits;
+        self
+    }
+
+    pub fn build(&self) -> Terminal {
+        let mut screen = Screen::new(Dimensions::new(self.cols, self.rows));
+        if let Some(lines) = self.scrollback_lines {
+            screen.set_scrollback_limit(lines);
+        }
+        screen.modes_mut().alternate_scroll = self.default_alternate_scroll;
+        screen.set_capture_alternate_screen(self.capture_alternate_screen);
+        let mut parser = Parser::new();
+        parser.set_tmux_passthrough(self.tmux_passthrough);
+        Terminal {
+            screen,
+            parser,
+            title: String::new(),
+            icon_name: String::new(),
+            title_changed: false,
+            title_stack: Vec::new(),
+            bell: false,
+            sync_output_first_enable: false,
+            pending_responses: Vec::new(),
+            cell_pixel_size: self.cell_pixel_size,
+            size_report: None,
+            current_directory: None,
+            default_alternate_scroll: self.default_alternate_scroll,
+            notifications: Vec::new(),
+            pointer_shape: None,
+            tab_accent: None,
+            progress: None,
+            user_vars: BTreeMap::new(),
+            color_overrides: ColorOverrides::default(),
+            unsupported_graphics: None,
+            capture_lines: self.capture_lines,
+            captured_lines: Vec::new(),
+            highlight_rules: self.highlight_rules.clone(),
+            zone: None,
+            commands: 0,
+            clipboard_writes: Vec::new(),
+            clipboard_reads: Vec::new(),
+            osc_answers: BTreeMap::new(),
+            deferred_osc: Vec::new(),
+            osc_prompt_raised: false,
+            action_log: None,
+            trace: None,
+            unhandled: false,
+            stats: ParserStats::default(),
+            options: self.options.clone(),
+        }
+    }
+}
 
 /// Terminal emulator state
 pub struct Terminal {
@@ -13,8 +525,12 @@ pub struct Terminal {
     parser: Parser,
     /// Window title
     title: String,
+    /// Icon name (OSC 0/1), preferred over the window title for tab labels
+    icon_name: String,
     /// Pending title change
     title_changed: bool,
+    /// Icon names and window titles saved with CSI 22 t, the latest last
+    title_stack: Vec<SavedTitle>,
     /// Bell triggered
     bell: bool,
     /// Track if synchronized output mode has been enabled before
@@ -23,20 +539,66 @@ pub struct Terminal {
     /// Pending responses to send back to the PTY
     /// Used for DSR (Device Status Report), DA1 (Primary Device Attributes), etc.
     pending_responses: Vec<Vec<u8>>,
+    /// Cell size in pixels (width, height), used to size inline images
+    cell_pixel_size: (u32, u32),
+    /// Last in-band size report sent (mode 2048), to report only changes
+    size_report: Option<String>,
+    /// Working directory reported by the shell (OSC 7)
+    current_directory: Option<PathBuf>,
+    /// Initial state of alternate scroll mode (1007), restored on reset
+    default_alternate_scroll: bool,
+    /// Notifications not yet taken by the application
+    notifications: Vec<Notification>,
+    /// Pointer shape requested with OSC 22 (comma-separated names)
+    pointer_shape: Option<String>,
+    /// Tab accent color set with OSC 1337 ; TabAccent
+    tab_accent: Option<Color>,
+    /// Progress reported with OSC 9 ; 4
+    progress: Option<Progress>,
+    /// Variables set with OSC 1337 ; SetUserVar
+    user_vars: BTreeMap<String, String>,
+    /// Colors changed with OSC 4, 10, 11 and 12
+    color_overrides: ColorOverrides,
+    /// Graphics received since the application last asked
+    unsupported_graphics: Option<UnsupportedGraphics>,
+    /// Whether completed output lines are kept for output hooks
+    capture_lines: bool,
+    /// Lines ended by a line feed since the application last asked
+    captured_lines: Vec<String>,
+    /// Rules that color lines as they are completed
+    highlight_rules: HighlightRules,
+    /// Shell integration zone the cursor is in (OSC 133)
+    zone: Option<SemanticZone>,
+    /// Commands started so far, numbering the zones
+    commands: u32,
+    /// OSC 52 writes not yet taken by the application
+    clipboard_writes: Vec<ClipboardWrite>,
+    /// OSC 52 reads not yet taken by the application
+    clipboard_reads: Vec<ClipboardRead>,
+    /// Features whose policy is to ask: the user's answer, or `None` while
+    /// the question is open
+    osc_answers: BTreeMap<OscFeature, Option<bool>>,
+    /// OSC sequences held until the user answers for their feature
+    deferred_osc: Vec<(OscFeature, OscAction)>,
+    /// A question was opened since the application last asked
+    osc_prompt_raised: bool,
+    /// Recent actions, while the inspector is open
+    action_log: Option<ActionLog>,
+    /// What came of the output processed while tracing
+    trace: Option<Vec<TraceEvent>>,
+    /// Whether the action being handled was (partly) ignored
+    unhandled: bool,
+    /// Unhandled sequences received so far
+    stats: ParserStats,
+    /// Behavior chosen by the builder
+    options: Options,
 }
 
 impl Terminal {
-    /// Create a new terminal with the given dimensions
+    /// Create a new terminal with the given dimensions and default behavior
+    #[cfg(test)]
     pub fn new(cols: usize, rows: usize) -> Self {
-        Self {
-            screen: Screen::new(Dimensions::new(cols, rows)),
-            parser: Parser::new(),
-            title: String::new(),
-            title_changed: false,
-            bell: false,
-            sync_output_first_enable: false,
-            pending_responses: Vec::new(),
-        }
+        TerminalBuilder::new(cols, rows).build()
     }
 
     /// Get screen reference
@@ -59,6 +621,22 @@ impl Terminal {
         }
     }
 
+    /// Get the title to show in the tab bar
+    ///
+    /// Uses the icon name (OSC 0/1) when set, otherwise the window title.
+    pub fn tab_title(&self) -> &str {
+        if self.icon_name.is_empty() {
+            self.title()
+        } else {
+            &self.icon_name
+        }
+    }
+
+    /// Get the working directory reported by the shell (OSC 7)
+    pub fn current_directory(&self) -> Option<&Path> {
+        self.current_directory.as_deref()
+    }
+
     /// Check and clear title changed flag
     pub fn take_title_changed(&mut self) -> bool {
         let changed = self.title_changed;
@@ -73,6 +651,148 @@ impl Terminal {
         bell
     }
 
+    /// Set a scroll mark on the cursor line
+    pub fn mark_cursor_line(&mut self) {
+        let row = self.screen.cursor().row;
+        self.screen.set_line_mark(row);
+    }
+
+    /// Pointer shape requested by the program (OSC 22), if any
+    pub fn pointer_shape(&self) -> Option<&str> {
+        self.pointer_shape.as_deref()
+    }
+
+    /// Accent color the program gave its tab (OSC 1337 ; TabAccent), if any
+    pub fn tab_accent(&self) -> Option<Color> {
+        self.tab_accent
+    }
+
+    /// Progress the program reported (OSC 9 ; 4), until it completes or is
+    /// removed
+    pub fn progress(&self) -> Option<Progress> {
+        self.progress
+    }
+
+    /// Variables published by the program or its shell (OSC 1337 ;
+    /// SetUserVar), e.g. the cloud profile or Kubernetes context
+    pub fn user_vars(&self) -> &BTreeMap<String, String> {
+        &self.user_vars
+    }
+
+    /// Colors the program changed, to draw over the theme
+    pub fn color_overrides(&self) -> &ColorOverrides {
+        &self.color_overrides
+    }
+
+    /// Take the kind of unsupported graphics received since the last call
+    pub fn take_unsupported_graphics(&mut self) -> Option<UnsupportedGraphics> {
+        self.unsupported_graphics.take()
+    }
+
+    /// Take the notifications sent since the last call
+    pub fn take_notifications(&mut self) -> Vec<Notification> {
+        std::mem::take(&mut self.notifications)
+    }
+
+    /// Keep the text of each line the program ends with a line feed
+    pub fn set_capture_lines(&mut self, enabled: bool) {
+        self.capture_lines = enabled;
+        if !enabled {
+            self.captured_lines.clear();
+        }
+    }
+
+    /// Take the lines completed since the last call, oldest first
+    pub fn take_captured_lines(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.captured_lines)
+    }
+
+    /// Apply new OSC policies from now on; `max_len` caps OSC 52 writes
+    ///
+    /// Pending clipboard writes and reads are dropped unless still allowed,
+    /// and held sequences whose feature no longer asks are dropped too.
+    pub fn set_osc_policies(&mut self, policies: OscPolicies, max_len: usize) {
+        self.options.osc_policies = policies;
+        self.options.limits.clipboard_len = max_len;
+        if policies.clipboard_write != OscPolicy::Allow {
+            self.clipboard_writes.clear();
+        }
+        if policies.clipboard_read != OscPolicy::Allow {
+            self.clipboard_reads.clear();
+        }
+        self.deferred_osc
+            .retain(|(feature, _)| policies.get(*feature) == OscPolicy::Prompt);
+        self.osc_answers
+            .retain(|feature, _| policies.get(*feature) == OscPolicy::Prompt);
+    }
+
+    /// Take the OSC 52 writes received since the last call, oldest first
+    pub fn take_clipboard_writes(&mut self) -> Vec<ClipboardWrite> {
+        std::mem::take(&mut self.clipboard_writes)
+    }
+
+    /// Take the OSC 52 reads received since the last call, oldest first
+    pub fn take_clipboard_reads(&mut self) -> Vec<ClipboardRead> {
+        std::mem::take(&mut self.clipboard_reads)
+    }
+
+    /// Feature the user is being asked about, if any
+    pub fn osc_prompt(&self) -> Option<OscFeature> {
+        self.osc_answers
+            .iter()
+            .find(|(_, answer)| answer.is_none())
+            .map(|(feature, _)| *feature)
+    }
+
+    /// Check and clear whether a question was opened
+    pub fn take_osc_prompt_raised(&mut self) -> bool {
+        let raised = self.osc_prompt_raised;
+        self.osc_prompt_raised = false;
+        raised
+    }
+
+    /// Record the user's answer for a feature, for as long as the terminal
+    /// lives, and handle or drop the sequences held for it
+    pub fn answer_osc_prompt(&mut self, feature: OscFeature, allow: bool) {
+        self.osc_answers.insert(feature, Some(allow));
+        let (held, kept) = std::mem::take(&mut self.deferred_osc)
+            .into_iter()
+            .partition(|(held, _)| *held == feature);
+        self.deferred_osc = kept;
+        if allow {
+            for (_, osc) in held {
+                self.apply_osc(osc);
+            }
+        }
+    }
+
+    /// Color lines matching these rules from now on
+    pub fn set_highlight_rules(&mut self, rules: HighlightRules) {
+        self.highlight_rules = rules;
+    }
+
+    /// Highlight and keep the cursor line, which is about to be ended by a
+    /// line feed
+    fn finish_cursor_line(&mut self) {
+        let row = self.screen.cursor().row;
+        if let Some(zone) = self.zone {
+            if self.screen.line(row).meta.zone.is_none() {
+                self.screen.set_line_zone(row, zone);
+            }
+        }
+        if !self.highlight_rules.is_empty() {
+            let highlights = self.highlight_rules.find(self.screen.line(row));
+            self.screen.set_line_highlights(row, highlights);
+        }
+        if !self.capture_lines || self.captured_lines.len() >= self.options.limits.captured_lines {
+            return;
+        }
+        let text = self.screen.line(row).text();
+        if !text.is_empty() {
+            self.captured_lines.push(text);
+        }
+    }
+
     /// Process input bytes from the PTY
     pub fn process(&mut self, data: &[u8]) {
         // Collect actions first to avoid borrow checker issues
@@ -83,7 +803,72 @@ impl Terminal {
 
         // Then handle each action
         for action in actions {
+            if let Some(log) = &mut self.action_log {
+                log.record(&action);
+            }
+            let traced = match (&mut self.trace, &action) {
+                (Some(events), Action::Print(c)) => {
+                    match events.last_mut() {
+                        Some(TraceEvent::Text(text)) => text.push(*c),
+                        _ => events.push(TraceEvent::Text(c.to_string())),
+                    }
+                    None
+                }
+                (Some(_), _) => Some(inspector::describe(&action)),
+                (None, _) => None,
+            };
             self.handle_action(action);
+            let unhandled = std::mem::take(&mut self.unhandled);
+            if let (Some(events), Some(description)) = (&mut self.trace, traced) {
+                events.push(TraceEvent::Action(description.clone()));
+                if unhandled {
+                    events.push(TraceEvent::Unhandled(description));
+                }
+            }
+        }
+    }
+
+    /// Start (or stop) keeping the recent actions for the inspector
+    pub fn set_action_log(&mut self, enabled: bool) {
+        if enabled != self.action_log.is_some() {
+            self.action_log = enabled.then(ActionLog::default);
+        }
+    }
+
+    /// Recent actions, if they are being kept
+    pub fn action_log(&self) -> Option<&ActionLog> {
+        self.action_log.as_ref()
+    }
+
+    /// Start (or stop) keeping what comes of processed output for a trace
+    pub fn set_trace(&mut self, enabled: bool) {
+        if enabled != self.trace.is_some() {
+            self.trace = enabled.then(Vec::new);
+        }
+    }
+
+    /// Take what came of the output processed since the last call, while
+    /// tracing
+    pub fn take_trace(&mut self) -> Vec<TraceEvent> {
+        self.trace.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    /// Counts of the unhandled sequences received so far
+    pub fn stats(&self) -> &ParserStats {
+        &self.stats
+    }
+
+    /// State of the parser (mid-sequence or ground)
+    pub fn parser_state(&self) -> ParserState {
+        self.parser.state()
+    }
+
+    /// Memory held by the scrollback and the inline images
+    pub fn memory_usage(&self) -> MemoryUsage {
+        MemoryUsage {
+            scrollback: self.screen.scrollback().memory_bytes(),
+            images: self.screen.image_bytes(),
+            ..Default::default()
         }
     }
 
@@ -105,22 +890,84 @@ impl Terminal {
             Action::Osc(osc) => {
                 self.handle_osc(osc);
             }
-            Action::Dcs { .. } => {
-                // DCS sequences are currently not implemented
-                log::debug!("DCS sequence ignored");
-            }
-            Action::Apc(_) | Action::Pm(_) | Action::Sos(_) => {
-                // These are consumed but ignored
+            Action::Dcs {
+                intermediates,
+                final_byte: b'p',
+                data,
+                ..
+            } if intermediates.is_empty() => {
+                log::debug!("ReGIS graphics ignored ({} bytes)", data.len());
+                self.show_graphics_placeholder(UnsupportedGraphics::Regis);
+            }
+            Action::Dcs {
+                intermediates,
+                final_byte: b'q',
+                data,
+                ..
+            } if intermediates == b"+" => {
+                // XTGETTCAP - Request Termcap/Terminfo String
+                log::debug!("XTGETTCAP request: {:?}", String::from_utf8_lossy(&data));
+                for response in termcap::responses(&data, &self.options.term_name) {
+                    self.queue_response(response);
+                }
             }
+            Action::Dcs {
+                intermediates,
+                final_byte,
+                ..
+            } => {
+                // Other DCS sequences are currently not implemented
+                log::debug!("DCS sequence ignored (final {:?})", final_byte as char);
+                self.unhandled(Sequence::Dcs {
+                    intermediates,
+                    final_byte,
+                });
+            }
+            Action::Tek(data) => {
+                log::debug!("Tektronix graphics ignored ({} bytes)", data.len());
+                self.show_graphics_placeholder(UnsupportedGraphics::Tektronix);
+            }
+            // Consumed, and reported so traces show what was passed by
+            Action::Apc(data) => {
+                log::debug!("APC ignored ({} bytes)", data.len());
+                self.unhandled(Sequence::Apc(data.first().copied()));
+            }
+            Action::Pm(_) => self.unhandled(Sequence::Pm),
+            Action::Sos(_) => self.unhandled(Sequence::Sos),
             Action::Invalid(data) => {
                 log::debug!("Invalid sequence: {:?}", data);
+                self.unhandled(Sequence::Invalid);
             }
         }
     }
 
+    /// Stand in for graphics that cannot be drawn
+    ///
+    /// A reverse video label on a line of its own marks where the graphics
+    /// would have been, and the application is told so it can show a notice.
+    fn show_graphics_placeholder(&mut self, kind: UnsupportedGraphics) {
+        if self.screen.cursor().col > 0 {
+            self.screen.carriage_return();
+            self.screen.linefeed();
+        }
+        let attrs = self.screen.cursor().attrs;
+        self.screen.cursor_mut().attrs.inverse = true;
+        for c in format!("[{} graphics not supported]", kind.name()).chars() {
+            self.screen.print(c);
+        }
+        self.screen.cursor_mut().attrs = attrs;
+        self.screen.carriage_return();
+        self.screen.linefeed();
+        self.unsupported_graphics = Some(kind);
+    }
+
     /// Handle C0 control characters
     fn handle_control(&mut self, byte: u8) {
         match byte {
+            0x05 if !self.options.answerback.is_empty() => {
+                // ENQ - reply with the answerback string
+                self.queue_response(self.options.answerback.clone().into_bytes());
+            }
             0x07 => {
                 // BEL
                 self.bell = true;
@@ -135,6 +982,7 @@ impl Terminal {
             }
             0x0A..=0x0C => {
                 // LF, VT, FF
+                self.finish_cursor_line();
                 self.screen.linefeed();
             }
             0x0D => {
@@ -163,20 +1011,33 @@ impl Terminal {
                 self.screen.restore_cursor();
             }
             EscAction::Index => {
+                self.finish_cursor_line();
                 self.screen.index();
             }
             EscAction::ReverseIndex => {
                 self.screen.reverse_index();
             }
             EscAction::NextLine => {
+                self.finish_cursor_line();
                 self.screen.next_line();
             }
             EscAction::HorizontalTabSet => {
                 self.screen.set_tab_stop();
             }
             EscAction::FullReset => {
+                // RIS - screen state (including tab stops, scrollback and
+                // all modes), parser state and queued replies
                 self.screen.reset();
                 self.parser.reset();
+                self.pending_responses.clear();
+                self.sync_output_first_enable = false;
+                self.bell = false;
+                self.pointer_shape = None;
+                self.progress = None;
+                self.color_overrides = ColorOverrides::default();
+                self.zone = None;
+                self.title_stack.clear();
+                self.screen.modes_mut().alternate_scroll = self.default_alternate_scroll;
             }
             EscAction::ApplicationKeypad => {
                 // Application keypad mode - affects key encoding
@@ -203,19 +1064,11 @@ impl Terminal {
                 self.screen.designate_charset(3, c);
             }
             EscAction::DecAlignmentTest => {
-                // Fill screen with 'E' characters
-                let rows = self.screen.rows();
-                let cols = self.screen.cols();
-                for row in 0..rows {
-                    self.screen.move_cursor_to(row + 1, 1);
-                    for _ in 0..cols {
-                        self.screen.print('E');
-                    }
-                }
-                self.screen.move_cursor_to(1, 1);
+                self.screen.alignment_test();
             }
             EscAction::Unknown(data) => {
                 log::debug!("Unknown ESC sequence: {:?}", data);
+                self.unhandled(Sequence::Esc(data));
             }
         }
     }
@@ -272,11 +1125,31 @@ impl Terminal {
                 self.screen.move_cursor_up(n);
                 self.screen.carriage_return();
             }
-            b'G' => {
-                // CHA - Cursor Horizontal Absolute
+            b'G' | b'`' => {
+                // CHA/HPA - Cursor Horizontal Absolute / Character Position Absolute
                 let col = csi.param(0, 1) as usize;
                 self.screen.set_cursor_col(col);
             }
+            b'I' => {
+                // CHT - Cursor Forward Tabulation
+                let n = csi.param(0, 1) as usize;
+                self.screen.tab_forward(n);
+            }
+            b'Z' => {
+                // CBT - Cursor Backward Tabulation
+                let n = csi.param(0, 1) as usize;
+                self.screen.tab_backward(n);
+            }
+            b'a' => {
+                // HPR - Character Position Relative
+                let n = csi.param(0, 1) as usize;
+                self.screen.move_cursor_right(n);
+            }
+            b'b' => {
+                // REP - Repeat preceding graphic character
+                let n = csi.param(0, 1) as usize;
+                self.screen.repeat_last_char(n);
+            }
             b'H' | b'f' => {
                 // CUP/HVP - Cursor Position
                 let row = csi.param(0, 1) as usize;
@@ -328,6 +1201,11 @@ impl Terminal {
                 let row = csi.param(0, 1) as usize;
                 self.screen.set_cursor_row(row);
             }
+            b'e' => {
+                // VPR - Line Position Relative
+                let n = csi.param(0, 1) as usize;
+                self.screen.move_cursor_down(n);
+            }
             b'g' => {
                 // TBC - Tab Clear
                 let mode = csi.param(0, 0);
@@ -336,13 +1214,13 @@ impl Terminal {
             b'h' => {
                 // SM - Set Mode
                 for param in csi.params.iter() {
-                    self.screen.modes_mut().set_mode(param, true);
+                    self.set_ansi_mode(param, true);
                 }
             }
             b'l' => {
                 // RM - Reset Mode
                 for param in csi.params.iter() {
-                    self.screen.modes_mut().set_mode(param, false);
+                    self.set_ansi_mode(param, false);
                 }
             }
             b'm' => {
@@ -391,16 +1269,58 @@ impl Terminal {
                 // Restore cursor (ANSI.SYS)
                 self.screen.restore_cursor();
             }
+            b't' if matches!(csi.param(0, 0), 22 | 23) && csi.param(1, 0) <= 2 => {
+                // XTWINOPS - save (22) or restore (23) the icon name and
+                // window title (0), the icon name (1) or the title (2)
+                let which = csi.param(1, 0);
+                if csi.param(0, 0) == 22 {
+                    self.push_title(which);
+                } else {
+                    self.pop_title(which);
+                }
+            }
             _ => {
                 log::debug!(
                     "Unknown CSI sequence: {:?} {}",
                     csi.params,
                     csi.final_byte as char
                 );
+                self.unhandled(Sequence::csi(&csi));
             }
         }
     }
 
+    /// Save titles for CSI 22 t; `which` is 0 (both), 1 (icon) or 2 (title)
+    fn push_title(&mut self, which: u16) {
+        let saved = SavedTitle {
+            icon_name: (which != 2).then(|| self.icon_name.clone()),
+            title: (which != 1).then(|| self.title.clone()),
+        };
+        if self.options.limits.title_stack == 0 {
+            return;
+        }
+        if self.title_stack.len() >= self.options.limits.title_stack {
+            self.title_stack.remove(0);
+        }
+        self.title_stack.push(saved);
+    }
+
+    /// Restore the titles saved last, those of them that `which` names
+    fn pop_title(&mut self, which: u16) {
+        let Some(saved) = self.title_stack.pop() else {
+            return;
+        };
+        if let Some(icon_name) = saved.icon_name.filter(|_| which != 2) {
+            self.icon_name = icon_name;
+            self.title_changed = true;
+        }
+        if let Some(title) = saved.title.filter(|_| which != 1) {
+            self.screen.set_title(&title);
+            self.title = title;
+            self.title_changed = true;
+        }
+    }
+
     /// Handle CSI sequences with private marker (?)
     fn handle_csi_private(&mut self, csi: &CsiAction) {
         match csi.final_byte {
@@ -422,9 +1342,36 @@ impl Terminal {
                 // Response: CSI ? 62 ; 1 ; 2 ; 6 ; 7 ; 8 ; 9 c
                 // This indicates: VT220, 132 columns, printer, selective erase,
                 // user-defined keys, national replacement character sets, technical characters
-                // A simpler response that works well: CSI ? 1 ; 2 c (VT100 with AVO)
-                self.queue_response(b"\x1b[?1;2c".to_vec());
-                log::debug!("DA1 request: responding as VT100 with AVO");
+                // A simpler response that works well: CSI ? 1 ; 2 c (VT100 with AVO),
+                // the default device attributes
+                let response = format!("\x1b[?{}c", self.options.device_attributes);
+                log::debug!("DA1 request: responding {:?}", response);
+                self.queue_response(response.into_bytes());
+            }
+            b'W' if csi.param(0, 0) == 5 => {
+                // DECST8C - Set tab stops every 8 columns
+                self.screen.reset_tab_stops();
+            }
+            b's' => {
+                // XTSAVE - Save DEC private modes
+                for param in csi.params.iter() {
+                    self.screen.modes_mut().save_dec_mode(param);
+                }
+            }
+            b'r' => {
+                // XTRESTORE - Restore DEC private modes saved by XTSAVE
+                for param in csi.params.iter() {
+                    if let Some(value) = self.screen.modes_mut().take_saved_dec_mode(param) {
+                        self.set_dec_mode(param, value);
+                    }
+                }
+            }
+            b'p' if csi.intermediates == [b'$'] => {
+                // DECRQM - Request DEC private mode; reply CSI ? Ps ; Pm $ y
+                let mode = csi.param(0, 0);
+                let state = self.screen.modes().dec_mode_state(mode);
+                let response = format!("\x1b[?{};{}$y", mode, state as u8);
+                self.queue_response(response.into_bytes());
             }
             _ => {
                 log::debug!(
@@ -432,6 +1379,7 @@ impl Terminal {
                     csi.params,
                     csi.final_byte as char
                 );
+                self.unhandled(Sequence::csi(csi));
             }
         }
     }
@@ -439,9 +1387,47 @@ impl Terminal {
     /// Handle CSI sequences with intermediate bytes
     fn handle_csi_intermediate(&mut self, csi: &CsiAction) {
         match (csi.intermediates.as_slice(), csi.final_byte) {
-            ([b' '], b'q') => {
-                // DECSCUSR - Set Cursor Style
-                let style = csi.param(0, 1);
+            ([b'!'], b'p') => {
+                // DECSTR - Soft Terminal Reset
+                self.screen.soft_reset();
+            }
+            ([b'$'], b'p') => {
+                // DECRQM - Request ANSI mode; reply CSI Ps ; Pm $ y
+                let mode = csi.param(0, 0);
+                let state = self.screen.modes().mode_state(mode);
+                let response = format!("\x1b[{};{}$y", mode, state as u8);
+                self.queue_response(response.into_bytes());
+            }
+            ([b'>'], b'm') if csi.param(0, 0) == 4 => {
+                // XTMODKEYS - modifyOtherKeys; no value restores the default
+                self.screen.modes_mut().modify_other_keys = csi.param(1, 0).min(2) as u8;
+            }
+            ([b'>'], b'q') if csi.param(0, 0) == 0 => {
+                // XTVERSION - reply DCS > | name(version) ST
+                let version = if self.options.identify_as_xterm {
+                    XTERM_VERSION.to_string()
+                } else {
+                    format!("mochi {}", env!("CARGO_PKG_VERSION"))
+                };
+                let response = format!("\x1bP>|{}\x1b\\", version);
+                self.queue_response(response.into_bytes());
+            }
+            ([b'#'], b'{') | ([b'#'], b'p') => {
+                // XTPUSHSGR - save the attributes the parameters name (all
+                // without any)
+                self.screen.push_sgr(csi.params.as_slice());
+            }
+            ([b'#'], b'}') | ([b'#'], b'q') => {
+                // XTPOPSGR
+                self.screen.pop_sgr();
+            }
+            ([b'>'], b'n') if csi.param(0, 0) == 4 => {
+                // Disable modifyOtherKeys
+                self.screen.modes_mut().modify_other_keys = 0;
+            }
+            ([b' '], b'q') => {
+                // DECSCUSR - Set Cursor Style
+                let style = csi.param(0, 1);
                 let cursor = self.screen.cursor_mut();
                 match style {
                     0 | 1 => {
@@ -478,6 +1464,7 @@ impl Terminal {
                     csi.params,
                     csi.final_byte as char
                 );
+                self.unhandled(Sequence::csi(csi));
             }
         }
     }
@@ -485,50 +1472,15 @@ impl Terminal {
     /// Set DEC private mode
     fn set_dec_mode(&mut self, mode: u16, value: bool) {
         match mode {
-            1 => {
-                // DECCKM - Cursor Keys Mode
-                self.screen.modes_mut().cursor_keys_application = value;
-            }
             6 => {
                 // DECOM - Origin Mode
-                self.screen.modes_mut().origin_mode = value;
-                self.screen.cursor_mut().origin_mode = value;
-                if value {
-                    let (top, _) = self.screen.scroll_region();
-                    self.screen.move_cursor_to(top + 1, 1);
-                } else {
-                    self.screen.move_cursor_to(1, 1);
-                }
-            }
-            7 => {
-                // DECAWM - Auto-wrap Mode
-                self.screen.modes_mut().auto_wrap = value;
+                self.screen.set_origin_mode(value);
             }
             25 => {
                 // DECTCEM - Text Cursor Enable Mode
                 self.screen.modes_mut().cursor_visible = value;
                 self.screen.cursor_mut().visible = value;
             }
-            1000 => {
-                // Mouse tracking: VT200
-                self.screen.modes_mut().mouse_vt200 = value;
-            }
-            1002 => {
-                // Mouse tracking: Button event
-                self.screen.modes_mut().mouse_button_event = value;
-            }
-            1003 => {
-                // Mouse tracking: Any event
-                self.screen.modes_mut().mouse_any_event = value;
-            }
-            1004 => {
-                // Focus events
-                self.screen.modes_mut().focus_events = value;
-            }
-            1006 => {
-                // SGR mouse mode
-                self.screen.modes_mut().mouse_sgr = value;
-            }
             47 => {
                 // Alternate screen buffer (without clearing)
                 if value {
@@ -563,10 +1515,6 @@ impl Terminal {
                     self.screen.restore_cursor();
                 }
             }
-            2004 => {
-                // Bracketed paste mode
-                self.screen.modes_mut().bracketed_paste = value;
-            }
             2026 => {
                 // Synchronized output mode (used by TUI apps like Claude Code)
                 // When enabled, the terminal should buffer output until disabled
@@ -590,13 +1538,39 @@ impl Terminal {
                 self.screen.modes_mut().synchronized_output = value;
                 log::debug!("Synchronized output mode: {}", value);
             }
+            2048 => {
+                // In-band resize notifications; enabling reports the size
+                self.screen.modes_mut().in_band_resize = value;
+                if value {
+                    self.report_size(true);
+                }
+            }
             _ => {
+                // Modes that are only a flag
+                if self.screen.modes().dec_mode_state(mode) == ModeState::NotRecognized {
+                    self.unhandled(Sequence::DecMode(mode));
+                }
                 self.screen.modes_mut().set_dec_mode(mode, value);
             }
         }
     }
 
+    /// Count a sequence that was (partly) ignored
+    fn unhandled(&mut self, sequence: Sequence) {
+        self.stats.record(sequence);
+        self.unhandled = true;
+    }
+
+    /// Set a standard (non-DEC) mode (SM / RM)
+    fn set_ansi_mode(&mut self, mode: u16, value: bool) {
+        if self.screen.modes().mode_state(mode) == ModeState::NotRecognized {
+            self.unhandled(Sequence::Mode(mode));
+        }
+        self.screen.modes_mut().set_mode(mode, value);
+    }
+
     /// Handle SGR (Select Graphic Rendition)
+    #[allow(clippy::collapsible_match)]
     fn handle_sgr(&mut self, csi: &CsiAction) {
         let attrs = &mut self.screen.cursor_mut().attrs;
 
@@ -606,7 +1580,7 @@ impl Terminal {
         }
 
         let mut i = 0;
-        let params: Vec<u16> = csi.params.iter().collect();
+        let params = csi.params.as_slice();
 
         while i < params.len() {
             let param = params[i];
@@ -615,22 +1589,34 @@ impl Terminal {
                 1 => attrs.bold = true,
                 2 => attrs.faint = true,
                 3 => attrs.italic = true,
-                4 => attrs.underline = true,
+                4 => {
+                    attrs.underline = true;
+                    attrs.double_underline = false;
+                }
                 5 => attrs.blink = true,
                 7 => attrs.inverse = true,
                 8 => attrs.hidden = true,
                 9 => attrs.strikethrough = true,
-                21 => attrs.bold = false, // Double underline or bold off
+                21 => {
+                    // Double underline (ECMA-48 and xterm, not "bold off")
+                    attrs.underline = false;
+                    attrs.double_underline = true;
+                }
                 22 => {
                     attrs.bold = false;
                     attrs.faint = false;
                 }
                 23 => attrs.italic = false,
-                24 => attrs.underline = false,
+                24 => {
+                    attrs.underline = false;
+                    attrs.double_underline = false;
+                }
                 25 => attrs.blink = false,
                 27 => attrs.inverse = false,
                 28 => attrs.hidden = false,
                 29 => attrs.strikethrough = false,
+                53 => attrs.overline = true,
+                55 => attrs.overline = false,
                 30..=37 => {
                     attrs.fg = Color::Indexed((param - 30) as u8);
                 }
@@ -701,67 +1687,388 @@ impl Terminal {
                 }
                 _ => {
                     log::debug!("Unknown SGR parameter: {}", param);
+                    self.stats.record(Sequence::Sgr(param));
+                    self.unhandled = true;
                 }
             }
             i += 1;
         }
     }
 
-    /// Handle OSC sequences
+    /// Handle OSC sequences that the policy of their feature allows
+    ///
+    /// Denied sequences are dropped. Sequences of a feature that asks are
+    /// held (the latest few) until the user answers.
     fn handle_osc(&mut self, osc: OscAction) {
+        let Some(feature) = OscFeature::of(&osc) else {
+            self.apply_osc(osc);
+            return;
+        };
+        let allowed = match self.options.osc_policies.get(feature) {
+            OscPolicy::Allow => Some(true),
+            OscPolicy::Deny => Some(false),
+            OscPolicy::Prompt => *self.osc_answers.entry(feature).or_insert_with(|| {
+                self.osc_prompt_raised = true;
+                None
+            }),
+        };
+        match allowed {
+            Some(true) => self.apply_osc(osc),
+            Some(false) => log::debug!("OSC {:?} denied by policy", feature),
+            None => {
+                if self.deferred_osc.len() >= self.options.limits.pending_notifications {
+                    self.deferred_osc.remove(0);
+                }
+                self.deferred_osc.push((feature, osc));
+            }
+        }
+    }
+
+    /// Carry out an OSC sequence
+    fn apply_osc(&mut self, osc: OscAction) {
         match osc {
-            OscAction::SetIconAndTitle(title) | OscAction::SetTitle(title) => {
+            OscAction::SetIconAndTitle(title) => {
+                self.icon_name = title.clone();
+                self.title = title.clone();
+                self.screen.set_title(&title);
+                self.title_changed = true;
+            }
+            OscAction::SetTitle(title) => {
                 self.title = title.clone();
                 self.screen.set_title(&title);
                 self.title_changed = true;
             }
-            OscAction::SetIconName(_) => {
-                // Icon name is typically ignored in modern terminals
+            OscAction::SetIconName(name) => {
+                self.icon_name = name;
+                self.title_changed = true;
             }
-            OscAction::Hyperlink { params: _, uri } => {
+            OscAction::Hyperlink { params, uri } => {
                 if uri.is_empty() {
                     // End hyperlink
                     self.screen.cursor_mut().hyperlink_id = 0;
                 } else {
-                    // Start hyperlink
-                    let id = self.screen.register_hyperlink(&uri);
+                    // Start hyperlink; params are `key=value` pairs split by ':'
+                    let link_id = params
+                        .split(':')
+                        .find_map(|param| param.strip_prefix("id="));
+                    let id = match link_id {
+                        Some(link_id) => self.screen.register_hyperlink_with_id(link_id, &uri),
+                        None => self.screen.register_hyperlink(&uri),
+                    };
                     self.screen.cursor_mut().hyperlink_id = id;
                 }
             }
-            OscAction::Clipboard { clipboard: _, data } => {
-                // OSC 52 clipboard - handled by the application layer
-                log::debug!("OSC 52 clipboard: {} bytes", data.len());
+            OscAction::Notify { title, body } => {
+                let notification = Notification {
+                    title: sanitize_notification(&title, self.options.limits.notification_len),
+                    body: sanitize_notification(&body, self.options.limits.notification_len),
+                };
+                if notification.title.is_empty() && notification.body.is_empty() {
+                    return;
+                }
+                if self.notifications.len() < self.options.limits.pending_notifications {
+                    self.notifications.push(notification);
+                } else {
+                    log::debug!("Dropping notification: too many pending");
+                }
+            }
+            OscAction::Progress { state, value } => {
+                // Error and paused keep the last percentage when none is given
+                let percent = value
+                    .map(|value| value.min(100) as u8)
+                    .or_else(|| self.progress.and_then(Progress::percent))
+                    .unwrap_or(0);
+                self.progress = match state {
+                    0 => None,
+                    // Reaching 100% completes the task
+                    1 => match value.unwrap_or(0).min(100) {
+                        100 => None,
+                        percent => Some(Progress::Normal(percent as u8)),
+                    },
+                    2 => Some(Progress::Error(percent)),
+                    3 => Some(Progress::Indeterminate),
+                    4 => Some(Progress::Paused(percent)),
+                    _ => {
+                        log::debug!("Ignoring progress state {}", state);
+                        return;
+                    }
+                };
+            }
+            OscAction::SetUserVar { name, value } => {
+                self.set_user_var(name, &value);
+            }
+            OscAction::SetPointerShape(shape) => {
+                let valid = shape.len() <= self.options.limits.pointer_shape_len
+                    && shape
+                        .bytes()
+                        .all(|b| b.is_ascii_alphanumeric() || b"-_,".contains(&b));
+                if !valid {
+                    log::debug!("Ignoring pointer shape: {:?}", shape);
+                } else if shape.is_empty() {
+                    self.pointer_shape = None;
+                } else {
+                    self.pointer_shape = Some(shape);
+                }
+            }
+            OscAction::SetTabAccent(color) => {
+                if color.is_empty() {
+                    self.tab_accent = None;
+                } else if let Some(color) = highlight::parse_color(&color) {
+                    self.tab_accent = Some(color);
+                } else {
+                    log::debug!("Ignoring tab accent: {:?}", color);
+                }
+            }
+            OscAction::SemanticPrompt { kind, params } => {
+                log::debug!("Semantic prompt {}: {}", kind, params);
+                // Prompt starts are scroll marks and begin a new command;
+                // a line is in the zone it starts in
+                let row = self.screen.cursor().row;
+                let kind = match kind {
+                    'A' => {
+                        self.commands = self.commands.wrapping_add(1);
+                        self.screen.set_line_mark(row);
+                        Some(ZoneKind::Prompt)
+                    }
+                    'B' => Some(ZoneKind::Input),
+                    'C' => Some(ZoneKind::Output),
+                    _ => None,
+                };
+                self.zone = kind.map(|kind| SemanticZone {
+                    id: self.commands,
+                    kind,
+                });
+                if let Some(zone) = self.zone {
+                    if kind == Some(ZoneKind::Prompt) || self.screen.line(row).meta.zone.is_none() {
+                        self.screen.set_line_zone(row, zone);
+                    }
+                }
+            }
+            OscAction::Clipboard { clipboard, data } => {
+                self.handle_clipboard(&clipboard, &data);
             }
             OscAction::SetColor { index, color } => {
                 log::debug!("Set color {}: {}", index, color);
+                // Colors past the 16 ANSI ones are not themed
+                if let Some(color) = parse_color_spec(&color).filter(|_| index < 16) {
+                    self.color_overrides.palette.insert(index, color);
+                }
             }
             OscAction::SetForegroundColor(color) => {
                 log::debug!("Set foreground color: {}", color);
+                if let Some(color) = parse_color_spec(&color) {
+                    self.color_overrides.foreground = Some(color);
+                }
             }
             OscAction::SetBackgroundColor(color) => {
                 log::debug!("Set background color: {}", color);
+                if let Some(color) = parse_color_spec(&color) {
+                    self.color_overrides.background = Some(color);
+                }
             }
             OscAction::SetCursorColor(color) => {
                 log::debug!("Set cursor color: {}", color);
+                if let Some(color) = parse_color_spec(&color) {
+                    self.color_overrides.cursor = Some(color);
+                }
+            }
+            OscAction::SetCurrentDirectory(uri) => {
+                log::debug!("Set current directory: {}", uri);
+                if let Some(dir) = parse_file_uri(&uri) {
+                    self.current_directory = Some(dir);
+                }
+            }
+            OscAction::ResetColor(index) => {
+                log::debug!("Reset color {:?}", index);
+                match index {
+                    Some(index) => {
+                        self.color_overrides.palette.remove(&index);
+                    }
+                    None => self.color_overrides.palette.clear(),
+                }
+            }
+            OscAction::ResetForegroundColor => {
+                self.color_overrides.foreground = None;
             }
-            OscAction::SetCurrentDirectory(dir) => {
-                log::debug!("Set current directory: {}", dir);
+            OscAction::ResetBackgroundColor => {
+                self.color_overrides.background = None;
             }
-            OscAction::ResetColor(_)
-            | OscAction::ResetForegroundColor
-            | OscAction::ResetBackgroundColor
-            | OscAction::ResetCursorColor => {
-                log::debug!("Reset color");
+            OscAction::ResetCursorColor => {
+                self.color_overrides.cursor = None;
+            }
+            OscAction::InlineFile { args, data } => {
+                self.handle_inline_file(&args, &data);
             }
             OscAction::Unknown { command, data } => {
                 log::debug!("Unknown OSC {}: {}", command, data);
+                self.unhandled(Sequence::Osc(command));
+            }
+        }
+    }
+
+    /// Set or, with an empty value, unset a user variable
+    ///
+    /// Names are limited to letters, digits, `_`, `-` and `.`; values are
+    /// base64 encoded UTF-8 and are kept without control characters.
+    fn set_user_var(&mut self, name: String, value: &str) {
+        let valid_name = !name.is_empty()
+            && name.len() <= 64
+            && name
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b"_-.".contains(&b));
+        let value = inline_image::decode_base64(value)
+            .and_then(|bytes| String::from_utf8(bytes).ok())
+            .map(|value| sanitize_notification(&value, self.options.limits.user_var_len));
+        let (true, Some(value)) = (valid_name, value) else {
+            log::debug!("Ignoring user variable {:?}", name);
+            return;
+        };
+        if value.is_empty() {
+            self.user_vars.remove(&name);
+        } else if self.user_vars.contains_key(&name)
+            || self.user_vars.len() < self.options.limits.user_vars
+        {
+            self.user_vars.insert(name, value);
+        } else {
+            log::debug!("Dropping user variable {}: too many", name);
+        }
+    }
+
+    /// Handle OSC 52: queue a clipboard write or read for the application
+    fn handle_clipboard(&mut self, clipboard: &str, data: &str) {
+        // Targets are c (clipboard), p (primary), s (select) and 0-7; the
+        // clipboard is used unless only the primary selection is named
+        let primary = clipboard.contains('p') && !clipboard.contains('c');
+        if data == "?" {
+            let selection: String = clipboard
+                .chars()
+                .filter(|c| "cpqs01234567".contains(*c))
+                .collect();
+            if self.clipboard_reads.len() < self.options.limits.pending_notifications {
+                self.clipboard_reads.push(ClipboardRead {
+                    primary,
+                    selection: if selection.is_empty() {
+                        "c".to_string()
+                    } else {
+                        selection
+                    },
+                });
             }
+            return;
+        }
+        if data.len() > self.options.limits.clipboard_len {
+            log::debug!("OSC 52 clipboard write too large ({} bytes)", data.len());
+            return;
+        }
+        let Some(text) =
+            inline_image::decode_base64(data).and_then(|bytes| String::from_utf8(bytes).ok())
+        else {
+            log::debug!("OSC 52 clipboard data is not base64 encoded UTF-8");
+            return;
+        };
+        if text.is_empty() {
+            return;
+        }
+        if self.clipboard_writes.len() < self.options.limits.pending_notifications {
+            self.clipboard_writes.push(ClipboardWrite { primary, text });
         }
     }
 
+    /// Handle OSC 1337 File= (iTerm2 inline image)
+    fn handle_inline_file(&mut self, args: &str, data: &str) {
+        let args = InlineImageArgs::parse(args);
+        if !args.inline {
+            // File downloads are not supported
+            log::debug!("Ignoring OSC 1337 file download ({} bytes)", data.len());
+            return;
+        }
+
+        let Some(image) = inline_image::decode_base64(data)
+            .as_deref()
+            .and_then(inline_image::decode_image)
+        else {
+            log::debug!("Failed to decode inline image");
+            return;
+        };
+
+        let (cols, rows) = args.cell_box(
+            &image,
+            self.cell_pixel_size,
+            (self.screen.cols(), self.screen.rows()),
+        );
+        self.screen
+            .place_image(image, cols, rows, args.preserve_aspect_ratio);
+    }
+
+    /// Set the maximum number of scrollback lines
+    pub fn set_scrollback_limit(&mut self, max_lines: usize) {
+        self.screen.set_scrollback_limit(max_lines);
+    }
+
+    /// Answer status and capability queries from now on, or only listen
+    pub fn set_answer_queries(&mut self, enabled: bool) {
+        self.options.answer_queries = enabled;
+    }
+
+    /// Report `attributes` in the primary device attributes reply from now on
+    pub fn set_device_attributes(&mut self, attributes: &str) {
+        self.options.device_attributes = attributes.to_string();
+    }
+
+    /// Reply to ENQ with `answerback` from now on
+    pub fn set_answerback(&mut self, answerback: &str) {
+        self.options.answerback = answerback.to_string();
+    }
+
+    /// Answer XTVERSION as xterm from now on
+    pub fn set_identify_as_xterm(&mut self, enabled: bool) {
+        self.options.identify_as_xterm = enabled;
+    }
+
+    /// Unwrap tmux passthrough sequences from now on
+    pub fn set_tmux_passthrough(&mut self, enabled: bool) {
+        self.parser.set_tmux_passthrough(enabled);
+    }
+
+    /// Keep the alternate screen in the scrollback when it is left
+    pub fn set_capture_alternate_screen(&mut self, enabled: bool) {
+        self.screen.set_capture_alternate_screen(enabled);
+    }
+
+    /// Set the cell size in pixels (used to size inline images)
+    pub fn set_cell_pixel_size(&mut self, width: u32, height: u32) {
+        self.cell_pixel_size = (width.max(1), height.max(1));
+    }
+
     /// Resize the terminal
+    ///
+    /// With in-band resize notifications on (mode 2048), the new size is
+    /// reported to the application if it changed.
     pub fn resize(&mut self, cols: usize, rows: usize) {
         self.screen.resize(Dimensions::new(cols, rows));
+        if self.screen.modes().in_band_resize {
+            self.report_size(false);
+        }
+    }
+
+    /// Queue an in-band size report, `CSI 48 ; rows ; cols ; height ; width t`
+    /// with the text area in cells and pixels; unless `always`, only if it
+    /// differs from the last one sent
+    fn report_size(&mut self, always: bool) {
+        let cols = self.screen.cols();
+        let rows = self.screen.rows();
+        let (cell_width, cell_height) = self.cell_pixel_size;
+        let report = format!(
+            "\x1b[48;{};{};{};{}t",
+            rows,
+            cols,
+            rows as u32 * cell_height,
+            cols as u32 * cell_width
+        );
+        if always || self.size_report.as_deref() != Some(report.as_str()) {
+            self.queue_response(report.clone().into_bytes());
+            self.size_report = Some(report);
+        }
     }
 
     /// Create a snapshot of the current state
@@ -785,13 +2092,54 @@ impl Terminal {
 
     /// Queue a response to be sent back to the PTY
     fn queue_response(&mut self, response: Vec<u8>) {
-        self.pending_responses.push(response);
+        if self.options.answer_queries {
+            self.pending_responses.push(response);
+        }
+    }
+}
+
+/// Parse an OSC 7 `file://host/path` URI into a local path
+///
+/// The host is not checked; percent-encoded bytes in the path are decoded.
+fn parse_file_uri(uri: &str) -> Option<PathBuf> {
+    use std::ffi::OsString;
+    use std::os::unix::ffi::OsStringExt;
+
+    let rest = uri.strip_prefix("file://")?;
+    let path = &rest[rest.find('/')?..];
+
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = |b: u8| (b as char).to_digit(16);
+            if let (Some(high), Some(low)) = (hex(bytes[i + 1]), hex(bytes[i + 2])) {
+                decoded.push((high * 16 + low) as u8);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
     }
+    Some(PathBuf::from(OsString::from_vec(decoded)))
+}
+
+/// Strip control characters and clamp notification text to one short line
+fn sanitize_notification(text: &str, max_len: usize) -> String {
+    text.chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .take(max_len)
+        .collect::<String>()
+        .trim()
+        .to_string()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use terminal_core::{CellAttributes, Modes};
 
     #[test]
     fn test_terminal_new() {
@@ -832,6 +2180,28 @@ mod tests {
         assert_eq!(attrs.fg, Color::Default);
     }
 
+    #[test]
+    fn test_terminal_sgr_decorations() {
+        let mut term = Terminal::new(80, 24);
+        term.process(b"\x1b[1;21m");
+        let attrs = term.screen().cursor().attrs;
+        assert!(attrs.bold && attrs.double_underline && !attrs.underline);
+
+        // The last underline style wins; 24 clears both
+        term.process(b"\x1b[4m");
+        let attrs = term.screen().cursor().attrs;
+        assert!(attrs.underline && !attrs.double_underline);
+        term.process(b"\x1b[21;24m");
+        let attrs = term.screen().cursor().attrs;
+        assert!(!attrs.underline && !attrs.double_underline);
+
+        term.process(b"\x1b[8;9;53mx\x1b[28;29;55m");
+        let cell = term.screen().line(0).cell(0);
+        assert!(cell.attrs.hidden && cell.attrs.strikethrough && cell.attrs.overline);
+        let attrs = term.screen().cursor().attrs;
+        assert!(!attrs.hidden && !attrs.strikethrough && !attrs.overline);
+    }
+
     #[test]
     fn test_terminal_erase() {
         let mut term = Terminal::new(10, 3);
@@ -871,6 +2241,42 @@ mod tests {
         assert_eq!(term.screen().line(0).cell(0).display_char(), 'P');
     }
 
+    #[test]
+    fn test_terminal_decsc_decrc_traces() {
+        // vim: 1049 around the session, with its own SGR and DECSC inside
+        let mut term = Terminal::new(20, 5);
+        term.process(b"\x1b[3;5H\x1b[1m$ \x1b(0");
+        term.process(b"\x1b[?1049h\x1b[22;0;0t\x1b[>4;2m\x1b[?1h\x1b=");
+        term.process(b"\x1b[0m\x1b(B\x1b[H~\x1b7\x1b[5;1H:wq\x1b8x");
+        assert_eq!(term.screen().line(0).text(), "~x");
+        term.process(b"\x1b[?1049l\x1b[23;0;0t\x1b[>4;m\x1b[?1l\x1b>");
+        let cursor = term.screen().cursor();
+        assert_eq!((cursor.row, cursor.col), (2, 6));
+        assert!(cursor.attrs.bold);
+        // The shell's G0 (DEC Special Graphics) is back
+        term.process(b"q");
+        assert_eq!(term.screen().line(2).cell(6).display_char(), '─');
+
+        // tmux-style status line: save, draw lines and reverse video at
+        // the bottom, restore
+        let mut term = Terminal::new(20, 5);
+        term.process(b"\x1b[?6h\x1b[2;4r\x1b[2;3Habc");
+        term.process(b"\x1b7\x1b[?6l\x1b[r\x1b[5;1H\x1b[7m\x1b(0qqq\x1b8");
+        let cursor = term.screen().cursor();
+        assert_eq!((cursor.row, cursor.col), (2, 5));
+        assert!(cursor.origin_mode && term.screen().modes().origin_mode);
+        assert!(!cursor.attrs.inverse);
+        term.process(b"q");
+        assert_eq!(term.screen().line(2).text(), "  abcq");
+        assert_eq!(term.screen().line(4).text(), "───");
+
+        // ANSI.SYS save/restore shares the DECSC slot
+        let mut term = Terminal::new(20, 5);
+        term.process(b"\x1b[2;2H\x1b[s\x1b[H\x1b8");
+        let cursor = term.screen().cursor();
+        assert_eq!((cursor.row, cursor.col), (1, 1));
+    }
+
     #[test]
     fn test_terminal_title() {
         let mut term = Terminal::new(80, 24);
@@ -880,4 +2286,799 @@ mod tests {
         assert!(term.take_title_changed());
         assert!(!term.take_title_changed()); // Should be cleared
     }
+
+    #[test]
+    fn test_terminal_notifications() {
+        let mut term = Terminal::new(80, 24);
+        term.process(b"\x1b]9;Build finished\x07");
+        term.process(b"\x1b]777;notify;make;line\tone\x07");
+        term.process(b"\x1b]9;\x07");
+
+        let notifications = term.take_notifications();
+        assert_eq!(notifications.len(), 2);
+        assert_eq!(notifications[0].text(), "Build finished");
+        assert_eq!(notifications[1].text(), "make: line one");
+        assert!(term.take_notifications().is_empty());
+
+        for _ in 0..MAX_PENDING_NOTIFICATIONS * 2 {
+            term.process(b"\x1b]9;spam\x07");
+        }
+        assert_eq!(term.take_notifications().len(), MAX_PENDING_NOTIFICATIONS);
+    }
+
+    #[test]
+    fn test_terminal_pointer_shape() {
+        let mut term = Terminal::new(80, 24);
+        term.process(b"\x1b]22;crosshair\x07");
+        assert_eq!(term.pointer_shape(), Some("crosshair"));
+        term.process(b"\x1b]22;bad shape!\x07");
+        assert_eq!(term.pointer_shape(), Some("crosshair"));
+        term.process(b"\x1b]22;\x07");
+        assert_eq!(term.pointer_shape(), None);
+
+        term.process(b"\x1b]22;wait\x07\x1bc");
+        assert_eq!(term.pointer_shape(), None);
+    }
+
+    #[test]
+    fn test_terminal_hyperlink_ids() {
+        let mut term = Terminal::new(80, 24);
+        term.process(b"\x1b]8;id=1;https://a.example\x1b\\one\x1b]8;;\x1b\\ ");
+        term.process(b"\x1b]8;id=2;https://a.example\x1b\\two\x1b]8;;\x1b\\ ");
+        term.process(b"\x1b]8;foo=bar:id=1;https://a.example\x1b\\3\x1b]8;;\x1b\\");
+        let line = term.screen().line(0);
+        let (one, two, three) = (
+            line.cell(0).hyperlink_id,
+            line.cell(4).hyperlink_id,
+            line.cell(8).hyperlink_id,
+        );
+        assert_ne!(one, 0);
+        assert_ne!(one, two);
+        assert_eq!(one, three);
+        assert_eq!(line.cell(3).hyperlink_id, 0);
+        assert_eq!(term.screen().get_hyperlink(two), Some("https://a.example"));
+    }
+
+    #[test]
+    fn test_terminal_decrqm() {
+        let mut term = Terminal::new(80, 24);
+        term.process(b"\x1b[?2004$p\x1b[?2004h\x1b[?2004$p\x1b[?9999$p");
+        term.process(b"\x1b[?1049h\x1b[?1049$p\x1b[4$p");
+        let responses: Vec<String> = term
+            .take_pending_responses()
+            .into_iter()
+            .map(|response| String::from_utf8(response).unwrap())
+            .collect();
+        assert_eq!(
+            responses,
+            [
+                "\x1b[?2004;2$y",
+                "\x1b[?2004;1$y",
+                "\x1b[?9999;0$y",
+                "\x1b[?1049;1$y",
+                "\x1b[4;2$y",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_terminal_in_band_resize() {
+        let mut term = Terminal::new(80, 24);
+        term.set_cell_pixel_size(10, 20);
+        term.resize(100, 30);
+        assert!(term.take_pending_responses().is_empty());
+
+        term.process(b"\x1b[?2048h");
+        term.resize(120, 40);
+        // Same size again: nothing new to report
+        term.resize(120, 40);
+        term.set_cell_pixel_size(12, 24);
+        term.resize(120, 40);
+        assert_eq!(
+            term.take_pending_responses(),
+            [
+                b"\x1b[48;30;100;600;1000t".to_vec(),
+                b"\x1b[48;40;120;800;1200t".to_vec(),
+                b"\x1b[48;40;120;960;1440t".to_vec(),
+            ]
+        );
+
+        term.process(b"\x1b[?2048$p\x1b[?2048l");
+        term.resize(80, 24);
+        assert_eq!(term.take_pending_responses(), [b"\x1b[?2048;1$y".to_vec()]);
+    }
+
+    #[test]
+    fn test_terminal_xtversion() {
+        let mut term = Terminal::new(80, 24);
+        term.process(b"\x1b[>q\x1b[>0q\x1b[>1q");
+        let expected = format!("\x1bP>|mochi {}\x1b\\", env!("CARGO_PKG_VERSION"));
+        assert_eq!(
+            term.take_pending_responses(),
+            [expected.as_bytes(), expected.as_bytes()]
+        );
+
+        term.set_identify_as_xterm(true);
+        term.process(b"\x1b[>q");
+        assert_eq!(
+            term.take_pending_responses(),
+            [b"\x1bP>|XTerm(388)\x1b\\".to_vec()]
+        );
+    }
+
+    #[test]
+    fn test_terminal_xtsave_xtrestore() {
+        let mut term = Terminal::new(80, 24);
+        term.process(b"\x1b[?2004h\x1b[?2004;7s\x1b[?2004l\x1b[?7l");
+        assert!(!term.screen().modes().bracketed_paste);
+        term.process(b"\x1b[?2004;7r");
+        assert!(term.screen().modes().bracketed_paste);
+        assert!(term.screen().modes().auto_wrap);
+
+        // Restoring goes through DECSET, so the screen switches too
+        term.process(b"\x1b[?1049s\x1b[?1049h");
+        assert!(term.screen().modes().alternate_screen);
+        term.process(b"\x1b[?1049r");
+        assert!(!term.screen().modes().alternate_screen);
+    }
+
+    #[test]
+    fn test_terminal_tab_accent() {
+        let mut term = Terminal::new(80, 24);
+        term.process(b"\x1b]1337;TabAccent=#ff0000\x07");
+        assert_eq!(term.tab_accent(), Some(Color::Rgb { r: 255, g: 0, b: 0 }));
+        term.process(b"\x1b]1337;TabAccent=no-such-color\x07");
+        assert_eq!(term.tab_accent(), Some(Color::Rgb { r: 255, g: 0, b: 0 }));
+        term.process(b"\x1b]1337;TabAccent=bright-green\x07");
+        assert_eq!(term.tab_accent(), Some(Color::Indexed(10)));
+        term.process(b"\x1b]1337;TabAccent=\x07");
+        assert_eq!(term.tab_accent(), None);
+    }
+
+    #[test]
+    fn test_terminal_progress() {
+        let mut term = Terminal::new(80, 24);
+        term.process(b"\x1b]9;4;1;40\x07");
+        assert_eq!(term.progress(), Some(Progress::Normal(40)));
+        term.process(b"\x1b]9;4;2\x07");
+        assert_eq!(term.progress(), Some(Progress::Error(40)));
+        term.process(b"\x1b]9;4;4;250\x07");
+        assert_eq!(term.progress(), Some(Progress::Paused(100)));
+        term.process(b"\x1b]9;4;9;1\x07");
+        assert_eq!(term.progress(), Some(Progress::Paused(100)));
+        term.process(b"\x1b]9;4;3\x07");
+        assert_eq!(term.progress(), Some(Progress::Indeterminate));
+        term.process(b"\x1b]9;4;1;100\x07");
+        assert_eq!(term.progress(), None);
+
+        term.process(b"\x1b]9;4;1;5\x07\x1b]9;4;0\x07");
+        assert_eq!(term.progress(), None);
+        // An OSC 9 progress is not a notification
+        assert!(term.take_notifications().is_empty());
+        term.process(b"\x1b]9;4;1;5\x07\x1bc");
+        assert_eq!(term.progress(), None);
+    }
+
+    #[test]
+    fn test_terminal_user_vars() {
+        let mut term = TerminalBuilder::new(80, 24)
+            .limits(TerminalLimits {
+                user_vars: 2,
+                ..TerminalLimits::default()
+            })
+            .build();
+        // "prod", "dev\x1b" and "eu-west-1"
+        term.process(b"\x1b]1337;SetUserVar=AWS_PROFILE=cHJvZA==\x07");
+        term.process(b"\x1b]1337;SetUserVar=k8s.context=ZGV2Gw==\x07");
+        term.process(b"\x1b]1337;SetUserVar=REGION=ZXUtd2VzdC0x\x07");
+        let vars: Vec<(&str, &str)> = term
+            .user_vars()
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect();
+        assert_eq!(vars, [("AWS_PROFILE", "prod"), ("k8s.context", "dev")]);
+
+        // Not base64, a bad name, then unsetting
+        term.process(b"\x1b]1337;SetUserVar=AWS_PROFILE=!!\x07");
+        term.process(b"\x1b]1337;SetUserVar=a b=cHJvZA==\x07");
+        assert_eq!(term.user_vars()["AWS_PROFILE"], "prod");
+        term.process(b"\x1b]1337;SetUserVar=AWS_PROFILE=\x07");
+        assert!(!term.user_vars().contains_key("AWS_PROFILE"));
+        assert_eq!(term.user_vars().len(), 1);
+    }
+
+    #[test]
+    fn test_terminal_color_overrides() {
+        let mut term = Terminal::new(80, 24);
+        term.process(b"\x1b]10;#102030\x07\x1b]11;rgb:ff/8/0000\x07");
+        term.process(b"\x1b]4;1;#ff0000\x07\x1b]4;100;#00ff00\x07\x1b]10;?\x07");
+        let overrides = term.color_overrides();
+        assert_eq!(overrides.foreground, Some((0x10, 0x20, 0x30)));
+        assert_eq!(overrides.background, Some((255, 136, 0)));
+        assert_eq!(overrides.palette.len(), 1);
+
+        let mut colors = ColorScheme::dark();
+        overrides.apply(&mut colors);
+        assert_eq!(colors.foreground, "#102030");
+        assert_eq!(colors.ansi[1], "#ff0000");
+        assert_eq!(colors.cursor, ColorScheme::dark().cursor);
+
+        term.process(b"\x1b]110\x07\x1b]104\x07");
+        assert_eq!(term.color_overrides().foreground, None);
+        assert!(term.color_overrides().palette.is_empty());
+        term.process(b"\x1bc");
+        assert_eq!(term.color_overrides(), &ColorOverrides::default());
+    }
+
+    #[test]
+    fn test_terminal_unsupported_graphics() {
+        let mut term = Terminal::new(40, 5);
+        term.process(b"before\x1bP0pS(E)P[100,100]V[200,200]\x1b\\after");
+        assert_eq!(
+            term.take_unsupported_graphics(),
+            Some(UnsupportedGraphics::Regis)
+        );
+        assert_eq!(term.screen().line(0).text().trim_end(), "before");
+        assert_eq!(
+            term.screen().line(1).text().trim_end(),
+            "[ReGIS graphics not supported]"
+        );
+        assert!(term.screen().line(1).cell(0).attrs.inverse);
+        assert_eq!(term.screen().line(2).text().trim_end(), "after");
+
+        // Tek mode swallows everything up to ESC ETX
+        let mut term = Terminal::new(40, 5);
+        term.process(b"\x1b[?38h\x1d\x20\x60hidden\x1b\x0c\x1b\x03shown");
+        assert_eq!(
+            term.take_unsupported_graphics(),
+            Some(UnsupportedGraphics::Tektronix)
+        );
+        assert_eq!(term.take_unsupported_graphics(), None);
+        assert_eq!(term.screen().line(1).text().trim_end(), "shown");
+    }
+
+    #[test]
+    fn test_terminal_xtgettcap() {
+        let mut term = Terminal::new(80, 24);
+        // "TN" and an unknown "zz"
+        term.process(b"\x1bP+q544E;7A7A\x1b\\");
+        let expected = format!(
+            "\x1bP1+r544E={}\x1b\\\x1bP0+r7A7A\x1b\\",
+            terminal_pty::DEFAULT_TERM
+                .bytes()
+                .map(|b| format!("{:02X}", b))
+                .collect::<String>()
+        );
+        assert_eq!(
+            term.take_pending_responses().concat(),
+            expected.into_bytes()
+        );
+    }
+
+    #[test]
+    fn test_terminal_captured_lines() {
+        let mut term = Terminal::new(80, 24);
+        term.process(b"ignored\r\n");
+        assert!(term.take_captured_lines().is_empty());
+
+        term.set_capture_lines(true);
+        term.process(b"\x1b[31mERROR\x1b[0m: disk full\r\n\r\nok\n$ ");
+        assert_eq!(term.take_captured_lines(), ["ERROR: disk full", "ok"]);
+        // The unfinished prompt line is not captured
+        assert!(term.take_captured_lines().is_empty());
+    }
+
+    #[test]
+    fn test_terminal_highlights_survive_scrollback() {
+        let mut term = Terminal::new(20, 2);
+        let rules = HighlightRules::new(&[crate::config::HighlightRule {
+            pattern: "ERROR".to_string(),
+            foreground: Some("red".to_string()),
+            background: None,
+            whole_line: false,
+        }])
+        .unwrap();
+        term.set_highlight_rules(rules);
+        term.process(b"\x1b[1mx ERROR\x1b[0m\r\nok\r\nmore\r\n");
+
+        let screen = term.screen();
+        let line = screen.scrollback().get(0).unwrap();
+        let highlight = line.highlight_at(2).unwrap();
+        assert_eq!((highlight.start, highlight.end), (2, 6));
+        assert_eq!(highlight.fg, Some(Color::Indexed(1)));
+        // SGR attributes are kept underneath
+        assert!(line.cell(2).attrs.bold);
+        assert!(screen.scrollback().get(1).unwrap().highlights.is_empty());
+    }
+
+    #[test]
+    fn test_terminal_prompt_marks() {
+        let mut term = Terminal::new(80, 24);
+        term.process(b"\x1b]133;A\x07$ ls\r\n\x1b]133;C\x07out\r\n\x1b]133;A\x07$ ");
+        let screen = term.screen();
+        let marked: Vec<bool> = (0..3).map(|row| screen.line(row).meta.marked).collect();
+        assert_eq!(marked, [true, false, true]);
+    }
+
+    #[test]
+    fn test_terminal_semantic_zones() {
+        let mut term = Terminal::new(80, 24);
+        term.process(
+            b"]133;A$ ]133;Bls
+]133;Ca
+b
+",
+        );
+        term.process(b"]133;D;0]133;A$ ");
+        let zones: Vec<_> = (0..5)
+            .map(|row| {
+                let zone = term.screen().line(row).meta.zone;
+                zone.map(|zone| (zone.id, zone.kind))
+            })
+            .collect();
+        assert_eq!(
+            zones,
+            [
+                Some((1, ZoneKind::Prompt)),
+                Some((1, ZoneKind::Output)),
+                Some((1, ZoneKind::Output)),
+                Some((2, ZoneKind::Prompt)),
+                None,
+            ]
+        );
+
+        // Zones are kept in snapshots of the scrollback
+        term.process(&b"\r\n".repeat(30));
+        let snapshot = term.screen().snapshot(true);
+        let scrollback = snapshot.scrollback.unwrap();
+        assert_eq!(
+            scrollback[1].meta.zone.map(|zone| zone.kind),
+            Some(ZoneKind::Output)
+        );
+        assert!(scrollback[3].meta.marked);
+    }
+
+    #[test]
+    fn test_terminal_inline_image() {
+        let mut term = Terminal::new(80, 24);
+        term.set_cell_pixel_size(1, 1);
+        // 2x1 PNG displayed 4 cells wide (2 rows at 2:1)
+        term.process(b"\x1b]1337;File=inline=1;width=4:iVBORw0KGgoAAAANSUhEUgAAAAIAAAABCAIAAAB7QOjdAAAADUlEQVR4nGP4zwAE/wEHAAH/4iOeWQAAAABJRU5ErkJggg==\x07");
+
+        let placements = term.screen().images().placements();
+        assert_eq!(placements.len(), 1);
+        assert_eq!((placements[0].col, placements[0].row), (0, 0));
+        assert_eq!((placements[0].cols, placements[0].rows), (4, 2));
+        assert_eq!(placements[0].image.width, 2);
+
+        // Cursor ends up after the image on its last row
+        assert_eq!(term.screen().cursor().col, 4);
+        assert_eq!(term.screen().cursor().row, 1);
+    }
+
+    #[test]
+    fn test_terminal_inline_image_ignores_downloads() {
+        let mut term = Terminal::new(80, 24);
+        term.process(b"\x1b]1337;File=name=Zm9v:aGVsbG8=\x07");
+        assert!(term.screen().images().is_empty());
+    }
+
+    #[test]
+    fn test_terminal_builder() {
+        const IMAGE: &[u8] = b"\x1b]1337;File=inline=1;width=4:iVBORw0KGgoAAAANSUhEUgAAAAIAAAABCAIAAAB7QOjdAAAADUlEQVR4nGP4zwAE/wEHAAH/4iOeWQAAAABJRU5ErkJggg==\x07";
+
+        let mut term = Terminal::new(80, 24);
+        term.process(b"\x1b]52;c;aGk=\x07");
+        assert!(term.take_clipboard_writes().is_empty());
+
+        let mut term = TerminalBuilder::new(80, 24)
+            .device_attributes("62;22")
+            .term_name("mochi")
+            .osc_policies(OscPolicies {
+                clipboard_write: OscPolicy::Allow,
+                images: OscPolicy::Deny,
+                ..OscPolicies::default()
+            })
+            .build();
+        term.process(b"\x1b[?c\x1bP+q544E\x1b\\");
+        assert_eq!(
+            term.take_pending_responses(),
+            [
+                b"\x1b[?62;22c".to_vec(),
+                b"\x1bP1+r544E=6D6F636869\x1b\\".to_vec()
+            ]
+        );
+        // Reads are refused and p targets the primary selection
+        term.process(b"\x1b]52;c;?\x07\x1b]52;c;aGk=\x07\x1b]52;p;aGk=\x07");
+        assert_eq!(
+            term.take_clipboard_writes(),
+            [
+                ClipboardWrite {
+                    primary: false,
+                    text: "hi".to_string()
+                },
+                ClipboardWrite {
+                    primary: true,
+                    text: "hi".to_string()
+                },
+            ]
+        );
+        term.process(IMAGE);
+        assert!(term.screen().images().is_empty());
+
+        let limits = TerminalLimits {
+            clipboard_len: 3,
+            ..TerminalLimits::default()
+        };
+        let mut term = TerminalBuilder::new(80, 24)
+            .answer_queries(false)
+            .osc_policies(OscPolicies {
+                clipboard_write: OscPolicy::Allow,
+                ..OscPolicies::default()
+            })
+            .limits(limits)
+            .build();
+        term.process(b"\x1b[6n\x1b]52;c;aGk=\x07");
+        assert!(term.take_pending_responses().is_empty());
+        assert!(term.take_clipboard_writes().is_empty());
+    }
+
+    #[test]
+    fn test_terminal_osc_policies() {
+        let policies = OscPolicies {
+            title: OscPolicy::Prompt,
+            colors: OscPolicy::Deny,
+            clipboard_read: OscPolicy::Allow,
+            ..OscPolicies::default()
+        };
+        let mut term = TerminalBuilder::new(80, 24).osc_policies(policies).build();
+        term.process(b"\x1b]10;#ff0000\x07\x1b]52;c;aGk=\x07");
+        assert_eq!(term.color_overrides().foreground, None);
+        assert!(term.take_clipboard_writes().is_empty());
+
+        // Reads are answered by the application with the clipboard's text
+        term.process(b"\x1b]52;;?\x07");
+        let reads = term.take_clipboard_reads();
+        assert_eq!(reads.len(), 1);
+        assert!(!reads[0].primary);
+        assert_eq!(reads[0].reply("hi"), b"\x1b]52;c;aGk=\x1b\\");
+
+        // Titles wait for the answer, which is kept
+        term.process(b"\x1b]2;One\x07\x1b]2;Two\x07");
+        assert!(term.take_osc_prompt_raised());
+        assert_eq!(term.osc_prompt(), Some(OscFeature::Title));
+        assert_eq!(term.title(), "");
+        term.answer_osc_prompt(OscFeature::Title, true);
+        assert_eq!(term.osc_prompt(), None);
+        assert_eq!(term.title(), "Two");
+        term.process(b"\x1b]2;Three\x07");
+        assert!(!term.take_osc_prompt_raised());
+        assert_eq!(term.title(), "Three");
+
+        term.set_osc_policies(
+            OscPolicies {
+                title: OscPolicy::Prompt,
+                ..OscPolicies::default()
+            },
+            MAX_CLIPBOARD_LEN,
+        );
+        term.process(b"\x1b]2;Four\x07");
+        assert_eq!(term.title(), "Four");
+        term.answer_osc_prompt(OscFeature::Title, false);
+        term.process(b"\x1b]2;Five\x07");
+        assert_eq!(term.title(), "Four");
+        term.process(b"\x1b]10;#ff0000\x07");
+        assert!(term.color_overrides().foreground.is_some());
+    }
+
+    #[test]
+    fn test_terminal_sgr_stack() {
+        let mut term = Terminal::new(20, 5);
+        term.process(b"\x1b[1;31m\x1b[#{\x1b[0;3;44ma\x1b[#}b");
+        let line = term.screen().line(0);
+        assert!(line.cell(0).attrs.italic && !line.cell(0).attrs.bold);
+        let attrs = line.cell(1).attrs;
+        assert!(attrs.bold && !attrs.italic);
+        assert_eq!((attrs.fg, attrs.bg), (Color::Indexed(1), Color::Default));
+
+        // The aliases, with only the background saved
+        term.process(b"\x1b[42m\x1b[31#p\x1b[0m\x1b[#q");
+        let attrs = term.screen().cursor().attrs;
+        assert!(!attrs.bold);
+        assert_eq!((attrs.fg, attrs.bg), (Color::Default, Color::Indexed(2)));
+        assert_eq!(term.stats().total(), 0);
+    }
+
+    #[test]
+    fn test_terminal_title_stack() {
+        let mut term = TerminalBuilder::new(80, 24)
+            .limits(TerminalLimits {
+                title_stack: 2,
+                ..TerminalLimits::default()
+            })
+            .build();
+        term.process(b"\x1b]0;shell\x07\x1b[22;0t\x1b]0;vim\x07");
+        assert!(term.take_title_changed());
+        term.process(b"\x1b[23;0t");
+        assert!(term.take_title_changed());
+        assert_eq!((term.title(), term.tab_title()), ("shell", "shell"));
+
+        // Only the parts named are saved and restored
+        term.process(b"\x1b[22;2t\x1b]0;top\x07\x1b[23;0t");
+        assert_eq!((term.title(), term.tab_title()), ("shell", "top"));
+        term.process(b"\x1b[22t\x1b]0;less\x07\x1b[23;1t");
+        assert_eq!((term.title(), term.tab_title()), ("less", "top"));
+
+        // The oldest entry goes past the limit; popping an empty stack
+        // changes nothing
+        for title in ["a", "b", "c"] {
+            term.process(format!("\x1b]2;{}\x07\x1b[22;2t", title).as_bytes());
+        }
+        term.process(b"\x1b]2;d\x07\x1b[23;2t\x1b[23;2t");
+        assert_eq!(term.title(), "b");
+        term.take_title_changed();
+        term.process(b"\x1b[23;2t");
+        assert!(!term.take_title_changed());
+        assert_eq!(term.title(), "b");
+        assert_eq!(term.stats().total(), 0);
+    }
+
+    #[test]
+    fn test_terminal_tab_title_prefers_icon_name() {
+        let mut term = Terminal::new(80, 24);
+        term.process(b"\x1b]2;Window\x07");
+        assert_eq!(term.tab_title(), "Window");
+
+        term.process(b"\x1b]1;Icon\x07");
+        assert!(term.take_title_changed());
+        assert_eq!(term.title(), "Window");
+        assert_eq!(term.tab_title(), "Icon");
+
+        term.process(b"\x1b]0;Both\x07");
+        assert_eq!(term.title(), "Both");
+        assert_eq!(term.tab_title(), "Both");
+    }
+
+    #[test]
+    fn test_terminal_osc7_current_directory() {
+        let mut term = Terminal::new(80, 24);
+        assert!(term.current_directory().is_none());
+
+        term.process(b"\x1b]7;file://host/home/user/my%20dir\x07");
+        assert_eq!(
+            term.current_directory(),
+            Some(Path::new("/home/user/my dir"))
+        );
+
+        // Non-file URIs are ignored
+        term.process(b"\x1b]7;https://example.com/\x07");
+        assert_eq!(
+            term.current_directory(),
+            Some(Path::new("/home/user/my dir"))
+        );
+    }
+
+    #[test]
+    fn test_terminal_alternate_scroll_mode() {
+        let mut term = Terminal::new(80, 24);
+        assert!(!term.screen().modes().alternate_scroll);
+
+        term.process(b"\x1b[?1007h");
+        assert!(term.screen().modes().alternate_scroll);
+        term.process(b"\x1b[?1007l");
+        assert!(!term.screen().modes().alternate_scroll);
+
+        // The configured default survives a full reset
+        let mut term = TerminalBuilder::new(80, 24).alternate_scroll(true).build();
+        assert!(term.screen().modes().alternate_scroll);
+        term.process(b"\x1b[?1007l\x1bc");
+        assert!(term.screen().modes().alternate_scroll);
+    }
+
+    #[test]
+    fn test_terminal_enq_answerback() {
+        let mut term = Terminal::new(80, 24);
+        term.process(b"\x05");
+        assert!(term.take_pending_responses().is_empty());
+
+        let mut term = TerminalBuilder::new(80, 24).answerback("mochi").build();
+        term.process(b"a\x05b");
+        assert_eq!(term.take_pending_responses(), vec![b"mochi".to_vec()]);
+        assert_eq!(term.screen().line(0).text(), "ab");
+
+        term.set_answerback("");
+        term.process(b"\x05");
+        assert!(term.take_pending_responses().is_empty());
+    }
+
+    #[test]
+    fn test_terminal_tmux_passthrough() {
+        let mut term = Terminal::new(80, 24);
+        term.process(b"\x1bPtmux;\x1b\x1b]2;wrapped\x1b\x1b\\\x1b\\ok");
+        assert_eq!(term.title(), "wrapped");
+        assert_eq!(term.screen().line(0).text(), "ok");
+
+        let mut term = TerminalBuilder::new(80, 24).tmux_passthrough(false).build();
+        term.process(b"\x1bPtmux;\x1b\x1b]2;wrapped\x1b\x1b\\\x1b\\ok");
+        assert_eq!(term.title(), "");
+        assert_eq!(term.screen().line(0).text(), "ok");
+        term.set_tmux_passthrough(true);
+        term.process(b"\x1bPtmux;\x1b\x1b]2;later\x07\x1b\\");
+        assert_eq!(term.title(), "later");
+    }
+
+    #[test]
+    fn test_terminal_modify_other_keys() {
+        let mut term = Terminal::new(80, 24);
+        term.process(b"\x1b[>4;2m");
+        assert_eq!(term.screen().modes().modify_other_keys, 2);
+        // Not SGR 4;2 (underline, faint)
+        let attrs = term.screen().cursor().attrs;
+        assert!(!attrs.underline && !attrs.faint);
+
+        term.process(b"\x1b[>4;1m");
+        assert_eq!(term.screen().modes().modify_other_keys, 1);
+        term.process(b"\x1b[>4m");
+        assert_eq!(term.screen().modes().modify_other_keys, 0);
+        term.process(b"\x1b[>4;2m\x1b[>4n");
+        assert_eq!(term.screen().modes().modify_other_keys, 0);
+        term.process(b"\x1b[>4;2m\x1bc");
+        assert_eq!(term.screen().modes().modify_other_keys, 0);
+    }
+
+    #[test]
+    fn test_terminal_rep() {
+        let mut term = Terminal::new(20, 5);
+        term.process(b"ab\x1b[3b-\x1b[b");
+        assert_eq!(term.screen().line(0).text(), "abbbb--");
+
+        // Repeats the character through the active charset
+        term.process(b"\r\n\x1b(0q\x1b[4b\x1b(B");
+        assert_eq!(term.screen().line(1).text(), "\u{2500}".repeat(5));
+    }
+
+    #[test]
+    fn test_terminal_tab_navigation() {
+        let mut term = Terminal::new(80, 24);
+        term.process(b"\x1b[2I");
+        assert_eq!(term.screen().cursor().col, 16);
+        term.process(b"\x1b[I");
+        assert_eq!(term.screen().cursor().col, 24);
+        term.process(b"\x1b[Z");
+        assert_eq!(term.screen().cursor().col, 16);
+        term.process(b"\x1b[5Z");
+        assert_eq!(term.screen().cursor().col, 0);
+    }
+
+    #[test]
+    fn test_terminal_hpa_hpr_vpr() {
+        let mut term = Terminal::new(80, 24);
+        term.process(b"\x1b[10`");
+        assert_eq!(term.screen().cursor().col, 9);
+        term.process(b"\x1b[5a");
+        assert_eq!(term.screen().cursor().col, 14);
+        term.process(b"\x1b[3e");
+        assert_eq!(term.screen().cursor().row, 3);
+    }
+
+    #[test]
+    fn test_terminal_tab_and_rep_golden() {
+        let mut term = Terminal::new(20, 3);
+        term.process(b"A\x1b[IB\x1b[b\x1b[ZC\x1b[20`D");
+        assert_eq!(term.screen().line(0).text(), "A       CB         D");
+    }
+
+    #[test]
+    fn test_terminal_decst8c() {
+        let mut term = Terminal::new(40, 5);
+        term.process(b"\x1b[3g\x1b[5G\x1bH\r");
+        assert_eq!(term.screen().tab_stops().positions(), vec![4]);
+
+        term.process(b"\x1b[?5W");
+        assert_eq!(
+            term.screen().tab_stops().positions(),
+            vec![0, 8, 16, 24, 32]
+        );
+        term.process(b"\t");
+        assert_eq!(term.screen().cursor().col, 8);
+    }
+
+    /// Streams that dirty different parts of the terminal state
+    const RESET_MATRIX: &[&[u8]] = &[
+        b"hello\x1b[1;31mred\x1b[5;10r\x1b[?6h",
+        b"\x1b[?1049h\x1b[?25l\x1b[?7l\x1b[4h\x1b[?1h",
+        b"\x1b[?1000h\x1b[?1006h\x1b[?2004h\x1b[?1004h",
+        b"\x1b[3g\x1b[5G\x1bH\x1b(0qq\x0e",
+        b"\x1b[10;10H\x1b7\x1b[2;2H\x1b]2;title\x07",
+        b"\x1b[6n\x1b[c\x1b[2 q",
+    ];
+
+    #[test]
+    fn test_terminal_full_reset_matrix() {
+        let fresh = Terminal::new(40, 10).snapshot().to_json().unwrap();
+
+        for stream in RESET_MATRIX {
+            let mut term = Terminal::new(40, 10);
+            term.process(stream);
+            term.process(b"\x1bc");
+
+            assert_eq!(
+                term.snapshot().to_json().unwrap(),
+                fresh,
+                "RIS after {:?}",
+                String::from_utf8_lossy(stream)
+            );
+            assert!(term.take_pending_responses().is_empty());
+            assert_eq!(*term.screen().modes(), Modes::new());
+            assert_eq!(term.screen().scrollback().len(), 0);
+
+            // Default tab stops and charsets are back
+            term.process(b"\tq\x1b8x");
+            assert_eq!(term.screen().line(0).text(), "x       q");
+        }
+    }
+
+    #[test]
+    fn test_terminal_soft_reset_matrix() {
+        for stream in RESET_MATRIX {
+            let mut term = Terminal::new(40, 10);
+            term.process(stream);
+            let before = term.snapshot();
+            term.process(b"\x1b[!p");
+            let after = term.snapshot();
+
+            let context = String::from_utf8_lossy(stream);
+            // Screen contents and cursor position are untouched
+            assert_eq!(before.screen_text(), after.screen_text(), "{context:?}");
+            assert_eq!(
+                (before.cursor.row, before.cursor.col),
+                (after.cursor.row, after.cursor.col),
+                "{context:?}"
+            );
+            assert_eq!(before.modes.alternate_screen, after.modes.alternate_screen);
+            assert_eq!(before.modes.bracketed_paste, after.modes.bracketed_paste);
+
+            // Modes, margins and attributes are back to their defaults
+            assert_eq!(after.scroll_region, None, "{context:?}");
+            assert!(!after.modes.origin_mode);
+            assert!(!after.modes.insert_mode);
+            assert!(after.modes.auto_wrap);
+            assert!(after.modes.cursor_visible);
+            assert!(term.screen().cursor().visible, "{context:?}");
+            assert!(!term.screen().modes().cursor_keys_application);
+            assert_eq!(term.screen().cursor().attrs, CellAttributes::default());
+        }
+    }
+
+    #[test]
+    fn test_terminal_trace() {
+        use crate::trace::TraceEvent;
+
+        let mut term = Terminal::new(40, 10);
+        term.process(b"before");
+        assert!(term.take_trace().is_empty());
+
+        term.set_trace(true);
+        term.process(b"ab\x1b[1mc\x1b[?9999h\x1b[?25l\x1b[99h\x1b]999;x\x07");
+        assert_eq!(
+            term.take_trace(),
+            [
+                TraceEvent::Text("ab".to_string()),
+                TraceEvent::Action("CSI 1 m".to_string()),
+                TraceEvent::Text("c".to_string()),
+                TraceEvent::Action("CSI ? 9999 h".to_string()),
+                TraceEvent::Unhandled("CSI ? 9999 h".to_string()),
+                TraceEvent::Action("CSI ? 25 l".to_string()),
+                TraceEvent::Action("CSI 99 h".to_string()),
+                TraceEvent::Unhandled("CSI 99 h".to_string()),
+                TraceEvent::Action("OSC Unknown { command: 999, data: \"x\" }".to_string()),
+                TraceEvent::Unhandled("OSC Unknown { command: 999, data: \"x\" }".to_string()),
+            ]
+        );
+        assert!(term.take_trace().is_empty());
+
+        term.set_trace(false);
+        term.process(b"\x1b[?9999h");
+        assert!(term.take_trace().is_empty());
+    }
 }
@@ -0,0 +1,243 @@
+//! Terminfo entry for `TERM=mochi`
+//!
+//! `mochi --emit-terminfo` prints the entry's source, which `tic` compiles
+//! and installs (`mochi --emit-terminfo | tic -x -` puts it in
+//! `~/.terminfo`). It lists what mochi implements, rather than borrowing
+//! xterm's entry, plus the extensions also answered to XTGETTCAP.
+//!
+//! A TERM whose entry is not installed falls back to `DEFAULT_TERM`, so
+//! programs never run with a TERM they cannot look up; remote hosts get
+//! `[ssh] term` through the shell integration.
+
+use std::env;
+use std::path::{Path, PathBuf};
+
+/// Name of the entry
+pub const NAME: &str = "mochi";
+
+/// A capability of the entry; strings are the bytes sent, not escaped
+enum Capability {
+    Flag(&'static str),
+    Number(&'static str, u32),
+    Text(&'static str, &'static str),
+}
+
+use Capability::{Flag, Number, Text};
+
+#[rustfmt::skip]
+const CAPABILITIES: &[Capability] = &[
+    // Auto margins with the xterm last-column wrap, background color erase,
+    // redefinable colors, safe movement in insert and standout modes
+    Flag("am"), Flag("bce"), Flag("ccc"), Flag("km"), Flag("mir"), Flag("msgr"),
+    Flag("xenl"),
+    Number("colors", 256), Number("cols", 80), Number("it", 8), Number("lines", 24),
+    Number("pairs", 0x10000),
+    // Cursor movement
+    Text("cr", "\r"), Text("cub", "\x1b[%p1%dD"), Text("cub1", "\x08"),
+    Text("cud", "\x1b[%p1%dB"), Text("cud1", "\n"), Text("cuf", "\x1b[%p1%dC"),
+    Text("cuf1", "\x1b[C"), Text("cup", "\x1b[%i%p1%d;%p2%dH"), Text("cuu", "\x1b[%p1%dA"),
+    Text("cuu1", "\x1b[A"), Text("home", "\x1b[H"), Text("hpa", "\x1b[%i%p1%dG"),
+    Text("vpa", "\x1b[%i%p1%dd"), Text("sc", "\x1b7"), Text("rc", "\x1b8"),
+    Text("ind", "\n"), Text("indn", "\x1b[%p1%dS"), Text("ri", "\x1bM"),
+    Text("rin", "\x1b[%p1%dT"), Text("nel", "\x1bE"), Text("csr", "\x1b[%i%p1%d;%p2%dr"),
+    Text("u6", "\x1b[%i%d;%dR"), Text("u7", "\x1b[6n"),
+    Text("u8", "\x1b[?%[;0123456789]c"), Text("u9", "\x1b[c"),
+    // Tabs
+    Text("ht", "\t"), Text("cbt", "\x1b[Z"), Text("hts", "\x1bH"), Text("tbc", "\x1b[3g"),
+    // Editing
+    Text("clear", "\x1b[H\x1b[2J"), Text("E3", "\x1b[3J"), Text("ed", "\x1b[J"),
+    Text("el", "\x1b[K"), Text("el1", "\x1b[1K"), Text("ech", "\x1b[%p1%dX"),
+    Text("dch", "\x1b[%p1%dP"), Text("dch1", "\x1b[P"), Text("dl", "\x1b[%p1%dM"),
+    Text("dl1", "\x1b[M"), Text("ich", "\x1b[%p1%d@"), Text("il", "\x1b[%p1%dL"),
+    Text("il1", "\x1b[L"), Text("rep", "%p1%c\x1b[%p2%{1}%-%db"),
+    Text("smir", "\x1b[4h"), Text("rmir", "\x1b[4l"),
+    // Attributes and colors
+    Text("sgr0", "\x1b(B\x1b[m"), Text("bold", "\x1b[1m"), Text("dim", "\x1b[2m"),
+    Text("sitm", "\x1b[3m"), Text("ritm", "\x1b[23m"), Text("smul", "\x1b[4m"),
+    Text("rmul", "\x1b[24m"), Text("blink", "\x1b[5m"), Text("rev", "\x1b[7m"),
+    Text("smso", "\x1b[7m"), Text("rmso", "\x1b[27m"), Text("invis", "\x1b[8m"),
+    Text("smxx", "\x1b[9m"), Text("rmxx", "\x1b[29m"), Text("op", "\x1b[39;49m"),
+    Text("setaf", "\x1b[%?%p1%{8}%<%t3%p1%d%e%p1%{16}%<%t9%p1%{8}%-%d%e38;5;%p1%d%;m"),
+    Text("setab", "\x1b[%?%p1%{8}%<%t4%p1%d%e%p1%{16}%<%t10%p1%{8}%-%d%e48;5;%p1%d%;m"),
+    Text("initc", "\x1b]4;%p1%d;rgb:%p2%{255}%*%{1000}%/%2.2X/%p3%{255}%*%{1000}%/%2.2X/%p4%{255}%*%{1000}%/%2.2X\x1b\\"),
+    Text("oc", "\x1b]104\x07"),
+    Text("acsc", "``aaffggiijjkkllmmnnooppqqrrssttuuvvwwxxyyzz{{||}}~~"),
+    Text("smacs", "\x1b(0"), Text("rmacs", "\x1b(B"),
+    // Modes
+    Text("smam", "\x1b[?7h"), Text("rmam", "\x1b[?7l"), Text("smkx", "\x1b[?1h\x1b="),
+    Text("rmkx", "\x1b[?1l\x1b>"), Text("smcup", "\x1b[?1049h"), Text("rmcup", "\x1b[?1049l"),
+    Text("civis", "\x1b[?25l"), Text("cnorm", "\x1b[?12l\x1b[?25h"),
+    Text("cvvis", "\x1b[?12;25h"), Text("bel", "\x07"), Text("flash", "\x1b[?5h$<100/>\x1b[?5l"),
+    Text("rs1", "\x1bc"),
+    // Keys
+    Text("kbs", "\x7f"), Text("kcuu1", "\x1bOA"), Text("kcud1", "\x1bOB"),
+    Text("kcuf1", "\x1bOC"), Text("kcub1", "\x1bOD"), Text("khome", "\x1bOH"),
+    Text("kend", "\x1bOF"), Text("kich1", "\x1b[2~"), Text("kdch1", "\x1b[3~"),
+    Text("kpp", "\x1b[5~"), Text("knp", "\x1b[6~"), Text("kcbt", "\x1b[Z"),
+    Text("kf1", "\x1bOP"), Text("kf2", "\x1bOQ"), Text("kf3", "\x1bOR"), Text("kf4", "\x1bOS"),
+    Text("kf5", "\x1b[15~"), Text("kf6", "\x1b[17~"), Text("kf7", "\x1b[18~"),
+    Text("kf8", "\x1b[19~"), Text("kf9", "\x1b[20~"), Text("kf10", "\x1b[21~"),
+    Text("kf11", "\x1b[23~"), Text("kf12", "\x1b[24~"), Text("kmous", "\x1b[<"),
+    // Extensions (tic -x): SGR mouse, bracketed paste, focus events,
+    // truecolor, cursor shape, OSC 52 and synchronized output
+    Text("XM", "\x1b[?1006;1000%?%p1%{1}%=%th%el%;"),
+    Text("xm", "\x1b[<%i%p3%d;%p1%d;%p2%d;%?%p4%tM%em%;"),
+    Text("BE", "\x1b[?2004h"), Text("BD", "\x1b[?2004l"), Text("PS", "\x1b[200~"),
+    Text("PE", "\x1b[201~"), Text("fe", "\x1b[?1004h"), Text("fd", "\x1b[?1004l"),
+    Text("kxIN", "\x1b[I"), Text("kxOUT", "\x1b[O"),
+    Flag("Tc"), Number("RGB", 8),
+    Text("setrgbf", "\x1b[38;2;%p1%d;%p2%d;%p3%dm"),
+    Text("setrgbb", "\x1b[48;2;%p1%d;%p2%d;%p3%dm"),
+    Text("Ss", "\x1b[%p1%d q"), Text("Se", "\x1b[2 q"), Text("Ms", "\x1b]52;%p1%s;%p2%s\x07"),
+    Text("Sync", "\x1b[?2026%?%p1%{1}%-%tl%eh%;"),
+];
+
+/// Source of the entry, for `tic -x`
+pub fn source() -> String {
+    let mut source = format!("{}|mochi terminal emulator,\n", NAME);
+    for capability in CAPABILITIES {
+        let field = match capability {
+            Flag(name) => name.to_string(),
+            Number(name, value) => format!("{}#{}", name, value),
+            Text(name, value) => format!("{}={}", name, escape(value)),
+        };
+        source.push('\t');
+        source.push_str(&field);
+        source.push_str(",\n");
+    }
+    source
+}
+
+/// Write a string capability the way terminfo sources spell it
+fn escape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\x1b' => out.push_str("\\E"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\x7f' => out.push_str("^?"),
+            '\\' | ',' | '^' => {
+                out.push('\\');
+                out.push(c);
+            }
+            c if c.is_ascii_control() => {
+                out.push('^');
+                out.push((c as u8 + b'@') as char);
+            }
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Directories searched for compiled entries, in the order of ncurses
+fn search_dirs() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    if let Some(dir) = env::var_os("TERMINFO") {
+        dirs.push(PathBuf::from(dir));
+    }
+    if let Some(home) = env::var_os("HOME") {
+        dirs.push(Path::new(&home).join(".terminfo"));
+    }
+    let defaults = [
+        "/etc/terminfo",
+        "/lib/terminfo",
+        "/usr/share/terminfo",
+        "/usr/lib/terminfo",
+        "/usr/local/share/terminfo",
+    ];
+    match env::var("TERMINFO_DIRS") {
+        // An empty element stands for the default directories
+        Ok(list) => {
+            for dir in list.split(':') {
+                if dir.is_empty() {
+                    dirs.extend(defaults.iter().map(PathBuf::from));
+                } else {
+                    dirs.push(PathBuf::from(dir));
+                }
+            }
+        }
+        Err(_) => dirs.extend(defaults.iter().map(PathBuf::from)),
+    }
+    dirs
+}
+
+/// Whether a compiled entry for `term` is in one of `dirs`, under its first
+/// letter (Linux) or that letter's hex code (macOS)
+fn is_installed_in(term: &str, dirs: &[PathBuf]) -> bool {
+    let Some(first) = term.chars().next() else {
+        return false;
+    };
+    let subdirs = [first.to_string(), format!("{:x}", first as u32)];
+    dirs.iter()
+        .any(|dir| subdirs.iter().any(|sub| dir.join(sub).join(term).is_file()))
+}
+
+/// Whether programs on this host can look up `term`
+pub fn is_installed(term: &str) -> bool {
+    is_installed_in(term, &search_dirs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::termcap;
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("\x1b[?1049h"), "\\E[?1049h");
+        assert_eq!(escape("\x07\x08\x7f"), "^G^H^?");
+        assert_eq!(escape("a,b^c\\"), "a\\,b\\^c\\\\");
+    }
+
+    #[test]
+    fn test_source() {
+        let source = source();
+        assert!(source.starts_with("mochi|mochi terminal emulator,\n"));
+        assert!(source.contains("\tcolors#256,\n"));
+        assert!(source.contains("\tcup=\\E[%i%p1%d;%p2%dH,\n"));
+        assert!(source.contains("\tTc,\n"));
+        // One capability per line, each only once
+        let names: Vec<&str> = source
+            .lines()
+            .skip(1)
+            .map(|line| line.trim().split(['=', '#', ',']).next().unwrap())
+            .collect();
+        let mut unique = names.clone();
+        unique.sort_unstable();
+        unique.dedup();
+        assert_eq!(unique.len(), names.len());
+    }
+
+    #[test]
+    fn test_matches_xtgettcap() {
+        // Capabilities answered to XTGETTCAP have the same values here
+        for capability in CAPABILITIES {
+            let (name, value) = match capability {
+                Flag(name) => (name, None),
+                Number(name, value) => (name, Some(value.to_string())),
+                Text(name, value) => (name, Some(value.to_string())),
+            };
+            if let Some(answer) = termcap::lookup(name) {
+                assert_eq!(answer.map(str::to_string), value, "{}", name);
+            }
+        }
+    }
+
+    #[test]
+    fn test_is_installed_in() {
+        let dir = std::env::temp_dir().join(format!("mochi-terminfo-test-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("m")).unwrap();
+        std::fs::write(dir.join("m").join("mochi"), b"").unwrap();
+        std::fs::create_dir_all(dir.join("78")).unwrap();
+        std::fs::write(dir.join("78").join("xterm-mochi"), b"").unwrap();
+        let dirs = [PathBuf::from("/nonexistent"), dir.clone()];
+        assert!(is_installed_in("mochi", &dirs));
+        assert!(is_installed_in("xterm-mochi", &dirs));
+        assert!(!is_installed_in("mochi-direct", &dirs));
+        assert!(!is_installed_in("", &dirs));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,150 @@
+//! User theme files
+//!
+//! A theme file is a TOML color scheme (foreground, background, cursor,
+//! selection and the 16 ANSI colors) stored as `<name>.toml` in the themes
+//! directory, `~/.config/mochi/themes` by default. Setting `theme = "<name>"`
+//! selects it; the file is read again whenever the configuration is reloaded.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::{ColorScheme, ConfigError};
+
+/// Get the directory user themes are loaded from
+pub fn themes_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|p| p.join("mochi").join("themes"))
+}
+
+/// Load a user theme by name from the themes directory
+pub fn load(name: &str) -> Result<ColorScheme, ConfigError> {
+    let dir = themes_dir().ok_or_else(|| theme_error(name, "no config directory"))?;
+    load_from(&dir, name)
+}
+
+/// Load a user theme by name from a directory
+pub fn load_from(dir: &Path, name: &str) -> Result<ColorScheme, ConfigError> {
+    if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+        return Err(theme_error(name, "not a valid theme name"));
+    }
+    let path = dir.join(format!("{}.toml", name));
+    let content = fs::read_to_string(&path)
+        .map_err(|e| theme_error(name, &format!("cannot read {}: {}", path.display(), e)))?;
+    let colors: ColorScheme = toml::from_str(&content)
+        .map_err(|e| theme_error(name, &format!("invalid theme file: {}", e)))?;
+
+    let named = [
+        ("foreground", &colors.foreground),
+        ("background", &colors.background),
+        ("cursor", &colors.cursor),
+        ("selection", &colors.selection),
+    ];
+    for (field, color) in named {
+        check_color(name, field, color)?;
+    }
+    for (i, color) in colors.ansi.iter().enumerate() {
+        check_color(name, &format!("ansi[{}]", i), color)?;
+    }
+    Ok(colors)
+}
+
+/// List the names of the user themes in the themes directory
+pub fn list() -> Vec<String> {
+    themes_dir().map(|dir| list_in(&dir)).unwrap_or_default()
+}
+
+/// List the names of the theme files in a directory, sorted
+pub fn list_in(dir: &Path) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+        .filter_map(|path| Some(path.file_stem()?.to_str()?.to_string()))
+        .filter(|name| !name.starts_with('.'))
+        .collect();
+    names.sort();
+    names
+}
+
+fn check_color(name: &str, field: &str, color: &str) -> Result<(), ConfigError> {
+    match ColorScheme::parse_hex(color) {
+        Some(_) => Ok(()),
+        None => Err(theme_error(
+            name,
+            &format!("invalid hex color '{}' for {}", color, field),
+        )),
+    }
+}
+
+fn theme_error(name: &str, message: &str) -> ConfigError {
+    ConfigError {
+        message: format!("Theme '{}': {}", name, message),
+        field: Some("theme".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GRUVBOX: &str = r##"
+        foreground = "#ebdbb2"
+        background = "#282828"
+        cursor = "#ebdbb2"
+        selection = "#504945"
+        ansi = [
+            "#282828", "#cc241d", "#98971a", "#d79921",
+            "#458588", "#b16286", "#689d6a", "#a89984",
+            "#928374", "#fb4934", "#b8bb26", "#fabd2f",
+            "#83a598", "#d3869b", "#8ec07c", "#ebdbb2",
+        ]
+    "##;
+
+    fn theme_dir(test: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("mochi-theme-test-{}-{}", test, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_load_theme_file() {
+        let dir = theme_dir("load");
+        fs::write(dir.join("gruvbox.toml"), GRUVBOX).unwrap();
+
+        let colors = load_from(&dir, "gruvbox").unwrap();
+        assert_eq!(colors.background, "#282828");
+        assert_eq!(colors.ansi[9], "#fb4934");
+
+        assert!(load_from(&dir, "missing").is_err());
+        assert!(load_from(&dir, "../gruvbox").is_err());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_load_rejects_bad_colors() {
+        let dir = theme_dir("bad");
+        fs::write(dir.join("bad.toml"), GRUVBOX.replace("#cc241d", "red")).unwrap();
+        fs::write(dir.join("short.toml"), "foreground = \"#ffffff\"").unwrap();
+
+        let err = load_from(&dir, "bad").unwrap_err();
+        assert!(err.message.contains("ansi[1]"));
+        assert_eq!(err.field.as_deref(), Some("theme"));
+        assert!(load_from(&dir, "short").is_err());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_list_themes() {
+        let dir = theme_dir("list");
+        fs::write(dir.join("zenburn.toml"), GRUVBOX).unwrap();
+        fs::write(dir.join("gruvbox.toml"), GRUVBOX).unwrap();
+        fs::write(dir.join("notes.txt"), "").unwrap();
+
+        assert_eq!(list_in(&dir), ["gruvbox", "zenburn"]);
+        assert!(list_in(&dir.join("missing")).is_empty());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,117 @@
+//! Transient on-screen messages
+//!
+//! Toasts are short messages (configuration reloaded, text copied, a bell in
+//! a background tab, notifications sent by programs with OSC 9 / OSC 777)
+//! drawn in the corner of a window until they expire.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// How long a toast stays on screen unless given another duration
+pub const DEFAULT_TTL: Duration = Duration::from_secs(3);
+
+/// How long notifications sent by programs stay on screen
+pub const NOTIFICATION_TTL: Duration = Duration::from_secs(6);
+
+/// Most toasts shown at once; older ones are dropped first
+const MAX_TOASTS: usize = 4;
+
+/// A message and when it disappears
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Toast {
+    pub text: String,
+    pub expires: Instant,
+}
+
+/// Toasts currently on screen, oldest first
+#[derive(Debug, Default)]
+pub struct ToastQueue {
+    toasts: VecDeque<Toast>,
+}
+
+impl ToastQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Show a message for `ttl`
+    ///
+    /// Repeating the newest message extends it instead of stacking a copy.
+    pub fn push(&mut self, text: impl Into<String>, ttl: Duration) {
+        self.push_at(text.into(), ttl, Instant::now());
+    }
+
+    fn push_at(&mut self, text: String, ttl: Duration, now: Instant) {
+        let expires = now + ttl;
+        if let Some(last) = self.toasts.back_mut() {
+            if last.text == text {
+                last.expires = last.expires.max(expires);
+                return;
+            }
+        }
+        if self.toasts.len() == MAX_TOASTS {
+            self.toasts.pop_front();
+        }
+        self.toasts.push_back(Toast { text, expires });
+    }
+
+    /// Drop expired toasts; returns true if any were removed
+    pub fn expire(&mut self, now: Instant) -> bool {
+        let before = self.toasts.len();
+        self.toasts.retain(|toast| toast.expires > now);
+        self.toasts.len() != before
+    }
+
+    /// When the next toast expires
+    pub fn next_expiry(&self) -> Option<Instant> {
+        self.toasts.iter().map(|toast| toast.expires).min()
+    }
+
+    /// Texts of the toasts on screen, oldest first
+    pub fn texts(&self) -> impl Iterator<Item = &str> {
+        self.toasts.iter().map(|toast| toast.text.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_toasts_expire() {
+        let now = Instant::now();
+        let mut queue = ToastQueue::new();
+        queue.push_at("short".into(), Duration::from_secs(1), now);
+        queue.push_at("long".into(), Duration::from_secs(5), now);
+        assert_eq!(queue.next_expiry(), Some(now + Duration::from_secs(1)));
+
+        assert!(!queue.expire(now));
+        assert!(queue.expire(now + Duration::from_secs(2)));
+        assert_eq!(queue.texts().collect::<Vec<_>>(), ["long"]);
+        assert!(queue.expire(now + Duration::from_secs(5)));
+        assert_eq!(queue.texts().count(), 0);
+        assert_eq!(queue.next_expiry(), None);
+    }
+
+    #[test]
+    fn test_repeated_toast_is_extended() {
+        let now = Instant::now();
+        let mut queue = ToastQueue::new();
+        queue.push_at("Bell in tab 2".into(), DEFAULT_TTL, now);
+        queue.push_at("Bell in tab 2".into(), DEFAULT_TTL, now + DEFAULT_TTL / 2);
+        assert_eq!(queue.texts().count(), 1);
+        assert_eq!(queue.next_expiry(), Some(now + DEFAULT_TTL * 3 / 2));
+    }
+
+    #[test]
+    fn test_oldest_toast_dropped_when_full() {
+        let now = Instant::now();
+        let mut queue = ToastQueue::new();
+        for i in 0..=MAX_TOASTS {
+            queue.push_at(format!("toast {}", i), DEFAULT_TTL, now);
+        }
+        let texts: Vec<_> = queue.texts().collect();
+        assert_eq!(texts.len(), MAX_TOASTS);
+        assert_eq!(texts[0], "toast 1");
+    }
+}
@@ -0,0 +1,214 @@
+//! Trace mode (an annotated log of shell output, for bug reports)
+//!
+//! `mochi --trace <file>` (or Ctrl+Shift+L at runtime) writes one JSON
+//! object per line for every chunk of output read from a shell, followed by
+//! what the parser made of it and the sequences the terminal did not
+//! handle:
+//!
+//! ```text
+//! {"time":0.013204,"pid":4242,"bytes":"\\x1b[?2004h$ "}
+//! {"time":0.013204,"pid":4242,"action":"CSI ? 2004 h"}
+//! {"time":0.013204,"pid":4242,"text":"$ "}
+//! {"time":0.013310,"pid":4242,"unhandled":"CSI ? 9999 h"}
+//! ```
+//!
+//! `time` is in seconds since the trace started and `pid` is the tab's
+//! shell. `bytes` keeps printable ASCII as is and writes other bytes (and
+//! the backslash) as `\xNN`, so [`read_chunks`] gives back the exact stream:
+//! traces in `tests/corpus` are replayed like the `.vt` captures.
+
+use std::env;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use serde_json::{json, Value};
+
+/// What a terminal did with traced output
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TraceEvent {
+    /// Printed characters (one event per run)
+    Text(String),
+    /// Any other action, as the inspector describes it
+    Action(String),
+    /// An action the terminal ignored, or a part of it (e.g. an unknown mode)
+    Unhandled(String),
+}
+
+/// The trace file, shared by all windows
+pub struct Trace {
+    /// Where the trace goes: the --trace file or one in the temp directory
+    path: PathBuf,
+    /// Open file, while tracing
+    writer: Option<BufWriter<File>>,
+    /// Whether the file was written before (later starts append to it)
+    written: bool,
+    started: Instant,
+}
+
+impl Trace {
+    /// A trace to `path` (None = `mochi-trace-<pid>.jsonl` in the temp
+    /// directory), not started yet
+    pub fn new(path: Option<PathBuf>) -> Self {
+        let path = path.unwrap_or_else(|| {
+            env::temp_dir().join(format!("mochi-trace-{}.jsonl", std::process::id()))
+        });
+        Self {
+            path,
+            writer: None,
+            written: false,
+            started: Instant::now(),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.writer.is_some()
+    }
+
+    /// Start tracing; the first start truncates the file
+    pub fn start(&mut self) -> io::Result<()> {
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(self.written)
+            .truncate(!self.written)
+            .open(&self.path)?;
+        self.writer = Some(BufWriter::new(file));
+        self.written = true;
+        Ok(())
+    }
+
+    /// Stop tracing, flushing what is left
+    pub fn stop(&mut self) -> io::Result<()> {
+        match self.writer.take() {
+            Some(mut writer) => writer.flush(),
+            None => Ok(()),
+        }
+    }
+
+    /// Write a chunk of output from the shell `pid` and what came of it
+    pub fn record(&mut self, pid: i32, data: &[u8], events: &[TraceEvent]) -> io::Result<()> {
+        let time = (self.started.elapsed().as_secs_f64() * 1e6).round() / 1e6;
+        let Some(writer) = &mut self.writer else {
+            return Ok(());
+        };
+        let line = |key: &str, value: &str| json!({ "time": time, "pid": pid, key: value });
+        writeln!(writer, "{}", line("bytes", &encode_bytes(data)))?;
+        for event in events {
+            let entry = match event {
+                TraceEvent::Text(text) => line("text", text),
+                TraceEvent::Action(description) => line("action", description),
+                TraceEvent::Unhandled(description) => line("unhandled", description),
+            };
+            writeln!(writer, "{}", entry)?;
+        }
+        writer.flush()
+    }
+}
+
+/// Bytes as text: printable ASCII as is, others as `\xNN`
+pub fn encode_bytes(data: &[u8]) -> String {
+    let mut text = String::with_capacity(data.len());
+    for &byte in data {
+        if (0x20..0x7f).contains(&byte) && byte != b'\\' {
+            text.push(byte as char);
+        } else {
+            text.push_str(&format!("\\x{:02x}", byte));
+        }
+    }
+    text
+}
+
+/// The bytes of `encode_bytes` output, or None if it is not
+#[allow(dead_code)]
+pub fn decode_bytes(text: &str) -> Option<Vec<u8>> {
+    let mut data = Vec::with_capacity(text.len());
+    let mut bytes = text.bytes();
+    while let Some(byte) = bytes.next() {
+        if byte != b'\\' {
+            data.push(byte);
+            continue;
+        }
+        if bytes.next()? != b'x' {
+            return None;
+        }
+        let hex = [bytes.next()?, bytes.next()?];
+        data.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+    }
+    Some(data)
+}
+
+/// The output chunks of a trace, with the shell they came from
+#[allow(dead_code)]
+pub fn read_chunks(trace: &str) -> Result<Vec<(i32, Vec<u8>)>, String> {
+    let mut chunks = Vec::new();
+    for (i, line) in trace.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry: Value =
+            serde_json::from_str(line).map_err(|e| format!("line {}: {}", i + 1, e))?;
+        let Some(bytes) = entry.get("bytes").and_then(Value::as_str) else {
+            continue;
+        };
+        let data = decode_bytes(bytes).ok_or_else(|| format!("line {}: invalid bytes", i + 1))?;
+        let pid = entry.get("pid").and_then(Value::as_i64).unwrap_or(0) as i32;
+        chunks.push((pid, data));
+    }
+    Ok(chunks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_encode_bytes() {
+        let data = b"\x1b[?1049h\\ caf\xc3\xa9\r\n";
+        let text = encode_bytes(data);
+        assert_eq!(text, "\\x1b[?1049h\\x5c caf\\xc3\\xa9\\x0d\\x0a");
+        assert_eq!(decode_bytes(&text).as_deref(), Some(&data[..]));
+        assert_eq!(decode_bytes("\\"), None);
+        assert_eq!(decode_bytes("\\x1"), None);
+        assert_eq!(decode_bytes("\\y00"), None);
+        assert_eq!(decode_bytes("\\xzz"), None);
+    }
+
+    #[test]
+    fn test_trace() {
+        let path = env::temp_dir().join(format!("mochi-trace-test-{}.jsonl", std::process::id()));
+        let mut trace = Trace::new(Some(path.clone()));
+        assert!(!trace.is_active());
+        trace.start().unwrap();
+        let events = [
+            TraceEvent::Action("CSI ? 9999 h".to_string()),
+            TraceEvent::Unhandled("CSI ? 9999 h".to_string()),
+            TraceEvent::Text("$ ".to_string()),
+        ];
+        trace.record(42, b"\x1b[?9999h$ ", &events).unwrap();
+        trace.stop().unwrap();
+        // Not written while stopped, appended to when started again
+        trace.record(42, b"lost", &[]).unwrap();
+        trace.start().unwrap();
+        trace.record(7, b"\xff", &[]).unwrap();
+        trace.stop().unwrap();
+
+        let text = fs::read_to_string(&path).unwrap();
+        assert_eq!(text.lines().count(), 5);
+        let entry: Value = serde_json::from_str(text.lines().nth(2).unwrap()).unwrap();
+        assert_eq!(entry["unhandled"], "CSI ? 9999 h");
+        assert_eq!(entry["pid"], 42);
+        assert_eq!(
+            read_chunks(&text).unwrap(),
+            [(42, b"\x1b[?9999h$ ".to_vec()), (7, vec![0xff])]
+        );
+        assert!(read_chunks("{\"bytes\":\"\\\\q\"}").is_err());
+        let _ = fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,338 @@
+#!/usr/bin/env python3
+"""A small esctest-style conformance suite.
+
+Runs inside the terminal under test (for mochi: `mochi --headless --command
+"python3 vt_subset.py results.log"`), sends escape sequences to its own tty
+and checks the terminal's state through its replies, mostly cursor position
+reports. Expected values follow xterm. Each test writes "PASS name" or
+"FAIL name: details" to the log file given as the only argument.
+"""
+
+import os
+import select
+import sys
+import termios
+import tty
+
+ESC = "\x1b"
+CSI = ESC + "["
+TIMEOUT = 2.0
+
+fd = sys.stdin.fileno()
+
+
+def write(data):
+    os.write(sys.stdout.fileno(), data.encode())
+
+
+def read_reply(terminator):
+    """Read a reply up to and including `terminator`, or None on timeout"""
+    reply = ""
+    while not reply.endswith(terminator):
+        ready, _, _ = select.select([fd], [], [], TIMEOUT)
+        if not ready:
+            return None
+        reply += os.read(fd, 1).decode(errors="replace")
+    return reply
+
+
+def query(request, terminator):
+    write(request)
+    return read_reply(terminator)
+
+
+def position():
+    """The cursor position as (row, column), 1-based"""
+    reply = query(CSI + "6n", "R")
+    if reply is None or not reply.startswith(CSI):
+        return reply
+    row, col = reply[len(CSI):-1].split(";")
+    return int(row), int(col)
+
+
+class Failure(Exception):
+    pass
+
+
+def expect(actual, expected):
+    if actual != expected:
+        raise Failure("expected %r, got %r" % (expected, actual))
+
+
+def expect_position(row, col):
+    expect(position(), (row, col))
+
+
+TESTS = []
+
+
+def test(function):
+    TESTS.append(function)
+    return function
+
+
+@test
+def cup():
+    write(CSI + "5;10H")
+    expect_position(5, 10)
+
+
+@test
+def cup_default_params():
+    write(CSI + "5;10H" + CSI + "H")
+    expect_position(1, 1)
+
+
+@test
+def cup_clamps_to_screen():
+    write(CSI + "999;999H")
+    expect_position(24, 80)
+
+
+@test
+def hvp():
+    write(CSI + "3;4f")
+    expect_position(3, 4)
+
+
+@test
+def cuu_cud_cuf_cub():
+    write(CSI + "10;10H" + CSI + "3A" + CSI + "5B" + CSI + "4C" + CSI + "2D")
+    expect_position(12, 12)
+
+
+@test
+def cuu_stops_at_top():
+    write(CSI + "5;3H" + CSI + "99A")
+    expect_position(1, 3)
+
+
+@test
+def cub_stops_at_left():
+    write(CSI + "5;3H" + CSI + "99D")
+    expect_position(5, 1)
+
+
+@test
+def cha():
+    write(CSI + "4;4H" + CSI + "20G")
+    expect_position(4, 20)
+
+
+@test
+def vpa():
+    write(CSI + "4;4H" + CSI + "7d")
+    expect_position(7, 4)
+
+
+@test
+def cnl():
+    write(CSI + "5;5H" + CSI + "2E")
+    expect_position(7, 1)
+
+
+@test
+def cpl():
+    write(CSI + "5;5H" + CSI + "2F")
+    expect_position(3, 1)
+
+
+@test
+def decsc_decrc():
+    write(CSI + "4;6H" + ESC + "7" + CSI + "H" + ESC + "8")
+    expect_position(4, 6)
+
+
+@test
+def decstbm_homes_cursor():
+    write(CSI + "5;5H" + CSI + "3;10r")
+    expect_position(1, 1)
+
+
+@test
+def origin_mode_home():
+    # Reports are relative to the scroll region in origin mode
+    write(CSI + "5;10r" + CSI + "?6h" + CSI + "H")
+    expect_position(1, 1)
+
+
+@test
+def origin_mode_clamps_to_region():
+    write(CSI + "5;10r" + CSI + "?6h" + CSI + "99;1H")
+    expect_position(6, 1)
+
+
+@test
+def lf_stops_at_region_bottom():
+    write(CSI + "5;10r" + CSI + "10;3H\n")
+    expect_position(10, 3)
+
+
+@test
+def ind_at_bottom():
+    write(CSI + "24;5H" + ESC + "D")
+    expect_position(24, 5)
+
+
+@test
+def ri_at_top():
+    write(CSI + "1;5H" + ESC + "M")
+    expect_position(1, 5)
+
+
+@test
+def nel():
+    write(CSI + "3;5H" + ESC + "E")
+    expect_position(4, 1)
+
+
+@test
+def cr_and_bs():
+    write(CSI + "3;7H\r")
+    expect_position(3, 1)
+    write("\b")
+    expect_position(3, 1)
+
+
+@test
+def autowrap_pending():
+    write(CSI + "1;79Hab")
+    expect_position(1, 80)
+    write("c")
+    expect_position(2, 2)
+
+
+@test
+def no_autowrap():
+    write(CSI + "?7l" + CSI + "1;79Habc" + CSI + "?7h")
+    expect_position(1, 80)
+
+
+@test
+def default_tab_stops():
+    write(CSI + "1;1H\t")
+    expect_position(1, 9)
+    write("\t\t")
+    expect_position(1, 25)
+
+
+@test
+def hts_and_tbc():
+    write(CSI + "3g" + CSI + "1;5H" + ESC + "H" + CSI + "1;1H\t")
+    expect_position(1, 5)
+    write("\t")
+    expect_position(1, 80)
+
+
+@test
+def cht_and_cbt():
+    write(CSI + "1;1H" + CSI + "2I")
+    expect_position(1, 17)
+    write(CSI + "1;20H" + CSI + "Z")
+    expect_position(1, 17)
+
+
+@test
+def wide_character_advances_two():
+    write(CSI + "2;1H日")
+    expect_position(2, 3)
+
+
+@test
+def rep():
+    write(CSI + "2;1Ha" + CSI + "3b")
+    expect_position(2, 5)
+
+
+@test
+def ich_dch_ech_keep_cursor():
+    write(CSI + "1;5H" + CSI + "3@" + CSI + "2P" + CSI + "4X")
+    expect_position(1, 5)
+
+
+@test
+def ed_el_keep_cursor():
+    write(CSI + "6;7H" + CSI + "J" + CSI + "1K" + CSI + "2J")
+    expect_position(6, 7)
+
+
+@test
+def il_dl_move_to_left_margin():
+    write(CSI + "6;7H" + CSI + "2L")
+    expect_position(6, 1)
+    write(CSI + "6;7H" + CSI + "M")
+    expect_position(6, 1)
+
+
+@test
+def su_sd_keep_cursor():
+    write(CSI + "6;7H" + CSI + "2S" + CSI + "2T")
+    expect_position(6, 7)
+
+
+@test
+def decaln_homes_cursor():
+    write(CSI + "6;7H" + ESC + "#8")
+    expect_position(1, 1)
+
+
+@test
+def insert_mode_advances():
+    write(CSI + "4h" + CSI + "1;1Habc" + CSI + "4l")
+    expect_position(1, 4)
+
+
+@test
+def dsr_status():
+    expect(query(CSI + "5n", "n"), CSI + "0n")
+
+
+@test
+def decrqm():
+    expect(query(CSI + "?2004$p", "y"), CSI + "?2004;2$y")
+    write(CSI + "?2004h")
+    expect(query(CSI + "?2004$p", "y"), CSI + "?2004;1$y")
+    write(CSI + "?2004l")
+    expect(query(CSI + "4$p", "y"), CSI + "4;2$y")
+
+
+@test
+def xtversion():
+    reply = query(CSI + ">q", ESC + "\\")
+    if reply is None or not reply.startswith(ESC + "P>|"):
+        raise Failure("unexpected reply %r" % (reply,))
+
+
+@test
+def da1():
+    reply = query(CSI + "c", "c")
+    if reply is None or not reply.startswith(CSI + "?"):
+        raise Failure("unexpected reply %r" % (reply,))
+
+
+def main():
+    if len(sys.argv) != 2:
+        sys.exit("usage: vt_subset.py LOGFILE")
+    saved = termios.tcgetattr(fd)
+    tty.setraw(fd)
+    results = []
+    try:
+        for function in TESTS:
+            # Start each test from a reset terminal
+            write(ESC + "c")
+            try:
+                function()
+                results.append("PASS " + function.__name__)
+            except Failure as failure:
+                results.append("FAIL %s: %s" % (function.__name__, failure))
+        write(ESC + "c")
+    finally:
+        termios.tcsetattr(fd, termios.TCSADRAIN, saved)
+    with open(sys.argv[1], "w") as log:
+        log.write("\n".join(results) + "\n")
+    passed = sum(result.startswith("PASS") for result in results)
+    print("%d of %d tests passed" % (passed, len(results)))
+
+
+if __name__ == "__main__":
+    main()
@@ -24,6 +24,12 @@ pub struct CellAttributes {
     pub italic: bool,
     /// Underlined text (SGR 4)
     pub underline: bool,
+    /// Doubly underlined text (SGR 21); drawn instead of a single underline
+    #[serde(default)]
+    pub double_underline: bool,
+    /// Overlined text (SGR 53)
+    #[serde(default)]
+    pub overline: bool,
     /// Blinking text (SGR 5) - typically rendered as bold or ignored
     pub blink: bool,
     /// Inverse/reverse video (SGR 7)
@@ -126,6 +132,11 @@ impl Cell {
         self.width = s.chars().next().map(unicode_display_width).unwrap_or(1);
     }
 
+    /// Bytes the cell's content holds on the heap
+    pub fn heap_bytes(&self) -> usize {
+        self.content.capacity()
+    }
+
     /// Get the character content
     pub fn content(&self) -> &str {
         &self.content
@@ -5,6 +5,7 @@
 use serde::{Deserialize, Serialize};
 
 use crate::cell::CellAttributes;
+use crate::charset::CharsetState;
 
 /// Cursor visual style
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
@@ -125,6 +126,10 @@ impl Default for Cursor {
 }
 
 /// Saved cursor state for DECSC/DECRC
+///
+/// Everything DECSC saves on a VT510 and in xterm: position, SGR
+/// attributes, character sets (designations and the shift state), origin
+/// mode and the pending wrap flag, plus the OSC 8 hyperlink.
 #[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub struct SavedCursor {
     pub col: usize,
@@ -133,11 +138,13 @@ pub struct SavedCursor {
     pub origin_mode: bool,
     pub pending_wrap: bool,
     pub hyperlink_id: u32,
+    #[serde(default)]
+    pub charset: CharsetState,
 }
 
 impl SavedCursor {
     /// Save current cursor state
-    pub fn save(cursor: &Cursor) -> Self {
+    pub fn save(cursor: &Cursor, charset: &CharsetState) -> Self {
         Self {
             col: cursor.col,
             row: cursor.row,
@@ -145,17 +152,23 @@ impl SavedCursor {
             origin_mode: cursor.origin_mode,
             pending_wrap: cursor.pending_wrap,
             hyperlink_id: cursor.hyperlink_id,
+            charset: charset.clone(),
         }
     }
 
     /// Restore cursor state
-    pub fn restore(&self, cursor: &mut Cursor) {
+    ///
+    /// A pending single shift (SS2/SS3) is not part of the saved state and
+    /// is dropped.
+    pub fn restore(&self, cursor: &mut Cursor, charset: &mut CharsetState) {
         cursor.col = self.col;
         cursor.row = self.row;
         cursor.attrs = self.attrs;
         cursor.origin_mode = self.origin_mode;
         cursor.pending_wrap = self.pending_wrap;
         cursor.hyperlink_id = self.hyperlink_id;
+        *charset = self.charset.clone();
+        charset.clear_single_shift();
     }
 }
 
@@ -220,16 +233,21 @@ mod tests {
         cursor.row = 5;
         cursor.attrs.bold = true;
 
-        let saved = SavedCursor::save(&cursor);
+        let mut charset = CharsetState::default();
+        charset.shift_out();
+
+        let saved = SavedCursor::save(&cursor, &charset);
 
         cursor.col = 0;
         cursor.row = 0;
         cursor.attrs.bold = false;
+        charset.shift_in();
 
-        saved.restore(&mut cursor);
+        saved.restore(&mut cursor, &mut charset);
 
         assert_eq!(cursor.col, 10);
         assert_eq!(cursor.row, 5);
         assert!(cursor.attrs.bold);
+        assert_eq!(charset.active, 1);
     }
 }
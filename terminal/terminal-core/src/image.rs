@@ -0,0 +1,239 @@
+//! Inline image storage
+//!
+//! Images displayed by inline image protocols (such as iTerm2's OSC 1337)
+//! are anchored to a cell and cover a rectangle of cells. Placements scroll
+//! with the text and are dropped once they scroll out of the scrollback.
+
+use std::sync::Arc;
+
+/// Default limit on decoded image memory per store (bytes)
+pub const DEFAULT_MAX_IMAGE_BYTES: usize = 128 * 1024 * 1024;
+
+/// Decoded image pixels
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageData {
+    /// Width in pixels
+    pub width: u32,
+    /// Height in pixels
+    pub height: u32,
+    /// Pixel data in RGBA8 format (width * height * 4 bytes)
+    pub pixels: Vec<u8>,
+}
+
+impl ImageData {
+    /// Create image data from RGBA8 pixels
+    ///
+    /// Returns None if the pixel buffer does not match the dimensions.
+    pub fn new(width: u32, height: u32, pixels: Vec<u8>) -> Option<Self> {
+        if width == 0 || height == 0 || pixels.len() != width as usize * height as usize * 4 {
+            return None;
+        }
+        Some(Self {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    /// Size of the pixel data in bytes
+    pub fn size_bytes(&self) -> usize {
+        self.pixels.len()
+    }
+}
+
+/// An image placed on the grid
+#[derive(Debug, Clone)]
+pub struct ImagePlacement {
+    /// Decoded image
+    pub image: Arc<ImageData>,
+    /// Left column of the image box
+    pub col: usize,
+    /// Top row of the image box (relative to the top of the screen,
+    /// negative rows are in scrollback)
+    pub row: isize,
+    /// Width of the image box in cells
+    pub cols: usize,
+    /// Height of the image box in cells
+    pub rows: usize,
+    /// Scale the image to fit the box without distorting it
+    pub preserve_aspect_ratio: bool,
+}
+
+impl ImagePlacement {
+    /// Row just below the image box
+    pub fn bottom(&self) -> isize {
+        self.row + self.rows as isize
+    }
+}
+
+/// Image placements for one screen buffer
+#[derive(Debug, Clone)]
+pub struct ImageStore {
+    /// Placements in the order they were added (oldest first)
+    placements: Vec<ImagePlacement>,
+    /// Total decoded bytes held by placements
+    total_bytes: usize,
+    /// Maximum decoded bytes before the oldest placements are evicted
+    max_bytes: usize,
+}
+
+impl ImageStore {
+    /// Create a new image store with the given memory limit
+    pub fn new(max_bytes: usize) -> Self {
+        Self {
+            placements: Vec::new(),
+            total_bytes: 0,
+            max_bytes,
+        }
+    }
+
+    /// Get all placements (oldest first)
+    pub fn placements(&self) -> &[ImagePlacement] {
+        &self.placements
+    }
+
+    /// Check if the store has no placements
+    pub fn is_empty(&self) -> bool {
+        self.placements.is_empty()
+    }
+
+    /// Total decoded bytes held by the store
+    pub fn total_bytes(&self) -> usize {
+        self.total_bytes
+    }
+
+    /// Add a placement, evicting the oldest ones if over the memory limit
+    pub fn place(&mut self, placement: ImagePlacement) {
+        self.total_bytes += placement.image.size_bytes();
+        self.placements.push(placement);
+
+        while self.total_bytes > self.max_bytes && self.placements.len() > 1 {
+            let removed = self.placements.remove(0);
+            self.total_bytes -= removed.image.size_bytes();
+        }
+    }
+
+    /// Evict the oldest placements until the rest hold at most `max_bytes`
+    ///
+    /// Returns the number of placements evicted.
+    pub fn evict_to(&mut self, max_bytes: usize) -> usize {
+        let mut evicted = 0;
+        while self.total_bytes > max_bytes && evicted < self.placements.len() {
+            self.total_bytes -= self.placements[evicted].image.size_bytes();
+            evicted += 1;
+        }
+        self.placements.drain(..evicted);
+        evicted
+    }
+
+    /// Move placements starting at or above `bottom` up by n rows, dropping
+    /// any above `min_row`
+    ///
+    /// Placements below `bottom` (under the scroll region) stay in place.
+    pub fn scroll_up(&mut self, n: usize, bottom: isize, min_row: isize) {
+        for placement in &mut self.placements {
+            if placement.row <= bottom {
+                placement.row -= n as isize;
+            }
+        }
+        self.retain(|p| p.bottom() > min_row);
+    }
+
+    /// Move placements starting in `top..=bottom` down by n rows, dropping
+    /// any pushed below `bottom`
+    pub fn scroll_down(&mut self, n: usize, top: isize, bottom: isize) {
+        let n = n as isize;
+        let region = top..=bottom;
+        self.retain(|p| !region.contains(&p.row) || p.row + n <= bottom);
+        for placement in &mut self.placements {
+            if region.contains(&placement.row) {
+                placement.row += n;
+            }
+        }
+    }
+
+    /// Remove placements that overlap the visible screen (rows >= 0)
+    pub fn clear_visible(&mut self) {
+        self.retain(|p| p.bottom() <= 0);
+    }
+
+    /// Remove all placements
+    pub fn clear(&mut self) {
+        self.placements.clear();
+        self.total_bytes = 0;
+    }
+
+    fn retain<F: FnMut(&ImagePlacement) -> bool>(&mut self, f: F) {
+        self.placements.retain(f);
+        self.total_bytes = self.placements.iter().map(|p| p.image.size_bytes()).sum();
+    }
+}
+
+impl Default for ImageStore {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_IMAGE_BYTES)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn placement(row: isize, rows: usize) -> ImagePlacement {
+        ImagePlacement {
+            image: Arc::new(ImageData::new(1, 1, vec![0, 0, 0, 255]).unwrap()),
+            col: 0,
+            row,
+            cols: 1,
+            rows,
+            preserve_aspect_ratio: true,
+        }
+    }
+
+    #[test]
+    fn test_image_data_validates_size() {
+        assert!(ImageData::new(2, 2, vec![0; 16]).is_some());
+        assert!(ImageData::new(2, 2, vec![0; 15]).is_none());
+        assert!(ImageData::new(0, 2, Vec::new()).is_none());
+    }
+
+    #[test]
+    fn test_scroll_up_drops_old_placements() {
+        let mut store = ImageStore::default();
+        store.place(placement(0, 2));
+        store.scroll_up(3, 23, -10);
+        assert_eq!(store.placements()[0].row, -3);
+
+        store.scroll_up(10, 23, -10);
+        assert!(store.is_empty());
+        assert_eq!(store.total_bytes(), 0);
+    }
+
+    #[test]
+    fn test_clear_visible() {
+        let mut store = ImageStore::default();
+        store.place(placement(-5, 2));
+        store.place(placement(-1, 2));
+        store.place(placement(3, 1));
+        store.clear_visible();
+        assert_eq!(store.placements().len(), 1);
+        assert_eq!(store.placements()[0].row, -5);
+    }
+
+    #[test]
+    fn test_memory_limit_evicts_oldest() {
+        let mut store = ImageStore::new(8);
+        store.place(placement(0, 1));
+        store.place(placement(1, 1));
+        store.place(placement(2, 1));
+        assert_eq!(store.placements().len(), 2);
+        assert_eq!(store.placements()[0].row, 1);
+        assert_eq!(store.total_bytes(), 8);
+
+        assert_eq!(store.evict_to(4), 1);
+        assert_eq!(store.placements()[0].row, 2);
+        assert_eq!(store.evict_to(0), 1);
+        assert!(store.is_empty());
+        assert_eq!(store.total_bytes(), 0);
+    }
+}
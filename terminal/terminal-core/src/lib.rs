@@ -14,24 +14,28 @@ mod charset;
 mod color;
 mod cursor;
 mod grid;
+mod image;
 mod line;
 mod modes;
 mod screen;
 mod scrollback;
 mod selection;
 mod snapshot;
+mod tabs;
 
 pub use cell::{Cell, CellAttributes};
 pub use charset::{parse_charset_designation, Charset, CharsetState};
 pub use color::Color;
 pub use cursor::{Cursor, CursorStyle};
 pub use grid::Grid;
-pub use line::Line;
-pub use modes::Modes;
-pub use screen::Screen;
+pub use image::{ImageData, ImagePlacement, ImageStore};
+pub use line::{Line, LineHighlight, LineMeta, SemanticZone, TextRun, ZoneKind};
+pub use modes::{ModeState, Modes, MAX_SAVED_MODES};
+pub use screen::{Screen, TextRegion, MAX_SGR_STACK};
 pub use scrollback::Scrollback;
 pub use selection::{Point, Selection, SelectionType};
 pub use snapshot::Snapshot;
+pub use tabs::TabStops;
 
 /// Terminal dimensions
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
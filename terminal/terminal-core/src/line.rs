@@ -5,14 +5,93 @@
 use serde::{Deserialize, Serialize};
 
 use crate::cell::{Cell, CellAttributes};
+use crate::color::Color;
+
+/// Colors laid over a span of a line by a highlight rule
+///
+/// The colors replace the cells' own where set, so other attributes (bold,
+/// underline, ...) and the other color still come from SGR.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LineHighlight {
+    /// First column
+    pub start: usize,
+    /// Last column (inclusive)
+    pub end: usize,
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+}
+
+impl LineHighlight {
+    pub fn contains(&self, col: usize) -> bool {
+        (self.start..=self.end).contains(&col)
+    }
+}
+
+/// The part of a shell command a line belongs to (OSC 133)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ZoneKind {
+    Prompt,
+    Input,
+    Output,
+}
+
+/// A semantic zone: one part of one command
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SemanticZone {
+    /// Command number; all zones of one command share it
+    pub id: u32,
+    pub kind: ZoneKind,
+}
+
+/// Annotations on a line, for features that mark up output
+///
+/// The metadata stays with the line while it moves into scrollback, is kept
+/// by snapshots, and is cleared with the line's contents.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LineMeta {
+    /// Zone the line starts in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub zone: Option<SemanticZone>,
+    /// Scroll mark (a prompt start, or set by the user)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub marked: bool,
+    /// Id of the trigger that matched the line
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trigger: Option<u32>,
+}
+
+impl LineMeta {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Consecutive cells of a line with the same attributes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextRun {
+    /// Column of the first cell
+    pub col: usize,
+    /// Number of columns covered (wide characters count twice)
+    pub cols: usize,
+    /// Contents, with empty cells as spaces
+    pub text: String,
+    pub attrs: CellAttributes,
+}
 
 /// A single line in the terminal grid
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Line {
     /// Cells in this line
     cells: Vec<Cell>,
-    /// Whether this line was soft-wrapped (continuation of previous line)
+    /// Whether this line was soft-wrapped (continues on the next line)
     pub wrapped: bool,
+    /// Zone, mark and trigger annotations
+    #[serde(default, skip_serializing_if = "LineMeta::is_empty")]
+    pub meta: LineMeta,
+    /// Highlights applied after the line was printed; cleared with the line
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub highlights: Vec<LineHighlight>,
 }
 
 impl Line {
@@ -21,6 +100,8 @@ impl Line {
         Self {
             cells: vec![Cell::new(); cols],
             wrapped: false,
+            meta: LineMeta::default(),
+            highlights: Vec::new(),
         }
     }
 
@@ -36,6 +117,8 @@ impl Line {
         Self {
             cells,
             wrapped: false,
+            meta: LineMeta::default(),
+            highlights: Vec::new(),
         }
     }
 
@@ -44,6 +127,15 @@ impl Line {
         self.cells.len()
     }
 
+    /// Approximate memory the line holds, in bytes: the line itself, its
+    /// cells and their contents, and its highlights
+    pub fn memory_bytes(&self) -> usize {
+        std::mem::size_of::<Self>()
+            + self.cells.capacity() * std::mem::size_of::<Cell>()
+            + self.cells.iter().map(Cell::heap_bytes).sum::<usize>()
+            + self.highlights.capacity() * std::mem::size_of::<LineHighlight>()
+    }
+
     /// Get a reference to a cell
     pub fn get(&self, col: usize) -> Option<&Cell> {
         self.cells.get(col)
@@ -70,6 +162,13 @@ impl Line {
             cell.clear(attrs);
         }
         self.wrapped = false;
+        self.meta = LineMeta::default();
+        self.highlights.clear();
+    }
+
+    /// The highlight covering a column, if any (the first one wins)
+    pub fn highlight_at(&self, col: usize) -> Option<&LineHighlight> {
+        self.highlights.iter().find(|h| h.contains(col))
     }
 
     /// Clear from column to end of line
@@ -77,6 +176,7 @@ impl Line {
         for cell in self.cells.iter_mut().skip(col) {
             cell.clear(attrs);
         }
+        self.repair_wide_chars(col, col);
     }
 
     /// Clear from start of line to column (inclusive)
@@ -84,6 +184,7 @@ impl Line {
         for cell in self.cells.iter_mut().take(col + 1) {
             cell.clear(attrs);
         }
+        self.repair_wide_chars(col, col);
     }
 
     /// Resize the line to a new column count
@@ -97,89 +198,164 @@ impl Line {
             });
         } else {
             self.cells.truncate(cols);
+            self.repair_wide_chars(cols, cols);
         }
     }
 
     /// Insert n blank cells at column, shifting cells right
     /// Cells that shift past the end are lost
     pub fn insert_cells(&mut self, col: usize, n: usize, attrs: CellAttributes) {
-        if col >= self.cells.len() {
+        let len = self.cells.len();
+        if col >= len {
             return;
         }
 
-        // Remove cells from the end
-        let remove_count = n.min(self.cells.len() - col);
-        for _ in 0..remove_count {
-            self.cells.pop();
-        }
+        let n = n.min(len - col);
+        self.cells.truncate(len - n);
+        self.cells
+            .splice(col..col, (0..n).map(|_| blank_cell(attrs)));
 
-        // Insert blank cells at position
-        for _ in 0..n.min(self.cells.len() + n - col) {
-            let mut cell = Cell::new();
-            cell.attrs = attrs;
-            if col < self.cells.len() {
-                self.cells.insert(col, cell);
-            } else {
-                self.cells.push(cell);
-            }
-        }
-
-        // Ensure we maintain the correct size
-        while self.cells.len() < col + n {
-            let mut cell = Cell::new();
-            cell.attrs = attrs;
-            self.cells.push(cell);
-        }
+        // A wide character split by the insertion, or pushed half off the
+        // end of the line, is blanked
+        self.repair_wide_chars(col, (col + n).saturating_sub(1));
+        self.repair_wide_chars(len, len);
     }
 
     /// Delete n cells at column, shifting cells left
     /// New cells at the end are filled with attrs
     pub fn delete_cells(&mut self, col: usize, n: usize, attrs: CellAttributes) {
-        if col >= self.cells.len() {
+        let len = self.cells.len();
+        if col >= len {
             return;
         }
 
-        let original_len = self.cells.len();
-        let delete_count = n.min(self.cells.len() - col);
+        let n = n.min(len - col);
+        self.cells.drain(col..col + n);
+        self.cells.extend((0..n).map(|_| blank_cell(attrs)));
 
-        // Remove cells
-        for _ in 0..delete_count {
-            if col < self.cells.len() {
-                self.cells.remove(col);
-            }
-        }
-
-        // Add blank cells at the end
-        while self.cells.len() < original_len {
-            let mut cell = Cell::new();
-            cell.attrs = attrs;
-            self.cells.push(cell);
-        }
+        self.repair_wide_chars(col, col);
     }
 
     /// Erase n cells starting at column (replace with blanks, don't shift)
     pub fn erase_cells(&mut self, col: usize, n: usize, attrs: CellAttributes) {
-        for i in col..col.saturating_add(n).min(self.cells.len()) {
+        let end = col.saturating_add(n).min(self.cells.len());
+        for i in col..end {
             self.cells[i].clear(attrs);
         }
+        self.repair_wide_chars(col, end.saturating_sub(1));
+    }
+
+    /// Blank the halves of wide characters that were split apart
+    ///
+    /// Checks the cells in `start..=end` and their neighbours: a wide
+    /// character without its continuation cell, or a continuation cell
+    /// without its wide character, is replaced with a blank that keeps the
+    /// cell's attributes.
+    pub fn repair_wide_chars(&mut self, start: usize, end: usize) {
+        let len = self.cells.len();
+        for col in start.saturating_sub(1)..(end + 2).min(len) {
+            let cell = &self.cells[col];
+            let broken = if cell.is_continuation() {
+                col == 0 || self.cells[col - 1].width() != 2
+            } else if cell.width() == 2 {
+                col + 1 >= len || !self.cells[col + 1].is_continuation()
+            } else {
+                false
+            };
+
+            if broken {
+                let attrs = self.cells[col].attrs;
+                self.cells[col].clear(attrs);
+            }
+        }
     }
 
-    /// Get the text content of the line (for selection/copy)
+    /// Get the text content of the line, without trailing spaces
     pub fn text(&self) -> String {
-        let mut result = String::new();
-        for cell in &self.cells {
+        let mut text = self.text_range(0, self.cells.len());
+        text.truncate(text.trim_end().len());
+        text
+    }
+
+    /// Number of columns holding text that continues on the next row
+    ///
+    /// The last column of a wrapped line stays blank when a wide character
+    /// did not fit and moved to the next row; it is not part of the text.
+    pub fn wrap_end(&self) -> usize {
+        match self.cells.last() {
+            Some(last) if self.wrapped && !last.is_continuation() && last.content().is_empty() => {
+                self.cells.len() - 1
+            }
+            _ => self.cells.len(),
+        }
+    }
+
+    /// Text of the cells in `start..end`, with empty cells as spaces
+    ///
+    /// A wide character is included if its first column is in the range.
+    pub fn text_range(&self, start: usize, end: usize) -> String {
+        let end = end.min(self.cells.len());
+        let mut text = String::new();
+        for cell in self.cells.get(start..end).unwrap_or_default() {
             if cell.is_continuation() {
                 continue;
             }
-            let content = cell.content();
-            if content.is_empty() {
-                result.push(' ');
-            } else {
-                result.push_str(content);
+            match cell.content() {
+                "" => text.push(' '),
+                content => text.push_str(content),
             }
         }
-        // Trim trailing spaces
-        result.trim_end().to_string()
+        text
+    }
+
+    /// Text of the whole line and the column of each of its bytes, for
+    /// mapping pattern matches back to cells
+    pub fn text_columns(&self) -> (String, Vec<usize>) {
+        let mut text = String::new();
+        let mut cols = Vec::new();
+        for (col, cell) in self.cells.iter().enumerate() {
+            if cell.is_continuation() {
+                continue;
+            }
+            let content = match cell.content() {
+                "" => " ",
+                content => content,
+            };
+            cols.extend(std::iter::repeat_n(col, content.len()));
+            text.push_str(content);
+        }
+        (text, cols)
+    }
+
+    /// Runs of cells with the same attributes, from left to right
+    pub fn runs(&self) -> impl Iterator<Item = TextRun> + '_ {
+        let mut col = 0;
+        std::iter::from_fn(move || {
+            let first = self.cells.get(col)?;
+            let start = col;
+            while self
+                .cells
+                .get(col)
+                .is_some_and(|cell| cell.attrs == first.attrs)
+            {
+                col += 1;
+            }
+            Some(TextRun {
+                col: start,
+                cols: col - start,
+                text: self.text_range(start, col),
+                attrs: first.attrs,
+            })
+        })
+    }
+
+    /// Number of columns up to the end of the last non-blank cell (the
+    /// whole of a wide character)
+    pub fn content_end(&self) -> usize {
+        self.cells
+            .iter()
+            .rposition(|cell| !cell.is_empty())
+            .map_or(0, |col| col + self.cells[col].width() as usize)
     }
 
     /// Check if line is empty (all cells are empty/space)
@@ -198,6 +374,13 @@ impl Line {
     }
 }
 
+/// Create a blank cell with the given attributes
+fn blank_cell(attrs: CellAttributes) -> Cell {
+    let mut cell = Cell::new();
+    cell.attrs = attrs;
+    cell
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -207,6 +390,7 @@ mod tests {
         let line = Line::new(80);
         assert_eq!(line.cols(), 80);
         assert!(!line.wrapped);
+        assert!(line.meta.is_empty());
     }
 
     #[test]
@@ -221,9 +405,26 @@ mod tests {
         let mut line = Line::new(80);
         line.cell_mut(0).set_char('A');
         line.cell_mut(1).set_char('B');
+        line.meta.marked = true;
         line.clear(CellAttributes::default());
         assert!(line.cell(0).is_empty());
         assert!(line.cell(1).is_empty());
+        assert!(line.meta.is_empty());
+    }
+
+    #[test]
+    fn test_line_highlights() {
+        let mut line = Line::new(10);
+        line.highlights.push(LineHighlight {
+            start: 2,
+            end: 4,
+            fg: Some(Color::Indexed(Color::RED)),
+            bg: None,
+        });
+        assert!(line.highlight_at(1).is_none());
+        assert_eq!(line.highlight_at(4).map(|h| h.start), Some(2));
+        line.clear(CellAttributes::default());
+        assert!(line.highlights.is_empty());
     }
 
     #[test]
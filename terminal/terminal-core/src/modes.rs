@@ -2,8 +2,34 @@
 //!
 //! Various modes that affect terminal behavior, including DEC private modes.
 
+use std::collections::BTreeMap;
+
 use serde::{Deserialize, Serialize};
 
+/// Values kept per mode by XTSAVE
+pub const MAX_SAVED_MODES: usize = 16;
+
+/// A mode's state as reported to DECRQM (the Pm of the DECRPM reply
+/// `CSI ? Ps ; Pm $ y`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModeState {
+    NotRecognized = 0,
+    Set = 1,
+    Reset = 2,
+    PermanentlySet = 3,
+    PermanentlyReset = 4,
+}
+
+impl ModeState {
+    fn from_flag(value: bool) -> Self {
+        if value {
+            ModeState::Set
+        } else {
+            ModeState::Reset
+        }
+    }
+}
+
 /// Terminal mode flags
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Modes {
@@ -44,8 +70,12 @@ pub struct Modes {
     pub mouse_any_event: bool,
     /// Mouse tracking: SGR extended coordinates
     pub mouse_sgr: bool,
+    /// Mouse tracking: SGR coordinates in pixels (1016)
+    pub mouse_sgr_pixels: bool,
     /// Focus in/out events
     pub focus_events: bool,
+    /// Alternate scroll (1007) - wheel sends arrow keys on the alternate screen
+    pub alternate_scroll: bool,
     /// Alternate screen buffer
     pub alternate_screen: bool,
     /// Bracketed paste mode
@@ -53,6 +83,18 @@ pub struct Modes {
     /// Synchronized output mode (DEC 2026) - used by TUI apps like Claude Code
     /// When enabled, the terminal should buffer output until the mode is disabled
     pub synchronized_output: bool,
+    /// In-band resize notifications (DEC 2048): size reports are sent to
+    /// the application instead of it relying on SIGWINCH alone
+    #[serde(default)]
+    pub in_band_resize: bool,
+    /// modifyOtherKeys level (XTMODKEYS, `CSI > 4 ; n m`): 0 off, 1 for
+    /// modified keys that would otherwise lose a modifier, 2 for all
+    #[serde(default)]
+    pub modify_other_keys: u8,
+
+    /// Values saved by XTSAVE, by DEC private mode number
+    #[serde(default)]
+    saved: BTreeMap<u16, Vec<bool>>,
 }
 
 impl Modes {
@@ -80,10 +122,15 @@ impl Modes {
             mouse_button_event: false,
             mouse_any_event: false,
             mouse_sgr: false,
+            mouse_sgr_pixels: false,
             focus_events: false,
+            alternate_scroll: false,
             alternate_screen: false,
             bracketed_paste: false,
             synchronized_output: false,
+            in_band_resize: false,
+            modify_other_keys: 0,
+            saved: BTreeMap::new(),
         }
     }
 
@@ -94,67 +141,76 @@ impl Modes {
 
     /// Set a DEC private mode by number
     pub fn set_dec_mode(&mut self, mode: u16, value: bool) {
-        match mode {
-            1 => self.cursor_keys_application = value,
-            2 => self.ansi_mode = value,
-            3 => self.column_132 = value,
-            4 => self.smooth_scroll = value,
-            5 => self.reverse_video = value,
-            6 => self.origin_mode = value,
-            7 => self.auto_wrap = value,
-            8 => self.auto_repeat = value,
-            9 => self.mouse_x10 = value,
-            25 => self.cursor_visible = value,
-            1000 => self.mouse_vt200 = value,
-            1002 => self.mouse_button_event = value,
-            1003 => self.mouse_any_event = value,
-            1004 => self.focus_events = value,
-            1006 => self.mouse_sgr = value,
-            1049 => self.alternate_screen = value,
-            2004 => self.bracketed_paste = value,
-            2026 => self.synchronized_output = value,
-            _ => {
-                log::debug!("Unknown DEC private mode: {}", mode);
-            }
+        match find(DEC_MODES, mode).map(|info| info.storage) {
+            Some(Storage::Flag { set, .. }) => set(self, value),
+            Some(_) => log::debug!("DEC private mode {} cannot be changed", mode),
+            None => log::debug!("Unknown DEC private mode: {}", mode),
         }
     }
 
     /// Get a DEC private mode by number
     pub fn get_dec_mode(&self, mode: u16) -> bool {
-        match mode {
-            1 => self.cursor_keys_application,
-            2 => self.ansi_mode,
-            3 => self.column_132,
-            4 => self.smooth_scroll,
-            5 => self.reverse_video,
-            6 => self.origin_mode,
-            7 => self.auto_wrap,
-            8 => self.auto_repeat,
-            9 => self.mouse_x10,
-            25 => self.cursor_visible,
-            1000 => self.mouse_vt200,
-            1002 => self.mouse_button_event,
-            1003 => self.mouse_any_event,
-            1004 => self.focus_events,
-            1006 => self.mouse_sgr,
-            1049 => self.alternate_screen,
-            2004 => self.bracketed_paste,
-            2026 => self.synchronized_output,
+        match find(DEC_MODES, mode).map(|info| info.storage) {
+            Some(Storage::Flag { get, .. }) => get(self),
             _ => false,
         }
     }
 
+    /// State of a DEC private mode, for DECRQM
+    ///
+    /// Modes mochi knows but does not implement (other mouse encodings,
+    /// highlight tracking) are permanently reset.
+    pub fn dec_mode_state(&self, mode: u16) -> ModeState {
+        state(self, find(DEC_MODES, mode))
+    }
+
+    /// State of a standard (non-DEC) mode, for DECRQM
+    pub fn mode_state(&self, mode: u16) -> ModeState {
+        state(self, find(ANSI_MODES, mode))
+    }
+
     /// Set a standard (non-DEC) mode by number
     pub fn set_mode(&mut self, mode: u16, value: bool) {
-        match mode {
-            4 => self.insert_mode = value,
-            20 => self.linefeed_mode = value,
-            _ => {
-                log::debug!("Unknown standard mode: {}", mode);
-            }
+        match find(ANSI_MODES, mode).map(|info| info.storage) {
+            Some(Storage::Flag { set, .. }) => set(self, value),
+            Some(_) => log::debug!("Mode {} cannot be changed", mode),
+            None => log::debug!("Unknown standard mode: {}", mode),
         }
     }
 
+    /// Remember the value of a DEC private mode (XTSAVE, `CSI ? Pm s`)
+    ///
+    /// Each mode has its own stack of at most `MAX_SAVED_MODES` values;
+    /// saving more drops the oldest. Modes without a value are ignored.
+    pub fn save_dec_mode(&mut self, mode: u16) {
+        if !matches!(
+            find(DEC_MODES, mode).map(|info| info.storage),
+            Some(Storage::Flag { .. })
+        ) {
+            return;
+        }
+        let value = self.get_dec_mode(mode);
+        let stack = self.saved.entry(mode).or_default();
+        if stack.len() == MAX_SAVED_MODES {
+            stack.remove(0);
+        }
+        stack.push(value);
+    }
+
+    /// The value last saved for a DEC private mode, removed from its stack
+    /// (XTRESTORE, `CSI ? Pm r`)
+    ///
+    /// The caller applies it, so that modes with side effects (the
+    /// alternate screen) take them.
+    pub fn take_saved_dec_mode(&mut self, mode: u16) -> Option<bool> {
+        let stack = self.saved.get_mut(&mode)?;
+        let value = stack.pop();
+        if stack.is_empty() {
+            self.saved.remove(&mode);
+        }
+        value
+    }
+
     /// Check if any mouse mode is active
     pub fn mouse_tracking_enabled(&self) -> bool {
         self.mouse_x10 || self.mouse_vt200 || self.mouse_button_event || self.mouse_any_event
@@ -167,6 +223,98 @@ impl Default for Modes {
     }
 }
 
+/// Where a registered mode keeps its value
+#[derive(Clone, Copy)]
+enum Storage {
+    /// A flag of `Modes`
+    Flag {
+        get: fn(&Modes) -> bool,
+        set: fn(&mut Modes, bool),
+    },
+    /// Recognized but not implemented, so never set
+    Unsupported,
+    /// An action rather than a state (reported as reset)
+    Action,
+}
+
+/// A mode number mochi knows
+struct ModeInfo {
+    number: u16,
+    storage: Storage,
+}
+
+macro_rules! flag {
+    ($number:expr, $field:ident) => {
+        ModeInfo {
+            number: $number,
+            storage: Storage::Flag {
+                get: |modes| modes.$field,
+                set: |modes, value| modes.$field = value,
+            },
+        }
+    };
+}
+
+const fn other(number: u16, storage: Storage) -> ModeInfo {
+    ModeInfo { number, storage }
+}
+
+/// The DEC private modes (`CSI ? Pm h`) and their flags
+///
+/// This is the one place mode numbers are mapped to flags of `Modes`:
+/// setting, getting, DECRQM and XTSAVE all go through it.
+const DEC_MODES: &[ModeInfo] = &[
+    flag!(1, cursor_keys_application),
+    flag!(2, ansi_mode),
+    flag!(3, column_132),
+    flag!(4, smooth_scroll),
+    flag!(5, reverse_video),
+    flag!(6, origin_mode),
+    flag!(7, auto_wrap),
+    flag!(8, auto_repeat),
+    flag!(9, mouse_x10),
+    flag!(25, cursor_visible),
+    // The legacy alternate screen modes share the flag with 1049
+    flag!(47, alternate_screen),
+    flag!(1000, mouse_vt200),
+    other(1001, Storage::Unsupported),
+    flag!(1002, mouse_button_event),
+    flag!(1003, mouse_any_event),
+    flag!(1004, focus_events),
+    other(1005, Storage::Unsupported),
+    flag!(1006, mouse_sgr),
+    flag!(1007, alternate_scroll),
+    other(1015, Storage::Unsupported),
+    flag!(1016, mouse_sgr_pixels),
+    flag!(1047, alternate_screen),
+    other(1048, Storage::Action),
+    flag!(1049, alternate_screen),
+    flag!(2004, bracketed_paste),
+    flag!(2026, synchronized_output),
+    flag!(2048, in_band_resize),
+];
+
+/// The standard modes (`CSI Pm h`)
+const ANSI_MODES: &[ModeInfo] = &[
+    // KAM (keyboard locked)
+    other(2, Storage::Unsupported),
+    flag!(4, insert_mode),
+    flag!(20, linefeed_mode),
+];
+
+fn find(registry: &'static [ModeInfo], number: u16) -> Option<&'static ModeInfo> {
+    registry.iter().find(|info| info.number == number)
+}
+
+fn state(modes: &Modes, info: Option<&ModeInfo>) -> ModeState {
+    match info.map(|info| info.storage) {
+        Some(Storage::Flag { get, .. }) => ModeState::from_flag(get(modes)),
+        Some(Storage::Unsupported) => ModeState::PermanentlyReset,
+        Some(Storage::Action) => ModeState::Reset,
+        None => ModeState::NotRecognized,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -192,6 +340,10 @@ mod tests {
 
         modes.set_dec_mode(2004, true);
         assert!(modes.bracketed_paste);
+
+        modes.set_dec_mode(1007, true);
+        assert!(modes.alternate_scroll);
+        assert!(modes.get_dec_mode(1007));
     }
 
     #[test]
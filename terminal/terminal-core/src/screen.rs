@@ -7,15 +7,45 @@ use crate::cell::CellAttributes;
 use crate::charset::{parse_charset_designation, CharsetState};
 use crate::cursor::{Cursor, SavedCursor};
 use crate::grid::Grid;
-use crate::line::Line;
+use crate::image::{ImageData, ImagePlacement, ImageStore};
+use crate::line::{Line, LineHighlight, SemanticZone};
 use crate::modes::Modes;
 use crate::scrollback::Scrollback;
-use crate::selection::Selection;
+use crate::selection::{Point, Selection, SelectionType};
 use crate::snapshot::Snapshot;
+use crate::tabs::TabStops;
 use crate::Dimensions;
 
-/// Tab stop interval (default)
-const DEFAULT_TAB_WIDTH: usize = 8;
+use std::sync::Arc;
+
+/// Attribute sets kept by XTPUSHSGR, as in xterm
+pub const MAX_SGR_STACK: usize = 10;
+
+/// SGR parameters that XTPUSHSGR can name, each for one attribute
+/// (30 and 31 stand for the foreground and background colors)
+const SGR_PARTS: [u16; 11] = [1, 2, 3, 4, 5, 7, 8, 9, 21, 30, 31];
+
+/// Attributes saved by XTPUSHSGR, and which of them XTPOPSGR restores
+#[derive(Debug, Clone)]
+struct SavedSgr {
+    attrs: CellAttributes,
+    /// Bits of `SGR_PARTS`; `None` restores everything
+    parts: Option<u16>,
+}
+
+/// A part of the screen and scrollback to read text from
+///
+/// Rows count from the top of the screen, negative rows are in the
+/// scrollback (as in selections). Both ends are inclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextRegion {
+    /// From `start` to `end`, following the rows
+    Stream { start: Point, end: Point },
+    /// Whole rows
+    Rows { top: isize, bottom: isize },
+    /// The rectangle with these corners
+    Block { start: Point, end: Point },
+}
 
 /// The complete terminal screen state
 #[derive(Debug, Clone)]
@@ -34,32 +64,41 @@ pub struct Screen {
     saved_cursor_primary: SavedCursor,
     /// Saved cursor for alternate screen
     saved_cursor_alternate: SavedCursor,
+    /// Primary screen cursor to return to when leaving the alternate
+    /// screen; kept apart from the DECSC slot so 1047 does not clobber it
+    alternate_return: SavedCursor,
     /// Terminal modes
     modes: Modes,
     /// Scroll region (top, bottom) - 0-indexed, inclusive
     scroll_region: Option<(usize, usize)>,
     /// Tab stops
-    tab_stops: Vec<bool>,
+    tab_stops: TabStops,
     /// Current selection
     selection: Selection,
     /// Window title
     title: String,
-    /// Hyperlink registry (id -> url)
-    hyperlinks: Vec<String>,
+    /// Hyperlink registry (id -> OSC 8 `id=` parameter and url)
+    hyperlinks: Vec<(Option<String>, String)>,
     /// Next hyperlink ID
     next_hyperlink_id: u32,
     /// Character set state
     charset: CharsetState,
+    /// Inline images on the primary screen
+    images: ImageStore,
+    /// Inline images on the alternate screen
+    alternate_images: ImageStore,
+    /// Last printed graphic character (for REP)
+    last_char: Option<char>,
+    /// Whether leaving the alternate screen keeps its contents in the
+    /// primary screen's history
+    capture_alternate: bool,
+    /// Attributes saved by XTPUSHSGR, the latest last
+    sgr_stack: Vec<SavedSgr>,
 }
 
 impl Screen {
     /// Create a new screen with the specified dimensions
     pub fn new(dims: Dimensions) -> Self {
-        let mut tab_stops = vec![false; dims.cols];
-        for i in (0..dims.cols).step_by(DEFAULT_TAB_WIDTH) {
-            tab_stops[i] = true;
-        }
-
         Self {
             primary_grid: Grid::new(dims),
             alternate_grid: Grid::new(dims),
@@ -68,14 +107,20 @@ impl Screen {
             cursor: Cursor::new(),
             saved_cursor_primary: SavedCursor::default(),
             saved_cursor_alternate: SavedCursor::default(),
+            alternate_return: SavedCursor::default(),
             modes: Modes::new(),
             scroll_region: None,
-            tab_stops,
+            tab_stops: TabStops::new(dims.cols),
             selection: Selection::new(),
             title: String::new(),
             hyperlinks: Vec::new(),
             next_hyperlink_id: 1,
             charset: CharsetState::new(),
+            images: ImageStore::default(),
+            alternate_images: ImageStore::default(),
+            last_char: None,
+            capture_alternate: false,
+            sgr_stack: Vec::new(),
         }
     }
 
@@ -137,6 +182,46 @@ impl Screen {
         &self.scrollback
     }
 
+    /// Set the maximum number of scrollback lines, dropping the oldest lines if needed
+    pub fn set_scrollback_limit(&mut self, max_lines: usize) {
+        self.scrollback.resize(max_lines);
+    }
+
+    /// Drop the oldest scrollback lines until the rest hold at most
+    /// `max_bytes`, with the images that scroll out with them
+    ///
+    /// Returns the number of lines dropped.
+    pub fn trim_scrollback(&mut self, max_bytes: usize) -> usize {
+        let dropped = self.scrollback.trim_to_bytes(max_bytes);
+        if dropped > 0 {
+            let min_row = -(self.scrollback.len() as isize);
+            let bottom = self.rows() as isize - 1;
+            self.images.scroll_up(0, bottom, min_row);
+        }
+        dropped
+    }
+
+    /// Decoded bytes held by the images of both screen buffers
+    pub fn image_bytes(&self) -> usize {
+        self.images.total_bytes() + self.alternate_images.total_bytes()
+    }
+
+    /// Evict the oldest images until both screen buffers hold at most
+    /// `max_bytes`; the buffer not in use gives up its images first
+    ///
+    /// Returns the number of images evicted.
+    pub fn trim_images(&mut self, max_bytes: usize) -> usize {
+        let (current, other) = if self.using_alternate {
+            (&mut self.alternate_images, &mut self.images)
+        } else {
+            (&mut self.images, &mut self.alternate_images)
+        };
+        let other_max = max_bytes.saturating_sub(current.total_bytes());
+        let evicted = other.evict_to(other_max);
+        let current_max = max_bytes.saturating_sub(other.total_bytes());
+        evicted + current.evict_to(current_max)
+    }
+
     /// Get selection reference
     pub fn selection(&self) -> &Selection {
         &self.selection
@@ -164,27 +249,74 @@ impl Screen {
     }
 
     /// Set scroll region (1-indexed as per VT spec, converted to 0-indexed)
+    ///
+    /// A bottom of 0 means the last row. A full-screen or invalid region
+    /// clears the scroll region.
     pub fn set_scroll_region(&mut self, top: usize, bottom: usize) {
         let rows = self.rows();
+        let bottom = if bottom == 0 { rows } else { bottom };
         let top = top.saturating_sub(1).min(rows - 1);
         let bottom = bottom.saturating_sub(1).min(rows - 1);
 
-        if top < bottom {
+        if top < bottom && (top, bottom) != (0, rows - 1) {
             self.scroll_region = Some((top, bottom));
         } else {
             self.scroll_region = None;
         }
 
         // Move cursor to home position (respecting origin mode)
-        if self.modes.origin_mode {
-            self.cursor.row = top;
+        self.home_cursor();
+    }
+
+    /// Set origin mode (DECOM) and home the cursor
+    pub fn set_origin_mode(&mut self, enabled: bool) {
+        self.modes.origin_mode = enabled;
+        self.cursor.origin_mode = enabled;
+        self.home_cursor();
+    }
+
+    /// Move the cursor to the home position (top of the scroll region in
+    /// origin mode, top left of the screen otherwise)
+    fn home_cursor(&mut self) {
+        self.cursor.row = if self.modes.origin_mode {
+            self.scroll_region().0
         } else {
-            self.cursor.row = 0;
-        }
+            0
+        };
         self.cursor.col = 0;
         self.cursor.pending_wrap = false;
     }
 
+    /// Move the cursor down one line, scrolling at the bottom margin
+    ///
+    /// Below the scroll region the cursor stops at the last row without
+    /// scrolling. Returns true if the cursor moved or the region scrolled.
+    fn line_down(&mut self) -> bool {
+        let (_, scroll_bottom) = self.scroll_region();
+
+        if self.cursor.row == scroll_bottom {
+            self.scroll_up(1);
+            true
+        } else if self.cursor.row + 1 < self.rows() {
+            self.cursor.row += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Wrap to the start of the next line, marking the line as wrapped
+    fn wrap_line(&mut self) {
+        self.cursor.pending_wrap = false;
+        self.cursor.col = 0;
+
+        // Mark previous line as wrapped
+        if self.line_down() && self.cursor.row > 0 {
+            let row = self.cursor.row;
+            self.grid_mut().line_mut(row - 1).wrapped = true;
+        }
+    }
+
     /// Clear scroll region
     pub fn clear_scroll_region(&mut self) {
         self.scroll_region = None;
@@ -192,35 +324,32 @@ impl Screen {
 
     /// Print a character at the current cursor position
     pub fn print(&mut self, c: char) {
+        self.last_char = Some(c);
+
         // Translate character through current charset
         let c = self.charset.translate(c);
         // Clear single shift after use
         self.charset.clear_single_shift();
 
         let cols = self.cols();
-        let (_, scroll_bottom) = self.scroll_region();
 
         // Handle pending wrap
         if self.cursor.pending_wrap {
-            self.cursor.pending_wrap = false;
-            self.cursor.col = 0;
-
-            if self.cursor.row >= scroll_bottom {
-                self.scroll_up(1);
-            } else {
-                self.cursor.row += 1;
-            }
-
-            // Mark previous line as wrapped
-            if self.cursor.row > 0 {
-                let row = self.cursor.row;
-                self.grid_mut().line_mut(row - 1).wrapped = true;
-            }
+            self.wrap_line();
         }
 
         // Get character width
         let width = unicode_width::UnicodeWidthChar::width(c).unwrap_or(1);
 
+        // A wide character never straddles the right edge
+        if width == 2 && cols >= 2 && self.cursor.col + 1 >= cols {
+            if self.modes.auto_wrap {
+                self.wrap_line();
+            } else {
+                self.cursor.col = cols - 2;
+            }
+        }
+
         // Handle insert mode
         if self.modes.insert_mode && width > 0 {
             let row = self.cursor.row;
@@ -249,6 +378,11 @@ impl Screen {
                     .cell_mut(col + 1)
                     .set_continuation();
             }
+
+            // Blank any wide character that was half overwritten
+            self.grid_mut()
+                .line_mut(row)
+                .repair_wide_chars(col, col + width.max(1) - 1);
         }
 
         // Advance cursor
@@ -273,19 +407,60 @@ impl Screen {
         }
     }
 
+    /// Repeat the last printed graphic character n times (REP)
+    pub fn repeat_last_char(&mut self, n: usize) {
+        if let Some(c) = self.last_char {
+            // Never repeat more than fills the screen
+            let max = self.cols() * self.rows();
+            for _ in 0..n.min(max) {
+                self.print(c);
+            }
+        }
+    }
+
     /// Handle horizontal tab (HT)
     pub fn tab(&mut self) {
-        let cols = self.cols();
-        let mut col = self.cursor.col + 1;
+        self.tab_forward(1);
+    }
+
+    /// Move the cursor forward n tab stops (CHT)
+    ///
+    /// Stops at the last column if there are no more tab stops.
+    pub fn tab_forward(&mut self, n: usize) {
+        let last = self.cols() - 1;
+        let mut col = self.cursor.col;
+
+        for _ in 0..n {
+            match self.tab_stops.next(col) {
+                Some(next) => col = next,
+                None => {
+                    col = last;
+                    break;
+                }
+            }
+        }
 
-        while col < cols {
-            if self.tab_stops.get(col).copied().unwrap_or(false) {
-                break;
+        self.cursor.col = col.min(last);
+        self.cursor.pending_wrap = false;
+    }
+
+    /// Move the cursor back n tab stops (CBT)
+    ///
+    /// Stops at the first column if there are no more tab stops.
+    pub fn tab_backward(&mut self, n: usize) {
+        let mut col = self.cursor.col;
+
+        for _ in 0..n {
+            match self.tab_stops.prev(col) {
+                Some(prev) => col = prev,
+                None => {
+                    col = 0;
+                    break;
+                }
             }
-            col += 1;
         }
 
-        self.cursor.col = col.min(cols - 1);
+        self.cursor.col = col;
         self.cursor.pending_wrap = false;
     }
 
@@ -297,13 +472,7 @@ impl Screen {
 
     /// Handle line feed (LF), vertical tab (VT), form feed (FF)
     pub fn linefeed(&mut self) {
-        let (_, scroll_bottom) = self.scroll_region();
-
-        if self.cursor.row >= scroll_bottom {
-            self.scroll_up(1);
-        } else {
-            self.cursor.row += 1;
-        }
+        self.line_down();
         self.cursor.pending_wrap = false;
 
         // In linefeed mode, LF also does CR
@@ -316,9 +485,10 @@ impl Screen {
     pub fn reverse_index(&mut self) {
         let (scroll_top, _) = self.scroll_region();
 
-        if self.cursor.row <= scroll_top {
+        // Above the scroll region the cursor stops at the first row
+        if self.cursor.row == scroll_top {
             self.scroll_down(1);
-        } else {
+        } else if self.cursor.row > 0 {
             self.cursor.row -= 1;
         }
         self.cursor.pending_wrap = false;
@@ -342,10 +512,36 @@ impl Screen {
 
         let scrolled = self.grid_mut().scroll_up(top, bottom, n, attrs);
 
-        // Add to scrollback if scrolling primary screen from top
-        if !self.using_alternate && top == 0 {
+        if self.scrolls_into_history(top) {
             self.scrollback.push_lines(scrolled);
         }
+
+        // Images and the selection move with the text when scrolling from
+        // the top; those under the bottom margin stay
+        if top == 0 {
+            let min_row = if self.using_alternate {
+                0
+            } else {
+                -(self.scrollback.len() as isize)
+            };
+            self.images_mut().scroll_up(n, bottom as isize, min_row);
+            self.selection.scroll_up(n, bottom as isize, min_row);
+        }
+    }
+
+    /// Whether lines scrolled off the top of a region starting at `top` go
+    /// into the scrollback
+    ///
+    /// Only lines leaving the top of the primary screen do, as in xterm. A
+    /// region further down (vim's windows below a split, a pager under a
+    /// fixed header) scrolls in place, so redrawing it does not flood the
+    /// history with copies of the same rows. The bottom margin does not
+    /// matter: output scrolling above a status line at the bottom still
+    /// leaves the screen. Scrolls always span the full width (there are no
+    /// left/right margins). Inserting and deleting lines (IL/DL), scrolling
+    /// down and the alternate screen never add to the history.
+    fn scrolls_into_history(&self, top: usize) -> bool {
+        !self.using_alternate && top == 0
     }
 
     /// Scroll down by n lines within scroll region
@@ -353,6 +549,11 @@ impl Screen {
         let (top, bottom) = self.scroll_region();
         let attrs = self.cursor.attrs;
         self.grid_mut().scroll_down(top, bottom, n, attrs);
+
+        if top == 0 {
+            self.images_mut()
+                .scroll_down(n, top as isize, bottom as isize);
+        }
     }
 
     /// Move cursor to position (1-indexed as per VT spec)
@@ -377,9 +578,11 @@ impl Screen {
     }
 
     /// Move cursor up by n rows
+    ///
+    /// Stops at the top margin when starting inside the scroll region.
     pub fn move_cursor_up(&mut self, n: usize) {
         let (scroll_top, _) = self.scroll_region();
-        let min_row = if self.modes.origin_mode {
+        let min_row = if self.cursor.row >= scroll_top {
             scroll_top
         } else {
             0
@@ -389,9 +592,11 @@ impl Screen {
     }
 
     /// Move cursor down by n rows
+    ///
+    /// Stops at the bottom margin when starting inside the scroll region.
     pub fn move_cursor_down(&mut self, n: usize) {
         let (_, scroll_bottom) = self.scroll_region();
-        let max_row = if self.modes.origin_mode {
+        let max_row = if self.cursor.row <= scroll_bottom {
             scroll_bottom
         } else {
             self.rows() - 1
@@ -436,8 +641,10 @@ impl Screen {
     }
 
     /// Save cursor state (DECSC)
+    ///
+    /// The primary and alternate screens each have their own saved cursor.
     pub fn save_cursor(&mut self) {
-        let saved = SavedCursor::save(&self.cursor);
+        let saved = SavedCursor::save(&self.cursor, &self.charset);
         if self.using_alternate {
             self.saved_cursor_alternate = saved;
         } else {
@@ -452,15 +659,122 @@ impl Screen {
         } else {
             &self.saved_cursor_primary
         };
-        saved.restore(&mut self.cursor);
+        saved.restore(&mut self.cursor, &mut self.charset);
+        self.modes.origin_mode = self.cursor.origin_mode;
+        self.clamp_restored_cursor();
+    }
+
+    /// Save the current attributes (XTPUSHSGR, `CSI # {`)
+    ///
+    /// `parts` are the SGR parameters of the attributes that the matching
+    /// pop restores (1, 2, 3, 4, 5, 7, 8, 9, 21, 30 for the foreground and
+    /// 31 for the background); none restores all of them. Past
+    /// `MAX_SGR_STACK` entries the oldest is dropped.
+    pub fn push_sgr(&mut self, parts: &[u16]) {
+        let parts = parts.iter().filter(|&&p| p != 0).fold(None, |mask, p| {
+            let bit = SGR_PARTS
+                .iter()
+                .position(|part| part == p)
+                .map_or(0, |i| 1 << i);
+            Some(mask.unwrap_or(0) | bit)
+        });
+        if self.sgr_stack.len() >= MAX_SGR_STACK {
+            self.sgr_stack.remove(0);
+        }
+        self.sgr_stack.push(SavedSgr {
+            attrs: self.cursor.attrs,
+            parts,
+        });
+    }
+
+    /// Restore the attributes saved last (XTPOPSGR, `CSI # }`); nothing
+    /// happens when none are saved
+    pub fn pop_sgr(&mut self) {
+        let Some(SavedSgr {
+            attrs: saved,
+            parts,
+        }) = self.sgr_stack.pop()
+        else {
+            return;
+        };
+        let Some(parts) = parts else {
+            self.cursor.attrs = saved;
+            return;
+        };
+        let named = |sgr: u16| {
+            SGR_PARTS
+                .iter()
+                .position(|&part| part == sgr)
+                .is_some_and(|i| parts & (1 << i) != 0)
+        };
+        let attrs = &mut self.cursor.attrs;
+        if named(1) {
+            attrs.bold = saved.bold;
+        }
+        if named(2) {
+            attrs.faint = saved.faint;
+        }
+        if named(3) {
+            attrs.italic = saved.italic;
+        }
+        if named(4) {
+            attrs.underline = saved.underline;
+        }
+        if named(5) {
+            attrs.blink = saved.blink;
+        }
+        if named(7) {
+            attrs.inverse = saved.inverse;
+        }
+        if named(8) {
+            attrs.hidden = saved.hidden;
+        }
+        if named(9) {
+            attrs.strikethrough = saved.strikethrough;
+        }
+        if named(21) {
+            attrs.double_underline = saved.double_underline;
+        }
+        if named(30) {
+            attrs.fg = saved.fg;
+        }
+        if named(31) {
+            attrs.bg = saved.bg;
+        }
+    }
 
-        // Clamp to screen bounds
+    /// Keep a restored cursor on screen after a resize; a pending wrap
+    /// only survives if the cursor is still in the column it was saved in
+    fn clamp_restored_cursor(&mut self) {
         let cols = self.cols();
         let rows = self.rows();
-        self.cursor.col = self.cursor.col.min(cols - 1);
+        if self.cursor.col >= cols {
+            self.cursor.col = cols - 1;
+            self.cursor.pending_wrap = false;
+        }
         self.cursor.row = self.cursor.row.min(rows - 1);
     }
 
+    /// Screen alignment test (DECALN)
+    ///
+    /// Fills the screen with 'E' in the default rendition, resets the
+    /// scroll region and homes the cursor. The current SGR attributes,
+    /// charset and hyperlink are left alone, as in xterm.
+    pub fn alignment_test(&mut self) {
+        let cols = self.cols();
+        for line in self.grid_mut().iter_mut() {
+            *line = Line::new(cols);
+            for cell in line.iter_mut() {
+                cell.set_char('E');
+            }
+        }
+        self.images_mut().clear_visible();
+        self.scroll_region = None;
+        self.cursor.row = 0;
+        self.cursor.col = 0;
+        self.cursor.pending_wrap = false;
+    }
+
     /// Erase display (ED)
     pub fn erase_display(&mut self, mode: u16) {
         let attrs = self.cursor.attrs;
@@ -492,6 +806,7 @@ impl Screen {
                     }
                 }
                 self.grid_mut().clear(attrs);
+                self.images_mut().clear_visible();
             }
             3 => {
                 // Erase scrollback (xterm extension)
@@ -578,10 +893,7 @@ impl Screen {
 
     /// Set tab stop at current column (HTS)
     pub fn set_tab_stop(&mut self) {
-        let col = self.cursor.col;
-        if col < self.tab_stops.len() {
-            self.tab_stops[col] = true;
-        }
+        self.tab_stops.set(self.cursor.col);
     }
 
     /// Clear tab stops (TBC)
@@ -589,33 +901,39 @@ impl Screen {
         match mode {
             0 => {
                 // Clear tab stop at current column
-                let col = self.cursor.col;
-                if col < self.tab_stops.len() {
-                    self.tab_stops[col] = false;
-                }
+                self.tab_stops.clear(self.cursor.col);
             }
             3 => {
                 // Clear all tab stops
-                for stop in &mut self.tab_stops {
-                    *stop = false;
-                }
+                self.tab_stops.clear_all();
             }
             _ => {}
         }
     }
 
+    /// Reset tab stops to every 8 columns (DECST8C)
+    pub fn reset_tab_stops(&mut self) {
+        self.tab_stops.reset();
+    }
+
+    /// Get the tab stops
+    pub fn tab_stops(&self) -> &TabStops {
+        &self.tab_stops
+    }
+
     /// Switch to alternate screen
     /// Always clears the alternate grid to ensure a clean slate for TUI applications
     pub fn enter_alternate_screen(&mut self) {
         if !self.using_alternate {
             self.using_alternate = true;
             self.modes.alternate_screen = true;
-            self.saved_cursor_primary = SavedCursor::save(&self.cursor);
+            self.alternate_return = SavedCursor::save(&self.cursor, &self.charset);
         }
         // Always clear the alternate grid and reset cursor when entering alternate screen
         // This ensures TUI applications like Claude Code, vim, htop get a clean canvas
         self.cursor.reset();
         self.alternate_grid.clear(CellAttributes::default());
+        self.alternate_images.clear();
     }
 
     /// Switch back to primary screen
@@ -623,8 +941,84 @@ impl Screen {
         if self.using_alternate {
             self.using_alternate = false;
             self.modes.alternate_screen = false;
-            self.saved_cursor_primary.restore(&mut self.cursor);
+            self.alternate_return
+                .restore(&mut self.cursor, &mut self.charset);
+            self.modes.origin_mode = self.cursor.origin_mode;
+            self.clamp_restored_cursor();
+            self.alternate_images.clear();
+            if self.capture_alternate {
+                self.capture_alternate_screen();
+            }
+        }
+    }
+
+    /// Keep the alternate screen's contents when leaving it
+    ///
+    /// The non-blank rows are added to the primary screen at the cursor,
+    /// between two faint rules, so they scroll into the history like any
+    /// other output. The first rule is a scroll mark.
+    pub fn set_capture_alternate_screen(&mut self, enabled: bool) {
+        self.capture_alternate = enabled;
+    }
+
+    /// Copy the alternate grid's non-blank rows into the primary screen
+    fn capture_alternate_screen(&mut self) {
+        let rows: Vec<&Line> = self.alternate_grid.iter().collect();
+        let Some(first) = rows.iter().position(|line| !line.is_empty()) else {
+            return;
+        };
+        let last = rows
+            .iter()
+            .rposition(|line| !line.is_empty())
+            .unwrap_or(first);
+        let cols = self.cols();
+        let rule = |marked: bool| {
+            let attrs = CellAttributes {
+                faint: true,
+                ..CellAttributes::default()
+            };
+            let mut line = Line::with_attrs(cols, attrs);
+            for cell in line.iter_mut() {
+                cell.set_char('─');
+            }
+            line.meta.marked = marked;
+            line
+        };
+        let mut block = vec![rule(true)];
+        block.extend(rows[first..=last].iter().map(|&line| {
+            let mut line = line.clone();
+            line.meta = Default::default();
+            line
+        }));
+        block.push(rule(false));
+
+        // Start below the cursor line if something is on it
+        if !self.primary_grid.line(self.cursor.row).is_empty() {
+            self.capture_line_down();
+        }
+        for line in block {
+            *self.primary_grid.line_mut(self.cursor.row) = line;
+            self.capture_line_down();
+        }
+        self.cursor.col = 0;
+        self.cursor.pending_wrap = false;
+    }
+
+    /// Move the cursor down a row of the primary screen, scrolling the whole
+    /// screen into the history at the bottom
+    fn capture_line_down(&mut self) {
+        let rows = self.rows();
+        if self.cursor.row + 1 < rows {
+            self.cursor.row += 1;
+            return;
         }
+        let scrolled = self
+            .primary_grid
+            .scroll_up(0, rows - 1, 1, CellAttributes::default());
+        self.scrollback.push_lines(scrolled);
+        let min_row = -(self.scrollback.len() as isize);
+        self.images.scroll_up(1, rows as isize - 1, min_row);
+        self.selection.scroll_up(1, rows as isize - 1, min_row);
     }
 
     /// Resize the screen
@@ -634,11 +1028,8 @@ impl Screen {
         self.primary_grid.resize(dims, attrs);
         self.alternate_grid.resize(dims, attrs);
 
-        // Update tab stops
-        self.tab_stops.resize(dims.cols, false);
-        for i in (0..dims.cols).step_by(DEFAULT_TAB_WIDTH) {
-            self.tab_stops[i] = true;
-        }
+        // Keep user-set tab stops, new columns get the defaults
+        self.tab_stops.resize(dims.cols);
 
         // Clamp cursor
         self.cursor.col = self.cursor.col.min(dims.cols.saturating_sub(1));
@@ -651,12 +1042,40 @@ impl Screen {
     /// Reset terminal to initial state
     pub fn reset(&mut self) {
         let dims = self.dimensions();
+        let capture_alternate = self.capture_alternate;
         *self = Self::new(dims);
+        self.capture_alternate = capture_alternate;
+    }
+
+    /// Soft terminal reset (DECSTR)
+    ///
+    /// Resets modes, margins, character sets, SGR attributes and the saved
+    /// cursors without touching the screen contents or the cursor position.
+    /// Auto-wrap is restored to its default (on), as xterm does.
+    pub fn soft_reset(&mut self) {
+        self.modes.insert_mode = false;
+        self.modes.origin_mode = false;
+        self.modes.auto_wrap = true;
+        self.modes.cursor_keys_application = false;
+        self.modes.cursor_visible = true;
+
+        self.scroll_region = None;
+        self.charset.reset();
+
+        self.cursor.attrs = CellAttributes::default();
+        self.cursor.visible = true;
+        self.cursor.origin_mode = false;
+        self.cursor.pending_wrap = false;
+        self.cursor.hyperlink_id = 0;
+
+        self.saved_cursor_primary = SavedCursor::default();
+        self.saved_cursor_alternate = SavedCursor::default();
+        self.last_char = None;
     }
 
     /// Create a snapshot of the current state
     pub fn snapshot(&self, include_scrollback: bool) -> Snapshot {
-        Snapshot::from_terminal(
+        let mut snapshot = Snapshot::from_terminal(
             self.grid(),
             &self.cursor,
             &self.modes,
@@ -672,22 +1091,41 @@ impl Screen {
                 Some(&self.title)
             },
             include_scrollback,
-        )
+        );
+        snapshot.tab_stops = Some(self.tab_stops.positions());
+        snapshot
     }
 
     /// Register a hyperlink and return its ID
+    ///
+    /// Links without an OSC 8 `id=` share an ID with every other link to
+    /// the same URL.
     pub fn register_hyperlink(&mut self, url: &str) -> u32 {
-        // Check if URL already registered
-        for (i, existing) in self.hyperlinks.iter().enumerate() {
-            if existing == url {
+        self.register_link(None, url)
+    }
+
+    /// Register a hyperlink with an OSC 8 `id=` parameter
+    ///
+    /// Links with the same parameter and URL share an ID (a link the
+    /// program drew in pieces, e.g. across a split window), while links to
+    /// the same URL with different parameters are kept apart.
+    pub fn register_hyperlink_with_id(&mut self, link_id: &str, url: &str) -> u32 {
+        self.register_link(Some(link_id), url)
+    }
+
+    fn register_link(&mut self, link_id: Option<&str>, url: &str) -> u32 {
+        // Check if the link is already registered
+        for (i, (existing_id, existing)) in self.hyperlinks.iter().enumerate() {
+            if existing_id.as_deref() == link_id && existing == url {
                 return (i + 1) as u32;
             }
         }
 
-        // Register new URL
+        // Register new link
         let id = self.next_hyperlink_id;
         self.next_hyperlink_id += 1;
-        self.hyperlinks.push(url.to_string());
+        self.hyperlinks
+            .push((link_id.map(str::to_string), url.to_string()));
         id
     }
 
@@ -696,7 +1134,71 @@ impl Screen {
         if id == 0 {
             return None;
         }
-        self.hyperlinks.get((id - 1) as usize).map(|s| s.as_str())
+        self.hyperlinks
+            .get((id - 1) as usize)
+            .map(|(_, url)| url.as_str())
+    }
+
+    /// Get the inline images of the current screen buffer
+    pub fn images(&self) -> &ImageStore {
+        if self.using_alternate {
+            &self.alternate_images
+        } else {
+            &self.images
+        }
+    }
+
+    fn images_mut(&mut self) -> &mut ImageStore {
+        if self.using_alternate {
+            &mut self.alternate_images
+        } else {
+            &mut self.images
+        }
+    }
+
+    /// Place an image at the cursor, covering `cols` x `rows` cells
+    ///
+    /// The cursor ends up on the image's last row, just right of the image,
+    /// as if the image were a single wide character. The screen scrolls if
+    /// the image extends past the bottom of the scroll region. The box is
+    /// clamped to the rest of the row and to the screen height.
+    pub fn place_image(
+        &mut self,
+        image: ImageData,
+        cols: usize,
+        rows: usize,
+        preserve_aspect_ratio: bool,
+    ) {
+        let col = self.cursor.col;
+        let cols = cols.clamp(1, self.cols().saturating_sub(col).max(1));
+        let rows = rows.clamp(1, self.rows());
+
+        self.cursor.pending_wrap = false;
+        let row = self.cursor.row as isize;
+        self.images_mut().place(ImagePlacement {
+            image: Arc::new(image),
+            col,
+            row,
+            cols,
+            rows,
+            preserve_aspect_ratio,
+        });
+
+        // Move past the image, scrolling at the bottom of the scroll region
+        // (scrolling moves the placement along)
+        let (_, scroll_bottom) = self.scroll_region();
+        let down = scroll_bottom.saturating_sub(self.cursor.row).min(rows - 1);
+        self.cursor.row += down;
+        if down < rows - 1 {
+            self.scroll_up(rows - 1 - down);
+        }
+        let new_col = col + cols;
+        if new_col >= self.cols() {
+            self.cursor.col = self.cols() - 1;
+            self.cursor.pending_wrap = self.modes.auto_wrap;
+        } else {
+            self.cursor.col = new_col;
+        }
     }
 
     /// Get a line from the grid
@@ -704,6 +1206,197 @@ impl Screen {
         self.grid().line(row)
     }
 
+    /// Get a line of history, counting from the oldest scrollback line
+    ///
+    /// Indices from `scrollback().len()` on are rows of the grid.
+    pub fn history_line(&self, index: usize) -> Option<&Line> {
+        let scrollback_len = self.scrollback.len();
+        if index < scrollback_len {
+            self.scrollback.get(index)
+        } else if index - scrollback_len < self.rows() {
+            Some(self.line(index - scrollback_len))
+        } else {
+            None
+        }
+    }
+
+    /// Get a line by row, counting from the top of the screen (negative rows
+    /// are in the scrollback)
+    pub fn line_at(&self, row: isize) -> Option<&Line> {
+        let index = row + self.scrollback.len() as isize;
+        usize::try_from(index)
+            .ok()
+            .and_then(|index| self.history_line(index))
+    }
+
+    /// First and last row of the logical line containing `row`: a run of
+    /// rows joined by soft wraps
+    pub fn logical_line_rows(&self, row: isize) -> Option<(isize, isize)> {
+        self.line_at(row)?;
+        let mut top = row;
+        while self.line_at(top - 1).is_some_and(|line| line.wrapped) {
+            top -= 1;
+        }
+        let mut bottom = row;
+        while self.line_at(bottom).is_some_and(|line| line.wrapped)
+            && self.line_at(bottom + 1).is_some()
+        {
+            bottom += 1;
+        }
+        Some((top, bottom))
+    }
+
+    /// Text of the logical line containing `row`, with the soft wraps
+    /// removed and without trailing spaces
+    pub fn logical_line(&self, row: isize) -> Option<String> {
+        let (top, bottom) = self.logical_line_rows(row)?;
+        Some(self.text_region(TextRegion::Rows { top, bottom }, true))
+    }
+
+    /// Whether the cell at `col` of `row` (negative rows are in the
+    /// scrollback) is highlighted as part of the selection
+    ///
+    /// Both halves of a wide character are highlighted when either is
+    /// selected, since copying takes the whole character. Blank cells after
+    /// the end of a row's text are only highlighted when the selection
+    /// continues onto the next row: they stand for the line break, and the
+    /// trailing spaces themselves are trimmed from the copy. A soft-wrapped
+    /// row highlights to the edge, and block selections are always drawn as
+    /// the full rectangle.
+    pub fn is_selected(&self, col: usize, row: isize) -> bool {
+        let selection = &self.selection;
+        if selection.is_empty() {
+            return false;
+        }
+        let Some(line) = self.line_at(row) else {
+            return false;
+        };
+        let lead = if col > 0 && line.get(col).is_some_and(|cell| cell.is_continuation()) {
+            col - 1
+        } else {
+            col
+        };
+        let width = line
+            .get(lead)
+            .map_or(1, |cell| cell.width().max(1) as usize);
+        if !(lead..lead + width).any(|col| selection.contains(col, row)) {
+            return false;
+        }
+
+        let (_, end) = selection.bounds();
+        selection.selection_type == SelectionType::Block
+            || row < end.row
+            || line.wrapped
+            || lead < line.content_end()
+    }
+
+    /// Text of a region, one line per row
+    ///
+    /// Rows joined by a soft wrap are joined in the text too, except in
+    /// blocks. With `trim`, trailing spaces are removed from each line.
+    /// Rows outside the scrollback and screen are skipped.
+    pub fn text_region(&self, region: TextRegion, trim: bool) -> String {
+        let (top, bottom) = match region {
+            TextRegion::Stream { start, end } | TextRegion::Block { start, end } => {
+                (start.row.min(end.row), start.row.max(end.row))
+            }
+            TextRegion::Rows { top, bottom } => (top.min(bottom), top.max(bottom)),
+        };
+        let top = top.max(-(self.scrollback.len() as isize));
+        let bottom = bottom.min(self.rows() as isize - 1);
+        let mut text = String::new();
+        // Where the current line of text starts
+        let mut line_start = 0;
+        for row in top..=bottom {
+            let Some(line) = self.line_at(row) else {
+                continue;
+            };
+            let joined =
+                line.wrapped && row < bottom && !matches!(region, TextRegion::Block { .. });
+            let (mut from, to) = match region {
+                TextRegion::Rows { .. } => (0, line.cols()),
+                TextRegion::Block { start, end } => {
+                    (start.col.min(end.col), start.col.max(end.col) + 1)
+                }
+                TextRegion::Stream { start, end } => {
+                    let (start, end) = if (start.row, start.col) <= (end.row, end.col) {
+                        (start, end)
+                    } else {
+                        (end, start)
+                    };
+                    (
+                        if row == start.row { start.col } else { 0 },
+                        if row == end.row {
+                            end.col + 1
+                        } else {
+                            line.cols()
+                        },
+                    )
+                }
+            };
+            // A region starting on the right half of a wide character
+            // takes the whole character, as it is highlighted
+            if from > 0 && line.get(from).is_some_and(|cell| cell.is_continuation()) {
+                from -= 1;
+            }
+            if joined {
+                text.push_str(&line.text_range(from, to.min(line.wrap_end())));
+                continue;
+            }
+            text.push_str(&line.text_range(from, to));
+            if trim {
+                let len = line_start + text[line_start..].trim_end().len();
+                text.truncate(len);
+            }
+            if row < bottom {
+                text.push('\n');
+                line_start = text.len();
+            }
+        }
+        text
+    }
+
+    /// Set a scroll mark on a row of the grid
+    pub fn set_line_mark(&mut self, row: usize) {
+        if row < self.rows() {
+            self.grid_mut().line_mut(row).meta.marked = true;
+        }
+    }
+
+    /// Put a row of the grid in a semantic zone
+    pub fn set_line_zone(&mut self, row: usize, zone: SemanticZone) {
+        if row < self.rows() {
+            self.grid_mut().line_mut(row).meta.zone = Some(zone);
+        }
+    }
+
+    /// Record the trigger that matched a row of the grid
+    pub fn set_line_trigger(&mut self, row: usize, trigger: u32) {
+        if row < self.rows() {
+            self.grid_mut().line_mut(row).meta.trigger = Some(trigger);
+        }
+    }
+
+    /// Replace the highlights of a row of the grid
+    pub fn set_line_highlights(&mut self, row: usize, highlights: Vec<LineHighlight>) {
+        if row < self.rows() {
+            self.grid_mut().line_mut(row).highlights = highlights;
+        }
+    }
+
+    /// Find the nearest marked history line before `index`
+    pub fn previous_mark(&self, index: usize) -> Option<usize> {
+        (0..index)
+            .rev()
+            .find(|&i| self.history_line(i).is_some_and(|line| line.meta.marked))
+    }
+
+    /// Find the nearest marked history line after `index`
+    pub fn next_mark(&self, index: usize) -> Option<usize> {
+        let end = self.scrollback.len() + self.rows();
+        (index + 1..end).find(|&i| self.history_line(i).is_some_and(|line| line.meta.marked))
+    }
+
     /// Get charset state reference
     pub fn charset(&self) -> &CharsetState {
         &self.charset
@@ -734,6 +1427,40 @@ impl Screen {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::Color;
+
+    #[test]
+    fn test_screen_sgr_stack() {
+        let mut screen = Screen::new(Dimensions::new(80, 24));
+        screen.cursor_mut().attrs.bold = true;
+        screen.cursor_mut().attrs.fg = Color::Indexed(1);
+        screen.push_sgr(&[]);
+        screen.cursor_mut().attrs = CellAttributes::default();
+        screen.cursor_mut().attrs.italic = true;
+        screen.pop_sgr();
+        let attrs = screen.cursor().attrs;
+        assert!(attrs.bold && !attrs.italic);
+        assert_eq!(attrs.fg, Color::Indexed(1));
+
+        // Only the named attributes come back
+        screen.push_sgr(&[1, 31]);
+        screen.cursor_mut().attrs = CellAttributes::default();
+        screen.cursor_mut().attrs.bg = Color::Indexed(4);
+        screen.pop_sgr();
+        let attrs = screen.cursor().attrs;
+        assert!(attrs.bold);
+        assert_eq!((attrs.fg, attrs.bg), (Color::Default, Color::Default));
+
+        // The stack is bounded and popping it empty changes nothing
+        for _ in 0..MAX_SGR_STACK + 1 {
+            screen.push_sgr(&[]);
+        }
+        screen.cursor_mut().attrs = CellAttributes::default();
+        for _ in 0..MAX_SGR_STACK + 1 {
+            screen.pop_sgr();
+        }
+        assert!(screen.cursor().attrs.bold);
+    }
 
     #[test]
     fn test_screen_new() {
@@ -787,6 +1514,75 @@ mod tests {
         assert_eq!(screen.line(2).cell(0).display_char(), 'D');
     }
 
+    #[test]
+    fn test_screen_set_scrollback_limit() {
+        let mut screen = Screen::new(Dimensions::new(80, 2));
+        for c in ['A', 'B', 'C', 'D', 'E'] {
+            screen.print(c);
+            screen.linefeed();
+            screen.carriage_return();
+        }
+        assert_eq!(screen.scrollback().len(), 4);
+
+        screen.set_scrollback_limit(2);
+        assert_eq!(screen.scrollback().max_lines(), 2);
+        assert_eq!(screen.scrollback().len(), 2);
+        assert_eq!(
+            screen.scrollback().get(0).unwrap().cell(0).display_char(),
+            'C'
+        );
+    }
+
+    #[test]
+    fn test_screen_memory_budget() {
+        let mut screen = Screen::new(Dimensions::new(10, 2));
+        let pixel = || ImageData::new(1, 1, vec![0; 4]).unwrap();
+        screen.place_image(pixel(), 1, 1, true);
+        for c in ['A', 'B', 'C', 'D'] {
+            screen.linefeed();
+            screen.carriage_return();
+            screen.print(c);
+        }
+        screen.place_image(pixel(), 1, 1, true);
+        assert_eq!(screen.scrollback().len(), 3);
+        assert_eq!(screen.image_bytes(), 8);
+
+        // The image on the oldest line is dropped with it
+        let oldest = screen.scrollback().get(0).unwrap().memory_bytes();
+        let budget = screen.scrollback().memory_bytes() - oldest;
+        assert_eq!(screen.trim_scrollback(budget), 1);
+        assert_eq!(screen.scrollback().len(), 2);
+        assert_eq!(screen.image_bytes(), 4);
+
+        assert_eq!(screen.trim_images(0), 1);
+        assert_eq!(screen.image_bytes(), 0);
+    }
+
+    #[test]
+    fn test_screen_marks_follow_lines_into_scrollback() {
+        let mut screen = Screen::new(Dimensions::new(80, 3));
+        for c in ['A', 'B', 'C', 'D', 'E'] {
+            if c == 'B' || c == 'D' {
+                screen.set_line_mark(screen.cursor().row);
+            }
+            screen.print(c);
+            screen.linefeed();
+            screen.carriage_return();
+        }
+        // A, B, C in scrollback; D, E, blank on screen
+        assert_eq!(screen.scrollback().len(), 3);
+        assert_eq!(screen.history_line(1).unwrap().cell(0).display_char(), 'B');
+        assert_eq!(screen.history_line(3).unwrap().cell(0).display_char(), 'D');
+        assert!(screen.history_line(6).is_none());
+
+        assert_eq!(screen.previous_mark(6), Some(3));
+        assert_eq!(screen.previous_mark(3), Some(1));
+        assert_eq!(screen.previous_mark(1), None);
+        assert_eq!(screen.next_mark(0), Some(1));
+        assert_eq!(screen.next_mark(1), Some(3));
+        assert_eq!(screen.next_mark(3), None);
+    }
+
     #[test]
     fn test_screen_cursor_movement() {
         let mut screen = Screen::new(Dimensions::new(80, 24));
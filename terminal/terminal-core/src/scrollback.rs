@@ -20,6 +20,12 @@ pub struct Scrollback {
     start: usize,
     /// Number of lines currently stored
     len: usize,
+    /// Lines pushed so far, including those dropped since
+    #[serde(default)]
+    pushed: u64,
+    /// Memory held by the stored lines (see `Line::memory_bytes`)
+    #[serde(skip)]
+    bytes: usize,
 }
 
 impl Scrollback {
@@ -30,6 +36,8 @@ impl Scrollback {
             max_lines,
             start: 0,
             len: 0,
+            pushed: 0,
+            bytes: 0,
         }
     }
 
@@ -43,6 +51,20 @@ impl Scrollback {
         self.len
     }
 
+    /// Number of lines pushed since the buffer was created
+    ///
+    /// Unlike `len`, this keeps growing once the buffer is full (and is not
+    /// reset by `clear`), so the difference between two readings is how many
+    /// lines scrolled into the history in between.
+    pub fn pushed(&self) -> u64 {
+        self.pushed
+    }
+
+    /// Approximate memory held by the stored lines, in bytes
+    pub fn memory_bytes(&self) -> usize {
+        self.bytes
+    }
+
     /// Check if the scrollback is empty
     pub fn is_empty(&self) -> bool {
         self.len == 0
@@ -53,6 +75,8 @@ impl Scrollback {
         if self.max_lines == 0 {
             return;
         }
+        self.pushed += 1;
+        self.bytes += line.memory_bytes();
 
         if self.lines.len() < self.max_lines {
             // Buffer not yet full, just append
@@ -61,7 +85,10 @@ impl Scrollback {
         } else {
             // Buffer full, overwrite oldest
             let idx = (self.start + self.len) % self.max_lines;
-            self.lines[idx] = line;
+            let old = std::mem::replace(&mut self.lines[idx], line);
+            if self.len == self.max_lines {
+                self.bytes = self.bytes.saturating_sub(old.memory_bytes());
+            }
             if self.len < self.max_lines {
                 self.len += 1;
             } else {
@@ -100,6 +127,25 @@ impl Scrollback {
         self.lines.clear();
         self.start = 0;
         self.len = 0;
+        self.bytes = 0;
+    }
+
+    /// Drop the oldest lines until the rest hold at most `max_bytes`
+    ///
+    /// Returns the number of lines dropped.
+    pub fn trim_to_bytes(&mut self, max_bytes: usize) -> usize {
+        let mut dropped = 0;
+        while self.bytes > max_bytes && self.len > 0 {
+            let old = std::mem::replace(&mut self.lines[self.start], Line::new(0));
+            self.bytes = self.bytes.saturating_sub(old.memory_bytes());
+            self.start = (self.start + 1) % self.lines.len();
+            self.len -= 1;
+            dropped += 1;
+        }
+        if self.len == 0 {
+            self.clear();
+        }
+        dropped
     }
 
     /// Resize the maximum scrollback size
@@ -135,6 +181,7 @@ impl Scrollback {
 
         self.max_lines = max_lines;
         self.lines.truncate(max_lines);
+        self.bytes = self.iter().map(Line::memory_bytes).sum();
     }
 
     /// Iterator over lines from oldest to newest
@@ -297,4 +344,28 @@ mod tests {
         // Should keep newest lines
         assert_eq!(sb.get(0).unwrap().text(), "line5");
     }
+
+    #[test]
+    fn test_scrollback_trim_to_bytes() {
+        let mut sb = Scrollback::new(4);
+        for i in 0..6 {
+            sb.push(make_line(&format!("line{}", i)));
+        }
+        let line_bytes = sb.get(0).unwrap().memory_bytes();
+        assert_eq!(sb.memory_bytes(), 4 * line_bytes);
+
+        // The oldest lines go first, and their slots are reused
+        assert_eq!(sb.trim_to_bytes(2 * line_bytes + 1), 2);
+        assert_eq!(sb.memory_bytes(), 2 * line_bytes);
+        let texts: Vec<_> = sb.iter().map(|l| l.text()).collect();
+        assert_eq!(texts, vec!["line4", "line5"]);
+        sb.push(make_line("line6"));
+        assert_eq!(sb.get(0).unwrap().text(), "line4");
+        assert_eq!(sb.get(2).unwrap().text(), "line6");
+        assert_eq!(sb.memory_bytes(), 3 * line_bytes);
+
+        assert_eq!(sb.trim_to_bytes(0), 3);
+        assert!(sb.is_empty());
+        assert_eq!(sb.memory_bytes(), 0);
+    }
 }
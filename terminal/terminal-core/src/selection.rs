@@ -126,6 +126,39 @@ impl Selection {
         }
     }
 
+    /// Move the selection up by n rows along with the text it covers,
+    /// dropping it once it is above `min_row`
+    ///
+    /// Only rows at or above `bottom` scroll: a selection below it (under
+    /// the scroll region) stays, and one across it is dropped since its
+    /// text is split.
+    pub fn scroll_up(&mut self, n: usize, bottom: isize, min_row: isize) {
+        if !self.active {
+            return;
+        }
+        let (first, last) = self.bounds();
+        if first.row > bottom {
+            return;
+        }
+        if last.row > bottom {
+            self.active = false;
+            return;
+        }
+        self.start.row -= n as isize;
+        self.end.row -= n as isize;
+        let (start, end) = if self.start.row <= self.end.row {
+            (&mut self.start, &mut self.end)
+        } else {
+            (&mut self.end, &mut self.start)
+        };
+        if end.row < min_row {
+            self.active = false;
+        } else if start.row < min_row {
+            // The oldest rows left the history; keep the part that is left
+            *start = Point::new(0, min_row);
+        }
+    }
+
     /// Check if selection spans multiple lines
     pub fn is_multiline(&self) -> bool {
         self.active && self.start.row != self.end.row
@@ -255,6 +288,38 @@ mod tests {
         assert!(!sel.contains(16, 11));
     }
 
+    #[test]
+    fn test_selection_scroll_up() {
+        let mut sel = Selection::new();
+        sel.start(Point::new(5, 2), SelectionType::Normal);
+        sel.update(Point::new(3, 4));
+
+        sel.scroll_up(3, 23, -10);
+        assert_eq!(sel.bounds(), (Point::new(5, -1), Point::new(3, 1)));
+
+        // The start falls out of the history
+        sel.scroll_up(10, 23, -10);
+        assert_eq!(sel.bounds(), (Point::new(0, -10), Point::new(3, -9)));
+        sel.scroll_up(2, 23, -10);
+        assert!(!sel.active);
+    }
+
+    #[test]
+    fn test_selection_scroll_up_below_region() {
+        // Under the bottom margin the selection stays with its text
+        let mut sel = Selection::new();
+        sel.start(Point::new(0, 22), SelectionType::Line);
+        sel.update(Point::new(5, 23));
+        sel.scroll_up(2, 19, -10);
+        assert_eq!(sel.bounds(), (Point::new(0, 22), Point::new(5, 23)));
+
+        // Across the margin its text is split apart
+        sel.start(Point::new(0, 18), SelectionType::Normal);
+        sel.update(Point::new(5, 21));
+        sel.scroll_up(1, 19, -10);
+        assert!(!sel.active);
+    }
+
     #[test]
     fn test_selection_clear() {
         let mut sel = Selection::new();
@@ -6,6 +6,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::cursor::{Cursor, CursorStyle};
 use crate::grid::Grid;
+use crate::line::{Line, LineMeta};
 use crate::modes::Modes;
 use crate::scrollback::Scrollback;
 
@@ -28,6 +29,9 @@ pub struct Snapshot {
     /// Window title
     #[serde(skip_serializing_if = "Option::is_none")]
     pub title: Option<String>,
+    /// Columns with a tab stop (if known)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tab_stops: Option<Vec<usize>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -54,6 +58,20 @@ pub struct SnapshotLine {
     /// Attribute spans (for detailed comparison)
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub attrs: Vec<SnapshotAttrSpan>,
+    /// Line annotations (zone, mark, trigger)
+    #[serde(default, skip_serializing_if = "LineMeta::is_empty")]
+    pub meta: LineMeta,
+}
+
+impl SnapshotLine {
+    fn new(line: &Line) -> Self {
+        Self {
+            text: line.text(),
+            wrapped: line.wrapped,
+            attrs: extract_attr_spans(line),
+            meta: line.meta,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -72,6 +90,13 @@ pub struct SnapshotAttrSpan {
     pub underline: bool,
     #[serde(skip_serializing_if = "std::ops::Not::not")]
     pub inverse: bool,
+    /// Hyperlink ID (see `Screen::get_hyperlink`), 0 for none
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub hyperlink: u32,
+}
+
+fn is_zero(id: &u32) -> bool {
+    *id == 0
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -97,25 +122,10 @@ impl Snapshot {
     ) -> Self {
         let dims = grid.dimensions();
 
-        let screen: Vec<SnapshotLine> = grid
-            .iter()
-            .map(|line| SnapshotLine {
-                text: line.text(),
-                wrapped: line.wrapped,
-                attrs: extract_attr_spans(line),
-            })
-            .collect();
+        let screen: Vec<SnapshotLine> = grid.iter().map(SnapshotLine::new).collect();
 
         let scrollback_lines = if include_scrollback {
-            scrollback.map(|sb| {
-                sb.iter()
-                    .map(|line| SnapshotLine {
-                        text: line.text(),
-                        wrapped: line.wrapped,
-                        attrs: extract_attr_spans(line),
-                    })
-                    .collect()
-            })
+            scrollback.map(|sb| sb.iter().map(SnapshotLine::new).collect())
         } else {
             None
         };
@@ -147,6 +157,7 @@ impl Snapshot {
             },
             scroll_region,
             title: title.map(|s| s.to_string()),
+            tab_stops: None,
         }
     }
 
@@ -171,7 +182,7 @@ impl Snapshot {
 }
 
 /// Extract attribute spans from a line
-fn extract_attr_spans(line: &crate::line::Line) -> Vec<SnapshotAttrSpan> {
+fn extract_attr_spans(line: &Line) -> Vec<SnapshotAttrSpan> {
     use crate::color::Color;
 
     let mut spans = Vec::new();
@@ -187,6 +198,7 @@ fn extract_attr_spans(line: &crate::line::Line) -> Vec<SnapshotAttrSpan> {
             || attrs.italic
             || attrs.underline
             || attrs.inverse
+            || cell.hyperlink_id != 0
             || attrs.fg != Color::Default
             || attrs.bg != Color::Default;
 
@@ -219,6 +231,7 @@ fn extract_attr_spans(line: &crate::line::Line) -> Vec<SnapshotAttrSpan> {
                 && span.italic == attrs.italic
                 && span.underline == attrs.underline
                 && span.inverse == attrs.inverse
+                && span.hyperlink == cell.hyperlink_id
             {
                 // Same attributes, continue span
                 continue;
@@ -240,6 +253,7 @@ fn extract_attr_spans(line: &crate::line::Line) -> Vec<SnapshotAttrSpan> {
             italic: attrs.italic,
             underline: attrs.underline,
             inverse: attrs.inverse,
+            hyperlink: cell.hyperlink_id,
         });
     }
 
@@ -0,0 +1,148 @@
+//! Tab stops
+//!
+//! Tracks horizontal tab stops for one screen. Stops default to every 8
+//! columns; HTS, TBC and DECST8C change them. User-set stops survive
+//! resizes: widening keeps the existing stops and adds default stops in
+//! the new columns, narrowing drops the stops past the new width.
+
+/// Default tab stop interval
+pub const DEFAULT_TAB_WIDTH: usize = 8;
+
+/// Horizontal tab stops
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TabStops {
+    /// One flag per column
+    stops: Vec<bool>,
+}
+
+impl TabStops {
+    /// Create tab stops every 8 columns
+    pub fn new(cols: usize) -> Self {
+        let mut tabs = Self {
+            stops: vec![false; cols],
+        };
+        tabs.reset();
+        tabs
+    }
+
+    /// Number of columns covered
+    pub fn cols(&self) -> usize {
+        self.stops.len()
+    }
+
+    /// Check if there is a tab stop at a column
+    pub fn is_set(&self, col: usize) -> bool {
+        self.stops.get(col).copied().unwrap_or(false)
+    }
+
+    /// Set a tab stop (HTS)
+    pub fn set(&mut self, col: usize) {
+        if let Some(stop) = self.stops.get_mut(col) {
+            *stop = true;
+        }
+    }
+
+    /// Clear the tab stop at a column (TBC 0)
+    pub fn clear(&mut self, col: usize) {
+        if let Some(stop) = self.stops.get_mut(col) {
+            *stop = false;
+        }
+    }
+
+    /// Clear all tab stops (TBC 3)
+    pub fn clear_all(&mut self) {
+        self.stops.fill(false);
+    }
+
+    /// Reset to a tab stop every 8 columns (DECST8C)
+    pub fn reset(&mut self) {
+        for (col, stop) in self.stops.iter_mut().enumerate() {
+            *stop = col % DEFAULT_TAB_WIDTH == 0;
+        }
+    }
+
+    /// Resize to a new width, keeping existing stops
+    ///
+    /// New columns get the default stops.
+    pub fn resize(&mut self, cols: usize) {
+        let old_cols = self.stops.len();
+        self.stops.truncate(cols);
+        self.stops
+            .extend((old_cols..cols).map(|col| col % DEFAULT_TAB_WIDTH == 0));
+    }
+
+    /// Find the next tab stop after a column
+    pub fn next(&self, col: usize) -> Option<usize> {
+        (col + 1..self.stops.len()).find(|&c| self.stops[c])
+    }
+
+    /// Find the previous tab stop before a column
+    pub fn prev(&self, col: usize) -> Option<usize> {
+        (0..col.min(self.stops.len()))
+            .rev()
+            .find(|&c| self.stops[c])
+    }
+
+    /// Columns with a tab stop, in order
+    pub fn positions(&self) -> Vec<usize> {
+        self.stops
+            .iter()
+            .enumerate()
+            .filter_map(|(col, &stop)| stop.then_some(col))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_stops() {
+        let tabs = TabStops::new(20);
+        assert_eq!(tabs.positions(), vec![0, 8, 16]);
+    }
+
+    #[test]
+    fn test_next_and_prev() {
+        let tabs = TabStops::new(20);
+        assert_eq!(tabs.next(0), Some(8));
+        assert_eq!(tabs.next(16), None);
+        assert_eq!(tabs.prev(16), Some(8));
+        assert_eq!(tabs.prev(9), Some(8));
+        assert_eq!(tabs.prev(0), None);
+    }
+
+    #[test]
+    fn test_set_and_clear() {
+        let mut tabs = TabStops::new(20);
+        tabs.set(3);
+        tabs.clear(8);
+        tabs.set(100);
+        assert_eq!(tabs.positions(), vec![0, 3, 16]);
+
+        tabs.clear_all();
+        assert!(tabs.positions().is_empty());
+
+        tabs.reset();
+        assert_eq!(tabs.positions(), vec![0, 8, 16]);
+    }
+
+    #[test]
+    fn test_widen_keeps_user_stops() {
+        let mut tabs = TabStops::new(20);
+        tabs.set(3);
+        tabs.clear(16);
+        tabs.resize(40);
+        assert_eq!(tabs.positions(), vec![0, 3, 8, 24, 32]);
+    }
+
+    #[test]
+    fn test_narrow_drops_stops_past_width() {
+        let mut tabs = TabStops::new(40);
+        tabs.set(30);
+        tabs.resize(20);
+        assert_eq!(tabs.cols(), 20);
+        assert_eq!(tabs.positions(), vec![0, 8, 16]);
+    }
+}
@@ -456,6 +456,8 @@ fn test_cell_attrs_new_defaults() {
     assert!(!attrs.faint);
     assert!(!attrs.italic);
     assert!(!attrs.underline);
+    assert!(!attrs.double_underline);
+    assert!(!attrs.overline);
     assert!(!attrs.blink);
     assert!(!attrs.inverse);
     assert!(!attrs.hidden);
@@ -0,0 +1,530 @@
+//! Conformance tests for origin mode, scroll regions and autowrap
+//!
+//! Scenarios follow vttest's cursor movement, origin mode and scrolling
+//! screens, checked against xterm's behavior.
+
+use terminal_core::{Dimensions, Screen};
+
+fn print_str(screen: &mut Screen, s: &str) {
+    for c in s.chars() {
+        screen.print(c);
+    }
+}
+
+fn cursor(screen: &Screen) -> (usize, usize) {
+    (screen.cursor().row, screen.cursor().col)
+}
+
+/// Screen with each row labelled with a letter in the first column
+fn labelled_screen(cols: usize, rows: usize) -> Screen {
+    let mut screen = Screen::new(Dimensions::new(cols, rows));
+    for row in 0..rows {
+        screen.move_cursor_to(row + 1, 1);
+        screen.print((b'A' + row as u8) as char);
+    }
+    screen
+}
+
+fn first_column(screen: &Screen) -> String {
+    (0..screen.rows())
+        .map(|row| {
+            let cell = screen.line(row).cell(0);
+            if cell.is_empty() {
+                '.'
+            } else {
+                cell.display_char()
+            }
+        })
+        .collect()
+}
+
+// ============================================================
+// DECSTBM
+// ============================================================
+
+#[test]
+fn test_decstbm_homes_cursor() {
+    let mut screen = Screen::new(Dimensions::new(80, 24));
+    screen.move_cursor_to(10, 10);
+    screen.set_scroll_region(5, 20);
+    assert_eq!(cursor(&screen), (0, 0));
+}
+
+#[test]
+fn test_decstbm_homes_cursor_to_margin_in_origin_mode() {
+    let mut screen = Screen::new(Dimensions::new(80, 24));
+    screen.set_origin_mode(true);
+    screen.set_scroll_region(5, 20);
+    assert_eq!(cursor(&screen), (4, 0));
+}
+
+#[test]
+fn test_decstbm_default_bottom() {
+    let mut screen = Screen::new(Dimensions::new(80, 24));
+    screen.set_scroll_region(5, 0);
+    assert_eq!(screen.scroll_region(), (4, 23));
+}
+
+#[test]
+fn test_decstbm_full_screen_clears_region() {
+    let mut screen = Screen::new(Dimensions::new(80, 24));
+    screen.set_scroll_region(5, 20);
+    screen.set_scroll_region(1, 24);
+    assert_eq!(screen.snapshot(false).scroll_region, None);
+}
+
+// ============================================================
+// DECOM
+// ============================================================
+
+#[test]
+fn test_decom_homes_to_margin_top() {
+    let mut screen = Screen::new(Dimensions::new(80, 24));
+    screen.set_scroll_region(5, 10);
+    screen.move_cursor_to(20, 20);
+    screen.set_origin_mode(true);
+    assert_eq!(cursor(&screen), (4, 0));
+
+    screen.move_cursor_to(20, 20);
+    screen.set_origin_mode(false);
+    assert_eq!(cursor(&screen), (0, 0));
+}
+
+#[test]
+fn test_decom_cup_relative_to_margins() {
+    let mut screen = Screen::new(Dimensions::new(80, 24));
+    screen.set_scroll_region(5, 10);
+    screen.set_origin_mode(true);
+
+    screen.move_cursor_to(1, 1);
+    assert_eq!(cursor(&screen), (4, 0));
+    screen.move_cursor_to(3, 7);
+    assert_eq!(cursor(&screen), (6, 6));
+}
+
+#[test]
+fn test_decom_cup_clamped_to_bottom_margin() {
+    let mut screen = Screen::new(Dimensions::new(80, 24));
+    screen.set_scroll_region(5, 10);
+    screen.set_origin_mode(true);
+
+    screen.move_cursor_to(100, 100);
+    assert_eq!(cursor(&screen), (9, 79));
+}
+
+#[test]
+fn test_decom_vpa_relative_to_margins() {
+    let mut screen = Screen::new(Dimensions::new(80, 24));
+    screen.set_scroll_region(5, 10);
+    screen.set_origin_mode(true);
+
+    screen.set_cursor_row(2);
+    assert_eq!(screen.cursor().row, 5);
+    screen.set_cursor_row(50);
+    assert_eq!(screen.cursor().row, 9);
+}
+
+#[test]
+fn test_decom_cursor_cannot_leave_region() {
+    let mut screen = Screen::new(Dimensions::new(80, 24));
+    screen.set_scroll_region(5, 10);
+    screen.set_origin_mode(true);
+
+    screen.move_cursor_up(100);
+    assert_eq!(screen.cursor().row, 4);
+    screen.move_cursor_down(100);
+    assert_eq!(screen.cursor().row, 9);
+}
+
+#[test]
+fn test_decrc_restores_origin_mode() {
+    let mut screen = Screen::new(Dimensions::new(80, 24));
+    screen.set_scroll_region(5, 10);
+    screen.set_origin_mode(true);
+    screen.save_cursor();
+    screen.set_origin_mode(false);
+
+    screen.restore_cursor();
+    assert!(screen.modes().origin_mode);
+    screen.move_cursor_to(1, 1);
+    assert_eq!(cursor(&screen), (4, 0));
+}
+
+// ============================================================
+// Cursor Movement at Margins
+// ============================================================
+
+#[test]
+fn test_cuu_stops_at_top_margin() {
+    let mut screen = Screen::new(Dimensions::new(80, 24));
+    screen.set_scroll_region(5, 10);
+    screen.move_cursor_to(8, 1);
+    screen.move_cursor_up(100);
+    assert_eq!(screen.cursor().row, 4);
+}
+
+#[test]
+fn test_cuu_above_region_reaches_first_row() {
+    let mut screen = Screen::new(Dimensions::new(80, 24));
+    screen.set_scroll_region(5, 10);
+    screen.move_cursor_to(3, 1);
+    screen.move_cursor_up(100);
+    assert_eq!(screen.cursor().row, 0);
+}
+
+#[test]
+fn test_cud_stops_at_bottom_margin() {
+    let mut screen = Screen::new(Dimensions::new(80, 24));
+    screen.set_scroll_region(5, 10);
+    screen.move_cursor_to(8, 1);
+    screen.move_cursor_down(100);
+    assert_eq!(screen.cursor().row, 9);
+}
+
+#[test]
+fn test_cud_below_region_reaches_last_row() {
+    let mut screen = Screen::new(Dimensions::new(80, 24));
+    screen.set_scroll_region(5, 10);
+    screen.move_cursor_to(15, 1);
+    screen.move_cursor_down(100);
+    assert_eq!(screen.cursor().row, 23);
+}
+
+#[test]
+fn test_cup_ignores_margins_without_origin_mode() {
+    let mut screen = Screen::new(Dimensions::new(80, 24));
+    screen.set_scroll_region(5, 10);
+    screen.move_cursor_to(1, 1);
+    assert_eq!(cursor(&screen), (0, 0));
+    screen.move_cursor_to(24, 80);
+    assert_eq!(cursor(&screen), (23, 79));
+}
+
+// ============================================================
+// IND / RI / NEL at Margins
+// ============================================================
+
+#[test]
+fn test_ind_at_bottom_margin_scrolls_region() {
+    let mut screen = labelled_screen(10, 8);
+    screen.set_scroll_region(3, 6);
+    screen.move_cursor_to(6, 1);
+    screen.index();
+
+    assert_eq!(first_column(&screen), "ABDEF.GH");
+    assert_eq!(screen.cursor().row, 5);
+}
+
+#[test]
+fn test_ind_below_region_stops_at_last_row() {
+    let mut screen = labelled_screen(10, 8);
+    screen.set_scroll_region(3, 6);
+    screen.move_cursor_to(8, 1);
+    screen.index();
+
+    // Nothing scrolls and the cursor stays put
+    assert_eq!(first_column(&screen), "ABCDEFGH");
+    assert_eq!(screen.cursor().row, 7);
+}
+
+#[test]
+fn test_ind_below_region_moves_down() {
+    let mut screen = labelled_screen(10, 8);
+    screen.set_scroll_region(3, 6);
+    screen.move_cursor_to(7, 1);
+    screen.index();
+
+    assert_eq!(first_column(&screen), "ABCDEFGH");
+    assert_eq!(screen.cursor().row, 7);
+}
+
+#[test]
+fn test_ri_at_top_margin_scrolls_region() {
+    let mut screen = labelled_screen(10, 8);
+    screen.set_scroll_region(3, 6);
+    screen.move_cursor_to(3, 1);
+    screen.reverse_index();
+
+    assert_eq!(first_column(&screen), "AB.CDEGH");
+    assert_eq!(screen.cursor().row, 2);
+}
+
+#[test]
+fn test_ri_above_region_stops_at_first_row() {
+    let mut screen = labelled_screen(10, 8);
+    screen.set_scroll_region(3, 6);
+    screen.move_cursor_to(1, 1);
+    screen.reverse_index();
+
+    assert_eq!(first_column(&screen), "ABCDEFGH");
+    assert_eq!(screen.cursor().row, 0);
+}
+
+#[test]
+fn test_nel_at_bottom_margin_scrolls_region() {
+    let mut screen = labelled_screen(10, 8);
+    screen.set_scroll_region(3, 6);
+    screen.move_cursor_to(6, 5);
+    screen.next_line();
+
+    assert_eq!(first_column(&screen), "ABDEF.GH");
+    assert_eq!(cursor(&screen), (5, 0));
+}
+
+#[test]
+fn test_region_scroll_keeps_scrollback_empty() {
+    let mut screen = labelled_screen(10, 8);
+    screen.set_scroll_region(3, 6);
+    screen.move_cursor_to(6, 1);
+    for _ in 0..10 {
+        screen.linefeed();
+    }
+    assert_eq!(screen.scrollback().len(), 0);
+    assert_eq!(first_column(&screen), "AB....GH");
+}
+
+fn scrollback_first_column(screen: &Screen) -> String {
+    let history = screen.scrollback();
+    (0..history.len())
+        .map(|i| history.get(i).unwrap().cell(0).display_char())
+        .collect()
+}
+
+#[test]
+fn test_region_at_top_scrolls_into_scrollback() {
+    // Output scrolling above a status line leaves the screen
+    let mut screen = labelled_screen(10, 8);
+    screen.set_scroll_region(1, 7);
+    screen.move_cursor_to(7, 1);
+    screen.linefeed();
+    screen.linefeed();
+
+    assert_eq!(scrollback_first_column(&screen), "AB");
+    assert_eq!(first_column(&screen), "CDEFG..H");
+}
+
+#[test]
+fn test_vim_viewport_scroll_keeps_scrollback_empty() {
+    // A lower window (rows 4-7 under a split, status line on 8) scrolled a
+    // screenful at a time, the way vim does it: by line feeds at the bottom
+    // of the window's region, and by deleting lines at its top
+    let mut screen = labelled_screen(10, 8);
+    for _ in 0..20 {
+        screen.set_scroll_region(4, 7);
+        screen.move_cursor_to(7, 1);
+        for _ in 0..4 {
+            screen.linefeed();
+        }
+        screen.move_cursor_to(4, 1);
+        screen.delete_lines(2);
+        screen.set_scroll_region(0, 0);
+    }
+    assert_eq!(screen.scrollback().len(), 0);
+
+    // The upper window starts on the first row; vim scrolls it with DL and
+    // IL, which never add to the history either
+    for _ in 0..20 {
+        screen.set_scroll_region(1, 3);
+        screen.move_cursor_to(1, 1);
+        screen.delete_lines(1);
+        screen.insert_lines(1);
+        screen.reverse_index();
+        screen.set_scroll_region(0, 0);
+    }
+    assert_eq!(screen.scrollback().len(), 0);
+}
+
+#[test]
+fn test_scroll_up_only_pushes_from_top() {
+    let mut screen = labelled_screen(10, 8);
+    screen.set_scroll_region(3, 6);
+    screen.scroll_up(2);
+    screen.scroll_down(2);
+    assert_eq!(screen.scrollback().len(), 0);
+
+    screen.set_scroll_region(0, 0);
+    screen.scroll_up(2);
+    assert_eq!(scrollback_first_column(&screen), "AB");
+}
+
+// ============================================================
+// Autowrap and Pending Wrap (xenl)
+// ============================================================
+
+#[test]
+fn test_pending_wrap_at_last_column() {
+    let mut screen = Screen::new(Dimensions::new(10, 5));
+    print_str(&mut screen, "0123456789");
+
+    // The cursor stays on the last column until the next character
+    assert_eq!(cursor(&screen), (0, 9));
+    assert!(screen.cursor().pending_wrap);
+
+    screen.print('X');
+    assert_eq!(cursor(&screen), (1, 1));
+    assert_eq!(screen.line(1).text(), "X");
+    assert!(screen.line(0).wrapped);
+}
+
+#[test]
+fn test_pending_wrap_cleared_by_cr() {
+    let mut screen = Screen::new(Dimensions::new(10, 5));
+    print_str(&mut screen, "0123456789");
+    screen.carriage_return();
+    screen.print('X');
+    assert_eq!(screen.line(0).text(), "X123456789");
+    assert!(screen.line(1).text().is_empty());
+}
+
+#[test]
+fn test_pending_wrap_cleared_by_cursor_movement() {
+    let mut screen = Screen::new(Dimensions::new(10, 5));
+    print_str(&mut screen, "0123456789");
+    screen.move_cursor_left(1);
+    screen.print('X');
+    assert_eq!(screen.line(0).text(), "01234567X9");
+    assert_eq!(cursor(&screen), (0, 9));
+}
+
+#[test]
+fn test_pending_wrap_backspace() {
+    let mut screen = Screen::new(Dimensions::new(10, 5));
+    print_str(&mut screen, "0123456789");
+    screen.backspace();
+    assert_eq!(cursor(&screen), (0, 8));
+    assert!(!screen.cursor().pending_wrap);
+}
+
+#[test]
+fn test_pending_wrap_linefeed_keeps_column() {
+    let mut screen = Screen::new(Dimensions::new(10, 5));
+    print_str(&mut screen, "0123456789");
+    screen.linefeed();
+    assert_eq!(cursor(&screen), (1, 9));
+    screen.print('X');
+    assert_eq!(screen.line(1).cell(9).display_char(), 'X');
+}
+
+#[test]
+fn test_pending_wrap_at_bottom_margin_scrolls_region() {
+    let mut screen = labelled_screen(10, 8);
+    screen.set_scroll_region(3, 6);
+    screen.move_cursor_to(6, 1);
+    print_str(&mut screen, "FFFFFFFFFFx");
+
+    assert_eq!(first_column(&screen), "ABDEFxGH");
+    assert_eq!(cursor(&screen), (5, 1));
+}
+
+#[test]
+fn test_pending_wrap_at_last_row_below_region() {
+    let mut screen = labelled_screen(10, 8);
+    screen.set_scroll_region(3, 6);
+    screen.move_cursor_to(8, 1);
+    print_str(&mut screen, "HHHHHHHHHHx");
+
+    // No scrolling outside the region: the wrap returns to the first column
+    assert_eq!(first_column(&screen), "ABCDEFGx");
+    assert_eq!(cursor(&screen), (7, 1));
+}
+
+#[test]
+fn test_no_autowrap_overwrites_last_column() {
+    let mut screen = Screen::new(Dimensions::new(10, 5));
+    screen.modes_mut().auto_wrap = false;
+    print_str(&mut screen, "0123456789XYZ");
+    assert_eq!(screen.line(0).text(), "012345678Z");
+    assert_eq!(cursor(&screen), (0, 9));
+    assert!(!screen.cursor().pending_wrap);
+}
+
+#[test]
+fn test_vttest_wraparound_fill() {
+    // vttest screen 1: fill the screen edge to edge with wrapping text
+    let mut screen = Screen::new(Dimensions::new(10, 3));
+    print_str(&mut screen, &"*".repeat(30));
+
+    for row in 0..3 {
+        assert_eq!(screen.line(row).text(), "**********");
+    }
+    assert_eq!(cursor(&screen), (2, 9));
+    assert_eq!(screen.scrollback().len(), 0);
+}
+
+#[test]
+fn test_vttest_box_inside_margins() {
+    // vttest screen 1: draw a box with absolute positioning and relative
+    // moves clamped at the margins
+    let mut screen = Screen::new(Dimensions::new(10, 6));
+    screen.set_scroll_region(2, 5);
+    screen.set_origin_mode(true);
+
+    for col in 1..=10 {
+        screen.move_cursor_to(1, col);
+        screen.print('*');
+        screen.move_cursor_to(4, col);
+        screen.print('*');
+    }
+    // Left edge walks down from the top, right edge walks up from the bottom
+    screen.move_cursor_to(2, 1);
+    for _ in 0..2 {
+        screen.print('*');
+        screen.move_cursor_left(1);
+        screen.move_cursor_down(1);
+    }
+    screen.move_cursor_to(100, 10);
+    screen.move_cursor_up(1);
+    for _ in 0..2 {
+        screen.print('*');
+        screen.move_cursor_up(1);
+    }
+
+    // Relative moves never leave the region
+    screen.move_cursor_up(100);
+    assert_eq!(screen.cursor().row, 1);
+
+    assert!(screen.line(0).text().is_empty());
+    assert_eq!(screen.line(1).text(), "**********");
+    assert_eq!(screen.line(2).text(), "*        *");
+    assert_eq!(screen.line(3).text(), "*        *");
+    assert_eq!(screen.line(4).text(), "**********");
+    assert!(screen.line(5).text().is_empty());
+}
+
+// ============================================================
+// DECALN
+// ============================================================
+
+#[test]
+fn test_decaln_fills_screen_and_homes_cursor() {
+    let mut screen = labelled_screen(10, 5);
+    screen.set_scroll_region(2, 4);
+    screen.move_cursor_to(3, 7);
+    screen.cursor_mut().attrs.bold = true;
+    screen.alignment_test();
+
+    for row in 0..5 {
+        assert_eq!(screen.line(row).text(), "EEEEEEEEEE");
+        assert!(!screen.line(row).wrapped);
+    }
+    // Filled in the default rendition; the cursor keeps its attributes
+    assert!(!screen.line(2).cell(3).attrs.bold);
+    assert!(screen.cursor().attrs.bold);
+    assert_eq!(cursor(&screen), (0, 0));
+    assert_eq!(screen.scroll_region(), (0, 4));
+}
+
+#[test]
+fn test_decaln_resets_margins_in_origin_mode() {
+    let mut screen = Screen::new(Dimensions::new(10, 5));
+    screen.set_scroll_region(2, 4);
+    screen.set_origin_mode(true);
+    screen.alignment_test();
+    assert_eq!(cursor(&screen), (0, 0));
+
+    // Linefeeds now scroll the whole screen
+    screen.move_cursor_to(5, 1);
+    screen.linefeed();
+    assert_eq!(screen.line(3).text(), "EEEEEEEEEE");
+    assert!(screen.line(4).text().is_empty());
+}
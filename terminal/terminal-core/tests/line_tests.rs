@@ -341,6 +341,134 @@ fn test_line_erase_cells_overflow() {
     assert!(line.cell(4).is_empty());
 }
 
+// ============================================================
+// Wide Character Repair Tests
+// ============================================================
+
+/// Line "a中b" followed by blanks
+fn wide_line(cols: usize) -> Line {
+    let mut line = Line::new(cols);
+    line.cell_mut(0).set_char('a');
+    line.cell_mut(1).set_char('中');
+    line.cell_mut(2).set_continuation();
+    line.cell_mut(3).set_char('b');
+    line
+}
+
+fn assert_no_broken_wide_chars(line: &Line) {
+    for col in 0..line.cols() {
+        let cell = line.cell(col);
+        if cell.is_continuation() {
+            assert!(
+                col > 0 && line.cell(col - 1).width() == 2,
+                "orphan at {col}"
+            );
+        } else if cell.width() == 2 {
+            assert!(
+                col + 1 < line.cols() && line.cell(col + 1).is_continuation(),
+                "split wide char at {col}"
+            );
+        }
+    }
+}
+
+#[test]
+fn test_line_insert_cells_splitting_wide_char() {
+    let mut line = wide_line(8);
+    line.insert_cells(2, 1, CellAttributes::default());
+    assert_eq!(line.text(), "a   b");
+    assert_no_broken_wide_chars(&line);
+}
+
+#[test]
+fn test_line_insert_cells_before_wide_char() {
+    let mut line = wide_line(8);
+    line.insert_cells(1, 2, CellAttributes::default());
+    assert_eq!(line.text(), "a  中b");
+    assert_no_broken_wide_chars(&line);
+}
+
+#[test]
+fn test_line_insert_cells_pushes_wide_char_half_off() {
+    let mut line = wide_line(4);
+    line.insert_cells(0, 2, CellAttributes::default());
+    // The continuation shifted off the end, so the wide char is blanked
+    assert_eq!(line.text(), "  a");
+    assert_no_broken_wide_chars(&line);
+}
+
+#[test]
+fn test_line_delete_cells_splitting_wide_char() {
+    let mut line = wide_line(8);
+    line.delete_cells(1, 1, CellAttributes::default());
+    assert_eq!(line.text(), "a b");
+    assert_no_broken_wide_chars(&line);
+}
+
+#[test]
+fn test_line_delete_whole_wide_char() {
+    let mut line = wide_line(8);
+    line.delete_cells(1, 2, CellAttributes::default());
+    assert_eq!(line.text(), "ab");
+    assert_no_broken_wide_chars(&line);
+}
+
+#[test]
+fn test_line_delete_cells_pulls_continuation() {
+    let mut line = wide_line(8);
+    line.delete_cells(0, 2, CellAttributes::default());
+    assert_eq!(line.text(), " b");
+    assert_no_broken_wide_chars(&line);
+}
+
+#[test]
+fn test_line_erase_cells_half_of_wide_char() {
+    let mut line = wide_line(8);
+    line.erase_cells(2, 1, CellAttributes::default());
+    assert_eq!(line.text(), "a  b");
+    assert_no_broken_wide_chars(&line);
+
+    let mut line = wide_line(8);
+    line.erase_cells(1, 1, CellAttributes::default());
+    assert_eq!(line.text(), "a  b");
+    assert_no_broken_wide_chars(&line);
+}
+
+#[test]
+fn test_line_clear_from_inside_wide_char() {
+    let mut line = wide_line(8);
+    line.clear_from(2, CellAttributes::default());
+    assert_eq!(line.text(), "a");
+    assert_no_broken_wide_chars(&line);
+}
+
+#[test]
+fn test_line_clear_to_inside_wide_char() {
+    let mut line = wide_line(8);
+    line.clear_to(1, CellAttributes::default());
+    assert_eq!(line.text(), "   b");
+    assert_no_broken_wide_chars(&line);
+}
+
+#[test]
+fn test_line_resize_splitting_wide_char() {
+    let mut line = wide_line(8);
+    line.resize(2, CellAttributes::default());
+    assert_eq!(line.text(), "a");
+    assert_no_broken_wide_chars(&line);
+}
+
+#[test]
+fn test_line_repair_keeps_attrs() {
+    let mut line = wide_line(8);
+    line.cell_mut(1).attrs.bold = true;
+    line.cell_mut(2).set_char('x');
+    line.repair_wide_chars(2, 2);
+    assert!(line.cell(1).attrs.bold);
+    assert!(line.cell(1).is_empty());
+    assert_eq!(line.cell(2).display_char(), 'x');
+}
+
 // ============================================================
 // Text Extraction Tests
 // ============================================================
@@ -389,6 +517,62 @@ fn test_line_text_with_wide_char() {
     assert!(text.contains('文'));
 }
 
+#[test]
+fn test_line_text_range_and_columns() {
+    let mut line = Line::new(6);
+    line.cell_mut(0).set_char('a');
+    line.cell_mut(1).set_char('中');
+    line.cell_mut(2).set_continuation();
+    line.cell_mut(3).set_char('b');
+    assert_eq!(line.text_range(0, 6), "a中b  ");
+    assert_eq!(line.text_range(2, 4), "b");
+    assert_eq!(line.text_range(4, 100), "  ");
+
+    let (text, cols) = line.text_columns();
+    assert_eq!(text, "a中b  ");
+    // '中' is three bytes in column 1; 'b' is in column 3
+    assert_eq!(cols, [0, 1, 1, 1, 3, 4, 5]);
+}
+
+#[test]
+fn test_line_content_end() {
+    let mut line = Line::new(6);
+    assert_eq!(line.content_end(), 0);
+    line.cell_mut(0).set_char('a');
+    line.cell_mut(2).set_char(' ');
+    assert_eq!(line.content_end(), 1);
+    // A trailing wide character ends after its right half
+    line.cell_mut(3).set_char('中');
+    line.cell_mut(4).set_continuation();
+    assert_eq!(line.content_end(), 5);
+}
+
+#[test]
+fn test_line_runs() {
+    let mut line = Line::new(5);
+    let bold = CellAttributes {
+        bold: true,
+        ..CellAttributes::default()
+    };
+    line.cell_mut(0).set_char('a');
+    for col in 1..3 {
+        line.cell_mut(col).set_char('B');
+        line.cell_mut(col).attrs = bold;
+    }
+    let runs: Vec<_> = line
+        .runs()
+        .map(|run| (run.col, run.cols, run.text, run.attrs.bold))
+        .collect();
+    assert_eq!(
+        runs,
+        [
+            (0, 1, "a".to_string(), false),
+            (1, 2, "BB".to_string(), true),
+            (3, 2, "  ".to_string(), false),
+        ]
+    );
+}
+
 // ============================================================
 // is_empty Tests
 // ============================================================
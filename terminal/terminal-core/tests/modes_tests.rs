@@ -1,6 +1,6 @@
 //! Comprehensive tests for terminal modes
 
-use terminal_core::Modes;
+use terminal_core::{ModeState, Modes, MAX_SAVED_MODES};
 
 // ============================================================
 // Modes Creation Tests
@@ -296,6 +296,15 @@ fn test_mouse_tracking_any_event() {
     assert!(modes.mouse_tracking_enabled());
 }
 
+#[test]
+fn test_set_dec_mode_mouse_sgr_pixels() {
+    let mut modes = Modes::new();
+    assert!(!modes.mouse_sgr_pixels);
+    modes.set_dec_mode(1016, true);
+    assert!(modes.mouse_sgr_pixels);
+    assert!(!modes.mouse_tracking_enabled());
+}
+
 #[test]
 fn test_mouse_tracking_sgr_alone_not_sufficient() {
     let mut modes = Modes::new();
@@ -349,7 +358,7 @@ fn test_modes_inequality() {
 #[test]
 fn test_all_known_dec_modes_set_get() {
     let known_modes: &[u16] = &[
-        1, 2, 3, 4, 5, 6, 7, 8, 9, 25, 1000, 1002, 1003, 1004, 1006, 1049, 2004, 2026,
+        1, 2, 3, 4, 5, 6, 7, 8, 9, 25, 1000, 1002, 1003, 1004, 1006, 1016, 1049, 2004, 2026,
     ];
     for &mode in known_modes {
         let mut modes = Modes::new();
@@ -363,3 +372,63 @@ fn test_all_known_dec_modes_set_get() {
         );
     }
 }
+
+// ============================================================
+// DECRQM State Tests
+// ============================================================
+
+#[test]
+fn test_mode_states() {
+    let mut modes = Modes::new();
+    assert_eq!(modes.dec_mode_state(2004), ModeState::Reset);
+    modes.set_dec_mode(2004, true);
+    assert_eq!(modes.dec_mode_state(2004), ModeState::Set);
+    assert_eq!(modes.dec_mode_state(25), ModeState::Set);
+
+    modes.alternate_screen = true;
+    assert_eq!(modes.dec_mode_state(1047), ModeState::Set);
+    assert_eq!(modes.dec_mode_state(1005), ModeState::PermanentlyReset);
+    assert_eq!(modes.dec_mode_state(9999), ModeState::NotRecognized);
+
+    assert_eq!(modes.mode_state(4), ModeState::Reset);
+    modes.set_mode(4, true);
+    assert_eq!(modes.mode_state(4), ModeState::Set);
+    assert_eq!(modes.mode_state(2), ModeState::PermanentlyReset);
+    assert_eq!(modes.mode_state(99), ModeState::NotRecognized);
+}
+
+#[test]
+fn test_save_restore_dec_mode() {
+    let mut modes = Modes::new();
+    modes.set_dec_mode(2004, true);
+    modes.save_dec_mode(2004);
+    modes.set_dec_mode(2004, false);
+    modes.save_dec_mode(2004);
+    assert_eq!(modes.take_saved_dec_mode(2004), Some(false));
+    assert_eq!(modes.take_saved_dec_mode(2004), Some(true));
+    assert_eq!(modes.take_saved_dec_mode(2004), None);
+
+    // Only modes with a value are saved
+    modes.save_dec_mode(1048);
+    modes.save_dec_mode(9999);
+    assert_eq!(modes.take_saved_dec_mode(1048), None);
+    assert_eq!(modes.take_saved_dec_mode(9999), None);
+
+    // The oldest values are dropped past the limit
+    for _ in 0..MAX_SAVED_MODES + 4 {
+        modes.save_dec_mode(25);
+    }
+    let mut count = 0;
+    while modes.take_saved_dec_mode(25).is_some() {
+        count += 1;
+    }
+    assert_eq!(count, MAX_SAVED_MODES);
+}
+
+#[test]
+fn test_reset_clears_saved_modes() {
+    let mut modes = Modes::new();
+    modes.save_dec_mode(7);
+    modes.reset();
+    assert_eq!(modes.take_saved_dec_mode(7), None);
+}
@@ -1,6 +1,8 @@
 //! Comprehensive tests for terminal screen
 
-use terminal_core::{Charset, Color, Dimensions, Screen};
+use terminal_core::{
+    Charset, Color, Dimensions, ImageData, Point, Screen, SelectionType, TextRegion,
+};
 
 // ============================================================
 // Screen Creation Tests
@@ -288,6 +290,113 @@ fn test_screen_clear_all_tab_stops() {
     assert_eq!(screen.cursor().col, 79);
 }
 
+#[test]
+fn test_screen_tab_forward_count() {
+    let mut screen = Screen::new(Dimensions::new(80, 24));
+    screen.tab_forward(3);
+    assert_eq!(screen.cursor().col, 24);
+    // Stops at the last column
+    screen.tab_forward(100);
+    assert_eq!(screen.cursor().col, 79);
+}
+
+#[test]
+fn test_screen_tab_backward() {
+    let mut screen = Screen::new(Dimensions::new(80, 24));
+    screen.move_cursor_to(1, 21);
+    screen.tab_backward(1);
+    assert_eq!(screen.cursor().col, 16);
+    screen.tab_backward(1);
+    assert_eq!(screen.cursor().col, 8);
+    // Stops at the first column
+    screen.tab_backward(5);
+    assert_eq!(screen.cursor().col, 0);
+}
+
+#[test]
+fn test_screen_tab_backward_from_tab_stop() {
+    let mut screen = Screen::new(Dimensions::new(80, 24));
+    screen.move_cursor_to(1, 17);
+    screen.tab_backward(1);
+    assert_eq!(screen.cursor().col, 8);
+}
+
+#[test]
+fn test_screen_tab_stops_survive_widen() {
+    let mut screen = Screen::new(Dimensions::new(20, 5));
+    screen.clear_tab_stop(3);
+    screen.move_cursor_to(1, 6);
+    screen.set_tab_stop();
+    screen.resize(Dimensions::new(40, 5));
+
+    assert_eq!(screen.tab_stops().positions(), vec![5, 24, 32]);
+    screen.move_cursor_to(1, 1);
+    screen.tab();
+    assert_eq!(screen.cursor().col, 5);
+}
+
+#[test]
+fn test_screen_tab_stops_narrow_then_widen() {
+    let mut screen = Screen::new(Dimensions::new(40, 5));
+    screen.move_cursor_to(1, 4);
+    screen.set_tab_stop();
+    screen.resize(Dimensions::new(10, 5));
+    assert_eq!(screen.tab_stops().positions(), vec![0, 3, 8]);
+
+    // Columns past the narrow width come back with default stops
+    screen.resize(Dimensions::new(40, 5));
+    assert_eq!(screen.tab_stops().positions(), vec![0, 3, 8, 16, 24, 32]);
+}
+
+#[test]
+fn test_screen_reset_tab_stops() {
+    let mut screen = Screen::new(Dimensions::new(30, 5));
+    screen.clear_tab_stop(3);
+    screen.reset_tab_stops();
+    assert_eq!(screen.tab_stops().positions(), vec![0, 8, 16, 24]);
+}
+
+#[test]
+fn test_screen_snapshot_includes_tab_stops() {
+    let mut screen = Screen::new(Dimensions::new(20, 5));
+    screen.move_cursor_to(1, 3);
+    screen.set_tab_stop();
+
+    let snapshot = screen.snapshot(false);
+    assert_eq!(snapshot.tab_stops, Some(vec![0, 2, 8, 16]));
+
+    let json = snapshot.to_json().unwrap();
+    let restored = terminal_core::Snapshot::from_json(&json).unwrap();
+    assert_eq!(restored.tab_stops, Some(vec![0, 2, 8, 16]));
+}
+
+#[test]
+fn test_screen_repeat_last_char() {
+    let mut screen = Screen::new(Dimensions::new(10, 5));
+    screen.print('a');
+    screen.print('b');
+    screen.repeat_last_char(3);
+    assert_eq!(screen.line(0).text(), "abbbb");
+    assert_eq!(screen.cursor().col, 5);
+}
+
+#[test]
+fn test_screen_repeat_wraps() {
+    let mut screen = Screen::new(Dimensions::new(5, 5));
+    screen.print('x');
+    screen.repeat_last_char(6);
+    assert_eq!(screen.line(0).text(), "xxxxx");
+    assert_eq!(screen.line(1).text(), "xx");
+}
+
+#[test]
+fn test_screen_repeat_without_previous_char() {
+    let mut screen = Screen::new(Dimensions::new(10, 5));
+    screen.repeat_last_char(3);
+    assert_eq!(screen.line(0).text(), "");
+    assert_eq!(screen.cursor().col, 0);
+}
+
 // ============================================================
 // Carriage Return / Linefeed Tests
 // ============================================================
@@ -565,6 +674,74 @@ fn test_screen_save_cursor_alternate_screen() {
     assert_eq!(screen.cursor().col, 4);
 }
 
+#[test]
+fn test_screen_save_restore_cursor_charsets() {
+    let mut screen = Screen::new(Dimensions::new(80, 24));
+    screen.designate_charset(0, '0');
+    screen.designate_charset(1, 'A');
+    screen.shift_out();
+    screen.save_cursor();
+
+    screen.charset_mut().reset();
+    screen.restore_cursor();
+    assert_eq!(screen.charset().active, 1);
+    screen.shift_in();
+    screen.print('q');
+    // G0 is DEC Special Graphics again
+    assert_eq!(screen.line(0).cell(0).display_char(), '─');
+}
+
+#[test]
+fn test_screen_save_restore_cursor_pending_wrap_and_origin() {
+    let mut screen = Screen::new(Dimensions::new(10, 5));
+    screen.set_scroll_region(2, 4);
+    screen.set_origin_mode(true);
+    screen.move_cursor_to(1, 10);
+    screen.print('x');
+    assert!(screen.cursor().pending_wrap);
+    screen.save_cursor();
+
+    screen.set_origin_mode(false);
+    screen.move_cursor_to(5, 1);
+    screen.restore_cursor();
+    assert!(screen.modes().origin_mode);
+    assert!(screen.cursor().pending_wrap);
+    assert_eq!((screen.cursor().row, screen.cursor().col), (1, 9));
+
+    // The pending wrap is honoured: the next character wraps
+    screen.print('y');
+    assert_eq!(screen.line(2).cell(0).display_char(), 'y');
+}
+
+#[test]
+fn test_screen_restore_cursor_after_shrink() {
+    let mut screen = Screen::new(Dimensions::new(10, 5));
+    screen.move_cursor_to(5, 10);
+    screen.print('x');
+    screen.save_cursor();
+    screen.resize(Dimensions::new(6, 3));
+    screen.restore_cursor();
+
+    assert_eq!((screen.cursor().row, screen.cursor().col), (2, 5));
+    assert!(!screen.cursor().pending_wrap);
+}
+
+#[test]
+fn test_screen_alternate_screen_keeps_decsc_slot() {
+    let mut screen = Screen::new(Dimensions::new(80, 24));
+    screen.move_cursor_to(3, 4);
+    screen.save_cursor();
+
+    // 1047-style switch: no DECSC of its own
+    screen.move_cursor_to(10, 10);
+    screen.enter_alternate_screen();
+    screen.exit_alternate_screen();
+    assert_eq!((screen.cursor().row, screen.cursor().col), (9, 9));
+
+    screen.restore_cursor();
+    assert_eq!((screen.cursor().row, screen.cursor().col), (2, 3));
+}
+
 // ============================================================
 // Erase Display Tests
 // ============================================================
@@ -879,6 +1056,52 @@ fn test_screen_alternate_no_scrollback() {
     assert!(screen.scrollback().is_empty());
 }
 
+#[test]
+fn test_screen_exit_alternate_discards_by_default() {
+    let mut screen = Screen::new(Dimensions::new(10, 3));
+    screen.enter_alternate_screen();
+    screen.print('B');
+    screen.exit_alternate_screen();
+    assert!(screen.scrollback().is_empty());
+    assert!(screen.line(0).is_empty());
+}
+
+#[test]
+fn test_screen_exit_alternate_capture() {
+    let mut screen = Screen::new(Dimensions::new(10, 4));
+    screen.set_capture_alternate_screen(true);
+    for c in "$ less".chars() {
+        screen.print(c);
+    }
+    screen.enter_alternate_screen();
+    screen.move_cursor_to(2, 1);
+    screen.print('a');
+    screen.move_cursor_to(3, 1);
+    screen.print('b');
+    screen.exit_alternate_screen();
+
+    // The prompt, a rule, the rows, a closing rule, and the cursor below
+    let text = |screen: &Screen| -> Vec<String> {
+        let history = screen.scrollback();
+        (0..history.len())
+            .map(|i| history.get(i).unwrap().text())
+            .chain((0..screen.rows()).map(|row| screen.line(row).text()))
+            .collect()
+    };
+    let rule = "─".repeat(10);
+    assert_eq!(text(&screen), ["$ less", &rule, "a", "b", &rule, ""]);
+    assert_eq!(screen.scrollback().len(), 2);
+    assert!(screen.scrollback().get(1).unwrap().meta.marked);
+    assert!(screen.scrollback().get(1).unwrap().cell(0).attrs.faint);
+    assert_eq!((screen.cursor().row, screen.cursor().col), (3, 0));
+
+    // A blank alternate screen adds nothing
+    screen.enter_alternate_screen();
+    screen.exit_alternate_screen();
+    assert_eq!(screen.scrollback().len(), 2);
+    assert_eq!((screen.cursor().row, screen.cursor().col), (3, 0));
+}
+
 #[test]
 fn test_screen_enter_alternate_resets_cursor() {
     let mut screen = Screen::new(Dimensions::new(80, 24));
@@ -950,6 +1173,57 @@ fn test_screen_reset() {
     assert_eq!(screen.title(), "");
 }
 
+#[test]
+fn test_screen_soft_reset_keeps_contents() {
+    let mut screen = Screen::new(Dimensions::new(80, 24));
+    screen.print('A');
+    screen.move_cursor_to(5, 10);
+    screen.soft_reset();
+
+    assert_eq!(screen.line(0).text(), "A");
+    assert_eq!(screen.cursor().row, 4);
+    assert_eq!(screen.cursor().col, 9);
+}
+
+#[test]
+fn test_screen_soft_reset_modes_and_margins() {
+    let mut screen = Screen::new(Dimensions::new(80, 24));
+    screen.set_scroll_region(5, 10);
+    screen.modes_mut().origin_mode = true;
+    screen.modes_mut().insert_mode = true;
+    screen.modes_mut().auto_wrap = false;
+    screen.modes_mut().cursor_visible = false;
+    screen.modes_mut().bracketed_paste = true;
+    screen.cursor_mut().attrs.bold = true;
+    screen.designate_charset(0, '0');
+    screen.soft_reset();
+
+    assert_eq!(screen.scroll_region(), (0, 23));
+    assert!(!screen.modes().origin_mode);
+    assert!(!screen.modes().insert_mode);
+    assert!(screen.modes().auto_wrap);
+    assert!(screen.modes().cursor_visible);
+    // Modes outside DECSTR's scope are kept
+    assert!(screen.modes().bracketed_paste);
+    assert!(!screen.cursor().attrs.bold);
+
+    screen.move_cursor_to(1, 1);
+    screen.print('q');
+    assert_eq!(screen.line(0).cell(0).display_char(), 'q');
+}
+
+#[test]
+fn test_screen_soft_reset_clears_saved_cursor() {
+    let mut screen = Screen::new(Dimensions::new(80, 24));
+    screen.move_cursor_to(10, 20);
+    screen.save_cursor();
+    screen.soft_reset();
+    screen.restore_cursor();
+
+    assert_eq!(screen.cursor().row, 0);
+    assert_eq!(screen.cursor().col, 0);
+}
+
 // ============================================================
 // Title Tests
 // ============================================================
@@ -1017,6 +1291,25 @@ fn test_screen_register_different_hyperlinks() {
     assert_ne!(id1, id2);
 }
 
+#[test]
+fn test_screen_register_hyperlink_with_id() {
+    let mut screen = Screen::new(Dimensions::new(80, 24));
+    let anonymous = screen.register_hyperlink("https://example.com");
+    let first = screen.register_hyperlink_with_id("a", "https://example.com");
+    let second = screen.register_hyperlink_with_id("b", "https://example.com");
+    assert_ne!(anonymous, first);
+    assert_ne!(first, second);
+    assert_eq!(
+        screen.register_hyperlink_with_id("a", "https://example.com"),
+        first
+    );
+    assert_ne!(
+        screen.register_hyperlink_with_id("a", "https://other.com"),
+        first
+    );
+    assert_eq!(screen.get_hyperlink(second), Some("https://example.com"));
+}
+
 // ============================================================
 // Snapshot Tests
 // ============================================================
@@ -1041,6 +1334,25 @@ fn test_screen_snapshot_with_scrollback() {
     assert!(snap.scrollback.is_some());
 }
 
+#[test]
+fn test_screen_snapshot_hyperlink_spans() {
+    let mut screen = Screen::new(Dimensions::new(10, 3));
+    screen.print('a');
+    let id = screen.register_hyperlink("https://example.com");
+    screen.cursor_mut().hyperlink_id = id;
+    screen.print('b');
+    screen.print('c');
+    screen.cursor_mut().hyperlink_id = 0;
+    screen.print('d');
+    let snap = screen.snapshot(false);
+    let spans: Vec<_> = snap.screen[0]
+        .attrs
+        .iter()
+        .map(|span| (span.start, span.end, span.hyperlink))
+        .collect();
+    assert_eq!(spans, [(1, 3, id)]);
+}
+
 #[test]
 fn test_screen_snapshot_without_scrollback() {
     let screen = Screen::new(Dimensions::new(10, 3));
@@ -1111,6 +1423,122 @@ fn test_screen_insert_mode() {
     assert_eq!(screen.line(0).cell(2).display_char(), 'X');
 }
 
+#[test]
+fn test_screen_insert_mode_shifts_wide_chars() {
+    let mut screen = Screen::new(Dimensions::new(10, 3));
+    for c in "a中b".chars() {
+        screen.print(c);
+    }
+    screen.modes_mut().insert_mode = true;
+    screen.move_cursor_to(1, 1);
+    screen.print('文');
+    assert_eq!(screen.line(0).text(), "文a中b");
+    assert_eq!(screen.cursor().col, 2);
+}
+
+#[test]
+fn test_screen_insert_mode_inside_wide_char() {
+    let mut screen = Screen::new(Dimensions::new(10, 3));
+    for c in "a中b".chars() {
+        screen.print(c);
+    }
+    screen.modes_mut().insert_mode = true;
+    screen.move_cursor_to(1, 3);
+    screen.print('X');
+    // The split wide character is blanked on both sides
+    assert_eq!(screen.line(0).text(), "a X b");
+}
+
+#[test]
+fn test_screen_insert_mode_pushes_text_off_line() {
+    let mut screen = Screen::new(Dimensions::new(5, 3));
+    for c in "ABCDE".chars() {
+        screen.print(c);
+    }
+    screen.modes_mut().insert_mode = true;
+    screen.move_cursor_to(1, 1);
+    screen.print('X');
+    assert_eq!(screen.line(0).text(), "XABCD");
+    assert!(screen.line(1).text().is_empty());
+}
+
+#[test]
+fn test_screen_overwrite_wide_char_halves() {
+    let mut screen = Screen::new(Dimensions::new(10, 3));
+    for c in "中文".chars() {
+        screen.print(c);
+    }
+    // Overwrite the continuation of the first and the start of the second
+    screen.move_cursor_to(1, 2);
+    screen.print('X');
+    assert_eq!(screen.line(0).text(), " X文");
+    screen.print('Y');
+    assert_eq!(screen.line(0).text(), " XY");
+    assert!(!screen.line(0).cell(3).is_continuation());
+}
+
+#[test]
+fn test_screen_wide_char_over_wide_char_boundary() {
+    let mut screen = Screen::new(Dimensions::new(10, 3));
+    for c in "中文".chars() {
+        screen.print(c);
+    }
+    screen.move_cursor_to(1, 2);
+    screen.print('字');
+    assert_eq!(screen.line(0).text(), " 字");
+    assert!(!screen.line(0).cell(3).is_continuation());
+}
+
+#[test]
+fn test_screen_wide_char_wraps_at_last_column() {
+    let mut screen = Screen::new(Dimensions::new(5, 3));
+    for c in "abcd中".chars() {
+        screen.print(c);
+    }
+    assert_eq!(screen.line(0).text(), "abcd");
+    assert_eq!(screen.line(1).text(), "中");
+    assert!(screen.line(0).wrapped);
+    assert_eq!(screen.cursor().col, 2);
+}
+
+#[test]
+fn test_screen_wide_char_at_last_column_without_autowrap() {
+    let mut screen = Screen::new(Dimensions::new(5, 3));
+    screen.modes_mut().auto_wrap = false;
+    for c in "abcd中".chars() {
+        screen.print(c);
+    }
+    assert_eq!(screen.line(0).text(), "abc中");
+    assert!(screen.line(1).text().is_empty());
+}
+
+#[test]
+fn test_screen_ich_dch_repair_wide_chars() {
+    let mut screen = Screen::new(Dimensions::new(10, 3));
+    for c in "a中b".chars() {
+        screen.print(c);
+    }
+    screen.move_cursor_to(1, 3);
+    screen.insert_chars(1);
+    assert_eq!(screen.line(0).text(), "a   b");
+
+    let mut screen = Screen::new(Dimensions::new(10, 3));
+    for c in "a中b".chars() {
+        screen.print(c);
+    }
+    screen.move_cursor_to(1, 3);
+    screen.delete_chars(1);
+    assert_eq!(screen.line(0).text(), "a b");
+
+    let mut screen = Screen::new(Dimensions::new(10, 3));
+    for c in "a中b".chars() {
+        screen.print(c);
+    }
+    screen.move_cursor_to(1, 3);
+    screen.erase_chars(1);
+    assert_eq!(screen.line(0).text(), "a  b");
+}
+
 // ============================================================
 // Line Access Tests
 // ============================================================
@@ -1122,3 +1550,325 @@ fn test_screen_line_access() {
     let line = screen.line(0);
     assert_eq!(line.cell(0).display_char(), 'A');
 }
+
+// ============================================================================
+// Inline Images
+// ============================================================================
+
+fn test_image() -> ImageData {
+    ImageData::new(1, 1, vec![255, 0, 0, 255]).unwrap()
+}
+
+#[test]
+fn test_screen_place_image_moves_cursor() {
+    let mut screen = Screen::new(Dimensions::new(80, 24));
+    screen.move_cursor_to(3, 6);
+    screen.place_image(test_image(), 10, 3, true);
+
+    let placement = &screen.images().placements()[0];
+    assert_eq!((placement.col, placement.row), (5, 2));
+    assert_eq!((placement.cols, placement.rows), (10, 3));
+    assert_eq!(screen.cursor().row, 4);
+    assert_eq!(screen.cursor().col, 15);
+}
+
+#[test]
+fn test_screen_place_image_clamps_box() {
+    let mut screen = Screen::new(Dimensions::new(80, 24));
+    screen.move_cursor_to(24, 71);
+    screen.place_image(test_image(), 100, 100_000_000, true);
+
+    // The box fits the rest of the row and the screen height, and the
+    // screen scrolls once for the rows below the cursor
+    let placement = &screen.images().placements()[0];
+    assert_eq!((placement.col, placement.row), (70, 0));
+    assert_eq!((placement.cols, placement.rows), (10, 24));
+    assert_eq!(screen.scrollback().len(), 23);
+    assert_eq!(screen.cursor().row, 23);
+}
+
+#[test]
+fn test_screen_image_scrolls_into_scrollback() {
+    let mut screen = Screen::new(Dimensions::new(80, 5));
+    screen.move_cursor_to(4, 1);
+    // Image taller than the remaining rows scrolls the screen
+    screen.place_image(test_image(), 4, 4, true);
+
+    let placement = &screen.images().placements()[0];
+    assert_eq!(placement.row, 1);
+    assert_eq!(screen.cursor().row, 4);
+
+    for _ in 0..3 {
+        screen.linefeed();
+    }
+    assert_eq!(screen.images().placements()[0].row, -2);
+}
+
+#[test]
+fn test_screen_image_below_bottom_margin_stays() {
+    let mut screen = Screen::new(Dimensions::new(80, 24));
+    screen.move_cursor_to(2, 1);
+    screen.place_image(test_image(), 2, 1, true);
+    // A status line image under the scroll region
+    screen.move_cursor_to(23, 1);
+    screen.place_image(test_image(), 2, 1, true);
+    screen.set_scroll_region(1, 20);
+
+    screen.scroll_up(2);
+    let rows: Vec<isize> = screen.images().placements().iter().map(|p| p.row).collect();
+    assert_eq!(rows, [-1, 22]);
+
+    screen.scroll_down(3);
+    let rows: Vec<isize> = screen.images().placements().iter().map(|p| p.row).collect();
+    assert_eq!(rows, [-1, 22]);
+
+    // Images pushed past the bottom margin are dropped
+    screen.move_cursor_to(18, 1);
+    screen.place_image(test_image(), 2, 1, true);
+    screen.scroll_down(3);
+    let rows: Vec<isize> = screen.images().placements().iter().map(|p| p.row).collect();
+    assert_eq!(rows, [-1, 22]);
+}
+
+#[test]
+fn test_screen_erase_display_clears_images() {
+    let mut screen = Screen::new(Dimensions::new(80, 24));
+    screen.place_image(test_image(), 2, 2, true);
+    screen.erase_display(2);
+    assert!(screen.images().is_empty());
+}
+
+#[test]
+fn test_screen_alternate_images_are_separate() {
+    let mut screen = Screen::new(Dimensions::new(80, 24));
+    screen.place_image(test_image(), 2, 2, true);
+
+    screen.enter_alternate_screen();
+    assert!(screen.images().is_empty());
+    screen.place_image(test_image(), 2, 2, true);
+    assert_eq!(screen.images().placements().len(), 1);
+
+    screen.exit_alternate_screen();
+    assert_eq!(screen.images().placements().len(), 1);
+    assert_eq!(screen.images().placements()[0].row, 0);
+
+    screen.enter_alternate_screen();
+    assert!(screen.images().is_empty());
+}
+
+#[test]
+fn test_screen_text_region() {
+    let mut screen = Screen::new(Dimensions::new(10, 2));
+    for (i, line) in ["first", "second", "third"].iter().enumerate() {
+        if i > 0 {
+            screen.carriage_return();
+            screen.linefeed();
+        }
+        for c in line.chars() {
+            screen.print(c);
+        }
+    }
+    // "first" scrolled into the scrollback
+    assert_eq!(
+        screen.line_at(-1).map(|line| line.text()),
+        Some("first".into())
+    );
+    assert!(screen.line_at(-2).is_none());
+
+    let rows = TextRegion::Rows { top: -1, bottom: 1 };
+    assert_eq!(screen.text_region(rows, true), "first\nsecond\nthird");
+    assert_eq!(
+        screen.text_region(TextRegion::Rows { top: 1, bottom: 1 }, false),
+        "third     "
+    );
+
+    let stream = TextRegion::Stream {
+        start: Point::new(2, 0),
+        end: Point::new(1, -1),
+    };
+    assert_eq!(screen.text_region(stream, true), "irst\nsec");
+
+    let block = TextRegion::Block {
+        start: Point::new(1, -1),
+        end: Point::new(3, 1),
+    };
+    assert_eq!(screen.text_region(block, false), "irs\neco\nhir");
+}
+
+/// Print `lines`, one per row
+fn print_lines(screen: &mut Screen, lines: &[&str]) {
+    for (i, line) in lines.iter().enumerate() {
+        if i > 0 {
+            screen.carriage_return();
+            screen.linefeed();
+        }
+        for c in line.chars() {
+            screen.print(c);
+        }
+    }
+}
+
+/// Columns of `row` highlighted as selected, as '#' and '.'
+fn selected_cells(screen: &Screen, row: isize) -> String {
+    (0..screen.cols())
+        .map(|col| {
+            if screen.is_selected(col, row) {
+                '#'
+            } else {
+                '.'
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn test_screen_selection_wide_chars() {
+    let mut screen = Screen::new(Dimensions::new(8, 2));
+    print_lines(&mut screen, &["a中b"]);
+
+    // Starting on the right half of the wide character takes all of it
+    screen
+        .selection_mut()
+        .start(Point::new(2, 0), SelectionType::Normal);
+    screen.selection_mut().update(Point::new(3, 0));
+    assert_eq!(selected_cells(&screen, 0), ".###....");
+    let (start, end) = screen.selection().bounds();
+    let stream = TextRegion::Stream { start, end };
+    assert_eq!(screen.text_region(stream, true), "中b");
+
+    // Ending on its left half highlights both halves
+    screen
+        .selection_mut()
+        .start(Point::new(0, 0), SelectionType::Normal);
+    screen.selection_mut().update(Point::new(1, 0));
+    assert_eq!(selected_cells(&screen, 0), "###.....");
+}
+
+#[test]
+fn test_screen_selection_below_bottom_margin_stays() {
+    let mut screen = Screen::new(Dimensions::new(8, 6));
+    print_lines(&mut screen, &["a", "b", "c", "d", "e", "status"]);
+    screen.set_scroll_region(1, 4);
+    screen
+        .selection_mut()
+        .start(Point::new(0, 5), SelectionType::Normal);
+    screen.selection_mut().update(Point::new(5, 5));
+
+    // The region above the status line scrolls without moving it
+    screen.scroll_up(2);
+    let (start, end) = screen.selection().bounds();
+    assert_eq!((start, end), (Point::new(0, 5), Point::new(5, 5)));
+    let stream = TextRegion::Stream { start, end };
+    assert_eq!(screen.text_region(stream, true), "status");
+}
+
+#[test]
+fn test_screen_selection_trailing_whitespace() {
+    let mut screen = Screen::new(Dimensions::new(8, 3));
+    print_lines(&mut screen, &["one", "two", "three"]);
+    screen
+        .selection_mut()
+        .start(Point::new(1, 0), SelectionType::Normal);
+    screen.selection_mut().update(Point::new(6, 2));
+
+    // Blanks are highlighted where the line break is selected, not after
+    // the text of the last row
+    assert_eq!(selected_cells(&screen, 0), ".#######");
+    assert_eq!(selected_cells(&screen, 1), "########");
+    assert_eq!(selected_cells(&screen, 2), "#####...");
+
+    // Blocks are drawn as the full rectangle
+    screen
+        .selection_mut()
+        .start(Point::new(2, 0), SelectionType::Block);
+    screen.selection_mut().update(Point::new(4, 2));
+    assert_eq!(selected_cells(&screen, 0), "..###...");
+
+    // A click without a drag selects nothing
+    screen
+        .selection_mut()
+        .start(Point::new(1, 1), SelectionType::Normal);
+    assert_eq!(selected_cells(&screen, 1), "........");
+}
+
+#[test]
+fn test_screen_selection_follows_scrolled_text() {
+    let mut screen = Screen::new(Dimensions::new(8, 2));
+    screen.set_scrollback_limit(3);
+    print_lines(&mut screen, &["aaa", "bbb"]);
+    screen
+        .selection_mut()
+        .start(Point::new(0, 1), SelectionType::Normal);
+    screen.selection_mut().update(Point::new(2, 1));
+
+    // New output pushes "bbb" into the scrollback; the selection stays on it
+    print_lines(&mut screen, &["", "ccc"]);
+    assert_eq!(screen.selection().bounds().0, Point::new(0, 0));
+    print_lines(&mut screen, &["", "ddd"]);
+    let (start, end) = screen.selection().bounds();
+    assert_eq!((start.row, end.row), (-1, -1));
+    assert_eq!(selected_cells(&screen, -1), "###.....");
+    assert_eq!(
+        screen.text_region(TextRegion::Stream { start, end }, true),
+        "bbb"
+    );
+
+    // Across the boundary between the scrollback and the screen
+    screen.selection_mut().update(Point::new(1, 0));
+    assert_eq!(
+        screen.text_region(
+            TextRegion::Stream {
+                start,
+                end: Point::new(1, 0)
+            },
+            true
+        ),
+        "bbb\ncc"
+    );
+    assert_eq!(selected_cells(&screen, -1), "########");
+    assert_eq!(selected_cells(&screen, 0), "##......");
+
+    // Once the text leaves the history, the selection goes with it
+    print_lines(&mut screen, &["", "e", "f", "g", "h"]);
+    assert!(!screen.selection().active);
+    assert!(!screen.is_selected(0, -3));
+}
+
+#[test]
+fn test_screen_logical_lines() {
+    let mut screen = Screen::new(Dimensions::new(5, 4));
+    // "abcdefg" wraps after "abcde"; the wide "中" does not fit after "wxyz"
+    // and wraps early, leaving the last column of that row blank
+    for c in "abcdefg".chars() {
+        screen.print(c);
+    }
+    screen.carriage_return();
+    screen.linefeed();
+    for c in "wxyz中".chars() {
+        screen.print(c);
+    }
+
+    assert!(screen.line(0).wrapped);
+    assert!(!screen.line(1).wrapped);
+    assert_eq!(screen.logical_line_rows(1), Some((0, 1)));
+    assert_eq!(screen.logical_line_rows(0), Some((0, 1)));
+    assert_eq!(screen.logical_line(0).as_deref(), Some("abcdefg"));
+    assert_eq!(screen.logical_line(3).as_deref(), Some("wxyz中"));
+    assert_eq!(screen.logical_line(4), None);
+
+    let rows = TextRegion::Rows { top: 0, bottom: 3 };
+    assert_eq!(screen.text_region(rows, true), "abcdefg\nwxyz中");
+    // Blocks keep one line per row
+    let block = TextRegion::Block {
+        start: Point::new(0, 0),
+        end: Point::new(1, 1),
+    };
+    assert_eq!(screen.text_region(block, true), "ab\nfg");
+    // Blank rows in the middle are kept; "abcdefg" scrolls off
+    screen.carriage_return();
+    screen.linefeed();
+    screen.linefeed();
+    screen.print('z');
+    let rows = TextRegion::Rows { top: 0, bottom: 3 };
+    assert_eq!(screen.text_region(rows, true), "wxyz中\n\nz");
+}
@@ -333,6 +333,22 @@ fn test_scrollback_iter_rev_with_ring() {
     assert_eq!(texts, vec!["line4", "line3", "line2"]);
 }
 
+#[test]
+fn test_scrollback_pushed_counts_dropped_lines() {
+    let mut sb = Scrollback::new(3);
+    for i in 0..5 {
+        sb.push(make_line(&format!("line{}", i)));
+    }
+    assert_eq!(sb.len(), 3);
+    assert_eq!(sb.pushed(), 5);
+    sb.clear();
+    assert_eq!(sb.pushed(), 5);
+
+    let mut sb = Scrollback::new(0);
+    sb.push(make_line("dropped"));
+    assert_eq!(sb.pushed(), 0);
+}
+
 // ============================================================
 // Stress Tests
 // ============================================================
@@ -1,11 +1,17 @@
 //! Terminal actions produced by the parser
 //!
 //! These represent the semantic meaning of parsed escape sequences.
+//!
+//! Printable characters, controls, ESC and CSI sequences (the common
+//! actions) are built without heap allocation. With the `serde` feature
+//! all actions serialize (externally tagged, as serde does by default), so
+//! traces and tests can store and compare them.
 
 use crate::params::Params;
 
 /// Actions produced by the parser
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Action {
     /// Print a character to the screen
     Print(char),
@@ -23,8 +29,19 @@ pub enum Action {
     /// OSC (Operating System Command) sequence
     Osc(OscAction),
 
-    /// DCS (Device Control String) - currently just consumed
-    Dcs { params: Params, data: Vec<u8> },
+    /// DCS (Device Control String): `final_byte` picks the function (e.g.
+    /// `p` for ReGIS, `q` for sixel), `intermediates` holds any intermediate
+    /// and private marker bytes
+    Dcs {
+        params: Params,
+        intermediates: Vec<u8>,
+        final_byte: u8,
+        data: Vec<u8>,
+    },
+
+    /// Tektronix 4014 graphics, from DECSET 38 up to ESC ETX (or RIS) -
+    /// consumed, not drawn
+    Tek(Vec<u8>),
 
     /// APC (Application Program Command) - consumed and ignored
     Apc(Vec<u8>),
@@ -41,6 +58,7 @@ pub enum Action {
 
 /// ESC sequence actions (non-CSI)
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum EscAction {
     /// ESC 7 - Save cursor (DECSC)
     SaveCursor,
@@ -76,10 +94,12 @@ pub enum EscAction {
 
 /// CSI sequence actions
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CsiAction {
     /// Parameters (semicolon-separated numbers)
     pub params: Params,
-    /// Intermediate bytes (0x20-0x2F)
+    /// Intermediate bytes (0x20-0x2F), after the private marker if it is
+    /// `>`, `<` or `=` (empty, and not allocated, for most sequences)
     pub intermediates: Vec<u8>,
     /// Final byte (0x40-0x7E)
     pub final_byte: u8,
@@ -106,6 +126,7 @@ impl CsiAction {
 
 /// OSC sequence actions
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum OscAction {
     /// OSC 0 - Set icon name and window title
     SetIconAndTitle(String),
@@ -119,14 +140,27 @@ pub enum OscAction {
     SetCurrentDirectory(String),
     /// OSC 8 - Hyperlink
     Hyperlink { params: String, uri: String },
+    /// OSC 9 ; body / OSC 777 ; notify ; title ; body - desktop notification
+    /// (title is empty for OSC 9)
+    Notify { title: String, body: String },
+    /// OSC 9 ; 4 ; state ; value - progress (ConEmu / Windows Terminal):
+    /// state 0 removes it, 1 is normal, 2 error, 3 indeterminate and 4
+    /// paused; `value` is the percentage, if given
+    Progress { state: u16, value: Option<u16> },
     /// OSC 10 - Set foreground color
     SetForegroundColor(String),
     /// OSC 11 - Set background color
     SetBackgroundColor(String),
     /// OSC 12 - Set cursor color
     SetCursorColor(String),
+    /// OSC 22 - Set the mouse pointer shape (empty to reset)
+    SetPointerShape(String),
     /// OSC 52 - Clipboard operation
     Clipboard { clipboard: String, data: String },
+    /// OSC 133 - Semantic prompt (shell integration): `kind` is A (prompt
+    /// start), B (command start), C (output start) or D (command finished);
+    /// `params` is the rest after the first `;`
+    SemanticPrompt { kind: char, params: String },
     /// OSC 104 - Reset color
     ResetColor(Option<u8>),
     /// OSC 110 - Reset foreground color
@@ -135,6 +169,15 @@ pub enum OscAction {
     ResetBackgroundColor,
     /// OSC 112 - Reset cursor color
     ResetCursorColor,
+    /// OSC 1337 ; File= - iTerm2 inline file (args are `key=value;...`,
+    /// data is base64 encoded)
+    InlineFile { args: String, data: String },
+    /// OSC 1337 ; TabAccent=color - mochi extension: accent color of the
+    /// tab (empty to reset)
+    SetTabAccent(String),
+    /// OSC 1337 ; SetUserVar=name=value - iTerm2 user variable (value is
+    /// base64 encoded; empty unsets it)
+    SetUserVar { name: String, value: String },
     /// Unknown OSC sequence
     Unknown { command: u16, data: String },
 }
@@ -183,4 +226,44 @@ mod tests {
         assert!(csi.is_private(b'h'));
         assert!(!csi.is(b'h'));
     }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_action_serde_round_trip() {
+        let actions = [
+            Action::Print('é'),
+            Action::Control(0x07),
+            Action::Esc(EscAction::DesignateG0('0')),
+            Action::Csi(CsiAction {
+                params: Params::parse(b"38:2:1:2:3;1"),
+                intermediates: vec![b' '],
+                final_byte: b'q',
+                private: false,
+            }),
+            Action::Osc(OscAction::Progress {
+                state: 1,
+                value: Some(50),
+            }),
+            Action::Dcs {
+                params: Params::from_slice(&[0]),
+                intermediates: vec![],
+                final_byte: b'q',
+                data: b"#0".to_vec(),
+            },
+        ];
+        for action in actions {
+            let json = serde_json::to_string(&action).unwrap();
+            assert_eq!(serde_json::from_str::<Action>(&json).unwrap(), action);
+        }
+        let csi = Action::Csi(CsiAction {
+            params: Params::from_slice(&[1, 2]),
+            intermediates: vec![],
+            final_byte: b'H',
+            private: false,
+        });
+        assert_eq!(
+            serde_json::to_string(&csi).unwrap(),
+            r#"{"Csi":{"params":{"values":[1,2],"subparams":[[],[]]},"intermediates":[],"final_byte":72,"private":false}}"#
+        );
+    }
 }
@@ -18,4 +18,4 @@ mod utf8;
 
 pub use action::{Action, CsiAction, EscAction, OscAction};
 pub use params::Params;
-pub use parser::{Parser, ParserState};
+pub use parser::{Parser, ParserState, MAX_PASSTHROUGH_DEPTH, MAX_STRING_LENGTH};
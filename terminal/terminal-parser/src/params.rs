@@ -5,29 +5,69 @@
 /// Maximum number of parameters we'll track
 const MAX_PARAMS: usize = 32;
 
+/// Maximum number of subparameters, over all parameters
+const MAX_SUBPARAMS: usize = 32;
+
 /// CSI parameters
+///
+/// Stored inline, so parsing a sequence does not allocate. Parameters past
+/// `MAX_PARAMS` and subparameters past `MAX_SUBPARAMS` are dropped.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "ParamsRepr", into = "ParamsRepr")
+)]
 pub struct Params {
-    /// Parameter values (0 means default/unspecified)
-    values: Vec<u16>,
-    /// Subparameters (for colon-separated values like SGR)
-    subparams: Vec<Vec<u16>>,
+    /// Parameter values (0 means default/unspecified); unused slots are 0
+    values: [u16; MAX_PARAMS],
+    /// Number of parameters
+    len: usize,
+    /// Subparameters (for colon-separated values like SGR) of all
+    /// parameters, one after the other; unused slots are 0
+    subparams: [u16; MAX_SUBPARAMS],
+    /// Where the subparameters of each parameter end in `subparams`
+    subparam_ends: [u8; MAX_PARAMS],
 }
 
 impl Params {
     /// Create empty params
     pub fn new() -> Self {
         Self {
-            values: Vec::new(),
-            subparams: Vec::new(),
+            values: [0; MAX_PARAMS],
+            len: 0,
+            subparams: [0; MAX_SUBPARAMS],
+            subparam_ends: [0; MAX_PARAMS],
         }
     }
 
     /// Create params from a slice
     pub fn from_slice(values: &[u16]) -> Self {
-        Self {
-            values: values.to_vec(),
-            subparams: Vec::new(),
+        let mut params = Self::new();
+        for &value in values {
+            params.push(value, &[]);
+        }
+        params
+    }
+
+    /// Add a parameter with its subparameters, if there is room
+    fn push(&mut self, value: u16, subparams: &[u16]) {
+        if self.len == MAX_PARAMS {
+            return;
+        }
+        let start = self.subparams_end();
+        let count = subparams.len().min(MAX_SUBPARAMS - start);
+        self.subparams[start..start + count].copy_from_slice(&subparams[..count]);
+        self.values[self.len] = value;
+        self.subparam_ends[self.len] = (start + count) as u8;
+        self.len += 1;
+    }
+
+    /// End of the last parameter's subparameters
+    fn subparams_end(&self) -> usize {
+        match self.len {
+            0 => 0,
+            len => self.subparam_ends[len - 1] as usize,
         }
     }
 
@@ -36,7 +76,8 @@ impl Params {
         let mut params = Self::new();
         let mut current: u16 = 0;
         let mut has_value = false;
-        let mut current_subparams: Vec<u16> = Vec::new();
+        let mut current_subparams = [0u16; MAX_SUBPARAMS];
+        let mut subparam_count = 0;
 
         for &byte in bytes {
             match byte {
@@ -47,21 +88,20 @@ impl Params {
                         .saturating_add((byte - b'0') as u16);
                 }
                 b';' => {
-                    if params.values.len() < MAX_PARAMS {
-                        params.values.push(if has_value { current } else { 0 });
-                        if !current_subparams.is_empty() {
-                            params.subparams.push(current_subparams.clone());
-                            current_subparams.clear();
-                        } else {
-                            params.subparams.push(Vec::new());
-                        }
-                    }
+                    params.push(
+                        if has_value { current } else { 0 },
+                        &current_subparams[..subparam_count],
+                    );
+                    subparam_count = 0;
                     current = 0;
                     has_value = false;
                 }
                 b':' => {
                     // Subparameter separator (used in SGR for underline styles, etc.)
-                    current_subparams.push(if has_value { current } else { 0 });
+                    if subparam_count < MAX_SUBPARAMS {
+                        current_subparams[subparam_count] = if has_value { current } else { 0 };
+                        subparam_count += 1;
+                    }
                     current = 0;
                     has_value = false;
                 }
@@ -72,14 +112,15 @@ impl Params {
         }
 
         // Don't forget the last parameter
-        if (has_value || !params.values.is_empty()) && params.values.len() < MAX_PARAMS {
-            params.values.push(if has_value { current } else { 0 });
-            if !current_subparams.is_empty() {
-                current_subparams.push(current);
-                params.subparams.push(current_subparams);
-            } else {
-                params.subparams.push(Vec::new());
+        if has_value || !params.is_empty() {
+            if subparam_count > 0 && subparam_count < MAX_SUBPARAMS {
+                current_subparams[subparam_count] = current;
+                subparam_count += 1;
             }
+            params.push(
+                if has_value { current } else { 0 },
+                &current_subparams[..subparam_count],
+            );
         }
 
         params
@@ -87,7 +128,7 @@ impl Params {
 
     /// Get parameter at index, returning None if not present
     pub fn get(&self, index: usize) -> Option<u16> {
-        self.values.get(index).copied().filter(|&v| v != 0)
+        self.as_slice().get(index).copied().filter(|&v| v != 0)
     }
 
     /// Get parameter at index with default value
@@ -97,35 +138,46 @@ impl Params {
 
     /// Get raw value at index (0 if not present)
     pub fn raw(&self, index: usize) -> u16 {
-        self.values.get(index).copied().unwrap_or(0)
+        self.as_slice().get(index).copied().unwrap_or(0)
     }
 
     /// Get number of parameters
     pub fn len(&self) -> usize {
-        self.values.len()
+        self.len
     }
 
     /// Check if empty
     pub fn is_empty(&self) -> bool {
-        self.values.is_empty()
+        self.len == 0
+    }
+
+    /// The raw parameter values (0 for default)
+    pub fn as_slice(&self) -> &[u16] {
+        &self.values[..self.len]
     }
 
     /// Get subparameters for a parameter
     pub fn subparams(&self, index: usize) -> Option<&[u16]> {
-        self.subparams.get(index).map(|v| v.as_slice())
+        if index >= self.len {
+            return None;
+        }
+        let start = match index {
+            0 => 0,
+            _ => self.subparam_ends[index - 1] as usize,
+        };
+        Some(&self.subparams[start..self.subparam_ends[index] as usize])
     }
 
     /// Iterate over parameters
     pub fn iter(&self) -> impl Iterator<Item = u16> + '_ {
-        self.values.iter().copied()
+        self.as_slice().iter().copied()
     }
 
     /// Iterate over parameters with subparameters
     pub fn iter_with_subparams(&self) -> impl Iterator<Item = (u16, &[u16])> + '_ {
-        self.values.iter().enumerate().map(move |(i, &v)| {
-            let subparams = self.subparams.get(i).map(|v| v.as_slice()).unwrap_or(&[]);
-            (v, subparams)
-        })
+        self.iter()
+            .enumerate()
+            .map(move |(i, v)| (v, self.subparams(i).unwrap_or(&[])))
     }
 }
 
@@ -135,6 +187,38 @@ impl Default for Params {
     }
 }
 
+/// How params are serialized: the values, and the subparameters of each
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct ParamsRepr {
+    values: Vec<u16>,
+    #[serde(default)]
+    subparams: Vec<Vec<u16>>,
+}
+
+#[cfg(feature = "serde")]
+impl From<Params> for ParamsRepr {
+    fn from(params: Params) -> Self {
+        Self {
+            values: params.as_slice().to_vec(),
+            subparams: (0..params.len())
+                .map(|i| params.subparams(i).unwrap_or(&[]).to_vec())
+                .collect(),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<ParamsRepr> for Params {
+    fn from(repr: ParamsRepr) -> Self {
+        let mut params = Params::new();
+        for (i, &value) in repr.values.iter().enumerate() {
+            params.push(value, repr.subparams.get(i).map_or(&[], |v| v.as_slice()));
+        }
+        params
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -195,6 +279,27 @@ mod tests {
         assert!(subparams.is_some());
     }
 
+    #[test]
+    fn test_params_subparam_values() {
+        let params = Params::parse(b"4:3;38:2::1:2:3");
+        assert_eq!(params.as_slice(), &[3, 3]);
+        assert_eq!(params.subparams(0), Some(&[4][..]));
+        assert_eq!(params.subparams(1), Some(&[38, 2, 0, 1, 2, 3][..]));
+        assert_eq!(params.subparams(2), None);
+    }
+
+    #[test]
+    fn test_params_limits() {
+        let many: Vec<u8> = (0..40).map(|_| "7;").collect::<String>().into_bytes();
+        let params = Params::parse(&many);
+        assert_eq!(params.len(), MAX_PARAMS);
+        assert_eq!(params, Params::from_slice(&[7; 40]));
+
+        let colons: Vec<u8> = (0..40).map(|_| "1:").collect::<String>().into_bytes();
+        let params = Params::parse(&[&colons[..], b"1"].concat());
+        assert_eq!(params.subparams(0).unwrap().len(), MAX_SUBPARAMS);
+    }
+
     #[test]
     fn test_params_iter() {
         let params = Params::parse(b"1;2;3");
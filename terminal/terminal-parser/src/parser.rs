@@ -17,6 +17,21 @@ use crate::utf8::{Utf8Decoder, Utf8Result};
 
 /// Maximum length for OSC/DCS data to prevent DoS
 const MAX_OSC_LEN: usize = 65536;
+/// Maximum length for OSC 1337 File= data (inline images are large)
+const MAX_OSC_FILE_LEN: usize = 16 * 1024 * 1024;
+/// Prefix of OSC 1337 file transfers
+const OSC_FILE_PREFIX: &[u8] = b"1337;File=";
+/// Maximum length of APC, PM and SOS strings; longer ones are dropped
+/// whole (and reported as `Action::Invalid`), since a cut off kitty graphics
+/// command cannot be used
+pub const MAX_STRING_LENGTH: usize = 1024 * 1024;
+/// Bytes of an overlong string kept in its `Action::Invalid`
+const INVALID_STRING_PREFIX: usize = 16;
+/// Start of a tmux passthrough wrapper (`DCS tmux; ... ST`) after ESC P t
+const TMUX_PREFIX: &[u8] = b"mux;";
+/// Most tmux passthrough wrappers unwrapped inside each other (one per
+/// nested tmux); deeper ones are passed on as plain DCS
+pub const MAX_PASSTHROUGH_DEPTH: usize = 4;
 /// Maximum length for intermediate bytes
 const MAX_INTERMEDIATES: usize = 4;
 
@@ -41,6 +56,8 @@ pub enum ParserState {
     DcsEntry,
     /// Collecting DCS parameters
     DcsParam,
+    /// Collecting DCS intermediate bytes
+    DcsIntermediate,
     /// DCS passthrough mode
     DcsPassthrough,
     /// DCS sequence is invalid
@@ -53,6 +70,8 @@ pub enum ParserState {
     SosString,
     /// Escape intermediate (ESC followed by intermediate byte)
     EscapeIntermediate,
+    /// Tektronix 4014 mode (DECSET 38), consumed until ESC ETX
+    TekGraphics,
 }
 
 /// The terminal parser
@@ -72,6 +91,20 @@ pub struct Parser {
     osc_data: Vec<u8>,
     /// DCS parameters
     dcs_params: Vec<u8>,
+    /// DCS intermediate and private marker bytes
+    dcs_intermediates: Vec<u8>,
+    /// DCS final byte
+    dcs_final: u8,
+    /// An ESC was seen in Tek mode
+    tek_escape: bool,
+    /// The APC, PM or SOS string went past `MAX_STRING_LENGTH`
+    string_overflow: bool,
+    /// Parse the sequences inside tmux passthrough wrappers
+    tmux_passthrough: bool,
+    /// An ESC was seen inside a tmux passthrough wrapper
+    tmux_escape: bool,
+    /// Wrappers this parser is inside of
+    depth: usize,
     /// Escape intermediate bytes
     esc_intermediates: Vec<u8>,
 }
@@ -87,6 +120,13 @@ impl Parser {
             private_marker: false,
             osc_data: Vec::with_capacity(256),
             dcs_params: Vec::with_capacity(64),
+            dcs_intermediates: Vec::with_capacity(MAX_INTERMEDIATES),
+            dcs_final: 0,
+            tek_escape: false,
+            string_overflow: false,
+            tmux_passthrough: false,
+            tmux_escape: false,
+            depth: 0,
             esc_intermediates: Vec::with_capacity(MAX_INTERMEDIATES),
         }
     }
@@ -105,9 +145,21 @@ impl Parser {
         self.private_marker = false;
         self.osc_data.clear();
         self.dcs_params.clear();
+        self.dcs_intermediates.clear();
+        self.tek_escape = false;
+        self.string_overflow = false;
+        self.tmux_escape = false;
         self.esc_intermediates.clear();
     }
 
+    /// Parse what programs inside tmux send wrapped in `DCS tmux; ... ST`
+    /// (with each ESC doubled) as if it had been sent directly
+    ///
+    /// Without it the wrapper is consumed whole and emitted as a DCS.
+    pub fn set_tmux_passthrough(&mut self, enabled: bool) {
+        self.tmux_passthrough = enabled;
+    }
+
     /// Parse a chunk of bytes, calling the callback for each action
     pub fn parse<F>(&mut self, data: &[u8], mut callback: F)
     where
@@ -126,10 +178,27 @@ impl Parser {
     }
 
     /// Advance the parser by one byte
+    #[allow(clippy::collapsible_match)]
     fn advance<F>(&mut self, byte: u8, callback: &mut F)
     where
         F: FnMut(Action),
     {
+        if self.state == ParserState::TekGraphics {
+            self.advance_tek(byte, callback);
+            return;
+        }
+
+        // Inside a tmux passthrough wrapper ESC ESC is an ESC of the wrapped
+        // sequence, and ESC followed by anything else ends the wrapper
+        if self.tmux_escape {
+            self.tmux_escape = false;
+            if byte == 0x1B {
+                self.collect_string_byte(byte);
+                return;
+            }
+            self.finish_string_to_escape(callback);
+        }
+
         // Handle C0 controls that are always processed (except in string states)
         match self.state {
             ParserState::OscString
@@ -139,6 +208,9 @@ impl Parser {
             | ParserState::SosString => {
                 // In string states, only ESC and certain controls terminate
                 match byte {
+                    0x1B if self.in_tmux_wrapper() => {
+                        self.tmux_escape = true;
+                    }
                     0x1B => {
                         // ESC - might be ST (ESC \)
                         // We need to peek at next byte, but for streaming we handle it differently
@@ -182,8 +254,8 @@ impl Parser {
                     // CAN, SUB - cancel current sequence
                     self.state = ParserState::Ground;
                 }
-                0x07..=0x0D => {
-                    // BEL, BS, HT, LF, VT, FF, CR
+                0x05 | 0x07..=0x0D if !self.in_dcs_header() => {
+                    // ENQ, BEL, BS, HT, LF, VT, FF, CR
                     callback(Action::Control(byte));
                 }
                 _ => {
@@ -209,6 +281,10 @@ impl Parser {
                     // OSC
                     self.enter_osc();
                 }
+                0x98 => {
+                    // SOS
+                    self.enter_sos();
+                }
                 0x9E => {
                     // PM
                     self.enter_pm();
@@ -217,6 +293,10 @@ impl Parser {
                     // APC
                     self.enter_apc();
                 }
+                0x9C if self.state == ParserState::DcsIgnore => {
+                    // ST - ends an ignored DCS
+                    self.state = ParserState::Ground;
+                }
                 0x9C => {
                     // ST - ignore if not in string state
                 }
@@ -256,6 +336,9 @@ impl Parser {
             ParserState::DcsParam => {
                 self.handle_dcs_param(byte);
             }
+            ParserState::DcsIntermediate => {
+                self.handle_dcs_intermediate(byte);
+            }
             ParserState::DcsPassthrough => {
                 // Handled above in string states
             }
@@ -265,12 +348,53 @@ impl Parser {
             ParserState::OscString
             | ParserState::ApcString
             | ParserState::PmString
-            | ParserState::SosString => {
+            | ParserState::SosString
+            | ParserState::TekGraphics => {
                 // Handled above in string states
             }
         }
     }
 
+    /// Between ESC P and the DCS final byte, or in an ignored DCS, where C0
+    /// controls are not executed
+    fn in_dcs_header(&self) -> bool {
+        matches!(
+            self.state,
+            ParserState::DcsEntry
+                | ParserState::DcsParam
+                | ParserState::DcsIntermediate
+                | ParserState::DcsIgnore
+        )
+    }
+
+    /// Consume a byte of Tektronix graphics
+    ///
+    /// ESC ETX returns to VT mode; RIS (ESC c) also leaves Tek mode so a
+    /// reset always recovers the terminal.
+    fn advance_tek<F>(&mut self, byte: u8, callback: &mut F)
+    where
+        F: FnMut(Action),
+    {
+        if std::mem::take(&mut self.tek_escape) {
+            match byte {
+                0x03 | b'c' => {
+                    callback(Action::Tek(std::mem::take(&mut self.osc_data)));
+                    self.state = ParserState::Ground;
+                    if byte == b'c' {
+                        callback(Action::Esc(EscAction::FullReset));
+                    }
+                    return;
+                }
+                _ => self.collect_string_byte(0x1B),
+            }
+        }
+        if byte == 0x1B {
+            self.tek_escape = true;
+        } else {
+            self.collect_string_byte(byte);
+        }
+    }
+
     fn handle_ground<F>(&mut self, byte: u8, callback: &mut F)
     where
         F: FnMut(Action),
@@ -437,9 +561,16 @@ impl Parser {
         F: FnMut(Action),
     {
         match byte {
-            b'?' | b'>' | b'<' | b'=' => {
-                // Private marker
-                self.private_marker = byte == b'?';
+            b'?' => {
+                // DEC private marker
+                self.private_marker = true;
+                self.state = ParserState::CsiParam;
+            }
+            b'>' | b'<' | b'=' => {
+                // Other private markers (e.g. xterm's CSI > Ps m) are kept
+                // with the intermediates, so they never match the plain
+                // sequence with the same final byte
+                self.intermediates.push(byte);
                 self.state = ParserState::CsiParam;
             }
             b'0'..=b'9' | b';' | b':' => {
@@ -454,7 +585,6 @@ impl Parser {
             0x40..=0x7E => {
                 // Final byte - dispatch
                 self.dispatch_csi(byte, callback);
-                self.state = ParserState::Ground;
             }
             _ => {
                 self.state = ParserState::CsiIgnore;
@@ -478,7 +608,6 @@ impl Parser {
             0x40..=0x7E => {
                 // Final byte - dispatch
                 self.dispatch_csi(byte, callback);
-                self.state = ParserState::Ground;
             }
             b'?' | b'>' | b'<' | b'=' => {
                 // Private marker in wrong position - ignore sequence
@@ -490,6 +619,7 @@ impl Parser {
         }
     }
 
+    #[allow(clippy::collapsible_match)]
     fn handle_csi_intermediate<F>(&mut self, byte: u8, callback: &mut F)
     where
         F: FnMut(Action),
@@ -505,7 +635,6 @@ impl Parser {
             0x40..=0x7E => {
                 // Final byte - dispatch
                 self.dispatch_csi(byte, callback);
-                self.state = ParserState::Ground;
             }
             _ => {
                 self.state = ParserState::CsiIgnore;
@@ -530,7 +659,17 @@ impl Parser {
             final_byte,
             private: self.private_marker,
         };
+        // DECSET 38 switches to Tektronix mode
+        let tek =
+            self.private_marker && final_byte == b'h' && action.params.iter().any(|p| p == 38);
         callback(Action::Csi(action));
+        if tek {
+            self.state = ParserState::TekGraphics;
+            self.osc_data.clear();
+            self.tek_escape = false;
+        } else {
+            self.state = ParserState::Ground;
+        }
     }
 
     fn enter_osc(&mut self) {
@@ -541,6 +680,7 @@ impl Parser {
     fn enter_dcs(&mut self) {
         self.state = ParserState::DcsEntry;
         self.dcs_params.clear();
+        self.dcs_intermediates.clear();
         self.osc_data.clear();
     }
 
@@ -550,10 +690,13 @@ impl Parser {
                 self.dcs_params.push(byte);
                 self.state = ParserState::DcsParam;
             }
-            0x40..=0x7E => {
-                // Final byte - enter passthrough
-                self.state = ParserState::DcsPassthrough;
+            b'<'..=b'?' => {
+                // Private marker, kept with the intermediates
+                self.dcs_intermediates.push(byte);
+                self.state = ParserState::DcsParam;
             }
+            0x20..=0x2F => self.collect_dcs_intermediate(byte),
+            0x40..=0x7E => self.enter_dcs_passthrough(byte),
             _ => {
                 self.state = ParserState::DcsIgnore;
             }
@@ -565,34 +708,129 @@ impl Parser {
             b'0'..=b'9' | b';' => {
                 self.dcs_params.push(byte);
             }
-            0x40..=0x7E => {
-                // Final byte - enter passthrough
-                self.state = ParserState::DcsPassthrough;
+            0x20..=0x2F => self.collect_dcs_intermediate(byte),
+            0x40..=0x7E => self.enter_dcs_passthrough(byte),
+            _ => {
+                self.state = ParserState::DcsIgnore;
             }
+        }
+    }
+
+    fn handle_dcs_intermediate(&mut self, byte: u8) {
+        match byte {
+            0x20..=0x2F => self.collect_dcs_intermediate(byte),
+            0x40..=0x7E => self.enter_dcs_passthrough(byte),
             _ => {
                 self.state = ParserState::DcsIgnore;
             }
         }
     }
 
+    fn collect_dcs_intermediate(&mut self, byte: u8) {
+        if self.dcs_intermediates.len() < MAX_INTERMEDIATES {
+            self.dcs_intermediates.push(byte);
+            self.state = ParserState::DcsIntermediate;
+        } else {
+            self.state = ParserState::DcsIgnore;
+        }
+    }
+
+    fn enter_dcs_passthrough(&mut self, final_byte: u8) {
+        self.dcs_final = final_byte;
+        self.state = ParserState::DcsPassthrough;
+    }
+
+    /// The DCS string collected so far
+    fn dcs_action(&self) -> Action {
+        Action::Dcs {
+            params: Params::parse(&self.dcs_params),
+            intermediates: self.dcs_intermediates.clone(),
+            final_byte: self.dcs_final,
+            data: self.osc_data.clone(),
+        }
+    }
+
+    /// In the data of `DCS tmux; ... ST`
+    fn in_tmux_wrapper(&self) -> bool {
+        self.state == ParserState::DcsPassthrough
+            && self.dcs_final == b't'
+            && self.dcs_params.is_empty()
+            && self.dcs_intermediates.is_empty()
+            && self.osc_data.starts_with(TMUX_PREFIX)
+    }
+
+    fn finish_dcs<F>(&mut self, callback: &mut F)
+    where
+        F: FnMut(Action),
+    {
+        if self.tmux_passthrough && self.depth < MAX_PASSTHROUGH_DEPTH && self.in_tmux_wrapper() {
+            // A parser of its own, so a sequence cut off inside the wrapper
+            // does not leak out of it
+            let mut inner = Parser::new();
+            inner.tmux_passthrough = true;
+            inner.depth = self.depth + 1;
+            for action in inner.parse_collect(&self.osc_data[TMUX_PREFIX.len()..]) {
+                callback(action);
+            }
+        } else {
+            callback(self.dcs_action());
+        }
+    }
+
     fn enter_apc(&mut self) {
-        self.state = ParserState::ApcString;
-        self.osc_data.clear();
+        self.enter_string(ParserState::ApcString);
     }
 
     fn enter_pm(&mut self) {
-        self.state = ParserState::PmString;
-        self.osc_data.clear();
+        self.enter_string(ParserState::PmString);
     }
 
     fn enter_sos(&mut self) {
-        self.state = ParserState::SosString;
+        self.enter_string(ParserState::SosString);
+    }
+
+    fn enter_string(&mut self, state: ParserState) {
+        self.state = state;
         self.osc_data.clear();
+        self.string_overflow = false;
     }
 
     fn collect_string_byte(&mut self, byte: u8) {
-        if self.osc_data.len() < MAX_OSC_LEN {
+        let limit = match self.state {
+            ParserState::OscString if self.osc_data.starts_with(OSC_FILE_PREFIX) => {
+                MAX_OSC_FILE_LEN
+            }
+            // Wrapped sequences include images
+            ParserState::DcsPassthrough if self.in_tmux_wrapper() => MAX_OSC_FILE_LEN,
+            ParserState::ApcString | ParserState::PmString | ParserState::SosString => {
+                MAX_STRING_LENGTH
+            }
+            _ => MAX_OSC_LEN,
+        };
+        if self.osc_data.len() < limit {
             self.osc_data.push(byte);
+        } else {
+            self.string_overflow = true;
+        }
+    }
+
+    /// The action for a finished APC, PM or SOS string
+    fn string_action(&mut self) -> Action {
+        if self.string_overflow {
+            self.string_overflow = false;
+            let introducer: &[u8] = match self.state {
+                ParserState::ApcString => b"\x1b_",
+                ParserState::PmString => b"\x1b^",
+                _ => b"\x1bX",
+            };
+            let prefix = &self.osc_data[..INVALID_STRING_PREFIX.min(self.osc_data.len())];
+            return Action::Invalid([introducer, prefix].concat());
+        }
+        let data = std::mem::take(&mut self.osc_data);
+        match self.state {
+            ParserState::ApcString => Action::Apc(data),
+            ParserState::PmString => Action::Pm(data),
+            _ => Action::Sos(data),
         }
     }
 
@@ -616,20 +854,10 @@ impl Parser {
                 self.finish_osc(callback);
             }
             ParserState::DcsPassthrough => {
-                let params = Params::parse(&self.dcs_params);
-                callback(Action::Dcs {
-                    params,
-                    data: self.osc_data.clone(),
-                });
-            }
-            ParserState::ApcString => {
-                callback(Action::Apc(self.osc_data.clone()));
+                self.finish_dcs(callback);
             }
-            ParserState::PmString => {
-                callback(Action::Pm(self.osc_data.clone()));
-            }
-            ParserState::SosString => {
-                callback(Action::Sos(self.osc_data.clone()));
+            ParserState::ApcString | ParserState::PmString | ParserState::SosString => {
+                callback(self.string_action());
             }
             _ => {}
         }
@@ -648,20 +876,10 @@ impl Parser {
                 self.finish_osc(callback);
             }
             ParserState::DcsPassthrough => {
-                let params = Params::parse(&self.dcs_params);
-                callback(Action::Dcs {
-                    params,
-                    data: self.osc_data.clone(),
-                });
-            }
-            ParserState::ApcString => {
-                callback(Action::Apc(self.osc_data.clone()));
+                self.finish_dcs(callback);
             }
-            ParserState::PmString => {
-                callback(Action::Pm(self.osc_data.clone()));
-            }
-            ParserState::SosString => {
-                callback(Action::Sos(self.osc_data.clone()));
+            ParserState::ApcString | ParserState::PmString | ParserState::SosString => {
+                callback(self.string_action());
             }
             _ => {}
         }
@@ -717,9 +935,27 @@ impl Parser {
                     }
                 }
             }
+            9 if payload == "4" || payload.starts_with("4;") => {
+                // Progress: OSC 9 ; 4 ; state ; value ST
+                let mut params = payload.split(';').skip(1);
+                let state = params.next().unwrap_or("0");
+                let value = params.next().and_then(|value| value.parse().ok());
+                match state.parse() {
+                    Ok(state) => OscAction::Progress { state, value },
+                    Err(_) => OscAction::Unknown {
+                        command: cmd,
+                        data: payload,
+                    },
+                }
+            }
+            9 => OscAction::Notify {
+                title: String::new(),
+                body: payload,
+            },
             10 => OscAction::SetForegroundColor(payload),
             11 => OscAction::SetBackgroundColor(payload),
             12 => OscAction::SetCursorColor(payload),
+            22 => OscAction::SetPointerShape(payload),
             52 => {
                 // Clipboard: OSC 52 ; clipboard ; data ST
                 let parts: Vec<&str> = payload.splitn(2, ';').collect();
@@ -740,9 +976,58 @@ impl Parser {
                 let index = payload.parse::<u8>().ok();
                 OscAction::ResetColor(index)
             }
+            133 if !payload.is_empty() => {
+                // Semantic prompt: OSC 133 ; kind [; params] ST
+                let (kind, params) = payload.split_once(';').unwrap_or((&payload, ""));
+                match kind.chars().next() {
+                    Some(k) if kind.len() == 1 => OscAction::SemanticPrompt {
+                        kind: k,
+                        params: params.to_string(),
+                    },
+                    _ => OscAction::Unknown {
+                        command: cmd,
+                        data: payload,
+                    },
+                }
+            }
             110 => OscAction::ResetForegroundColor,
             111 => OscAction::ResetBackgroundColor,
             112 => OscAction::ResetCursorColor,
+            1337 if payload.starts_with("File=") => {
+                // Inline file: OSC 1337 ; File=args : base64 ST
+                let file = &payload["File=".len()..];
+                match file.split_once(':') {
+                    Some((args, data)) => OscAction::InlineFile {
+                        args: args.to_string(),
+                        data: data.to_string(),
+                    },
+                    None => OscAction::InlineFile {
+                        args: file.to_string(),
+                        data: String::new(),
+                    },
+                }
+            }
+            1337 if payload.starts_with("TabAccent=") => {
+                OscAction::SetTabAccent(payload["TabAccent=".len()..].to_string())
+            }
+            1337 if payload.starts_with("SetUserVar=") => {
+                // User variable: OSC 1337 ; SetUserVar=name=base64 ST
+                let var = &payload["SetUserVar=".len()..];
+                let (name, value) = var.split_once('=').unwrap_or((var, ""));
+                OscAction::SetUserVar {
+                    name: name.to_string(),
+                    value: value.to_string(),
+                }
+            }
+            777 if payload.starts_with("notify;") => {
+                // Notification: OSC 777 ; notify ; title ; body ST
+                let notify = &payload["notify;".len()..];
+                let (title, body) = notify.split_once(';').unwrap_or((notify, ""));
+                OscAction::Notify {
+                    title: title.to_string(),
+                    body: body.to_string(),
+                }
+            }
             _ => OscAction::Unknown {
                 command: cmd,
                 data: payload,
@@ -786,6 +1071,10 @@ mod tests {
         assert_eq!(actions[2], Action::Control(0x09)); // HT
         assert_eq!(actions[3], Action::Control(0x0A)); // LF
         assert_eq!(actions[4], Action::Control(0x0D)); // CR
+
+        // ENQ is passed through; other C0 controls are dropped
+        let actions = parser.parse_collect(b"\x05\x01\x06");
+        assert_eq!(actions, vec![Action::Control(0x05)]);
     }
 
     #[test]
@@ -819,6 +1108,27 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parser_csi_other_private_markers() {
+        let mut parser = Parser::new();
+        let actions = parser.parse_collect(b"\x1b[>4;2m\x1b[=c\x1b[>0 q");
+
+        let csis: Vec<&CsiAction> = actions
+            .iter()
+            .map(|action| match action {
+                Action::Csi(csi) => csi,
+                other => panic!("Expected CSI action, got {:?}", other),
+            })
+            .collect();
+        assert_eq!(csis.len(), 3);
+        assert_eq!(csis[0].intermediates, b">");
+        assert_eq!(csis[0].param(1, 0), 2);
+        assert!(!csis[0].private);
+        assert!(!csis[0].is(b'm'));
+        assert_eq!(csis[1].intermediates, b"=");
+        assert_eq!(csis[2].intermediates, b"> ");
+    }
+
     #[test]
     fn test_parser_csi_sgr() {
         let mut parser = Parser::new();
@@ -884,6 +1194,348 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parser_osc_notify() {
+        let mut parser = Parser::new();
+        let actions = parser.parse_collect(b"\x1b]9;Build finished\x07");
+        assert_eq!(
+            actions,
+            [Action::Osc(OscAction::Notify {
+                title: String::new(),
+                body: "Build finished".to_string(),
+            })]
+        );
+
+        let actions = parser.parse_collect(b"\x1b]777;notify;make;Done; 0 errors\x1b\\");
+        assert_eq!(
+            actions,
+            [Action::Osc(OscAction::Notify {
+                title: "make".to_string(),
+                body: "Done; 0 errors".to_string(),
+            })]
+        );
+
+        let actions = parser.parse_collect(b"\x1b]9;4;1;42\x07\x1b]9;4;3\x07\x1b]9;4\x07");
+        assert_eq!(
+            actions,
+            [
+                Action::Osc(OscAction::Progress {
+                    state: 1,
+                    value: Some(42),
+                }),
+                Action::Osc(OscAction::Progress {
+                    state: 3,
+                    value: None,
+                }),
+                Action::Osc(OscAction::Progress {
+                    state: 0,
+                    value: None,
+                }),
+            ]
+        );
+
+        let actions = parser.parse_collect(b"\x1b]1337;SetUserVar=AWS_PROFILE=cHJvZA==\x07");
+        assert_eq!(
+            actions,
+            [Action::Osc(OscAction::SetUserVar {
+                name: "AWS_PROFILE".to_string(),
+                value: "cHJvZA==".to_string(),
+            })]
+        );
+
+        // Other OSC 777 extensions are not notifications
+        let actions = parser.parse_collect(b"\x1b]777;precmd\x07");
+        assert!(matches!(
+            actions[0],
+            Action::Osc(OscAction::Unknown { command: 777, .. })
+        ));
+    }
+
+    #[test]
+    fn test_parser_dcs_header() {
+        let mut parser = Parser::new();
+        let actions = parser.parse_collect(b"\x1bP1pS(E)\x1b\\\x1bP$qm\x1b\\\x1bP>|x\x1b\\");
+        assert_eq!(
+            actions,
+            [
+                Action::Dcs {
+                    params: Params::parse(b"1"),
+                    intermediates: Vec::new(),
+                    final_byte: b'p',
+                    data: b"S(E)".to_vec(),
+                },
+                Action::Dcs {
+                    params: Params::new(),
+                    intermediates: b"$".to_vec(),
+                    final_byte: b'q',
+                    data: b"m".to_vec(),
+                },
+                Action::Dcs {
+                    params: Params::new(),
+                    intermediates: b">".to_vec(),
+                    final_byte: b'|',
+                    data: b"x".to_vec(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parser_dcs_ignore_recovers() {
+        let mut parser = Parser::new();
+        // Controls in the header are not executed, and a bad header is
+        // consumed up to ST (7-bit or 8-bit)
+        let actions = parser.parse_collect(b"\x1bP1\n;\x7fgarbage\x9cA\x1bP\x7fjunk\x1b\\B");
+        assert!(actions.iter().all(|a| !matches!(a, Action::Control(_))));
+        let printed: String = actions
+            .iter()
+            .filter_map(|a| match a {
+                Action::Print(c) => Some(*c),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(printed, "AB");
+        assert_eq!(parser.state(), ParserState::Ground);
+    }
+
+    #[test]
+    fn test_parser_tek_mode() {
+        let mut parser = Parser::new();
+        let actions = parser.parse_collect(b"\x1b[?38h\x1d#d\x1b\x0cA\x1b\x03B");
+        assert!(matches!(&actions[0], Action::Csi(csi) if csi.final_byte == b'h'));
+        assert_eq!(actions[1], Action::Tek(b"\x1d#d\x1b\x0cA".to_vec()));
+        assert_eq!(actions[2], Action::Print('B'));
+
+        // Split across reads, and left with a reset
+        let mut parser = Parser::new();
+        parser.parse_collect(b"\x1b[?38h\x1f");
+        assert_eq!(parser.state(), ParserState::TekGraphics);
+        let actions = parser.parse_collect(b"xy\x1bc");
+        assert_eq!(
+            actions,
+            [
+                Action::Tek(b"\x1fxy".to_vec()),
+                Action::Esc(EscAction::FullReset)
+            ]
+        );
+        assert_eq!(parser.state(), ParserState::Ground);
+    }
+
+    #[test]
+    fn test_parser_osc_pointer_shape() {
+        let mut parser = Parser::new();
+        let actions = parser.parse_collect(b"\x1b]22;pointer\x07\x1b]22;\x1b\\");
+        assert_eq!(
+            actions,
+            [
+                Action::Osc(OscAction::SetPointerShape("pointer".to_string())),
+                Action::Osc(OscAction::SetPointerShape(String::new())),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parser_osc_semantic_prompt() {
+        let mut parser = Parser::new();
+        let actions = parser.parse_collect(b"\x1b]133;A\x07\x1b]133;D;0\x1b\\");
+        assert_eq!(
+            actions,
+            [
+                Action::Osc(OscAction::SemanticPrompt {
+                    kind: 'A',
+                    params: String::new(),
+                }),
+                Action::Osc(OscAction::SemanticPrompt {
+                    kind: 'D',
+                    params: "0".to_string(),
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parser_osc_inline_file() {
+        let mut parser = Parser::new();
+        let actions = parser.parse_collect(b"\x1b]1337;File=inline=1;width=10:aGVsbG8=\x07");
+
+        assert_eq!(actions.len(), 1);
+        if let Action::Osc(OscAction::InlineFile { args, data }) = &actions[0] {
+            assert_eq!(args, "inline=1;width=10");
+            assert_eq!(data, "aGVsbG8=");
+        } else {
+            panic!("Expected OSC InlineFile action");
+        }
+    }
+
+    #[test]
+    fn test_parser_osc_tab_accent() {
+        let mut parser = Parser::new();
+        let actions =
+            parser.parse_collect(b"\x1b]1337;TabAccent=#ff5555\x07\x1b]1337;TabAccent=\x07");
+        assert_eq!(
+            actions,
+            [
+                Action::Osc(OscAction::SetTabAccent("#ff5555".to_string())),
+                Action::Osc(OscAction::SetTabAccent(String::new())),
+            ]
+        );
+    }
+
+    /// Wrap a sequence the way tmux passes it through
+    fn tmux_wrap(sequence: &[u8]) -> Vec<u8> {
+        let mut wrapped = b"\x1bPtmux;".to_vec();
+        for &byte in sequence {
+            if byte == 0x1b {
+                wrapped.push(0x1b);
+            }
+            wrapped.push(byte);
+        }
+        wrapped.extend(b"\x1b\\");
+        wrapped
+    }
+
+    #[test]
+    fn test_parser_tmux_passthrough() {
+        let mut parser = Parser::new();
+        parser.set_tmux_passthrough(true);
+        let mut input = tmux_wrap(b"\x1b]52;c;aGk=\x1b\\\x1b[1m");
+        input.push(b'x');
+        let expected = [
+            Action::Osc(OscAction::Clipboard {
+                clipboard: "c".to_string(),
+                data: "aGk=".to_string(),
+            }),
+            Action::Csi(CsiAction {
+                params: Params::parse(b"1"),
+                intermediates: vec![],
+                final_byte: b'm',
+                private: false,
+            }),
+            Action::Print('x'),
+        ];
+        assert_eq!(parser.parse_collect(&input), expected);
+        // One byte at a time (ESC ESC split across chunks)
+        let actions: Vec<Action> = input
+            .iter()
+            .flat_map(|&byte| parser.parse_collect(&[byte]))
+            .collect();
+        assert_eq!(actions, expected);
+        assert_eq!(parser.state(), ParserState::Ground);
+    }
+
+    #[test]
+    fn test_parser_tmux_passthrough_depth() {
+        let mut parser = Parser::new();
+        parser.set_tmux_passthrough(true);
+        let mut nested = b"\x1b]2;deep\x07".to_vec();
+        for _ in 0..MAX_PASSTHROUGH_DEPTH {
+            nested = tmux_wrap(&nested);
+        }
+        assert_eq!(
+            parser.parse_collect(&nested),
+            [Action::Osc(OscAction::SetTitle("deep".to_string()))]
+        );
+        // One more level is not unwrapped, and nothing of it leaks out
+        let actions = parser.parse_collect(&tmux_wrap(&nested));
+        assert_eq!(actions.len(), 1);
+        assert!(matches!(
+            actions[0],
+            Action::Dcs {
+                final_byte: b't',
+                ..
+            }
+        ));
+        // A sequence cut off inside the wrapper ends with it
+        assert_eq!(
+            parser.parse_collect(&[&tmux_wrap(b"\x1b]2;cut")[..], b"y"].concat()),
+            [Action::Print('y')]
+        );
+    }
+
+    #[test]
+    fn test_parser_tmux_passthrough_disabled() {
+        let mut parser = Parser::new();
+        let mut input = tmux_wrap(b"\x1b]2;title\x07");
+        input.push(b'z');
+        let actions = parser.parse_collect(&input);
+        // The wrapper is consumed whole
+        assert_eq!(actions.len(), 2);
+        let Action::Dcs {
+            final_byte, data, ..
+        } = &actions[0]
+        else {
+            panic!("Expected DCS, got {:?}", actions[0]);
+        };
+        assert_eq!(*final_byte, b't');
+        assert_eq!(data, b"mux;\x1b]2;title\x07");
+        assert_eq!(actions[1], Action::Print('z'));
+    }
+
+    #[test]
+    fn test_parser_apc_pm_sos() {
+        let mut parser = Parser::new();
+        let actions = parser.parse_collect(b"\x1b_Ga=q;AAAA\x1b\\\x1b^note\x9c\x1bXs\x07os\x1b\\x");
+        assert_eq!(
+            actions,
+            [
+                Action::Apc(b"Ga=q;AAAA".to_vec()),
+                Action::Pm(b"note".to_vec()),
+                // BEL ends OSC only
+                Action::Sos(b"s\x07os".to_vec()),
+                Action::Print('x'),
+            ]
+        );
+        // 8-bit introducers, split across chunks
+        let mut actions = parser.parse_collect(b"\x9fG");
+        actions.extend(parser.parse_collect(b"i=1\x9c\x98a\x9c\x9eb\x9c"));
+        assert_eq!(
+            actions,
+            [
+                Action::Apc(b"Gi=1".to_vec()),
+                Action::Sos(b"a".to_vec()),
+                Action::Pm(b"b".to_vec()),
+            ]
+        );
+        // CAN aborts the string
+        assert_eq!(
+            parser.parse_collect(b"\x1b_Gabc\x18d"),
+            [Action::Print('d')]
+        );
+    }
+
+    #[test]
+    fn test_parser_apc_too_long() {
+        let mut parser = Parser::new();
+        let mut input = b"\x1b_G".to_vec();
+        input.extend(std::iter::repeat_n(b'A', MAX_STRING_LENGTH));
+        input.extend(b"\x1b\\\x1b_Gok\x1b\\");
+        let actions = parser.parse_collect(&input);
+        assert_eq!(actions.len(), 2);
+        let Action::Invalid(data) = &actions[0] else {
+            panic!("Expected invalid action, got {:?}", actions[0]);
+        };
+        assert!(data.starts_with(b"\x1b_GAAA"));
+        assert_eq!(data.len(), 2 + INVALID_STRING_PREFIX);
+        // The next string is not affected
+        assert_eq!(actions[1], Action::Apc(b"Gok".to_vec()));
+    }
+
+    #[test]
+    fn test_parser_osc_inline_file_exceeds_osc_limit() {
+        let mut parser = Parser::new();
+        let mut input = b"\x1b]1337;File=inline=1:".to_vec();
+        input.extend(std::iter::repeat_n(b'A', MAX_OSC_LEN * 2));
+        input.push(0x07);
+        let actions = parser.parse_collect(&input);
+
+        assert_eq!(actions.len(), 1);
+        if let Action::Osc(OscAction::InlineFile { data, .. }) = &actions[0] {
+            assert_eq!(data.len(), MAX_OSC_LEN * 2);
+        } else {
+            panic!("Expected OSC InlineFile action");
+        }
+    }
+
     #[test]
     fn test_parser_utf8() {
         let mut parser = Parser::new();
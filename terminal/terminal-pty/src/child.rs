@@ -3,25 +3,67 @@
 //! Handles spawning and managing child processes attached to a PTY.
 
 use std::ffi::{CString, OsStr};
+use std::fmt;
 use std::io;
 use std::os::fd::{AsRawFd, RawFd};
 use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
 
 use nix::libc;
-use nix::sys::signal::{kill, Signal};
+use nix::sys::signal::{kill, killpg, sigprocmask, SigSet, SigmaskHow, Signal};
 use nix::sys::wait::{waitpid, WaitPidFlag, WaitStatus};
 use nix::unistd::{dup2, execvp, fork, setsid, ForkResult, Pid};
 
+use crate::command::CommandBuilder;
 use crate::error::{Error, Result};
+use crate::priority::Priority;
+use crate::process::ProcessInfo;
 use crate::pty::{configure_slave, open_slave, Pty};
+use crate::reaper::Reaper;
 use crate::size::WindowSize;
 
+/// How a child process ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitStatus {
+    /// Exited with a status code
+    Code(i32),
+    /// Killed by a signal
+    Signal(Signal),
+}
+
+impl ExitStatus {
+    /// Convert a wait status, if it reports that the process ended
+    pub fn from_wait_status(status: WaitStatus) -> Option<Self> {
+        match status {
+            WaitStatus::Exited(_, code) => Some(Self::Code(code)),
+            WaitStatus::Signaled(_, signal, _) => Some(Self::Signal(signal)),
+            _ => None,
+        }
+    }
+
+    /// Check if the process exited with status code 0
+    pub fn success(&self) -> bool {
+        *self == Self::Code(0)
+    }
+}
+
+impl fmt::Display for ExitStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Code(code) => write!(f, "exited with code {}", code),
+            Self::Signal(signal) => write!(f, "killed by {}", signal.as_str()),
+        }
+    }
+}
+
 /// A child process attached to a PTY
 pub struct Child {
     /// The PTY master
     pty: Pty,
     /// Child process ID
     pid: Pid,
+    /// Reaps the child when it exits (see `set_reaper`)
+    reaper: Option<Reaper>,
 }
 
 impl Child {
@@ -31,11 +73,13 @@ impl Child {
     /// * `program` - The program to execute
     /// * `args` - Arguments to pass to the program
     /// * `env` - Environment variables (if None, inherits from parent)
+    /// * `cwd` - Working directory (if None or unusable, $HOME)
     /// * `size` - Initial window size
     pub fn spawn<S, I, E, K, V>(
         program: S,
         args: I,
         env: Option<E>,
+        cwd: Option<&Path>,
         size: WindowSize,
     ) -> Result<Self>
     where
@@ -44,6 +88,28 @@ impl Child {
         E: IntoIterator<Item = (K, V)>,
         K: AsRef<OsStr>,
         V: AsRef<OsStr>,
+    {
+        let program = program.as_ref();
+        let argv = std::iter::once(program.to_os_string())
+            .chain(args.into_iter().map(|arg| arg.as_ref().to_os_string()));
+        Self::spawn_argv(program, argv, env, cwd, Priority::default(), size)
+    }
+
+    /// Spawn a child process with a full argument vector (argv[0] first)
+    pub(crate) fn spawn_argv<A, S, E, K, V>(
+        program: &OsStr,
+        argv: A,
+        env: Option<E>,
+        cwd: Option<&Path>,
+        priority: Priority,
+        size: WindowSize,
+    ) -> Result<Self>
+    where
+        A: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+        E: IntoIterator<Item = (K, V)>,
+        K: AsRef<OsStr>,
+        V: AsRef<OsStr>,
     {
         // Create PTY
         let pty = Pty::new()?;
@@ -52,11 +118,11 @@ impl Child {
         let slave_path = pty.slave_path().to_string();
 
         // Prepare arguments
-        let program_cstr = CString::new(program.as_ref().as_bytes())
-            .map_err(|e| Error::SpawnFailed(e.to_string()))?;
+        let program_cstr =
+            CString::new(program.as_bytes()).map_err(|e| Error::SpawnFailed(e.to_string()))?;
 
-        let mut args_cstr: Vec<CString> = vec![program_cstr.clone()];
-        for arg in args {
+        let mut args_cstr: Vec<CString> = Vec::new();
+        for arg in argv {
             let arg_cstr = CString::new(arg.as_ref().as_bytes())
                 .map_err(|e| Error::SpawnFailed(e.to_string()))?;
             args_cstr.push(arg_cstr);
@@ -81,16 +147,26 @@ impl Child {
         match unsafe { fork() }? {
             ForkResult::Parent { child } => {
                 // Parent process
-                Ok(Self { pty, pid: child })
+                Ok(Self {
+                    pty,
+                    pid: child,
+                    reaper: None,
+                })
             }
             ForkResult::Child => {
                 // Child process - this code runs in the child
 
+                // A reaper blocks SIGCHLD, and the mask would survive exec
+                let _ = sigprocmask(SigmaskHow::SIG_SETMASK, Some(&SigSet::empty()), None);
+
                 // Create new session and set controlling terminal
                 if setsid().is_err() {
                     std::process::exit(1);
                 }
 
+                // A priority the child may not take is left as inherited
+                let _ = priority.apply_to_self();
+
                 // Open slave PTY
                 let slave_fd = match open_slave(&slave_path) {
                     Ok(fd) => fd,
@@ -129,11 +205,14 @@ impl Child {
                     drop(slave_fd);
                 }
 
-                // Change to home directory if available
+                // Change to the requested directory, or home directory if available
                 // This ensures the shell starts in the user's home directory
                 // rather than wherever the app was launched from (e.g., "/" for macOS app bundles)
-                if let Some(home) = std::env::var_os("HOME") {
-                    let _ = std::env::set_current_dir(&home);
+                let in_cwd = cwd.is_some_and(|dir| std::env::set_current_dir(dir).is_ok());
+                if !in_cwd {
+                    if let Some(home) = std::env::var_os("HOME") {
+                        let _ = std::env::set_current_dir(&home);
+                    }
                 }
 
                 // Set environment if provided
@@ -171,25 +250,36 @@ impl Child {
 
     /// Spawn a shell (uses $SHELL or /bin/bash)
     ///
-    /// The shell is launched as a login shell (-l flag) to ensure proper
-    /// environment setup when launched from GUI applications (e.g., macOS app bundles).
+    /// The shell is launched as a login shell to ensure proper environment
+    /// setup when launched from GUI applications (e.g., macOS app bundles).
     /// This sources ~/.zshrc, ~/.bash_profile, etc. which sets up PATH and tools like direnv.
+    /// See `CommandBuilder` to run a different program or change its environment.
     pub fn spawn_shell(size: WindowSize) -> Result<Self> {
-        let shell = std::env::var("SHELL").unwrap_or_else(|_| "/bin/bash".to_string());
-
-        // Get current environment
-        let env: Vec<(String, String)> = std::env::vars().collect();
+        Self::spawn_shell_in(size, None)
+    }
 
-        // Add TERM variable
-        let mut env_with_term: Vec<(String, String)> =
-            env.into_iter().filter(|(k, _)| k != "TERM").collect();
-        env_with_term.push(("TERM".to_string(), "xterm-256color".to_string()));
+    /// Spawn a shell in the given working directory
+    ///
+    /// Falls back to the home directory if `cwd` is None or cannot be
+    /// entered (e.g. it was deleted).
+    pub fn spawn_shell_in(size: WindowSize, cwd: Option<&Path>) -> Result<Self> {
+        let mut builder = CommandBuilder::default_shell();
+        if let Some(cwd) = cwd {
+            builder.cwd(cwd);
+        }
+        builder.spawn(size)
+    }
 
-        // Launch as login shell to properly source shell profile
-        // This is important for GUI-launched terminals (e.g., macOS app bundles)
-        // where the environment may not include user's PATH modifications
-        let args = vec!["-l".to_string()];
-        Self::spawn(shell, args, Some(env_with_term), size)
+    /// Run a command line through `/bin/sh -c` instead of a shell session
+    ///
+    /// The command gets the same environment as a shell would (including
+    /// TERM) and starts in `cwd`, or $HOME if None or unusable.
+    pub fn spawn_command(command: &str, size: WindowSize, cwd: Option<&Path>) -> Result<Self> {
+        let mut builder = CommandBuilder::shell_command(command);
+        if let Some(cwd) = cwd {
+            builder.cwd(cwd);
+        }
+        builder.spawn(size)
     }
 
     /// Get the PTY master
@@ -207,22 +297,70 @@ impl Child {
         self.pid
     }
 
+    /// Get the process in the foreground of the PTY
+    ///
+    /// This is the child itself while the shell is waiting for input, or
+    /// the leader of the job running in it (e.g. an editor).
+    pub fn foreground_process(&self) -> Option<ProcessInfo> {
+        let pgrp = self.pty.foreground_pgrp().ok()?;
+        ProcessInfo::for_pid(pgrp)
+    }
+
+    /// Check if a process other than the child is in the foreground
+    pub fn has_foreground_job(&self) -> bool {
+        self.pty
+            .foreground_pgrp()
+            .is_ok_and(|pgrp| pgrp != self.pid)
+    }
+
+    /// Get the nice level and I/O priority of the child
+    pub fn priority(&self) -> Result<Priority> {
+        Priority::of(self.pid)
+    }
+
+    /// Change the priority of the child's process group and of the job in
+    /// the foreground
+    ///
+    /// Processes the shell starts later inherit the new priority; jobs
+    /// already running in the background keep theirs.
+    pub fn set_priority(&self, priority: Priority) -> Result<()> {
+        priority.apply_to_process_group(self.pid)?;
+        if let Ok(pgrp) = self.pty.foreground_pgrp() {
+            if pgrp.as_raw() > 1 && pgrp != self.pid {
+                priority.apply_to_process_group(pgrp)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Let a reaper reap the child
+    ///
+    /// From then on `wait`, `try_wait` and `is_running` read the status the
+    /// reaper recorded, and the child is reaped even after this `Child` is
+    /// dropped.
+    pub fn set_reaper(&mut self, reaper: &Reaper) {
+        reaper.register(self.pid);
+        self.reaper = Some(reaper.clone());
+    }
+
     /// Check if the child process is still running
     pub fn is_running(&self) -> bool {
-        match waitpid(self.pid, Some(WaitPidFlag::WNOHANG)) {
-            Ok(WaitStatus::StillAlive) => true,
-            Ok(_) => false,
-            Err(_) => false,
-        }
+        matches!(self.try_wait(), Ok(None))
     }
 
     /// Wait for the child process to exit
     pub fn wait(&self) -> Result<WaitStatus> {
-        waitpid(self.pid, None).map_err(Error::from)
+        match &self.reaper {
+            Some(reaper) => reaper.wait(self.pid),
+            None => waitpid(self.pid, None).map_err(Error::from),
+        }
     }
 
     /// Try to wait for the child (non-blocking)
     pub fn try_wait(&self) -> Result<Option<WaitStatus>> {
+        if let Some(reaper) = &self.reaper {
+            return reaper.try_wait(self.pid);
+        }
         match waitpid(self.pid, Some(WaitPidFlag::WNOHANG)) {
             Ok(WaitStatus::StillAlive) => Ok(None),
             Ok(status) => Ok(Some(status)),
@@ -273,8 +411,25 @@ impl Child {
 
 impl Drop for Child {
     fn drop(&mut self) {
-        // Try to terminate the child gracefully
-        let _ = self.signal(Signal::SIGHUP);
+        // Hang up the child's process group (it leads its own session) and
+        // the job in the foreground; a reaper escalates to SIGTERM and
+        // SIGKILL if they stay
+        let mut groups = vec![self.pid];
+        // With no session left on the PTY, tcgetpgrp(3) may report 0, which
+        // killpg(2) would take as our own group
+        if let Ok(pgrp) = self.pty.foreground_pgrp() {
+            if pgrp.as_raw() > 1 && pgrp != self.pid {
+                groups.push(pgrp);
+            }
+        }
+        match &self.reaper {
+            Some(reaper) => reaper.release(self.pid, groups),
+            None => {
+                for group in groups {
+                    let _ = killpg(group, Signal::SIGHUP);
+                }
+            }
+        }
     }
 }
 
@@ -284,6 +439,31 @@ mod tests {
     use std::thread;
     use std::time::Duration;
 
+    #[test]
+    fn test_exit_status_from_wait_status() {
+        let pid = Pid::from_raw(1);
+        assert_eq!(
+            ExitStatus::from_wait_status(WaitStatus::Exited(pid, 3)),
+            Some(ExitStatus::Code(3))
+        );
+        assert_eq!(
+            ExitStatus::from_wait_status(WaitStatus::Signaled(pid, Signal::SIGKILL, false)),
+            Some(ExitStatus::Signal(Signal::SIGKILL))
+        );
+        assert_eq!(ExitStatus::from_wait_status(WaitStatus::StillAlive), None);
+    }
+
+    #[test]
+    fn test_exit_status_display() {
+        assert!(ExitStatus::Code(0).success());
+        assert!(!ExitStatus::Code(1).success());
+        assert_eq!(ExitStatus::Code(1).to_string(), "exited with code 1");
+        assert_eq!(
+            ExitStatus::Signal(Signal::SIGKILL).to_string(),
+            "killed by SIGKILL"
+        );
+    }
+
     #[test]
     fn test_spawn_shell() {
         let child = Child::spawn_shell(WindowSize::default());
@@ -309,6 +489,7 @@ mod tests {
             "/bin/echo",
             ["hello"],
             None::<Vec<(String, String)>>,
+            None,
             WindowSize::default(),
         )
         .unwrap();
@@ -326,6 +507,40 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_priority() {
+        let lowest = Priority {
+            nice: Some(19),
+            io: None,
+        };
+        let mut builder = CommandBuilder::new("/bin/sleep");
+        builder.arg("5").priority(lowest);
+        let child = builder.spawn(WindowSize::default()).unwrap();
+        // The child applies it between fork and exec
+        let mut nice = None;
+        for _ in 0..50 {
+            nice = child.priority().unwrap().nice;
+            if nice == Some(19) {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(nice, Some(19));
+        let _ = child.signal(Signal::SIGKILL);
+
+        let child = CommandBuilder::new("/bin/sleep")
+            .arg("5")
+            .spawn(WindowSize::default())
+            .unwrap();
+        child.set_priority(Priority::BACKGROUND).unwrap();
+        let priority = child.priority().unwrap();
+        assert_eq!(priority.nice, Some(19));
+        if cfg!(target_os = "linux") {
+            assert_eq!(priority.io, Some(crate::IoPriority::Idle));
+        }
+        let _ = child.signal(Signal::SIGKILL);
+    }
+
     #[test]
     fn test_resize() {
         let child = Child::spawn_shell(WindowSize::default()).unwrap();
@@ -0,0 +1,327 @@
+//! Command description for spawning a child on a PTY
+//!
+//! `CommandBuilder` collects the program, its arguments, environment
+//! changes and working directory, then spawns it attached to a new PTY.
+//! The environment starts as a copy of the current process's with TERM
+//! and COLORTERM set for the emulator; `env`, `env_remove` and `env_clear`
+//! change it from there, `priority` sets the child's nice level and
+//! I/O priority, and `systemd_scope` runs it in a systemd scope of its own.
+
+use std::collections::BTreeMap;
+use std::ffi::{OsStr, OsString};
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+
+use crate::child::Child;
+use crate::error::Result;
+use crate::priority::Priority;
+use crate::scope::SystemdScope;
+use crate::size::WindowSize;
+
+/// TERM value given to child processes
+pub const DEFAULT_TERM: &str = "xterm-256color";
+
+/// COLORTERM value given to child processes
+pub const DEFAULT_COLORTERM: &str = "truecolor";
+
+/// Fallback when $SHELL is not set
+const FALLBACK_SHELL: &str = "/bin/bash";
+
+/// A command to run on a PTY
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandBuilder {
+    /// Program to execute (looked up in PATH if it has no slash)
+    program: OsString,
+    /// Arguments after argv[0]
+    args: Vec<OsString>,
+    /// Environment of the child
+    env: BTreeMap<OsString, OsString>,
+    /// Working directory (None = $HOME)
+    cwd: Option<PathBuf>,
+    /// Start as a login shell (argv[0] prefixed with '-')
+    login_shell: bool,
+    /// Priority set in the child before it executes the program
+    priority: Priority,
+    /// Transient systemd scope to run the program in
+    scope: Option<SystemdScope>,
+}
+
+impl CommandBuilder {
+    /// Create a command for a program, with the default environment
+    pub fn new(program: impl AsRef<OsStr>) -> Self {
+        let mut env: BTreeMap<OsString, OsString> = std::env::vars_os().collect();
+        env.insert("TERM".into(), DEFAULT_TERM.into());
+        env.insert("COLORTERM".into(), DEFAULT_COLORTERM.into());
+        Self {
+            program: program.as_ref().to_os_string(),
+            args: Vec::new(),
+            env,
+            cwd: None,
+            login_shell: false,
+            priority: Priority::default(),
+            scope: None,
+        }
+    }
+
+    /// Create a command for the user's shell ($SHELL or /bin/bash)
+    ///
+    /// The shell is started as a login shell to ensure proper environment
+    /// setup when launched from GUI applications (e.g., macOS app bundles),
+    /// where the environment may not include the user's PATH changes.
+    pub fn default_shell() -> Self {
+        let shell = std::env::var_os("SHELL").unwrap_or_else(|| FALLBACK_SHELL.into());
+        let mut builder = Self::new(shell);
+        builder.login_shell(true);
+        builder
+    }
+
+    /// Create a command that runs a command line with `/bin/sh -c`
+    pub fn shell_command(line: impl AsRef<OsStr>) -> Self {
+        let mut builder = Self::new("/bin/sh");
+        builder.arg("-c").arg(line);
+        builder
+    }
+
+    /// Create a command from an argument vector (program first)
+    ///
+    /// Returns None if `argv` is empty.
+    pub fn from_argv<S: AsRef<OsStr>>(argv: &[S]) -> Option<Self> {
+        let (program, args) = argv.split_first()?;
+        let mut builder = Self::new(program);
+        builder.args(args);
+        Some(builder)
+    }
+
+    /// Add an argument
+    pub fn arg(&mut self, arg: impl AsRef<OsStr>) -> &mut Self {
+        self.args.push(arg.as_ref().to_os_string());
+        self
+    }
+
+    /// Add several arguments
+    pub fn args<I, S>(&mut self, args: I) -> &mut Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        self.args
+            .extend(args.into_iter().map(|arg| arg.as_ref().to_os_string()));
+        self
+    }
+
+    /// Set an environment variable
+    pub fn env(&mut self, key: impl AsRef<OsStr>, value: impl AsRef<OsStr>) -> &mut Self {
+        self.env
+            .insert(key.as_ref().to_os_string(), value.as_ref().to_os_string());
+        self
+    }
+
+    /// Remove an environment variable
+    pub fn env_remove(&mut self, key: impl AsRef<OsStr>) -> &mut Self {
+        self.env.remove(key.as_ref());
+        self
+    }
+
+    /// Remove all environment variables, including TERM and COLORTERM
+    pub fn env_clear(&mut self) -> &mut Self {
+        self.env.clear();
+        self
+    }
+
+    /// Set the working directory
+    ///
+    /// The child falls back to $HOME if the directory cannot be entered.
+    pub fn cwd(&mut self, dir: impl AsRef<Path>) -> &mut Self {
+        self.cwd = Some(dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Start the program as a login shell
+    ///
+    /// Like login(1), this passes argv[0] with a leading '-' (e.g. "-zsh"),
+    /// which every common shell understands.
+    pub fn login_shell(&mut self, login: bool) -> &mut Self {
+        self.login_shell = login;
+        self
+    }
+
+    /// Set the nice level and I/O priority of the child
+    ///
+    /// A priority the child may not take (e.g. a negative nice level
+    /// without privileges) is left as it was inherited.
+    pub fn priority(&mut self, priority: Priority) -> &mut Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Run the program in a transient systemd scope (Linux)
+    ///
+    /// The program is started through `systemd-run --user --scope`, which
+    /// executes it in the same process once the scope exists. argv[0] cannot
+    /// be passed through, so a login shell gets `-l` instead of a leading
+    /// '-'. Check `SystemdScope::is_available` first: without systemd the
+    /// spawn fails.
+    pub fn systemd_scope(&mut self, scope: Option<SystemdScope>) -> &mut Self {
+        self.scope = scope;
+        self
+    }
+
+    /// Get the program
+    pub fn get_program(&self) -> &OsStr {
+        &self.program
+    }
+
+    /// Get the arguments after argv[0]
+    pub fn get_args(&self) -> &[OsString] {
+        &self.args
+    }
+
+    /// Get the value of an environment variable in the child
+    pub fn get_env(&self, key: impl AsRef<OsStr>) -> Option<&OsStr> {
+        self.env.get(key.as_ref()).map(OsString::as_os_str)
+    }
+
+    /// Get the working directory
+    pub fn get_cwd(&self) -> Option<&Path> {
+        self.cwd.as_deref()
+    }
+
+    /// Check if the program is started as a login shell
+    pub fn is_login_shell(&self) -> bool {
+        self.login_shell
+    }
+
+    /// Get the priority set in the child
+    pub fn get_priority(&self) -> Priority {
+        self.priority
+    }
+
+    /// Get the systemd scope the program runs in
+    pub fn get_systemd_scope(&self) -> Option<&SystemdScope> {
+        self.scope.as_ref()
+    }
+
+    /// argv[0] passed to the program
+    pub fn argv0(&self) -> OsString {
+        if !self.login_shell {
+            return self.program.clone();
+        }
+        let name = self
+            .program
+            .as_bytes()
+            .rsplit(|&b| b == b'/')
+            .next()
+            .unwrap_or_default();
+        let mut argv0 = OsString::from("-");
+        argv0.push(OsStr::from_bytes(name));
+        argv0
+    }
+
+    /// The argv the program is executed with, starting with argv[0]
+    pub fn argv(&self) -> Vec<OsString> {
+        let Some(scope) = &self.scope else {
+            return std::iter::once(self.argv0())
+                .chain(self.args.iter().cloned())
+                .collect();
+        };
+        let login = self.login_shell.then(|| OsString::from("-l"));
+        scope.wrap(
+            std::iter::once(self.program.clone())
+                .chain(login)
+                .chain(self.args.iter().cloned()),
+        )
+    }
+
+    /// Spawn the command on a new PTY
+    pub fn spawn(&self, size: WindowSize) -> Result<Child> {
+        let argv = self.argv();
+        // argv[0] of a login shell is not a program name
+        let program = match self.scope {
+            Some(_) => &argv[0],
+            None => &self.program,
+        };
+        Child::spawn_argv(
+            program,
+            &argv,
+            Some(&self.env),
+            self.cwd.as_deref(),
+            self.priority,
+            size,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_environment() {
+        let builder = CommandBuilder::new("/bin/true");
+        assert_eq!(builder.get_env("TERM"), Some(OsStr::new(DEFAULT_TERM)));
+        assert_eq!(
+            builder.get_env("COLORTERM"),
+            Some(OsStr::new(DEFAULT_COLORTERM))
+        );
+    }
+
+    #[test]
+    fn test_env_changes() {
+        let mut builder = CommandBuilder::new("/bin/true");
+        builder.env("MOCHI_TEST", "1").env_remove("TERM");
+        assert_eq!(builder.get_env("MOCHI_TEST"), Some(OsStr::new("1")));
+        assert_eq!(builder.get_env("TERM"), None);
+
+        builder.env_clear();
+        assert_eq!(builder.get_env("MOCHI_TEST"), None);
+    }
+
+    #[test]
+    fn test_argv0() {
+        let mut builder = CommandBuilder::new("/usr/bin/zsh");
+        assert_eq!(builder.argv0(), "/usr/bin/zsh");
+        builder.login_shell(true);
+        assert_eq!(builder.argv0(), "-zsh");
+        assert_eq!(
+            CommandBuilder::new("fish").login_shell(true).argv0(),
+            "-fish"
+        );
+    }
+
+    #[test]
+    fn test_from_argv() {
+        let builder = CommandBuilder::from_argv(&["zsh", "-l", "-i"]).unwrap();
+        assert_eq!(builder.get_program(), "zsh");
+        assert_eq!(builder.get_args(), ["-l", "-i"]);
+        assert!(!builder.is_login_shell());
+        assert!(CommandBuilder::from_argv::<&str>(&[]).is_none());
+    }
+
+    #[test]
+    fn test_priority() {
+        let mut builder = CommandBuilder::new("/bin/true");
+        assert!(builder.get_priority().is_unchanged());
+        builder.priority(Priority::BACKGROUND);
+        assert_eq!(builder.get_priority(), Priority::BACKGROUND);
+    }
+
+    #[test]
+    fn test_systemd_scope() {
+        let mut builder = CommandBuilder::new("/usr/bin/zsh");
+        builder.login_shell(true).arg("-i");
+        assert_eq!(builder.argv(), ["-zsh", "-i"]);
+        builder.systemd_scope(Some(SystemdScope::new("mochi-1-1")));
+        assert_eq!(builder.get_systemd_scope().unwrap().unit, "mochi-1-1");
+        let argv = builder.argv();
+        assert_eq!(argv[0], "systemd-run");
+        assert!(argv.contains(&"--unit=mochi-1-1".into()));
+        assert!(argv.ends_with(&["--".into(), "/usr/bin/zsh".into(), "-l".into(), "-i".into()]));
+    }
+
+    #[test]
+    fn test_shell_command() {
+        let builder = CommandBuilder::shell_command("make test");
+        assert_eq!(builder.get_program(), "/bin/sh");
+        assert_eq!(builder.get_args(), ["-c", "make test"]);
+    }
+}
@@ -6,17 +6,34 @@
 //! Key features:
 //! - PTY creation and management
 //! - Child process spawning with proper session setup
-//! - Non-blocking I/O
+//! - Command description (argv, environment, cwd, login shell) via `CommandBuilder`
+//! - Non-blocking I/O, with `PtyWatcher` to wait for output on a thread
 //! - Window size management (TIOCSWINSZ)
+//! - Nice level and I/O priority of children, at spawn and at runtime
+//! - Transient systemd scopes for children, and the usage of their cgroup
+//! - Foreground process lookup (tcgetpgrp)
+//! - Reaping children on SIGCHLD via `Reaper` (signalfd on Linux, kqueue on macOS)
 //!
 //! Reference: https://www.man7.org/linux/man-pages/man3/posix_openpt.3.html
 
 mod child;
+mod command;
 mod error;
+mod priority;
+mod process;
 mod pty;
+mod reaper;
+mod scope;
 mod size;
+mod watch;
 
-pub use child::Child;
+pub use child::{Child, ExitStatus};
+pub use command::{CommandBuilder, DEFAULT_COLORTERM, DEFAULT_TERM};
 pub use error::{Error, Result};
+pub use priority::{IoPriority, Priority};
+pub use process::ProcessInfo;
 pub use pty::Pty;
+pub use reaper::{Reaper, DEFAULT_GRACE_PERIOD};
+pub use scope::{CgroupUsage, SystemdScope};
 pub use size::WindowSize;
+pub use watch::PtyWatcher;
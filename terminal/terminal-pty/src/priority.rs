@@ -0,0 +1,205 @@
+//! Scheduling priority of child processes
+//!
+//! A `Priority` holds a nice level (setpriority(2)) and, on Linux, an I/O
+//! scheduling class (ioprio_set(2)). `CommandBuilder::priority` applies one
+//! in the child before it executes the program, and `Child::set_priority`
+//! changes it at runtime for the child's process group and the job in the
+//! foreground; processes started later inherit it from the shell.
+//!
+//! Raising a priority (a lower nice level, or leaving the idle class for a
+//! real-time one) needs privileges, so it usually fails for normal users.
+
+use std::io;
+
+use nix::errno::Errno;
+use nix::unistd::Pid;
+
+use crate::error::{Error, Result};
+
+/// I/O scheduling class and level (Linux only)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IoPriority {
+    /// No class set: the kernel derives one from the nice level
+    Normal,
+    /// Real-time class with a level from 0 (highest) to 7 (lowest)
+    RealTime(u8),
+    /// Best-effort class with a level from 0 (highest) to 7 (lowest)
+    BestEffort(u8),
+    /// Disk time only when no other process needs it
+    Idle,
+}
+
+/// ioprio_set(2) encoding: the class above the level
+const IOPRIO_CLASS_SHIFT: i32 = 13;
+
+impl IoPriority {
+    fn to_raw(self) -> i32 {
+        let (class, level) = match self {
+            IoPriority::Normal => (0, 0),
+            IoPriority::RealTime(level) => (1, level.min(7)),
+            IoPriority::BestEffort(level) => (2, level.min(7)),
+            IoPriority::Idle => (3, 0),
+        };
+        (class << IOPRIO_CLASS_SHIFT) | level as i32
+    }
+
+    fn from_raw(raw: i32) -> Option<Self> {
+        let level = (raw & 0xff) as u8;
+        match raw >> IOPRIO_CLASS_SHIFT {
+            0 => Some(IoPriority::Normal),
+            1 => Some(IoPriority::RealTime(level)),
+            2 => Some(IoPriority::BestEffort(level)),
+            3 => Some(IoPriority::Idle),
+            _ => None,
+        }
+    }
+}
+
+/// Nice level and I/O priority; None leaves that part unchanged
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Priority {
+    /// Nice level, from -20 (highest) to 19 (lowest)
+    pub nice: Option<i32>,
+    /// I/O priority (ignored outside Linux)
+    pub io: Option<IoPriority>,
+}
+
+/// Who a priority applies to
+#[derive(Debug, Clone, Copy)]
+enum Target {
+    Process(Pid),
+    Group(Pid),
+}
+
+impl Priority {
+    /// The lowest priority: nice 19 and the idle I/O class
+    pub const BACKGROUND: Priority = Priority {
+        nice: Some(19),
+        io: Some(IoPriority::Idle),
+    };
+
+    /// Check if the priority changes nothing
+    pub fn is_unchanged(&self) -> bool {
+        self.nice.is_none() && self.io.is_none()
+    }
+
+    /// Read the priority of a process
+    ///
+    /// Outside Linux `io` is None.
+    pub fn of(pid: Pid) -> Result<Self> {
+        // getpriority(2) may return -1 as a nice level, so errno tells
+        // errors apart
+        Errno::clear();
+        let nice = unsafe { libc::getpriority(libc::PRIO_PROCESS, pid.as_raw() as libc::id_t) };
+        if nice == -1 && Errno::last_raw() != 0 {
+            return Err(Error::from(Errno::last()));
+        }
+        Ok(Self {
+            nice: Some(nice),
+            io: io_priority(pid)?,
+        })
+    }
+
+    /// Apply to the calling process (in a forked child before exec)
+    pub(crate) fn apply_to_self(&self) -> io::Result<()> {
+        self.apply(Target::Process(Pid::from_raw(0)))
+    }
+
+    /// Apply to a process and every process in a process group led by it
+    ///
+    /// The group may not exist yet (a child that has not called setsid(2)),
+    /// or any more, which is not an error.
+    pub(crate) fn apply_to_process_group(&self, pid: Pid) -> Result<()> {
+        self.apply(Target::Process(pid))?;
+        match self.apply(Target::Group(pid)) {
+            Err(e) if e.raw_os_error() == Some(libc::ESRCH) => Ok(()),
+            result => Ok(result?),
+        }
+    }
+
+    fn apply(&self, target: Target) -> io::Result<()> {
+        if let Some(nice) = self.nice {
+            let (which, who) = match target {
+                Target::Process(pid) => (libc::PRIO_PROCESS, pid),
+                Target::Group(pgrp) => (libc::PRIO_PGRP, pgrp),
+            };
+            let nice = nice.clamp(-20, 19);
+            if unsafe { libc::setpriority(which, who.as_raw() as libc::id_t, nice) } < 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        if let Some(io) = self.io {
+            set_io_priority(target, io)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn set_io_priority(target: Target, io: IoPriority) -> io::Result<()> {
+    const IOPRIO_WHO_PROCESS: libc::c_int = 1;
+    const IOPRIO_WHO_PGRP: libc::c_int = 2;
+    let (which, who) = match target {
+        Target::Process(pid) => (IOPRIO_WHO_PROCESS, pid),
+        Target::Group(pgrp) => (IOPRIO_WHO_PGRP, pgrp),
+    };
+    let ret = unsafe { libc::syscall(libc::SYS_ioprio_set, which, who.as_raw(), io.to_raw()) };
+    if ret < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn set_io_priority(_target: Target, _io: IoPriority) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn io_priority(pid: Pid) -> Result<Option<IoPriority>> {
+    const IOPRIO_WHO_PROCESS: libc::c_int = 1;
+    let ret = unsafe { libc::syscall(libc::SYS_ioprio_get, IOPRIO_WHO_PROCESS, pid.as_raw()) };
+    if ret < 0 {
+        return Err(Error::from(Errno::last()));
+    }
+    Ok(IoPriority::from_raw(ret as i32))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn io_priority(_pid: Pid) -> Result<Option<IoPriority>> {
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_io_priority_encoding() {
+        for io in [
+            IoPriority::Normal,
+            IoPriority::RealTime(0),
+            IoPriority::BestEffort(4),
+            IoPriority::BestEffort(7),
+            IoPriority::Idle,
+        ] {
+            assert_eq!(IoPriority::from_raw(io.to_raw()), Some(io));
+        }
+        assert_eq!(IoPriority::BestEffort(4).to_raw(), (2 << 13) | 4);
+        assert_eq!(
+            IoPriority::from_raw(IoPriority::BestEffort(9).to_raw()),
+            Some(IoPriority::BestEffort(7))
+        );
+        assert_eq!(IoPriority::from_raw(7 << 13), None);
+    }
+
+    #[test]
+    fn test_priority_of_self() {
+        let priority = Priority::of(Pid::this()).unwrap();
+        let nice = priority.nice.unwrap();
+        assert!((-20..=19).contains(&nice));
+        assert_eq!(priority.io.is_some(), cfg!(target_os = "linux"));
+        assert!(Priority::default().is_unchanged());
+        assert!(!Priority::BACKGROUND.is_unchanged());
+    }
+}
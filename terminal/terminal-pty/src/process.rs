@@ -0,0 +1,96 @@
+//! Foreground process information
+//!
+//! The foreground process group of a PTY is the job the user is currently
+//! interacting with (the shell itself, or a command started from it). Its
+//! name and working directory are read from /proc on Linux and libproc on
+//! macOS.
+
+use std::path::PathBuf;
+
+use nix::unistd::Pid;
+
+/// Information about a process
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProcessInfo {
+    /// Process ID
+    pub pid: Pid,
+    /// Command name (e.g. "vim")
+    pub name: String,
+    /// Current working directory, if it can be read
+    pub cwd: Option<PathBuf>,
+}
+
+impl ProcessInfo {
+    /// Look up a process by ID
+    ///
+    /// Returns None if the process does not exist or its name cannot be read.
+    #[cfg(target_os = "linux")]
+    pub fn for_pid(pid: Pid) -> Option<Self> {
+        let name = std::fs::read_to_string(format!("/proc/{}/comm", pid)).ok()?;
+        let cwd = std::fs::read_link(format!("/proc/{}/cwd", pid)).ok();
+        Some(Self {
+            pid,
+            name: name.trim_end().to_string(),
+            cwd,
+        })
+    }
+
+    /// Look up a process by ID
+    ///
+    /// Returns None if the process does not exist or its name cannot be read.
+    #[cfg(target_os = "macos")]
+    pub fn for_pid(pid: Pid) -> Option<Self> {
+        use std::ffi::CStr;
+        use std::mem;
+
+        let mut name_buf = [0u8; 256];
+        let len = unsafe {
+            libc::proc_name(
+                pid.as_raw(),
+                name_buf.as_mut_ptr() as *mut libc::c_void,
+                name_buf.len() as u32,
+            )
+        };
+        if len <= 0 {
+            return None;
+        }
+        let name = String::from_utf8_lossy(&name_buf[..len as usize]).into_owned();
+
+        let mut info: libc::proc_vnodepathinfo = unsafe { mem::zeroed() };
+        let size = mem::size_of::<libc::proc_vnodepathinfo>() as libc::c_int;
+        let ret = unsafe {
+            libc::proc_pidinfo(
+                pid.as_raw(),
+                libc::PROC_PIDVNODEPATHINFO,
+                0,
+                &mut info as *mut _ as *mut libc::c_void,
+                size,
+            )
+        };
+        let cwd = (ret == size)
+            .then(|| unsafe { CStr::from_ptr(info.pvi_cdir.vip_path.as_ptr() as *const _) })
+            .map(|path| PathBuf::from(path.to_string_lossy().into_owned()))
+            .filter(|path| !path.as_os_str().is_empty());
+
+        Some(Self { pid, name, cwd })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_for_current_process() {
+        let info = ProcessInfo::for_pid(Pid::this()).unwrap();
+        assert_eq!(info.pid, Pid::this());
+        assert!(!info.name.is_empty());
+        assert_eq!(info.cwd, std::env::current_dir().ok());
+    }
+
+    #[test]
+    fn test_for_missing_process() {
+        // PIDs are never this large on Linux or macOS
+        assert!(ProcessInfo::for_pid(Pid::from_raw(i32::MAX)).is_none());
+    }
+}
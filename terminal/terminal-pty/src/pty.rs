@@ -244,6 +244,25 @@ impl AsFd for Pty {
     }
 }
 
+impl Pty {
+    /// Get the foreground process group of the terminal (tcgetpgrp)
+    pub fn foreground_pgrp(&self) -> Result<nix::unistd::Pid> {
+        nix::unistd::tcgetpgrp(self.as_fd()).map_err(Error::from)
+    }
+
+    /// Get the number of bytes waiting to be read (FIONREAD)
+    pub fn bytes_available(&self) -> Result<usize> {
+        let mut count: libc::c_int = 0;
+        let fd = self.as_raw_fd();
+        let result = unsafe { libc::ioctl(fd, libc::FIONREAD as libc::c_ulong, &mut count) };
+        if result == -1 {
+            Err(Error::Io(io::Error::last_os_error()))
+        } else {
+            Ok(count.max(0) as usize)
+        }
+    }
+}
+
 pub fn open_slave(path: &str) -> Result<OwnedFd> {
     use std::ffi::CString;
     let path_cstr = CString::new(path).map_err(|e| Error::PtyCreation(e.to_string()))?;
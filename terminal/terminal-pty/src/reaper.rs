@@ -0,0 +1,548 @@
+//! Reaping child processes as they exit
+//!
+//! A `Reaper` waits for SIGCHLD on a background thread (through a signalfd
+//! on Linux, a kqueue on macOS) and reaps the children registered with it,
+//! calling a notify function for each one that exited. `Child::try_wait`
+//! then reads the recorded status instead of calling waitpid(2).
+//!
+//! Only registered children are waited for, so processes started elsewhere
+//! (e.g. with `std::process::Command`) keep their status for their own
+//! `wait`. A child whose `Child` is dropped stays registered until it has
+//! been reaped, so closing tabs quickly leaves no zombies behind.
+//!
+//! Dropping a `Child` hangs up its process group and the job in the
+//! foreground of its PTY. Groups still running a grace period later get
+//! SIGTERM, then SIGKILL; groups that survive even that are logged. A
+//! reaper that is dropped finishes this before its thread stops.
+//!
+//! On Linux, SIGCHLD must be blocked in every thread for the signalfd to
+//! see it. `Reaper::spawn` blocks it in the calling thread, which threads
+//! started later inherit, so start the reaper before other threads. In case
+//! a signal goes to a thread that does not block it, registered children
+//! are also checked every `RESCAN_INTERVAL`.
+
+use std::collections::HashMap;
+use std::os::fd::{AsFd, OwnedFd};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use nix::errno::Errno;
+use nix::poll::{poll, PollFd, PollFlags, PollTimeout};
+use nix::sys::signal::{killpg, Signal};
+use nix::sys::wait::{waitpid, WaitPidFlag, WaitStatus};
+use nix::unistd::Pid;
+
+use crate::error::{Error, Result};
+
+/// How often registered children are checked without a SIGCHLD
+const RESCAN_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long closed children get to exit before the next signal, by default
+pub const DEFAULT_GRACE_PERIOD: Duration = Duration::from_secs(2);
+
+/// What the reaper knows about a registered child
+#[derive(Debug, Clone, Copy)]
+enum State {
+    Running,
+    Exited(WaitStatus),
+    /// Reaped by someone else (waitpid failed with ECHILD)
+    Lost,
+}
+
+#[derive(Debug)]
+struct Entry {
+    state: State,
+    /// The `Child` was dropped; forget the entry once it is reaped
+    released: bool,
+}
+
+/// Process groups of a dropped `Child` that are being stopped
+#[derive(Debug)]
+struct Closing {
+    /// Groups with processes left
+    groups: Vec<Pid>,
+    /// The signal sent last
+    signal: Signal,
+    /// When the next signal is due
+    deadline: Instant,
+}
+
+/// Registered children, shared with the reaper thread
+#[derive(Debug)]
+struct Children {
+    entries: Mutex<HashMap<Pid, Entry>>,
+    /// Signalled whenever a child is reaped
+    reaped: Condvar,
+    closing: Mutex<Vec<Closing>>,
+    /// Time between the signals sent to closing groups, in milliseconds
+    grace_ms: AtomicU64,
+}
+
+impl Children {
+    fn new() -> Self {
+        Self {
+            entries: Mutex::default(),
+            reaped: Condvar::new(),
+            closing: Mutex::default(),
+            grace_ms: AtomicU64::new(DEFAULT_GRACE_PERIOD.as_millis() as u64),
+        }
+    }
+
+    fn grace_period(&self) -> Duration {
+        Duration::from_millis(self.grace_ms.load(Ordering::Relaxed))
+    }
+
+    /// Signal closing groups whose grace period is over; returns when the
+    /// next one is due
+    fn escalate(&self, now: Instant) -> Option<Instant> {
+        let grace = self.grace_period();
+        let mut closing = self.closing.lock().unwrap_or_else(|e| e.into_inner());
+        closing.retain_mut(|closing| {
+            closing.groups.retain(|&group| killpg(group, None).is_ok());
+            if closing.groups.is_empty() {
+                return false;
+            }
+            if now < closing.deadline {
+                return true;
+            }
+            match escalation(closing.signal) {
+                Some(signal) => {
+                    log::debug!(
+                        "Process groups {:?} ignored {}, sending {}",
+                        closing.groups,
+                        closing.signal.as_str(),
+                        signal.as_str()
+                    );
+                    signal_groups(&closing.groups, signal);
+                    closing.signal = signal;
+                    closing.deadline = now + grace;
+                    true
+                }
+                None => {
+                    log::warn!(
+                        "Process groups {:?} are still running after SIGKILL",
+                        closing.groups
+                    );
+                    false
+                }
+            }
+        });
+        closing.iter().map(|closing| closing.deadline).min()
+    }
+
+    /// Reap the registered children that exited; returns their pids
+    fn reap(&self) -> Vec<Pid> {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        let mut reaped = Vec::new();
+        entries.retain(|&pid, entry| {
+            if !matches!(entry.state, State::Running) {
+                return true;
+            }
+            entry.state = match waitpid(pid, Some(WaitPidFlag::WNOHANG)) {
+                Ok(WaitStatus::StillAlive) => return true,
+                Ok(status @ (WaitStatus::Exited(..) | WaitStatus::Signaled(..))) => {
+                    State::Exited(status)
+                }
+                Ok(_) => return true,
+                Err(_) => State::Lost,
+            };
+            reaped.push(pid);
+            !entry.released
+        });
+        if !reaped.is_empty() {
+            self.reaped.notify_all();
+        }
+        reaped
+    }
+}
+
+/// The signal that follows `signal` for groups that stay
+fn escalation(signal: Signal) -> Option<Signal> {
+    match signal {
+        Signal::SIGHUP => Some(Signal::SIGTERM),
+        Signal::SIGTERM => Some(Signal::SIGKILL),
+        _ => None,
+    }
+}
+
+fn signal_groups(groups: &[Pid], signal: Signal) {
+    for &group in groups {
+        let _ = killpg(group, signal);
+        // Stopped jobs only act on the signal once they are continued
+        if signal != Signal::SIGKILL {
+            let _ = killpg(group, Signal::SIGCONT);
+        }
+    }
+}
+
+/// Stops the reaper thread when the last handle is dropped
+///
+/// The thread first waits for closing groups to be gone, which takes at
+/// most three grace periods.
+#[derive(Debug)]
+struct Inner {
+    children: Arc<Children>,
+    /// Write end of the pipe that interrupts poll(2) on drop
+    stop_tx: Option<OwnedFd>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        self.stop_tx.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Reaps children on SIGCHLD and reports their exits
+///
+/// Cloning gives another handle to the same reaper; its thread stops when
+/// the last handle is dropped.
+#[derive(Debug, Clone)]
+pub struct Reaper {
+    inner: Arc<Inner>,
+}
+
+impl Reaper {
+    /// Start reaping
+    ///
+    /// `notify` is called from the reaper thread with the pid of each
+    /// registered child that exited.
+    pub fn spawn(notify: impl Fn(Pid) + Send + 'static) -> Result<Self> {
+        let signals = Signals::new()?;
+        let (stop_rx, stop_tx) = nix::unistd::pipe()?;
+        let children = Arc::new(Children::new());
+
+        let thread_children = Arc::clone(&children);
+        let thread = thread::Builder::new()
+            .name("child-reaper".to_string())
+            .spawn(move || run(signals, stop_rx, thread_children, notify))?;
+
+        Ok(Self {
+            inner: Arc::new(Inner {
+                children,
+                stop_tx: Some(stop_tx),
+                thread: Some(thread),
+            }),
+        })
+    }
+
+    /// Reap `pid` when it exits
+    ///
+    /// A child that exited before it was registered is reaped right away.
+    pub(crate) fn register(&self, pid: Pid) {
+        let children = &self.inner.children;
+        children
+            .entries
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(
+                pid,
+                Entry {
+                    state: State::Running,
+                    released: false,
+                },
+            );
+        children.reap();
+    }
+
+    /// Set how long closed children get to exit before the next signal
+    pub fn set_grace_period(&self, grace: Duration) {
+        self.inner
+            .children
+            .grace_ms
+            .store(grace.as_millis() as u64, Ordering::Relaxed);
+    }
+
+    /// Hang up the process groups of a dropped `Child`, escalating if
+    /// they stay, and forget `pid` once it has been reaped
+    pub(crate) fn release(&self, pid: Pid, groups: Vec<Pid>) {
+        {
+            let mut entries = self.entries();
+            if let Some(entry) = entries.get_mut(&pid) {
+                if matches!(entry.state, State::Running) {
+                    entry.released = true;
+                } else {
+                    entries.remove(&pid);
+                }
+            }
+        }
+        signal_groups(&groups, Signal::SIGHUP);
+        let children = &self.inner.children;
+        children
+            .closing
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(Closing {
+                groups,
+                signal: Signal::SIGHUP,
+                deadline: Instant::now() + children.grace_period(),
+            });
+    }
+
+    /// How `pid` ended, or None while it is running
+    pub(crate) fn try_wait(&self, pid: Pid) -> Result<Option<WaitStatus>> {
+        match self.entries().get(&pid).map(|entry| entry.state) {
+            Some(State::Running) => Ok(None),
+            Some(State::Exited(status)) => Ok(Some(status)),
+            Some(State::Lost) | None => Err(Error::from(Errno::ECHILD)),
+        }
+    }
+
+    /// Block until `pid` has been reaped
+    pub(crate) fn wait(&self, pid: Pid) -> Result<WaitStatus> {
+        let mut entries = self.entries();
+        loop {
+            match entries.get(&pid).map(|entry| entry.state) {
+                Some(State::Running) => {
+                    entries = self
+                        .inner
+                        .children
+                        .reaped
+                        .wait(entries)
+                        .unwrap_or_else(|e| e.into_inner());
+                }
+                Some(State::Exited(status)) => return Ok(status),
+                Some(State::Lost) | None => return Err(Error::from(Errno::ECHILD)),
+            }
+        }
+    }
+
+    fn entries(&self) -> std::sync::MutexGuard<'_, HashMap<Pid, Entry>> {
+        self.inner
+            .children
+            .entries
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Reaper thread body
+fn run(signals: Signals, stop_rx: OwnedFd, children: Arc<Children>, notify: impl Fn(Pid)) {
+    let mut stopping = false;
+    loop {
+        for pid in children.reap() {
+            notify(pid);
+        }
+        let next = children.escalate(Instant::now());
+        if stopping && next.is_none() {
+            return;
+        }
+        let timeout = next.map_or(RESCAN_INTERVAL, |deadline| {
+            deadline
+                .saturating_duration_since(Instant::now())
+                .min(RESCAN_INTERVAL)
+        });
+        let timeout = PollTimeout::try_from(timeout).unwrap_or(PollTimeout::MAX);
+
+        let mut fds = vec![PollFd::new(signals.as_fd(), PollFlags::POLLIN)];
+        if !stopping {
+            fds.push(PollFd::new(stop_rx.as_fd(), PollFlags::POLLIN));
+        }
+        match poll(&mut fds, timeout) {
+            Ok(_) => {}
+            Err(Errno::EINTR) => continue,
+            Err(e) => {
+                log::warn!("poll for SIGCHLD failed: {}", e);
+                return;
+            }
+        }
+        if fds.get(1).is_some_and(|fd| fd.any().unwrap_or(true)) {
+            stopping = true;
+        }
+        if fds[0].any().unwrap_or(false) {
+            // SIGCHLDs coalesce, so one wakeup may stand for several exits
+            signals.drain();
+        }
+    }
+}
+
+/// SIGCHLD as a pollable descriptor (signalfd)
+#[cfg(target_os = "linux")]
+struct Signals(nix::sys::signalfd::SignalFd);
+
+#[cfg(target_os = "linux")]
+impl Signals {
+    fn new() -> Result<Self> {
+        use nix::sys::signal::{SigSet, Signal};
+        use nix::sys::signalfd::{SfdFlags, SignalFd};
+
+        let mut mask = SigSet::empty();
+        mask.add(Signal::SIGCHLD);
+        mask.thread_block()?;
+        let fd = SignalFd::with_flags(&mask, SfdFlags::SFD_NONBLOCK | SfdFlags::SFD_CLOEXEC)?;
+        Ok(Self(fd))
+    }
+
+    fn as_fd(&self) -> std::os::fd::BorrowedFd<'_> {
+        self.0.as_fd()
+    }
+
+    fn drain(&self) {
+        while let Ok(Some(_)) = self.0.read_signal() {}
+    }
+}
+
+/// SIGCHLD as a pollable descriptor (kqueue with EVFILT_SIGNAL)
+///
+/// kqueue records signals whatever their disposition, so nothing needs to
+/// be blocked.
+#[cfg(target_os = "macos")]
+struct Signals(OwnedFd);
+
+#[cfg(target_os = "macos")]
+impl Signals {
+    fn new() -> Result<Self> {
+        use std::os::fd::{AsRawFd, FromRawFd};
+
+        let kq = unsafe { libc::kqueue() };
+        if kq < 0 {
+            return Err(Error::from(Errno::last()));
+        }
+        let kq = unsafe { OwnedFd::from_raw_fd(kq) };
+        let change = libc::kevent {
+            ident: libc::SIGCHLD as libc::uintptr_t,
+            filter: libc::EVFILT_SIGNAL,
+            flags: libc::EV_ADD | libc::EV_CLEAR,
+            fflags: 0,
+            data: 0,
+            udata: std::ptr::null_mut(),
+        };
+        let result = unsafe {
+            libc::kevent(
+                kq.as_raw_fd(),
+                &change,
+                1,
+                std::ptr::null_mut(),
+                0,
+                std::ptr::null(),
+            )
+        };
+        if result < 0 {
+            return Err(Error::from(Errno::last()));
+        }
+        Ok(Self(kq))
+    }
+
+    fn as_fd(&self) -> std::os::fd::BorrowedFd<'_> {
+        self.0.as_fd()
+    }
+
+    fn drain(&self) {
+        use std::os::fd::AsRawFd;
+
+        let zero = libc::timespec {
+            tv_sec: 0,
+            tv_nsec: 0,
+        };
+        let mut event = std::mem::MaybeUninit::<libc::kevent>::uninit();
+        while unsafe {
+            libc::kevent(
+                self.0.as_raw_fd(),
+                std::ptr::null(),
+                0,
+                event.as_mut_ptr(),
+                1,
+                &zero,
+            )
+        } > 0
+        {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::child::Child;
+    use crate::size::WindowSize;
+    use std::sync::mpsc;
+    use std::time::Instant;
+
+    fn spawn_sh(line: &str) -> Child {
+        Child::spawn(
+            "/bin/sh",
+            ["-c", line],
+            None::<Vec<(String, String)>>,
+            None,
+            WindowSize::default(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_reaper() {
+        let (tx, rx) = mpsc::channel();
+        let reaper = Reaper::spawn(move |pid| {
+            let _ = tx.send(pid);
+        })
+        .unwrap();
+
+        let mut child = spawn_sh("sleep 0.2; exit 3");
+        child.set_reaper(&reaper);
+        assert_eq!(child.try_wait().unwrap(), None);
+        assert_eq!(rx.recv_timeout(RESCAN_INTERVAL * 5), Ok(child.pid()));
+        assert_eq!(
+            child.try_wait().unwrap(),
+            Some(WaitStatus::Exited(child.pid(), 3))
+        );
+        assert!(!child.is_running());
+
+        let mut children: Vec<Child> = (0..3)
+            .map(|code| spawn_sh(&format!("exit {}", code)))
+            .collect();
+        for child in &mut children {
+            child.set_reaper(&reaper);
+        }
+        for (code, child) in children.iter().enumerate() {
+            assert_eq!(
+                child.wait().unwrap(),
+                WaitStatus::Exited(child.pid(), code as i32)
+            );
+        }
+
+        // Dropped children are forgotten once reaped
+        drop(child);
+        drop(children);
+        assert!(reaper.entries().is_empty());
+        let mut child = spawn_sh("sleep 0.1");
+        let pid = child.pid();
+        child.set_reaper(&reaper);
+        drop(child);
+        let deadline = Instant::now() + RESCAN_INTERVAL * 5;
+        while reaper.entries().contains_key(&pid) {
+            assert!(Instant::now() < deadline, "child was not reaped");
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(waitpid(pid, Some(WaitPidFlag::WNOHANG)), Err(Errno::ECHILD));
+    }
+
+    #[test]
+    fn test_escalation() {
+        assert_eq!(escalation(Signal::SIGHUP), Some(Signal::SIGTERM));
+        assert_eq!(escalation(Signal::SIGTERM), Some(Signal::SIGKILL));
+        assert_eq!(escalation(Signal::SIGKILL), None);
+
+        let reaper = Reaper::spawn(|_| {}).unwrap();
+        reaper.set_grace_period(Duration::from_millis(50));
+        // Ignored signals stay ignored in `sleep`, so only SIGKILL works
+        let stubborn = spawn_sh("trap '' HUP TERM; sleep 30; :");
+        let plain = spawn_sh("sleep 30; :");
+        let groups = [stubborn.pid(), plain.pid()];
+        thread::sleep(Duration::from_millis(100));
+        for mut child in [stubborn, plain] {
+            child.set_reaper(&reaper);
+            drop(child);
+        }
+
+        let deadline = Instant::now() + RESCAN_INTERVAL * 5;
+        while groups.iter().any(|&group| killpg(group, None).is_ok()) {
+            assert!(Instant::now() < deadline, "process groups were not stopped");
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(reaper.entries().is_empty());
+    }
+}
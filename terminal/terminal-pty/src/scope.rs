@@ -0,0 +1,182 @@
+//! Transient systemd scopes for children (Linux)
+//!
+//! `CommandBuilder::systemd_scope` runs the program through
+//! `systemd-run --user --scope`, which puts it in a cgroup of its own
+//! before executing it. Everything the program starts stays in that cgroup,
+//! so the scope's properties (e.g. `MemoryMax=4G`, `CPUQuota=200%`) limit
+//! the whole tree, `systemctl --user stop <unit>` kills it as a group, and
+//! `CgroupUsage` reports what it uses.
+
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use nix::unistd::Pid;
+
+/// Program that creates the scope
+const SYSTEMD_RUN: &str = "systemd-run";
+
+/// Where cgroup v2 is mounted
+const CGROUP_ROOT: &str = "/sys/fs/cgroup";
+
+/// A transient scope unit to run a child in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SystemdScope {
+    /// Unit name (systemd adds `.scope`); must be unique while it runs
+    pub unit: String,
+    /// Unit properties, as for `systemd-run --property`
+    pub properties: Vec<String>,
+}
+
+impl SystemdScope {
+    pub fn new(unit: impl Into<String>) -> Self {
+        Self {
+            unit: unit.into(),
+            properties: Vec::new(),
+        }
+    }
+
+    /// Check if scopes can be created: `systemd-run` is in PATH and the
+    /// user's service manager is running
+    pub fn is_available() -> bool {
+        if !cfg!(target_os = "linux") {
+            return false;
+        }
+        let manager = std::env::var_os("XDG_RUNTIME_DIR")
+            .map(|dir| Path::new(&dir).join("systemd/private"))
+            .is_some_and(|socket| socket.exists());
+        let in_path = std::env::var_os("PATH").is_some_and(|path| {
+            std::env::split_paths(&path).any(|dir| dir.join(SYSTEMD_RUN).is_file())
+        });
+        manager && in_path
+    }
+
+    /// The `systemd-run` command line running `argv` in the scope
+    pub(crate) fn wrap(&self, argv: impl IntoIterator<Item = OsString>) -> Vec<OsString> {
+        let mut wrapped: Vec<OsString> = vec![
+            SYSTEMD_RUN.into(),
+            "--user".into(),
+            "--scope".into(),
+            "--quiet".into(),
+            "--collect".into(),
+            format!("--unit={}", self.unit).into(),
+        ];
+        for property in &self.properties {
+            wrapped.push("--property".into());
+            wrapped.push(property.into());
+        }
+        wrapped.push("--".into());
+        wrapped.extend(argv);
+        wrapped
+    }
+}
+
+/// Resources used by the processes of a cgroup
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CgroupUsage {
+    /// Memory in use, in bytes (including the page cache)
+    pub memory: u64,
+    /// CPU time used since the cgroup was created
+    pub cpu: Duration,
+    /// Number of processes and threads
+    pub tasks: u64,
+}
+
+impl CgroupUsage {
+    /// Read the usage of the cgroup a process is in
+    ///
+    /// Returns None outside Linux, on cgroup v1, or if the process is gone.
+    pub fn for_pid(pid: Pid) -> Option<Self> {
+        Self::read(&cgroup_path(pid)?)
+    }
+
+    fn read(dir: &Path) -> Option<Self> {
+        let read = |name: &str| std::fs::read_to_string(dir.join(name)).ok();
+        let memory = read("memory.current")?.trim().parse().ok()?;
+        let cpu = read("cpu.stat")?.lines().find_map(|line| {
+            let usec = line.strip_prefix("usage_usec ")?;
+            usec.trim().parse().ok().map(Duration::from_micros)
+        })?;
+        let tasks = read("pids.current")
+            .and_then(|text| text.trim().parse().ok())
+            .unwrap_or(0);
+        Some(Self { memory, cpu, tasks })
+    }
+}
+
+/// The cgroup v2 directory of a process
+fn cgroup_path(pid: Pid) -> Option<PathBuf> {
+    let text = std::fs::read_to_string(format!("/proc/{}/cgroup", pid)).ok()?;
+    parse_cgroup(&text).map(|path| Path::new(CGROUP_ROOT).join(path.trim_start_matches('/')))
+}
+
+/// The unified hierarchy's path in /proc/<pid>/cgroup
+fn parse_cgroup(text: &str) -> Option<&str> {
+    text.lines().find_map(|line| line.strip_prefix("0::"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wrap() {
+        let mut scope = SystemdScope::new("mochi-1-2");
+        scope.properties.push("MemoryMax=1G".to_string());
+        let argv = scope.wrap(["zsh".into(), "-l".into()]);
+        assert_eq!(
+            argv,
+            [
+                "systemd-run",
+                "--user",
+                "--scope",
+                "--quiet",
+                "--collect",
+                "--unit=mochi-1-2",
+                "--property",
+                "MemoryMax=1G",
+                "--",
+                "zsh",
+                "-l"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_cgroup() {
+        let text = "0::/user.slice/user-1000.slice/user@1000.service/app.slice/mochi-1-2.scope\n";
+        assert_eq!(
+            parse_cgroup(text),
+            Some("/user.slice/user-1000.slice/user@1000.service/app.slice/mochi-1-2.scope")
+        );
+        // cgroup v1 only
+        assert_eq!(
+            parse_cgroup("12:pids:/user.slice\n1:name=systemd:/\n"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_read_usage() {
+        let dir = std::env::temp_dir().join(format!("mochi-cgroup-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("memory.current"), "1048576\n").unwrap();
+        std::fs::write(
+            dir.join("cpu.stat"),
+            "usage_usec 2500000\nuser_usec 2000000\nsystem_usec 500000\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("pids.current"), "3\n").unwrap();
+        assert_eq!(
+            CgroupUsage::read(&dir),
+            Some(CgroupUsage {
+                memory: 1048576,
+                cpu: Duration::from_millis(2500),
+                tasks: 3,
+            })
+        );
+        std::fs::remove_file(dir.join("memory.current")).unwrap();
+        assert_eq!(CgroupUsage::read(&dir), None);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,124 @@
+//! Waiting for PTY output without polling
+//!
+//! A `PtyWatcher` blocks in poll(2) on a background thread and calls a
+//! notify function once the PTY becomes readable (or hangs up). It then
+//! sleeps until `rearm` is called, so the owner can drain the PTY at its own
+//! pace without the watcher spinning on level-triggered readiness.
+
+use std::os::fd::{AsFd, AsRawFd, OwnedFd};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+
+use nix::poll::{poll, PollFd, PollFlags, PollTimeout};
+
+use crate::error::Result;
+use crate::pty::Pty;
+
+/// Watcher state shared with the background thread
+#[derive(Debug, Default)]
+struct State {
+    /// Waiting for the PTY to become readable
+    armed: bool,
+    /// The watcher is being dropped
+    stopped: bool,
+}
+
+/// Calls a function when a PTY has output to read
+#[derive(Debug)]
+pub struct PtyWatcher {
+    /// Armed/stopped flags and the condition variable that signals them
+    state: Arc<(Mutex<State>, Condvar)>,
+    /// Write end of the pipe that interrupts poll(2) on drop
+    stop_tx: Option<OwnedFd>,
+    /// Background thread
+    thread: Option<JoinHandle<()>>,
+}
+
+impl PtyWatcher {
+    /// Start watching a PTY
+    ///
+    /// `notify` is called from the watcher thread each time the PTY becomes
+    /// readable after being armed. The watcher starts armed.
+    pub fn spawn(pty: &Pty, notify: impl Fn() + Send + 'static) -> Result<Self> {
+        // Own a duplicate so the descriptor stays valid until the thread exits
+        let fd = pty.as_fd().try_clone_to_owned()?;
+        let (stop_rx, stop_tx) = nix::unistd::pipe()?;
+        let state = Arc::new((
+            Mutex::new(State {
+                armed: true,
+                stopped: false,
+            }),
+            Condvar::new(),
+        ));
+
+        let thread_state = Arc::clone(&state);
+        let thread = thread::Builder::new()
+            .name("pty-watcher".to_string())
+            .spawn(move || watch(fd, stop_rx, thread_state, notify))?;
+
+        Ok(Self {
+            state,
+            stop_tx: Some(stop_tx),
+            thread: Some(thread),
+        })
+    }
+
+    /// Wait for the next output after the PTY has been read
+    pub fn rearm(&self) {
+        let (lock, cvar) = &*self.state;
+        let mut state = lock.lock().unwrap_or_else(|e| e.into_inner());
+        if !state.armed {
+            state.armed = true;
+            cvar.notify_one();
+        }
+    }
+}
+
+impl Drop for PtyWatcher {
+    fn drop(&mut self) {
+        let (lock, cvar) = &*self.state;
+        lock.lock().unwrap_or_else(|e| e.into_inner()).stopped = true;
+        cvar.notify_one();
+        // Closing the write end wakes the thread if it is blocked in poll(2)
+        self.stop_tx.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Watcher thread body
+fn watch(fd: OwnedFd, stop_rx: OwnedFd, state: Arc<(Mutex<State>, Condvar)>, notify: impl Fn()) {
+    let (lock, cvar) = &*state;
+    loop {
+        {
+            let mut state = lock.lock().unwrap_or_else(|e| e.into_inner());
+            while !state.armed && !state.stopped {
+                state = cvar.wait(state).unwrap_or_else(|e| e.into_inner());
+            }
+            if state.stopped {
+                return;
+            }
+        }
+
+        let mut fds = [
+            PollFd::new(fd.as_fd(), PollFlags::POLLIN),
+            PollFd::new(stop_rx.as_fd(), PollFlags::POLLIN),
+        ];
+        match poll(&mut fds, PollTimeout::NONE) {
+            Ok(_) => {}
+            Err(nix::errno::Errno::EINTR) => continue,
+            Err(e) => {
+                log::warn!("poll on PTY {} failed: {}", fd.as_raw_fd(), e);
+                return;
+            }
+        }
+        if fds[1].any().unwrap_or(true) {
+            return;
+        }
+        if fds[0].any().unwrap_or(false) {
+            lock.lock().unwrap_or_else(|e| e.into_inner()).armed = false;
+            notify();
+        }
+    }
+}
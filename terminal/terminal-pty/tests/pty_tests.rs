@@ -1,9 +1,10 @@
 //! Comprehensive tests for terminal-pty module
 
 use std::io;
+use std::sync::mpsc;
 use std::thread;
 use std::time::Duration;
-use terminal_pty::{Child, Pty, WindowSize};
+use terminal_pty::{Child, CommandBuilder, ExitStatus, Pty, PtyWatcher, WindowSize};
 
 // ============================================================
 // WindowSize Tests
@@ -215,6 +216,7 @@ fn test_child_spawn_echo() {
         "/bin/echo",
         ["test_output"],
         None::<Vec<(String, String)>>,
+        None,
         WindowSize::default(),
     );
     assert!(child.is_ok());
@@ -226,6 +228,7 @@ fn test_child_spawn_true() {
         "/bin/true",
         std::iter::empty::<&str>(),
         None::<Vec<(String, String)>>,
+        None,
         WindowSize::default(),
     );
     assert!(child.is_ok());
@@ -237,6 +240,7 @@ fn test_child_spawn_false() {
         "/bin/false",
         std::iter::empty::<&str>(),
         None::<Vec<(String, String)>>,
+        None,
         WindowSize::default(),
     );
     assert!(child.is_ok());
@@ -250,12 +254,208 @@ fn test_child_spawn_shell() {
     let _ = child.signal(nix::sys::signal::Signal::SIGTERM);
 }
 
+#[test]
+#[cfg(target_os = "linux")]
+fn test_child_spawn_shell_in_directory() {
+    let dir = std::env::temp_dir().canonicalize().unwrap();
+    let child = Child::spawn_shell_in(WindowSize::default(), Some(&dir)).unwrap();
+
+    // The working directory is set before exec, so poll until the child has forked
+    let proc_cwd = format!("/proc/{}/cwd", child.pid());
+    let mut cwd = None;
+    for _ in 0..50 {
+        cwd = std::fs::read_link(&proc_cwd).ok();
+        if cwd.as_deref() == Some(dir.as_path()) {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+    assert_eq!(cwd.as_deref(), Some(dir.as_path()));
+    let _ = child.signal(nix::sys::signal::Signal::SIGTERM);
+}
+
+#[test]
+fn test_child_foreground_process() {
+    let child = Child::spawn(
+        "/bin/sleep",
+        ["5"],
+        None::<Vec<(String, String)>>,
+        None,
+        WindowSize::default(),
+    )
+    .unwrap();
+
+    // The child leads the foreground process group once it has exec'd
+    let mut info = None;
+    for _ in 0..100 {
+        info = child.foreground_process();
+        if info.as_ref().is_some_and(|p| p.name == "sleep") {
+            break;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    let info = info.unwrap();
+    assert_eq!(info.name, "sleep");
+    assert_eq!(info.pid, child.pid());
+    assert!(!child.has_foreground_job());
+    let _ = child.signal(nix::sys::signal::Signal::SIGTERM);
+}
+
+#[test]
+fn test_child_exit_status() {
+    let child = Child::spawn(
+        "/bin/sh",
+        ["-c", "exit 3"],
+        None::<Vec<(String, String)>>,
+        None,
+        WindowSize::default(),
+    )
+    .unwrap();
+
+    let status = child.wait().unwrap();
+    assert_eq!(
+        ExitStatus::from_wait_status(status),
+        Some(ExitStatus::Code(3))
+    );
+}
+
+#[test]
+fn test_child_spawn_in_directory() {
+    let dir = std::env::temp_dir().canonicalize().unwrap();
+    let child = Child::spawn(
+        "/bin/sleep",
+        ["5"],
+        None::<Vec<(String, String)>>,
+        Some(&dir),
+        WindowSize::default(),
+    )
+    .unwrap();
+
+    let mut cwd = None;
+    for _ in 0..100 {
+        cwd = child.foreground_process().and_then(|p| p.cwd);
+        if cwd.as_ref() == Some(&dir) {
+            break;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(cwd, Some(dir));
+    let _ = child.signal(nix::sys::signal::Signal::SIGTERM);
+}
+
+#[test]
+fn test_child_spawn_command() {
+    let child = Child::spawn_command("exit 7", WindowSize::default(), None).unwrap();
+    let status = child.wait().unwrap();
+    assert_eq!(
+        ExitStatus::from_wait_status(status),
+        Some(ExitStatus::Code(7))
+    );
+}
+
+#[test]
+fn test_command_builder_spawn() {
+    let dir = std::env::temp_dir().canonicalize().unwrap();
+    let script = format!(
+        r#"test "$MOCHI_TEST" = yes && test -z "$COLORTERM" && test "$TERM" = xterm-256color && test "$(pwd -P)" = "{}""#,
+        dir.display()
+    );
+    let mut builder = CommandBuilder::shell_command(script);
+    builder
+        .env("MOCHI_TEST", "yes")
+        .env_remove("COLORTERM")
+        .cwd(&dir);
+
+    let child = builder.spawn(WindowSize::default()).unwrap();
+    let status = child.wait().unwrap();
+    assert_eq!(
+        ExitStatus::from_wait_status(status),
+        Some(ExitStatus::Code(0))
+    );
+}
+
+#[test]
+fn test_command_builder_login_shell() {
+    // A login shell sees argv[0] with a leading '-'
+    let mut builder = CommandBuilder::new("/bin/sh");
+    builder
+        .login_shell(true)
+        .args(["-c", r#"case "$0" in -sh) exit 0;; *) exit 1;; esac"#]);
+
+    let child = builder.spawn(WindowSize::default()).unwrap();
+    let status = child.wait().unwrap();
+    assert_eq!(
+        ExitStatus::from_wait_status(status),
+        Some(ExitStatus::Code(0))
+    );
+}
+
+#[test]
+fn test_pty_bytes_available() {
+    let child = Child::spawn(
+        "/bin/echo",
+        ["pending output"],
+        None::<Vec<(String, String)>>,
+        None,
+        WindowSize::default(),
+    )
+    .unwrap();
+    child.wait().unwrap();
+
+    // The echoed line (plus CR LF) waits in the master until it is read
+    let mut available = 0;
+    for _ in 0..100 {
+        available = child.pty().bytes_available().unwrap();
+        if available > 0 {
+            break;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert!(available >= "pending output".len());
+}
+
+#[test]
+fn test_pty_watcher_notifies_on_output() {
+    let child = Child::spawn(
+        "/bin/sh",
+        ["-c", "sleep 0.2; echo ready"],
+        None::<Vec<(String, String)>>,
+        None,
+        WindowSize::default(),
+    )
+    .unwrap();
+
+    let (tx, rx) = mpsc::channel();
+    let watcher = PtyWatcher::spawn(child.pty(), move || {
+        let _ = tx.send(());
+    })
+    .unwrap();
+
+    rx.recv_timeout(Duration::from_secs(5))
+        .expect("watcher should notify when output arrives");
+
+    // Disarmed until rearmed, even though the output has not been read
+    assert!(rx.recv_timeout(Duration::from_millis(100)).is_err());
+    watcher.rearm();
+    rx.recv_timeout(Duration::from_secs(5))
+        .expect("rearmed watcher should notify for unread output");
+}
+
+#[test]
+fn test_pty_watcher_drop_stops_thread() {
+    let child = Child::spawn_shell(WindowSize::default()).unwrap();
+    let watcher = PtyWatcher::spawn(child.pty(), || {}).unwrap();
+    // Dropping while blocked in poll must return promptly
+    drop(watcher);
+}
+
 #[test]
 fn test_child_pid_valid() {
     let child = Child::spawn(
         "/bin/sleep",
         ["1"],
         None::<Vec<(String, String)>>,
+        None,
         WindowSize::default(),
     )
     .unwrap();
@@ -302,6 +502,7 @@ fn test_child_is_running() {
         "/bin/sleep",
         ["5"],
         None::<Vec<(String, String)>>,
+        None,
         WindowSize::default(),
     )
     .unwrap();
@@ -316,6 +517,7 @@ fn test_child_try_wait_running() {
         "/bin/sleep",
         ["5"],
         None::<Vec<(String, String)>>,
+        None,
         WindowSize::default(),
     )
     .unwrap();
@@ -331,6 +533,7 @@ fn test_child_read_output() {
         "/bin/echo",
         ["hello_pty_test"],
         None::<Vec<(String, String)>>,
+        None,
         WindowSize::default(),
     )
     .unwrap();
@@ -407,7 +610,13 @@ fn test_child_spawn_with_env() {
         ("PATH".to_string(), "/usr/bin:/bin".to_string()),
         ("SHELL".to_string(), "/bin/sh".to_string()),
     ];
-    let child = Child::spawn("/bin/echo", ["env_test"], Some(env), WindowSize::default());
+    let child = Child::spawn(
+        "/bin/echo",
+        ["env_test"],
+        Some(env),
+        None,
+        WindowSize::default(),
+    );
     assert!(child.is_ok());
 }
 
@@ -417,6 +626,7 @@ fn test_child_signal_sigterm() {
         "/bin/sleep",
         ["10"],
         None::<Vec<(String, String)>>,
+        None,
         WindowSize::default(),
     )
     .unwrap();